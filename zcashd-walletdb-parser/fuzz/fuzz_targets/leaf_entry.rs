@@ -0,0 +1,27 @@
+#![no_main]
+
+//! The first two bytes pick the item offset, the rest is the page. Whole-page pairing is
+//! exercised as well, since it walks the slot array the page claims to have.
+
+use libfuzzer_sys::fuzz_target;
+use zcashd_walletdb_parser::{
+    entry::parser::leaf_pairs_on_page_checked,
+    error::ParseMode,
+    leaf::parse_leaf_entry,
+    util::{Endian, parse_page_header},
+};
+
+fuzz_target!(|data: &[u8]| {
+    let [a, b, page @ ..] = data else {
+        return;
+    };
+    let off = u16::from_le_bytes([*a, *b]) as usize;
+    for e in [Endian::Le, Endian::Be] {
+        let _ = parse_leaf_entry(page, off, e);
+        if let Ok(hdr) = parse_page_header(page, e) {
+            for mode in [ParseMode::Strict, ParseMode::Lenient] {
+                let _ = leaf_pairs_on_page_checked("fuzz", page, 512, e, page, &hdr, mode);
+            }
+        }
+    }
+});
@@ -0,0 +1,8 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use zcashd_walletdb_parser::headers::parse_btree_meta_page0;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = parse_btree_meta_page0(data);
+});
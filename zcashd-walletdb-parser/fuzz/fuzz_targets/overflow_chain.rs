@@ -0,0 +1,56 @@
+#![no_main]
+
+//! The first ten bytes pick the chain (first page, length, byte order, page protection),
+//! the rest is an image of 512-byte pages. The chain is read both from a source that knows
+//! its page count and from one that does not, which cannot bound `total_len` up front.
+
+use libfuzzer_sys::fuzz_target;
+use zcashd_walletdb_parser::{
+    entry::{constants::OverflowRef, parser::read_overflow},
+    storage::{
+        page::PageProtection,
+        source::MemoryPageSource,
+        supplier::read_overflow_chain,
+        types::{ByteVec, Endianness, PageNumber, PageSource},
+    },
+};
+
+#[derive(Debug)]
+struct Streaming(MemoryPageSource);
+
+impl PageSource for Streaming {
+    fn read_page(&self, page_no: PageNumber) -> std::io::Result<ByteVec> {
+        self.0.read_page(page_no)
+    }
+
+    fn page_count(&self) -> Option<u64> {
+        None
+    }
+
+    fn source_id(&self) -> String {
+        self.0.source_id()
+    }
+}
+
+fuzz_target!(|data: &[u8]| {
+    let Some((head, image)) = data.split_first_chunk::<10>() else {
+        return;
+    };
+    let reference = OverflowRef {
+        first_page: u32::from_le_bytes([head[0], head[1], head[2], head[3]]),
+        total_len: u32::from_le_bytes([head[4], head[5], head[6], head[7]]),
+    };
+    let e = match head[8] & 1 {
+        0 => Endianness::Little,
+        _ => Endianness::Big,
+    };
+    let protection = match head[9] % 3 {
+        0 => PageProtection::None,
+        1 => PageProtection::Checksum,
+        _ => PageProtection::Encrypted,
+    };
+    let _ = read_overflow(image, 512, e.into(), reference);
+    let source = MemoryPageSource::new(image.to_vec(), 512, "fuzz").expect("valid page size");
+    let _ = read_overflow_chain(&source, reference, e, protection);
+    let _ = read_overflow_chain(&Streaming(source), reference, e, protection);
+});
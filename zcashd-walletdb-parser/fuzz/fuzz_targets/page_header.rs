@@ -0,0 +1,20 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use zcashd_walletdb_parser::{
+    storage::page::{PageHeader, PageProtection},
+    util::{Endian, parse_page_header},
+};
+
+fuzz_target!(|data: &[u8]| {
+    for e in [Endian::Le, Endian::Be] {
+        let _ = parse_page_header(data, e);
+        for protection in [
+            PageProtection::None,
+            PageProtection::Checksum,
+            PageProtection::Encrypted,
+        ] {
+            let _ = PageHeader::parse(data, e.into(), protection);
+        }
+    }
+});
@@ -0,0 +1,9 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use zcashd_walletdb_parser::entry::parser::{read_compact_size, split_walletdb_key};
+
+fuzz_target!(|data: &[u8]| {
+    let _ = read_compact_size(data);
+    let _ = split_walletdb_key(data);
+});
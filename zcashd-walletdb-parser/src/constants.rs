@@ -1,3 +1,119 @@
-/// Start of the per-page header/slot area on BDB 4.x/5.x pages.
-/// The slot array lives in [BTDATAOFF .. lower), and item payloads are in [upper .. page.len()).
-pub const BTDATAOFF: usize = 28;
+/// Size of the generic page header (`SIZEOF_PAGE`): LSN, pgno, prev/next, entries,
+/// hf_offset, level and type.
+pub const SIZEOF_PAGE: usize = 26;
+
+/// Size of the `PG_CHKSUM` block that follows the page header when the database was
+/// created with DB_CHKSUM: 2 unused bytes + a 4-byte hash.
+pub const PG_CHKSUM_SIZE: usize = 6;
+
+/// Offset of the checksum bytes on a non-meta page (`SIZEOF_PAGE` + 2 unused bytes).
+pub const PG_CHKSUM_OFF: usize = SIZEOF_PAGE + 2;
+
+/// Number of leading bytes of a meta page covered by the meta checksum (`DBMETASIZE`).
+pub const DBMETASIZE: usize = 512;
+
+/// Offset of the checksum bytes on a meta page (`BTMETA.chksum`, 492..512).
+pub const META_CHKSUM_OFF: usize = 492;
+
+/// Offset of the IV on an encrypted meta page (`BTMETA.iv`, 476..492).
+pub const META_IV_OFF: usize = 476;
+
+/// Offset of `BTMETA.crypto_magic`, which decrypts to the btree magic with the right key.
+pub const META_CRYPTO_MAGIC_OFF: usize = 460;
+
+/// Size of the `PG_CRYPTO` block that follows the page header on encrypted databases:
+/// 2 unused bytes + a 20-byte HMAC-SHA1 + a 16-byte IV.
+pub const PG_CRYPTO_SIZE: usize = 38;
+
+/// Offset of the IV on an encrypted non-meta page.
+pub const PG_IV_OFF: usize = PG_CHKSUM_OFF + DB_MAC_KEY;
+
+/// Length of the HMAC-SHA1 page checksum on encrypted databases (`DB_MAC_KEY`).
+pub const DB_MAC_KEY: usize = 20;
+
+/// `DBMETA.encrypt_alg` value for AES (`DB_ENCRYPT_AES`).
+pub const DB_ENCRYPT_AES: u8 = 1;
+
+/// `DBMETA.metaflags` bit set when every page carries a checksum.
+pub const DBMETA_CHKSUM: u8 = 0x01;
+
+/// Smallest page size Berkeley DB accepts (`DB_MIN_PGSIZE`).
+pub const DB_MIN_PGSIZE: usize = 512;
+
+/// Largest page size Berkeley DB accepts (`DB_MAX_PGSIZE`).
+pub const DB_MAX_PGSIZE: usize = 64 * 1024;
+
+/// Page type: a free or never-initialised page (`P_INVALID`).
+pub const P_INVALID: u8 = 0;
+
+/// Page type: an off-page duplicate page of Berkeley DB before 3.0 (`__P_DUPLICATE`).
+pub const P_DUPLICATE: u8 = 1;
+
+/// Page type: a hash bucket page with unsorted items (`P_HASH_UNSORTED`).
+pub const P_HASH_UNSORTED: u8 = 2;
+
+/// Page type: a btree internal page (`P_IBTREE`).
+pub const P_IBTREE: u8 = 3;
+
+/// Page type: a recno internal page (`P_IRECNO`).
+pub const P_IRECNO: u8 = 4;
+
+/// Page type: a btree leaf page (`P_LBTREE`).
+pub const P_LBTREE: u8 = 5;
+
+/// Page type: a recno leaf page (`P_LRECNO`).
+pub const P_LRECNO: u8 = 6;
+
+/// Page type: an overflow page (`P_OVERFLOW`).
+pub const P_OVERFLOW: u8 = 7;
+
+/// Page type: the meta page of a hash database (`P_HASHMETA`).
+pub const P_HASHMETA: u8 = 8;
+
+/// Page type: the meta page of a btree or recno database (`P_BTREEMETA`).
+pub const P_BTREEMETA: u8 = 9;
+
+/// Page type: the meta page of a queue database (`P_QAMMETA`).
+pub const P_QAMMETA: u8 = 10;
+
+/// Page type: a queue data page (`P_QAMDATA`).
+pub const P_QAMDATA: u8 = 11;
+
+/// Page type: a leaf page of an off-page duplicate tree (`P_LDUP`).
+pub const P_LDUP: u8 = 12;
+
+/// Page type: a hash bucket page with sorted items (`P_HASH`).
+pub const P_HASH: u8 = 13;
+
+/// Page type: the meta page of a heap database (`P_HEAPMETA`, 5.2+).
+pub const P_HEAPMETA: u8 = 14;
+
+/// Page type: a heap data page (`P_HEAP`, 5.2+).
+pub const P_HEAP: u8 = 15;
+
+/// Page type: a heap region page (`P_IHEAP`, 5.2+).
+pub const P_IHEAP: u8 = 16;
+
+/// Item type: key/data bytes stored inline (`B_KEYDATA`).
+pub const B_KEYDATA: u8 = 1;
+
+/// Item type: reference to an off-page duplicate tree (`B_DUPLICATE`).
+pub const B_DUPLICATE: u8 = 2;
+
+/// Item type: reference to an overflow chain (`B_OVERFLOW`).
+pub const B_OVERFLOW: u8 = 3;
+
+/// Item type: reference to a value in an external blob file (`B_BLOB`, 6.x only).
+pub const B_BLOB: u8 = 5;
+
+/// Deleted flag OR-ed into the item type (`B_DELETE`).
+pub const B_DELETE: u8 = 0x80;
+
+/// On-page size of a `BOVERFLOW` item: pad:u16, type:u8, pad:u8, pgno:u32, tlen:u32.
+pub const BOVERFLOW_SIZE: usize = 12;
+
+/// Fixed part of a `BINTERNAL` item: len:u16, type:u8, pad:u8, pgno:u32, nrecs:u32.
+pub const BINTERNAL_SIZE: usize = 12;
+
+/// On-page size of a `BBLOB` item, see `storage::blob::BlobRef`.
+pub const BBLOB_SIZE: usize = 48;
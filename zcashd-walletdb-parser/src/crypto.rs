@@ -0,0 +1,81 @@
+//! Minimal cryptographic primitives needed to read protected Berkeley DB files, to
+//! check zcashd's own record checksums, to decrypt the secrets of encrypted wallets, and
+//! to recompute what Zcash derives from the wallet's data: public keys, addresses, txids
+//! and note commitment tree roots.
+
+pub mod aes;
+pub mod blake2b;
+pub mod blake2s;
+pub mod field;
+pub mod jubjub;
+pub mod pedersen;
+pub mod ripemd160;
+pub mod secp256k1;
+pub mod sha1;
+pub mod sha256;
+pub mod sha512;
+
+/// A streaming hash function usable with [`hmac`].
+pub trait Digest: Sized {
+    /// Internal block size in bytes.
+    const BLOCK_LEN: usize;
+    /// Output size in bytes.
+    const OUTPUT_LEN: usize;
+
+    fn new() -> Self;
+    fn update(&mut self, data: &[u8]);
+    fn finalize(self) -> Vec<u8>;
+
+    /// One-shot hash of `data`.
+    fn digest(data: &[u8]) -> Vec<u8> {
+        let mut h = Self::new();
+        h.update(data);
+        h.finalize()
+    }
+}
+
+/// HMAC (RFC 2104) over any [`Digest`].
+pub fn hmac<D: Digest>(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut k = if key.len() > D::BLOCK_LEN {
+        D::digest(key)
+    } else {
+        key.to_vec()
+    };
+    k.resize(D::BLOCK_LEN, 0);
+
+    let mut inner = D::new();
+    inner.update(&k.iter().map(|b| b ^ 0x36).collect::<Vec<_>>());
+    inner.update(data);
+    let inner = inner.finalize();
+
+    let mut outer = D::new();
+    outer.update(&k.iter().map(|b| b ^ 0x5c).collect::<Vec<_>>());
+    outer.update(&inner);
+    outer.finalize()
+}
+
+/// PBKDF2 (RFC 8018) with HMAC over any [`Digest`], producing `out_len` bytes.
+pub fn pbkdf2_hmac<D: Digest>(
+    password: &[u8],
+    salt: &[u8],
+    rounds: u32,
+    out_len: usize,
+) -> Vec<u8> {
+    let mut out = Vec::with_capacity(out_len);
+    for block in 1u32.. {
+        if out.len() >= out_len {
+            break;
+        }
+        let mut u = hmac::<D>(password, &[salt, &block.to_be_bytes()].concat());
+        let mut t = u.clone();
+        for _ in 1..rounds {
+            u = hmac::<D>(password, &u);
+            for (t, u) in t.iter_mut().zip(&u) {
+                *t ^= u;
+            }
+        }
+        out.extend_from_slice(&t);
+    }
+    out.truncate(out_len);
+    out
+}
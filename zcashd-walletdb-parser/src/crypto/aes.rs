@@ -0,0 +1,216 @@
+//! AES block cipher (FIPS 197) with CBC helpers, for 128- and 256-bit keys.
+
+const SBOX: [u8; 256] = {
+    // Build the S-box from the multiplicative inverse in GF(2^8) plus the affine map.
+    let mut sbox = [0u8; 256];
+    let mut p: u8 = 1;
+    let mut q: u8 = 1;
+    loop {
+        // p <- p * 3
+        p = p ^ (p << 1) ^ if p & 0x80 != 0 { 0x1b } else { 0 };
+        // q <- q / 3
+        q ^= q << 1;
+        q ^= q << 2;
+        q ^= q << 4;
+        if q & 0x80 != 0 {
+            q ^= 0x09;
+        }
+        let x = q ^ q.rotate_left(1) ^ q.rotate_left(2) ^ q.rotate_left(3) ^ q.rotate_left(4);
+        sbox[p as usize] = x ^ 0x63;
+        if p == 1 {
+            break;
+        }
+    }
+    sbox[0] = 0x63;
+    sbox
+};
+
+const INV_SBOX: [u8; 256] = {
+    let mut inv = [0u8; 256];
+    let mut i = 0;
+    while i < 256 {
+        inv[SBOX[i] as usize] = i as u8;
+        i += 1;
+    }
+    inv
+};
+
+const fn xtime(x: u8) -> u8 {
+    (x << 1) ^ if x & 0x80 != 0 { 0x1b } else { 0 }
+}
+
+const fn gmul(mut a: u8, mut b: u8) -> u8 {
+    let mut r = 0;
+    while b != 0 {
+        if b & 1 != 0 {
+            r ^= a;
+        }
+        a = xtime(a);
+        b >>= 1;
+    }
+    r
+}
+
+/// An expanded AES key.
+#[derive(Clone)]
+pub struct Aes {
+    round_keys: Vec<[u8; 16]>,
+}
+
+impl Aes {
+    /// Expand a 16-, 24- or 32-byte key. Returns `None` for any other length.
+    pub fn new(key: &[u8]) -> Option<Self> {
+        let nk = match key.len() {
+            16 | 24 | 32 => key.len() / 4,
+            _ => return None,
+        };
+        let rounds = nk + 6;
+        let mut words: Vec<[u8; 4]> = key
+            .chunks_exact(4)
+            .map(|c| [c[0], c[1], c[2], c[3]])
+            .collect();
+        let mut rcon = 1u8;
+        for i in nk..4 * (rounds + 1) {
+            let mut t = words[i - 1];
+            if i % nk == 0 {
+                t = [
+                    SBOX[t[1] as usize] ^ rcon,
+                    SBOX[t[2] as usize],
+                    SBOX[t[3] as usize],
+                    SBOX[t[0] as usize],
+                ];
+                rcon = xtime(rcon);
+            } else if nk > 6 && i % nk == 4 {
+                t = t.map(|b| SBOX[b as usize]);
+            }
+            let prev = words[i - nk];
+            words.push([
+                prev[0] ^ t[0],
+                prev[1] ^ t[1],
+                prev[2] ^ t[2],
+                prev[3] ^ t[3],
+            ]);
+        }
+        let round_keys = words
+            .chunks_exact(4)
+            .map(|w| {
+                let mut k = [0u8; 16];
+                for (i, word) in w.iter().enumerate() {
+                    k[4 * i..4 * i + 4].copy_from_slice(word);
+                }
+                k
+            })
+            .collect();
+        Some(Aes { round_keys })
+    }
+
+    fn rounds(&self) -> usize {
+        self.round_keys.len() - 1
+    }
+
+    /// Encrypt a single 16-byte block in place.
+    pub fn encrypt_block(&self, block: &mut [u8; 16]) {
+        add_round_key(block, &self.round_keys[0]);
+        for round in 1..=self.rounds() {
+            for b in block.iter_mut() {
+                *b = SBOX[*b as usize];
+            }
+            shift_rows(block);
+            if round != self.rounds() {
+                mix_columns(block);
+            }
+            add_round_key(block, &self.round_keys[round]);
+        }
+    }
+
+    /// Decrypt a single 16-byte block in place.
+    pub fn decrypt_block(&self, block: &mut [u8; 16]) {
+        add_round_key(block, &self.round_keys[self.rounds()]);
+        for round in (0..self.rounds()).rev() {
+            inv_shift_rows(block);
+            for b in block.iter_mut() {
+                *b = INV_SBOX[*b as usize];
+            }
+            add_round_key(block, &self.round_keys[round]);
+            if round != 0 {
+                inv_mix_columns(block);
+            }
+        }
+    }
+
+    /// CBC-encrypt `data` in place. `data.len()` must be a multiple of 16.
+    pub fn cbc_encrypt(&self, iv: &[u8; 16], data: &mut [u8]) {
+        debug_assert!(data.len().is_multiple_of(16));
+        let mut prev = *iv;
+        for chunk in data.chunks_exact_mut(16) {
+            let mut block = [0u8; 16];
+            for i in 0..16 {
+                block[i] = chunk[i] ^ prev[i];
+            }
+            self.encrypt_block(&mut block);
+            chunk.copy_from_slice(&block);
+            prev = block;
+        }
+    }
+
+    /// CBC-decrypt `data` in place. `data.len()` must be a multiple of 16.
+    pub fn cbc_decrypt(&self, iv: &[u8; 16], data: &mut [u8]) {
+        debug_assert!(data.len().is_multiple_of(16));
+        let mut prev = *iv;
+        for chunk in data.chunks_exact_mut(16) {
+            let mut block = [0u8; 16];
+            block.copy_from_slice(chunk);
+            let cipher = block;
+            self.decrypt_block(&mut block);
+            for i in 0..16 {
+                chunk[i] = block[i] ^ prev[i];
+            }
+            prev = cipher;
+        }
+    }
+}
+
+fn add_round_key(block: &mut [u8; 16], key: &[u8; 16]) {
+    for (b, k) in block.iter_mut().zip(key) {
+        *b ^= k;
+    }
+}
+
+// The state is column-major: byte `r + 4c` is row r, column c.
+fn shift_rows(s: &mut [u8; 16]) {
+    let t = *s;
+    for c in 0..4 {
+        for r in 0..4 {
+            s[r + 4 * c] = t[r + 4 * ((c + r) % 4)];
+        }
+    }
+}
+
+fn inv_shift_rows(s: &mut [u8; 16]) {
+    let t = *s;
+    for c in 0..4 {
+        for r in 0..4 {
+            s[r + 4 * ((c + r) % 4)] = t[r + 4 * c];
+        }
+    }
+}
+
+fn mix_columns(s: &mut [u8; 16]) {
+    for col in s.chunks_exact_mut(4) {
+        let [a, b, c, d] = [col[0], col[1], col[2], col[3]];
+        col[0] = gmul(a, 2) ^ gmul(b, 3) ^ c ^ d;
+        col[1] = a ^ gmul(b, 2) ^ gmul(c, 3) ^ d;
+        col[2] = a ^ b ^ gmul(c, 2) ^ gmul(d, 3);
+        col[3] = gmul(a, 3) ^ b ^ c ^ gmul(d, 2);
+    }
+}
+
+fn inv_mix_columns(s: &mut [u8; 16]) {
+    for col in s.chunks_exact_mut(4) {
+        let [a, b, c, d] = [col[0], col[1], col[2], col[3]];
+        col[0] = gmul(a, 14) ^ gmul(b, 11) ^ gmul(c, 13) ^ gmul(d, 9);
+        col[1] = gmul(a, 9) ^ gmul(b, 14) ^ gmul(c, 11) ^ gmul(d, 13);
+        col[2] = gmul(a, 13) ^ gmul(b, 9) ^ gmul(c, 14) ^ gmul(d, 11);
+        col[3] = gmul(a, 11) ^ gmul(b, 13) ^ gmul(c, 9) ^ gmul(d, 14);
+    }
+}
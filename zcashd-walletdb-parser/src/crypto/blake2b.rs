@@ -0,0 +1,127 @@
+//! BLAKE2b (RFC 7693), with the personalization Zcash uses to separate its domains.
+//! ZIP 316 builds F4Jumble, the permutation under unified encodings, from it.
+
+const IV: [u64; 8] = [
+    0x6a09_e667_f3bc_c908,
+    0xbb67_ae85_84ca_a73b,
+    0x3c6e_f372_fe94_f82b,
+    0xa54f_f53a_5f1d_36f1,
+    0x510e_527f_ade6_82d1,
+    0x9b05_688c_2b3e_6c1f,
+    0x1f83_d9ab_fb41_bd6b,
+    0x5be0_cd19_137e_2179,
+];
+
+const SIGMA: [[usize; 16]; 10] = [
+    [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15],
+    [14, 10, 4, 8, 9, 15, 13, 6, 1, 12, 0, 2, 11, 7, 5, 3],
+    [11, 8, 12, 0, 5, 2, 15, 13, 10, 14, 3, 6, 7, 1, 9, 4],
+    [7, 9, 3, 1, 13, 12, 11, 14, 2, 6, 5, 10, 4, 0, 15, 8],
+    [9, 0, 5, 7, 2, 4, 10, 15, 14, 1, 11, 12, 6, 8, 3, 13],
+    [2, 12, 6, 10, 0, 11, 8, 3, 4, 13, 7, 5, 15, 14, 1, 9],
+    [12, 5, 1, 15, 14, 13, 4, 10, 0, 7, 6, 3, 9, 2, 8, 11],
+    [13, 11, 7, 14, 12, 1, 3, 9, 5, 0, 15, 4, 8, 6, 2, 10],
+    [6, 15, 14, 9, 11, 3, 0, 8, 12, 2, 13, 7, 1, 4, 10, 5],
+    [10, 2, 8, 4, 7, 6, 1, 5, 15, 11, 9, 14, 3, 12, 13, 0],
+];
+
+#[derive(Clone)]
+pub struct Blake2b {
+    state: [u64; 8],
+    buf: Vec<u8>,
+    /// Bytes compressed so far.
+    len: u128,
+    out_len: usize,
+}
+
+impl Blake2b {
+    /// An unkeyed hash with an `out_len`-byte digest (1 to 64) and up to 16 bytes of
+    /// personalization, zero-padded.
+    pub fn new(out_len: usize, personal: &[u8]) -> Self {
+        assert!((1..=64).contains(&out_len) && personal.len() <= 16);
+        let mut state = IV;
+        state[0] ^= 0x0101_0000 ^ out_len as u64;
+        let mut padded = [0u8; 16];
+        padded[..personal.len()].copy_from_slice(personal);
+        state[6] ^= u64::from_le_bytes(padded[..8].try_into().unwrap());
+        state[7] ^= u64::from_le_bytes(padded[8..].try_into().unwrap());
+        Blake2b {
+            state,
+            buf: Vec::with_capacity(128),
+            len: 0,
+            out_len,
+        }
+    }
+
+    fn compress(&mut self, block: &[u8], last: bool) {
+        let mut m = [0u64; 16];
+        for (i, chunk) in block.chunks_exact(8).enumerate() {
+            m[i] = u64::from_le_bytes(chunk.try_into().unwrap());
+        }
+        let mut v = [0u64; 16];
+        v[..8].copy_from_slice(&self.state);
+        v[8..].copy_from_slice(&IV);
+        v[12] ^= self.len as u64;
+        v[13] ^= (self.len >> 64) as u64;
+        if last {
+            v[14] = !v[14];
+        }
+        let mut g = |a: usize, b: usize, c: usize, d: usize, x: u64, y: u64| {
+            v[a] = v[a].wrapping_add(v[b]).wrapping_add(x);
+            v[d] = (v[d] ^ v[a]).rotate_right(32);
+            v[c] = v[c].wrapping_add(v[d]);
+            v[b] = (v[b] ^ v[c]).rotate_right(24);
+            v[a] = v[a].wrapping_add(v[b]).wrapping_add(y);
+            v[d] = (v[d] ^ v[a]).rotate_right(16);
+            v[c] = v[c].wrapping_add(v[d]);
+            v[b] = (v[b] ^ v[c]).rotate_right(63);
+        };
+        for round in 0..12 {
+            let s = &SIGMA[round % 10];
+            g(0, 4, 8, 12, m[s[0]], m[s[1]]);
+            g(1, 5, 9, 13, m[s[2]], m[s[3]]);
+            g(2, 6, 10, 14, m[s[4]], m[s[5]]);
+            g(3, 7, 11, 15, m[s[6]], m[s[7]]);
+            g(0, 5, 10, 15, m[s[8]], m[s[9]]);
+            g(1, 6, 11, 12, m[s[10]], m[s[11]]);
+            g(2, 7, 8, 13, m[s[12]], m[s[13]]);
+            g(3, 4, 9, 14, m[s[14]], m[s[15]]);
+        }
+        for (i, h) in self.state.iter_mut().enumerate() {
+            *h ^= v[i] ^ v[i + 8];
+        }
+    }
+
+    /// The last block is only compressed in [`Self::finalize`], so a full buffer waits
+    /// for more data before it is compressed.
+    pub fn update(&mut self, mut data: &[u8]) {
+        while !data.is_empty() {
+            if self.buf.len() == 128 {
+                self.len += 128;
+                let block = std::mem::take(&mut self.buf);
+                self.compress(&block, false);
+            }
+            let take = (128 - self.buf.len()).min(data.len());
+            self.buf.extend_from_slice(&data[..take]);
+            data = &data[take..];
+        }
+    }
+
+    pub fn finalize(mut self) -> Vec<u8> {
+        self.len += self.buf.len() as u128;
+        let mut block = std::mem::take(&mut self.buf);
+        block.resize(128, 0);
+        self.compress(&block, true);
+        let mut out: Vec<u8> = self.state.iter().flat_map(|h| h.to_le_bytes()).collect();
+        out.truncate(self.out_len);
+        out
+    }
+}
+
+/// One-shot BLAKE2b of `data` with an `out_len`-byte digest and personalization
+/// `personal`.
+pub fn blake2b(out_len: usize, personal: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut h = Blake2b::new(out_len, personal);
+    h.update(data);
+    h.finalize()
+}
@@ -0,0 +1,304 @@
+//! Prime field arithmetic in Montgomery form, for moduli below 2^256: the base field of
+//! Jubjub, Sapling's curve, and whichever others a [`Modulus`] names.
+
+use std::{
+    fmt,
+    marker::PhantomData,
+    ops::{Add, Mul, Neg, Sub},
+};
+
+/// A prime modulus, as four little-endian 64-bit limbs. The other constants derive
+/// from it.
+pub trait Modulus: Copy + Eq + fmt::Debug + 'static {
+    const P: [u64; 4];
+    /// `-P^-1 mod 2^64`.
+    const INV: u64 = neg_inverse(Self::P[0]);
+    /// `2^256 mod P`: one, in Montgomery form.
+    const R: [u64; 4] = double_times([1, 0, 0, 0], 256, Self::P);
+    /// `2^512 mod P`, which takes values into Montgomery form.
+    const R2: [u64; 4] = double_times(Self::R, 256, Self::P);
+}
+
+const fn neg_inverse(p0: u64) -> u64 {
+    // Newton's iteration doubles the number of correct low bits each step.
+    let mut inv: u64 = 1;
+    let mut i = 0;
+    while i < 6 {
+        inv = inv.wrapping_mul(2u64.wrapping_sub(p0.wrapping_mul(inv)));
+        i += 1;
+    }
+    inv.wrapping_neg()
+}
+
+const fn geq(a: [u64; 4], b: [u64; 4]) -> bool {
+    let mut i = 4;
+    while i > 0 {
+        i -= 1;
+        if a[i] != b[i] {
+            return a[i] > b[i];
+        }
+    }
+    true
+}
+
+/// `a - b`, with the borrow out.
+const fn sub_limbs(a: [u64; 4], b: [u64; 4]) -> ([u64; 4], bool) {
+    let mut out = [0; 4];
+    let mut borrow = false;
+    let mut i = 0;
+    while i < 4 {
+        let (d, b1) = a[i].overflowing_sub(b[i]);
+        let (d, b2) = d.overflowing_sub(borrow as u64);
+        out[i] = d;
+        borrow = b1 || b2;
+        i += 1;
+    }
+    (out, borrow)
+}
+
+/// `a + b`, with the carry out.
+const fn add_limbs(a: [u64; 4], b: [u64; 4]) -> ([u64; 4], bool) {
+    let mut out = [0; 4];
+    let mut carry = false;
+    let mut i = 0;
+    while i < 4 {
+        let (s, c1) = a[i].overflowing_add(b[i]);
+        let (s, c2) = s.overflowing_add(carry as u64);
+        out[i] = s;
+        carry = c1 || c2;
+        i += 1;
+    }
+    (out, carry)
+}
+
+/// `a + b mod p`, for `a` and `b` below `p`.
+const fn add_mod(a: [u64; 4], b: [u64; 4], p: [u64; 4]) -> [u64; 4] {
+    let (sum, carry) = add_limbs(a, b);
+    if carry || geq(sum, p) {
+        sub_limbs(sum, p).0
+    } else {
+        sum
+    }
+}
+
+const fn double_times(mut x: [u64; 4], times: u32, p: [u64; 4]) -> [u64; 4] {
+    let mut i = 0;
+    while i < times {
+        x = add_mod(x, x, p);
+        i += 1;
+    }
+    x
+}
+
+/// `a + b * c + carry`, as low and high words.
+fn mac(a: u64, b: u64, c: u64, carry: u64) -> (u64, u64) {
+    let t = u128::from(a) + u128::from(b) * u128::from(c) + u128::from(carry);
+    (t as u64, (t >> 64) as u64)
+}
+
+/// An element of the field `M` names.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Fp<M: Modulus>([u64; 4], PhantomData<M>);
+
+impl<M: Modulus> Fp<M> {
+    pub const ZERO: Self = Fp([0; 4], PhantomData);
+    pub const ONE: Self = Fp(M::R, PhantomData);
+
+    /// Montgomery multiplication: `a * b / 2^256 mod P`.
+    fn mont_mul(a: &[u64; 4], b: &[u64; 4]) -> [u64; 4] {
+        let p = M::P;
+        let mut t = [0u64; 6];
+        for &ai in a {
+            let mut carry = 0;
+            for j in 0..4 {
+                (t[j], carry) = mac(t[j], ai, b[j], carry);
+            }
+            let (sum, overflow) = t[4].overflowing_add(carry);
+            t[4] = sum;
+            t[5] = overflow as u64;
+
+            let m = t[0].wrapping_mul(M::INV);
+            let (_, mut carry) = mac(t[0], m, p[0], 0);
+            for j in 1..4 {
+                (t[j - 1], carry) = mac(t[j], m, p[j], carry);
+            }
+            let (sum, overflow) = t[4].overflowing_add(carry);
+            t[3] = sum;
+            t[4] = t[5] + overflow as u64;
+        }
+        let r = [t[0], t[1], t[2], t[3]];
+        if t[4] != 0 || geq(r, p) {
+            sub_limbs(r, p).0
+        } else {
+            r
+        }
+    }
+
+    /// The element with the little-endian limbs `limbs`, which must be below `P`.
+    pub fn from_limbs(limbs: [u64; 4]) -> Option<Self> {
+        (!geq(limbs, M::P)).then(|| Fp(Self::mont_mul(&limbs, &M::R2), PhantomData))
+    }
+
+    pub fn from_u64(n: u64) -> Self {
+        Self::from_limbs([n, 0, 0, 0]).unwrap_or(Self::ZERO)
+    }
+
+    /// The canonical little-endian limbs.
+    pub fn to_limbs(self) -> [u64; 4] {
+        Self::mont_mul(&self.0, &[1, 0, 0, 0])
+    }
+
+    /// Parse the canonical 32-byte little-endian encoding; `None` if it is not below `P`.
+    pub fn from_bytes_le(bytes: &[u8; 32]) -> Option<Self> {
+        let mut limbs = [0; 4];
+        for (limb, chunk) in limbs.iter_mut().zip(bytes.chunks_exact(8)) {
+            *limb = u64::from_le_bytes(chunk.try_into().unwrap());
+        }
+        Self::from_limbs(limbs)
+    }
+
+    pub fn to_bytes_le(self) -> [u8; 32] {
+        let mut out = [0; 32];
+        for (chunk, limb) in out.chunks_exact_mut(8).zip(self.to_limbs()) {
+            chunk.copy_from_slice(&limb.to_le_bytes());
+        }
+        out
+    }
+
+    pub fn is_zero(self) -> bool {
+        self.0 == [0; 4]
+    }
+
+    /// Whether the canonical value is odd: the "sign" point encodings carry.
+    pub fn is_odd(self) -> bool {
+        self.to_limbs()[0] & 1 == 1
+    }
+
+    pub fn double(self) -> Self {
+        self + self
+    }
+
+    pub fn square(self) -> Self {
+        self * self
+    }
+
+    /// `self` to the power of the little-endian limbs `exp`.
+    pub fn pow(self, exp: [u64; 4]) -> Self {
+        let mut acc = Self::ONE;
+        for limb in exp.iter().rev() {
+            for bit in (0..64).rev() {
+                acc = acc.square();
+                if (limb >> bit) & 1 == 1 {
+                    acc = acc * self;
+                }
+            }
+        }
+        acc
+    }
+
+    /// The multiplicative inverse, `None` for zero.
+    pub fn invert(self) -> Option<Self> {
+        (!self.is_zero()).then(|| self.pow(sub_limbs(M::P, [2, 0, 0, 0]).0))
+    }
+
+    /// A square root, by Tonelli–Shanks; `None` if `self` is not a square.
+    pub fn sqrt(self) -> Option<Self> {
+        if self.is_zero() {
+            return Some(self);
+        }
+        let p_minus_1 = sub_limbs(M::P, [1, 0, 0, 0]).0;
+        let half = shr(p_minus_1, 1);
+        if self.pow(half) != Self::ONE {
+            return None;
+        }
+        // p - 1 = q * 2^s with q odd.
+        let s = p_minus_1
+            .iter()
+            .enumerate()
+            .find(|(_, limb)| **limb != 0)
+            .map_or(0, |(i, limb)| i as u32 * 64 + limb.trailing_zeros());
+        let q = shr(p_minus_1, s);
+        let minus_one = -Self::ONE;
+        let non_residue = (2..)
+            .map(Self::from_u64)
+            .find(|z| z.pow(half) == minus_one)
+            .expect("a prime field has non-residues");
+
+        let mut m = s;
+        let mut c = non_residue.pow(q);
+        let mut t = self.pow(q);
+        let mut r = self.pow(shr(add_limbs(q, [1, 0, 0, 0]).0, 1));
+        while t != Self::ONE {
+            let mut i = 0;
+            let mut t2i = t;
+            while t2i != Self::ONE {
+                t2i = t2i.square();
+                i += 1;
+            }
+            let mut b = c;
+            for _ in 0..m - i - 1 {
+                b = b.square();
+            }
+            m = i;
+            c = b.square();
+            t = t * c;
+            r = r * b;
+        }
+        Some(r)
+    }
+}
+
+/// `x >> n`, for `n` below 256.
+fn shr(x: [u64; 4], n: u32) -> [u64; 4] {
+    let (words, bits) = ((n / 64) as usize, n % 64);
+    let mut out = [0; 4];
+    for i in 0..4 - words {
+        out[i] = x[i + words] >> bits;
+        if bits > 0 && i + words + 1 < 4 {
+            out[i] |= x[i + words + 1] << (64 - bits);
+        }
+    }
+    out
+}
+
+impl<M: Modulus> Add for Fp<M> {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self {
+        Fp(add_mod(self.0, rhs.0, M::P), PhantomData)
+    }
+}
+
+impl<M: Modulus> Sub for Fp<M> {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self {
+        self + -rhs
+    }
+}
+
+impl<M: Modulus> Neg for Fp<M> {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        match self.is_zero() {
+            true => self,
+            false => Fp(sub_limbs(M::P, self.0).0, PhantomData),
+        }
+    }
+}
+
+impl<M: Modulus> Mul for Fp<M> {
+    type Output = Self;
+
+    fn mul(self, rhs: Self) -> Self {
+        Fp(Self::mont_mul(&self.0, &rhs.0), PhantomData)
+    }
+}
+
+impl<M: Modulus> fmt::Debug for Fp<M> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let [a, b, c, d] = self.to_limbs();
+        write!(f, "0x{d:016x}{c:016x}{b:016x}{a:016x}")
+    }
+}
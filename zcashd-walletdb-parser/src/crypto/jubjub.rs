@@ -0,0 +1,198 @@
+//! Jubjub, the twisted Edwards curve `-u^2 + v^2 = 1 + d u^2 v^2` over the scalar field
+//! of BLS12-381 that Sapling builds its keys and commitments on, and the group hash
+//! Sapling derives its generators with.
+
+use std::sync::OnceLock;
+
+use crate::crypto::{
+    blake2s::Blake2s,
+    field::{Fp, Modulus},
+};
+
+/// The base field of Jubjub: the scalar field of BLS12-381.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct JubjubBase;
+
+impl Modulus for JubjubBase {
+    const P: [u64; 4] = [
+        0xffff_ffff_0000_0001,
+        0x53bd_a402_fffe_5bfe,
+        0x3339_d808_09a1_d805,
+        0x73ed_a753_299d_7d48,
+    ];
+}
+
+pub type Fq = Fp<JubjubBase>;
+
+/// The first block every group hash input starts with: a string committed to before
+/// Zcash's parameters, so that no one could choose generators with known relations.
+pub const GH_FIRST_BLOCK: &[u8; 64] =
+    b"096b36a5804bfacef1691e173c366a47ff5ba84a44f26ddd7e8d9f79d5b42df0";
+
+/// `d = -(10240/10241)`.
+fn edwards_d() -> Fq {
+    static D: OnceLock<Fq> = OnceLock::new();
+    *D.get_or_init(|| {
+        let inverse = Fq::from_u64(10241).invert().expect("10241 is not zero");
+        -(Fq::from_u64(10240) * inverse)
+    })
+}
+
+/// A point in extended coordinates: `u = U/Z`, `v = V/Z` and `T = U V / Z`.
+#[derive(Debug, Clone, Copy)]
+pub struct Point {
+    u: Fq,
+    v: Fq,
+    z: Fq,
+    t: Fq,
+}
+
+impl Point {
+    pub const IDENTITY: Point = Point {
+        u: Fq::ZERO,
+        v: Fq::ONE,
+        z: Fq::ONE,
+        t: Fq::ZERO,
+    };
+
+    /// The point `(u, v)`, if it is on the curve.
+    pub fn from_affine(u: Fq, v: Fq) -> Option<Self> {
+        let (u2, v2) = (u.square(), v.square());
+        (v2 - u2 == Fq::ONE + edwards_d() * u2 * v2).then_some(Point {
+            u,
+            v,
+            z: Fq::ONE,
+            t: u * v,
+        })
+    }
+
+    /// Decode the 32-byte encoding `repr_J`: `v` little-endian, with the sign of `u` in
+    /// the top bit. Non-canonical encodings and `u = 0` with the sign set (ZIP 216) are
+    /// rejected.
+    pub fn from_bytes(bytes: &[u8; 32]) -> Option<Self> {
+        let sign = bytes[31] >> 7 == 1;
+        let mut v_bytes = *bytes;
+        v_bytes[31] &= 0x7f;
+        let v = Fq::from_bytes_le(&v_bytes)?;
+        // u^2 = (v^2 - 1) / (d v^2 + 1); the denominator is never zero as d is not a square.
+        let v2 = v.square();
+        let u2 = (v2 - Fq::ONE) * (edwards_d() * v2 + Fq::ONE).invert()?;
+        let mut u = u2.sqrt()?;
+        if u.is_zero() && sign {
+            return None;
+        }
+        if u.is_odd() != sign {
+            u = -u;
+        }
+        Point::from_affine(u, v)
+    }
+
+    /// The affine coordinates `(u, v)`.
+    pub fn to_affine(&self) -> (Fq, Fq) {
+        let zinv = self.z.invert().expect("extended points have Z != 0");
+        (self.u * zinv, self.v * zinv)
+    }
+
+    pub fn to_bytes(&self) -> [u8; 32] {
+        let (u, v) = self.to_affine();
+        let mut bytes = v.to_bytes_le();
+        bytes[31] |= u8::from(u.is_odd()) << 7;
+        bytes
+    }
+
+    /// `Extract_J`: the `u`-coordinate, which is all Sapling's hashes keep of a point.
+    pub fn extract(&self) -> [u8; 32] {
+        self.to_affine().0.to_bytes_le()
+    }
+
+    pub fn is_identity(&self) -> bool {
+        self.u.is_zero() && self.v == self.z
+    }
+
+    /// Complete addition for `a = -1` (Hisil–Wong–Carter–Dawson, `add-2008-hwcd-3`).
+    pub fn add(&self, other: &Point) -> Point {
+        let two_d = edwards_d().double();
+        let a = (self.v - self.u) * (other.v - other.u);
+        let b = (self.v + self.u) * (other.v + other.u);
+        let c = self.t * two_d * other.t;
+        let d = self.z.double() * other.z;
+        let (e, f, g, h) = (b - a, d - c, d + c, b + a);
+        Point {
+            u: e * f,
+            v: g * h,
+            z: f * g,
+            t: e * h,
+        }
+    }
+
+    pub fn double(&self) -> Point {
+        self.add(self)
+    }
+
+    pub fn neg(&self) -> Point {
+        Point {
+            u: -self.u,
+            t: -self.t,
+            ..*self
+        }
+    }
+
+    /// The point times the little-endian integer `scalar`, of any length. Points of the
+    /// prime-order subgroup only depend on the scalar modulo the group order.
+    pub fn mul_le(&self, scalar: &[u8]) -> Point {
+        let mut acc = Point::IDENTITY;
+        for byte in scalar.iter().rev() {
+            for bit in (0..8).rev() {
+                acc = acc.double();
+                if (byte >> bit) & 1 == 1 {
+                    acc = acc.add(self);
+                }
+            }
+        }
+        acc
+    }
+
+    /// Multiply by the cofactor, 8, landing in the prime-order subgroup.
+    pub fn clear_cofactor(&self) -> Point {
+        self.double().double().double()
+    }
+}
+
+impl PartialEq for Point {
+    fn eq(&self, other: &Self) -> bool {
+        self.u * other.z == other.u * self.z && self.v * other.z == other.v * self.z
+    }
+}
+
+impl Eq for Point {}
+
+/// `GroupHash^J`: hash `tag` under the 8-byte `personal`ization to a point of the
+/// prime-order subgroup, or `None` if the digest does not decode to one.
+pub fn group_hash(tag: &[u8], personal: &[u8; 8]) -> Option<Point> {
+    let mut h = Blake2s::new(32, personal);
+    h.update(GH_FIRST_BLOCK);
+    h.update(tag);
+    let digest: [u8; 32] = h.finalize().try_into().unwrap();
+    let point = Point::from_bytes(&digest)?.clear_cofactor();
+    (!point.is_identity()).then_some(point)
+}
+
+/// `FindGroupHash^J`: the first of `group_hash(tag || [i])` for `i = 0, 1, ...` that
+/// yields a point.
+pub fn find_group_hash(tag: &[u8], personal: &[u8; 8]) -> Point {
+    (0..=u8::MAX)
+        .find_map(|i| group_hash(&[tag, &[i]].concat(), personal))
+        .expect("one of 256 group hashes lands on the curve")
+}
+
+/// `𝒢^Sapling`, the spend authorization generator: `ak = [ask] 𝒢`.
+pub fn spending_key_generator() -> Point {
+    static GENERATOR: OnceLock<Point> = OnceLock::new();
+    *GENERATOR.get_or_init(|| find_group_hash(b"", b"Zcash_G_"))
+}
+
+/// `ℋ^Sapling`, the proof generation key generator: `nk = [nsk] ℋ`.
+pub fn proof_generation_key_generator() -> Point {
+    static GENERATOR: OnceLock<Point> = OnceLock::new();
+    *GENERATOR.get_or_init(|| find_group_hash(b"", b"Zcash_H_"))
+}
@@ -0,0 +1,76 @@
+//! Sapling's Pedersen hash, which hashes the nodes of the Sapling note commitment tree.
+//!
+//! The input bits are cut into 3-bit chunks and the chunks into segments of 63. Chunk
+//! `j` of a segment encodes `(1 - 2 s2) (1 + s0 + 2 s1)` times `16^j`, and each segment
+//! multiplies its own generator, found with the group hash.
+
+use std::sync::OnceLock;
+
+use crate::crypto::jubjub::{Point, find_group_hash};
+
+/// The group hash personalization of the Pedersen generators.
+const GENERATORS_PERSONALIZATION: &[u8; 8] = b"Zcash_PH";
+
+const CHUNKS_PER_GENERATOR: usize = 63;
+
+/// Enough generators for every Pedersen hash Sapling computes.
+const GENERATORS: usize = 6;
+
+/// The bits of the field elements a tree node holds: the 255 of a BLS12-381 scalar.
+const NODE_BITS: usize = 255;
+
+fn generators() -> &'static [Point; GENERATORS] {
+    static GENERATORS_TABLE: OnceLock<[Point; GENERATORS]> = OnceLock::new();
+    GENERATORS_TABLE.get_or_init(|| {
+        std::array::from_fn(|i| {
+            find_group_hash(&(i as u32).to_le_bytes(), GENERATORS_PERSONALIZATION)
+        })
+    })
+}
+
+/// `PedersenHashToPoint` of `bits`. Panics if the input needs more generators than
+/// Sapling defines.
+pub fn pedersen_hash(bits: impl IntoIterator<Item = bool>) -> Point {
+    let mut bits = bits.into_iter().peekable();
+    let mut result = Point::IDENTITY;
+    for generator in generators() {
+        if bits.peek().is_none() {
+            return result;
+        }
+        // Walk the multiples 16^j of the generator rather than summing scalars.
+        let mut base = *generator;
+        for _ in 0..CHUNKS_PER_GENERATOR {
+            let Some(s0) = bits.next() else {
+                break;
+            };
+            let s1 = bits.next().unwrap_or(false);
+            let s2 = bits.next().unwrap_or(false);
+            let mut chunk = match (s0, s1) {
+                (false, false) => base,
+                (true, false) => base.double(),
+                (false, true) => base.double().add(&base),
+                (true, true) => base.double().double(),
+            };
+            if s2 {
+                chunk = chunk.neg();
+            }
+            result = result.add(&chunk);
+            base = base.double().double().double().double();
+        }
+    }
+    assert!(bits.peek().is_none(), "Pedersen hash input too long");
+    result
+}
+
+/// `MerkleCRH^Sapling`: the parent of `left` and `right` at `level` of the note commitment
+/// tree, counting the leaves as level 0.
+pub fn merkle_hash(level: u8, left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+    let bits_of = |bytes: &[u8; 32]| {
+        (0..NODE_BITS)
+            .map(|i| (bytes[i / 8] >> (i % 8)) & 1 == 1)
+            .collect::<Vec<_>>()
+    };
+    let personalization = (0..6).map(|i| (level >> i) & 1 == 1);
+    let bits = personalization.chain(bits_of(left)).chain(bits_of(right));
+    pedersen_hash(bits).extract()
+}
@@ -0,0 +1,150 @@
+//! RIPEMD-160. zcashd hashes public keys and scripts with it, after SHA-256, to form
+//! transparent addresses.
+
+use crate::crypto::{Digest, sha256::Sha256};
+
+/// Message word order of the left and right lines, round by round.
+const R: [usize; 80] = [
+    0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 7, 4, 13, 1, 10, 6, 15, 3, 12, 0, 9, 5,
+    2, 14, 11, 8, 3, 10, 14, 4, 9, 15, 8, 1, 2, 7, 0, 6, 13, 11, 5, 12, 1, 9, 11, 10, 0, 8, 12, 4,
+    13, 3, 7, 15, 14, 5, 6, 2, 4, 0, 5, 9, 7, 12, 2, 10, 14, 1, 3, 8, 11, 6, 15, 13,
+];
+const R_PRIME: [usize; 80] = [
+    5, 14, 7, 0, 9, 2, 11, 4, 13, 6, 15, 8, 1, 10, 3, 12, 6, 11, 3, 7, 0, 13, 5, 10, 14, 15, 8, 12,
+    4, 9, 1, 2, 15, 5, 1, 3, 7, 14, 6, 9, 11, 8, 12, 2, 10, 0, 4, 13, 8, 6, 4, 1, 3, 11, 15, 0, 5,
+    12, 2, 13, 9, 7, 10, 14, 12, 15, 10, 4, 1, 5, 8, 7, 6, 2, 13, 14, 0, 3, 9, 11,
+];
+
+/// Rotation amounts of the left and right lines.
+const S: [u32; 80] = [
+    11, 14, 15, 12, 5, 8, 7, 9, 11, 13, 14, 15, 6, 7, 9, 8, 7, 6, 8, 13, 11, 9, 7, 15, 7, 12, 15,
+    9, 11, 7, 13, 12, 11, 13, 6, 7, 14, 9, 13, 15, 14, 8, 13, 6, 5, 12, 7, 5, 11, 12, 14, 15, 14,
+    15, 9, 8, 9, 14, 5, 6, 8, 6, 5, 12, 9, 15, 5, 11, 6, 8, 13, 12, 5, 12, 13, 14, 11, 8, 5, 6,
+];
+const S_PRIME: [u32; 80] = [
+    8, 9, 9, 11, 13, 15, 15, 5, 7, 7, 8, 11, 14, 14, 12, 6, 9, 13, 15, 7, 12, 8, 9, 11, 7, 7, 12,
+    7, 6, 15, 13, 11, 9, 7, 15, 11, 8, 6, 6, 14, 12, 13, 5, 14, 13, 13, 7, 5, 15, 5, 8, 11, 14, 14,
+    6, 14, 6, 9, 12, 9, 12, 5, 15, 8, 8, 5, 12, 9, 12, 5, 14, 6, 8, 13, 6, 5, 15, 13, 11, 11,
+];
+
+const K: [u32; 5] = [
+    0x0000_0000,
+    0x5a82_7999,
+    0x6ed9_eba1,
+    0x8f1b_bcdc,
+    0xa953_fd4e,
+];
+const K_PRIME: [u32; 5] = [
+    0x50a2_8be6,
+    0x5c4d_d124,
+    0x6d70_3ef3,
+    0x7a6d_76e9,
+    0x0000_0000,
+];
+
+/// The boolean function of round `round` (0 to 4).
+fn f(round: usize, x: u32, y: u32, z: u32) -> u32 {
+    match round {
+        0 => x ^ y ^ z,
+        1 => (x & y) | (!x & z),
+        2 => (x | !y) ^ z,
+        3 => (x & z) | (y & !z),
+        _ => x ^ (y | !z),
+    }
+}
+
+#[derive(Clone)]
+pub struct Ripemd160 {
+    state: [u32; 5],
+    buf: Vec<u8>,
+    len: u64,
+}
+
+impl Ripemd160 {
+    fn compress(&mut self, block: &[u8]) {
+        let mut x = [0u32; 16];
+        for (i, chunk) in block.chunks_exact(4).enumerate() {
+            x[i] = u32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]);
+        }
+
+        let [mut a, mut b, mut c, mut d, mut e] = self.state;
+        let [mut ap, mut bp, mut cp, mut dp, mut ep] = self.state;
+        for j in 0..80 {
+            let round = j / 16;
+            let t = a
+                .wrapping_add(f(round, b, c, d))
+                .wrapping_add(x[R[j]])
+                .wrapping_add(K[round])
+                .rotate_left(S[j])
+                .wrapping_add(e);
+            (a, e, d, c, b) = (e, d, c.rotate_left(10), b, t);
+            let t = ap
+                .wrapping_add(f(4 - round, bp, cp, dp))
+                .wrapping_add(x[R_PRIME[j]])
+                .wrapping_add(K_PRIME[round])
+                .rotate_left(S_PRIME[j])
+                .wrapping_add(ep);
+            (ap, ep, dp, cp, bp) = (ep, dp, cp.rotate_left(10), bp, t);
+        }
+        let [h0, h1, h2, h3, h4] = self.state;
+        self.state = [
+            h1.wrapping_add(c).wrapping_add(dp),
+            h2.wrapping_add(d).wrapping_add(ep),
+            h3.wrapping_add(e).wrapping_add(ap),
+            h4.wrapping_add(a).wrapping_add(bp),
+            h0.wrapping_add(b).wrapping_add(cp),
+        ];
+    }
+}
+
+impl Digest for Ripemd160 {
+    const BLOCK_LEN: usize = 64;
+    const OUTPUT_LEN: usize = 20;
+
+    fn new() -> Self {
+        Ripemd160 {
+            state: [
+                0x6745_2301,
+                0xefcd_ab89,
+                0x98ba_dcfe,
+                0x1032_5476,
+                0xc3d2_e1f0,
+            ],
+            buf: Vec::with_capacity(64),
+            len: 0,
+        }
+    }
+
+    fn update(&mut self, mut data: &[u8]) {
+        self.len += data.len() as u64;
+        if !self.buf.is_empty() {
+            let take = (64 - self.buf.len()).min(data.len());
+            self.buf.extend_from_slice(&data[..take]);
+            data = &data[take..];
+            if self.buf.len() < 64 {
+                return;
+            }
+            let block = std::mem::take(&mut self.buf);
+            self.compress(&block);
+        }
+        let mut blocks = data.chunks_exact(64);
+        for block in &mut blocks {
+            self.compress(block);
+        }
+        self.buf.extend_from_slice(blocks.remainder());
+    }
+
+    fn finalize(mut self) -> Vec<u8> {
+        let bit_len = self.len.wrapping_mul(8);
+        let mut pad = vec![0x80u8];
+        pad.resize((119 - (self.len % 64) as usize) % 64 + 1, 0);
+        pad.extend_from_slice(&bit_len.to_le_bytes());
+        self.update(&pad);
+        self.state.iter().flat_map(|s| s.to_le_bytes()).collect()
+    }
+}
+
+/// RIPEMD-160 of SHA-256, as zcashd's `Hash160()` computes it.
+pub fn hash160(data: &[u8]) -> [u8; 20] {
+    Ripemd160::digest(&Sha256::digest(data)).try_into().unwrap()
+}
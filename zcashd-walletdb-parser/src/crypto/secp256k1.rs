@@ -0,0 +1,173 @@
+//! secp256k1, the short Weierstrass curve `y^2 = x^3 + 7` transparent keys live on: just
+//! enough to recompute the public key of a private key.
+//!
+//! This is variable-time arithmetic, fine for checking keys read from a file, not for
+//! signing.
+
+use crate::crypto::field::{Fp, Modulus};
+
+/// The base field of secp256k1: `p = 2^256 - 2^32 - 977`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Secp256k1Base;
+
+impl Modulus for Secp256k1Base {
+    const P: [u64; 4] = [
+        0xffff_fffe_ffff_fc2f,
+        0xffff_ffff_ffff_ffff,
+        0xffff_ffff_ffff_ffff,
+        0xffff_ffff_ffff_ffff,
+    ];
+}
+
+pub type Fe = Fp<Secp256k1Base>;
+
+/// The order of the generator, big-endian: private keys are the scalars in `1..N`.
+pub const N: [u8; 32] = [
+    0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xfe,
+    0xba, 0xae, 0xdc, 0xe6, 0xaf, 0x48, 0xa0, 0x3b, 0xbf, 0xd2, 0x5e, 0x8c, 0xd0, 0x36, 0x41, 0x41,
+];
+
+const GX: [u8; 32] = [
+    0x79, 0xbe, 0x66, 0x7e, 0xf9, 0xdc, 0xbb, 0xac, 0x55, 0xa0, 0x62, 0x95, 0xce, 0x87, 0x0b, 0x07,
+    0x02, 0x9b, 0xfc, 0xdb, 0x2d, 0xce, 0x28, 0xd9, 0x59, 0xf2, 0x81, 0x5b, 0x16, 0xf8, 0x17, 0x98,
+];
+
+const GY: [u8; 32] = [
+    0x48, 0x3a, 0xda, 0x77, 0x26, 0xa3, 0xc4, 0x65, 0x5d, 0xa4, 0xfb, 0xfc, 0x0e, 0x11, 0x08, 0xa8,
+    0xfd, 0x17, 0xb4, 0x48, 0xa6, 0x85, 0x54, 0x19, 0x9c, 0x47, 0xd0, 0x8f, 0xfb, 0x10, 0xd4, 0xb8,
+];
+
+fn fe_from_be(bytes: &[u8; 32]) -> Option<Fe> {
+    let mut le = *bytes;
+    le.reverse();
+    Fe::from_bytes_le(&le)
+}
+
+fn fe_to_be(fe: Fe) -> [u8; 32] {
+    let mut be = fe.to_bytes_le();
+    be.reverse();
+    be
+}
+
+/// A point in Jacobian coordinates: `x = X/Z^2`, `y = Y/Z^3`, and `Z = 0` at infinity.
+#[derive(Debug, Clone, Copy)]
+pub struct Point {
+    x: Fe,
+    y: Fe,
+    z: Fe,
+}
+
+impl Point {
+    pub const INFINITY: Point = Point {
+        x: Fe::ONE,
+        y: Fe::ONE,
+        z: Fe::ZERO,
+    };
+
+    /// The standard generator `G`.
+    pub fn generator() -> Point {
+        Point {
+            x: fe_from_be(&GX).expect("Gx is below p"),
+            y: fe_from_be(&GY).expect("Gy is below p"),
+            z: Fe::ONE,
+        }
+    }
+
+    pub fn is_infinity(&self) -> bool {
+        self.z.is_zero()
+    }
+
+    /// `(x, y)`, or `None` at infinity.
+    pub fn to_affine(&self) -> Option<(Fe, Fe)> {
+        let z_inv = self.z.invert()?;
+        let z_inv2 = z_inv.square();
+        Some((self.x * z_inv2, self.y * z_inv2 * z_inv))
+    }
+
+    /// The SEC 1 encoding: `02`/`03` and `x` when `compressed`, else `04`, `x` and `y`.
+    /// `None` at infinity, which has none.
+    pub fn to_sec1(&self, compressed: bool) -> Option<Vec<u8>> {
+        let (x, y) = self.to_affine()?;
+        Some(match compressed {
+            true => [&[0x02 | y.is_odd() as u8][..], &fe_to_be(x)].concat(),
+            false => [&[0x04][..], &fe_to_be(x), &fe_to_be(y)].concat(),
+        })
+    }
+
+    /// Doubling for `a = 0` (`dbl-2009-l`).
+    pub fn double(&self) -> Point {
+        if self.is_infinity() {
+            return *self;
+        }
+        let a = self.x.square();
+        let b = self.y.square();
+        let c = b.square();
+        let d = ((self.x + b).square() - a - c).double();
+        let e = a.double() + a;
+        let f = e.square();
+        let x = f - d.double();
+        let eight_c = c.double().double().double();
+        Point {
+            x,
+            y: e * (d - x) - eight_c,
+            z: (self.y * self.z).double(),
+        }
+    }
+
+    /// Addition (`add-2007-bl`), falling back to doubling for equal points.
+    pub fn add(&self, other: &Point) -> Point {
+        if self.is_infinity() {
+            return *other;
+        }
+        if other.is_infinity() {
+            return *self;
+        }
+        let z1z1 = self.z.square();
+        let z2z2 = other.z.square();
+        let u1 = self.x * z2z2;
+        let u2 = other.x * z1z1;
+        let s1 = self.y * other.z * z2z2;
+        let s2 = other.y * self.z * z1z1;
+        let h = u2 - u1;
+        let r = (s2 - s1).double();
+        if h.is_zero() {
+            return match r.is_zero() {
+                true => self.double(),
+                false => Point::INFINITY,
+            };
+        }
+        let i = h.double().square();
+        let j = h * i;
+        let v = u1 * i;
+        let x = r.square() - j - v.double();
+        Point {
+            x,
+            y: r * (v - x) - (s1 * j).double(),
+            z: ((self.z + other.z).square() - z1z1 - z2z2) * h,
+        }
+    }
+
+    /// The point times the big-endian integer `scalar`, of any length.
+    pub fn mul_be(&self, scalar: &[u8]) -> Point {
+        let mut acc = Point::INFINITY;
+        for byte in scalar {
+            for bit in (0..8).rev() {
+                acc = acc.double();
+                if (byte >> bit) & 1 == 1 {
+                    acc = acc.add(self);
+                }
+            }
+        }
+        acc
+    }
+}
+
+/// The SEC 1 public key of the 32-byte big-endian private key `secret`, or `None` if
+/// `secret` is not a scalar in `1..N`.
+pub fn public_key(secret: &[u8], compressed: bool) -> Option<Vec<u8>> {
+    let secret: &[u8; 32] = secret.try_into().ok()?;
+    if secret.iter().all(|&b| b == 0) || secret >= &N {
+        return None;
+    }
+    Point::generator().mul_be(secret).to_sec1(compressed)
+}
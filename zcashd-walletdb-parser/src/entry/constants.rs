@@ -1,5 +1,5 @@
 use crate::{
-    constants::BTDATAOFF,
+    storage::page::PageProtection,
     util::{Endian, u16e},
 };
 
@@ -11,17 +11,17 @@ pub struct OverflowRef {
     pub total_len: u32,
 }
 
-/// A BLEAF field (key or value), either inline on the leaf page or off-page (overflow).
-#[derive(Debug)]
-pub enum Field<'a> {
-    Inline(&'a [u8]),
-    Overflow(OverflowRef),
-}
-
-/// Read u16 offsets from the slot array (28..lower).
-pub fn iter_slots<'a>(page: &'a [u8], e: Endian, lower: u16) -> impl Iterator<Item = u16> + 'a {
+/// Read u16 offsets from the slot array, which runs from the end of the page header and
+/// its `protection` block up to `lower`, stopping at the end of the page if `lower` points
+/// past it.
+pub fn iter_slots<'a>(
+    page: &'a [u8],
+    e: Endian,
+    protection: PageProtection,
+    lower: u16,
+) -> impl Iterator<Item = u16> + 'a {
     let lower = lower as usize;
-    (BTDATAOFF..lower)
+    (protection.header_size()..lower)
         .step_by(2)
-        .map(move |i| u16e(e, &page[i..i + 2]))
+        .map_while(move |i| page.get(i..i + 2).map(|b| u16e(e, b)))
 }
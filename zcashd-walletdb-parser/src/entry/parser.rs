@@ -1,248 +1,369 @@
-use anyhow::{Result, ensure};
+use alloc::{collections::BTreeSet, string::ToString, vec::Vec};
 
 use crate::{
-    constants::BTDATAOFF,
-    entry::constants::{Field, OverflowRef},
+    entry::constants::OverflowRef,
+    error::{OverflowChainError, ParseMode, Result, WalletDbError},
     leaf::{LeafItem, ParsedLeafEntry, parse_leaf_entry},
-    page::PageType,
-    util::{Endian, PageHeader, page_slice, parse_page_header, u16e, u32e},
+    storage::{
+        entry::{Confidence, Provenance},
+        page::{PageHeader, PageProtection, PageType},
+        slots::{SlotReport, validate_slot_array},
+    },
+    util::{Endian, page_slice, parse_page_header},
 };
 
-/// Read absolute byte offsets from the slot array [BTDATAOFF .. lower).
-#[inline]
-fn slot_abs_offsets<'a>(page: &'a [u8], e: Endian, lower: u16) -> impl Iterator<Item = usize> + 'a {
-    let lower = lower as usize;
-    (BTDATAOFF..lower)
-        .step_by(2)
-        .map(move |i| u16e(e, &page[i..i + 2]) as usize)
-}
+/// A materialized key/value pair and where it was read from.
+pub type KvRecord = (Vec<u8>, Vec<u8>, Provenance);
 
-/// Walk an OVERFLOW chain and materialize `total_len` bytes.
-/// Each page contributes `page[BTDATAOFF..]`; follow `hdr.next`.
-fn read_overflow_chain(
-    all: &[u8],
-    ps: usize,
+/// Validate the slot array of a leaf page, which starts right after the page header and
+/// its `protection` block.
+fn leaf_slot_report(
+    page: &[u8],
     e: Endian,
-    r: OverflowRef,
-) -> anyhow::Result<Vec<u8>> {
-    use anyhow::ensure;
-    let mut out = Vec::with_capacity(r.total_len as usize);
-    let mut pg = r.first_page;
-    let mut rem = r.total_len as usize;
-
-    while rem > 0 {
-        let page = page_slice(all, ps, pg);
-        let hdr = parse_page_header(page, e)?;
-        ensure!(
-            matches!(hdr.ptype, PageType::Overflow),
-            "expected overflow page"
-        );
-        let payload = &page[BTDATAOFF..];
-        let take = rem.min(payload.len());
-        out.extend_from_slice(&payload[..take]);
-        rem -= take;
-        if rem == 0 {
-            break;
-        }
-        ensure!(
-            hdr.next != 0,
-            "overflow chain ended early (need {rem} more bytes)"
-        );
-        pg = hdr.next;
-    }
-    Ok(out)
+    protection: PageProtection,
+    hdr: &PageHeader,
+) -> SlotReport {
+    validate_slot_array(
+        page,
+        hdr.pgno,
+        PageType::BtreeLeaf,
+        protection.header_size(),
+        hdr.entries as usize,
+        hdr.hf_offset as usize,
+        e.into(),
+    )
 }
+
 /// Extract (key,value) pairs from a **leaf** page.
 /// Pairs are formed by taking the next **non-deleted** entry as value
 /// for the previous **non-deleted** entry as key.
+/// Anomalies are skipped; see [`leaf_pairs_on_page_checked`].
+///
+/// Each pair carries its [`Provenance`]: `source_id`, the page number from `hdr` and the
+/// key's slot. Pairs from a page with slot violations have [`Confidence::Medium`].
+///
+/// `protection` is that of every page of the image, as the meta page records it.
 pub fn leaf_pairs_on_page(
+    source_id: &str,
     all: &[u8],
     ps: usize,
     e: Endian,
+    protection: PageProtection,
     page: &[u8],
     hdr: &PageHeader,
-) -> anyhow::Result<Vec<(Vec<u8>, Vec<u8>)>> {
-    use anyhow::ensure;
-    ensure!(matches!(hdr.ptype, PageType::Leaf), "not a leaf page");
+) -> Result<Vec<KvRecord>> {
+    leaf_pairs_on_page_checked(
+        source_id,
+        all,
+        ps,
+        e,
+        protection,
+        page,
+        hdr,
+        ParseMode::Lenient,
+    )
+    .map(|leaf| leaf.pairs)
+}
 
-    // Build absolute offsets from the slot array.
-    let offs: Vec<usize> = slot_abs_offsets(page, e, hdr.entries).collect();
+/// Pairs of one leaf page, with what was left out of them.
+#[derive(Debug)]
+pub struct LeafPairs {
+    pub pairs: Vec<KvRecord>,
+    /// Slot validation report of the page.
+    pub report: SlotReport,
+    /// Slots left out in [`ParseMode::Lenient`], and why.
+    pub skipped: Vec<(usize, WalletDbError)>,
+}
 
-    for &off in &offs {
-        // entry should live in packed region near the end of the page
-        if off < hdr.hf_offset as usize || off + 3 > page.len() {
-            // Log suspicious offsets instead of crashing
-            eprintln!(
-                "skip bad slot off={off} upper={} len={}",
-                hdr.hf_offset,
-                page.len()
-            );
-            continue;
-        }
+/// Like [`leaf_pairs_on_page`], also returning the slot validation report and the slots
+/// that were skipped. In [`ParseMode::Strict`] the first anomaly is an error instead:
+/// an unusable slot, an item that does not parse, an overflow chain that cannot be
+/// read, or a key left without a value at the end of the page.
+#[allow(clippy::too_many_arguments)]
+pub fn leaf_pairs_on_page_checked(
+    source_id: &str,
+    all: &[u8],
+    ps: usize,
+    e: Endian,
+    protection: PageProtection,
+    page: &[u8],
+    hdr: &PageHeader,
+    mode: ParseMode,
+) -> Result<LeafPairs> {
+    expect_leaf(hdr)?;
+
+    let report = leaf_slot_report(page, e, protection, hdr);
+    if mode == ParseMode::Strict
+        && let Some(violation) = report.violations.first()
+    {
+        return Err(WalletDbError::BadSlot {
+            pgno: hdr.pgno,
+            violation: violation.clone(),
+        });
     }
+    let confidence = if report.is_clean() {
+        Confidence::High
+    } else {
+        Confidence::Medium
+    };
+
+    let mut skipped = Vec::new();
+    let mut skip = |slot: usize, err: WalletDbError| -> Result<()> {
+        if mode == ParseMode::Strict {
+            return Err(err);
+        }
+        skipped.push((slot, err));
+        Ok(())
+    };
+    let read_item = |item: LeafItem| materialize(all, ps, e, protection, item);
 
     let mut out = Vec::new();
-    let mut pend: Option<ParsedLeafEntry> = None;
+    let mut pend: Option<(usize, ParsedLeafEntry)> = None;
 
-    for off in offs {
-        let entry = parse_leaf_entry(page, off, e)?;
+    // Keys sit at even slots and their values right after them, so a slot that is
+    // skipped takes its partner with it instead of shifting the pairs that follow.
+    for (slot, &off) in report.offsets.iter().enumerate() {
+        if !report.is_usable(slot) {
+            let violation = report
+                .violations
+                .iter()
+                .find(|v| v.slot() == Some(slot))
+                .cloned();
+            if let Some(violation) = violation {
+                skip(
+                    slot,
+                    WalletDbError::BadSlot {
+                        pgno: hdr.pgno,
+                        violation,
+                    },
+                )?;
+            }
+            pend = None;
+            continue;
+        }
+        let entry = match parse_leaf_entry(page, off, e) {
+            Ok(entry) => entry,
+            Err(err) => {
+                skip(slot, err)?;
+                pend = None;
+                continue;
+            }
+        };
         if entry.deleted {
+            pend = None;
             continue;
         }
 
         match pend.take() {
+            // the value of a key that was skipped
+            None if slot % 2 == 1 => {}
             None => {
-                // treat as key, wait for next non-deleted for value
-                pend = Some(entry);
+                // treat as key, wait for the value in the next slot
+                pend = Some((slot, entry));
             }
-            Some(k) => {
-                // materialize key
-                let key = match k.item {
-                    LeafItem::KeyData(s) => s.to_vec(),
-                    LeafItem::Overflow {
-                        first_pg,
-                        total_len,
-                    } => read_overflow_chain(
-                        all,
-                        ps,
-                        e,
-                        OverflowRef {
-                            first_page: first_pg,
-                            total_len,
-                        },
-                    )?,
+            Some((key_slot, k)) => {
+                let pair = read_item(k.item).and_then(|key| Ok((key, read_item(entry.item)?)));
+                let (key, val) = match pair {
+                    Ok(pair) => pair,
+                    Err(err) => {
+                        skip(key_slot, err)?;
+                        continue;
+                    }
                 };
-                // materialize value
-                let val = match entry.item {
-                    LeafItem::KeyData(s) => s.to_vec(),
-                    LeafItem::Overflow {
-                        first_pg,
-                        total_len,
-                    } => read_overflow_chain(
-                        all,
-                        ps,
-                        e,
-                        OverflowRef {
-                            first_page: first_pg,
-                            total_len,
-                        },
-                    )?,
+                let provenance = Provenance {
+                    source_id: source_id.to_string(),
+                    page_no: hdr.pgno,
+                    slot_index: key_slot as u16,
+                    confidence,
                 };
-                out.push((key, val));
+                out.push((key, val, provenance));
             }
         }
     }
-    // If pend is Some(..) here, the page ended with an unpaired key (tombstoned value, etc.). Skip it.
-    Ok(out)
+    // A key left over means the page ended with an unpaired key (an odd slot count).
+    if let Some((key_slot, _)) = pend {
+        skip(
+            key_slot,
+            WalletDbError::UnpairedKey {
+                pgno: hdr.pgno,
+                slot: key_slot,
+            },
+        )?;
+    }
+    Ok(LeafPairs {
+        pairs: out,
+        report,
+        skipped,
+    })
 }
 
-/// Parse one BLEAF entry at `off` into key/data fields (either inline slices or BigRef).
-fn parse_bleaf_fields<'a>(page: &'a [u8], off: usize, e: Endian) -> Result<(Field<'a>, Field<'a>)> {
-    ensure!(off + 9 <= page.len(), "BLEAF header out of bounds");
-    let ksize = u32e(e, &page[off..off + 4]) as usize;
-    let dsize = u32e(e, &page[off + 4..off + 8]) as usize;
-    let flags = page[off + 8];
-    let mut p = off + 9;
+fn expect_leaf(hdr: &PageHeader) -> Result<()> {
+    match hdr.kind() {
+        PageType::BtreeLeaf => Ok(()),
+        other => Err(WalletDbError::WrongPageType {
+            pgno: hdr.pgno,
+            expected: "leaf",
+            found: other.code(),
+        }),
+    }
+}
 
-    // key
-    let key = if (flags & 0x01) == 0 {
-        ensure!(p + ksize <= page.len(), "inline key OOB");
-        let s = &page[p..p + ksize];
-        p += ksize;
-        Field::Inline(s)
-    } else {
-        ensure!(p + 8 <= page.len(), "big-key ref OOB");
-        let first_page = u32e(e, &page[p..p + 4]);
-        let total_len = u32e(e, &page[p + 4..p + 8]);
-        p += 8;
-        Field::Overflow(OverflowRef {
-            first_page,
-            total_len,
-        })
-    };
+/// Follow an overflow chain and materialize `total_len` bytes.
+/// Each overflow page’s payload is the `hf_offset` bytes after the page header and its
+/// `protection` block. Use header.next to chain; cycles, early ends, non-overflow pages
+/// and lengths longer than the image fail with an [`OverflowChainError`].
+pub fn read_overflow(
+    all: &[u8],
+    ps: usize,
+    e: Endian,
+    protection: PageProtection,
+    br: OverflowRef,
+) -> Result<Vec<u8>> {
+    let mut out = Vec::with_capacity(checked_overflow_len(all, ps, protection, br)?);
+    for_each_overflow_payload(all, ps, e, protection, br, |payload| {
+        out.extend_from_slice(payload);
+        Ok(())
+    })?;
+    Ok(out)
+}
 
-    // data
-    let data = if (flags & 0x02) == 0 {
-        ensure!(p + dsize <= page.len(), "inline data OOB");
-        let s = &page[p..p + dsize];
-        Field::Inline(s)
-    } else {
-        ensure!(p + 8 <= page.len(), "big-data ref OOB");
-        let first_page = u32e(e, &page[p..p + 4]);
-        let total_len = u32e(e, &page[p + 4..p + 8]);
-        Field::Overflow(OverflowRef {
-            first_page,
-            total_len,
-        })
-    };
+/// Like [`read_overflow`], but write the value to `sink` one page at a time, so a
+/// multi-megabyte value never sits in memory whole. On error, `sink` holds the part of
+/// the value read so far.
+#[cfg(feature = "std")]
+pub fn read_overflow_into(
+    all: &[u8],
+    ps: usize,
+    e: Endian,
+    protection: PageProtection,
+    br: OverflowRef,
+    sink: &mut impl std::io::Write,
+) -> Result<()> {
+    checked_overflow_len(all, ps, protection, br)?;
+    for_each_overflow_payload(all, ps, e, protection, br, |payload| {
+        Ok(sink.write_all(payload)?)
+    })
+}
 
-    Ok((key, data))
+/// `total_len` of `br`, unless it needs more pages than `all` holds.
+fn checked_overflow_len(
+    all: &[u8],
+    ps: usize,
+    protection: PageProtection,
+    br: OverflowRef,
+) -> Result<usize> {
+    let max_pages = all.len().div_ceil(ps.max(1)) as u64;
+    let per_page = ps.saturating_sub(protection.header_size()).max(1);
+    if (br.total_len as usize).div_ceil(per_page) as u64 > max_pages {
+        return Err(OverflowChainError::TooLong {
+            total_len: br.total_len,
+            max_pages,
+        }
+        .into());
+    }
+    Ok(br.total_len as usize)
 }
 
-/// Follow an overflow chain and materialize `total_len` bytes.
-/// Each overflow page’s payload is `page[BTDATAOFF..]`. Use header.next to chain.
-pub fn read_overflow(all: &[u8], ps: usize, e: Endian, br: OverflowRef) -> Result<Vec<u8>> {
-    let mut out = Vec::with_capacity(br.total_len as usize);
+/// Walk the chain of `br`, handing each page's share of the value to `f` in order.
+fn for_each_overflow_payload(
+    all: &[u8],
+    ps: usize,
+    e: Endian,
+    protection: PageProtection,
+    br: OverflowRef,
+    mut f: impl FnMut(&[u8]) -> Result<()>,
+) -> Result<()> {
+    let mut seen = BTreeSet::new();
     let mut pg = br.first_page;
     let mut rem = br.total_len as usize;
 
     while rem > 0 {
-        let page = page_slice(all, ps, pg);
+        if !seen.insert(pg) {
+            return Err(OverflowChainError::Cycle { pgno: pg }.into());
+        }
+        let page = page_slice(all, ps, pg)?;
         let hdr = parse_page_header(page, e)?;
-        ensure!(
-            matches!(hdr.ptype, PageType::Overflow),
-            "expected overflow page, got {:?}",
-            hdr.ptype
-        );
+        if hdr.kind() != PageType::Overflow {
+            return Err(OverflowChainError::WrongPageType {
+                pgno: pg,
+                page_type: hdr.page_type,
+            }
+            .into());
+        }
 
-        let payload = &page[BTDATAOFF..];
-        let take = rem.min(payload.len());
-        out.extend_from_slice(&payload[..take]);
+        let payload = &page[protection.header_size()..];
+        let take = rem.min(hdr.hf_offset as usize).min(payload.len());
+        f(&payload[..take])?;
         rem -= take;
 
         if rem == 0 {
             break;
         }
-        ensure!(
-            hdr.next != 0,
-            "overflow chain ended early (need {rem} more bytes)"
-        );
-        pg = hdr.next;
+        if hdr.next_pgno == 0 {
+            return Err(OverflowChainError::TooShort {
+                total_len: br.total_len,
+                missing: rem,
+            }
+            .into());
+        }
+        pg = hdr.next_pgno;
     }
-    Ok(out)
+    Ok(())
 }
 
-/// Read one BLEAF item fully into owned Vecs (follows overflow if needed).
+/// Read the leaf item at `off` fully into an owned Vec, following its overflow chain if
+/// it has one.
 pub fn read_leaf_item(
     all: &[u8],
     ps: usize,
     e: Endian,
+    protection: PageProtection,
     page: &[u8],
     off: usize,
-) -> Result<(Vec<u8>, Vec<u8>)> {
-    let (kf, df) = parse_bleaf_fields(page, off, e)?;
-    let key = match kf {
-        Field::Inline(s) => s.to_vec(),
-        Field::Overflow(r) => read_overflow(all, ps, e, r)?,
-    };
-    let val = match df {
-        Field::Inline(s) => s.to_vec(),
-        Field::Overflow(r) => read_overflow(all, ps, e, r)?,
-    };
-    Ok((key, val))
+) -> Result<Vec<u8>> {
+    let entry = parse_leaf_entry(page, off, e)?;
+    materialize(all, ps, e, protection, entry.item)
+}
+
+/// The bytes of `item`, read from its overflow chain if it has one.
+fn materialize(
+    all: &[u8],
+    ps: usize,
+    e: Endian,
+    protection: PageProtection,
+    item: LeafItem,
+) -> Result<Vec<u8>> {
+    match item {
+        LeafItem::KeyData(s) => Ok(s.to_vec()),
+        LeafItem::Overflow {
+            first_pg,
+            total_len,
+        } => read_overflow(
+            all,
+            ps,
+            e,
+            protection,
+            OverflowRef {
+                first_page: first_pg,
+                total_len,
+            },
+        ),
+    }
 }
 
 /// Convenience wrapper: extract pairs from a leaf page by page number.
 pub fn extract_leaf_pairs(
+    source_id: &str,
     all: &[u8],
     ps: usize,
     e: Endian,
+    protection: PageProtection,
     leaf_pgno: u32,
-) -> Result<Vec<(Vec<u8>, Vec<u8>)>> {
-    let page = page_slice(all, ps, leaf_pgno);
+) -> Result<Vec<KvRecord>> {
+    let page = page_slice(all, ps, leaf_pgno)?;
     let hdr = parse_page_header(page, e)?;
-    ensure!(matches!(hdr.ptype, PageType::Leaf), "not a leaf page");
-    leaf_pairs_on_page(all, ps, e, page, &hdr)
+    expect_leaf(&hdr)?;
+    leaf_pairs_on_page(source_id, all, ps, e, protection, page, &hdr)
 }
 
 pub fn read_compact_size(s: &[u8]) -> Option<(u64, usize)> {
@@ -279,10 +400,38 @@ pub fn read_compact_size(s: &[u8]) -> Option<(u64, usize)> {
     }
 }
 
+/// Serialize a Bitcoin-style compact size.
+pub fn write_compact_size(out: &mut Vec<u8>, n: u64) {
+    match n {
+        0..=0xfc => out.push(n as u8),
+        0xfd..=0xffff => {
+            out.push(0xfd);
+            out.extend_from_slice(&(n as u16).to_le_bytes());
+        }
+        0x1_0000..=0xffff_ffff => {
+            out.push(0xfe);
+            out.extend_from_slice(&(n as u32).to_le_bytes());
+        }
+        _ => {
+            out.push(0xff);
+            out.extend_from_slice(&n.to_le_bytes());
+        }
+    }
+}
+
+/// Raw key prefix shared by every walletdb record with the given tag (e.g. `"tx"`),
+/// i.e. the compact-size length followed by the tag bytes.
+pub fn walletdb_key_prefix(tag: &str) -> Vec<u8> {
+    let mut out = Vec::with_capacity(tag.len() + 1);
+    write_compact_size(&mut out, tag.len() as u64);
+    out.extend_from_slice(tag.as_bytes());
+    out
+}
+
 pub fn split_walletdb_key(key: &[u8]) -> Option<(&str, &[u8])> {
     let (len, n) = read_compact_size(key)?;
-    let len = len as usize;
-    if key.len() < n + len {
+    let len = usize::try_from(len).ok()?;
+    if key.len() - n < len {
         return None;
     }
     let tag_bytes = &key[n..n + len];
@@ -0,0 +1,204 @@
+//! Errors of the page-level parsing functions (`headers`, `util`, `leaf`, `entry::parser`),
+//! and the [`ParseMode`] that decides whether an anomaly is one.
+//!
+//! Every variant names the page and/or byte offset it is about, so callers can branch on
+//! the category and still report where the image is damaged. The `storage` layer works
+//! in `io::Result`; a [`WalletDbError`] converts into an `io::Error` that wraps it, so
+//! `get_ref()` and `downcast_ref` recover it there too.
+//!
+//! Nothing here needs `std`; the `Io` variant only exists with the `std` feature.
+
+use alloc::{format, string::String};
+use core::{error, fmt};
+#[cfg(feature = "std")]
+use std::io;
+
+use crate::{storage::slots::SlotViolation, util::PageSliceError};
+
+pub type Result<T, E = WalletDbError> = core::result::Result<T, E>;
+
+/// What a reader does when it meets a structural anomaly: a page that cannot be read or
+/// parsed, a damaged slot array, a broken overflow chain, a truncated image.
+///
+/// [`SalvageMode`](crate::storage::consistency::SalvageMode) chooses where records are
+/// looked for; `ParseMode` chooses whether an anomaly on the way ends the read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ParseMode {
+    /// Fail on the first anomaly. For validation pipelines, where a partial result is
+    /// worse than none.
+    Strict,
+    /// Skip what cannot be read and record each anomaly, e.g. as a
+    /// [`Finding`](crate::storage::consistency::Finding). For recovery.
+    #[default]
+    Lenient,
+}
+
+#[derive(Debug)]
+pub enum WalletDbError {
+    /// Reading the image failed.
+    #[cfg(feature = "std")]
+    Io(io::Error),
+    /// Page 0 is not a usable btree meta page; `offset` is the field that gave it away.
+    BadMeta { offset: usize, reason: String },
+    /// The buffer is too short to hold a page header.
+    BadPageHeader { len: usize },
+    /// The page requested from an image does not exist, or is cut short.
+    MissingPage(PageSliceError),
+    /// The page is not of the type the caller needs.
+    WrongPageType {
+        pgno: u32,
+        expected: &'static str,
+        found: u8,
+    },
+    /// The slot array of a leaf page is damaged.
+    BadSlot { pgno: u32, violation: SlotViolation },
+    /// The item at `offset` runs past the end of the page.
+    SlotOutOfBounds {
+        pgno: Option<u32>,
+        offset: usize,
+        end: usize,
+        page_len: usize,
+    },
+    /// The item type byte at `offset` is not one a leaf page holds.
+    UnknownLeafKind {
+        pgno: Option<u32>,
+        offset: usize,
+        kind: u8,
+    },
+    /// The last key on a leaf page has no value after it.
+    UnpairedKey { pgno: u32, slot: usize },
+    /// An overflow chain could not be followed to its end.
+    OverflowChainBroken(OverflowChainError),
+}
+
+/// Why an overflow chain could not be reassembled.
+///
+/// [`read_overflow_chain`](crate::storage::supplier::read_overflow_chain) reports these
+/// wrapped in an `io::Error` of kind `InvalidData`; use `get_ref()` and `downcast_ref` to
+/// tell them apart.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OverflowChainError {
+    /// `next_pgno` led back to a page already in the chain.
+    Cycle { pgno: u32 },
+    /// The chain ended (`next_pgno == 0`) with `missing` of `total_len` bytes still unread.
+    TooShort { total_len: u32, missing: usize },
+    /// `total_len` needs more pages than the image holds, so it cannot be genuine.
+    TooLong { total_len: u32, max_pages: u64 },
+    /// A page in the chain is not an overflow page.
+    WrongPageType { pgno: u32, page_type: u8 },
+}
+
+impl fmt::Display for OverflowChainError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OverflowChainError::Cycle { pgno } => {
+                write!(f, "overflow chain cycle: page {pgno} is linked twice")
+            }
+            OverflowChainError::TooShort { total_len, missing } => write!(
+                f,
+                "overflow chain too short: ended {missing} bytes short of {total_len}"
+            ),
+            OverflowChainError::TooLong {
+                total_len,
+                max_pages,
+            } => write!(
+                f,
+                "overflow length {total_len} needs more pages than the image holds ({max_pages})"
+            ),
+            OverflowChainError::WrongPageType { pgno, page_type } => write!(
+                f,
+                "overflow chain page {pgno} has page type {page_type}, expected 7"
+            ),
+        }
+    }
+}
+
+impl error::Error for OverflowChainError {}
+
+impl fmt::Display for WalletDbError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let page = |pgno: &Option<u32>| match pgno {
+            Some(pgno) => format!("page {pgno}: "),
+            None => String::new(),
+        };
+        match self {
+            #[cfg(feature = "std")]
+            WalletDbError::Io(e) => write!(f, "{e}"),
+            WalletDbError::BadMeta { offset, reason } => {
+                write!(f, "bad meta page at offset {offset}: {reason}")
+            }
+            WalletDbError::BadPageHeader { len } => {
+                write!(f, "short page: {len} bytes cannot hold a page header")
+            }
+            WalletDbError::MissingPage(e) => write!(f, "{e}"),
+            WalletDbError::WrongPageType {
+                pgno,
+                expected,
+                found,
+            } => write!(f, "page {pgno}: page type {found}, expected {expected}"),
+            WalletDbError::BadSlot { pgno, violation } => write!(f, "page {pgno}: {violation}"),
+            WalletDbError::SlotOutOfBounds {
+                pgno,
+                offset,
+                end,
+                page_len,
+            } => write!(
+                f,
+                "{}item at {offset} ends at {end}, past the page end {page_len}",
+                page(pgno)
+            ),
+            WalletDbError::UnknownLeafKind { pgno, offset, kind } => {
+                write!(
+                    f,
+                    "{}unknown leaf item kind {kind} at off={offset}",
+                    page(pgno)
+                )
+            }
+            WalletDbError::UnpairedKey { pgno, slot } => {
+                write!(f, "page {pgno} slot {slot}: key without a value")
+            }
+            WalletDbError::OverflowChainBroken(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+impl error::Error for WalletDbError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            #[cfg(feature = "std")]
+            WalletDbError::Io(e) => Some(e),
+            WalletDbError::MissingPage(e) => Some(e),
+            WalletDbError::OverflowChainBroken(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<io::Error> for WalletDbError {
+    fn from(e: io::Error) -> Self {
+        WalletDbError::Io(e)
+    }
+}
+
+impl From<PageSliceError> for WalletDbError {
+    fn from(e: PageSliceError) -> Self {
+        WalletDbError::MissingPage(e)
+    }
+}
+
+impl From<OverflowChainError> for WalletDbError {
+    fn from(e: OverflowChainError) -> Self {
+        WalletDbError::OverflowChainBroken(e)
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<WalletDbError> for io::Error {
+    fn from(e: WalletDbError) -> Self {
+        match e {
+            WalletDbError::Io(e) => e,
+            e => io::Error::new(io::ErrorKind::InvalidData, e),
+        }
+    }
+}
@@ -1,11 +1,18 @@
+use alloc::{format, string::ToString};
 use core::fmt;
 
 use crate::{
-    page::PageType,
-    util::{Endian, detect_endian, hex, u32e},
+    constants::DBMETA_CHKSUM,
+    error::{Result, WalletDbError},
+    storage::{
+        flags::{BtreeMetaFlags, MetaFlags},
+        page::PageType,
+    },
+    util::{Endian, detect_endian, hex, is_valid_page_size, u32e},
 };
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct BtreeMeta {
     pub endian: Endian,
     // Common 12-byte page header
@@ -18,8 +25,8 @@ pub struct BtreeMeta {
     pub version: u32,  // 16..=19
     pub pagesize: u32, // 20..=23
 
-    pub encrypt_alg: u8,  // 24
-    pub p_type: PageType, // 25 (9 = meta, 3 = internal, 5 = leaf)
+    pub encrypt_alg: u8,  // 24 (0 = none, 1 = AES)
+    pub p_type: PageType, // 25 (P_BTREEMETA)
     pub metaflags: u8,    // 26
     pub _unused1: u8,     // 27
 
@@ -29,7 +36,11 @@ pub struct BtreeMeta {
     pub key_count: u32,    // 40..=43  (cached stats; often 0)
     pub record_count: u32, // 44..=47  (cached stats; often 0)
     pub flags: u32,        // 48..=51  (btree meta flags)
-    pub uid: [u8; 20],     // 52..=71  (file ID)
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::util::serde_hex::bytes")
+    )]
+    pub uid: [u8; 20], // 52..=71  (file ID)
 
     pub _unused_after_uid: u32, // 72..=75
     pub minkey: u32,            // 76..=79  (DB->set_bt_minkey)
@@ -39,13 +50,34 @@ pub struct BtreeMeta {
 
     // Tail (encryption-era fields; present even if unused)
     pub crypto_magic: u32, // 460..=463
-    pub iv: [u8; 16],      // 476..=491
-    pub chksum: [u8; 20],  // 496..=515
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::util::serde_hex::bytes")
+    )]
+    pub iv: [u8; 16], // 476..=491
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::util::serde_hex::bytes")
+    )]
+    pub chksum: [u8; 20], // 492..=511
+}
+
+impl BtreeMeta {
+    /// Whether every page carries a checksum (database created with DB_CHKSUM).
+    pub fn has_checksum(&self) -> bool {
+        self.metaflags & DBMETA_CHKSUM != 0
+    }
+
+    /// Whether pages are encrypted (database created with DB_ENCRYPT).
+    /// Encrypted meta pages must be decrypted before `root`/`minkey` are meaningful.
+    pub fn is_encrypted(&self) -> bool {
+        self.encrypt_alg != 0
+    }
 }
 
 impl fmt::Display for BtreeMeta {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "BtreeMeta {{\n")?;
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "BtreeMeta {{")?;
         writeln!(f, "  endianness   : {:?}", self.endian)?;
         writeln!(f, "  pagesize     : {}", self.pagesize)?;
         writeln!(f, "  page0.pgno   : {}", self.pgno)?;
@@ -56,13 +88,23 @@ impl fmt::Display for BtreeMeta {
         )?;
         writeln!(f, "  magic        : 0x{:08x}", self.magic)?;
         writeln!(f, "  version      : {}", self.version)?;
-        writeln!(f, "  type         : {}", self.p_type)?; // 9=meta, 3=internal, 5=leaf
-        writeln!(f, "  metaflags    : 0x{:x}", self.metaflags)?;
+        writeln!(f, "  type         : {}", self.p_type)?;
+        writeln!(
+            f,
+            "  metaflags    : 0x{:x} ({})",
+            self.metaflags,
+            MetaFlags(self.metaflags)
+        )?;
         writeln!(f, "  free         : {}", self.free)?;
         writeln!(f, "  last_pgno    : {}", self.last_pgno)?;
         writeln!(f, "  key_count    : {}", self.key_count)?;
         writeln!(f, "  record_count : {}", self.record_count)?;
-        writeln!(f, "  flags        : 0x{:08x}", self.flags)?;
+        writeln!(
+            f,
+            "  flags        : 0x{:08x} ({})",
+            self.flags,
+            BtreeMetaFlags(self.flags)
+        )?;
         writeln!(f, "  uid          : {}", hex(&self.uid))?;
         writeln!(f, "  minkey       : {}", self.minkey)?;
         writeln!(f, "  re_len       : {}", self.re_len)?;
@@ -78,18 +120,26 @@ impl fmt::Display for BtreeMeta {
     }
 }
 
-pub fn parse_btree_meta_page0(page: &[u8]) -> anyhow::Result<BtreeMeta> {
-    use anyhow::{Context, bail};
+pub fn parse_btree_meta_page0(page: &[u8]) -> Result<BtreeMeta> {
     if page.len() < 512 {
-        bail!("page buffer too small (<512)");
+        return Err(WalletDbError::BadMeta {
+            offset: page.len(),
+            reason: "page buffer too small (<512)".to_string(),
+        });
     }
 
-    let endian = detect_endian(page).context("not a Btree meta page: magic not found at 12..16")?;
+    let endian = detect_endian(page).ok_or_else(|| WalletDbError::BadMeta {
+        offset: 12,
+        reason: "not a Btree meta page: magic not found at 12..16".to_string(),
+    })?;
     let pagesize = u32e(endian, &page[20..24]);
 
     // Basic sanity
-    if pagesize == 0 || pagesize as usize % 512 != 0 {
-        bail!("implausible pagesize {pagesize}");
+    if !is_valid_page_size(pagesize as usize) {
+        return Err(WalletDbError::BadMeta {
+            offset: 20,
+            reason: format!("implausible pagesize {pagesize}"),
+        });
     }
 
     // Common header
@@ -133,9 +183,7 @@ pub fn parse_btree_meta_page0(page: &[u8]) -> anyhow::Result<BtreeMeta> {
         iv.copy_from_slice(&page[476..492]);
     }
     let mut chksum = [0u8; 20];
-    if page.len() >= 516 {
-        chksum.copy_from_slice(&page[496..516]);
-    }
+    chksum.copy_from_slice(&page[492..512]);
 
     Ok(BtreeMeta {
         endian,
@@ -1,4 +1,9 @@
-use crate::util::{Endian, u16e, u32e};
+use crate::{
+    constants::{B_BLOB, B_DELETE, B_DUPLICATE, B_KEYDATA, B_OVERFLOW, BBLOB_SIZE, BOVERFLOW_SIZE},
+    error::{Result, WalletDbError},
+    storage::page::{PageProtection, PageType},
+    util::{Endian, parse_page_header, u16e, u32e},
+};
 
 /// Leaf entry kinds in BDB 4.x/5.x.
 /// 1 = inline bytes; 3 = overflow reference; high bit is "deleted".
@@ -16,38 +21,54 @@ pub struct ParsedLeafEntry<'a> {
     pub(crate) item: LeafItem<'a>,
 }
 
+impl<'a> ParsedLeafEntry<'a> {
+    /// Whether the item carries `B_DELETE`.
+    pub fn is_deleted(&self) -> bool {
+        self.deleted
+    }
+
+    pub fn item(&self) -> &LeafItem<'a> {
+        &self.item
+    }
+}
+
 /// Parse a single **leaf** item at absolute `off`.
 /// Layout:
 ///   - Inline:   len:u16, kind:u8(=1 or 0x81 if deleted), data[len]
 ///   - Overflow: pad:u16, kind:u8(=3 or 0x83 if deleted), pad:u8,
-///               first_pg:u32, total_len:u32
-pub fn parse_leaf_entry<'a>(
-    page: &'a [u8],
-    off: usize,
-    e: Endian,
-) -> anyhow::Result<ParsedLeafEntry<'a>> {
-    use anyhow::{bail, ensure};
-    ensure!(off + 3 <= page.len(), "leaf entry header OOB");
-    let len = u16e(e, &page[off..off + 2]) as usize;
-    let kind_raw = page[off + 2];
+///     first_pg:u32, total_len:u32
+///
+/// Errors carry the page number from the page header when `page` is long enough to hold one.
+pub fn parse_leaf_entry<'a>(page: &'a [u8], off: usize, e: Endian) -> Result<ParsedLeafEntry<'a>> {
+    let pgno = page.get(8..12).map(|b| u32e(e, b));
+    // `len` bytes from `start`, or the error naming the item at `off`. Offsets come from
+    // the page itself, so the end is computed without overflowing.
+    let bytes = |start: usize, len: usize| {
+        start
+            .checked_add(len)
+            .and_then(|end| page.get(start..end))
+            .ok_or(WalletDbError::SlotOutOfBounds {
+                pgno,
+                offset: off,
+                end: start.saturating_add(len),
+                page_len: page.len(),
+            })
+    };
+    let header = bytes(off, 3)?;
+    let len = u16e(e, &header[0..2]) as usize;
+    let kind_raw = header[2];
     let deleted = (kind_raw & 0x80) != 0;
     let kind = kind_raw & 0x7F;
 
     match kind {
-        1 => {
-            let start = off + 3;
-            let end = start + len;
-            ensure!(end <= page.len(), "KeyData OOB");
-            Ok(ParsedLeafEntry {
-                deleted,
-                item: LeafItem::KeyData(&page[start..end]),
-            })
-        }
+        1 => Ok(ParsedLeafEntry {
+            deleted,
+            item: LeafItem::KeyData(bytes(off + 3, len)?),
+        }),
         3 => {
-            let start = off + 4; // skip pad
-            ensure!(start + 8 <= page.len(), "Overflow OOB");
-            let first_pg = u32e(e, &page[start..start + 4]);
-            let total_len = u32e(e, &page[start + 4..start + 8]);
+            let item = bytes(off + 4, 8)?; // skip pad
+            let first_pg = u32e(e, &item[0..4]);
+            let total_len = u32e(e, &item[4..8]);
             Ok(ParsedLeafEntry {
                 deleted,
                 item: LeafItem::Overflow {
@@ -56,6 +77,103 @@ pub fn parse_leaf_entry<'a>(
                 },
             })
         }
-        k => bail!("unknown leaf item kind {k} at off={off}"),
+        kind => Err(WalletDbError::UnknownLeafKind {
+            pgno,
+            offset: off,
+            kind,
+        }),
+    }
+}
+
+/// Item type of a leaf slot, with `B_DELETE` masked off.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LeafItemKind {
+    /// `B_KEYDATA`: bytes stored inline.
+    KeyData,
+    /// `B_DUPLICATE`: reference to an off-page duplicate tree.
+    Duplicate,
+    /// `B_OVERFLOW`: reference to an overflow chain.
+    Overflow,
+    /// `B_BLOB`: reference to an external blob file.
+    Blob,
+    Unknown(u8),
+}
+
+impl From<u8> for LeafItemKind {
+    fn from(t: u8) -> Self {
+        match t & !B_DELETE {
+            B_KEYDATA => LeafItemKind::KeyData,
+            B_DUPLICATE => LeafItemKind::Duplicate,
+            B_OVERFLOW => LeafItemKind::Overflow,
+            B_BLOB => LeafItemKind::Blob,
+            other => LeafItemKind::Unknown(other),
+        }
+    }
+}
+
+/// One slot of a leaf page as stored, see [`leaf_slots`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LeafSlot {
+    /// Position in the slot array.
+    pub index: usize,
+    /// Offset of the item, as stored in the slot.
+    pub offset: usize,
+    /// `None` when the offset points past the page, so not even the type byte is there.
+    pub kind: Option<LeafItemKind>,
+    pub deleted: bool,
+    /// Size of the item on the page, header included. `None` for unknown item types and
+    /// for items whose size cannot be read.
+    pub len: Option<usize>,
+}
+
+impl LeafSlot {
+    /// The bytes of the item, header included, if they lie within `page`.
+    pub fn bytes<'a>(&self, page: &'a [u8]) -> Option<&'a [u8]> {
+        page.get(self.offset..self.offset.checked_add(self.len?)?)
+    }
+}
+
+/// Every slot of a **leaf** page in slot order, live and deleted alike, without pairing
+/// keys with values or checking the items. For forensics: [`leaf_pairs_on_page`] only
+/// returns live, paired entries.
+///
+/// The slot count comes from the page header; slots that would lie past the end of the
+/// page are not returned. The slot array follows the page header and its `protection`
+/// block.
+///
+/// [`leaf_pairs_on_page`]: crate::entry::parser::leaf_pairs_on_page
+pub fn leaf_slots(
+    page: &[u8],
+    e: Endian,
+    protection: PageProtection,
+) -> Result<impl Iterator<Item = LeafSlot> + '_> {
+    let hdr = parse_page_header(page, e)?;
+    if hdr.kind() != PageType::BtreeLeaf {
+        return Err(WalletDbError::WrongPageType {
+            pgno: hdr.pgno,
+            expected: "leaf",
+            found: hdr.page_type,
+        });
     }
+    Ok((0..hdr.entries as usize).map_while(move |index| {
+        let at = protection.header_size() + index * 2;
+        let offset = u16e(e, page.get(at..at + 2)?) as usize;
+        let raw = page.get(offset + 2).copied();
+        let kind = raw.map(LeafItemKind::from);
+        let len = match kind {
+            Some(LeafItemKind::KeyData) => page
+                .get(offset..offset + 2)
+                .map(|b| 3 + u16e(e, b) as usize),
+            Some(LeafItemKind::Duplicate | LeafItemKind::Overflow) => Some(BOVERFLOW_SIZE),
+            Some(LeafItemKind::Blob) => Some(BBLOB_SIZE),
+            Some(LeafItemKind::Unknown(_)) | None => None,
+        };
+        Some(LeafSlot {
+            index,
+            offset,
+            kind,
+            deleted: raw.is_some_and(|t| t & B_DELETE != 0),
+            len,
+        })
+    }))
 }
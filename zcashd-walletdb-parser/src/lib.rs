@@ -1,8 +1,15 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
 pub mod constants;
+#[cfg(feature = "std")]
+pub mod crypto;
 pub mod entry;
+pub mod error;
 pub mod headers;
 pub mod leaf;
-pub mod page;
+#[cfg(feature = "std")]
 pub mod parser;
 pub mod storage;
 pub mod util;
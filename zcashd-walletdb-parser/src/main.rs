@@ -1,59 +1,330 @@
-use std::{env, fs, path::PathBuf, process};
+use std::{
+    env,
+    fs::{self, File, OpenOptions},
+    io::{BufWriter, Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+    process,
+    sync::Arc,
+    time::{SystemTime, UNIX_EPOCH},
+};
 
 use anyhow::Result;
 use zcashd_walletdb_parser::{
-    entry::parser::leaf_pairs_on_page,
+    entry::parser::split_walletdb_key,
     headers::parse_btree_meta_page0,
-    page::PageType,
-    util::{page_slice, parse_page_header},
+    leaf::leaf_slots,
+    parser::{
+        balance::Balances,
+        crypter::decrypt_wallet,
+        decoders::network::ZcashNetwork,
+        export::{dump::write_wallet_dump, sapling_keys, transparent_keys},
+        lineage::detect_lineage,
+        lint::LintReport,
+        merkle::check_witnesses,
+        mnemonic::check_seeds,
+        profile::WalletProfile,
+        record::DecodeContext,
+        registry::default_registry,
+        render::{Render, Style},
+        serialize::uint256_hex,
+        summary::WalletSummary,
+        verify::KeyVerification,
+        wallet::ZcashdWallet,
+    },
+    storage::{
+        blob::BlobDirectory,
+        carve::carve,
+        consistency::{DbImageReader, ParseMode, SalvageMode, check},
+        diff::diff_pages,
+        encryption::{DbCipher, decrypt_image},
+        entry::ConflictPolicy,
+        environment::{EnvironmentFile, scan_environment},
+        freelist::walk_freelist,
+        lsn::{analyze_lsns, parse_lsn},
+        meta_recovery::{meta_problem, rebuild_meta},
+        orphans::analyze_reachability,
+        page::{Page, PageProtection},
+        reader::FileDbImageReader,
+        repack::repack,
+        salvage::salvage_image,
+        source::{FilePageSource, MemoryPageSource},
+        stats::collect_stats,
+        types::PageSource,
+    },
 };
 
+const USAGE: &str = "[--passphrase <pw>] [--wallet-passphrase <pw>] [--offset <bytes>] [--blob-dir <dir>] [--salvage] [--carve] [--check] [--repack <out.dat>] [--freelist] [--stats] [--lineage] [--summary] [--profile] [--lint] [--verify-keys] [--records] [--keys] [--export-wif] [--export-sapling] [--export-dump <out.txt>] [--export-sqlite <out.db>] [--balance] [--lsn] [--checkpoint <file/offset>] [--diff <backup.dat>] [--dump-page <pgno>] [--slots <pgno>] [--orphans] [--best-effort] [--strict] [--deleted] <wallet.dat | ->";
+
 fn main() -> Result<()> {
     let mut args = env::args_os();
     let prog = args.next().unwrap_or_default(); // program name
+    let usage = |msg: &str| -> ! {
+        eprintln!("{msg}usage: {} {USAGE}", prog.to_string_lossy());
+        process::exit(2);
+    };
 
-    // Expect exactly one positional argument: the wallet.dat path (or "-" for stdin)
-    let path: PathBuf = match args.next() {
-        Some(p) => p.into(),
-        None => {
-            eprintln!("usage: {} <wallet.dat | ->", prog.to_string_lossy());
-            process::exit(2);
+    // One positional argument: the wallet.dat path (or "-" for stdin), plus options.
+    let mut path: Option<PathBuf> = None;
+    let mut passphrase: Option<String> = None;
+    let mut wallet_passphrase: Option<String> = None;
+    let mut offset = 0u64;
+    let mut show_freelist = false;
+    let mut show_stats = false;
+    let mut show_lineage = false;
+    let mut show_summary = false;
+    let mut show_profile = false;
+    let mut show_records = false;
+    let mut run_lint = false;
+    let mut verify_keys = false;
+    let mut show_keys = false;
+    let mut export_wif = false;
+    let mut export_sapling = false;
+    let mut show_balance = false;
+    let mut show_lsns = false;
+    let mut checkpoint = None;
+    let mut show_orphans = false;
+    let mut run_check = false;
+    let mut repack_to: Option<PathBuf> = None;
+    let mut dump_to: Option<PathBuf> = None;
+    let mut sqlite_to: Option<PathBuf> = None;
+    let mut blob_dir: Option<PathBuf> = None;
+    let mut dump_page: Option<u32> = None;
+    let mut show_slots: Option<u32> = None;
+    let mut diff_with: Option<PathBuf> = None;
+    let mut salvage = SalvageMode::Conservative;
+    let mut mode = ParseMode::Lenient;
+    let mut show_deleted = false;
+    let mut force_salvage = false;
+    let mut carve_blob = false;
+    while let Some(arg) = args.next() {
+        match arg.to_str() {
+            Some("--passphrase") => match args.next().and_then(|p| p.into_string().ok()) {
+                Some(p) => passphrase = Some(p),
+                None => usage("error: --passphrase needs a value\n"),
+            },
+            Some("--wallet-passphrase") => match args.next().and_then(|p| p.into_string().ok()) {
+                Some(p) => wallet_passphrase = Some(p),
+                None => usage("error: --wallet-passphrase needs a value\n"),
+            },
+            Some("--offset") => match args.next().and_then(|o| o.to_str()?.parse().ok()) {
+                Some(o) => offset = o,
+                None => usage("error: --offset needs a byte count\n"),
+            },
+            Some("--blob-dir") => match args.next() {
+                Some(dir) => blob_dir = Some(dir.into()),
+                None => usage("error: --blob-dir needs a directory\n"),
+            },
+            Some("--freelist") => show_freelist = true,
+            Some("--stats") => show_stats = true,
+            Some("--lineage") => show_lineage = true,
+            Some("--summary") => show_summary = true,
+            Some("--profile") => show_profile = true,
+            Some("--records") => show_records = true,
+            Some("--lint") => run_lint = true,
+            Some("--verify-keys") => verify_keys = true,
+            Some("--keys") => show_keys = true,
+            Some("--export-wif") => export_wif = true,
+            Some("--export-sapling") => export_sapling = true,
+            Some("--balance") => show_balance = true,
+            Some("--lsn") => show_lsns = true,
+            Some("--checkpoint") => match args.next().and_then(|c| parse_lsn(c.to_str()?)) {
+                Some(c) => checkpoint = Some(c),
+                None => usage("error: --checkpoint needs an LSN such as 1/28\n"),
+            },
+            Some("--diff") => match args.next() {
+                Some(other) => diff_with = Some(other.into()),
+                None => usage("error: --diff needs a second wallet\n"),
+            },
+            Some("--dump-page") => match args.next().and_then(|p| p.to_str()?.parse().ok()) {
+                Some(p) => dump_page = Some(p),
+                None => usage("error: --dump-page needs a page number\n"),
+            },
+            Some("--slots") => match args.next().and_then(|p| p.to_str()?.parse().ok()) {
+                Some(p) => show_slots = Some(p),
+                None => usage("error: --slots needs a page number\n"),
+            },
+            Some("--orphans") => show_orphans = true,
+            Some("--check") => run_check = true,
+            Some("--export-dump") => match args.next() {
+                Some(out) => dump_to = Some(out.into()),
+                None => usage("error: --export-dump needs an output path\n"),
+            },
+            Some("--export-sqlite") => match args.next() {
+                Some(_) if !cfg!(feature = "sqlite") => {
+                    usage("error: --export-sqlite needs the sqlite feature\n")
+                }
+                Some(out) => sqlite_to = Some(out.into()),
+                None => usage("error: --export-sqlite needs an output path\n"),
+            },
+            Some("--repack") => match args.next() {
+                Some(out) => repack_to = Some(out.into()),
+                None => usage("error: --repack needs an output path\n"),
+            },
+            Some("--salvage") => force_salvage = true,
+            Some("--carve") => carve_blob = true,
+            Some("--best-effort") => salvage = SalvageMode::BestEffort,
+            Some("--strict") => mode = ParseMode::Strict,
+            Some("--deleted") => show_deleted = true,
+            _ if path.is_none() => path = Some(arg.into()),
+            // Optional: reject extra args
+            _ => usage("error: too many arguments\n"),
         }
-    };
+    }
+    let Some(mut path) = path else { usage("") };
 
-    // Optional: reject extra args
-    if args.next().is_some() {
-        eprintln!(
-            "error: too many arguments\nusage: {} <wallet.dat | ->",
-            prog.to_string_lossy()
-        );
-        process::exit(2);
+    // Each of these runs on its own and exits. Without one, `--wallet-passphrase` decrypts
+    // and prints the wallet's secrets, and otherwise the records are listed.
+    let modes = [
+        ("--carve", carve_blob),
+        ("--salvage", force_salvage),
+        ("--diff", diff_with.is_some()),
+        ("--dump-page", dump_page.is_some()),
+        ("--slots", show_slots.is_some()),
+        ("--stats", show_stats),
+        ("--lineage", show_lineage),
+        ("--summary", show_summary),
+        ("--profile", show_profile),
+        ("--lint", run_lint),
+        ("--verify-keys", verify_keys),
+        ("--records", show_records),
+        ("--keys", show_keys),
+        ("--export-wif", export_wif),
+        ("--export-sapling", export_sapling),
+        ("--export-dump", dump_to.is_some()),
+        ("--export-sqlite", sqlite_to.is_some()),
+        ("--balance", show_balance),
+        ("--lsn", show_lsns),
+        ("--check", run_check),
+        ("--repack", repack_to.is_some()),
+    ];
+    let chosen: Vec<&str> = modes
+        .iter()
+        .filter(|(_, on)| *on)
+        .map(|(f, _)| *f)
+        .collect();
+    if let [first, second, ..] = chosen[..] {
+        usage(&format!("error: {first} cannot be used with {second}\n"));
+    }
+    // These print ahead of the mode. `--carve` and `--salvage` do without the meta page
+    // the free list hangs off, and the modes that read single pages without the tree
+    // the other two walk.
+    let raw = ["--carve", "--salvage"];
+    let paged = ["--carve", "--salvage", "--diff", "--dump-page", "--slots"];
+    let extras = [
+        ("--freelist", show_freelist, &raw[..]),
+        ("--orphans", show_orphans, &paged[..]),
+        ("--deleted", show_deleted, &paged[..]),
+    ];
+    for (flag, on, skipped_by) in extras {
+        if let Some(first) = chosen
+            .first()
+            .filter(|mode| on && skipped_by.contains(mode))
+        {
+            usage(&format!("error: {flag} cannot be used with {first}\n"));
+        }
     }
 
-    let bytes = fs::read(path)?;
+    // A zcashd datadir holds the wallet next to the environment's region and log files.
+    if path.is_dir() {
+        let env = scan_environment(&path)?;
+        println!("{env}");
+        for note in env.notes() {
+            eprintln!("warning: {note}");
+        }
+        path = match env.wallet() {
+            Some(wallet) => wallet.to_path_buf(),
+            None if env.databases.is_empty() => {
+                anyhow::bail!("no database file in {}", path.display())
+            }
+            None => anyhow::bail!(
+                "{} holds several databases; name the one to read",
+                path.display()
+            ),
+        };
+    } else if let Some(kind) = EnvironmentFile::classify(&path) {
+        anyhow::bail!("{} is {}", path.display(), kind.explain());
+    }
+
+    // A wallet embedded in a container or disk image starts `offset` bytes in.
+    let source_id = match offset {
+        0 => path.display().to_string(),
+        _ => format!("{}@{offset}", path.display()),
+    };
+    let mut file = File::open(&path)?;
+    if offset > file.metadata()?.len() {
+        anyhow::bail!("--offset {offset} is past the end of {}", path.display());
+    }
+    file.seek(SeekFrom::Start(offset))?;
+    let mut bytes = Vec::new();
+    file.read_to_end(&mut bytes)?;
+
+    if carve_blob {
+        return carve_raw(bytes.into(), source_id);
+    }
 
     // Grab page 0 using the largest plausible default (we’ll trim by pagesize after parsing)
     if bytes.len() < 512 {
         anyhow::bail!("file < 512 bytes");
     }
-    // Parse directly from start of file (page 0):
-    let meta = parse_btree_meta_page0(&bytes[..std::cmp::min(bytes.len(), 4096)])?;
+    if force_salvage {
+        return salvage_raw(bytes, source_id);
+    }
+    // Parse directly from start of file (page 0). A damaged meta page is rebuilt from the
+    // other pages; failing that, salvage whatever the page headers still describe.
+    let parsed = parse_btree_meta_page0(&bytes[..std::cmp::min(bytes.len(), 4096)])
+        .map_err(|e| e.to_string())
+        .and_then(|meta| match meta_problem(&meta, &bytes) {
+            Some(problem) => Err(problem),
+            None => Ok(meta),
+        });
+    let mut meta = match parsed {
+        Ok(meta) => meta,
+        Err(problem) if mode == ParseMode::Strict => anyhow::bail!("{problem}"),
+        Err(problem) => match rebuild_meta(&bytes) {
+            Some(rebuilt) => {
+                eprintln!("warning: {problem}; rebuilt the meta page");
+                println!("{rebuilt}");
+                rebuilt.meta
+            }
+            None => {
+                eprintln!("warning: {problem}; salvaging from page headers");
+                return salvage_raw(bytes, source_id);
+            }
+        },
+    };
+    if meta.is_encrypted() {
+        let Some(pw) = passphrase else {
+            anyhow::bail!("database is encrypted (DB_ENCRYPT); pass --passphrase");
+        };
+        decrypt_image(&mut bytes, &DbCipher::from_passphrase(pw.as_bytes()))?;
+        meta = parse_btree_meta_page0(&bytes[..std::cmp::min(bytes.len(), 4096)])?;
+    }
     println!("{}", meta);
 
     let ps = meta.pagesize as usize;
-    let endian = meta.endian;
 
-    // Basic sanity
+    // Basic sanity. A damaged image is reported but still read as far as possible, unless
+    // --strict asks to stop at the first anomaly.
     let npages = bytes.len() / ps;
-    assert_eq!(meta.pgno, 0, "page 0 should be pgno=0");
-    assert!(
-        npages >= (meta.last_pgno as usize + 1),
-        "file shorter than last_pgno"
-    );
-    assert!(
-        meta.root != 0 && meta.root <= meta.last_pgno,
-        "root out of range"
-    );
+    let mut problems = Vec::new();
+    if meta.pgno != 0 {
+        problems.push(format!("meta page claims pgno={}", meta.pgno));
+    }
+    if bytes.len() % ps != 0 {
+        problems.push(format!(
+            "file ends {} bytes into page {npages}",
+            bytes.len() % ps
+        ));
+    }
+    if meta.root == 0 || meta.root > meta.last_pgno {
+        problems.push(format!("root {} out of range", meta.root));
+    }
+    for problem in &problems {
+        if mode == ParseMode::Strict {
+            anyhow::bail!("{problem}");
+        }
+        eprintln!("warning: {problem}");
+    }
 
     // // Walk headers for all pages (skip meta 0)
     // for pg in 1..=meta.last_pgno {
@@ -73,23 +344,442 @@ fn main() -> Result<()> {
     //     );
     // }
 
+    let source = Arc::new(MemoryPageSource::new(bytes, meta.pagesize, source_id)?);
+    if show_freelist {
+        println!("{}", walk_freelist(source.as_ref())?);
+    }
+    if let Some(other) = diff_with {
+        // The other file is taken as the older snapshot, e.g. a backup.
+        let backup = FilePageSource::open(&other)?;
+        let diff = diff_pages(&backup, source.as_ref())?;
+        println!("{diff}");
+        if diff.is_identical() {
+            println!("{} is identical", other.display());
+        } else if diff.b_is_newer() {
+            println!("{} is an older copy", other.display());
+        }
+        return Ok(());
+    }
+    if let Some(pgno) = dump_page {
+        let e = meta.endian.into();
+        let protection = PageProtection::from_meta(meta.encrypt_alg, meta.has_checksum());
+        let page = Page::parse(source.read_page(pgno)?, e, protection)?;
+        print!("{}", page.annotated_dump(e, protection));
+        return Ok(());
+    }
+    if let Some(pgno) = show_slots {
+        // Every slot as stored, deleted ones included, without pairing keys and values.
+        let protection = PageProtection::from_meta(meta.encrypt_alg, meta.has_checksum());
+        let page = source.read_page(pgno)?;
+        for slot in leaf_slots(&page, meta.endian, protection)? {
+            let bytes = slot.bytes(&page).unwrap_or_default();
+            println!(
+                "slot {}: offset={} kind={} deleted={} len={} bytes={}",
+                slot.index,
+                slot.offset,
+                slot.kind.map_or("?".to_string(), |k| format!("{k:?}")),
+                slot.deleted,
+                slot.len.map_or("?".to_string(), |n| n.to_string()),
+                hex::encode(bytes)
+            );
+        }
+        return Ok(());
+    }
+
+    let reader = FileDbImageReader::with_meta(source, meta)?.with_parse_mode(mode);
+    // Values of Berkeley DB 6.x databases may live in blob files beside the wallet.
+    let reader = match blob_dir
+        .map(BlobDirectory::new)
+        .or_else(|| BlobDirectory::beside(&path))
+    {
+        Some(dir) => reader.with_blob_resolver(Arc::new(dir)),
+        None => reader,
+    };
+    println!("{}", reader.probe()?);
+    if show_orphans {
+        println!("{}", analyze_reachability(&reader)?);
+        for (key, value, prov) in reader.orphan_entries()? {
+            println!(
+                "orphan: page {} slot {}: key={} value={} confidence={:?}",
+                prov.page_no,
+                prov.slot_index,
+                hex::encode(&key),
+                hex::encode(value.materialize()?),
+                prov.confidence
+            );
+        }
+    }
+    if show_deleted {
+        let mut n = 0usize;
+        for (key, value, prov) in reader.deleted_entries() {
+            let tag = split_walletdb_key(&key).map_or("?", |(tag, _)| tag);
+            println!(
+                "deleted: page {} slot {}: tag={tag} key={} value={} confidence={:?}",
+                prov.page_no,
+                prov.slot_index,
+                hex::encode(&key),
+                hex::encode(value.materialize()?),
+                prov.confidence
+            );
+            n += 1;
+        }
+        println!("total deleted pairs = {n}");
+    }
+    if show_stats {
+        println!("{}", collect_stats(&reader, 10)?);
+        return Ok(());
+    }
+    if show_lineage {
+        println!("{}", detect_lineage(read_records(&reader, salvage)));
+        return Ok(());
+    }
+    if show_summary {
+        let wallet = decode_wallet(&reader, salvage);
+        println!("{}", WalletSummary::of(&wallet));
+        return Ok(());
+    }
+    if show_profile {
+        let wallet = decode_wallet(&reader, salvage);
+        println!("{}", WalletProfile::of(&wallet));
+        return Ok(());
+    }
+    if run_lint {
+        let report = LintReport::of(&decode_wallet(&reader, salvage));
+        println!("{report}");
+        if !report.is_clean() {
+            process::exit(1);
+        }
+        return Ok(());
+    }
+    if verify_keys {
+        // Encrypted keys are only checked when the passphrase unlocks them.
+        let wallet = decode_wallet(&reader, salvage);
+        let secrets = match &wallet_passphrase {
+            Some(pw) => Some(decrypt_wallet(&wallet, pw.as_bytes())?),
+            None => None,
+        };
+        let report = KeyVerification::of(&wallet, secrets.as_ref());
+        println!("{report}");
+        for failure in secrets.iter().flat_map(|secrets| &secrets.failures) {
+            eprintln!("warning: {failure}");
+        }
+        if !report.is_clean() {
+            process::exit(1);
+        }
+        return Ok(());
+    }
+    if show_records {
+        // Every record as decoded, then the wallet they add up to.
+        let style = Style::detect();
+        let records: Vec<_> = read_records(&reader, salvage).collect();
+        let ctx = DecodeContext::scan(records.iter().map(|(key, value)| (key, value)));
+        let registry = default_registry();
+        for (key, value) in &records {
+            let record = registry.decode_record(&ctx, key, value);
+            println!("{}", record.render().paint(style));
+        }
+        let wallet = ZcashdWallet::from_records(&registry, records);
+        println!("{}", wallet.render().paint(style));
+        for warning in &wallet.warnings {
+            eprintln!("warning: {warning}");
+        }
+        return Ok(());
+    }
+    if show_keys {
+        let wallet = decode_wallet(&reader, salvage);
+        let network = wallet.network().unwrap_or_else(|| {
+            eprintln!("warning: no Zcash networkinfo record; encoding addresses for mainnet");
+            ZcashNetwork::Main
+        });
+        for (address, tag) in wallet.keys.transparent_addresses() {
+            println!("{} ({tag})", address.encode(network));
+        }
+        for ufvk in wallet.keys.unified_fvks.values() {
+            match ufvk.parse() {
+                Ok(parsed) => println!("{} (unifiedfvk)", parsed.encode()),
+                Err(e) => eprintln!("warning: ufvk {}: {e}", ufvk.ufvk_id_hex()),
+            }
+        }
+        for issue in check_seeds(&wallet, None) {
+            eprintln!("warning: {issue}");
+        }
+        return Ok(());
+    }
+    if export_wif {
+        // Secrets go to stdout; `ckey` secrets only with the wallet passphrase.
+        let wallet = decode_wallet(&reader, salvage);
+        let network = wallet.network().unwrap_or_else(|| {
+            eprintln!("warning: no Zcash networkinfo record; encoding keys for mainnet");
+            ZcashNetwork::Main
+        });
+        let secrets = match &wallet_passphrase {
+            Some(pw) => Some(decrypt_wallet(&wallet, pw.as_bytes())?),
+            None => None,
+        };
+        let exported = transparent_keys(&wallet, secrets.as_ref(), network);
+        for key in &exported {
+            println!("{} {}", key.wif.expose(), key.address.encode(network));
+        }
+        for failure in secrets.iter().flat_map(|secrets| &secrets.failures) {
+            eprintln!("warning: {failure}");
+        }
+        let locked = wallet.keys.encrypted_transparent.len();
+        if secrets.is_none() && locked > 0 {
+            eprintln!("warning: {locked} ckey records not exported; pass --wallet-passphrase");
+        }
+        return Ok(());
+    }
+    if export_sapling {
+        // Secrets go to stdout; `csapzkey` secrets only with the wallet passphrase.
+        let wallet = decode_wallet(&reader, salvage);
+        let network = wallet.network().unwrap_or_else(|| {
+            eprintln!("warning: no Zcash networkinfo record; encoding keys for mainnet");
+            ZcashNetwork::Main
+        });
+        let secrets = match &wallet_passphrase {
+            Some(pw) => Some(decrypt_wallet(&wallet, pw.as_bytes())?),
+            None => None,
+        };
+        for key in sapling_keys(&wallet, secrets.as_ref(), network) {
+            println!("{} {}", key.extsk.expose(), uint256_hex(&key.ivk));
+        }
+        for failure in secrets.iter().flat_map(|secrets| &secrets.failures) {
+            eprintln!("warning: {failure}");
+        }
+        let locked = wallet.keys.encrypted_sapling.len();
+        if secrets.is_none() && locked > 0 {
+            eprintln!("warning: {locked} csapzkey records not exported; pass --wallet-passphrase");
+        }
+        return Ok(());
+    }
+    if let Some(out) = dump_to {
+        // Encrypted keys are only dumped when the passphrase unlocks them.
+        let wallet = decode_wallet(&reader, salvage);
+        let network = wallet.network().unwrap_or_else(|| {
+            eprintln!("warning: no Zcash networkinfo record; encoding keys for mainnet");
+            ZcashNetwork::Main
+        });
+        let secrets = match &wallet_passphrase {
+            Some(pw) => Some(decrypt_wallet(&wallet, pw.as_bytes())?),
+            None => None,
+        };
+        let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs() as i64;
+        let mut file = BufWriter::new(create_private(&out)?);
+        write_wallet_dump(&mut file, &wallet, secrets.as_ref(), network, now)?;
+        file.flush()?;
+        for failure in secrets.iter().flat_map(|secrets| &secrets.failures) {
+            eprintln!("warning: {failure}");
+        }
+        if secrets.is_none() && wallet.crypto.is_encrypted() {
+            eprintln!("warning: encrypted keys not dumped; pass --wallet-passphrase");
+        }
+        println!("wrote {}", out.display());
+        return Ok(());
+    }
+    if let Some(out) = sqlite_to {
+        let wallet = decode_wallet(&reader, salvage);
+        let network = wallet.network().unwrap_or_else(|| {
+            eprintln!("warning: no Zcash networkinfo record; encoding addresses for mainnet");
+            ZcashNetwork::Main
+        });
+        export_sqlite(&out, &wallet, network)?;
+        println!("wrote {}", out.display());
+        return Ok(());
+    }
+    if let Some(pw) = wallet_passphrase.filter(|_| chosen.is_empty()) {
+        let wallet = decode_wallet(&reader, salvage);
+        let secrets = decrypt_wallet(&wallet, pw.as_bytes())?;
+        println!("{secrets}");
+        for failure in &secrets.failures {
+            eprintln!("warning: {failure}");
+        }
+        for issue in check_seeds(&wallet, secrets.mnemonic.as_ref()) {
+            eprintln!("warning: {issue}");
+        }
+        return Ok(());
+    }
+    if show_balance {
+        let wallet = decode_wallet(&reader, salvage);
+        let balances = Balances::of(&wallet);
+        println!("{balances}");
+        if balances.undecoded_transactions > 0 {
+            eprintln!(
+                "warning: {} tx records failed to decode; the balances leave them out",
+                balances.undecoded_transactions
+            );
+        }
+        if balances.requires_rescan().next().is_some() {
+            eprintln!("warning: some notes have no witness; zcashd must rescan to spend them");
+        }
+        for issue in check_witnesses(&wallet) {
+            eprintln!("warning: witness {issue}");
+        }
+        return Ok(());
+    }
+    if show_lsns {
+        let lsns = analyze_lsns(&reader, checkpoint);
+        println!("{lsns}");
+        for warning in lsns.warnings() {
+            eprintln!("warning: {warning}");
+        }
+        return Ok(());
+    }
+    if run_check {
+        let report = check(&reader)?;
+        println!("{report}");
+        if !report.is_clean() {
+            process::exit(1);
+        }
+        return Ok(());
+    }
+    if let Some(out) = repack_to {
+        let (image, report) = repack(&reader)?;
+        fs::write(&out, image)?;
+        println!("{report}");
+        println!("wrote {}", out.display());
+        return Ok(());
+    }
+    if mode == ParseMode::Strict {
+        // Read every record once up front so a damaged tree fails before anything is listed.
+        reader.build_map_with(salvage, ConflictPolicy::KeepAll)?;
+    }
     let mut total = 0usize;
-    for pg in 1..=meta.last_pgno {
-        let page = page_slice(&bytes, ps, pg);
-        let hdr = parse_page_header(page, endian)?;
-        if matches!(hdr.ptype, PageType::Leaf) {
-            let pairs = leaf_pairs_on_page(&bytes, ps, endian, page, &hdr)?;
-            total += pairs.len();
-            for (i, (k, v)) in pairs.iter().take(3).enumerate() {
-                println!(
-                    "page {pg} item {i}: key_len={} val_len={}",
-                    k.len(),
-                    v.len()
+    let mut current = (0, 0usize); // (page, items shown on it)
+    for (key, value, prov) in reader.entries(salvage) {
+        total += 1;
+        if current.0 != prov.page_no {
+            current = (prov.page_no, 0);
+        }
+        if current.1 < 3 {
+            println!(
+                "page {} item {}: key_len={} val_len={} confidence={:?}",
+                prov.page_no,
+                current.1,
+                key.len(),
+                value.materialize()?.len(),
+                prov.confidence
+            );
+            current.1 += 1;
+        }
+    }
+    println!("total kv pairs (incl. overflow) = {total}");
+    for finding in reader.diagnostics() {
+        eprintln!("warning: {}", finding.message);
+    }
+    // A copy taken from a running node reads fine but may be missing recent records.
+    for warning in analyze_lsns(&reader, checkpoint).warnings() {
+        eprintln!("warning: {warning}");
+    }
+
+    Ok(())
+}
+
+/// The records with their values read in full, warning about those whose overflow or
+/// blob value cannot be read.
+fn read_records(
+    reader: &impl DbImageReader,
+    salvage: SalvageMode,
+) -> impl Iterator<Item = (Vec<u8>, Vec<u8>)> {
+    reader
+        .entries(salvage)
+        .filter_map(|(key, value, prov)| match value.materialize() {
+            Ok(value) => Some((key, value)),
+            Err(e) => {
+                let tag = split_walletdb_key(&key).map_or("?", |(tag, _)| tag);
+                eprintln!(
+                    "warning: {tag} record on page {} slot {}: {e}",
+                    prov.page_no, prov.slot_index
                 );
+                None
             }
+        })
+}
+
+/// Decode the wallet's records into the model, warning about those it passed over.
+fn decode_wallet(reader: &impl DbImageReader, salvage: SalvageMode) -> ZcashdWallet {
+    let wallet = ZcashdWallet::from_records(&default_registry(), read_records(reader, salvage));
+    for warning in &wallet.warnings {
+        eprintln!("warning: {warning}");
+    }
+    wallet
+}
+
+/// Recover every parseable leaf entry of an image whose meta page or tail may be gone.
+fn salvage_raw(bytes: Vec<u8>, source_id: String) -> Result<()> {
+    let (geometry, scan) = salvage_image(bytes, source_id)?;
+    println!("{geometry}");
+    if geometry.trailing_bytes != 0 {
+        eprintln!(
+            "warning: dropped a partial page of {} bytes at the end",
+            geometry.trailing_bytes
+        );
+    }
+    let mut total = 0usize;
+    for (key, value, prov) in scan {
+        let tag = split_walletdb_key(&key).map_or("?", |(tag, _)| tag);
+        let value = value.materialize()?;
+        println!(
+            "salvaged: page {} slot {}: tag={tag} key_len={} val_len={} confidence={:?}",
+            prov.page_no,
+            prov.slot_index,
+            key.len(),
+            value.len(),
+            prov.confidence
+        );
+        total += 1;
+    }
+    println!("total salvaged pairs = {total}");
+    Ok(())
+}
+
+/// Carve database pages out of an arbitrary blob and salvage what they hold.
+fn carve_raw(blob: Arc<[u8]>, source_id: String) -> Result<()> {
+    let databases = carve(&blob);
+    println!("carved databases = {}", databases.len());
+    for (i, db) in databases.iter().enumerate() {
+        println!("{db}");
+        let mut total = 0usize;
+        for (key, value, prov) in db.entries(blob.clone(), format!("{source_id}#{i}")) {
+            let tag = split_walletdb_key(&key).map_or("?", |(tag, _)| tag);
+            println!(
+                "carved: db {i} page {} slot {}: tag={tag} key_len={} val_len={} confidence={:?}",
+                prov.page_no,
+                prov.slot_index,
+                key.len(),
+                value.materialize()?.len(),
+                prov.confidence
+            );
+            total += 1;
         }
+        println!("db {i}: total carved pairs = {total}");
     }
-    println!("total kv pairs (incl. overflow) = {total}");
+    Ok(())
+}
+
+/// Create `path` for secrets: readable by the owner only, and never over an existing
+/// file, as `z_exportwallet` refuses to overwrite one.
+fn create_private(path: &Path) -> std::io::Result<File> {
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    options.open(path)
+}
+
+/// Write `wallet` to a new `zcash_client_sqlite` database at `path`.
+#[cfg(feature = "sqlite")]
+fn export_sqlite(path: &Path, wallet: &ZcashdWallet, network: ZcashNetwork) -> Result<()> {
+    use zcashd_walletdb_parser::parser::export::sqlite::write_wallet_db;
 
+    if path.exists() {
+        anyhow::bail!("{} already exists", path.display());
+    }
+    let mut conn = rusqlite::Connection::open(path)?;
+    write_wallet_db(&mut conn, wallet, network)?;
     Ok(())
 }
+
+#[cfg(not(feature = "sqlite"))]
+fn export_sqlite(_: &Path, _: &ZcashdWallet, _: ZcashNetwork) -> Result<()> {
+    unreachable!("--export-sqlite is rejected without the sqlite feature")
+}
@@ -1,2 +1,21 @@
+pub mod address;
+pub mod balance;
+pub mod crypter;
+pub mod decoders;
+pub mod export;
+pub mod key;
+pub mod keypath;
+pub mod lineage;
+pub mod lint;
+pub mod merkle;
+pub mod mnemonic;
+pub mod profile;
 pub mod record;
 pub mod registry;
+pub mod render;
+pub mod secret;
+pub mod serialize;
+pub mod summary;
+pub mod tx;
+pub mod verify;
+pub mod wallet;
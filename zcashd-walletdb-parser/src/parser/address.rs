@@ -0,0 +1,80 @@
+//! Encoded addresses: the strings zcashd shows for the keys and scripts a wallet holds.
+
+use crate::{
+    crypto::ripemd160::hash160,
+    parser::decoders::{network::ZcashNetwork, script::ScriptKind},
+};
+
+pub mod base58;
+pub mod bech32;
+pub mod unified;
+
+/// A transparent address: the hash160 of a public key or of a redeem script.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum TransparentAddress {
+    /// P2PKH, `t1` on mainnet.
+    PublicKeyHash([u8; 20]),
+    /// P2SH, `t3` on mainnet.
+    ScriptHash([u8; 20]),
+}
+
+impl TransparentAddress {
+    /// The P2PKH address of a serialized public key, compressed or not.
+    pub fn from_pubkey(pubkey: &[u8]) -> Self {
+        TransparentAddress::PublicKeyHash(hash160(pubkey))
+    }
+
+    /// The P2SH address of a redeem script.
+    pub fn from_redeem_script(script: &[u8]) -> Self {
+        TransparentAddress::ScriptHash(hash160(script))
+    }
+
+    /// The address a script pays, as zcashd's `ExtractDestination` finds it: pay to public
+    /// key counts as its key's P2PKH address. `None` for multisig, null data and
+    /// non-standard scripts, which have none.
+    pub fn of_script(script: &[u8]) -> Option<Self> {
+        match ScriptKind::of(script) {
+            ScriptKind::PubKey(pubkey) => Some(TransparentAddress::from_pubkey(&pubkey)),
+            ScriptKind::PubKeyHash(hash) => Some(TransparentAddress::PublicKeyHash(hash)),
+            ScriptKind::ScriptHash(hash) => Some(TransparentAddress::ScriptHash(hash)),
+            _ => None,
+        }
+    }
+
+    pub fn hash(&self) -> &[u8; 20] {
+        match self {
+            TransparentAddress::PublicKeyHash(hash) | TransparentAddress::ScriptHash(hash) => hash,
+        }
+    }
+
+    /// The Base58Check encoding on `network`.
+    pub fn encode(&self, network: ZcashNetwork) -> String {
+        let prefix = match self {
+            TransparentAddress::PublicKeyHash(_) => network.b58_pubkey_address_prefix(),
+            TransparentAddress::ScriptHash(_) => network.b58_script_address_prefix(),
+        };
+        base58::encode_check(&[&prefix[..], self.hash()].concat())
+    }
+
+    /// Parse a Base58Check t-address, with the networks it is encoded for. `None` if it
+    /// is not one, or its checksum does not match.
+    pub fn decode(address: &str) -> Option<(Self, &'static [ZcashNetwork])> {
+        const MAIN: &[ZcashNetwork] = &[ZcashNetwork::Main];
+        const TEST_OR_REGTEST: &[ZcashNetwork] = &[ZcashNetwork::Test, ZcashNetwork::Regtest];
+        let data = base58::decode_check(address)?;
+        let (prefix, hash) = data.split_first_chunk::<2>()?;
+        let hash: [u8; 20] = hash.try_into().ok()?;
+        for (network, networks) in [
+            (ZcashNetwork::Main, MAIN),
+            (ZcashNetwork::Test, TEST_OR_REGTEST),
+        ] {
+            if *prefix == network.b58_pubkey_address_prefix() {
+                return Some((TransparentAddress::PublicKeyHash(hash), networks));
+            }
+            if *prefix == network.b58_script_address_prefix() {
+                return Some((TransparentAddress::ScriptHash(hash), networks));
+            }
+        }
+        None
+    }
+}
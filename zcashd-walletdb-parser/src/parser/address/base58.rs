@@ -0,0 +1,65 @@
+//! Base58 and Base58Check, the encodings of transparent and Sprout addresses and of WIF
+//! private keys.
+
+use crate::crypto::sha256::sha256d;
+
+const ALPHABET: &[u8; 58] = b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+
+/// Encode `data` in Base58. Each leading zero byte becomes a leading `1`.
+pub fn encode(data: &[u8]) -> String {
+    let zeros = data.iter().take_while(|&&b| b == 0).count();
+    // Base-58 digits, least significant first.
+    let mut digits: Vec<u8> = Vec::with_capacity(data.len() * 138 / 100 + 1);
+    for &byte in &data[zeros..] {
+        let mut carry = byte as u32;
+        for digit in &mut digits {
+            carry += (*digit as u32) << 8;
+            *digit = (carry % 58) as u8;
+            carry /= 58;
+        }
+        while carry > 0 {
+            digits.push((carry % 58) as u8);
+            carry /= 58;
+        }
+    }
+    let mut encoded = "1".repeat(zeros);
+    encoded.extend(digits.iter().rev().map(|&d| ALPHABET[d as usize] as char));
+    encoded
+}
+
+/// Decode a Base58 string, or `None` if it holds a character outside the alphabet.
+pub fn decode(encoded: &str) -> Option<Vec<u8>> {
+    let ones = encoded.bytes().take_while(|&c| c == b'1').count();
+    // Bytes, least significant first.
+    let mut bytes: Vec<u8> = Vec::with_capacity(encoded.len() * 733 / 1000 + 1);
+    for c in encoded[ones..].bytes() {
+        let mut carry = ALPHABET.iter().position(|&a| a == c)? as u32;
+        for byte in &mut bytes {
+            carry += *byte as u32 * 58;
+            *byte = carry as u8;
+            carry >>= 8;
+        }
+        while carry > 0 {
+            bytes.push(carry as u8);
+            carry >>= 8;
+        }
+    }
+    let mut decoded = vec![0; ones];
+    decoded.extend(bytes.iter().rev());
+    Some(decoded)
+}
+
+/// Encode `data` followed by the first four bytes of its double SHA-256.
+pub fn encode_check(data: &[u8]) -> String {
+    let checksum = sha256d(data);
+    encode(&[data, &checksum[..4]].concat())
+}
+
+/// Decode a Base58Check string and strip its checksum, or `None` if it is not Base58 or
+/// the checksum does not match.
+pub fn decode_check(encoded: &str) -> Option<Vec<u8>> {
+    let mut data = decode(encoded)?;
+    let split = data.len().checked_sub(4)?;
+    let checksum = data.split_off(split);
+    (sha256d(&data)[..4] == checksum[..]).then_some(data)
+}
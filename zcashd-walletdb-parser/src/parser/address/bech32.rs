@@ -0,0 +1,123 @@
+//! Bech32 (BIP 173) and Bech32m (BIP 350), the encodings of Sapling addresses and keys
+//! and, as Bech32m, of ZIP 316 unified encodings.
+//!
+//! Zcash lifts BIP 173's 90-character limit for unified encodings, which are far longer,
+//! so none is enforced here.
+
+const CHARSET: &[u8; 32] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+
+const GENERATOR: [u32; 5] = [
+    0x3b6a_57b2,
+    0x2650_8e6d,
+    0x1ea1_19fa,
+    0x3d42_33dd,
+    0x2a14_62b3,
+];
+
+/// The checksum flavour of an encoding.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Variant {
+    Bech32,
+    Bech32m,
+}
+
+impl Variant {
+    fn constant(self) -> u32 {
+        match self {
+            Variant::Bech32 => 1,
+            Variant::Bech32m => 0x2bc8_30a3,
+        }
+    }
+}
+
+fn polymod(values: impl IntoIterator<Item = u8>) -> u32 {
+    let mut chk = 1u32;
+    for v in values {
+        let top = chk >> 25;
+        chk = ((chk & 0x01ff_ffff) << 5) ^ v as u32;
+        for (i, g) in GENERATOR.iter().enumerate() {
+            if (top >> i) & 1 == 1 {
+                chk ^= g;
+            }
+        }
+    }
+    chk
+}
+
+fn hrp_expand(hrp: &str) -> impl Iterator<Item = u8> + '_ {
+    hrp.bytes()
+        .map(|c| c >> 5)
+        .chain([0])
+        .chain(hrp.bytes().map(|c| c & 0x1f))
+}
+
+/// Encode 5-bit `data` under `hrp`, which must be lowercase.
+pub fn encode(hrp: &str, data: &[u8], variant: Variant) -> String {
+    let values: Vec<u8> = hrp_expand(hrp).chain(data.iter().copied()).collect();
+    let checksum = polymod(values.into_iter().chain([0; 6])) ^ variant.constant();
+    let mut encoded = String::with_capacity(hrp.len() + 1 + data.len() + 6);
+    encoded.push_str(hrp);
+    encoded.push('1');
+    let checksum = (0..6).map(|i| ((checksum >> (5 * (5 - i))) & 0x1f) as u8);
+    encoded.extend(
+        data.iter()
+            .copied()
+            .chain(checksum)
+            .map(|d| CHARSET[d as usize] as char),
+    );
+    encoded
+}
+
+/// Split an encoding into its lowercased human-readable part, its 5-bit data and the
+/// variant its checksum matches, or `None` if it is not valid Bech32 or Bech32m.
+pub fn decode(encoded: &str) -> Option<(String, Vec<u8>, Variant)> {
+    let has_lower = encoded.bytes().any(|c| c.is_ascii_lowercase());
+    let has_upper = encoded.bytes().any(|c| c.is_ascii_uppercase());
+    if has_lower && has_upper || encoded.bytes().any(|c| !(33..=126).contains(&c)) {
+        return None;
+    }
+    let encoded = encoded.to_ascii_lowercase();
+    let (hrp, data) = encoded.rsplit_once('1')?;
+    if hrp.is_empty() || data.len() < 6 {
+        return None;
+    }
+    let data = data
+        .bytes()
+        .map(|c| CHARSET.iter().position(|&d| d == c).map(|d| d as u8))
+        .collect::<Option<Vec<u8>>>()?;
+    let variant = match polymod(hrp_expand(hrp).chain(data.iter().copied())) {
+        c if c == Variant::Bech32.constant() => Variant::Bech32,
+        c if c == Variant::Bech32m.constant() => Variant::Bech32m,
+        _ => return None,
+    };
+    let payload = data[..data.len() - 6].to_vec();
+    Some((hrp.to_string(), payload, variant))
+}
+
+/// Regroup `data` from `from`-bit to `to`-bit values. With `pad`, leftover bits are
+/// zero-padded into a last value; without it, they must be fewer than `from` and zero.
+pub fn convert_bits(data: &[u8], from: u32, to: u32, pad: bool) -> Option<Vec<u8>> {
+    let (mut acc, mut bits) = (0u32, 0u32);
+    let max = (1u32 << to) - 1;
+    let max_acc = (1u32 << (from + to - 1)) - 1;
+    let mut out = Vec::with_capacity(data.len() * from as usize / to as usize + 1);
+    for &value in data {
+        if (value as u32) >> from != 0 {
+            return None;
+        }
+        acc = ((acc << from) | value as u32) & max_acc;
+        bits += from;
+        while bits >= to {
+            bits -= to;
+            out.push(((acc >> bits) & max) as u8);
+        }
+    }
+    if pad {
+        if bits > 0 {
+            out.push(((acc << (to - bits)) & max) as u8);
+        }
+    } else if bits >= from || (acc << (to - bits)) & max != 0 {
+        return None;
+    }
+    Some(out)
+}
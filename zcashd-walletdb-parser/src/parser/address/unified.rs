@@ -0,0 +1,262 @@
+//! ZIP 316 unified encodings: unified addresses (`u1...`) and unified full and incoming
+//! viewing keys (`uview1...`, `uivk1...`).
+//!
+//! An encoding is a list of typed items, each a receiver or a viewing key of one pool,
+//! serialized in ascending typecode order, followed by the human-readable part padded to
+//! 16 bytes, permuted with F4Jumble and written as Bech32m. zcashd keeps its unified
+//! full viewing keys encoded; [`UnifiedEncoding::decode`] and [`UnifiedEncoding::encode`]
+//! take them apart and put them back together.
+//!
+//! zcashd stores no receivers, only the diversifier index and receiver types of each
+//! unified address it generated. Rendering one means deriving each receiver from the full
+//! viewing key, which takes Jubjub, Pallas and secp256k1 arithmetic this crate does not
+//! implement; [`UnifiedEncoding::address`] encodes receivers obtained elsewhere.
+
+use crate::{
+    crypto::blake2b::blake2b,
+    entry::parser::write_compact_size,
+    parser::{
+        address::bech32::{self, Variant},
+        decoders::{network::ZcashNetwork, unified::ReceiverType},
+        record::{DecodeError, DecodeResult},
+        serialize::Reader,
+    },
+};
+
+/// The shortest and longest inputs F4Jumble accepts.
+pub const F4JUMBLE_MIN_LEN: usize = 48;
+pub const F4JUMBLE_MAX_LEN: usize = 4_194_368;
+
+/// What a unified encoding encodes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnifiedKind {
+    Address,
+    FullViewingKey,
+    IncomingViewingKey,
+}
+
+impl UnifiedKind {
+    const ALL: [UnifiedKind; 3] = [
+        UnifiedKind::Address,
+        UnifiedKind::FullViewingKey,
+        UnifiedKind::IncomingViewingKey,
+    ];
+
+    /// The human-readable part on `network`.
+    pub fn hrp(self, network: ZcashNetwork) -> &'static str {
+        match (self, network) {
+            (UnifiedKind::Address, ZcashNetwork::Main) => "u",
+            (UnifiedKind::Address, ZcashNetwork::Test) => "utest",
+            (UnifiedKind::Address, ZcashNetwork::Regtest) => "uregtest",
+            (UnifiedKind::FullViewingKey, ZcashNetwork::Main) => "uview",
+            (UnifiedKind::FullViewingKey, ZcashNetwork::Test) => "uviewtest",
+            (UnifiedKind::FullViewingKey, ZcashNetwork::Regtest) => "uviewregtest",
+            (UnifiedKind::IncomingViewingKey, ZcashNetwork::Main) => "uivk",
+            (UnifiedKind::IncomingViewingKey, ZcashNetwork::Test) => "uivktest",
+            (UnifiedKind::IncomingViewingKey, ZcashNetwork::Regtest) => "uivkregtest",
+        }
+    }
+
+    /// The length ZIP 316 fixes for an item of type `receiver`, if it fixes one.
+    fn item_len(self, receiver: ReceiverType) -> Option<usize> {
+        match (self, receiver) {
+            (UnifiedKind::Address, ReceiverType::P2pkh | ReceiverType::P2sh) => Some(20),
+            (UnifiedKind::Address, ReceiverType::Sapling | ReceiverType::Orchard) => Some(43),
+            (UnifiedKind::FullViewingKey, ReceiverType::P2pkh) => Some(65),
+            (UnifiedKind::FullViewingKey, ReceiverType::Sapling) => Some(128),
+            (UnifiedKind::FullViewingKey, ReceiverType::Orchard) => Some(96),
+            (UnifiedKind::IncomingViewingKey, ReceiverType::P2pkh) => Some(65),
+            (UnifiedKind::IncomingViewingKey, ReceiverType::Sapling) => Some(64),
+            (UnifiedKind::IncomingViewingKey, ReceiverType::Orchard) => Some(64),
+            _ => None,
+        }
+    }
+}
+
+/// One item of a unified encoding.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnifiedItem {
+    pub typecode: u32,
+    pub data: Vec<u8>,
+}
+
+impl UnifiedItem {
+    /// The pool of the item. Typecodes number pools the way zcashd's receiver types do.
+    pub fn receiver_type(&self) -> ReceiverType {
+        ReceiverType::from(self.typecode)
+    }
+}
+
+/// A decoded unified address or viewing key.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnifiedEncoding {
+    pub kind: UnifiedKind,
+    pub network: ZcashNetwork,
+    /// In ascending typecode order.
+    pub items: Vec<UnifiedItem>,
+}
+
+impl UnifiedEncoding {
+    /// A unified address with `receivers`, which are sorted into typecode order.
+    pub fn address(network: ZcashNetwork, mut receivers: Vec<UnifiedItem>) -> Self {
+        receivers.sort_by_key(|item| item.typecode);
+        UnifiedEncoding {
+            kind: UnifiedKind::Address,
+            network,
+            items: receivers,
+        }
+    }
+
+    pub fn item(&self, receiver: ReceiverType) -> Option<&UnifiedItem> {
+        self.items
+            .iter()
+            .find(|item| item.receiver_type() == receiver)
+    }
+
+    /// Parse an encoded unified address or viewing key.
+    pub fn decode(encoded: &str) -> DecodeResult<Self> {
+        let (hrp, data, variant) = bech32::decode(encoded)
+            .ok_or_else(|| DecodeError::new("unified encoding: not valid Bech32m"))?;
+        if variant != Variant::Bech32m {
+            return Err(DecodeError::new(
+                "unified encoding: Bech32 checksum, expected Bech32m",
+            ));
+        }
+        let (kind, network) = UnifiedKind::ALL
+            .iter()
+            .flat_map(|&kind| {
+                [
+                    ZcashNetwork::Main,
+                    ZcashNetwork::Test,
+                    ZcashNetwork::Regtest,
+                ]
+                .map(|network| (kind, network))
+            })
+            .find(|&(kind, network)| kind.hrp(network) == hrp)
+            .ok_or_else(|| DecodeError::new(format!("unified encoding: unknown prefix {hrp}")))?;
+        let jumbled = bech32::convert_bits(&data, 5, 8, false)
+            .ok_or_else(|| DecodeError::new("unified encoding: invalid padding"))?;
+        let mut raw = f4jumble_inv(&jumbled).ok_or_else(|| {
+            DecodeError::new(format!(
+                "unified encoding: {} bytes is outside F4Jumble's range",
+                jumbled.len()
+            ))
+        })?;
+        let padding = raw.split_off(raw.len() - 16);
+        if padding != hrp_padding(&hrp) {
+            return Err(DecodeError::new(
+                "unified encoding: padding does not match the prefix",
+            ));
+        }
+
+        let mut r = Reader::new(&raw);
+        let mut items: Vec<UnifiedItem> = Vec::new();
+        while !r.is_empty() {
+            let typecode = r.compact_size("typecode")?;
+            let typecode = u32::try_from(typecode)
+                .map_err(|_| DecodeError::new("unified encoding: typecode out of range"))?;
+            let data = r.var_bytes("item")?.to_vec();
+            if items.last().is_some_and(|last| last.typecode >= typecode) {
+                return Err(DecodeError::new(format!(
+                    "unified encoding: typecode {typecode} out of order"
+                )));
+            }
+            let item = UnifiedItem { typecode, data };
+            if let Some(len) = kind.item_len(item.receiver_type())
+                && item.data.len() != len
+            {
+                return Err(DecodeError::new(format!(
+                    "unified encoding: {}-byte {} item, expected {len}",
+                    item.data.len(),
+                    item.receiver_type()
+                )));
+            }
+            items.push(item);
+        }
+        if items.is_empty() {
+            return Err(DecodeError::new("unified encoding: no items"));
+        }
+        Ok(UnifiedEncoding {
+            kind,
+            network,
+            items,
+        })
+    }
+
+    /// The Bech32m encoding, lowercase.
+    pub fn encode(&self) -> String {
+        let hrp = self.kind.hrp(self.network);
+        let mut raw = Vec::new();
+        for item in &self.items {
+            write_compact_size(&mut raw, item.typecode as u64);
+            write_compact_size(&mut raw, item.data.len() as u64);
+            raw.extend_from_slice(&item.data);
+        }
+        raw.extend_from_slice(&hrp_padding(hrp));
+        let jumbled = f4jumble(&raw).expect("unified encoding is too long for F4Jumble");
+        let data = bech32::convert_bits(&jumbled, 8, 5, true).unwrap();
+        bech32::encode(hrp, &data, Variant::Bech32m)
+    }
+}
+
+/// The human-readable part, zero-padded to 16 bytes.
+fn hrp_padding(hrp: &str) -> [u8; 16] {
+    let mut padding = [0u8; 16];
+    padding[..hrp.len()].copy_from_slice(hrp.as_bytes());
+    padding
+}
+
+/// `H_i`: BLAKE2b with an output as long as the left half.
+fn f4_h(i: u8, left_len: usize, u: &[u8]) -> Vec<u8> {
+    let mut personal = *b"UA_F4Jumble_H\0\0\0";
+    personal[13] = i;
+    blake2b(left_len, &personal, u)
+}
+
+/// `G_i`: BLAKE2b-512 in counter mode, truncated to the right half's length.
+fn f4_g(i: u8, right_len: usize, u: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(right_len + 64);
+    for j in 0..right_len.div_ceil(64) as u16 {
+        let mut personal = *b"UA_F4Jumble_G\0\0\0";
+        personal[13] = i;
+        personal[14..].copy_from_slice(&j.to_le_bytes());
+        out.extend(blake2b(64, &personal, u));
+    }
+    out.truncate(right_len);
+    out
+}
+
+fn xor(a: &[u8], b: &[u8]) -> Vec<u8> {
+    a.iter().zip(b).map(|(x, y)| x ^ y).collect()
+}
+
+/// The halves F4Jumble splits a message of `len` bytes into, or `None` if `len` is out of
+/// range.
+fn f4_split(len: usize) -> Option<usize> {
+    (F4JUMBLE_MIN_LEN..=F4JUMBLE_MAX_LEN)
+        .contains(&len)
+        .then_some((len / 2).min(64))
+}
+
+/// ZIP 316's F4Jumble, a four-round Feistel permutation that makes every output byte
+/// depend on every input byte.
+pub fn f4jumble(message: &[u8]) -> Option<Vec<u8>> {
+    let left_len = f4_split(message.len())?;
+    let (a, b) = message.split_at(left_len);
+    let x = xor(b, &f4_g(0, b.len(), a));
+    let y = xor(a, &f4_h(0, left_len, &x));
+    let d = xor(&x, &f4_g(1, x.len(), &y));
+    let c = xor(&y, &f4_h(1, left_len, &d));
+    Some([c, d].concat())
+}
+
+/// The inverse of [`f4jumble`].
+pub fn f4jumble_inv(jumbled: &[u8]) -> Option<Vec<u8>> {
+    let left_len = f4_split(jumbled.len())?;
+    let (c, d) = jumbled.split_at(left_len);
+    let y = xor(c, &f4_h(1, left_len, d));
+    let x = xor(d, &f4_g(1, d.len(), &y));
+    let a = xor(&y, &f4_h(0, left_len, &x));
+    let b = xor(&x, &f4_g(0, x.len(), &a));
+    Some([a, b].concat())
+}
@@ -0,0 +1,376 @@
+//! How much a wallet holds: [`Balances`], computed from its `tx` records.
+//!
+//! Transparent funds are the outputs of wallet transactions that pay one of the wallet's
+//! t-addresses and that no wallet transaction spends. Shielded values are encrypted in
+//! the transactions, so the shielded pools are counted in notes rather than zatoshis.
+//! Notes the wallet holds no witness for are listed apart: zcashd must rescan before it
+//! can spend them.
+//!
+//! A transaction is confirmed if it was mined at or below the wallet's best block.
+//! Records do not store the height of a transaction, so it is recovered where they allow
+//! it: from the height a coinbase commits to, from the Orchard tree, and from where a
+//! block sits in the best block locator. Where it cannot be recovered, a mined
+//! transaction counts as confirmed, as zcashd would count it.
+//!
+//! A `tx` record that fails to decode is left out of the totals, so they are only a lower
+//! bound while [`Balances::undecoded_transactions`] is not zero.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt,
+};
+
+use crate::parser::{
+    address::TransparentAddress,
+    decoders::wallet_tx::{NoteOutPoint, WalletTx},
+    record::RecordKind,
+    tx::OutPoint,
+    wallet::ZcashdWallet,
+};
+
+/// The number of blocks a coinbase output waits before it can be spent.
+pub const COINBASE_MATURITY: u32 = 100;
+
+/// The block hash zcashd gives transactions the user abandoned: the number 1.
+pub const ABANDON_HASH: [u8; 32] = {
+    let mut hash = [0; 32];
+    hash[0] = 1;
+    hash
+};
+
+/// Where a transaction stands relative to the wallet's best block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Confirmation {
+    /// Mined at or below the best block; `depth` counts the block itself, if known.
+    Confirmed { depth: Option<u32> },
+    /// Not mined, or mined in a block above the best block the wallet recorded.
+    Unconfirmed,
+    /// Abandoned: its outputs will not confirm and its inputs are free to spend again.
+    Abandoned,
+}
+
+/// Transparent funds, in zatoshis.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TransparentBalance {
+    pub confirmed: i64,
+    pub unconfirmed: i64,
+    /// Coinbase outputs fewer than [`COINBASE_MATURITY`] blocks deep.
+    pub immature: i64,
+    /// Outputs to scripts the wallet only watches, whatever their confirmation.
+    pub watch_only: i64,
+    /// The number of unspent outputs counted above.
+    pub utxos: usize,
+}
+
+/// The unspent notes of one shielded pool.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct NoteBalance {
+    pub confirmed: usize,
+    pub unconfirmed: usize,
+    /// Confirmed notes without a witness, which cannot be spent before a rescan.
+    pub requires_rescan: Vec<NoteOutPoint>,
+}
+
+/// What a wallet holds in each pool.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Balances {
+    /// The height of the wallet's best block, if the records reveal it.
+    pub best_height: Option<u32>,
+    pub transparent: TransparentBalance,
+    pub sprout: NoteBalance,
+    pub sapling: NoteBalance,
+    /// Orchard nullifiers live in zcashd's Rust wallet, not in its records, so spent
+    /// Orchard notes cannot be told apart and every received note is counted.
+    pub orchard: NoteBalance,
+    /// The `tx` records that failed to decode, whose outputs and spends the totals above
+    /// leave out.
+    pub undecoded_transactions: usize,
+}
+
+impl Balances {
+    /// Compute the balances of `wallet`. Outputs and notes the wallet spends, in any of
+    /// its transactions that is not abandoned, are left out.
+    pub fn of(wallet: &ZcashdWallet) -> Self {
+        let chain = Chain::of(wallet);
+        let live = || {
+            wallet
+                .transactions
+                .values()
+                .filter(|wtx| wtx.merkle_tx.block_hash != ABANDON_HASH)
+        };
+        let spent_outputs: BTreeSet<OutPoint> = live()
+            .flat_map(|wtx| wtx.tx().vin.iter().map(|input| input.prevout))
+            .collect();
+        let spent_nullifiers: BTreeSet<[u8; 32]> = live()
+            .flat_map(|wtx| {
+                let tx = wtx.tx();
+                let sprout = tx
+                    .sprout
+                    .iter()
+                    .flat_map(|bundle| bundle.joinsplits.iter().flat_map(|js| js.nullifiers));
+                let sapling = tx
+                    .sapling
+                    .iter()
+                    .flat_map(|bundle| bundle.spends.iter().map(|spend| spend.nullifier));
+                sprout.chain(sapling)
+            })
+            .collect();
+        let addresses = wallet.keys.transparent_addresses();
+
+        let mut balances = Balances {
+            best_height: chain.best_height,
+            undecoded_transactions: wallet
+                .errors
+                .iter()
+                .filter(|e| e.kind == RecordKind::Tx)
+                .count(),
+            ..Balances::default()
+        };
+        for wtx in live() {
+            let confirmation = chain.confirmation(wtx);
+            let t = &mut balances.transparent;
+            for (n, out) in (0..).zip(&wtx.tx().vout) {
+                let Some(tag) = TransparentAddress::of_script(&out.script_pubkey)
+                    .and_then(|address| addresses.get(&address))
+                else {
+                    continue;
+                };
+                if spent_outputs.contains(&OutPoint { txid: wtx.txid, n }) {
+                    continue;
+                }
+                t.utxos += 1;
+                match confirmation {
+                    _ if *tag == "watchs" => t.watch_only += out.value,
+                    Confirmation::Confirmed { depth: Some(depth) }
+                        if wtx.tx().is_coinbase() && depth <= COINBASE_MATURITY =>
+                    {
+                        t.immature += out.value
+                    }
+                    Confirmation::Confirmed { .. } => t.confirmed += out.value,
+                    _ => t.unconfirmed += out.value,
+                }
+            }
+
+            // `witness_caches` lists the Sprout notes, then the Sapling ones.
+            let nullifiers = (wtx.sprout_note_data.iter().map(|(_, data)| data.nullifier))
+                .chain(wtx.sapling_note_data.iter().map(|(_, data)| data.nullifier));
+            for (cache, nullifier) in wtx.witness_caches().into_iter().zip(nullifiers) {
+                let pool = match cache.outpoint {
+                    NoteOutPoint::Sprout(_) => &mut balances.sprout,
+                    NoteOutPoint::Sapling(_) => &mut balances.sapling,
+                    NoteOutPoint::Orchard { .. } => continue,
+                };
+                if nullifier.is_some_and(|nf| spent_nullifiers.contains(&nf)) {
+                    continue;
+                }
+                pool.add(cache.outpoint, confirmation, cache.is_witnessed());
+            }
+
+            if let Some(meta) = &wtx.orchard_meta {
+                for &action in meta.action_data.keys() {
+                    let witnessed = chain.orchard_marked.contains(&(wtx.txid, action));
+                    let outpoint = NoteOutPoint::Orchard {
+                        txid: wtx.txid,
+                        action,
+                    };
+                    balances.orchard.add(outpoint, confirmation, witnessed);
+                }
+            }
+        }
+        balances
+    }
+
+    /// Every note that cannot be spent before a rescan, in every pool.
+    pub fn requires_rescan(&self) -> impl Iterator<Item = &NoteOutPoint> {
+        [&self.sprout, &self.sapling, &self.orchard]
+            .into_iter()
+            .flat_map(|pool| &pool.requires_rescan)
+    }
+}
+
+impl NoteBalance {
+    fn add(&mut self, outpoint: NoteOutPoint, confirmation: Confirmation, witnessed: bool) {
+        match confirmation {
+            Confirmation::Confirmed { .. } => {
+                self.confirmed += 1;
+                if !witnessed {
+                    self.requires_rescan.push(outpoint);
+                }
+            }
+            Confirmation::Unconfirmed => self.unconfirmed += 1,
+            Confirmation::Abandoned => {}
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.confirmed + self.unconfirmed
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// The height of the wallet's best block, if the records reveal it.
+pub fn best_height(wallet: &ZcashdWallet) -> Option<u32> {
+    Chain::of(wallet).best_height
+}
+
+/// The heights the records reveal of the wallet's mined transactions, by txid, each with
+/// the record it is read from: the transaction's own `tx` record for a coinbase, the
+/// Orchard tree, or the best block locator the block sits in.
+pub fn mined_heights(wallet: &ZcashdWallet) -> BTreeMap<[u8; 32], (u32, RecordKind)> {
+    let chain = Chain::of(wallet);
+    wallet
+        .transactions
+        .values()
+        .filter(|wtx| wtx.merkle_tx.is_mined() && wtx.merkle_tx.block_hash != ABANDON_HASH)
+        .filter_map(|wtx| {
+            let mined = if let Some(height) = wtx.tx().coinbase_height() {
+                (height, RecordKind::Tx)
+            } else if let Some(&height) = chain.orchard_heights.get(&wtx.txid) {
+                (height, RecordKind::OrchardNoteCommitmentTree)
+            } else {
+                let depth = chain.locator_depth(wtx)?;
+                (
+                    chain.best_height?.checked_sub(depth)?,
+                    RecordKind::BestBlock,
+                )
+            };
+            Some((wtx.txid, mined))
+        })
+        .collect()
+}
+
+/// What the records tell of the chain: block heights and the Orchard notes the tree
+/// can build witnesses for.
+struct Chain<'a> {
+    wallet: &'a ZcashdWallet,
+    best_height: Option<u32>,
+    /// The heights the Orchard tree records, by txid.
+    orchard_heights: BTreeMap<[u8; 32], u32>,
+    /// Orchard notes whose position the tree marks, by txid and action.
+    orchard_marked: BTreeSet<([u8; 32], u32)>,
+}
+
+impl<'a> Chain<'a> {
+    fn of(wallet: &'a ZcashdWallet) -> Self {
+        let mut chain = Chain {
+            wallet,
+            best_height: None,
+            orchard_heights: BTreeMap::new(),
+            orchard_marked: BTreeSet::new(),
+        };
+        if let Some(tree) = &wallet.orchard {
+            let marked: BTreeSet<u64> = tree
+                .tree
+                .marked_indices
+                .iter()
+                .map(|&(position, _)| position)
+                .collect();
+            for notes in &tree.notes {
+                chain.orchard_heights.insert(notes.txid, notes.tx_height);
+                for &(action, position) in &notes.note_positions {
+                    if marked.contains(&position) {
+                        chain.orchard_marked.insert((notes.txid, action));
+                    }
+                }
+            }
+        }
+        // A transaction of known height mined in a block of the locator dates the tip.
+        chain.best_height = wallet.transactions.values().find_map(|wtx| {
+            let depth = chain.locator_depth(wtx)?;
+            chain.own_height(wtx)?.checked_add(depth)
+        });
+        chain
+    }
+
+    fn locator_depth(&self, wtx: &WalletTx) -> Option<u32> {
+        let locator = self.wallet.metadata.best_block.as_ref()?;
+        locator.depth_of(&wtx.merkle_tx.block_hash)
+    }
+
+    /// The height a transaction's own data gives it.
+    fn own_height(&self, wtx: &WalletTx) -> Option<u32> {
+        wtx.tx()
+            .coinbase_height()
+            .or_else(|| self.orchard_heights.get(&wtx.txid).copied())
+    }
+
+    fn confirmation(&self, wtx: &WalletTx) -> Confirmation {
+        if wtx.merkle_tx.block_hash == ABANDON_HASH {
+            return Confirmation::Abandoned;
+        }
+        if !wtx.merkle_tx.is_mined() {
+            return Confirmation::Unconfirmed;
+        }
+        if let Some(depth) = self.locator_depth(wtx) {
+            return Confirmation::Confirmed {
+                depth: Some(depth + 1),
+            };
+        }
+        match (self.own_height(wtx), self.best_height) {
+            (Some(height), Some(best)) if height > best => Confirmation::Unconfirmed,
+            (Some(height), Some(best)) => Confirmation::Confirmed {
+                depth: Some(best - height + 1),
+            },
+            _ => Confirmation::Confirmed { depth: None },
+        }
+    }
+}
+
+/// Zatoshis as ZEC, to the full eight decimals.
+pub(crate) fn zec(zatoshis: i64) -> String {
+    let sign = if zatoshis < 0 { "-" } else { "" };
+    let abs = zatoshis.unsigned_abs();
+    format!("{sign}{}.{:08}", abs / 100_000_000, abs % 100_000_000)
+}
+
+impl fmt::Display for Balances {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let t = &self.transparent;
+        writeln!(f, "Balances {{")?;
+        match self.best_height {
+            Some(height) => writeln!(f, "  best height  : {height}")?,
+            None => writeln!(f, "  best height  : unknown")?,
+        }
+        writeln!(
+            f,
+            "  transparent  : {} ZEC confirmed, {} unconfirmed, {} immature ({} UTXOs)",
+            zec(t.confirmed),
+            zec(t.unconfirmed),
+            zec(t.immature),
+            t.utxos
+        )?;
+        if t.watch_only != 0 {
+            writeln!(f, "  watch-only   : {} ZEC", zec(t.watch_only))?;
+        }
+        for (name, pool) in [
+            ("sprout", &self.sprout),
+            ("sapling", &self.sapling),
+            ("orchard", &self.orchard),
+        ] {
+            writeln!(
+                f,
+                "  {name:<13}: {} notes confirmed, {} unconfirmed",
+                pool.confirmed, pool.unconfirmed
+            )?;
+            if !pool.requires_rescan.is_empty() {
+                writeln!(
+                    f,
+                    "  {:<13}: {} notes require a rescan to spend",
+                    "",
+                    pool.requires_rescan.len()
+                )?;
+            }
+        }
+        if self.undecoded_transactions > 0 {
+            writeln!(
+                f,
+                "  undecoded    : {} tx records, left out of the totals above",
+                self.undecoded_transactions
+            )?;
+        }
+        write!(f, "}}")
+    }
+}
@@ -0,0 +1,407 @@
+//! Decrypting an encrypted wallet's secrets with its passphrase, as zcashd's `CCrypter`
+//! and `CCryptoKeyStore` do it.
+//!
+//! The passphrase and the salt of an `mkey` record are stretched with `EVP_BytesToKey`
+//! into an AES-256 key and IV, which decrypt the wallet's master key. Every secret is
+//! encrypted under that master key with AES-256-CBC and PKCS#7 padding; the IV is the
+//! first 16 bytes of a hash of what the record is keyed by, so it needs no storage:
+//!
+//! - `ckey`: `Hash(pubkey)`, the double SHA-256 of the serialized public key;
+//! - `czkey`: `Hash(address)`, of the serialized Sprout payment address;
+//! - `csapzkey`: the ZIP 32 fingerprint of the full viewing key stored beside it;
+//! - `chdseed`, `cmnemonicphrase`: the seed fingerprint.
+//!
+//! A wrong passphrase is caught at the master key, whose padding is a whole block.
+//! Each decrypted secret is then checked against what the record keeps in the clear
+//! wherever that needs no elliptic curve arithmetic: the Sprout address, the parts of
+//! the Sapling full viewing key copied from the spending key, and seed fingerprints.
+//! [`KeyVerification`](crate::parser::verify::KeyVerification) checks the rest.
+
+use std::{collections::BTreeMap, fmt};
+
+use crate::{
+    crypto::{Digest, aes::Aes, sha256::sha256d, sha512::Sha512},
+    parser::{
+        decoders::{
+            encryption::{DerivationMethod, MasterKey},
+            sapling::{EncryptedSaplingKey, SaplingExtendedSpendingKey, SaplingSpendingKey},
+            seed::{HdSeed, Language, MnemonicPhrase, seed_fingerprint},
+            sprout::{SproutPaymentAddress, SproutSpendingKey},
+        },
+        key::RecordKey,
+        mnemonic::phrase_fingerprint,
+        record::{DecodeError, DecodeResult, RecordKind},
+        secret::{SecretBytes, SecretString},
+        serialize::Reader,
+        wallet::{CryptoState, RecordError, ZcashdWallet},
+    },
+};
+
+/// The size of the master key and of the passphrase key: AES-256.
+pub const WALLET_CRYPTO_KEY_SIZE: usize = 32;
+
+/// The size of an AES block, and so of the IVs.
+const IV_LEN: usize = 16;
+
+/// `EVP_BytesToKey` with SHA-512 and one output block, which covers AES-256's key and
+/// IV: `D = SHA-512(passphrase || salt)`, rehashed until `iterations` hashes are done.
+pub fn bytes_to_key_sha512(
+    passphrase: &[u8],
+    salt: &[u8],
+    iterations: u32,
+) -> (SecretBytes, [u8; IV_LEN]) {
+    let mut h = Sha512::new();
+    h.update(passphrase);
+    h.update(salt);
+    let mut digest = SecretBytes::new(h.finalize());
+    for _ in 1..iterations {
+        digest = SecretBytes::new(Sha512::digest(digest.expose()));
+    }
+    let (key, rest) = digest.expose().split_at(WALLET_CRYPTO_KEY_SIZE);
+    (SecretBytes::from(key), rest[..IV_LEN].try_into().unwrap())
+}
+
+/// Decrypt AES-256-CBC `ciphertext` and strip its PKCS#7 padding; `None` if the padding
+/// is malformed, which is how a wrong key usually shows.
+fn cbc_decrypt(key: &[u8], iv: &[u8; IV_LEN], ciphertext: &[u8]) -> Option<SecretBytes> {
+    if ciphertext.is_empty() || !ciphertext.len().is_multiple_of(IV_LEN) {
+        return None;
+    }
+    let aes = Aes::new(key)?;
+    let mut bytes = ciphertext.to_vec();
+    aes.cbc_decrypt(iv, &mut bytes);
+    // Held as a secret from here on, so the padded plaintext is wiped too.
+    let plaintext = SecretBytes::new(bytes);
+    let bytes = plaintext.expose();
+    let pad = usize::from(*bytes.last()?);
+    let valid = (1..=IV_LEN).contains(&pad)
+        && bytes[bytes.len() - pad..]
+            .iter()
+            .all(|&b| usize::from(b) == pad);
+    valid.then(|| SecretBytes::from(&bytes[..bytes.len() - pad]))
+}
+
+/// Encrypt `plaintext` with AES-256-CBC and PKCS#7 padding.
+fn cbc_encrypt(key: &[u8], iv: &[u8; IV_LEN], plaintext: &[u8]) -> Option<Vec<u8>> {
+    let aes = Aes::new(key)?;
+    let pad = IV_LEN - plaintext.len() % IV_LEN;
+    let mut data = plaintext.to_vec();
+    data.resize(plaintext.len() + pad, pad as u8);
+    aes.cbc_encrypt(iv, &mut data);
+    Some(data)
+}
+
+/// Why a wallet could not be unlocked.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UnlockError {
+    /// The wallet has no `mkey` record: its secrets are stored in the clear.
+    NotEncrypted,
+    /// No master key uses a method this crate implements.
+    UnsupportedMethod(DerivationMethod),
+    /// The passphrase decrypts none of the master keys.
+    WrongPassphrase,
+}
+
+impl fmt::Display for UnlockError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UnlockError::NotEncrypted => f.write_str("wallet is not encrypted"),
+            UnlockError::UnsupportedMethod(method) => {
+                write!(f, "unsupported key derivation method {method}")
+            }
+            UnlockError::WrongPassphrase => f.write_str("wrong passphrase"),
+        }
+    }
+}
+
+impl std::error::Error for UnlockError {}
+
+/// The wallet's master key, decrypted with the passphrase: `vMasterKey`.
+#[derive(Debug, Clone)]
+pub struct MasterSecret {
+    /// The id of the `mkey` record it was decrypted from.
+    pub id: u32,
+    key: SecretBytes,
+}
+
+impl MasterSecret {
+    /// Decrypt the master key of `mkey` with `passphrase`; `None` if the method is not
+    /// SHA-512 or the passphrase is wrong.
+    pub fn decrypt(mkey: &MasterKey, passphrase: &[u8]) -> Option<Self> {
+        if mkey.derivation_method != DerivationMethod::Sha512 {
+            return None;
+        }
+        let (key, iv) = bytes_to_key_sha512(passphrase, &mkey.salt, mkey.derive_iterations);
+        let master = cbc_decrypt(key.expose(), &iv, &mkey.encrypted_key)?;
+        (master.len() == WALLET_CRYPTO_KEY_SIZE).then_some(MasterSecret {
+            id: mkey.id,
+            key: master,
+        })
+    }
+
+    /// Unlock the wallet with the first master key `passphrase` decrypts, as
+    /// `CWallet::Unlock` tries them.
+    pub fn unlock(crypto: &CryptoState, passphrase: &[u8]) -> Result<Self, UnlockError> {
+        if !crypto.is_encrypted() {
+            return Err(UnlockError::NotEncrypted);
+        }
+        if let Some(secret) = crypto
+            .master_keys
+            .values()
+            .find_map(|mkey| Self::decrypt(mkey, passphrase))
+        {
+            return Ok(secret);
+        }
+        let mut methods = crypto
+            .master_keys
+            .values()
+            .map(|mkey| mkey.derivation_method);
+        match methods
+            .clone()
+            .any(|method| method == DerivationMethod::Sha512)
+        {
+            true => Err(UnlockError::WrongPassphrase),
+            false => Err(UnlockError::UnsupportedMethod(methods.next().unwrap())),
+        }
+    }
+
+    /// Decrypt a secret whose IV derives from `iv_hash`: `DecryptSecret`.
+    pub fn decrypt_secret(&self, ciphertext: &[u8], iv_hash: &[u8; 32]) -> Option<SecretBytes> {
+        let iv = iv_hash[..IV_LEN].try_into().unwrap();
+        cbc_decrypt(self.key.expose(), &iv, ciphertext)
+    }
+
+    /// Encrypt a secret the way `EncryptSecret` does, so that
+    /// [`decrypt_secret`](Self::decrypt_secret) with the same `iv_hash` recovers it.
+    pub fn encrypt_secret(&self, plaintext: &[u8], iv_hash: &[u8; 32]) -> Vec<u8> {
+        let iv = iv_hash[..IV_LEN].try_into().unwrap();
+        cbc_encrypt(self.key.expose(), &iv, plaintext).expect("the master key is 32 bytes")
+    }
+
+    /// Decrypt every encrypted secret of `wallet`. Records that do not decrypt, or whose
+    /// plaintext does not fit the record, are listed in
+    /// [`DecryptedSecrets::failures`].
+    pub fn decrypt_wallet(&self, wallet: &ZcashdWallet) -> DecryptedSecrets {
+        let mut secrets = DecryptedSecrets {
+            master_key_id: self.id,
+            ..DecryptedSecrets::default()
+        };
+        let mut failures = Vec::new();
+        for (pubkey, ckey) in &wallet.keys.encrypted_transparent {
+            let key = RecordKey::PubKey(pubkey.clone());
+            match self.decrypt_ckey(pubkey, &ckey.encrypted_secret) {
+                Ok(secret) => {
+                    secrets.transparent.insert(pubkey.clone(), secret);
+                }
+                Err(error) => failures.push(failure(RecordKind::CKey, key, error)),
+            }
+        }
+        for (address, czkey) in &wallet.keys.encrypted_sprout {
+            let key = RecordKey::SproutAddress {
+                a_pk: address.a_pk,
+                pk_enc: address.pk_enc,
+            };
+            match self.decrypt_czkey(address, &czkey.encrypted_secret) {
+                Ok(sk) => {
+                    secrets.sprout.insert(*address, sk);
+                }
+                Err(error) => failures.push(failure(RecordKind::CZKey, key, error)),
+            }
+        }
+        for (ivk, csapzkey) in &wallet.keys.encrypted_sapling {
+            let iv_hash = csapzkey.extfvk.fingerprint();
+            let decrypted = self
+                .decrypt_secret(&csapzkey.encrypted_secret, &iv_hash)
+                .ok_or_else(|| DecodeError::new("does not decrypt"))
+                .and_then(|plaintext| {
+                    let mut r = Reader::new(plaintext.expose());
+                    let key = SaplingExtendedSpendingKey::read(&mut r)?;
+                    r.finish()?;
+                    check_sapling_key(&key, csapzkey)?;
+                    Ok(key)
+                });
+            match decrypted {
+                Ok(key) => {
+                    secrets
+                        .sapling
+                        .insert(*ivk, SaplingSpendingKey { ivk: *ivk, key });
+                }
+                Err(error) => failures.push(failure(
+                    RecordKind::CSapZKey,
+                    RecordKey::SaplingIvk(*ivk),
+                    error,
+                )),
+            }
+        }
+        if let Some(chdseed) = &wallet.crypto.encrypted_hd_seed {
+            let fingerprint = chdseed.fingerprint;
+            let decrypted = self
+                .decrypt_secret(&chdseed.encrypted_seed, &fingerprint)
+                .ok_or_else(|| DecodeError::new("does not decrypt"))
+                .and_then(
+                    |seed| match seed_fingerprint(seed.expose()) == fingerprint {
+                        true => Ok(HdSeed { fingerprint, seed }),
+                        false => Err(DecodeError::new(
+                            "decrypted seed does not match its fingerprint",
+                        )),
+                    },
+                );
+            match decrypted {
+                Ok(seed) => secrets.hd_seed = Some(seed),
+                Err(error) => failures.push(failure(
+                    RecordKind::CHdSeed,
+                    RecordKey::SeedFingerprint(fingerprint),
+                    error,
+                )),
+            }
+        }
+        if let Some(cmnemonic) = &wallet.crypto.encrypted_mnemonic {
+            let fingerprint = cmnemonic.fingerprint;
+            let decrypted = self
+                .decrypt_secret(&cmnemonic.encrypted_phrase, &fingerprint)
+                .ok_or_else(|| DecodeError::new("does not decrypt"))
+                .and_then(|plaintext| {
+                    let mut r = Reader::new(plaintext.expose());
+                    let language = Language::from(r.u32("language")?);
+                    let phrase = SecretString::new(r.string("phrase")?);
+                    r.finish()?;
+                    if let Some(derived) = phrase_fingerprint(phrase.expose())
+                        && derived != fingerprint
+                    {
+                        return Err(DecodeError::new(
+                            "decrypted phrase does not match its fingerprint",
+                        ));
+                    }
+                    Ok(MnemonicPhrase {
+                        fingerprint,
+                        language,
+                        phrase,
+                    })
+                });
+            match decrypted {
+                Ok(mnemonic) => secrets.mnemonic = Some(mnemonic),
+                Err(error) => failures.push(failure(
+                    RecordKind::CMnemonicPhrase,
+                    RecordKey::SeedFingerprint(fingerprint),
+                    error,
+                )),
+            }
+        }
+        secrets.failures = failures;
+        secrets
+    }
+
+    /// A `ckey` secret, which must be a 32-byte scalar.
+    fn decrypt_ckey(&self, pubkey: &[u8], ciphertext: &[u8]) -> DecodeResult<SecretBytes> {
+        let secret = self
+            .decrypt_secret(ciphertext, &sha256d(pubkey))
+            .ok_or_else(|| DecodeError::new("does not decrypt"))?;
+        if secret.len() != 32 {
+            return Err(DecodeError::new(format!(
+                "decrypted secret is {} bytes, expected 32",
+                secret.len()
+            )));
+        }
+        Ok(secret)
+    }
+
+    /// A `czkey` secret, which must be an `a_sk` deriving the address's `a_pk`.
+    fn decrypt_czkey(
+        &self,
+        address: &SproutPaymentAddress,
+        ciphertext: &[u8],
+    ) -> DecodeResult<SproutSpendingKey> {
+        let iv_hash = sha256d(&[address.a_pk, address.pk_enc].concat());
+        let a_sk = self
+            .decrypt_secret(ciphertext, &iv_hash)
+            .ok_or_else(|| DecodeError::new("does not decrypt"))?;
+        if a_sk.len() != 32 || a_sk.expose()[0] & 0xf0 != 0 {
+            return Err(DecodeError::new("decrypted secret is not an a_sk"));
+        }
+        let sk = SproutSpendingKey {
+            address: *address,
+            a_sk,
+        };
+        if sk.derive_a_pk() != address.a_pk {
+            return Err(DecodeError::new(
+                "decrypted a_sk does not derive the address",
+            ));
+        }
+        Ok(sk)
+    }
+}
+
+/// Check the parts of a decrypted Sapling spending key that its full viewing key keeps
+/// unchanged: the ZIP 32 header, `ovk` and `dk`.
+fn check_sapling_key(
+    key: &SaplingExtendedSpendingKey,
+    csapzkey: &EncryptedSaplingKey,
+) -> DecodeResult<()> {
+    let extfvk = &csapzkey.extfvk;
+    if key.header != extfvk.header || key.ovk != extfvk.ovk || key.dk != extfvk.dk {
+        return Err(DecodeError::new(
+            "decrypted key does not match the full viewing key",
+        ));
+    }
+    Ok(())
+}
+
+fn failure(kind: RecordKind, key: RecordKey, error: DecodeError) -> RecordError {
+    RecordError {
+        kind,
+        key: Some(key),
+        error,
+    }
+}
+
+/// The secrets of an encrypted wallet, decrypted, keyed like the records they come from.
+#[derive(Debug, Default)]
+pub struct DecryptedSecrets {
+    /// The id of the master key that decrypted them.
+    pub master_key_id: u32,
+    /// `ckey` secrets, by public key: the 32-byte secp256k1 scalars.
+    pub transparent: BTreeMap<Vec<u8>, SecretBytes>,
+    /// `czkey` secrets.
+    pub sprout: BTreeMap<SproutPaymentAddress, SproutSpendingKey>,
+    /// `csapzkey` secrets, by incoming viewing key.
+    pub sapling: BTreeMap<[u8; 32], SaplingSpendingKey>,
+    /// The `chdseed` secret.
+    pub hd_seed: Option<HdSeed>,
+    /// The `cmnemonicphrase` secret.
+    pub mnemonic: Option<MnemonicPhrase>,
+    /// Records that did not decrypt to what they should hold.
+    pub failures: Vec<RecordError>,
+}
+
+impl fmt::Display for DecryptedSecrets {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "DecryptedSecrets {{")?;
+        writeln!(f, "  master key   : {}", self.master_key_id)?;
+        writeln!(f, "  transparent  : {} keys", self.transparent.len())?;
+        writeln!(f, "  sprout       : {} keys", self.sprout.len())?;
+        writeln!(f, "  sapling      : {} keys", self.sapling.len())?;
+        match &self.hd_seed {
+            Some(seed) => writeln!(f, "  hd seed      : {}", seed.fingerprint_hex())?,
+            None => writeln!(f, "  hd seed      : none")?,
+        }
+        match &self.mnemonic {
+            Some(mnemonic) => writeln!(
+                f,
+                "  mnemonic     : {}, {} words",
+                mnemonic.fingerprint_hex(),
+                mnemonic.word_count()
+            )?,
+            None => writeln!(f, "  mnemonic     : none")?,
+        }
+        writeln!(f, "  failures     : {}", self.failures.len())?;
+        write!(f, "}}")
+    }
+}
+
+/// Unlock `wallet` with `passphrase` and decrypt all its secrets.
+pub fn decrypt_wallet(
+    wallet: &ZcashdWallet,
+    passphrase: &[u8],
+) -> Result<DecryptedSecrets, UnlockError> {
+    Ok(MasterSecret::unlock(&wallet.crypto, passphrase)?.decrypt_wallet(wallet))
+}
@@ -0,0 +1,75 @@
+//! The built-in [`RecordDecoder`](crate::parser::record::RecordDecoder)s, one module per
+//! family of records. [`register_all`] adds them to a registry.
+
+use crate::parser::{record::RecordKind, registry::DecoderRegistry};
+
+pub mod accounting;
+pub mod address_book;
+pub mod encryption;
+pub mod hd_chain;
+pub mod keymeta;
+pub mod keypool;
+pub mod locator;
+pub mod network;
+pub mod orchard;
+pub mod sapling;
+pub mod scalars;
+pub mod script;
+pub mod seed;
+pub mod sprout;
+pub mod transparent;
+pub mod unified;
+pub mod wallet_tx;
+
+/// Register every built-in decoder with `registry`.
+pub fn register_all(registry: &mut DecoderRegistry) {
+    registry.register(RecordKind::Name, address_book::NameDecoder);
+    registry.register(RecordKind::Purpose, address_book::PurposeDecoder);
+    registry.register(RecordKind::DestData, address_book::DestDataDecoder);
+    registry.register(RecordKind::Key, transparent::KeyDecoder);
+    registry.register(RecordKind::WKey, transparent::WKeyDecoder);
+    registry.register(RecordKind::CKey, transparent::CKeyDecoder);
+    registry.register(RecordKind::MKey, encryption::MKeyDecoder);
+    registry.register(RecordKind::KeyMeta, keymeta::KeyMetaDecoder);
+    registry.register(RecordKind::Pool, keypool::PoolDecoder);
+    registry.register(RecordKind::DefaultKey, scalars::DefaultKeyDecoder);
+    registry.register(RecordKind::Version, scalars::VersionDecoder);
+    registry.register(RecordKind::MinVersion, scalars::VersionDecoder);
+    registry.register(RecordKind::OrderPosNext, scalars::OrderPosNextDecoder);
+    registry.register(
+        RecordKind::WitnessCacheSize,
+        scalars::WitnessCacheSizeDecoder,
+    );
+    registry.register(RecordKind::Tx, wallet_tx::TxDecoder);
+    registry.register(RecordKind::Acc, accounting::AccDecoder);
+    registry.register(RecordKind::AcEntry, accounting::AcEntryDecoder);
+    registry.register(RecordKind::BestBlock, locator::BestBlockDecoder);
+    registry.register(RecordKind::BestBlockNoMerkle, locator::BestBlockDecoder);
+    registry.register(RecordKind::HdSeed, seed::HdSeedDecoder);
+    registry.register(RecordKind::CHdSeed, seed::CHdSeedDecoder);
+    registry.register(RecordKind::MnemonicPhrase, seed::MnemonicPhraseDecoder);
+    registry.register(RecordKind::CMnemonicPhrase, seed::CMnemonicPhraseDecoder);
+    registry.register(
+        RecordKind::MnemonicHdChain,
+        hd_chain::MnemonicHdChainDecoder,
+    );
+    registry.register(RecordKind::ZKey, sprout::ZKeyDecoder);
+    registry.register(RecordKind::CZKey, sprout::CZKeyDecoder);
+    registry.register(RecordKind::ZKeyMeta, sprout::ZKeyMetaDecoder);
+    registry.register(RecordKind::VKey, sprout::VKeyDecoder);
+    registry.register(RecordKind::SapZKey, sapling::SapZKeyDecoder);
+    registry.register(RecordKind::CSapZKey, sapling::CSapZKeyDecoder);
+    registry.register(RecordKind::SapZKeyMeta, sapling::SapZKeyMetaDecoder);
+    registry.register(RecordKind::SapZAddr, sapling::SapZAddrDecoder);
+    registry.register(RecordKind::SapExtFvk, sapling::SapExtFvkDecoder);
+    registry.register(RecordKind::Watchs, script::WatchsDecoder);
+    registry.register(RecordKind::CScript, script::CScriptDecoder);
+    registry.register(RecordKind::UnifiedAccount, unified::UnifiedAccountDecoder);
+    registry.register(RecordKind::UnifiedFvk, unified::UnifiedFvkDecoder);
+    registry.register(RecordKind::UnifiedAddrMeta, unified::UnifiedAddrMetaDecoder);
+    registry.register(RecordKind::NetworkInfo, network::NetworkInfoDecoder);
+    registry.register(
+        RecordKind::OrchardNoteCommitmentTree,
+        orchard::OrchardNoteCommitmentTreeDecoder,
+    );
+}
@@ -0,0 +1,137 @@
+//! Legacy accounts: `acc` records, keyed by account name, and `acentry` records, keyed
+//! by account name and entry number.
+//!
+//! zcashd inherited Bitcoin's account system and deprecated it early, but old wallets
+//! can still hold the bookkeeping of `move` calls. An accounting entry smuggles its
+//! `mapValue` (with the order position under `n`) into the comment, after a NUL byte.
+
+use std::collections::BTreeMap;
+
+use crate::parser::{
+    key::RecordKey,
+    record::{DecodeContext, DecodeError, DecodeResult, RecordDecoder},
+    serialize::Reader,
+};
+
+/// An `acc` record: zcashd's `CAccount`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Account {
+    pub name: String,
+    pub version: i32,
+    /// The account's current receiving key; empty if it never had one.
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::util::serde_hex::bytes")
+    )]
+    pub pubkey: Vec<u8>,
+}
+
+/// Decodes `acc` values: the serialization version and a `CPubKey`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AccDecoder;
+
+impl RecordDecoder for AccDecoder {
+    type Item = Account;
+
+    fn decode(
+        &self,
+        _ctx: &DecodeContext,
+        key: Option<&RecordKey>,
+        raw_value: &[u8],
+    ) -> DecodeResult<Account> {
+        let Some(RecordKey::Account(name)) = key else {
+            return Err(DecodeError::new("key is not an account name"));
+        };
+        let mut r = Reader::new(raw_value);
+        let account = Account {
+            name: name.clone(),
+            version: r.i32("version")?,
+            pubkey: r.var_bytes("public key")?.to_vec(),
+        };
+        r.finish()?;
+        Ok(account)
+    }
+
+    fn name(&self) -> &'static str {
+        "acc"
+    }
+}
+
+/// An `acentry` record: zcashd's `CAccountingEntry`, one side of a `move` between
+/// accounts.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct AccountingEntry {
+    pub account: String,
+    /// The entry number from the key.
+    pub number: u64,
+    pub version: i32,
+    /// Credited (positive) or debited (negative) amount, in zatoshis.
+    pub credit_debit: i64,
+    /// Unix time of the move.
+    pub time: i64,
+    /// The account on the other side of the move.
+    pub other_account: String,
+    /// The comment, without the `mapValue` stored after it.
+    pub comment: String,
+    /// Metadata stored after the comment, without `n`.
+    pub map_value: BTreeMap<String, String>,
+    /// The position in the wallet's transaction order, from `mapValue["n"]`.
+    pub order_pos: Option<i64>,
+}
+
+/// Decodes `acentry` values: the version, amount, time, other account and the comment,
+/// splitting the `mapValue` off the comment.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AcEntryDecoder;
+
+impl RecordDecoder for AcEntryDecoder {
+    type Item = AccountingEntry;
+
+    fn decode(
+        &self,
+        _ctx: &DecodeContext,
+        key: Option<&RecordKey>,
+        raw_value: &[u8],
+    ) -> DecodeResult<AccountingEntry> {
+        let Some(RecordKey::AccountingEntry { account, number }) = key else {
+            return Err(DecodeError::new("key is not an account and number"));
+        };
+        let mut r = Reader::new(raw_value);
+        let version = r.i32("version")?;
+        let credit_debit = r.i64("amount")?;
+        let time = r.i64("time")?;
+        let other_account = r.string("other account")?;
+        let comment = r.var_bytes("comment")?;
+        r.finish()?;
+
+        let (comment, mut map_value) = match comment.iter().position(|&b| b == 0) {
+            Some(sep) => (&comment[..sep], read_map_value(&comment[sep + 1..])?),
+            None => (comment, BTreeMap::new()),
+        };
+        let order_pos = map_value.remove("n").and_then(|n| n.parse().ok());
+        Ok(AccountingEntry {
+            account: account.clone(),
+            number: *number,
+            version,
+            credit_debit,
+            time,
+            other_account,
+            comment: String::from_utf8_lossy(comment).into_owned(),
+            map_value,
+            order_pos,
+        })
+    }
+
+    fn name(&self) -> &'static str {
+        "acentry"
+    }
+}
+
+/// A serialized `mapValue`; bytes after it (Bitcoin's `_ssExtra`) are ignored.
+fn read_map_value(bytes: &[u8]) -> DecodeResult<BTreeMap<String, String>> {
+    Reader::new(bytes).map(2, "acentry mapValue", |r| {
+        Ok((r.string("mapValue key")?, r.string("mapValue value")?))
+    })
+}
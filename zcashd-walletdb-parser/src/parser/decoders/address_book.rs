@@ -0,0 +1,312 @@
+//! The address book: `name` and `purpose` records, keyed by an encoded address, and
+//! `destdata` records, keyed by an address and the name of a datum stored for it.
+//! [`AddressBook`] joins them per address.
+
+use std::{collections::BTreeMap, fmt};
+
+use crate::parser::{
+    key::RecordKey,
+    record::{DecodeContext, DecodeError, DecodeResult, RecordDecoder},
+    serialize::Reader,
+};
+
+/// The pool an encoded address belongs to, from its human-readable prefix.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum AddressKind {
+    /// Base58 P2PKH or P2SH: `t1`/`t3` on mainnet, `tm`/`t2` on testnet and regtest.
+    Transparent,
+    /// Base58 Sprout: `zc` on mainnet, `zt` on testnet.
+    Sprout,
+    /// Bech32 Sapling: `zs`, `ztestsapling`, `zregtestsapling`.
+    Sapling,
+    /// Bech32m unified: `u`, `utest`, `uregtest`.
+    Unified,
+    Unknown,
+}
+
+impl AddressKind {
+    pub fn of(address: &str) -> Self {
+        let bech32 = |hrp: &str| {
+            address
+                .strip_prefix(hrp)
+                .is_some_and(|rest| rest.starts_with('1'))
+        };
+        if bech32("zs") || bech32("ztestsapling") || bech32("zregtestsapling") {
+            AddressKind::Sapling
+        } else if bech32("u") || bech32("utest") || bech32("uregtest") {
+            AddressKind::Unified
+        } else if ["t1", "t3", "tm", "t2"]
+            .iter()
+            .any(|p| address.starts_with(p))
+        {
+            AddressKind::Transparent
+        } else if ["zc", "zt"].iter().any(|p| address.starts_with(p)) {
+            AddressKind::Sprout
+        } else {
+            AddressKind::Unknown
+        }
+    }
+
+    pub fn is_shielded(self) -> bool {
+        matches!(
+            self,
+            AddressKind::Sprout | AddressKind::Sapling | AddressKind::Unified
+        )
+    }
+}
+
+impl fmt::Display for AddressKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            AddressKind::Transparent => "transparent",
+            AddressKind::Sprout => "sprout",
+            AddressKind::Sapling => "sapling",
+            AddressKind::Unified => "unified",
+            AddressKind::Unknown => "unknown",
+        })
+    }
+}
+
+/// The address a `name` or `purpose` record is keyed by.
+fn address_of(key: Option<&RecordKey>, tag: &str) -> DecodeResult<String> {
+    match key {
+        Some(RecordKey::Address(address)) => Ok(address.clone()),
+        _ => Err(DecodeError::new(format!("{tag}: key is not an address"))),
+    }
+}
+
+/// A `name` record: the label the user gave an address.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct AddressName {
+    pub address: String,
+    /// Often empty: zcashd labels every address it hands out, with `""` by default.
+    pub label: String,
+}
+
+impl AddressName {
+    pub fn kind(&self) -> AddressKind {
+        AddressKind::of(&self.address)
+    }
+}
+
+/// Decodes `name` values: a compact-size-prefixed label.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NameDecoder;
+
+impl RecordDecoder for NameDecoder {
+    type Item = AddressName;
+
+    fn decode(
+        &self,
+        _ctx: &DecodeContext,
+        key: Option<&RecordKey>,
+        raw_value: &[u8],
+    ) -> DecodeResult<AddressName> {
+        let address = address_of(key, "name")?;
+        let mut r = Reader::new(raw_value);
+        let label = r.string("label")?;
+        r.finish()?;
+        Ok(AddressName { address, label })
+    }
+
+    fn name(&self) -> &'static str {
+        "name"
+    }
+}
+
+/// What an address is for, as zcashd's `SetAddressBook` records it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum Purpose {
+    /// An address the wallet sent to.
+    Send,
+    /// One of the wallet's own addresses.
+    Receive,
+    /// Anything else, as written (e.g. `unknown`, or `refund` in Bitcoin Core wallets).
+    Other(String),
+}
+
+impl Purpose {
+    pub fn parse(s: &str) -> Self {
+        match s {
+            "send" => Purpose::Send,
+            "receive" => Purpose::Receive,
+            other => Purpose::Other(other.to_string()),
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        match self {
+            Purpose::Send => "send",
+            Purpose::Receive => "receive",
+            Purpose::Other(s) => s,
+        }
+    }
+}
+
+impl fmt::Display for Purpose {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A `purpose` record: what an address is for.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct AddressPurpose {
+    pub address: String,
+    pub purpose: Purpose,
+}
+
+/// Decodes `purpose` values: a compact-size-prefixed purpose string.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PurposeDecoder;
+
+impl RecordDecoder for PurposeDecoder {
+    type Item = AddressPurpose;
+
+    fn decode(
+        &self,
+        _ctx: &DecodeContext,
+        key: Option<&RecordKey>,
+        raw_value: &[u8],
+    ) -> DecodeResult<AddressPurpose> {
+        let address = address_of(key, "purpose")?;
+        let mut r = Reader::new(raw_value);
+        let purpose = Purpose::parse(&r.string("purpose")?);
+        r.finish()?;
+        Ok(AddressPurpose { address, purpose })
+    }
+
+    fn name(&self) -> &'static str {
+        "purpose"
+    }
+}
+
+/// A `destdata` record: one named datum stored for an address, such as the `used` flag
+/// or a payment request (`rr*`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct DestData {
+    pub address: String,
+    pub key: String,
+    pub value: String,
+}
+
+/// Decodes `destdata` values: a compact-size-prefixed string.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DestDataDecoder;
+
+impl RecordDecoder for DestDataDecoder {
+    type Item = DestData;
+
+    fn decode(
+        &self,
+        _ctx: &DecodeContext,
+        key: Option<&RecordKey>,
+        raw_value: &[u8],
+    ) -> DecodeResult<DestData> {
+        let Some(RecordKey::DestData { address, key }) = key else {
+            return Err(DecodeError::new("key is not an address and datum name"));
+        };
+        let mut r = Reader::new(raw_value);
+        let value = r.string("value")?;
+        r.finish()?;
+        Ok(DestData {
+            address: address.clone(),
+            key: key.clone(),
+            value,
+        })
+    }
+
+    fn name(&self) -> &'static str {
+        "destdata"
+    }
+}
+
+/// Everything the wallet records about one address.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct AddressBookEntry {
+    /// From the `name` record; `None` if the address has none.
+    pub label: Option<String>,
+    /// From the `purpose` record; `None` if the address has none.
+    pub purpose: Option<Purpose>,
+    /// From `destdata` records, by datum name.
+    pub dest_data: BTreeMap<String, String>,
+}
+
+impl AddressBookEntry {
+    /// Whether the wallet flagged the address as used (`destdata` `used`).
+    pub fn is_used(&self) -> bool {
+        self.dest_data.contains_key("used")
+    }
+}
+
+/// `name`, `purpose` and `destdata` records joined by address. zcashd writes a name and a
+/// purpose for every address book change, but either can be missing from a damaged file,
+/// so both fields are optional; an address can also carry data without being in the
+/// address book at all.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct AddressBook {
+    pub entries: BTreeMap<String, AddressBookEntry>,
+}
+
+impl AddressBook {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert_name(&mut self, name: AddressName) {
+        self.entries.entry(name.address).or_default().label = Some(name.label);
+    }
+
+    pub fn insert_purpose(&mut self, purpose: AddressPurpose) {
+        self.entries.entry(purpose.address).or_default().purpose = Some(purpose.purpose);
+    }
+
+    pub fn insert_dest_data(&mut self, data: DestData) {
+        let entry = self.entries.entry(data.address).or_default();
+        entry.dest_data.insert(data.key, data.value);
+    }
+
+    pub fn get(&self, address: &str) -> Option<&AddressBookEntry> {
+        self.entries.get(address)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Addresses with a label but no purpose, or a purpose but no label.
+    pub fn incomplete(&self) -> impl Iterator<Item = &str> {
+        self.entries
+            .iter()
+            .filter(|(_, e)| e.label.is_some() != e.purpose.is_some())
+            .map(|(address, _)| address.as_str())
+    }
+}
+
+impl fmt::Display for AddressBook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "AddressBook {{")?;
+        for (address, entry) in &self.entries {
+            let purpose = entry.purpose.as_ref().map_or("-", Purpose::as_str);
+            match &entry.label {
+                Some(label) => writeln!(f, "  {address} ({purpose}) {label:?}")?,
+                None => writeln!(f, "  {address} ({purpose})")?,
+            }
+            for (key, value) in &entry.dest_data {
+                writeln!(f, "    {key} = {value:?}")?;
+            }
+        }
+        write!(f, "}}")
+    }
+}
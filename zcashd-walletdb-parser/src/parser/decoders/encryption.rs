@@ -0,0 +1,117 @@
+//! Wallet encryption: `mkey` records, keyed by the master key's id.
+//!
+//! zcashd encrypts every secret under one random master key, and stores that master key
+//! encrypted under a key derived from the passphrase. The `mkey` record says how to
+//! derive it: the salt, the method and its iteration count.
+
+use std::fmt;
+
+use crate::parser::{
+    key::RecordKey,
+    record::{DecodeContext, DecodeError, DecodeResult, RecordDecoder},
+    serialize::Reader,
+};
+
+/// How a passphrase is stretched into the key that decrypts the master key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum DerivationMethod {
+    /// `EVP_BytesToKey` with SHA-512, the only method zcashd implements.
+    Sha512,
+    /// Reserved for scrypt, never written.
+    Scrypt,
+    Unknown(u32),
+}
+
+impl From<u32> for DerivationMethod {
+    fn from(code: u32) -> Self {
+        match code {
+            0 => DerivationMethod::Sha512,
+            1 => DerivationMethod::Scrypt,
+            other => DerivationMethod::Unknown(other),
+        }
+    }
+}
+
+impl fmt::Display for DerivationMethod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DerivationMethod::Sha512 => f.write_str("EVP_BytesToKey/SHA-512"),
+            DerivationMethod::Scrypt => f.write_str("scrypt"),
+            DerivationMethod::Unknown(code) => write!(f, "unknown ({code})"),
+        }
+    }
+}
+
+/// An `mkey` record: zcashd's `CMasterKey`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct MasterKey {
+    pub id: u32,
+    /// The 32-byte master key, AES-256-CBC encrypted under the passphrase key.
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::util::serde_hex::bytes")
+    )]
+    pub encrypted_key: Vec<u8>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::util::serde_hex::bytes")
+    )]
+    pub salt: Vec<u8>,
+    pub derivation_method: DerivationMethod,
+    pub derive_iterations: u32,
+    /// Parameters for other derivation methods; empty for SHA-512.
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::util::serde_hex::bytes")
+    )]
+    pub other_params: Vec<u8>,
+}
+
+impl fmt::Display for MasterKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "MasterKey {{")?;
+        writeln!(f, "  id           : {}", self.id)?;
+        writeln!(f, "  method       : {}", self.derivation_method)?;
+        writeln!(f, "  iterations   : {}", self.derive_iterations)?;
+        writeln!(f, "  salt         : {}", hex::encode(&self.salt))?;
+        writeln!(f, "  encrypted    : {} bytes", self.encrypted_key.len())?;
+        write!(f, "}}")
+    }
+}
+
+/// Decodes `mkey` values: the encrypted key, the salt, the derivation method and its
+/// iteration count, and the other derivation parameters.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MKeyDecoder;
+
+impl RecordDecoder for MKeyDecoder {
+    type Item = MasterKey;
+
+    fn decode(
+        &self,
+        _ctx: &DecodeContext,
+        key: Option<&RecordKey>,
+        raw_value: &[u8],
+    ) -> DecodeResult<MasterKey> {
+        let Some(&RecordKey::MasterKeyId(id)) = key else {
+            return Err(DecodeError::new("key is not a master key id"));
+        };
+        let mut r = Reader::new(raw_value);
+        let master_key = MasterKey {
+            id,
+            encrypted_key: r.var_bytes("encrypted key")?.to_vec(),
+            salt: r.var_bytes("salt")?.to_vec(),
+            derivation_method: r.u32("derivation method")?.into(),
+            derive_iterations: r.u32("iterations")?,
+            other_params: r.var_bytes("other parameters")?.to_vec(),
+        };
+        r.finish()?;
+        Ok(master_key)
+    }
+
+    fn name(&self) -> &'static str {
+        "mkey"
+    }
+}
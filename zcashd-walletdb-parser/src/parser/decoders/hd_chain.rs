@@ -0,0 +1,108 @@
+//! The `mnemonichdchain` record: zcashd's `CMnemonicHDChain`, the derivation state of the
+//! mnemonic seed.
+//!
+//! zcashd derives every new key from the seed and only counts how far it got, so these
+//! counters say how many keys and accounts another wallet has to re-derive from the
+//! phrase to find all of this wallet's funds.
+
+use std::fmt;
+
+use crate::parser::{
+    decoders::scalars::expect_bare,
+    key::RecordKey,
+    record::{DecodeContext, DecodeResult, RecordDecoder},
+    serialize::{Reader, uint256_hex},
+};
+
+/// zcashd's `CMnemonicHDChain`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct MnemonicHdChain {
+    pub version: i32,
+    /// The fingerprint of the mnemonic seed, as its `mnemonicphrase` record is keyed.
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::util::serde_hex::bytes")
+    )]
+    pub seed_fingerprint: [u8; 32],
+    /// Unix time the seed was generated.
+    pub create_time: i64,
+    /// The next ZIP 32 account to allocate for unified accounts.
+    pub account_counter: u32,
+    /// Keys derived on the external chain `m/44'/coin'/0x7fffffff'/0/i` for
+    /// `getnewaddress`.
+    pub legacy_transparent_external_counter: u32,
+    /// Keys derived on the internal chain `m/44'/coin'/0x7fffffff'/1/i` for change.
+    pub legacy_transparent_internal_counter: u32,
+    /// Keys derived under the legacy account `m/32'/coin'/0x7fffffff'/i'` for
+    /// `z_getnewaddress`.
+    pub legacy_sapling_counter: u32,
+    /// Whether the user ran `zcashd-wallet-tool` to confirm the phrase was backed up.
+    pub backup_confirmed: bool,
+}
+
+impl MnemonicHdChain {
+    /// The seed fingerprint the way zcashd prints it.
+    pub fn seed_fingerprint_hex(&self) -> String {
+        uint256_hex(&self.seed_fingerprint)
+    }
+
+    /// The transparent keys derived from the seed outside any unified account.
+    pub fn legacy_transparent_keys(&self) -> u64 {
+        u64::from(self.legacy_transparent_external_counter)
+            + u64::from(self.legacy_transparent_internal_counter)
+    }
+}
+
+impl fmt::Display for MnemonicHdChain {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "MnemonicHdChain {{")?;
+        writeln!(f, "  version      : {}", self.version)?;
+        writeln!(f, "  fingerprint  : {}", self.seed_fingerprint_hex())?;
+        writeln!(f, "  created      : {}", self.create_time)?;
+        writeln!(f, "  accounts     : {}", self.account_counter)?;
+        writeln!(
+            f,
+            "  transparent  : {} external, {} internal",
+            self.legacy_transparent_external_counter, self.legacy_transparent_internal_counter
+        )?;
+        writeln!(f, "  sapling      : {}", self.legacy_sapling_counter)?;
+        writeln!(f, "  backed up    : {}", self.backup_confirmed)?;
+        write!(f, "}}")
+    }
+}
+
+/// Decodes `mnemonichdchain` values: the version, seed fingerprint, creation time, the
+/// four `u32` counters and the backup flag.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MnemonicHdChainDecoder;
+
+impl RecordDecoder for MnemonicHdChainDecoder {
+    type Item = MnemonicHdChain;
+
+    fn decode(
+        &self,
+        _ctx: &DecodeContext,
+        key: Option<&RecordKey>,
+        raw_value: &[u8],
+    ) -> DecodeResult<MnemonicHdChain> {
+        expect_bare(key, "mnemonichdchain")?;
+        let mut r = Reader::new(raw_value);
+        let chain = MnemonicHdChain {
+            version: r.i32("version")?,
+            seed_fingerprint: r.uint256("seed fingerprint")?,
+            create_time: r.i64("creation time")?,
+            account_counter: r.u32("account counter")?,
+            legacy_transparent_external_counter: r.u32("external counter")?,
+            legacy_transparent_internal_counter: r.u32("internal counter")?,
+            legacy_sapling_counter: r.u32("sapling counter")?,
+            backup_confirmed: r.bool("backup confirmed")?,
+        };
+        r.finish()?;
+        Ok(chain)
+    }
+
+    fn name(&self) -> &'static str {
+        "mnemonichdchain"
+    }
+}
@@ -0,0 +1,117 @@
+//! Key metadata: `keymeta` records, keyed by a transparent public key.
+//!
+//! zcashd's `CKeyMetadata` records when a key was created and, for keys derived from the
+//! wallet seed, its HD path and the fingerprint of that seed. The creation times bound
+//! the wallet's birthday, the height a rescan has to start from.
+
+use crate::parser::{
+    key::RecordKey,
+    keypath::KeyPath,
+    record::{DecodeContext, DecodeError, DecodeResult, RecordDecoder},
+    serialize::{Reader, uint256_hex},
+};
+
+/// `CKeyMetadata::VERSION_WITH_HDDATA`: from this version on, the HD path and seed
+/// fingerprint follow the creation time.
+pub const VERSION_WITH_HDDATA: i32 = 10;
+
+/// zcashd's `CKeyMetadata`, shared by transparent and Sapling keys.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct KeyMetadata {
+    pub version: i32,
+    /// Unix time the key was created, or 0 if unknown (e.g. an imported key).
+    pub create_time: i64,
+    /// The key's derivation path, e.g. `m/44'/133'/0'/0/5`; `None` before
+    /// [`VERSION_WITH_HDDATA`], and empty for keys not derived from a seed.
+    pub hd_keypath: Option<String>,
+    /// The fingerprint of the seed the key derives from; `None` before
+    /// [`VERSION_WITH_HDDATA`].
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::util::serde_hex::opt_bytes")
+    )]
+    pub seed_fingerprint: Option<[u8; 32]>,
+}
+
+impl KeyMetadata {
+    /// Read a `CKeyMetadata` from `r`.
+    pub fn read(r: &mut Reader<'_>) -> DecodeResult<Self> {
+        let version = r.i32("version")?;
+        let create_time = r.i64("creation time")?;
+        let (hd_keypath, seed_fingerprint) = if version >= VERSION_WITH_HDDATA {
+            (
+                Some(r.string("HD key path")?),
+                Some(r.uint256("seed fingerprint")?),
+            )
+        } else {
+            (None, None)
+        };
+        Ok(KeyMetadata {
+            version,
+            create_time,
+            hd_keypath,
+            seed_fingerprint,
+        })
+    }
+
+    /// Whether the key derives from a seed rather than being random or imported.
+    pub fn is_hd(&self) -> bool {
+        self.hd_keypath
+            .as_deref()
+            .is_some_and(|path| !path.is_empty())
+    }
+
+    /// The parsed derivation path; `None` for keys not derived from a seed, and for
+    /// paths that do not parse.
+    pub fn key_path(&self) -> Option<KeyPath> {
+        KeyPath::parse(self.hd_keypath.as_deref()?).ok()
+    }
+
+    /// The seed fingerprint the way zcashd prints it.
+    pub fn seed_fingerprint_hex(&self) -> Option<String> {
+        self.seed_fingerprint.as_ref().map(uint256_hex)
+    }
+}
+
+/// A `keymeta` record: the metadata of one transparent key.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct TransparentKeyMetadata {
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::util::serde_hex::bytes")
+    )]
+    pub pubkey: Vec<u8>,
+    pub metadata: KeyMetadata,
+}
+
+/// Decodes `keymeta` values into a [`KeyMetadata`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct KeyMetaDecoder;
+
+impl RecordDecoder for KeyMetaDecoder {
+    type Item = TransparentKeyMetadata;
+
+    fn decode(
+        &self,
+        _ctx: &DecodeContext,
+        key: Option<&RecordKey>,
+        raw_value: &[u8],
+    ) -> DecodeResult<TransparentKeyMetadata> {
+        let Some(RecordKey::PubKey(pubkey)) = key else {
+            return Err(DecodeError::new("key is not a public key"));
+        };
+        let mut r = Reader::new(raw_value);
+        let metadata = KeyMetadata::read(&mut r)?;
+        r.finish()?;
+        Ok(TransparentKeyMetadata {
+            pubkey: pubkey.clone(),
+            metadata,
+        })
+    }
+
+    fn name(&self) -> &'static str {
+        "keymeta"
+    }
+}
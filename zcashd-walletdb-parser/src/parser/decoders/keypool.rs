@@ -0,0 +1,66 @@
+//! The key pool: `pool` records, keyed by their position in the pool.
+//!
+//! zcashd keeps transparent keys generated ahead of use in a pool, and hands them out as
+//! change and fresh addresses. Each `CKeyPool` entry names its key by public key only;
+//! the key pair itself is a `key` or `ckey` record like any other.
+
+use crate::parser::{
+    key::RecordKey,
+    record::{DecodeContext, DecodeError, DecodeResult, RecordDecoder},
+    serialize::Reader,
+};
+
+/// A `pool` record: zcashd's `CKeyPool`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct KeyPoolEntry {
+    /// The position in the pool the record is keyed by.
+    pub index: i64,
+    pub version: i32,
+    /// Unix time the key was added to the pool.
+    pub time: i64,
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::util::serde_hex::bytes")
+    )]
+    pub pubkey: Vec<u8>,
+}
+
+/// Decodes `pool` values: the version, the time and the compact-size-prefixed public
+/// key.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PoolDecoder;
+
+impl RecordDecoder for PoolDecoder {
+    type Item = KeyPoolEntry;
+
+    fn decode(
+        &self,
+        _ctx: &DecodeContext,
+        key: Option<&RecordKey>,
+        raw_value: &[u8],
+    ) -> DecodeResult<KeyPoolEntry> {
+        let Some(&RecordKey::PoolIndex(index)) = key else {
+            return Err(DecodeError::new("key is not a pool index"));
+        };
+        let mut r = Reader::new(raw_value);
+        let entry = KeyPoolEntry {
+            index,
+            version: r.i32("version")?,
+            time: r.i64("time")?,
+            pubkey: r.var_bytes("public key")?.to_vec(),
+        };
+        r.finish()?;
+        if !matches!(entry.pubkey.len(), 33 | 65) {
+            return Err(DecodeError::new(format!(
+                "{}-byte public key",
+                entry.pubkey.len()
+            )));
+        }
+        Ok(entry)
+    }
+
+    fn name(&self) -> &'static str {
+        "pool"
+    }
+}
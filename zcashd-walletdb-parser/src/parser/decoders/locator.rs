@@ -0,0 +1,102 @@
+//! The chain tip the wallet was synced to: `bestblock` and `bestblock_nomerkle` records.
+//!
+//! Both hold a `CBlockLocator`: block hashes from the tip back to genesis, dense near the
+//! tip and exponentially sparser after it. zcashd writes the locator under
+//! `bestblock_nomerkle` and leaves `bestblock` empty, so that clients predating the
+//! change, which expect merkle data there, rescan instead of trusting it.
+
+use crate::parser::{
+    key::RecordKey,
+    record::{DecodeContext, DecodeError, DecodeResult, RecordDecoder},
+    serialize::{Reader, uint256_hex},
+};
+
+/// A `CBlockLocator`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct BlockLocator {
+    pub version: i32,
+    /// Block hashes in serialized byte order, the tip first.
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::util::serde_hex::byte_list")
+    )]
+    pub hashes: Vec<[u8; 32]>,
+}
+
+impl BlockLocator {
+    /// Read a locator from `r`.
+    pub fn read(r: &mut Reader<'_>) -> DecodeResult<Self> {
+        Ok(BlockLocator {
+            version: r.i32("version")?,
+            hashes: r.list(32, "block hashes", |r| r.uint256("block hash"))?,
+        })
+    }
+
+    /// The hash of the best block the wallet saw, if the locator has any.
+    pub fn tip(&self) -> Option<&[u8; 32]> {
+        self.hashes.first()
+    }
+
+    /// The tip the way zcashd prints block hashes.
+    pub fn tip_hex(&self) -> Option<String> {
+        self.tip().map(uint256_hex)
+    }
+
+    /// The last hash, which names the chain: a locator always ends at genesis.
+    pub fn genesis(&self) -> Option<&[u8; 32]> {
+        self.hashes.last()
+    }
+
+    /// How many blocks below the tip the block `hash` is, if the locator holds it.
+    ///
+    /// zcashd steps back one block for each of the first twelve hashes, then doubles the
+    /// step for each hash after that. The genesis hash that ends the locator is the one
+    /// exception, as the last step is cut short there; it yields `None`.
+    pub fn depth_of(&self, hash: &[u8; 32]) -> Option<u32> {
+        let position = self.hashes.iter().position(|h| h == hash)?;
+        if position > 0 && position + 1 == self.hashes.len() {
+            return None;
+        }
+        let mut depth: u32 = 0;
+        let mut step: u32 = 1;
+        for i in 0..position {
+            depth = depth.checked_add(step)?;
+            if i >= 10 {
+                step = step.checked_mul(2)?;
+            }
+        }
+        Some(depth)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.hashes.is_empty()
+    }
+}
+
+/// Decodes `bestblock` and `bestblock_nomerkle` values into a [`BlockLocator`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BestBlockDecoder;
+
+impl RecordDecoder for BestBlockDecoder {
+    type Item = BlockLocator;
+
+    fn decode(
+        &self,
+        _ctx: &DecodeContext,
+        key: Option<&RecordKey>,
+        raw_value: &[u8],
+    ) -> DecodeResult<BlockLocator> {
+        if key.is_some() {
+            return Err(DecodeError::new("key has data after the tag"));
+        }
+        let mut r = Reader::new(raw_value);
+        let locator = BlockLocator::read(&mut r)?;
+        r.finish()?;
+        Ok(locator)
+    }
+
+    fn name(&self) -> &'static str {
+        "bestblock"
+    }
+}
@@ -0,0 +1,187 @@
+//! The `networkinfo` record, and the networks addresses are encoded for.
+//!
+//! zcashd writes `networkinfo` as the pair `("Zcash", <network id>)` and refuses to load
+//! a wallet written for another network. Tools reading the file directly have no such
+//! guard, so [`ZcashNetwork::of_address`] lets them check the addresses the wallet holds
+//! against the network it claims.
+
+use std::fmt;
+
+use crate::parser::{
+    decoders::{address_book::AddressKind, scalars::expect_bare},
+    key::RecordKey,
+    record::{DecodeContext, DecodeResult, RecordDecoder},
+    serialize::Reader,
+};
+
+/// A coin and one of its networks, e.g. `Zcash` `main`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Network {
+    pub coin: String,
+    pub name: String,
+}
+
+impl Network {
+    /// The Zcash network this names, if any.
+    pub fn zcash(&self) -> Option<ZcashNetwork> {
+        match self.coin.as_str() {
+            "Zcash" => ZcashNetwork::from_id(&self.name),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Network {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.coin, self.name)
+    }
+}
+
+/// A Zcash network, as zcashd's chain parameters name it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum ZcashNetwork {
+    Main,
+    Test,
+    Regtest,
+}
+
+impl ZcashNetwork {
+    /// The network with the id `networkinfo` records, e.g. `main`.
+    pub fn from_id(id: &str) -> Option<Self> {
+        match id {
+            "main" => Some(ZcashNetwork::Main),
+            "test" => Some(ZcashNetwork::Test),
+            "regtest" => Some(ZcashNetwork::Regtest),
+            _ => None,
+        }
+    }
+
+    pub fn id(self) -> &'static str {
+        match self {
+            ZcashNetwork::Main => "main",
+            ZcashNetwork::Test => "test",
+            ZcashNetwork::Regtest => "regtest",
+        }
+    }
+
+    /// The two-byte Base58Check prefix of P2PKH addresses: `t1` on mainnet, `tm` on
+    /// testnet and regtest.
+    pub fn b58_pubkey_address_prefix(self) -> [u8; 2] {
+        match self {
+            ZcashNetwork::Main => [0x1c, 0xb8],
+            ZcashNetwork::Test | ZcashNetwork::Regtest => [0x1d, 0x25],
+        }
+    }
+
+    /// The two-byte Base58Check prefix of P2SH addresses: `t3` on mainnet, `t2` on
+    /// testnet and regtest.
+    pub fn b58_script_address_prefix(self) -> [u8; 2] {
+        match self {
+            ZcashNetwork::Main => [0x1c, 0xbd],
+            ZcashNetwork::Test | ZcashNetwork::Regtest => [0x1c, 0xba],
+        }
+    }
+
+    /// The two-byte Base58Check prefix of Sprout payment addresses: `zc` on mainnet,
+    /// `zt` on testnet and regtest.
+    pub fn b58_sprout_address_prefix(self) -> [u8; 2] {
+        match self {
+            ZcashNetwork::Main => [0x16, 0x9a],
+            ZcashNetwork::Test | ZcashNetwork::Regtest => [0x16, 0xb6],
+        }
+    }
+
+    /// The two-byte Base58Check prefix of Sprout spending keys: `SK` on mainnet, `ST`
+    /// on testnet and regtest.
+    pub fn b58_sprout_spending_key_prefix(self) -> [u8; 2] {
+        match self {
+            ZcashNetwork::Main => [0xab, 0x36],
+            ZcashNetwork::Test | ZcashNetwork::Regtest => [0xac, 0x08],
+        }
+    }
+
+    /// The Base58Check prefix of private keys in Wallet Import Format: `0x80` on
+    /// mainnet, `0xef` on testnet and regtest, as in Bitcoin.
+    pub fn b58_secret_key_prefix(self) -> u8 {
+        match self {
+            ZcashNetwork::Main => 0x80,
+            ZcashNetwork::Test | ZcashNetwork::Regtest => 0xef,
+        }
+    }
+
+    /// The Bech32 human-readable part of Sapling payment addresses.
+    pub fn sapling_payment_address_hrp(self) -> &'static str {
+        match self {
+            ZcashNetwork::Main => "zs",
+            ZcashNetwork::Test => "ztestsapling",
+            ZcashNetwork::Regtest => "zregtestsapling",
+        }
+    }
+
+    /// The Bech32 human-readable part of Sapling extended spending keys, as
+    /// `z_exportkey` prints them.
+    pub fn sapling_extended_spending_key_hrp(self) -> &'static str {
+        match self {
+            ZcashNetwork::Main => "secret-extended-key-main",
+            ZcashNetwork::Test => "secret-extended-key-test",
+            ZcashNetwork::Regtest => "secret-extended-key-regtest",
+        }
+    }
+
+    /// The networks `address` is encoded for, from its prefix; empty if the prefix is
+    /// not a Zcash one. Testnet and regtest share their Base58 prefixes.
+    pub fn of_address(address: &str) -> &'static [ZcashNetwork] {
+        const MAIN: &[ZcashNetwork] = &[ZcashNetwork::Main];
+        const TEST: &[ZcashNetwork] = &[ZcashNetwork::Test];
+        const REGTEST: &[ZcashNetwork] = &[ZcashNetwork::Regtest];
+        const TEST_OR_REGTEST: &[ZcashNetwork] = &[ZcashNetwork::Test, ZcashNetwork::Regtest];
+        let hrp = address.rsplit_once('1').map_or("", |(hrp, _)| hrp);
+        match AddressKind::of(address) {
+            AddressKind::Transparent | AddressKind::Sprout => match &address[..2] {
+                "t1" | "t3" | "zc" => MAIN,
+                _ => TEST_OR_REGTEST,
+            },
+            AddressKind::Sapling | AddressKind::Unified => match hrp {
+                "zs" | "u" => MAIN,
+                "ztestsapling" | "utest" => TEST,
+                "zregtestsapling" | "uregtest" => REGTEST,
+                _ => &[],
+            },
+            AddressKind::Unknown => &[],
+        }
+    }
+}
+
+impl fmt::Display for ZcashNetwork {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.id())
+    }
+}
+
+/// Decodes `networkinfo` values: the coin and network id, as two strings.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NetworkInfoDecoder;
+
+impl RecordDecoder for NetworkInfoDecoder {
+    type Item = Network;
+
+    fn decode(
+        &self,
+        _ctx: &DecodeContext,
+        key: Option<&RecordKey>,
+        raw_value: &[u8],
+    ) -> DecodeResult<Network> {
+        expect_bare(key, "networkinfo")?;
+        let mut r = Reader::new(raw_value);
+        let coin = r.string("coin")?;
+        let name = r.string("network id")?;
+        r.finish()?;
+        Ok(Network { coin, name })
+    }
+
+    fn name(&self) -> &'static str {
+        "networkinfo"
+    }
+}
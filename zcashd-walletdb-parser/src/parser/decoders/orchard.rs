@@ -0,0 +1,394 @@
+//! The `orchard_note_commitment_tree` record: the state of zcashd's Rust Orchard wallet.
+//!
+//! zcashd does not keep Orchard witnesses in its `tx` records. It writes the client
+//! version, then the Rust wallet serializes itself: a note state version, the height of
+//! its last checkpoint, its `BridgeTree` of note commitments, and the tree position of
+//! each of its notes. Notes are only spendable without a rescan if the tree marks their
+//! positions and is checkpointed up to the chain tip.
+//!
+//! zcashd 5.0 to 5.5 write the tree in serialization v2, 5.6 and later in v3. v2 holds
+//! its bridges in their v1 layout, without a version byte and with the authentication
+//! path fragments of marked leaves in place of v3's tracked addresses and ommers, and
+//! its checkpoints without the height they were taken at.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt,
+};
+
+use crate::parser::{
+    decoders::scalars::{ClientVersion, expect_bare},
+    key::RecordKey,
+    record::{DecodeContext, DecodeError, DecodeResult, RecordDecoder},
+    serialize::Reader,
+};
+
+/// The note state version zcashd writes.
+pub const NOTE_STATE_V1: u8 = 1;
+
+/// The `BridgeTree` serialization version zcashd 5.0 to 5.5 write.
+pub const TREE_SER_V2: u8 = 2;
+
+/// The `BridgeTree` serialization version zcashd 5.6 and later write.
+pub const TREE_SER_V3: u8 = 3;
+
+/// The `MerkleBridge` serialization version of the bridges of a v3 tree.
+pub const BRIDGE_SER_V2: u8 = 2;
+
+/// The depth of the Orchard note commitment tree.
+const TREE_DEPTH: u8 = 32;
+
+/// A node of the tree: its level above the leaves and its index within the level.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct TreeAddress {
+    pub level: u8,
+    pub index: u64,
+}
+
+impl TreeAddress {
+    fn read(r: &mut Reader<'_>) -> DecodeResult<Self> {
+        Ok(TreeAddress {
+            level: r.u8("address level")?,
+            index: r.u64("address index")?,
+        })
+    }
+
+    /// The node at `level` whose subtree holds the leaf at `position`.
+    fn above(level: u8, position: u64) -> Self {
+        TreeAddress {
+            level,
+            index: position >> level,
+        }
+    }
+
+    fn sibling(self) -> Self {
+        TreeAddress {
+            index: self.index ^ 1,
+            ..self
+        }
+    }
+}
+
+/// The rightmost leaf of a tree and the ommers needed to compute its root.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Frontier {
+    /// The position of the leaf, 0 for the first note commitment.
+    pub position: u64,
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::util::serde_hex::bytes")
+    )]
+    pub leaf: [u8; 32],
+    /// From the lowest level up.
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::util::serde_hex::byte_list")
+    )]
+    pub ommers: Vec<[u8; 32]>,
+}
+
+impl Frontier {
+    /// Read a frontier in its v1 serialization, which keeps the layout of the former
+    /// two-leaf `Leaf` type: a left-hand leaf, then an optional right-hand leaf. The left
+    /// one of a pair is the sibling of the frontier's leaf rather than one of the other
+    /// ommers.
+    fn read(r: &mut Reader<'_>) -> DecodeResult<Self> {
+        let start = r.position();
+        let position = r.u64("frontier position")?;
+        let left = r.uint256("frontier leaf")?;
+        let right = r.optional("frontier right leaf", |r| r.uint256("frontier right leaf"))?;
+        let tail = r.list(32, "frontier ommers", |r| r.uint256("ommer"))?;
+        if right.is_some() != (position % 2 == 1) {
+            return Err(DecodeError::at(
+                start,
+                format!("frontier: leaf pair does not fit position {position}"),
+            ));
+        }
+        let (leaf, sibling) = match right {
+            Some(right) => (right, Some(left)),
+            None => (left, None),
+        };
+        Ok(Frontier {
+            position,
+            leaf,
+            ommers: sibling.into_iter().chain(tail).collect(),
+        })
+    }
+}
+
+/// A segment of the tree between two checkpoints or marked leaves.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct MerkleBridge {
+    pub prior_position: Option<u64>,
+    /// Nodes whose values the bridge still waits for, to complete a marked leaf's path.
+    pub tracking: Vec<TreeAddress>,
+    /// Completed nodes of the paths of marked leaves.
+    pub ommers: Vec<(TreeAddress, [u8; 32])>,
+    pub frontier: Frontier,
+}
+
+impl MerkleBridge {
+    /// Read a bridge of a v3 tree, which starts with its own serialization version.
+    fn read(r: &mut Reader<'_>) -> DecodeResult<Self> {
+        let version = r.u8("bridge version")?;
+        if version != BRIDGE_SER_V2 {
+            return Err(DecodeError::new(format!(
+                "unsupported bridge version {version}"
+            )));
+        }
+        Ok(MerkleBridge {
+            prior_position: r.optional("prior position", |r| r.u64("prior position"))?,
+            tracking: r.list(9, "tracking", TreeAddress::read)?,
+            ommers: r.list(9 + 32, "bridge ommers", |r| {
+                Ok((TreeAddress::read(r)?, r.uint256("ommer")?))
+            })?,
+            frontier: Frontier::read(r)?,
+        })
+    }
+
+    /// Read a bridge of a v2 tree, in the unversioned v1 layout: for each leaf marked in
+    /// the bridge, the ommers of its path the bridge saw. They become the tracked
+    /// addresses and ommers of v3, as `zcash_primitives` converts them when it loads the
+    /// tree.
+    fn read_v1(r: &mut Reader<'_>) -> DecodeResult<Self> {
+        let prior_position = r.optional("prior position", |r| r.u64("prior position"))?;
+        let fragments = r.list(8 + 8 + 8 + 1, "auth fragments", |r| {
+            let start = r.position();
+            let key = r.u64("fragment position")?;
+            let position = r.u64("fragment position")?;
+            let levels_observed = r.u64("levels observed")?;
+            let values = r.list(32, "fragment ommers", |r| r.uint256("ommer"))?;
+            if key != position || levels_observed >= u64::from(TREE_DEPTH) {
+                return Err(DecodeError::at(
+                    start,
+                    "auth fragment: inconsistent position",
+                ));
+            }
+            Ok((position, levels_observed as usize, values))
+        })?;
+        let frontier = Frontier::read(r)?;
+        let mut tracking = BTreeSet::new();
+        let mut ommers = BTreeMap::new();
+        for (position, levels_observed, values) in fragments {
+            // The levels at which the leaf's path goes left, and so waits for an ommer
+            // from the right: the lowest `levels_observed` of them are filled, the next
+            // one is being built.
+            let levels: Vec<u8> = (0..TREE_DEPTH)
+                .filter(|level| position & (1 << level) == 0)
+                .take(levels_observed + 1)
+                .collect();
+            if let Some(&building) = levels.last() {
+                tracking.insert(TreeAddress::above(building, position));
+            }
+            let filled = levels.iter().rev().skip(1);
+            for (&level, value) in filled.zip(values.into_iter().rev()) {
+                ommers.insert(TreeAddress::above(level, position).sibling(), value);
+            }
+        }
+        Ok(MerkleBridge {
+            prior_position,
+            tracking: tracking.into_iter().collect(),
+            ommers: ommers.into_iter().collect(),
+            frontier,
+        })
+    }
+}
+
+/// A checkpoint of the tree, which it can rewind to on a reorg.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct TreeCheckpoint {
+    /// The height of the block the checkpoint was taken at. v2 trees do not record it.
+    pub id: Option<u32>,
+    /// How many bridges the tree had.
+    pub bridges_len: u64,
+    /// Leaves marked since the previous checkpoint.
+    pub marked: Vec<u64>,
+    /// Leaves unmarked since the previous checkpoint, with the bridge index each was
+    /// marked in.
+    pub forgotten: Vec<(u64, u64)>,
+}
+
+impl TreeCheckpoint {
+    fn read(r: &mut Reader<'_>) -> DecodeResult<Self> {
+        Ok(TreeCheckpoint {
+            id: Some(r.u32("checkpoint id")?),
+            bridges_len: r.u64("checkpoint bridges")?,
+            marked: r.list(8, "checkpoint marked", |r| r.u64("position"))?,
+            forgotten: r.list(16, "checkpoint forgotten", |r| {
+                Ok((r.u64("position")?, r.u64("bridge index")?))
+            })?,
+        })
+    }
+
+    /// Read a checkpoint of a v2 tree: no id, and a flag of whether the tip was marked,
+    /// which v3 folds into `marked`.
+    fn read_v2(r: &mut Reader<'_>) -> DecodeResult<Self> {
+        let bridges_len = r.u64("checkpoint bridges")?;
+        r.bool("checkpoint marked tip")?;
+        Ok(TreeCheckpoint {
+            id: None,
+            bridges_len,
+            marked: r.list(8, "checkpoint marked", |r| r.u64("position"))?,
+            forgotten: r.list(16, "checkpoint forgotten", |r| {
+                Ok((r.u64("position")?, r.u64("bridge index")?))
+            })?,
+        })
+    }
+}
+
+/// The wallet's `BridgeTree` of Orchard note commitments.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct BridgeTree {
+    pub prior_bridges: Vec<MerkleBridge>,
+    /// `None` until the first note commitment is appended.
+    pub current_bridge: Option<MerkleBridge>,
+    /// Marked leaf positions, with the index of the bridge each was marked in.
+    pub marked_indices: Vec<(u64, u64)>,
+    /// The oldest first.
+    pub checkpoints: Vec<TreeCheckpoint>,
+    pub max_checkpoints: u64,
+}
+
+impl BridgeTree {
+    fn read(r: &mut Reader<'_>) -> DecodeResult<Self> {
+        type Read<T> = fn(&mut Reader<'_>) -> DecodeResult<T>;
+        let version = r.u8("tree version")?;
+        let (bridge, checkpoint): (Read<MerkleBridge>, Read<TreeCheckpoint>) = match version {
+            TREE_SER_V2 => (MerkleBridge::read_v1, TreeCheckpoint::read_v2),
+            TREE_SER_V3 => (MerkleBridge::read, TreeCheckpoint::read),
+            _ => {
+                return Err(DecodeError::new(format!(
+                    "unsupported tree version {version}"
+                )));
+            }
+        };
+        Ok(BridgeTree {
+            prior_bridges: r.list(1, "prior bridges", bridge)?,
+            current_bridge: r.optional("current bridge", bridge)?,
+            marked_indices: r.list(16, "marked indices", |r| {
+                Ok((r.u64("position")?, r.u64("bridge index")?))
+            })?,
+            checkpoints: r.list(10, "checkpoints", checkpoint)?,
+            max_checkpoints: r.u64("max checkpoints")?,
+        })
+    }
+
+    /// The number of note commitments appended to the tree.
+    pub fn size(&self) -> u64 {
+        self.current_bridge
+            .as_ref()
+            .map_or(0, |bridge| bridge.frontier.position + 1)
+    }
+}
+
+/// Where one transaction's notes sit in the tree.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct OrchardTxNotes {
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::util::serde_hex::bytes")
+    )]
+    pub txid: [u8; 32],
+    /// The height of the block that mined the transaction.
+    pub tx_height: u32,
+    /// Action indices and the tree positions of their notes.
+    pub note_positions: Vec<(u32, u64)>,
+}
+
+/// An `orchard_note_commitment_tree` record.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct OrchardNoteCommitmentTree {
+    /// The version of zcashd that wrote the record.
+    pub client_version: ClientVersion,
+    pub note_state_version: u8,
+    /// The height of the last block the wallet checkpointed, `None` until it has seen
+    /// one since NU5 activation.
+    pub last_checkpoint: Option<u32>,
+    pub tree: BridgeTree,
+    pub notes: Vec<OrchardTxNotes>,
+}
+
+impl OrchardNoteCommitmentTree {
+    /// The number of notes whose positions the wallet tracks.
+    pub fn note_count(&self) -> usize {
+        self.notes.iter().map(|tx| tx.note_positions.len()).sum()
+    }
+}
+
+impl fmt::Display for OrchardNoteCommitmentTree {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "OrchardNoteCommitmentTree {{")?;
+        writeln!(f, "  written by   : {}", self.client_version)?;
+        writeln!(f, "  version      : {}", self.note_state_version)?;
+        match self.last_checkpoint {
+            Some(height) => writeln!(f, "  checkpoint   : {height}")?,
+            None => writeln!(f, "  checkpoint   : none")?,
+        }
+        writeln!(f, "  tree size    : {}", self.tree.size())?;
+        writeln!(
+            f,
+            "  checkpoints  : {} of {}",
+            self.tree.checkpoints.len(),
+            self.tree.max_checkpoints
+        )?;
+        writeln!(f, "  notes        : {}", self.note_count())?;
+        write!(f, "}}")
+    }
+}
+
+/// Decodes `orchard_note_commitment_tree` values with note state version 1 and tree
+/// serialization version 2 or 3, the versions zcashd releases write. Other versions are
+/// reported as errors rather than guessed at.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct OrchardNoteCommitmentTreeDecoder;
+
+impl RecordDecoder for OrchardNoteCommitmentTreeDecoder {
+    type Item = OrchardNoteCommitmentTree;
+
+    fn decode(
+        &self,
+        _ctx: &DecodeContext,
+        key: Option<&RecordKey>,
+        raw_value: &[u8],
+    ) -> DecodeResult<OrchardNoteCommitmentTree> {
+        expect_bare(key, "orchard_note_commitment_tree")?;
+        let mut r = Reader::new(raw_value);
+        let client_version = ClientVersion(r.i32("client version")?);
+        let note_state_version = r.u8("note state version")?;
+        if note_state_version != NOTE_STATE_V1 {
+            return Err(DecodeError::new(format!(
+                "unsupported note state version {note_state_version}"
+            )));
+        }
+        let last_checkpoint = r.optional("last checkpoint", |r| r.u32("last checkpoint"))?;
+        let tree = BridgeTree::read(&mut r)?;
+        let notes = r.list(32 + 4 + 1, "note positions", |r| {
+            Ok(OrchardTxNotes {
+                txid: r.uint256("txid")?,
+                tx_height: r.u32("tx height")?,
+                note_positions: r.list(12, "action positions", |r| {
+                    Ok((r.u32("action index")?, r.u64("position")?))
+                })?,
+            })
+        })?;
+        r.finish()?;
+        Ok(OrchardNoteCommitmentTree {
+            client_version,
+            note_state_version,
+            last_checkpoint,
+            tree,
+            notes,
+        })
+    }
+
+    fn name(&self) -> &'static str {
+        "orchard_note_commitment_tree"
+    }
+}
@@ -0,0 +1,480 @@
+//! Sapling keys: `sapzkey`, `csapzkey` and `sapzkeymeta` records, keyed by the incoming
+//! viewing key, `sapextfvk` records of imported watch-only keys, and `sapzaddr` records,
+//! which map each address the wallet generated to the incoming viewing key it derives
+//! from.
+//!
+//! zcashd stores ZIP 32 extended keys: the derivation header (depth, parent tag, child
+//! index, chain code) followed by the expanded spending key or the full viewing key, and
+//! the diversifier key. Both serializations are 169 bytes.
+
+use crate::{
+    crypto::{
+        blake2b::blake2b,
+        blake2s::blake2s,
+        jubjub::{proof_generation_key_generator, spending_key_generator},
+    },
+    parser::{
+        address::bech32::{self, Variant},
+        decoders::{keymeta::KeyMetadata, network::ZcashNetwork},
+        key::RecordKey,
+        record::{DecodeContext, DecodeError, DecodeResult, RecordDecoder},
+        secret::SecretBytes,
+        serialize::{Reader, uint256_hex},
+    },
+};
+
+/// The serialized size of a Sapling extended spending or full viewing key.
+pub const EXTENDED_KEY_LEN: usize = 169;
+
+/// Set on the child index of hardened derivations.
+pub const HARDENED: u32 = 1 << 31;
+
+/// Where a ZIP 32 extended key sits in its derivation tree.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Zip32Header {
+    /// 0 for a master key.
+    pub depth: u8,
+    /// The first four bytes of the parent's full viewing key fingerprint.
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::util::serde_hex::bytes")
+    )]
+    pub parent_fvk_tag: [u8; 4],
+    pub child_index: u32,
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::util::serde_hex::bytes")
+    )]
+    pub chain_code: [u8; 32],
+}
+
+impl Zip32Header {
+    fn read(r: &mut Reader<'_>) -> DecodeResult<Self> {
+        Ok(Zip32Header {
+            depth: r.u8("depth")?,
+            parent_fvk_tag: r.array("parent fvk tag")?,
+            child_index: r.u32("child index")?,
+            chain_code: r.uint256("chain code")?,
+        })
+    }
+
+    fn write(&self, out: &mut Vec<u8>) {
+        out.push(self.depth);
+        out.extend_from_slice(&self.parent_fvk_tag);
+        out.extend_from_slice(&self.child_index.to_le_bytes());
+        out.extend_from_slice(&self.chain_code);
+    }
+
+    pub fn is_hardened(&self) -> bool {
+        self.child_index & HARDENED != 0
+    }
+
+    /// The child index without the hardened bit.
+    pub fn index(&self) -> u32 {
+        self.child_index & !HARDENED
+    }
+}
+
+/// zcashd's `SaplingExtendedSpendingKey`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct SaplingExtendedSpendingKey {
+    pub header: Zip32Header,
+    /// The spend authorizing key.
+    pub ask: SecretBytes,
+    /// The proof authorizing key.
+    pub nsk: SecretBytes,
+    /// The outgoing viewing key.
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::util::serde_hex::bytes")
+    )]
+    pub ovk: [u8; 32],
+    /// The diversifier key.
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::util::serde_hex::bytes")
+    )]
+    pub dk: [u8; 32],
+}
+
+impl SaplingExtendedSpendingKey {
+    /// Read a `SaplingExtendedSpendingKey` from `r`.
+    pub fn read(r: &mut Reader<'_>) -> DecodeResult<Self> {
+        Ok(SaplingExtendedSpendingKey {
+            header: Zip32Header::read(r)?,
+            ask: SecretBytes::from(r.bytes(32, "ask")?),
+            nsk: SecretBytes::from(r.bytes(32, "nsk")?),
+            ovk: r.uint256("ovk")?,
+            dk: r.uint256("dk")?,
+        })
+    }
+
+    /// The 169-byte serialization [`read`](Self::read) parses, as ZIP 32 and
+    /// `z_exportkey` encode it.
+    pub fn to_bytes(&self) -> SecretBytes {
+        let mut out = Vec::with_capacity(EXTENDED_KEY_LEN);
+        self.header.write(&mut out);
+        out.extend_from_slice(self.ask.expose());
+        out.extend_from_slice(self.nsk.expose());
+        out.extend_from_slice(&self.ovk);
+        out.extend_from_slice(&self.dk);
+        SecretBytes::new(out)
+    }
+
+    /// The full viewing key it derives: `ak = [ask] 𝒢` and `nk = [nsk] ℋ`, with the
+    /// header, `ovk` and `dk` copied over.
+    pub fn to_extfvk(&self) -> SaplingExtendedFullViewingKey {
+        SaplingExtendedFullViewingKey {
+            header: self.header,
+            ak: spending_key_generator()
+                .mul_le(self.ask.expose())
+                .to_bytes(),
+            nk: proof_generation_key_generator()
+                .mul_le(self.nsk.expose())
+                .to_bytes(),
+            ovk: self.ovk,
+            dk: self.dk,
+        }
+    }
+}
+
+/// zcashd's `SaplingExtendedFullViewingKey`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct SaplingExtendedFullViewingKey {
+    pub header: Zip32Header,
+    /// The spend validating key.
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::util::serde_hex::bytes")
+    )]
+    pub ak: [u8; 32],
+    /// The nullifier deriving key.
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::util::serde_hex::bytes")
+    )]
+    pub nk: [u8; 32],
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::util::serde_hex::bytes")
+    )]
+    pub ovk: [u8; 32],
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::util::serde_hex::bytes")
+    )]
+    pub dk: [u8; 32],
+}
+
+impl SaplingExtendedFullViewingKey {
+    /// Read a `SaplingExtendedFullViewingKey` from `r`.
+    pub fn read(r: &mut Reader<'_>) -> DecodeResult<Self> {
+        Ok(SaplingExtendedFullViewingKey {
+            header: Zip32Header::read(r)?,
+            ak: r.uint256("ak")?,
+            nk: r.uint256("nk")?,
+            ovk: r.uint256("ovk")?,
+            dk: r.uint256("dk")?,
+        })
+    }
+
+    /// The incoming viewing key: `CRH^ivk(ak, nk)`, BLAKE2s-256 of `ak || nk`
+    /// personalized `Zcashivk`, truncated to 251 bits. The key `sapzkey`, `csapzkey` and
+    /// `sapzaddr` records name it by.
+    pub fn ivk(&self) -> [u8; 32] {
+        let mut ivk: [u8; 32] = blake2s(32, b"Zcashivk", &[self.ak, self.nk].concat())
+            .try_into()
+            .unwrap();
+        ivk[31] &= 0x07;
+        ivk
+    }
+
+    /// The ZIP 32 fingerprint of the full viewing key: BLAKE2b-256 of `ak || nk || ovk`,
+    /// personalized `ZcashSaplingFVFP`. Children carry its first four bytes as their
+    /// parent tag, and zcashd derives the IV of the encrypted spending key from it.
+    pub fn fingerprint(&self) -> [u8; 32] {
+        let fvk = [self.ak, self.nk, self.ovk].concat();
+        blake2b(32, b"ZcashSaplingFVFP", &fvk).try_into().unwrap()
+    }
+}
+
+/// The incoming viewing key a Sapling key record is keyed by.
+fn ivk_of(key: Option<&RecordKey>, tag: &str) -> DecodeResult<[u8; 32]> {
+    match key {
+        Some(&RecordKey::SaplingIvk(ivk)) => Ok(ivk),
+        _ => Err(DecodeError::new(format!(
+            "{tag}: key is not an incoming viewing key"
+        ))),
+    }
+}
+
+/// A `sapzkey` record: an unencrypted Sapling extended spending key.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct SaplingSpendingKey {
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::util::serde_hex::bytes")
+    )]
+    pub ivk: [u8; 32],
+    pub key: SaplingExtendedSpendingKey,
+}
+
+impl SaplingSpendingKey {
+    /// The incoming viewing key the way zcashd prints it.
+    pub fn ivk_hex(&self) -> String {
+        uint256_hex(&self.ivk)
+    }
+}
+
+/// Decodes `sapzkey` values: a `SaplingExtendedSpendingKey`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SapZKeyDecoder;
+
+impl RecordDecoder for SapZKeyDecoder {
+    type Item = SaplingSpendingKey;
+
+    fn decode(
+        &self,
+        _ctx: &DecodeContext,
+        key: Option<&RecordKey>,
+        raw_value: &[u8],
+    ) -> DecodeResult<SaplingSpendingKey> {
+        let ivk = ivk_of(key, "sapzkey")?;
+        let mut r = Reader::new(raw_value);
+        let key = SaplingExtendedSpendingKey::read(&mut r)?;
+        r.finish()?;
+        Ok(SaplingSpendingKey { ivk, key })
+    }
+
+    fn name(&self) -> &'static str {
+        "sapzkey"
+    }
+}
+
+/// A `csapzkey` record: a Sapling extended spending key encrypted under the master key.
+/// The full viewing key is kept in the clear so a locked wallet can still scan.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct EncryptedSaplingKey {
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::util::serde_hex::bytes")
+    )]
+    pub ivk: [u8; 32],
+    pub extfvk: SaplingExtendedFullViewingKey,
+    /// The serialized extended spending key, AES-256-CBC encrypted with an IV derived
+    /// from the full viewing key.
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::util::serde_hex::bytes")
+    )]
+    pub encrypted_secret: Vec<u8>,
+}
+
+impl EncryptedSaplingKey {
+    pub fn ivk_hex(&self) -> String {
+        uint256_hex(&self.ivk)
+    }
+}
+
+/// Decodes `csapzkey` values: the `SaplingExtendedFullViewingKey`, then the
+/// compact-size-prefixed ciphertext, which must be whole AES blocks.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CSapZKeyDecoder;
+
+impl RecordDecoder for CSapZKeyDecoder {
+    type Item = EncryptedSaplingKey;
+
+    fn decode(
+        &self,
+        _ctx: &DecodeContext,
+        key: Option<&RecordKey>,
+        raw_value: &[u8],
+    ) -> DecodeResult<EncryptedSaplingKey> {
+        let ivk = ivk_of(key, "csapzkey")?;
+        let mut r = Reader::new(raw_value);
+        let extfvk = SaplingExtendedFullViewingKey::read(&mut r)?;
+        let encrypted_secret = r.var_bytes("encrypted secret")?.to_vec();
+        r.finish()?;
+        if encrypted_secret.is_empty() || encrypted_secret.len() % 16 != 0 {
+            return Err(DecodeError::new(format!(
+                "{}-byte ciphertext is not whole AES blocks",
+                encrypted_secret.len()
+            )));
+        }
+        Ok(EncryptedSaplingKey {
+            ivk,
+            extfvk,
+            encrypted_secret,
+        })
+    }
+
+    fn name(&self) -> &'static str {
+        "csapzkey"
+    }
+}
+
+/// A `sapzkeymeta` record: the creation time of a Sapling key and, for keys derived
+/// from the seed, its ZIP 32 path.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct SaplingKeyMetadata {
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::util::serde_hex::bytes")
+    )]
+    pub ivk: [u8; 32],
+    pub metadata: KeyMetadata,
+}
+
+/// Decodes `sapzkeymeta` values into a [`KeyMetadata`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SapZKeyMetaDecoder;
+
+impl RecordDecoder for SapZKeyMetaDecoder {
+    type Item = SaplingKeyMetadata;
+
+    fn decode(
+        &self,
+        _ctx: &DecodeContext,
+        key: Option<&RecordKey>,
+        raw_value: &[u8],
+    ) -> DecodeResult<SaplingKeyMetadata> {
+        let ivk = ivk_of(key, "sapzkeymeta")?;
+        let mut r = Reader::new(raw_value);
+        let metadata = KeyMetadata::read(&mut r)?;
+        r.finish()?;
+        Ok(SaplingKeyMetadata { ivk, metadata })
+    }
+
+    fn name(&self) -> &'static str {
+        "sapzkeymeta"
+    }
+}
+
+/// A `sapextfvk` record: a full viewing key imported with `z_importviewingkey`. It lets
+/// the wallet see incoming and outgoing notes, but not spend them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct SaplingWatchOnlyKey {
+    pub extfvk: SaplingExtendedFullViewingKey,
+}
+
+/// Decodes `sapextfvk` records. The key is the serialized
+/// `SaplingExtendedFullViewingKey`; the value only marks it present, as the byte `'1'`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SapExtFvkDecoder;
+
+impl RecordDecoder for SapExtFvkDecoder {
+    type Item = SaplingWatchOnlyKey;
+
+    fn decode(
+        &self,
+        _ctx: &DecodeContext,
+        key: Option<&RecordKey>,
+        raw_value: &[u8],
+    ) -> DecodeResult<SaplingWatchOnlyKey> {
+        let Some(RecordKey::SaplingExtFvk(extfvk)) = key else {
+            return Err(DecodeError::new("key is not an extended full viewing key"));
+        };
+        let mut r = Reader::new(extfvk);
+        let extfvk = SaplingExtendedFullViewingKey::read(&mut r)?;
+        r.finish()?;
+        if raw_value != b"1" {
+            return Err(DecodeError::new(format!(
+                "value is {}, expected '1'",
+                hex::encode(raw_value)
+            )));
+        }
+        Ok(SaplingWatchOnlyKey { extfvk })
+    }
+
+    fn name(&self) -> &'static str {
+        "sapextfvk"
+    }
+}
+
+/// A Sapling payment address: a diversifier and the diversified transmission key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct SaplingPaymentAddress {
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::util::serde_hex::bytes")
+    )]
+    pub diversifier: [u8; 11],
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::util::serde_hex::bytes")
+    )]
+    pub pk_d: [u8; 32],
+}
+
+impl SaplingPaymentAddress {
+    /// The Bech32 encoding on `network`: the diversifier, then `pk_d`.
+    pub fn encode(&self, network: ZcashNetwork) -> String {
+        let bytes = [&self.diversifier[..], &self.pk_d].concat();
+        let data = bech32::convert_bits(&bytes, 8, 5, true).unwrap();
+        bech32::encode(
+            network.sapling_payment_address_hrp(),
+            &data,
+            Variant::Bech32,
+        )
+    }
+}
+
+/// A `sapzaddr` record: one of the wallet's Sapling addresses. An incoming viewing key
+/// has many diversified addresses, and zcashd writes a record for each one it hands
+/// out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct SaplingAddressIvk {
+    pub address: SaplingPaymentAddress,
+    /// The key of the `sapzkey`, `csapzkey` or `sapextfvk` record the address
+    /// belongs to.
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::util::serde_hex::bytes")
+    )]
+    pub ivk: [u8; 32],
+}
+
+impl SaplingAddressIvk {
+    pub fn ivk_hex(&self) -> String {
+        uint256_hex(&self.ivk)
+    }
+}
+
+/// Decodes `sapzaddr` values: the incoming viewing key.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SapZAddrDecoder;
+
+impl RecordDecoder for SapZAddrDecoder {
+    type Item = SaplingAddressIvk;
+
+    fn decode(
+        &self,
+        _ctx: &DecodeContext,
+        key: Option<&RecordKey>,
+        raw_value: &[u8],
+    ) -> DecodeResult<SaplingAddressIvk> {
+        let Some(&RecordKey::SaplingAddress { diversifier, pk_d }) = key else {
+            return Err(DecodeError::new("key is not a Sapling address"));
+        };
+        let mut r = Reader::new(raw_value);
+        let ivk = r.uint256("incoming viewing key")?;
+        r.finish()?;
+        Ok(SaplingAddressIvk {
+            address: SaplingPaymentAddress { diversifier, pk_d },
+            ivk,
+        })
+    }
+
+    fn name(&self) -> &'static str {
+        "sapzaddr"
+    }
+}
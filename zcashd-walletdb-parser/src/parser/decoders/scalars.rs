@@ -0,0 +1,178 @@
+//! Single-value records keyed by their tag alone: `defaultkey`, `version`, `minversion`,
+//! `orderposnext` and `witnesscachesize`.
+
+use std::fmt;
+
+use crate::parser::{
+    key::RecordKey,
+    record::{DecodeContext, DecodeError, DecodeResult, RecordDecoder},
+    serialize::Reader,
+};
+
+/// A zcashd `CLIENT_VERSION`: `1_000_000 * major + 10_000 * minor + 100 * revision +
+/// build`, where builds below 25 are betas, below 50 release candidates, 50 the release
+/// and above it patch releases.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ClientVersion(pub i32);
+
+impl ClientVersion {
+    pub fn major(self) -> i32 {
+        self.0 / 1_000_000
+    }
+
+    pub fn minor(self) -> i32 {
+        self.0 / 10_000 % 100
+    }
+
+    pub fn revision(self) -> i32 {
+        self.0 / 100 % 100
+    }
+
+    pub fn build(self) -> i32 {
+        self.0 % 100
+    }
+}
+
+/// v2.0.0, the Sapling release: the first to write an `hdseed` and Sapling keys.
+pub const SAPLING_WALLET_VERSION: ClientVersion = ClientVersion(2_000_050);
+
+/// v4.7.0: the first to write seed phrases, unified accounts and Orchard wallet state.
+pub const NU5_WALLET_VERSION: ClientVersion = ClientVersion(4_070_050);
+
+/// Formatted as zcashd's `FormatVersion` does, e.g. `v5.6.0` or `v6.0.0-rc1`.
+impl fmt::Display for ClientVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "v{}.{}.{}", self.major(), self.minor(), self.revision())?;
+        match self.build() {
+            b @ 0..25 => write!(f, "-beta{}", b + 1),
+            b @ 25..50 => write!(f, "-rc{}", b - 24),
+            50 => Ok(()),
+            b => write!(f, "-{}", b - 50),
+        }
+    }
+}
+
+/// A `defaultkey` record: the public key the wallet hands out by default.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct DefaultKey {
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::util::serde_hex::bytes")
+    )]
+    pub pubkey: Vec<u8>,
+}
+
+/// Fail if a singleton record's key carries anything after the tag.
+pub(crate) fn expect_bare(key: Option<&RecordKey>, tag: &str) -> DecodeResult<()> {
+    match key {
+        None => Ok(()),
+        Some(_) => Err(DecodeError::new(format!(
+            "{tag}: key has data after the tag"
+        ))),
+    }
+}
+
+/// Decodes `defaultkey` values: a serialized `CPubKey`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DefaultKeyDecoder;
+
+impl RecordDecoder for DefaultKeyDecoder {
+    type Item = DefaultKey;
+
+    fn decode(
+        &self,
+        _ctx: &DecodeContext,
+        key: Option<&RecordKey>,
+        raw_value: &[u8],
+    ) -> DecodeResult<DefaultKey> {
+        expect_bare(key, "defaultkey")?;
+        let mut r = Reader::new(raw_value);
+        let pubkey = r.var_bytes("public key")?.to_vec();
+        r.finish()?;
+        Ok(DefaultKey { pubkey })
+    }
+
+    fn name(&self) -> &'static str {
+        "defaultkey"
+    }
+}
+
+/// Decodes `version` and `minversion` values: an `i32` client version. `minversion`
+/// holds a wallet feature level in the same encoding, e.g. `60000` for compressed keys.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct VersionDecoder;
+
+impl RecordDecoder for VersionDecoder {
+    type Item = ClientVersion;
+
+    fn decode(
+        &self,
+        _ctx: &DecodeContext,
+        key: Option<&RecordKey>,
+        raw_value: &[u8],
+    ) -> DecodeResult<ClientVersion> {
+        expect_bare(key, "version")?;
+        let mut r = Reader::new(raw_value);
+        let version = ClientVersion(r.i32("version")?);
+        r.finish()?;
+        Ok(version)
+    }
+
+    fn name(&self) -> &'static str {
+        "version"
+    }
+}
+
+/// Decodes `orderposnext` values: the `i64` order position the next transaction or
+/// accounting entry gets.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct OrderPosNextDecoder;
+
+impl RecordDecoder for OrderPosNextDecoder {
+    type Item = i64;
+
+    fn decode(
+        &self,
+        _ctx: &DecodeContext,
+        key: Option<&RecordKey>,
+        raw_value: &[u8],
+    ) -> DecodeResult<i64> {
+        expect_bare(key, "orderposnext")?;
+        let mut r = Reader::new(raw_value);
+        let next = r.i64("order position")?;
+        r.finish()?;
+        Ok(next)
+    }
+
+    fn name(&self) -> &'static str {
+        "orderposnext"
+    }
+}
+
+/// Decodes `witnesscachesize` values: the `i64` number of blocks of witnesses zcashd
+/// keeps per note, and so how far back a reorg can go without a rescan.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct WitnessCacheSizeDecoder;
+
+impl RecordDecoder for WitnessCacheSizeDecoder {
+    type Item = i64;
+
+    fn decode(
+        &self,
+        _ctx: &DecodeContext,
+        key: Option<&RecordKey>,
+        raw_value: &[u8],
+    ) -> DecodeResult<i64> {
+        expect_bare(key, "witnesscachesize")?;
+        let mut r = Reader::new(raw_value);
+        let size = r.i64("witness cache size")?;
+        r.finish()?;
+        Ok(size)
+    }
+
+    fn name(&self) -> &'static str {
+        "witnesscachesize"
+    }
+}
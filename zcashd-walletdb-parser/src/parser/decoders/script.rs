@@ -0,0 +1,226 @@
+//! Scripts: `watchs` records, keyed by a watch-only script, and `cscript` records, keyed
+//! by the hash160 of a redeem script (its `CScriptID`).
+//!
+//! Classification follows zcashd's `Solver` for the templates a wallet can hold: pay to
+//! public key, pay to public key hash, pay to script hash and bare multisig.
+
+use std::fmt;
+
+use crate::parser::{
+    key::RecordKey,
+    record::{DecodeContext, DecodeError, DecodeResult, RecordDecoder},
+    serialize::Reader,
+};
+
+const OP_1: u8 = 0x51;
+const OP_16: u8 = 0x60;
+const OP_RETURN: u8 = 0x6a;
+const OP_DUP: u8 = 0x76;
+const OP_EQUAL: u8 = 0x87;
+const OP_EQUALVERIFY: u8 = 0x88;
+const OP_HASH160: u8 = 0xa9;
+const OP_CHECKSIG: u8 = 0xac;
+const OP_CHECKMULTISIG: u8 = 0xae;
+
+/// The standard template a script matches.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum ScriptKind {
+    /// `<pubkey> OP_CHECKSIG`.
+    PubKey(
+        #[cfg_attr(
+            feature = "serde",
+            serde(serialize_with = "crate::util::serde_hex::bytes")
+        )]
+        Vec<u8>,
+    ),
+    /// `OP_DUP OP_HASH160 <hash> OP_EQUALVERIFY OP_CHECKSIG`.
+    PubKeyHash(
+        #[cfg_attr(
+            feature = "serde",
+            serde(serialize_with = "crate::util::serde_hex::bytes")
+        )]
+        [u8; 20],
+    ),
+    /// `OP_HASH160 <hash> OP_EQUAL`.
+    ScriptHash(
+        #[cfg_attr(
+            feature = "serde",
+            serde(serialize_with = "crate::util::serde_hex::bytes")
+        )]
+        [u8; 20],
+    ),
+    /// `OP_m <pubkey>... OP_n OP_CHECKMULTISIG`.
+    Multisig {
+        required: u8,
+        #[cfg_attr(
+            feature = "serde",
+            serde(serialize_with = "crate::util::serde_hex::byte_list")
+        )]
+        pubkeys: Vec<Vec<u8>>,
+    },
+    /// `OP_RETURN` followed by data.
+    NullData,
+    NonStandard,
+}
+
+impl ScriptKind {
+    /// Classify `script`.
+    pub fn of(script: &[u8]) -> Self {
+        match script {
+            [
+                OP_DUP,
+                OP_HASH160,
+                20,
+                hash @ ..,
+                OP_EQUALVERIFY,
+                OP_CHECKSIG,
+            ] if hash.len() == 20 => ScriptKind::PubKeyHash(hash.try_into().unwrap()),
+            [OP_HASH160, 20, hash @ .., OP_EQUAL] if hash.len() == 20 => {
+                ScriptKind::ScriptHash(hash.try_into().unwrap())
+            }
+            [len @ (33 | 65), pubkey @ .., OP_CHECKSIG] if pubkey.len() == *len as usize => {
+                ScriptKind::PubKey(pubkey.to_vec())
+            }
+            [OP_RETURN, ..] => ScriptKind::NullData,
+            [
+                m @ OP_1..=OP_16,
+                body @ ..,
+                n @ OP_1..=OP_16,
+                OP_CHECKMULTISIG,
+            ] => multisig(small_int(*m), small_int(*n), body).unwrap_or(ScriptKind::NonStandard),
+            _ => ScriptKind::NonStandard,
+        }
+    }
+}
+
+/// The value of `OP_1` to `OP_16`.
+fn small_int(op: u8) -> u8 {
+    op - OP_1 + 1
+}
+
+/// Split the body of a bare multisig script into its `n` public keys, requiring `m` of
+/// them to sign.
+fn multisig(m: u8, n: u8, mut body: &[u8]) -> Option<ScriptKind> {
+    let mut pubkeys = Vec::new();
+    while let [len @ (33 | 65), rest @ ..] = body {
+        let (pubkey, rest) = rest.split_at_checked(*len as usize)?;
+        pubkeys.push(pubkey.to_vec());
+        body = rest;
+    }
+    (body.is_empty() && m <= n && pubkeys.len() == n as usize).then_some(ScriptKind::Multisig {
+        required: m,
+        pubkeys,
+    })
+}
+
+impl fmt::Display for ScriptKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScriptKind::PubKey(_) => f.write_str("pubkey"),
+            ScriptKind::PubKeyHash(_) => f.write_str("pubkeyhash"),
+            ScriptKind::ScriptHash(_) => f.write_str("scripthash"),
+            ScriptKind::Multisig { required, pubkeys } => {
+                write!(f, "multisig {required}-of-{}", pubkeys.len())
+            }
+            ScriptKind::NullData => f.write_str("nulldata"),
+            ScriptKind::NonStandard => f.write_str("nonstandard"),
+        }
+    }
+}
+
+/// A `watchs` record: a script the wallet watches without holding its keys, added by
+/// `importaddress` or `importpubkey`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct WatchScript {
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::util::serde_hex::bytes")
+    )]
+    pub script: Vec<u8>,
+    pub kind: ScriptKind,
+}
+
+/// Decodes `watchs` records. The key is the script; the value only marks it present, as
+/// the byte `'1'`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct WatchsDecoder;
+
+impl RecordDecoder for WatchsDecoder {
+    type Item = WatchScript;
+
+    fn decode(
+        &self,
+        _ctx: &DecodeContext,
+        key: Option<&RecordKey>,
+        raw_value: &[u8],
+    ) -> DecodeResult<WatchScript> {
+        let Some(RecordKey::Script(script)) = key else {
+            return Err(DecodeError::new("key is not a script"));
+        };
+        if raw_value != b"1" {
+            return Err(DecodeError::new(format!(
+                "value is {}, expected '1'",
+                hex::encode(raw_value)
+            )));
+        }
+        Ok(WatchScript {
+            script: script.clone(),
+            kind: ScriptKind::of(script),
+        })
+    }
+
+    fn name(&self) -> &'static str {
+        "watchs"
+    }
+}
+
+/// A `cscript` record: a redeem script, by the hash P2SH outputs pay to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct RedeemScript {
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::util::serde_hex::bytes")
+    )]
+    pub script_id: [u8; 20],
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::util::serde_hex::bytes")
+    )]
+    pub script: Vec<u8>,
+    pub kind: ScriptKind,
+}
+
+/// Decodes `cscript` values: the compact-size-prefixed script.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CScriptDecoder;
+
+impl RecordDecoder for CScriptDecoder {
+    type Item = RedeemScript;
+
+    fn decode(
+        &self,
+        _ctx: &DecodeContext,
+        key: Option<&RecordKey>,
+        raw_value: &[u8],
+    ) -> DecodeResult<RedeemScript> {
+        let Some(&RecordKey::ScriptHash(script_id)) = key else {
+            return Err(DecodeError::new("key is not a script hash"));
+        };
+        let mut r = Reader::new(raw_value);
+        let script = r.var_bytes("script")?.to_vec();
+        r.finish()?;
+        let kind = ScriptKind::of(&script);
+        Ok(RedeemScript {
+            script_id,
+            script,
+            kind,
+        })
+    }
+
+    fn name(&self) -> &'static str {
+        "cscript"
+    }
+}
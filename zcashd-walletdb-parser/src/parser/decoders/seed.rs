@@ -0,0 +1,336 @@
+//! Wallet seeds, all keyed by the seed fingerprint: the legacy `hdseed` and `chdseed`
+//! records, and the `mnemonicphrase` and `cmnemonicphrase` records of wallets created
+//! since zcashd 4.7, whose seed is derived from a ZIP 339 (BIP 39) phrase.
+//!
+//! The fingerprint (a BLAKE2b-256 of the seed, personalized `Zcash_HD_Seed_FP`) is what
+//! ties keys to a seed: every HD key's metadata names it, so it is shown in full while
+//! the seed or phrase itself is redacted.
+
+use std::fmt;
+
+use crate::{
+    crypto::blake2b::blake2b,
+    entry::parser::write_compact_size,
+    parser::{
+        key::RecordKey,
+        record::{DecodeContext, DecodeError, DecodeResult, RecordDecoder},
+        secret::{SecretBytes, SecretString},
+        serialize::{Reader, uint256_hex},
+    },
+};
+
+/// The personalization of seed fingerprints.
+const SEED_FP_PERSONALIZATION: &[u8; 16] = b"Zcash_HD_Seed_FP";
+
+/// The fingerprint of `seed`: BLAKE2b-256 of its compact-size-prefixed serialization,
+/// as `HDSeed::Fingerprint` computes it.
+pub fn seed_fingerprint(seed: &[u8]) -> [u8; 32] {
+    let mut serialized = Vec::with_capacity(seed.len() + 1);
+    write_compact_size(&mut serialized, seed.len() as u64);
+    serialized.extend_from_slice(seed);
+    blake2b(32, SEED_FP_PERSONALIZATION, &serialized)
+        .try_into()
+        .unwrap()
+}
+
+/// The fingerprint a seed record is keyed by.
+fn fingerprint_of(key: Option<&RecordKey>, tag: &str) -> DecodeResult<[u8; 32]> {
+    match key {
+        Some(&RecordKey::SeedFingerprint(fingerprint)) => Ok(fingerprint),
+        _ => Err(DecodeError::new(format!(
+            "{tag}: key is not a seed fingerprint"
+        ))),
+    }
+}
+
+/// An `hdseed` record: the legacy HD seed, stored in the clear.
+#[derive(Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct HdSeed {
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::util::serde_hex::bytes")
+    )]
+    pub fingerprint: [u8; 32],
+    pub seed: SecretBytes,
+}
+
+impl fmt::Debug for HdSeed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HdSeed")
+            .field("fingerprint", &self.fingerprint_hex())
+            .field("seed", &self.seed)
+            .finish()
+    }
+}
+
+impl HdSeed {
+    /// The fingerprint the way zcashd prints it.
+    pub fn fingerprint_hex(&self) -> String {
+        uint256_hex(&self.fingerprint)
+    }
+}
+
+impl fmt::Display for HdSeed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "HdSeed {{")?;
+        writeln!(f, "  fingerprint  : {}", self.fingerprint_hex())?;
+        writeln!(f, "  seed         : {} bytes, redacted", self.seed.len())?;
+        write!(f, "}}")
+    }
+}
+
+/// Decodes `hdseed` values: the compact-size-prefixed raw seed.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct HdSeedDecoder;
+
+impl RecordDecoder for HdSeedDecoder {
+    type Item = HdSeed;
+
+    fn decode(
+        &self,
+        _ctx: &DecodeContext,
+        key: Option<&RecordKey>,
+        raw_value: &[u8],
+    ) -> DecodeResult<HdSeed> {
+        let fingerprint = fingerprint_of(key, "hdseed")?;
+        let mut r = Reader::new(raw_value);
+        let seed = SecretBytes::from(r.var_bytes("seed")?);
+        r.finish()?;
+        Ok(HdSeed { fingerprint, seed })
+    }
+
+    fn name(&self) -> &'static str {
+        "hdseed"
+    }
+}
+
+/// A `chdseed` record: the legacy HD seed, encrypted under the master key.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct EncryptedHdSeed {
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::util::serde_hex::bytes")
+    )]
+    pub fingerprint: [u8; 32],
+    /// AES-256-CBC encrypted with an IV derived from the fingerprint.
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::util::serde_hex::bytes")
+    )]
+    pub encrypted_seed: Vec<u8>,
+}
+
+impl EncryptedHdSeed {
+    pub fn fingerprint_hex(&self) -> String {
+        uint256_hex(&self.fingerprint)
+    }
+}
+
+impl fmt::Display for EncryptedHdSeed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "EncryptedHdSeed {{")?;
+        writeln!(f, "  fingerprint  : {}", self.fingerprint_hex())?;
+        writeln!(f, "  encrypted    : {} bytes", self.encrypted_seed.len())?;
+        write!(f, "}}")
+    }
+}
+
+/// Decodes `chdseed` values: the compact-size-prefixed ciphertext.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CHdSeedDecoder;
+
+impl RecordDecoder for CHdSeedDecoder {
+    type Item = EncryptedHdSeed;
+
+    fn decode(
+        &self,
+        _ctx: &DecodeContext,
+        key: Option<&RecordKey>,
+        raw_value: &[u8],
+    ) -> DecodeResult<EncryptedHdSeed> {
+        let fingerprint = fingerprint_of(key, "chdseed")?;
+        let mut r = Reader::new(raw_value);
+        let encrypted_seed = r.var_bytes("encrypted seed")?.to_vec();
+        r.finish()?;
+        Ok(EncryptedHdSeed {
+            fingerprint,
+            encrypted_seed,
+        })
+    }
+
+    fn name(&self) -> &'static str {
+        "chdseed"
+    }
+}
+
+/// The wordlist a mnemonic phrase is written in, as zcashd numbers ZIP 339 languages.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum Language {
+    English,
+    SimplifiedChinese,
+    TraditionalChinese,
+    Czech,
+    French,
+    Italian,
+    Japanese,
+    Korean,
+    Portuguese,
+    Spanish,
+    Unknown(u32),
+}
+
+impl From<u32> for Language {
+    fn from(code: u32) -> Self {
+        match code {
+            0 => Language::English,
+            1 => Language::SimplifiedChinese,
+            2 => Language::TraditionalChinese,
+            3 => Language::Czech,
+            4 => Language::French,
+            5 => Language::Italian,
+            6 => Language::Japanese,
+            7 => Language::Korean,
+            8 => Language::Portuguese,
+            9 => Language::Spanish,
+            other => Language::Unknown(other),
+        }
+    }
+}
+
+impl fmt::Display for Language {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Language::English => f.write_str("English"),
+            Language::SimplifiedChinese => f.write_str("Simplified Chinese"),
+            Language::TraditionalChinese => f.write_str("Traditional Chinese"),
+            Language::Czech => f.write_str("Czech"),
+            Language::French => f.write_str("French"),
+            Language::Italian => f.write_str("Italian"),
+            Language::Japanese => f.write_str("Japanese"),
+            Language::Korean => f.write_str("Korean"),
+            Language::Portuguese => f.write_str("Portuguese"),
+            Language::Spanish => f.write_str("Spanish"),
+            Language::Unknown(code) => write!(f, "unknown ({code})"),
+        }
+    }
+}
+
+/// A `mnemonicphrase` record: the wallet's seed phrase, stored in the clear.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct MnemonicPhrase {
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::util::serde_hex::bytes")
+    )]
+    pub fingerprint: [u8; 32],
+    pub language: Language,
+    pub phrase: SecretString,
+}
+
+impl MnemonicPhrase {
+    pub fn fingerprint_hex(&self) -> String {
+        uint256_hex(&self.fingerprint)
+    }
+
+    /// The number of words, which can be shown without revealing any of them.
+    pub fn word_count(&self) -> usize {
+        self.phrase.expose().split_whitespace().count()
+    }
+}
+
+impl fmt::Display for MnemonicPhrase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "MnemonicPhrase {{")?;
+        writeln!(f, "  fingerprint  : {}", self.fingerprint_hex())?;
+        writeln!(f, "  language     : {}", self.language)?;
+        writeln!(f, "  phrase       : {} words, redacted", self.word_count())?;
+        write!(f, "}}")
+    }
+}
+
+/// Decodes `mnemonicphrase` values: the `u32` language, then the phrase as a
+/// compact-size-prefixed string.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MnemonicPhraseDecoder;
+
+impl RecordDecoder for MnemonicPhraseDecoder {
+    type Item = MnemonicPhrase;
+
+    fn decode(
+        &self,
+        _ctx: &DecodeContext,
+        key: Option<&RecordKey>,
+        raw_value: &[u8],
+    ) -> DecodeResult<MnemonicPhrase> {
+        let fingerprint = fingerprint_of(key, "mnemonicphrase")?;
+        let mut r = Reader::new(raw_value);
+        let language = Language::from(r.u32("language")?);
+        let phrase = SecretString::new(r.string("phrase")?);
+        r.finish()?;
+        Ok(MnemonicPhrase {
+            fingerprint,
+            language,
+            phrase,
+        })
+    }
+
+    fn name(&self) -> &'static str {
+        "mnemonicphrase"
+    }
+}
+
+/// A `cmnemonicphrase` record: the seed phrase, encrypted under the master key.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct EncryptedMnemonicPhrase {
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::util::serde_hex::bytes")
+    )]
+    pub fingerprint: [u8; 32],
+    /// The serialized language and phrase, AES-256-CBC encrypted with an IV derived
+    /// from the fingerprint.
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::util::serde_hex::bytes")
+    )]
+    pub encrypted_phrase: Vec<u8>,
+}
+
+impl EncryptedMnemonicPhrase {
+    pub fn fingerprint_hex(&self) -> String {
+        uint256_hex(&self.fingerprint)
+    }
+}
+
+/// Decodes `cmnemonicphrase` values: the compact-size-prefixed ciphertext.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CMnemonicPhraseDecoder;
+
+impl RecordDecoder for CMnemonicPhraseDecoder {
+    type Item = EncryptedMnemonicPhrase;
+
+    fn decode(
+        &self,
+        _ctx: &DecodeContext,
+        key: Option<&RecordKey>,
+        raw_value: &[u8],
+    ) -> DecodeResult<EncryptedMnemonicPhrase> {
+        let fingerprint = fingerprint_of(key, "cmnemonicphrase")?;
+        let mut r = Reader::new(raw_value);
+        let encrypted_phrase = r.var_bytes("encrypted phrase")?.to_vec();
+        r.finish()?;
+        Ok(EncryptedMnemonicPhrase {
+            fingerprint,
+            encrypted_phrase,
+        })
+    }
+
+    fn name(&self) -> &'static str {
+        "cmnemonicphrase"
+    }
+}
@@ -0,0 +1,263 @@
+//! Sprout keys: `zkey`, `czkey`, `zkeymeta` and `vkey` records, keyed by the payment
+//! address.
+//!
+//! A Sprout spending key is the 252-bit `a_sk`, serialized as 32 bytes whose top four
+//! bits are zero. The payment address pairs `a_pk`, derived from `a_sk`, with the
+//! transmission key `pk_enc`; a viewing key pairs `a_pk` with the receiving key `sk_enc`
+//! that decrypts notes sent to the address.
+
+use crate::{
+    crypto::sha256::sha256_compress,
+    parser::{
+        address::base58,
+        decoders::{keymeta::KeyMetadata, network::ZcashNetwork},
+        key::RecordKey,
+        record::{DecodeContext, DecodeError, DecodeResult, RecordDecoder},
+        secret::{SecretBytes, SecretString},
+        serialize::{Reader, uint256_hex},
+    },
+};
+
+/// A Sprout payment address.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct SproutPaymentAddress {
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::util::serde_hex::bytes")
+    )]
+    pub a_pk: [u8; 32],
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::util::serde_hex::bytes")
+    )]
+    pub pk_enc: [u8; 32],
+}
+
+impl SproutPaymentAddress {
+    pub fn a_pk_hex(&self) -> String {
+        uint256_hex(&self.a_pk)
+    }
+
+    /// The Base58Check encoding on `network`: the prefix, `a_pk` and `pk_enc`.
+    pub fn encode(&self, network: ZcashNetwork) -> String {
+        let prefix = network.b58_sprout_address_prefix();
+        base58::encode_check(&[&prefix[..], &self.a_pk, &self.pk_enc].concat())
+    }
+}
+
+/// The payment address a Sprout record is keyed by.
+fn address_of(key: Option<&RecordKey>, tag: &str) -> DecodeResult<SproutPaymentAddress> {
+    match key {
+        Some(&RecordKey::SproutAddress { a_pk, pk_enc }) => {
+            Ok(SproutPaymentAddress { a_pk, pk_enc })
+        }
+        _ => Err(DecodeError::new(format!(
+            "{tag}: key is not a Sprout payment address"
+        ))),
+    }
+}
+
+/// A `zkey` record: an unencrypted Sprout spending key.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct SproutSpendingKey {
+    pub address: SproutPaymentAddress,
+    /// The 32-byte serialization of `a_sk`.
+    pub a_sk: SecretBytes,
+}
+
+impl SproutSpendingKey {
+    /// `a_pk = PRF^addr_{a_sk}(0)`: SHA256Compress of `1100 || a_sk || 0^256`.
+    pub fn derive_a_pk(&self) -> [u8; 32] {
+        let mut block = [0; 64];
+        block[..32].copy_from_slice(self.a_sk.expose());
+        block[0] = (block[0] & 0x0f) | 0xc0;
+        sha256_compress(&block)
+    }
+
+    /// The Base58Check encoding on `network`, as `z_exportkey` prints it.
+    pub fn encode(&self, network: ZcashNetwork) -> SecretString {
+        let prefix = network.b58_sprout_spending_key_prefix();
+        let payload = SecretBytes::new([&prefix[..], self.a_sk.expose()].concat());
+        SecretString::new(base58::encode_check(payload.expose()))
+    }
+}
+
+/// Decodes `zkey` values: the serialized `a_sk`, which must fit in 252 bits.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ZKeyDecoder;
+
+impl RecordDecoder for ZKeyDecoder {
+    type Item = SproutSpendingKey;
+
+    fn decode(
+        &self,
+        _ctx: &DecodeContext,
+        key: Option<&RecordKey>,
+        raw_value: &[u8],
+    ) -> DecodeResult<SproutSpendingKey> {
+        let address = address_of(key, "zkey")?;
+        let mut r = Reader::new(raw_value);
+        let a_sk = r.bytes(32, "a_sk")?;
+        r.finish()?;
+        if a_sk[0] & 0xf0 != 0 {
+            return Err(DecodeError::new("a_sk does not fit in 252 bits"));
+        }
+        Ok(SproutSpendingKey {
+            address,
+            a_sk: SecretBytes::from(a_sk),
+        })
+    }
+
+    fn name(&self) -> &'static str {
+        "zkey"
+    }
+}
+
+/// A `czkey` record: a Sprout spending key encrypted under the master key. The receiving
+/// key is kept in the clear so a locked wallet can still detect incoming notes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct EncryptedSproutKey {
+    pub address: SproutPaymentAddress,
+    /// `sk_enc`, which decrypts notes sent to the address.
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::util::serde_hex::bytes")
+    )]
+    pub receiving_key: [u8; 32],
+    /// The serialized `a_sk`, AES-256-CBC encrypted with an IV derived from the address.
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::util::serde_hex::bytes")
+    )]
+    pub encrypted_secret: Vec<u8>,
+}
+
+/// Decodes `czkey` values: the receiving key, then the compact-size-prefixed
+/// ciphertext, which must be whole AES blocks.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CZKeyDecoder;
+
+impl RecordDecoder for CZKeyDecoder {
+    type Item = EncryptedSproutKey;
+
+    fn decode(
+        &self,
+        _ctx: &DecodeContext,
+        key: Option<&RecordKey>,
+        raw_value: &[u8],
+    ) -> DecodeResult<EncryptedSproutKey> {
+        let address = address_of(key, "czkey")?;
+        let mut r = Reader::new(raw_value);
+        let receiving_key = r.uint256("receiving key")?;
+        let encrypted_secret = r.var_bytes("encrypted secret")?.to_vec();
+        r.finish()?;
+        if encrypted_secret.is_empty() || encrypted_secret.len() % 16 != 0 {
+            return Err(DecodeError::new(format!(
+                "{}-byte ciphertext is not whole AES blocks",
+                encrypted_secret.len()
+            )));
+        }
+        Ok(EncryptedSproutKey {
+            address,
+            receiving_key,
+            encrypted_secret,
+        })
+    }
+
+    fn name(&self) -> &'static str {
+        "czkey"
+    }
+}
+
+/// A `zkeymeta` record: the metadata of one Sprout key. Sprout keys are never derived
+/// from the seed, so only the creation time is meaningful.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct SproutKeyMetadata {
+    pub address: SproutPaymentAddress,
+    pub metadata: KeyMetadata,
+}
+
+/// Decodes `zkeymeta` values into a [`KeyMetadata`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ZKeyMetaDecoder;
+
+impl RecordDecoder for ZKeyMetaDecoder {
+    type Item = SproutKeyMetadata;
+
+    fn decode(
+        &self,
+        _ctx: &DecodeContext,
+        key: Option<&RecordKey>,
+        raw_value: &[u8],
+    ) -> DecodeResult<SproutKeyMetadata> {
+        let address = address_of(key, "zkeymeta")?;
+        let mut r = Reader::new(raw_value);
+        let metadata = KeyMetadata::read(&mut r)?;
+        r.finish()?;
+        Ok(SproutKeyMetadata { address, metadata })
+    }
+
+    fn name(&self) -> &'static str {
+        "zkeymeta"
+    }
+}
+
+/// A `vkey` record: a Sprout viewing key, imported with `z_importviewingkey`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct SproutViewingKey {
+    pub address: SproutPaymentAddress,
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::util::serde_hex::bytes")
+    )]
+    pub a_pk: [u8; 32],
+    /// `sk_enc`, which decrypts notes sent to the address.
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::util::serde_hex::bytes")
+    )]
+    pub receiving_key: [u8; 32],
+}
+
+/// Decodes `vkey` values: `a_pk` and the receiving key. `a_pk` must be the one of the
+/// address the record is keyed by.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct VKeyDecoder;
+
+impl RecordDecoder for VKeyDecoder {
+    type Item = SproutViewingKey;
+
+    fn decode(
+        &self,
+        _ctx: &DecodeContext,
+        key: Option<&RecordKey>,
+        raw_value: &[u8],
+    ) -> DecodeResult<SproutViewingKey> {
+        let address = address_of(key, "vkey")?;
+        let mut r = Reader::new(raw_value);
+        let a_pk = r.uint256("a_pk")?;
+        let receiving_key = r.uint256("receiving key")?;
+        r.finish()?;
+        if a_pk != address.a_pk {
+            return Err(DecodeError::new(format!(
+                "viewing key has a_pk {}, record is keyed by {}",
+                uint256_hex(&a_pk),
+                address.a_pk_hex()
+            )));
+        }
+        Ok(SproutViewingKey {
+            address,
+            a_pk,
+            receiving_key,
+        })
+    }
+
+    fn name(&self) -> &'static str {
+        "vkey"
+    }
+}
@@ -0,0 +1,290 @@
+//! Transparent keys: `key`, `wkey` and `ckey` records, keyed by the serialized public key.
+//!
+//! zcashd stores a private key as `CPrivKey`, the DER `ECPrivateKey` structure libsecp256k1
+//! exports: the 32-byte secret, the curve parameters, and the public key, compressed or
+//! not. `key` values follow it with `Hash(pubkey || privkey)`, which the oldest wallets,
+//! written by Bitcoin versions before the fork, lack.
+
+use crate::{
+    crypto::sha256::sha256d,
+    parser::{
+        key::RecordKey,
+        record::{DecodeContext, DecodeError, DecodeResult, RecordDecoder},
+        secret::SecretBytes,
+        serialize::Reader,
+    },
+};
+
+/// A `CPrivKey`: a DER-encoded secp256k1 private key.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct PrivateKey {
+    /// The encoding as stored, which contains the secret.
+    pub der: SecretBytes,
+    /// The 32-byte secret scalar.
+    pub secret: SecretBytes,
+    /// The public key embedded in the encoding.
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::util::serde_hex::bytes")
+    )]
+    pub pubkey: Vec<u8>,
+}
+
+impl PrivateKey {
+    /// Parse the DER `ECPrivateKey` structure: a sequence of the version `1`, the secret
+    /// as an octet string, the `[0]` curve parameters and the `[1]` public key.
+    pub fn parse(der: &[u8]) -> DecodeResult<Self> {
+        let mut r = Reader::new(der);
+        let body = der_element(&mut r, 0x30, "private key")?;
+        r.finish()?;
+        let mut r = Reader::new(body);
+        if der_element(&mut r, 0x02, "version")? != [1] {
+            return Err(DecodeError::new("private key: version is not 1"));
+        }
+        let secret = der_element(&mut r, 0x04, "secret")?;
+        if secret.len() != 32 {
+            return Err(DecodeError::new("private key: secret is not 32 bytes"));
+        }
+        let mut pubkey = None;
+        while !r.is_empty() {
+            let tag = r.u8("tag")?;
+            let len = der_length(&mut r)?;
+            let content = r.bytes(len, "element")?;
+            if tag == 0xa1 {
+                let mut r = Reader::new(content);
+                let bits = der_element(&mut r, 0x03, "public key")?;
+                match bits.split_first() {
+                    Some((0, key)) => pubkey = Some(key.to_vec()),
+                    _ => return Err(DecodeError::new("private key: malformed public key")),
+                }
+            }
+        }
+        let pubkey = pubkey.ok_or_else(|| DecodeError::new("private key: no public key"))?;
+        Ok(PrivateKey {
+            der: SecretBytes::from(der),
+            secret: SecretBytes::from(secret),
+            pubkey,
+        })
+    }
+
+    pub fn is_compressed(&self) -> bool {
+        self.pubkey.len() == 33
+    }
+}
+
+/// A DER length: short form, or long form in up to two bytes, which covers every key
+/// encoding.
+fn der_length(r: &mut Reader<'_>) -> DecodeResult<usize> {
+    match r.u8("length")? {
+        n @ 0..=0x7f => Ok(n as usize),
+        0x81 => Ok(r.u8("length")? as usize),
+        0x82 => Ok(u16::from_be_bytes(r.array("length")?) as usize),
+        _ => Err(DecodeError::new("private key: unsupported DER length")),
+    }
+}
+
+/// The content of the next DER element, which must have tag `tag`.
+fn der_element<'a>(r: &mut Reader<'a>, tag: u8, what: &str) -> DecodeResult<&'a [u8]> {
+    let found = r.u8(what)?;
+    if found != tag {
+        return Err(DecodeError::new(format!(
+            "{what}: DER tag {found:#04x}, expected {tag:#04x}"
+        )));
+    }
+    let len = der_length(r)?;
+    r.bytes(len, what)
+}
+
+/// The public key a key record is keyed by.
+fn pubkey_of(key: Option<&RecordKey>, tag: &str) -> DecodeResult<Vec<u8>> {
+    match key {
+        Some(RecordKey::PubKey(pubkey)) => Ok(pubkey.clone()),
+        _ => Err(DecodeError::new(format!("{tag}: key is not a public key"))),
+    }
+}
+
+/// Check that `private` belongs to the public key its record is keyed by.
+fn check_pubkey(pubkey: &[u8], private: &PrivateKey, tag: &str) -> DecodeResult<()> {
+    if private.pubkey != pubkey {
+        return Err(DecodeError::new(format!(
+            "{tag}: private key embeds public key {}, record is keyed by {}",
+            hex::encode(&private.pubkey),
+            hex::encode(pubkey)
+        )));
+    }
+    Ok(())
+}
+
+/// A `key` record: an unencrypted transparent key pair.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct TransparentKey {
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::util::serde_hex::bytes")
+    )]
+    pub pubkey: Vec<u8>,
+    pub private_key: PrivateKey,
+    /// `Hash(pubkey || privkey)`, checked on decode; `None` in old wallets.
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::util::serde_hex::opt_bytes")
+    )]
+    pub checksum: Option<[u8; 32]>,
+}
+
+/// Decodes `key` values: a `CPrivKey`, then optionally its checksum. Both the embedded
+/// public key and the checksum must match the record key.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct KeyDecoder;
+
+impl RecordDecoder for KeyDecoder {
+    type Item = TransparentKey;
+
+    fn decode(
+        &self,
+        _ctx: &DecodeContext,
+        key: Option<&RecordKey>,
+        raw_value: &[u8],
+    ) -> DecodeResult<TransparentKey> {
+        let pubkey = pubkey_of(key, "key")?;
+        let mut r = Reader::new(raw_value);
+        let private_key = PrivateKey::parse(r.var_bytes("private key")?)?;
+        check_pubkey(&pubkey, &private_key, "key")?;
+        let checksum = match r.is_empty() {
+            true => None,
+            false => Some(r.uint256("checksum")?),
+        };
+        r.finish()?;
+        if let Some(checksum) = checksum
+            && checksum != sha256d(&[pubkey.as_slice(), private_key.der.expose()].concat())
+        {
+            return Err(DecodeError::new("checksum does not match the key pair"));
+        }
+        Ok(TransparentKey {
+            pubkey,
+            private_key,
+            checksum,
+        })
+    }
+
+    fn name(&self) -> &'static str {
+        "key"
+    }
+}
+
+/// A `wkey` record: a key pair in the legacy `CWalletKey` wrapper.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct WalletKey {
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::util::serde_hex::bytes")
+    )]
+    pub pubkey: Vec<u8>,
+    pub version: i32,
+    pub private_key: PrivateKey,
+    /// Unix time the key was created.
+    pub created: i64,
+    /// Unix time the key expires, or 0 for never.
+    pub expires: i64,
+    pub comment: String,
+}
+
+/// Decodes `wkey` values: `CWalletKey`'s version, private key, creation and expiry
+/// times and comment.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct WKeyDecoder;
+
+impl RecordDecoder for WKeyDecoder {
+    type Item = WalletKey;
+
+    fn decode(
+        &self,
+        _ctx: &DecodeContext,
+        key: Option<&RecordKey>,
+        raw_value: &[u8],
+    ) -> DecodeResult<WalletKey> {
+        let pubkey = pubkey_of(key, "wkey")?;
+        let mut r = Reader::new(raw_value);
+        let version = r.i32("version")?;
+        let private_key = PrivateKey::parse(r.var_bytes("private key")?)?;
+        check_pubkey(&pubkey, &private_key, "wkey")?;
+        let created = r.i64("creation time")?;
+        let expires = r.i64("expiry time")?;
+        let comment = r.string("comment")?;
+        r.finish()?;
+        Ok(WalletKey {
+            pubkey,
+            version,
+            private_key,
+            created,
+            expires,
+            comment,
+        })
+    }
+
+    fn name(&self) -> &'static str {
+        "wkey"
+    }
+}
+
+/// A `ckey` record: a transparent key whose secret is encrypted under the master key.
+/// Enough to inventory an encrypted wallet's keys without its passphrase.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct EncryptedKey {
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::util::serde_hex::bytes")
+    )]
+    pub pubkey: Vec<u8>,
+    /// The 32-byte secret, AES-256-CBC encrypted with an IV derived from `pubkey`.
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::util::serde_hex::bytes")
+    )]
+    pub encrypted_secret: Vec<u8>,
+}
+
+impl EncryptedKey {
+    pub fn is_compressed(&self) -> bool {
+        self.pubkey.len() == 33
+    }
+}
+
+/// Decodes `ckey` values: the compact-size-prefixed ciphertext, which must be whole
+/// AES blocks.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CKeyDecoder;
+
+impl RecordDecoder for CKeyDecoder {
+    type Item = EncryptedKey;
+
+    fn decode(
+        &self,
+        _ctx: &DecodeContext,
+        key: Option<&RecordKey>,
+        raw_value: &[u8],
+    ) -> DecodeResult<EncryptedKey> {
+        let pubkey = pubkey_of(key, "ckey")?;
+        let mut r = Reader::new(raw_value);
+        let encrypted_secret = r.var_bytes("encrypted secret")?.to_vec();
+        r.finish()?;
+        if encrypted_secret.is_empty() || encrypted_secret.len() % 16 != 0 {
+            return Err(DecodeError::new(format!(
+                "{}-byte ciphertext is not whole AES blocks",
+                encrypted_secret.len()
+            )));
+        }
+        Ok(EncryptedKey {
+            pubkey,
+            encrypted_secret,
+        })
+    }
+
+    fn name(&self) -> &'static str {
+        "ckey"
+    }
+}
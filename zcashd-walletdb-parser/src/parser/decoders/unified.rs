@@ -0,0 +1,273 @@
+//! Unified accounts, from zcashd 5.0 on: `unifiedaccount`, `unifiedfvk` and
+//! `unifiedaddrmeta` records.
+//!
+//! A unified account is a ZIP 32 account of the mnemonic seed, named by the id of its
+//! unified full viewing key (a BLAKE2b-256 of the key's encoding). The wallet keeps the
+//! key itself in ZIP 316 encoding, and for each unified address it generated, the
+//! diversifier index and the receivers the address was given.
+
+use std::fmt;
+
+use crate::parser::{
+    address::unified::{UnifiedEncoding, UnifiedKind},
+    key::RecordKey,
+    record::{DecodeContext, DecodeError, DecodeResult, RecordDecoder},
+    serialize::{Reader, uint256_hex},
+};
+
+/// A `unifiedaccount` record: zcashd's `ZcashdUnifiedAccountMetadata`. Everything is in
+/// the key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct UnifiedAccount {
+    /// The fingerprint of the mnemonic seed the account derives from.
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::util::serde_hex::bytes")
+    )]
+    pub seed_fingerprint: [u8; 32],
+    /// The BIP 44 coin type: 133 on mainnet, 1 on testnet and regtest.
+    pub coin_type: u32,
+    /// The ZIP 32 account index.
+    pub account_id: u32,
+    /// The id of the account's unified full viewing key.
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::util::serde_hex::bytes")
+    )]
+    pub ufvk_id: [u8; 32],
+}
+
+impl UnifiedAccount {
+    pub fn ufvk_id_hex(&self) -> String {
+        uint256_hex(&self.ufvk_id)
+    }
+}
+
+impl fmt::Display for UnifiedAccount {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "UnifiedAccount {{")?;
+        writeln!(
+            f,
+            "  fingerprint  : {}",
+            uint256_hex(&self.seed_fingerprint)
+        )?;
+        writeln!(f, "  coin type    : {}", self.coin_type)?;
+        writeln!(f, "  account      : {}", self.account_id)?;
+        writeln!(f, "  ufvk id      : {}", self.ufvk_id_hex())?;
+        write!(f, "}}")
+    }
+}
+
+/// Decodes `unifiedaccount` records from their key. zcashd writes a placeholder value
+/// and never reads it back.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct UnifiedAccountDecoder;
+
+impl RecordDecoder for UnifiedAccountDecoder {
+    type Item = UnifiedAccount;
+
+    fn decode(
+        &self,
+        _ctx: &DecodeContext,
+        key: Option<&RecordKey>,
+        _raw_value: &[u8],
+    ) -> DecodeResult<UnifiedAccount> {
+        let Some(&RecordKey::UnifiedAccount {
+            seed_fingerprint,
+            coin_type,
+            account_id,
+            ufvk_id,
+        }) = key
+        else {
+            return Err(DecodeError::new("key is not unified account metadata"));
+        };
+        Ok(UnifiedAccount {
+            seed_fingerprint,
+            coin_type,
+            account_id,
+            ufvk_id,
+        })
+    }
+
+    fn name(&self) -> &'static str {
+        "unifiedaccount"
+    }
+}
+
+/// A `unifiedfvk` record: a unified full viewing key, by id.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct UnifiedFullViewingKey {
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::util::serde_hex::bytes")
+    )]
+    pub ufvk_id: [u8; 32],
+    /// The ZIP 316 encoding, e.g. `uview1...`.
+    pub encoding: String,
+}
+
+impl UnifiedFullViewingKey {
+    pub fn ufvk_id_hex(&self) -> String {
+        uint256_hex(&self.ufvk_id)
+    }
+
+    /// Take the stored encoding apart. It must be a full viewing key, and encoding the
+    /// parts again must give back the stored string.
+    pub fn parse(&self) -> DecodeResult<UnifiedEncoding> {
+        let parsed = UnifiedEncoding::decode(&self.encoding)?;
+        if parsed.kind != UnifiedKind::FullViewingKey {
+            return Err(DecodeError::new(format!(
+                "encoding is a {:?}, not a full viewing key",
+                parsed.kind
+            )));
+        }
+        if parsed.encode() != self.encoding {
+            return Err(DecodeError::new("encoding does not round-trip"));
+        }
+        Ok(parsed)
+    }
+}
+
+/// Decodes `unifiedfvk` values: the encoded key as a string.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct UnifiedFvkDecoder;
+
+impl RecordDecoder for UnifiedFvkDecoder {
+    type Item = UnifiedFullViewingKey;
+
+    fn decode(
+        &self,
+        _ctx: &DecodeContext,
+        key: Option<&RecordKey>,
+        raw_value: &[u8],
+    ) -> DecodeResult<UnifiedFullViewingKey> {
+        let Some(&RecordKey::UfvkId(ufvk_id)) = key else {
+            return Err(DecodeError::new("key is not a ufvk id"));
+        };
+        let mut r = Reader::new(raw_value);
+        let encoding = r.string("ufvk")?;
+        r.finish()?;
+        Ok(UnifiedFullViewingKey { ufvk_id, encoding })
+    }
+
+    fn name(&self) -> &'static str {
+        "unifiedfvk"
+    }
+}
+
+/// A receiver of a unified address, as zcashd's `ReceiverType` numbers it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum ReceiverType {
+    P2pkh,
+    P2sh,
+    Sapling,
+    Orchard,
+    Unknown(u32),
+}
+
+impl From<u32> for ReceiverType {
+    fn from(code: u32) -> Self {
+        match code {
+            0 => ReceiverType::P2pkh,
+            1 => ReceiverType::P2sh,
+            2 => ReceiverType::Sapling,
+            3 => ReceiverType::Orchard,
+            other => ReceiverType::Unknown(other),
+        }
+    }
+}
+
+impl fmt::Display for ReceiverType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReceiverType::P2pkh => f.write_str("p2pkh"),
+            ReceiverType::P2sh => f.write_str("p2sh"),
+            ReceiverType::Sapling => f.write_str("sapling"),
+            ReceiverType::Orchard => f.write_str("orchard"),
+            ReceiverType::Unknown(code) => write!(f, "unknown ({code})"),
+        }
+    }
+}
+
+/// A `unifiedaddrmeta` record: zcashd's `ZcashdUnifiedAddressMetadata`, enough to
+/// re-derive one unified address from its full viewing key.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct UnifiedAddressMetadata {
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::util::serde_hex::bytes")
+    )]
+    pub ufvk_id: [u8; 32],
+    /// The ZIP 32 diversifier index, an 88-bit little-endian integer.
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::util::serde_hex::bytes")
+    )]
+    pub diversifier_index: [u8; 11],
+    pub receiver_types: Vec<ReceiverType>,
+}
+
+impl UnifiedAddressMetadata {
+    /// The diversifier index as an integer.
+    pub fn index(&self) -> u128 {
+        let mut bytes = [0u8; 16];
+        bytes[..11].copy_from_slice(&self.diversifier_index);
+        u128::from_le_bytes(bytes)
+    }
+
+    pub fn has_receiver(&self, receiver: ReceiverType) -> bool {
+        self.receiver_types.contains(&receiver)
+    }
+}
+
+/// The only `unifiedaddrmeta` value zcashd writes or reads:
+/// `ZCASHD_UNIFIED_ADDRESS_METADATA_VERSION`.
+pub const UNIFIED_ADDRESS_METADATA_VERSION: u32 = 0;
+
+/// Decodes `unifiedaddrmeta` records. Everything is in the key; the value is the
+/// metadata version, and zcashd refuses to load a wallet with any other.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct UnifiedAddrMetaDecoder;
+
+impl RecordDecoder for UnifiedAddrMetaDecoder {
+    type Item = UnifiedAddressMetadata;
+
+    fn decode(
+        &self,
+        _ctx: &DecodeContext,
+        key: Option<&RecordKey>,
+        raw_value: &[u8],
+    ) -> DecodeResult<UnifiedAddressMetadata> {
+        let Some(RecordKey::UnifiedAddress {
+            ufvk_id,
+            diversifier_index,
+            receiver_types,
+        }) = key
+        else {
+            return Err(DecodeError::new(
+                "key is not a ufvk id, diversifier index and receiver types",
+            ));
+        };
+        let mut r = Reader::new(raw_value);
+        let version = r.u32("metadata version")?;
+        r.finish()?;
+        if version != UNIFIED_ADDRESS_METADATA_VERSION {
+            return Err(DecodeError::new(format!(
+                "unsupported metadata version {version}"
+            )));
+        }
+        Ok(UnifiedAddressMetadata {
+            ufvk_id: *ufvk_id,
+            diversifier_index: *diversifier_index,
+            receiver_types: receiver_types.clone(),
+        })
+    }
+
+    fn name(&self) -> &'static str {
+        "unifiedaddrmeta"
+    }
+}
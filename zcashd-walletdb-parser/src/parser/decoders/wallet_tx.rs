@@ -0,0 +1,414 @@
+//! Wallet transactions: `tx` records, keyed by txid.
+//!
+//! zcashd's `CWalletTx` extends `CMerkleTx`, itself the transaction followed by the hash
+//! of the block that mined it and the remnants of an SPV merkle branch. After that come
+//! the wallet's own fields: the unused `vtxPrev`, the `mapValue` string map (account,
+//! order position, smart time, comments), the note data of Sprout outputs, the order
+//! form, the receive time, `fFromMe` and a dead `fSpent` byte. v4 and later transactions
+//! add the Sapling note data, and v5 the Orchard action metadata.
+//!
+//! The note data carries a witness cache per Sprout and Sapling note: the note's
+//! authentication path as of each of the last blocks, up to `witnesscachesize` of them.
+//! A note without a cached witness cannot be spent until a rescan rebuilds it. Orchard
+//! witnesses live in the `orchard_note_commitment_tree` record instead.
+
+use std::collections::BTreeMap;
+
+use crate::parser::{
+    decoders::scalars::NU5_WALLET_VERSION,
+    key::RecordKey,
+    merkle::{SaplingHasher, SproutHasher, Witness},
+    record::{DecodeContext, DecodeError, DecodeResult, RecordDecoder},
+    serialize::{Reader, uint256_hex},
+    tx::{SAPLING_TX_VERSION, Transaction, ZIP225_TX_VERSION, legacy_txid},
+};
+
+fn read_optional_hash(r: &mut Reader<'_>, what: &str) -> DecodeResult<Option<[u8; 32]>> {
+    r.optional(what, |r| r.uint256(what))
+}
+
+/// One JoinSplit output: the transaction, JoinSplit and output index.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct JsOutPoint {
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::util::serde_hex::bytes")
+    )]
+    pub txid: [u8; 32],
+    pub js: u64,
+    pub n: u8,
+}
+
+/// What the wallet knows about one of its Sprout notes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct SproutNoteData {
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::util::serde_hex::bytes")
+    )]
+    pub a_pk: [u8; 32],
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::util::serde_hex::bytes")
+    )]
+    pub pk_enc: [u8; 32],
+    /// Known once the wallet has the spending key.
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::util::serde_hex::opt_bytes")
+    )]
+    pub nullifier: Option<[u8; 32]>,
+    /// The most recent first.
+    pub witnesses: Vec<Witness>,
+    pub witness_height: i32,
+}
+
+impl SproutNoteData {
+    /// The root of the note's newest witness: the anchor a spend of it would use.
+    pub fn anchor(&self) -> Option<[u8; 32]> {
+        self.witnesses.first().map(Witness::root::<SproutHasher>)
+    }
+}
+
+/// One Sapling output: the transaction and output index.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct SaplingOutPoint {
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::util::serde_hex::bytes")
+    )]
+    pub txid: [u8; 32],
+    pub n: u32,
+}
+
+/// What the wallet knows about one of its Sapling notes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct SaplingNoteData {
+    pub version: i32,
+    /// The incoming viewing key that decrypted the note.
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::util::serde_hex::bytes")
+    )]
+    pub ivk: [u8; 32],
+    /// Known once the wallet has the full viewing key.
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::util::serde_hex::opt_bytes")
+    )]
+    pub nullifier: Option<[u8; 32]>,
+    /// The most recent first.
+    pub witnesses: Vec<Witness>,
+    pub witness_height: i32,
+}
+
+impl SaplingNoteData {
+    /// The root of the note's newest witness: the anchor a spend of it would use.
+    pub fn anchor(&self) -> Option<[u8; 32]> {
+        self.witnesses.first().map(Witness::root::<SaplingHasher>)
+    }
+}
+
+/// A note of the wallet's, by its output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum NoteOutPoint {
+    Sprout(JsOutPoint),
+    Sapling(SaplingOutPoint),
+    /// An Orchard note, by transaction and action index. Witness caches never hold these,
+    /// as Orchard witnesses live in the note commitment tree.
+    Orchard {
+        #[cfg_attr(
+            feature = "serde",
+            serde(serialize_with = "crate::util::serde_hex::bytes")
+        )]
+        txid: [u8; 32],
+        action: u32,
+    },
+}
+
+/// The state of one note's witness cache.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct NoteWitnessCache {
+    pub outpoint: NoteOutPoint,
+    /// How many blocks' worth of witnesses are cached.
+    pub depth: usize,
+    /// The height the newest witness is for, or -1 if there is none.
+    pub witness_height: i32,
+}
+
+impl NoteWitnessCache {
+    /// Whether the note has a witness to spend it with, as of `witness_height`.
+    pub fn is_witnessed(&self) -> bool {
+        self.depth > 0 && self.witness_height >= 0
+    }
+}
+
+/// An Orchard incoming viewing key as `IncomingViewingKey::to_bytes` writes it: the
+/// diversifier key, then the `ivk` scalar.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct OrchardIncomingViewingKey {
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::util::serde_hex::bytes")
+    )]
+    pub dk: [u8; 32],
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::util::serde_hex::bytes")
+    )]
+    pub ivk: [u8; 32],
+}
+
+/// zcashd's `OrchardWalletTxMeta`. The notes themselves live in the Rust wallet, which
+/// rebuilds them on load; the record only says which actions concern the wallet.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct OrchardTxMeta {
+    pub version: i32,
+    /// `mapOrchardActionData`: actions whose output the wallet received, with the
+    /// incoming viewing key that decrypted it.
+    pub action_data: BTreeMap<u32, OrchardIncomingViewingKey>,
+    /// Actions spending one of the wallet's notes.
+    pub actions_spending_my_notes: Vec<u32>,
+}
+
+/// A transaction with the block that mined it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct MerkleTx {
+    pub tx: Transaction,
+    /// All zeroes while unconfirmed.
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::util::serde_hex::bytes")
+    )]
+    pub block_hash: [u8; 32],
+    /// Left over from SPV proofs; zcashd no longer fills it.
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::util::serde_hex::byte_list")
+    )]
+    pub merkle_branch: Vec<[u8; 32]>,
+    /// Position in the block, or -1.
+    pub index: i32,
+}
+
+impl MerkleTx {
+    /// Read a `CMerkleTx` from `r`, with the range of `r` the transaction took.
+    fn read(r: &mut Reader<'_>) -> DecodeResult<(Self, std::ops::Range<usize>)> {
+        let start = r.position();
+        let tx = Transaction::read(r)?;
+        let end = r.position();
+        let merkle_tx = MerkleTx {
+            tx,
+            block_hash: r.uint256("block hash")?,
+            merkle_branch: r.list(32, "merkle branch", |r| r.uint256("merkle branch"))?,
+            index: r.i32("index")?,
+        };
+        Ok((merkle_tx, start..end))
+    }
+
+    /// Whether the transaction is in a block, as far as the wallet last knew.
+    pub fn is_mined(&self) -> bool {
+        self.block_hash != [0; 32]
+    }
+}
+
+/// A `tx` record: zcashd's `CWalletTx`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct WalletTx {
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::util::serde_hex::bytes")
+    )]
+    pub txid: [u8; 32],
+    pub merkle_tx: MerkleTx,
+    /// The former `vtxPrev`, empty in every wallet zcashd wrote.
+    pub prev_txs: Vec<MerkleTx>,
+    /// String metadata: `fromaccount`, `n` (order position), `timesmart`, `comment`,
+    /// `to` and the like.
+    pub map_value: BTreeMap<String, String>,
+    pub sprout_note_data: Vec<(JsOutPoint, SproutNoteData)>,
+    pub order_form: Vec<(String, String)>,
+    pub time_received_is_tx_time: u32,
+    /// Unix time the wallet first saw the transaction.
+    pub time_received: u32,
+    /// Whether the wallet created the transaction.
+    pub from_me: bool,
+    /// Written as `false` by every client since Bitcoin 0.3.
+    pub spent: bool,
+    pub sapling_note_data: Vec<(SaplingOutPoint, SaplingNoteData)>,
+    pub orchard_meta: Option<OrchardTxMeta>,
+}
+
+impl WalletTx {
+    pub fn tx(&self) -> &Transaction {
+        &self.merkle_tx.tx
+    }
+
+    /// The txid the way zcashd prints it.
+    pub fn txid_hex(&self) -> String {
+        uint256_hex(&self.txid)
+    }
+
+    /// The position in the wallet's transaction order, from `mapValue["n"]`.
+    pub fn order_pos(&self) -> Option<i64> {
+        self.map_value.get("n")?.parse().ok()
+    }
+
+    /// The receive time adjusted to the block time, from `mapValue["timesmart"]`.
+    pub fn time_smart(&self) -> Option<u32> {
+        self.map_value.get("timesmart")?.parse().ok()
+    }
+
+    pub fn from_account(&self) -> Option<&str> {
+        self.map_value.get("fromaccount").map(String::as_str)
+    }
+
+    pub fn comment(&self) -> Option<&str> {
+        self.map_value.get("comment").map(String::as_str)
+    }
+
+    /// The witness caches of the transaction's Sprout and Sapling notes.
+    pub fn witness_caches(&self) -> Vec<NoteWitnessCache> {
+        let sprout = self
+            .sprout_note_data
+            .iter()
+            .map(|(outpoint, data)| NoteWitnessCache {
+                outpoint: NoteOutPoint::Sprout(*outpoint),
+                depth: data.witnesses.len(),
+                witness_height: data.witness_height,
+            });
+        let sapling = self
+            .sapling_note_data
+            .iter()
+            .map(|(outpoint, data)| NoteWitnessCache {
+                outpoint: NoteOutPoint::Sapling(*outpoint),
+                depth: data.witnesses.len(),
+                witness_height: data.witness_height,
+            });
+        sprout.chain(sapling).collect()
+    }
+}
+
+/// Decodes `tx` values into a [`WalletTx`]. For v1 to v4 transactions the record key
+/// must be the hash of the embedded transaction. v5 transactions carry Orchard metadata
+/// unless the [`DecodeContext`] says the wallet predates the Orchard wallet.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TxDecoder;
+
+impl RecordDecoder for TxDecoder {
+    type Item = WalletTx;
+
+    fn decode(
+        &self,
+        ctx: &DecodeContext,
+        key: Option<&RecordKey>,
+        raw_value: &[u8],
+    ) -> DecodeResult<WalletTx> {
+        let Some(&RecordKey::TxId(txid)) = key else {
+            return Err(DecodeError::new("key is not a txid"));
+        };
+        let mut r = Reader::new(raw_value);
+        let (merkle_tx, tx_range) = MerkleTx::read(&mut r)?;
+        let tx = &merkle_tx.tx;
+        if tx.version < ZIP225_TX_VERSION && legacy_txid(&raw_value[tx_range]) != txid {
+            return Err(DecodeError::new(format!(
+                "transaction does not hash to its key {}",
+                uint256_hex(&txid)
+            )));
+        }
+        let (overwintered, version) = (tx.overwintered, tx.version);
+        let prev_txs = r.list(4, "vtxPrev", |r| Ok(MerkleTx::read(r)?.0))?;
+        let map_value = r.map(2, "mapValue", |r| {
+            Ok((r.string("mapValue key")?, r.string("mapValue value")?))
+        })?;
+        let sprout_note_data = r.list(32 + 8 + 1 + 64, "sprout note data", |r| {
+            let outpoint = JsOutPoint {
+                txid: r.uint256("txid")?,
+                js: r.u64("joinsplit index")?,
+                n: r.u8("output index")?,
+            };
+            let data = SproutNoteData {
+                a_pk: r.uint256("a_pk")?,
+                pk_enc: r.uint256("pk_enc")?,
+                nullifier: read_optional_hash(r, "nullifier")?,
+                witnesses: r.list(3, "witnesses", Witness::read)?,
+                witness_height: r.i32("witness height")?,
+            };
+            Ok((outpoint, data))
+        })?;
+        let order_form = r.list(2, "vOrderForm", |r| {
+            Ok((r.string("order form key")?, r.string("order form value")?))
+        })?;
+        let time_received_is_tx_time = r.u32("fTimeReceivedIsTxTime")?;
+        let time_received = r.u32("nTimeReceived")?;
+        let from_me = r.bool("fFromMe")?;
+        let spent = r.bool("fSpent")?;
+        let mut sapling_note_data = Vec::new();
+        if overwintered && version >= SAPLING_TX_VERSION {
+            sapling_note_data = r.list(36 + 4 + 32, "sapling note data", |r| {
+                let outpoint = SaplingOutPoint {
+                    txid: r.uint256("txid")?,
+                    n: r.u32("output index")?,
+                };
+                let data = SaplingNoteData {
+                    version: r.i32("version")?,
+                    ivk: r.uint256("ivk")?,
+                    nullifier: read_optional_hash(r, "nullifier")?,
+                    witnesses: r.list(3, "witnesses", Witness::read)?,
+                    witness_height: r.i32("witness height")?,
+                };
+                Ok((outpoint, data))
+            })?;
+        }
+        // A client from before the Orchard wallet keeps no Orchard metadata, even for the
+        // v5 transactions it can already store.
+        let mut orchard_meta = None;
+        if overwintered && version >= ZIP225_TX_VERSION && !ctx.predates(NU5_WALLET_VERSION) {
+            let version = r.i32("orchard meta version")?;
+            let action_data = r.map(4 + 64, "orchard action data", |r| {
+                let index = r.u32("action index")?;
+                let ivk = OrchardIncomingViewingKey {
+                    dk: r.array("dk")?,
+                    ivk: r.array("ivk")?,
+                };
+                Ok((index, ivk))
+            })?;
+            let actions_spending_my_notes =
+                r.list(4, "actions spending my notes", |r| r.u32("action"))?;
+            orchard_meta = Some(OrchardTxMeta {
+                version,
+                action_data,
+                actions_spending_my_notes,
+            });
+        }
+        r.finish()?;
+        Ok(WalletTx {
+            txid,
+            merkle_tx,
+            prev_txs,
+            map_value,
+            sprout_note_data,
+            order_form,
+            time_received_is_tx_time,
+            time_received,
+            from_me,
+            spent,
+            sapling_note_data,
+            orchard_meta,
+        })
+    }
+
+    fn name(&self) -> &'static str {
+        "tx"
+    }
+}
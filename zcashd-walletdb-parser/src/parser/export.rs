@@ -0,0 +1,96 @@
+//! The wallet's secrets in the formats other wallets import.
+//!
+//! Encrypted secrets are only exported once decrypted, from [`DecryptedSecrets`]; the
+//! exported strings are [`SecretString`]s, and the caller decides where they go.
+
+use crate::parser::{
+    address::TransparentAddress, crypter::DecryptedSecrets, decoders::network::ZcashNetwork,
+    record::RecordKind, secret::SecretString, wallet::ZcashdWallet,
+};
+
+pub mod dump;
+pub mod extsk;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+pub mod wif;
+pub mod zewif;
+
+/// A transparent private key, ready for `importprivkey`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TransparentExport {
+    /// The record the key was read from: `key`, `wkey` or `ckey`.
+    pub kind: RecordKind,
+    pub pubkey: Vec<u8>,
+    pub address: TransparentAddress,
+    pub wif: SecretString,
+}
+
+/// Every transparent private key of `wallet` in WIF for `network`, by public key within
+/// each record kind. `ckey` secrets are taken from `secrets`; those it does not hold are
+/// left out.
+pub fn transparent_keys(
+    wallet: &ZcashdWallet,
+    secrets: Option<&DecryptedSecrets>,
+    network: ZcashNetwork,
+) -> Vec<TransparentExport> {
+    let keys = &wallet.keys;
+    let plain = keys
+        .transparent
+        .iter()
+        .map(|(pubkey, key)| (RecordKind::Key, pubkey, key.private_key.secret.expose()));
+    let wallet_keys = keys
+        .wallet_keys
+        .iter()
+        .map(|(pubkey, key)| (RecordKind::WKey, pubkey, key.private_key.secret.expose()));
+    let encrypted = keys.encrypted_transparent.keys().filter_map(|pubkey| {
+        let secret = secrets?.transparent.get(pubkey)?;
+        Some((RecordKind::CKey, pubkey, secret.expose()))
+    });
+    plain
+        .chain(wallet_keys)
+        .chain(encrypted)
+        .map(|(kind, pubkey, secret)| TransparentExport {
+            kind,
+            pubkey: pubkey.clone(),
+            address: TransparentAddress::from_pubkey(pubkey),
+            wif: wif::encode(secret, pubkey.len() == 33, network),
+        })
+        .collect()
+}
+
+/// A Sapling extended spending key, ready for `z_importkey`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SaplingExport {
+    /// The record the key was read from: `sapzkey` or `csapzkey`.
+    pub kind: RecordKind,
+    /// The incoming viewing key the record is keyed by.
+    pub ivk: [u8; 32],
+    pub extsk: SecretString,
+}
+
+/// Every Sapling extended spending key of `wallet` in Bech32 for `network`, by incoming
+/// viewing key within each record kind. `csapzkey` secrets are taken from `secrets`;
+/// those it does not hold are left out.
+pub fn sapling_keys(
+    wallet: &ZcashdWallet,
+    secrets: Option<&DecryptedSecrets>,
+    network: ZcashNetwork,
+) -> Vec<SaplingExport> {
+    let keys = &wallet.keys;
+    let plain = keys
+        .sapling
+        .iter()
+        .map(|(ivk, key)| (RecordKind::SapZKey, ivk, &key.key));
+    let encrypted = keys.encrypted_sapling.keys().filter_map(|ivk| {
+        let key = secrets?.sapling.get(ivk)?;
+        Some((RecordKind::CSapZKey, ivk, &key.key))
+    });
+    plain
+        .chain(encrypted)
+        .map(|(kind, ivk, key)| SaplingExport {
+            kind,
+            ivk: *ivk,
+            extsk: extsk::encode(key, network),
+        })
+        .collect()
+}
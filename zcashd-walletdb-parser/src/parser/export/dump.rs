@@ -0,0 +1,207 @@
+//! A wallet dump in the format of zcashd's `z_exportwallet`, which `z_importwallet`
+//! reads back into a node.
+//!
+//! Comment lines open with `#`. Each key line is the encoded secret, its birth time in
+//! ISO 8601, then fields: `label=` with the address book name, `reserve=1` for a key
+//! still in the pool and `change=1` for the others, and after a `#` the address and the
+//! HD path. `z_importwallet` rescans from the earliest birth time, so keys without
+//! metadata are dated 1970 rather than left out.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    io::{self, Write},
+};
+
+use crate::parser::{
+    balance::best_height,
+    crypter::DecryptedSecrets,
+    decoders::{keymeta::KeyMetadata, network::ZcashNetwork, sapling::SaplingExtendedSpendingKey},
+    export::{extsk, transparent_keys},
+    render::iso8601,
+    wallet::ZcashdWallet,
+};
+
+/// Write the dump of every key of `wallet` it holds in the clear or `secrets`
+/// decrypted, encoded for `network`, as `z_exportwallet` would have written it at Unix
+/// time `created`. What reaches `out` is secret.
+pub fn write_wallet_dump(
+    out: &mut impl Write,
+    wallet: &ZcashdWallet,
+    secrets: Option<&DecryptedSecrets>,
+    network: ZcashNetwork,
+    created: i64,
+) -> io::Result<()> {
+    write_header(out, wallet, secrets, created)?;
+    write_transparent(out, wallet, secrets, network)?;
+    write_sprout(out, wallet, secrets, network)?;
+    write_sapling(out, wallet, secrets, network)?;
+    writeln!(out, "# End of dump")
+}
+
+fn write_header(
+    out: &mut impl Write,
+    wallet: &ZcashdWallet,
+    secrets: Option<&DecryptedSecrets>,
+    created: i64,
+) -> io::Result<()> {
+    let version = wallet
+        .metadata
+        .version
+        .map(|v| format!(" from a Zcash {v} wallet"));
+    writeln!(
+        out,
+        "# Wallet dump created by zcashd-walletdb-parser {}{}",
+        env!("CARGO_PKG_VERSION"),
+        version.unwrap_or_default()
+    )?;
+    writeln!(out, "# * Created on {}", iso8601(created))?;
+    let tip = wallet
+        .metadata
+        .best_block
+        .as_ref()
+        .and_then(|b| b.tip_hex());
+    // zcashd goes on with the time the block was mined, which the wallet does not
+    // keep.
+    if let (Some(height), Some(tip)) = (best_height(wallet), tip) {
+        writeln!(out, "# * Best block at time of backup was {height} ({tip})")?;
+    }
+
+    let crypto = &wallet.crypto;
+    let mnemonic = secrets
+        .and_then(|s| s.mnemonic.as_ref())
+        .or(crypto.mnemonic.as_ref());
+    if let Some(mnemonic) = mnemonic {
+        writeln!(out, "# Emergency Recovery Information:")?;
+        writeln!(out, "# - recovery_phrase=\"{}\"", mnemonic.phrase.expose())?;
+        writeln!(out, "# - language={}", mnemonic.language)?;
+        writeln!(out, "# - fingerprint={}", mnemonic.fingerprint_hex())?;
+    }
+    let legacy = secrets
+        .and_then(|s| s.hd_seed.as_ref())
+        .or(crypto.hd_seed.as_ref());
+    if let Some(seed) = legacy {
+        writeln!(out, "# - legacy_seed={}", hex::encode(seed.seed.expose()))?;
+        writeln!(out, "# - legacy_seed_fp={}", seed.fingerprint_hex())?;
+    }
+    writeln!(out)
+}
+
+/// `key`, `wkey` and `ckey` lines, oldest first, like zcashd's `GetKeyBirthTimes`
+/// ordering.
+fn write_transparent(
+    out: &mut impl Write,
+    wallet: &ZcashdWallet,
+    secrets: Option<&DecryptedSecrets>,
+    network: ZcashNetwork,
+) -> io::Result<()> {
+    let keys = &wallet.keys;
+    let pool: BTreeSet<&[u8]> = keys
+        .key_pool
+        .values()
+        .map(|entry| &entry.pubkey[..])
+        .collect();
+    let mut exported = transparent_keys(wallet, secrets, network);
+    let birth = |pubkey: &Vec<u8>| created(keys.transparent_metadata.get(pubkey));
+    exported.sort_by_key(|key| (birth(&key.pubkey), *key.address.hash()));
+    for key in &exported {
+        let address = key.address.encode(network);
+        write!(out, "{} {} ", key.wif.expose(), iso8601(birth(&key.pubkey)))?;
+        match wallet.address_book.entries.get(&address) {
+            Some(entry) => {
+                let label = entry.label.as_deref().unwrap_or_default();
+                write!(out, "label={}", dump_string(label))?;
+            }
+            None if pool.contains(&key.pubkey[..]) => write!(out, "reserve=1")?,
+            None => write!(out, "change=1")?,
+        }
+        write!(out, " # addr={address}")?;
+        if let Some(path) = hd_keypath(keys.transparent_metadata.get(&key.pubkey)) {
+            write!(out, " hdkeypath={path}")?;
+        }
+        writeln!(out)?;
+    }
+    writeln!(out)
+}
+
+fn write_sprout(
+    out: &mut impl Write,
+    wallet: &ZcashdWallet,
+    secrets: Option<&DecryptedSecrets>,
+    network: ZcashNetwork,
+) -> io::Result<()> {
+    writeln!(out, "\n# Zkeys\n")?;
+    let keys = &wallet.keys;
+    let decrypted = secrets.into_iter().flat_map(|s| s.sprout.iter());
+    let sprout: BTreeMap<_, _> = keys.sprout.iter().chain(decrypted).collect();
+    for (address, key) in sprout {
+        let time = created(keys.sprout_metadata.get(address));
+        writeln!(
+            out,
+            "{} {} # zaddr={}",
+            key.encode(network).expose(),
+            iso8601(time),
+            address.encode(network)
+        )?;
+    }
+    Ok(())
+}
+
+/// Keys by address, as zcashd lists them: an incoming viewing key with several
+/// addresses gets a line for each.
+fn write_sapling(
+    out: &mut impl Write,
+    wallet: &ZcashdWallet,
+    secrets: Option<&DecryptedSecrets>,
+    network: ZcashNetwork,
+) -> io::Result<()> {
+    writeln!(out, "\n# Sapling keys\n")?;
+    let keys = &wallet.keys;
+    let spending_key = |ivk: &[u8; 32]| -> Option<&SaplingExtendedSpendingKey> {
+        let decrypted = secrets.and_then(|s| s.sapling.get(ivk));
+        keys.sapling.get(ivk).or(decrypted).map(|key| &key.key)
+    };
+    for (address, ivk) in &keys.sapling_addresses {
+        let Some(key) = spending_key(ivk) else {
+            continue;
+        };
+        let meta = keys.sapling_metadata.get(ivk);
+        write!(
+            out,
+            "{} {} # zaddr={}",
+            extsk::encode(key, network).expose(),
+            iso8601(created(meta)),
+            address.encode(network)
+        )?;
+        // Keys imported with `z_importkey` have no ZIP 32 metadata.
+        if let Some(path) = hd_keypath(meta)
+            && meta
+                .and_then(|m| m.seed_fingerprint)
+                .is_some_and(|fp| fp != [0; 32])
+        {
+            write!(out, " hdkeypath={path}")?;
+        }
+        writeln!(out)?;
+    }
+    writeln!(out)
+}
+
+fn created(meta: Option<&KeyMetadata>) -> i64 {
+    meta.map_or(0, |meta| meta.create_time)
+}
+
+fn hd_keypath(meta: Option<&KeyMetadata>) -> Option<&str> {
+    meta?.hd_keypath.as_deref().filter(|path| !path.is_empty())
+}
+
+/// zcashd's `EncodeDumpString`: `%`, spaces, control characters and bytes above ASCII
+/// as `%xx`, so a label stays one field.
+fn dump_string(s: &str) -> String {
+    let mut encoded = String::with_capacity(s.len());
+    for b in s.bytes() {
+        match b {
+            b'!'..=0x7f if b != b'%' => encoded.push(b as char),
+            _ => encoded.push_str(&format!("%{b:02x}")),
+        }
+    }
+    encoded
+}
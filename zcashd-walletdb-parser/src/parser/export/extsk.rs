@@ -0,0 +1,51 @@
+//! Sapling extended spending keys in Bech32, as `z_exportkey` prints them and
+//! `z_importkey` reads them back: the 169-byte ZIP 32 serialization under the network's
+//! `secret-extended-key-` prefix.
+
+use crate::parser::{
+    address::bech32::{self, Variant},
+    decoders::{
+        network::ZcashNetwork,
+        sapling::{EXTENDED_KEY_LEN, SaplingExtendedSpendingKey},
+    },
+    secret::{SecretBytes, SecretString},
+    serialize::Reader,
+};
+
+const NETWORKS: [ZcashNetwork; 3] = [
+    ZcashNetwork::Main,
+    ZcashNetwork::Test,
+    ZcashNetwork::Regtest,
+];
+
+/// The Bech32 encoding of `key` for `network`.
+pub fn encode(key: &SaplingExtendedSpendingKey, network: ZcashNetwork) -> SecretString {
+    let bytes = key.to_bytes();
+    let data = SecretBytes::new(bech32::convert_bits(bytes.expose(), 8, 5, true).unwrap());
+    SecretString::new(bech32::encode(
+        network.sapling_extended_spending_key_hrp(),
+        data.expose(),
+        Variant::Bech32,
+    ))
+}
+
+/// Parse an encoded extended spending key, with the network its prefix names; `None`
+/// if it is not Bech32 with one of the prefixes, or not a 169-byte key.
+pub fn decode(encoded: &str) -> Option<(SaplingExtendedSpendingKey, ZcashNetwork)> {
+    let (hrp, data, variant) = bech32::decode(encoded)?;
+    let data = SecretBytes::new(data);
+    let network = NETWORKS
+        .into_iter()
+        .find(|network| network.sapling_extended_spending_key_hrp() == hrp)?;
+    if variant != Variant::Bech32 {
+        return None;
+    }
+    let bytes = SecretBytes::new(bech32::convert_bits(data.expose(), 5, 8, false)?);
+    if bytes.len() != EXTENDED_KEY_LEN {
+        return None;
+    }
+    let mut r = Reader::new(bytes.expose());
+    let key = SaplingExtendedSpendingKey::read(&mut r).ok()?;
+    r.finish().ok()?;
+    Some((key, network))
+}
@@ -0,0 +1,275 @@
+//! The wallet as a `zcash_client_sqlite` database, the store zallet keeps its wallet in,
+//! so a zcashd wallet can move to zallet without running a zcashd node again.
+//!
+//! [`SCHEMA`] holds the `accounts`, `addresses` and `transactions` tables of
+//! `zcash_client_sqlite`, which [`write_wallet_db`] fills from the [`Zewif`] export, and
+//! `key_birthdays` with the creation time and HD path of each key. Each unified account
+//! becomes a derived account with its unified full viewing key; the keys zcashd held
+//! outside of any account become one imported account. An account's birthday is the
+//! height of its first transaction, or of the wallet's best block if it has none.
+//!
+//! Some of what `zcash_client_sqlite` stores is derived from keys with curves this crate
+//! does not implement: the unified incoming viewing key, the viewing key item caches
+//! and the encoding of unified addresses. Those columns are left NULL, and unified
+//! addresses out, for zallet to derive from the full viewing key. Sprout, which
+//! `zcash_client_sqlite` does not support, is left out, as are the addresses of others in
+//! the address book. No spending key is written: zallet keeps them in its own keystore.
+
+use std::collections::BTreeMap;
+
+use rusqlite::{Connection, params};
+
+use crate::{
+    crypto::{Digest, sha256::Sha256},
+    parser::{
+        address::TransparentAddress,
+        decoders::{address_book::AddressKind, network::ZcashNetwork},
+        export::zewif::{ProtocolAddress, Zewif, ZewifAccount, ZewifAddress},
+        keypath::{KeyPath, LEGACY_ACCOUNT},
+        record::RecordKind,
+        wallet::ZcashdWallet,
+    },
+};
+
+/// The tables [`write_wallet_db`] creates, after `zcash_client_sqlite`'s, with the
+/// columns it cannot fill made nullable.
+pub const SCHEMA: &str = "
+CREATE TABLE accounts (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name TEXT,
+    uuid BLOB NOT NULL UNIQUE,
+    account_kind INTEGER NOT NULL DEFAULT 0,
+    key_source TEXT,
+    hd_seed_fingerprint BLOB,
+    hd_account_index INTEGER,
+    ufvk TEXT,
+    uivk TEXT,
+    orchard_fvk_item_cache BLOB,
+    sapling_fvk_item_cache BLOB,
+    p2pkh_fvk_item_cache BLOB,
+    birthday_height INTEGER NOT NULL,
+    birthday_sapling_tree_size INTEGER,
+    birthday_orchard_tree_size INTEGER,
+    recover_until_height INTEGER,
+    has_spend_key INTEGER NOT NULL DEFAULT 1,
+    zcashd_legacy_address_index INTEGER,
+    CHECK (
+        (account_kind = 0 AND hd_seed_fingerprint IS NOT NULL
+            AND hd_account_index IS NOT NULL AND ufvk IS NOT NULL)
+        OR (account_kind = 1
+            AND (hd_seed_fingerprint IS NULL) = (hd_account_index IS NULL))
+    )
+);
+CREATE TABLE addresses (
+    id INTEGER NOT NULL PRIMARY KEY,
+    account_id INTEGER NOT NULL REFERENCES accounts(id),
+    key_scope INTEGER NOT NULL,
+    diversifier_index_be BLOB,
+    address TEXT NOT NULL,
+    transparent_child_index INTEGER,
+    cached_transparent_receiver_address TEXT,
+    exposed_at_height INTEGER,
+    receiver_flags INTEGER NOT NULL,
+    UNIQUE (account_id, address)
+);
+CREATE TABLE transactions (
+    id_tx INTEGER PRIMARY KEY,
+    txid BLOB NOT NULL UNIQUE,
+    created TEXT,
+    block INTEGER,
+    mined_height INTEGER,
+    tx_index INTEGER,
+    expiry_height INTEGER,
+    raw BLOB,
+    fee INTEGER,
+    target_height INTEGER,
+    min_observed_height INTEGER NOT NULL,
+    confirmed_unmined_at_height INTEGER,
+    trust_status INTEGER
+);
+CREATE TABLE key_birthdays (
+    address_id INTEGER NOT NULL PRIMARY KEY REFERENCES addresses(id),
+    birth_time INTEGER NOT NULL,
+    hd_keypath TEXT
+);
+";
+
+/// `account_kind`: an account derived from the wallet's seed.
+pub const ACCOUNT_DERIVED: i64 = 0;
+/// `account_kind`: an account of keys imported one by one.
+pub const ACCOUNT_IMPORTED: i64 = 1;
+
+/// `key_scope` of the addresses given out to receive funds.
+pub const SCOPE_EXTERNAL: i64 = 0;
+/// `key_scope` of change addresses.
+pub const SCOPE_INTERNAL: i64 = 1;
+
+/// `receiver_flags` bits, one per receiver an address has.
+pub const RECEIVER_P2PKH: i64 = 0b0001;
+pub const RECEIVER_P2SH: i64 = 0b0010;
+pub const RECEIVER_SAPLING: i64 = 0b0100;
+
+/// Create [`SCHEMA`] in `conn` and write `wallet` into it, its addresses encoded for
+/// `network`, in one transaction.
+pub fn write_wallet_db(
+    conn: &mut Connection,
+    wallet: &ZcashdWallet,
+    network: ZcashNetwork,
+) -> rusqlite::Result<()> {
+    let zewif = Zewif::of(wallet, None, network);
+    let heights: BTreeMap<[u8; 32], u32> = zewif
+        .transactions
+        .iter()
+        .filter_map(|(txid, tx)| Some((*txid, tx.mined_height.as_ref()?.value)))
+        .collect();
+    let db = conn.transaction()?;
+    db.execute_batch(SCHEMA)?;
+
+    for account in zewif.wallets.iter().flat_map(|w| &w.accounts) {
+        let birthday = account
+            .relevant_transactions
+            .iter()
+            .filter_map(|txid| heights.get(txid).copied())
+            .min()
+            .or(zewif.export_height)
+            .unwrap_or(0);
+        let (kind, fingerprint, index, has_spend_key) = match account.zip32_account_id {
+            Some(id) => {
+                let seed = wallet
+                    .keys
+                    .unified_accounts
+                    .iter()
+                    .find(|unified| unified.account_id == id)
+                    .map(|unified| unified.seed_fingerprint);
+                let has_seed =
+                    wallet.crypto.mnemonic.is_some() || wallet.crypto.encrypted_mnemonic.is_some();
+                // Without its full viewing key, zallet must derive the account again.
+                let kind = match account.ufvk {
+                    Some(_) => ACCOUNT_DERIVED,
+                    None => ACCOUNT_IMPORTED,
+                };
+                (kind, seed, Some(id), has_seed)
+            }
+            None => {
+                let keys = &wallet.keys;
+                let has_keys = keys.transparent_len() + keys.sprout_len() + keys.sapling_len() > 0;
+                (ACCOUNT_IMPORTED, None, None, has_keys)
+            }
+        };
+        let uuid = account_uuid(
+            &fingerprint
+                .or(wallet.crypto.seed_fingerprint())
+                .unwrap_or_default(),
+            index.unwrap_or(LEGACY_ACCOUNT),
+        );
+        db.execute(
+            "INSERT INTO accounts (name, uuid, account_kind, hd_seed_fingerprint,
+                hd_account_index, ufvk, birthday_height, has_spend_key)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![
+                account.name,
+                uuid,
+                kind,
+                fingerprint,
+                index,
+                account.ufvk.as_ref().map(|ufvk| &ufvk.value),
+                birthday,
+                has_spend_key,
+            ],
+        )?;
+        let account_id = db.last_insert_rowid();
+        write_addresses(&db, account_id, account)?;
+    }
+
+    for (txid, wtx) in &wallet.transactions {
+        let height = heights.get(txid).copied();
+        let tx = wtx.tx();
+        let index = (height.is_some() && wtx.merkle_tx.index >= 0).then_some(wtx.merkle_tx.index);
+        db.execute(
+            "INSERT INTO transactions (txid, block, mined_height, tx_index, expiry_height,
+                raw, min_observed_height)
+             VALUES (?1, ?2, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                &txid[..],
+                height,
+                index,
+                tx.expiry_height,
+                tx.to_bytes(),
+                height.or(zewif.export_height).unwrap_or(0),
+            ],
+        )?;
+    }
+    db.commit()
+}
+
+fn write_addresses(
+    db: &Connection,
+    account_id: i64,
+    account: &ZewifAccount,
+) -> rusqlite::Result<()> {
+    for entry in &account.addresses {
+        let Some((address, receivers)) = own_address(entry) else {
+            continue;
+        };
+        let path = entry
+            .hd_derivation_path
+            .as_ref()
+            .and_then(|path| KeyPath::parse(&path.value).ok());
+        let scope = match path.as_ref().and_then(KeyPath::chain) {
+            Some(1) => SCOPE_INTERNAL,
+            _ => SCOPE_EXTERNAL,
+        };
+        let child_index = match receivers {
+            RECEIVER_SAPLING => None,
+            _ => path.as_ref().and_then(KeyPath::address_index),
+        };
+        db.execute(
+            "INSERT INTO addresses (account_id, key_scope, address, transparent_child_index,
+                receiver_flags)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![account_id, scope, address, child_index, receivers],
+        )?;
+        if let Some(birth) = &entry.birth_time {
+            db.execute(
+                "INSERT INTO key_birthdays (address_id, birth_time, hd_keypath)
+                 VALUES (?1, ?2, ?3)",
+                params![
+                    db.last_insert_rowid(),
+                    birth.value,
+                    entry.hd_derivation_path.as_ref().map(|path| &path.value),
+                ],
+            )?;
+        }
+    }
+    Ok(())
+}
+
+/// The encoding and receivers of one of the wallet's own transparent or Sapling
+/// addresses.
+fn own_address(entry: &ZewifAddress) -> Option<(&str, i64)> {
+    if matches!(entry.source, RecordKind::Name | RecordKind::Purpose) {
+        return None;
+    }
+    let ProtocolAddress::Encoded { kind, address } = &entry.address else {
+        return None;
+    };
+    let receivers = match kind {
+        AddressKind::Transparent => match TransparentAddress::decode(address)?.0 {
+            TransparentAddress::PublicKeyHash(_) => RECEIVER_P2PKH,
+            TransparentAddress::ScriptHash(_) => RECEIVER_P2SH,
+        },
+        AddressKind::Sapling => RECEIVER_SAPLING,
+        _ => return None,
+    };
+    Some((address, receivers))
+}
+
+/// A version 8 UUID from the SHA-256 of the seed fingerprint and account index, so
+/// exporting a wallet twice gives its accounts the same ids.
+fn account_uuid(fingerprint: &[u8; 32], index: u32) -> [u8; 16] {
+    let hash = Sha256::digest(&[&fingerprint[..], &index.to_be_bytes()].concat());
+    let mut uuid: [u8; 16] = hash[..16].try_into().unwrap();
+    uuid[6] = (uuid[6] & 0x0f) | 0x80;
+    uuid[8] = (uuid[8] & 0x3f) | 0x80;
+    uuid
+}
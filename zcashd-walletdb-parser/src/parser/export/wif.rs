@@ -0,0 +1,58 @@
+//! Wallet Import Format: how `dumpprivkey` prints a transparent private key and
+//! `importprivkey` reads one back.
+//!
+//! The Base58Check encoding of the network's secret key prefix, the 32-byte secret, and
+//! a `0x01` byte when the key's public key is compressed.
+
+use crate::parser::{
+    address::base58,
+    decoders::network::ZcashNetwork,
+    secret::{SecretBytes, SecretString},
+};
+
+/// Marks a key whose public key is compressed.
+const COMPRESSED_FLAG: u8 = 0x01;
+
+/// A decoded WIF string.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WifKey {
+    pub secret: SecretBytes,
+    pub compressed: bool,
+    /// The networks the prefix is used on. Testnet and regtest share theirs.
+    pub networks: &'static [ZcashNetwork],
+}
+
+/// The WIF encoding of the 32-byte `secret` for `network`.
+pub fn encode(secret: &[u8], compressed: bool, network: ZcashNetwork) -> SecretString {
+    let mut payload = vec![network.b58_secret_key_prefix()];
+    payload.extend_from_slice(secret);
+    if compressed {
+        payload.push(COMPRESSED_FLAG);
+    }
+    let payload = SecretBytes::new(payload);
+    SecretString::new(base58::encode_check(payload.expose()))
+}
+
+/// Parse a WIF string; `None` if it is not Base58Check, has no Zcash prefix, or is not
+/// a 32-byte secret with an optional compressed flag.
+pub fn decode(wif: &str) -> Option<WifKey> {
+    const MAIN: &[ZcashNetwork] = &[ZcashNetwork::Main];
+    const TEST_OR_REGTEST: &[ZcashNetwork] = &[ZcashNetwork::Test, ZcashNetwork::Regtest];
+    let payload = SecretBytes::new(base58::decode_check(wif)?);
+    let (&prefix, rest) = payload.expose().split_first()?;
+    let networks = match prefix {
+        p if p == ZcashNetwork::Main.b58_secret_key_prefix() => MAIN,
+        p if p == ZcashNetwork::Test.b58_secret_key_prefix() => TEST_OR_REGTEST,
+        _ => return None,
+    };
+    let compressed = match rest.len() {
+        32 => false,
+        33 if rest[32] == COMPRESSED_FLAG => true,
+        _ => return None,
+    };
+    Some(WifKey {
+        secret: SecretBytes::from(&rest[..32]),
+        compressed,
+        networks,
+    })
+}
@@ -0,0 +1,542 @@
+//! The wallet as ZeWIF, the Zcash extensible wallet interchange format the wallets that
+//! succeed zcashd import from.
+//!
+//! [`Zewif`] follows the model of the `zewif` crate: wallets, each with its network, seed
+//! material and accounts, and the transactions they share. Until 5.0 zcashd kept keys
+//! outside of any account, so they go to one legacy account; each `unifiedaccount`
+//! becomes an account of its own, with its unified full viewing key, its unified
+//! addresses and the keys whose HD path names it. Each value carries the
+//! [`RecordKind`] it was read from, so an importer can tell what the wallet recorded
+//! from what was derived from it.
+//!
+//! With the `serde` feature the model serializes like the rest of the wallet, secrets
+//! redacted unless exposed. Writing it as a Gordian Envelope, as `zewif` stores it, is
+//! left to that crate.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::parser::{
+    address::TransparentAddress,
+    balance::{ABANDON_HASH, best_height, mined_heights},
+    crypter::DecryptedSecrets,
+    decoders::{
+        address_book::{AddressBookEntry, AddressKind, Purpose},
+        keymeta::KeyMetadata,
+        network::ZcashNetwork,
+        seed::Language,
+        unified::{ReceiverType, UnifiedAccount},
+        wallet_tx::WalletTx,
+    },
+    export::{extsk, transparent_keys},
+    record::RecordKind,
+    secret::{SecretBytes, SecretString},
+    wallet::ZcashdWallet,
+};
+
+/// A value and the record it was read from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Sourced<T> {
+    pub value: T,
+    pub source: RecordKind,
+}
+
+impl<T> Sourced<T> {
+    pub fn new(value: T, source: RecordKind) -> Self {
+        Sourced { value, source }
+    }
+}
+
+/// A ZeWIF export: the wallets and the transactions they share.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Zewif {
+    pub wallets: Vec<ZewifWallet>,
+    /// By txid.
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::util::serde_hex::byte_keys")
+    )]
+    pub transactions: BTreeMap<[u8; 32], ZewifTransaction>,
+    /// The height of the best block the wallet was synced to, if the records reveal it.
+    pub export_height: Option<u32>,
+}
+
+/// One wallet of a ZeWIF export.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ZewifWallet {
+    pub network: ZcashNetwork,
+    pub seed_material: Option<Sourced<SeedMaterial>>,
+    /// The legacy account first, then the unified accounts by ZIP 32 index.
+    pub accounts: Vec<ZewifAccount>,
+}
+
+/// The seed the wallet derives its keys from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum SeedMaterial {
+    /// A ZIP 339 mnemonic phrase, from zcashd 4.7 on.
+    Bip39Mnemonic {
+        phrase: SecretString,
+        language: Language,
+    },
+    /// The raw legacy HD seed of zcashd 2.0 to 4.6.
+    PreBip39Seed(SecretBytes),
+}
+
+/// One account of a ZeWIF wallet.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ZewifAccount {
+    pub name: String,
+    /// The ZIP 32 account index; `None` for the legacy account.
+    pub zip32_account_id: Option<u32>,
+    /// The unified full viewing key, in ZIP 316 encoding.
+    pub ufvk: Option<Sourced<String>>,
+    pub addresses: Vec<ZewifAddress>,
+    /// The txids of the transactions that pay or spend from the account's addresses, in
+    /// order.
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::util::serde_hex::byte_list")
+    )]
+    pub relevant_transactions: Vec<[u8; 32]>,
+}
+
+/// An address in a ZeWIF account.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum ProtocolAddress {
+    /// An encoded address, of the pool its prefix names.
+    Encoded { kind: AddressKind, address: String },
+    /// A unified address, by the diversifier index and receivers it was generated with:
+    /// the records hold its derivation, not its encoding.
+    Unified {
+        #[cfg_attr(
+            feature = "serde",
+            serde(serialize_with = "crate::util::serde_hex::bytes")
+        )]
+        diversifier_index: [u8; 11],
+        receiver_types: Vec<ReceiverType>,
+    },
+}
+
+/// An address, what the address book says of it and the key that spends from it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ZewifAddress {
+    pub address: ProtocolAddress,
+    /// The record the address comes from: a key, a script, a `sapzaddr` or a
+    /// `unifiedaddrmeta`, or the address book for the addresses of others.
+    pub source: RecordKind,
+    pub name: Option<Sourced<String>>,
+    pub purpose: Option<Sourced<Purpose>>,
+    /// The spending key, encoded as `z_exportkey` prints it.
+    pub spending_key: Option<Sourced<SecretString>>,
+    /// The Unix time the key was created.
+    pub birth_time: Option<Sourced<i64>>,
+    pub hd_derivation_path: Option<Sourced<String>>,
+}
+
+impl ZewifAddress {
+    fn new(address: ProtocolAddress, source: RecordKind) -> Self {
+        ZewifAddress {
+            address,
+            source,
+            name: None,
+            purpose: None,
+            spending_key: None,
+            birth_time: None,
+            hd_derivation_path: None,
+        }
+    }
+
+    fn encoded(kind: AddressKind, address: String, source: RecordKind) -> Self {
+        ZewifAddress::new(ProtocolAddress::Encoded { kind, address }, source)
+    }
+
+    fn with_metadata(mut self, meta: Option<&KeyMetadata>, source: RecordKind) -> Self {
+        if let Some(meta) = meta {
+            if meta.create_time != 0 {
+                self.birth_time = Some(Sourced::new(meta.create_time, source));
+            }
+            self.hd_derivation_path = meta
+                .hd_keypath
+                .clone()
+                .filter(|path| !path.is_empty())
+                .map(|path| Sourced::new(path, source));
+        }
+        self
+    }
+
+    fn label(&mut self, entry: Option<&AddressBookEntry>) {
+        if let Some(entry) = entry {
+            self.name = entry
+                .label
+                .clone()
+                .map(|label| Sourced::new(label, RecordKind::Name));
+            self.purpose = entry
+                .purpose
+                .clone()
+                .map(|purpose| Sourced::new(purpose, RecordKind::Purpose));
+        }
+    }
+}
+
+/// A wallet transaction, as its `tx` record has it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ZewifTransaction {
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::util::serde_hex::bytes")
+    )]
+    pub txid: [u8; 32],
+    /// The transaction as serialized on chain.
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::util::serde_hex::bytes")
+    )]
+    pub raw: Vec<u8>,
+    /// The hash of the block that mined it; `None` if it is unmined or abandoned.
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::util::serde_hex::opt_bytes")
+    )]
+    pub block_hash: Option<[u8; 32]>,
+    pub mined_height: Option<Sourced<u32>>,
+    pub abandoned: bool,
+    /// The Unix time the wallet first saw it.
+    pub time_received: u32,
+    pub from_me: bool,
+    pub comment: Option<String>,
+}
+
+impl ZewifTransaction {
+    fn of(wtx: &WalletTx, mined_height: Option<Sourced<u32>>) -> Self {
+        let block_hash = wtx.merkle_tx.block_hash;
+        let abandoned = block_hash == ABANDON_HASH;
+        ZewifTransaction {
+            txid: wtx.txid,
+            raw: wtx.tx().to_bytes(),
+            block_hash: (wtx.merkle_tx.is_mined() && !abandoned).then_some(block_hash),
+            mined_height,
+            abandoned,
+            time_received: wtx.time_received,
+            from_me: wtx.from_me,
+            comment: wtx.comment().map(str::to_owned),
+        }
+    }
+}
+
+/// The position in [`ZewifWallet::accounts`] of the legacy account.
+const LEGACY: usize = 0;
+
+impl Zewif {
+    /// Export every key and address of `wallet` it holds in the clear or `secrets`
+    /// decrypted, encoded for `network`, with its address book and transactions.
+    /// Encrypted keys `secrets` does not hold are left out; their addresses are not.
+    pub fn of(
+        wallet: &ZcashdWallet,
+        secrets: Option<&DecryptedSecrets>,
+        network: ZcashNetwork,
+    ) -> Self {
+        let mut builder = Builder::new(wallet);
+        builder.transparent(secrets, network);
+        builder.sprout(secrets, network);
+        builder.sapling(secrets, network);
+        builder.unified();
+        builder.address_book();
+        let relevant = builder.relevant_transactions();
+        for (account, txids) in builder.accounts.iter_mut().zip(relevant) {
+            account.relevant_transactions = txids.into_iter().collect();
+        }
+
+        let heights = mined_heights(wallet);
+        let transactions = wallet
+            .transactions
+            .iter()
+            .map(|(txid, wtx)| {
+                let height = heights
+                    .get(txid)
+                    .map(|&(height, source)| Sourced::new(height, source));
+                (*txid, ZewifTransaction::of(wtx, height))
+            })
+            .collect();
+        Zewif {
+            wallets: vec![ZewifWallet {
+                network,
+                seed_material: seed_material(wallet, secrets),
+                accounts: builder.accounts,
+            }],
+            transactions,
+            export_height: best_height(wallet),
+        }
+    }
+}
+
+fn seed_material(
+    wallet: &ZcashdWallet,
+    secrets: Option<&DecryptedSecrets>,
+) -> Option<Sourced<SeedMaterial>> {
+    let crypto = &wallet.crypto;
+    let mnemonic = crypto
+        .mnemonic
+        .as_ref()
+        .map(|m| (m, RecordKind::MnemonicPhrase))
+        .or_else(|| Some((secrets?.mnemonic.as_ref()?, RecordKind::CMnemonicPhrase)));
+    if let Some((mnemonic, source)) = mnemonic {
+        let seed = SeedMaterial::Bip39Mnemonic {
+            phrase: mnemonic.phrase.clone(),
+            language: mnemonic.language,
+        };
+        return Some(Sourced::new(seed, source));
+    }
+    let (seed, source) = crypto
+        .hd_seed
+        .as_ref()
+        .map(|seed| (seed, RecordKind::HdSeed))
+        .or_else(|| Some((secrets?.hd_seed.as_ref()?, RecordKind::CHdSeed)))?;
+    Some(Sourced::new(
+        SeedMaterial::PreBip39Seed(seed.seed.clone()),
+        source,
+    ))
+}
+
+/// The accounts as they fill up, and which of them owns each t-address and Sapling
+/// incoming viewing key, to sort transactions by.
+struct Builder<'a> {
+    wallet: &'a ZcashdWallet,
+    accounts: Vec<ZewifAccount>,
+    /// The unified accounts, in the order of `accounts` after the legacy one.
+    unified: Vec<&'a UnifiedAccount>,
+    transparent_owners: BTreeMap<TransparentAddress, usize>,
+    ivk_owners: BTreeMap<[u8; 32], usize>,
+}
+
+impl<'a> Builder<'a> {
+    fn new(wallet: &'a ZcashdWallet) -> Self {
+        let keys = &wallet.keys;
+        let mut unified: Vec<&UnifiedAccount> = keys.unified_accounts.iter().collect();
+        unified.sort_by_key(|account| account.account_id);
+        let legacy = ZewifAccount {
+            name: "Legacy".to_string(),
+            zip32_account_id: None,
+            ufvk: None,
+            addresses: Vec::new(),
+            relevant_transactions: Vec::new(),
+        };
+        let accounts = std::iter::once(legacy)
+            .chain(unified.iter().map(|account| {
+                ZewifAccount {
+                    name: format!("Account {}", account.account_id),
+                    zip32_account_id: Some(account.account_id),
+                    ufvk: keys
+                        .account_ufvk(account)
+                        .map(|ufvk| Sourced::new(ufvk.encoding.clone(), RecordKind::UnifiedFvk)),
+                    addresses: Vec::new(),
+                    relevant_transactions: Vec::new(),
+                }
+            }))
+            .collect();
+        Builder {
+            wallet,
+            accounts,
+            unified,
+            transparent_owners: BTreeMap::new(),
+            ivk_owners: BTreeMap::new(),
+        }
+    }
+
+    /// The account a key belongs to: the unified account its HD path and seed name, or
+    /// the legacy account.
+    fn owner(&self, meta: Option<&KeyMetadata>) -> usize {
+        let Some(meta) = meta else {
+            return LEGACY;
+        };
+        let account = meta.key_path().and_then(|path| path.account());
+        self.unified
+            .iter()
+            .position(|unified| {
+                Some(unified.account_id) == account
+                    && meta.seed_fingerprint == Some(unified.seed_fingerprint)
+            })
+            .map_or(LEGACY, |position| position + 1)
+    }
+
+    fn transparent(&mut self, secrets: Option<&DecryptedSecrets>, network: ZcashNetwork) {
+        let keys = &self.wallet.keys;
+        let exported: BTreeMap<_, _> = transparent_keys(self.wallet, secrets, network)
+            .into_iter()
+            .map(|key| (key.address, key))
+            .collect();
+        let pubkeys: BTreeMap<_, _> = keys
+            .transparent
+            .keys()
+            .chain(keys.wallet_keys.keys())
+            .chain(keys.encrypted_transparent.keys())
+            .map(|pubkey| (TransparentAddress::from_pubkey(pubkey), pubkey))
+            .collect();
+        for (address, tag) in keys.transparent_addresses() {
+            let meta = pubkeys
+                .get(&address)
+                .and_then(|pubkey| keys.transparent_metadata.get(*pubkey));
+            let mut entry = ZewifAddress::encoded(
+                AddressKind::Transparent,
+                address.encode(network),
+                RecordKind::from_tag(tag),
+            )
+            .with_metadata(meta, RecordKind::KeyMeta);
+            entry.spending_key = exported
+                .get(&address)
+                .map(|key| Sourced::new(key.wif.clone(), key.kind));
+            let owner = self.owner(meta);
+            self.transparent_owners.insert(address, owner);
+            self.accounts[owner].addresses.push(entry);
+        }
+    }
+
+    fn sprout(&mut self, secrets: Option<&DecryptedSecrets>, network: ZcashNetwork) {
+        let keys = &self.wallet.keys;
+        let mut sources = BTreeMap::new();
+        let records = [
+            (RecordKind::ZKey, keys.sprout.keys().collect::<Vec<_>>()),
+            (RecordKind::CZKey, keys.encrypted_sprout.keys().collect()),
+            (RecordKind::VKey, keys.sprout_viewing.keys().collect()),
+        ];
+        for (kind, addresses) in records {
+            for address in addresses {
+                sources.entry(*address).or_insert(kind);
+            }
+        }
+        for (address, source) in sources {
+            let meta = keys.sprout_metadata.get(&address);
+            let mut entry =
+                ZewifAddress::encoded(AddressKind::Sprout, address.encode(network), source)
+                    .with_metadata(meta, RecordKind::ZKeyMeta);
+            let plain = keys.sprout.get(&address).map(|key| (key, RecordKind::ZKey));
+            let decrypted = || Some((secrets?.sprout.get(&address)?, RecordKind::CZKey));
+            entry.spending_key = plain
+                .or_else(decrypted)
+                .map(|(key, kind)| Sourced::new(key.encode(network), kind));
+            self.accounts[LEGACY].addresses.push(entry);
+        }
+    }
+
+    fn sapling(&mut self, secrets: Option<&DecryptedSecrets>, network: ZcashNetwork) {
+        let keys = &self.wallet.keys;
+        for (address, ivk) in &keys.sapling_addresses {
+            let meta = keys.sapling_metadata.get(ivk);
+            let mut entry = ZewifAddress::encoded(
+                AddressKind::Sapling,
+                address.encode(network),
+                RecordKind::SapZAddr,
+            )
+            .with_metadata(meta, RecordKind::SapZKeyMeta);
+            let plain = keys.sapling.get(ivk).map(|key| (key, RecordKind::SapZKey));
+            let decrypted = || Some((secrets?.sapling.get(ivk)?, RecordKind::CSapZKey));
+            entry.spending_key = plain
+                .or_else(decrypted)
+                .map(|(key, kind)| Sourced::new(extsk::encode(&key.key, network), kind));
+            let owner = self.owner(meta);
+            self.ivk_owners.insert(*ivk, owner);
+            self.accounts[owner].addresses.push(entry);
+        }
+    }
+
+    fn unified(&mut self) {
+        for meta in &self.wallet.keys.unified_addresses {
+            let Some(position) = self
+                .unified
+                .iter()
+                .position(|account| account.ufvk_id == meta.ufvk_id)
+            else {
+                continue;
+            };
+            let address = ProtocolAddress::Unified {
+                diversifier_index: meta.diversifier_index,
+                receiver_types: meta.receiver_types.clone(),
+            };
+            let entry = ZewifAddress::new(address, RecordKind::UnifiedAddrMeta);
+            self.accounts[position + 1].addresses.push(entry);
+        }
+    }
+
+    /// Label the wallet's own addresses, and add those of others to the legacy account,
+    /// as zcashd's address book is not per account.
+    fn address_book(&mut self) {
+        let mut unlisted: BTreeSet<&str> = self
+            .wallet
+            .address_book
+            .entries
+            .keys()
+            .map(String::as_str)
+            .collect();
+        let book = &self.wallet.address_book;
+        for account in &mut self.accounts {
+            for entry in &mut account.addresses {
+                if let ProtocolAddress::Encoded { address, .. } = &entry.address {
+                    unlisted.remove(address.as_str());
+                    let book_entry = book.get(address);
+                    entry.label(book_entry);
+                }
+            }
+        }
+        for address in unlisted {
+            let book_entry = book.get(address);
+            let source = match book_entry.and_then(|e| e.label.as_ref()) {
+                Some(_) => RecordKind::Name,
+                None => RecordKind::Purpose,
+            };
+            let mut entry =
+                ZewifAddress::encoded(AddressKind::of(address), address.to_string(), source);
+            entry.label(book_entry);
+            self.accounts[LEGACY].addresses.push(entry);
+        }
+    }
+
+    /// The txids of each account's transactions: those paying or spending an output to
+    /// one of its t-addresses, and those with notes it can decrypt. Sprout notes are the
+    /// legacy account's. zcashd keeps no Orchard keys per account in its records, so
+    /// transactions with Orchard notes go to the unified account only if there is one.
+    fn relevant_transactions(&self) -> Vec<BTreeSet<[u8; 32]>> {
+        let transactions = &self.wallet.transactions;
+        let mut relevant = vec![BTreeSet::new(); self.accounts.len()];
+        let output_owner = |txid: &[u8; 32], n: u32| -> Option<usize> {
+            let out = transactions.get(txid)?.tx().vout.get(n as usize)?;
+            let address = TransparentAddress::of_script(&out.script_pubkey)?;
+            self.transparent_owners.get(&address).copied()
+        };
+        for (txid, wtx) in transactions {
+            let tx = wtx.tx();
+            let mut owners: BTreeSet<usize> = (0..tx.vout.len() as u32)
+                .filter_map(|n| output_owner(txid, n))
+                .collect();
+            owners.extend(
+                tx.vin
+                    .iter()
+                    .filter_map(|input| output_owner(&input.prevout.txid, input.prevout.n)),
+            );
+            if !wtx.sprout_note_data.is_empty() {
+                owners.insert(LEGACY);
+            }
+            owners.extend(
+                wtx.sapling_note_data
+                    .iter()
+                    .filter_map(|(_, note)| self.ivk_owners.get(&note.ivk).copied()),
+            );
+            let orchard = wtx
+                .orchard_meta
+                .as_ref()
+                .is_some_and(|meta| !meta.action_data.is_empty());
+            if orchard && self.unified.len() == 1 {
+                owners.insert(1);
+            }
+            for owner in owners {
+                relevant[owner].insert(*txid);
+            }
+        }
+        relevant
+    }
+}
@@ -0,0 +1,323 @@
+//! The structured part of a record key: whatever zcashd serializes after the tag.
+//!
+//! Most records are keyed by what they describe (an address, a public key, a txid),
+//! some by a pair of values, and singletons such as `version` or `bestblock` by the tag
+//! alone. [`DefaultClassifier`] splits a raw key into its [`RecordKind`] and that
+//! remainder, decoded into a [`RecordKey`].
+
+use std::fmt;
+
+use crate::{
+    entry::parser::split_walletdb_key,
+    parser::{
+        decoders::unified::ReceiverType,
+        record::{DecodeResult, RecordClassifier, RecordKind},
+        serialize::{Reader, uint256_hex},
+    },
+};
+
+/// The decoded remainder of a record key, after its tag.
+///
+/// `uint256` values (txids, fingerprints, key ids) are kept in serialized byte order and
+/// displayed the way zcashd prints them, byte-reversed.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum RecordKey {
+    /// `name`, `purpose`: an encoded address.
+    Address(String),
+    /// `key`, `wkey`, `ckey`, `keymeta`: a serialized `CPubKey`.
+    PubKey(
+        #[cfg_attr(
+            feature = "serde",
+            serde(serialize_with = "crate::util::serde_hex::bytes")
+        )]
+        Vec<u8>,
+    ),
+    /// `mkey`: the id of a master key.
+    MasterKeyId(u32),
+    /// `pool`: a key pool index.
+    PoolIndex(i64),
+    /// `tx`: a transaction id.
+    TxId(
+        #[cfg_attr(
+            feature = "serde",
+            serde(serialize_with = "crate::util::serde_hex::bytes")
+        )]
+        [u8; 32],
+    ),
+    /// `acc`: a legacy account name.
+    Account(String),
+    /// `acentry`: a legacy account and the number of its entry.
+    AccountingEntry { account: String, number: u64 },
+    /// `destdata`: an encoded address and the name of the datum stored for it.
+    DestData { address: String, key: String },
+    /// `hdseed`, `chdseed`, `mnemonicphrase`, `cmnemonicphrase`: a seed fingerprint.
+    SeedFingerprint(
+        #[cfg_attr(
+            feature = "serde",
+            serde(serialize_with = "crate::util::serde_hex::bytes")
+        )]
+        [u8; 32],
+    ),
+    /// `zkey`, `czkey`, `zkeymeta`, `vkey`: a Sprout payment address.
+    SproutAddress { a_pk: [u8; 32], pk_enc: [u8; 32] },
+    /// `sapzkey`, `csapzkey`, `sapzkeymeta`: a Sapling incoming viewing key.
+    SaplingIvk(
+        #[cfg_attr(
+            feature = "serde",
+            serde(serialize_with = "crate::util::serde_hex::bytes")
+        )]
+        [u8; 32],
+    ),
+    /// `sapzaddr`: a Sapling payment address.
+    SaplingAddress {
+        #[cfg_attr(
+            feature = "serde",
+            serde(serialize_with = "crate::util::serde_hex::bytes")
+        )]
+        diversifier: [u8; 11],
+        #[cfg_attr(
+            feature = "serde",
+            serde(serialize_with = "crate::util::serde_hex::bytes")
+        )]
+        pk_d: [u8; 32],
+    },
+    /// `sapextfvk`: a serialized Sapling extended full viewing key.
+    SaplingExtFvk(
+        #[cfg_attr(
+            feature = "serde",
+            serde(serialize_with = "crate::util::serde_hex::bytes")
+        )]
+        Vec<u8>,
+    ),
+    /// `watchs`: a watch-only script.
+    Script(
+        #[cfg_attr(
+            feature = "serde",
+            serde(serialize_with = "crate::util::serde_hex::bytes")
+        )]
+        Vec<u8>,
+    ),
+    /// `cscript`: the hash160 of a redeem script.
+    ScriptHash(
+        #[cfg_attr(
+            feature = "serde",
+            serde(serialize_with = "crate::util::serde_hex::bytes")
+        )]
+        [u8; 20],
+    ),
+    /// `unifiedaccount`: the seed, coin type and account a unified account derives
+    /// from, and the id of its full viewing key.
+    UnifiedAccount {
+        #[cfg_attr(
+            feature = "serde",
+            serde(serialize_with = "crate::util::serde_hex::bytes")
+        )]
+        seed_fingerprint: [u8; 32],
+        coin_type: u32,
+        account_id: u32,
+        #[cfg_attr(
+            feature = "serde",
+            serde(serialize_with = "crate::util::serde_hex::bytes")
+        )]
+        ufvk_id: [u8; 32],
+    },
+    /// `unifiedfvk`: the id of a unified full viewing key.
+    UfvkId(
+        #[cfg_attr(
+            feature = "serde",
+            serde(serialize_with = "crate::util::serde_hex::bytes")
+        )]
+        [u8; 32],
+    ),
+    /// `unifiedaddrmeta`: a unified full viewing key id, a diversifier index and the
+    /// receiver types of the address.
+    UnifiedAddress {
+        #[cfg_attr(
+            feature = "serde",
+            serde(serialize_with = "crate::util::serde_hex::bytes")
+        )]
+        ufvk_id: [u8; 32],
+        #[cfg_attr(
+            feature = "serde",
+            serde(serialize_with = "crate::util::serde_hex::bytes")
+        )]
+        diversifier_index: [u8; 11],
+        receiver_types: Vec<ReceiverType>,
+    },
+    /// `recipientmapping`: a txid and the encoded receiver it paid.
+    RecipientMapping { txid: [u8; 32], recipient: String },
+    /// A remainder that does not match the layout of its kind, or of an unknown kind.
+    Raw(
+        #[cfg_attr(
+            feature = "serde",
+            serde(serialize_with = "crate::util::serde_hex::bytes")
+        )]
+        Vec<u8>,
+    ),
+}
+
+impl fmt::Display for RecordKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RecordKey::Address(s) | RecordKey::Account(s) => f.write_str(s),
+            RecordKey::PubKey(b)
+            | RecordKey::SaplingExtFvk(b)
+            | RecordKey::Script(b)
+            | RecordKey::Raw(b) => f.write_str(&hex::encode(b)),
+            RecordKey::MasterKeyId(id) => write!(f, "{id}"),
+            RecordKey::PoolIndex(i) => write!(f, "{i}"),
+            RecordKey::TxId(h)
+            | RecordKey::SeedFingerprint(h)
+            | RecordKey::SaplingIvk(h)
+            | RecordKey::UfvkId(h) => f.write_str(&uint256_hex(h)),
+            RecordKey::AccountingEntry { account, number } => write!(f, "{account}/{number}"),
+            RecordKey::DestData { address, key } => write!(f, "{address}/{key}"),
+            RecordKey::SproutAddress { a_pk, pk_enc } => {
+                write!(f, "{}/{}", uint256_hex(a_pk), uint256_hex(pk_enc))
+            }
+            RecordKey::SaplingAddress { diversifier, pk_d } => {
+                write!(f, "{}/{}", hex::encode(diversifier), uint256_hex(pk_d))
+            }
+            RecordKey::ScriptHash(h) => f.write_str(&hex::encode(h)),
+            RecordKey::UnifiedAccount {
+                seed_fingerprint,
+                coin_type,
+                account_id,
+                ufvk_id,
+            } => write!(
+                f,
+                "{}/{coin_type}/{account_id}/{}",
+                uint256_hex(seed_fingerprint),
+                uint256_hex(ufvk_id)
+            ),
+            RecordKey::UnifiedAddress {
+                ufvk_id,
+                diversifier_index,
+                receiver_types,
+            } => {
+                write!(
+                    f,
+                    "{}/{}/",
+                    uint256_hex(ufvk_id),
+                    hex::encode(diversifier_index)
+                )?;
+                for (i, receiver) in receiver_types.iter().enumerate() {
+                    let separator = if i == 0 { "" } else { "," };
+                    write!(f, "{separator}{receiver}")?;
+                }
+                Ok(())
+            }
+            RecordKey::RecipientMapping { txid, recipient } => {
+                write!(f, "{}/{recipient}", uint256_hex(txid))
+            }
+        }
+    }
+}
+
+impl RecordKey {
+    /// Decode the remainder of a `kind` key. `Ok(None)` means the kind is keyed by its
+    /// tag alone and nothing follows it.
+    pub fn parse(kind: RecordKind, rest: &[u8]) -> DecodeResult<Option<Self>> {
+        let mut r = Reader::new(rest);
+        let key = match kind {
+            RecordKind::Name | RecordKind::Purpose => RecordKey::Address(r.string("address")?),
+            RecordKind::Key | RecordKind::WKey | RecordKind::CKey | RecordKind::KeyMeta => {
+                RecordKey::PubKey(r.var_bytes("public key")?.to_vec())
+            }
+            RecordKind::MKey => RecordKey::MasterKeyId(r.u32("master key id")?),
+            RecordKind::Pool => RecordKey::PoolIndex(r.i64("pool index")?),
+            RecordKind::Tx => RecordKey::TxId(r.uint256("txid")?),
+            RecordKind::Acc => RecordKey::Account(r.string("account")?),
+            RecordKind::AcEntry => RecordKey::AccountingEntry {
+                account: r.string("account")?,
+                number: r.u64("entry number")?,
+            },
+            RecordKind::DestData => RecordKey::DestData {
+                address: r.string("address")?,
+                key: r.string("datum name")?,
+            },
+            RecordKind::HdSeed
+            | RecordKind::CHdSeed
+            | RecordKind::MnemonicPhrase
+            | RecordKind::CMnemonicPhrase => {
+                RecordKey::SeedFingerprint(r.uint256("seed fingerprint")?)
+            }
+            RecordKind::ZKey | RecordKind::CZKey | RecordKind::ZKeyMeta | RecordKind::VKey => {
+                RecordKey::SproutAddress {
+                    a_pk: r.uint256("a_pk")?,
+                    pk_enc: r.uint256("pk_enc")?,
+                }
+            }
+            RecordKind::SapZKey | RecordKind::CSapZKey | RecordKind::SapZKeyMeta => {
+                RecordKey::SaplingIvk(r.uint256("incoming viewing key")?)
+            }
+            RecordKind::SapZAddr => RecordKey::SaplingAddress {
+                diversifier: r.array("diversifier")?,
+                pk_d: r.uint256("pk_d")?,
+            },
+            // depth, parent tag, child index, chain code, fvk (ak, nk, ovk), dk.
+            RecordKind::SapExtFvk => {
+                RecordKey::SaplingExtFvk(r.bytes(1 + 4 + 4 + 32 + 96 + 32, "extfvk")?.to_vec())
+            }
+            RecordKind::Watchs => RecordKey::Script(r.var_bytes("script")?.to_vec()),
+            RecordKind::CScript => RecordKey::ScriptHash(r.array("script hash")?),
+            RecordKind::UnifiedAccount => RecordKey::UnifiedAccount {
+                seed_fingerprint: r.uint256("seed fingerprint")?,
+                coin_type: r.u32("coin type")?,
+                account_id: r.u32("account id")?,
+                ufvk_id: r.uint256("ufvk id")?,
+            },
+            RecordKind::UnifiedFvk => RecordKey::UfvkId(r.uint256("ufvk id")?),
+            // The receiver types are compact sizes, which are at most `MAX_SIZE` and so
+            // fit a `u32`.
+            RecordKind::UnifiedAddrMeta => RecordKey::UnifiedAddress {
+                ufvk_id: r.uint256("ufvk id")?,
+                diversifier_index: r.array("diversifier index")?,
+                receiver_types: r.list(1, "receiver types", |r| {
+                    Ok(ReceiverType::from(r.compact_size("receiver type")? as u32))
+                })?,
+            },
+            RecordKind::RecipientMapping => RecordKey::RecipientMapping {
+                txid: r.uint256("txid")?,
+                recipient: r.string("recipient")?,
+            },
+            RecordKind::Unknown if !rest.is_empty() => RecordKey::Raw(rest.to_vec()),
+            RecordKind::DefaultKey
+            | RecordKind::Version
+            | RecordKind::MinVersion
+            | RecordKind::BestBlock
+            | RecordKind::BestBlockNoMerkle
+            | RecordKind::OrderPosNext
+            | RecordKind::HdChain
+            | RecordKind::MnemonicHdChain
+            | RecordKind::OrchardNoteCommitmentTree
+            | RecordKind::NetworkInfo
+            | RecordKind::WitnessCacheSize
+            | RecordKind::Unknown => {
+                r.finish()?;
+                return Ok(None);
+            }
+        };
+        r.finish()?;
+        Ok(Some(key))
+    }
+}
+
+/// The classifier for zcashd's walletdb keys: the compact-size-prefixed tag picks the
+/// [`RecordKind`], and [`RecordKey::parse`] decodes the rest. A remainder that does not
+/// decode is kept as [`RecordKey::Raw`] rather than lost.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DefaultClassifier;
+
+impl RecordClassifier for DefaultClassifier {
+    fn classify(&self, key: &[u8]) -> (RecordKind, Option<RecordKey>) {
+        let Some((tag, rest)) = split_walletdb_key(key) else {
+            return (RecordKind::Unknown, Some(RecordKey::Raw(key.to_vec())));
+        };
+        let kind = RecordKind::from_tag(tag);
+        let key =
+            RecordKey::parse(kind, rest).unwrap_or_else(|_| Some(RecordKey::Raw(rest.to_vec())));
+        (kind, key)
+    }
+}
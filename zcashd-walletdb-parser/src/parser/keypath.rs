@@ -0,0 +1,228 @@
+//! HD key paths, as zcashd writes them in key metadata: `m/44'/133'/0'/0/5` for
+//! transparent keys (BIP 44) and `m/32'/133'/0'` for Sapling keys (ZIP 32).
+//!
+//! zcashd derives each transparent key at the next index of its chain, so the paths of a
+//! wallet's keys should leave no holes; [`find_gaps`] lists the indices that are missing,
+//! e.g. because a key record was lost.
+
+use std::{collections::BTreeMap, fmt, ops::Range};
+
+use crate::parser::record::{DecodeError, DecodeResult};
+
+/// The bit that marks a hardened child index.
+pub const HARDENED: u32 = 0x8000_0000;
+
+/// BIP 44's purpose, the first component of transparent key paths.
+pub const PURPOSE_BIP44: u32 = 44;
+
+/// ZIP 32's purpose, the first component of Sapling and Orchard key paths.
+pub const PURPOSE_ZIP32: u32 = 32;
+
+/// The account zcashd derives its legacy (pre-unified) transparent keys under,
+/// `ZCASH_LEGACY_ACCOUNT`: the largest hardened index.
+pub const LEGACY_ACCOUNT: u32 = 0x7fff_ffff;
+
+/// One step of a key path, with the hardened bit in the BIP 32 position.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ChildIndex(pub u32);
+
+impl ChildIndex {
+    pub fn normal(index: u32) -> Self {
+        ChildIndex(index & !HARDENED)
+    }
+
+    pub fn hardened(index: u32) -> Self {
+        ChildIndex(index | HARDENED)
+    }
+
+    pub fn is_hardened(self) -> bool {
+        self.0 & HARDENED != 0
+    }
+
+    /// The index without its hardened bit.
+    pub fn index(self) -> u32 {
+        self.0 & !HARDENED
+    }
+}
+
+impl fmt::Display for ChildIndex {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.index())?;
+        if self.is_hardened() {
+            f.write_str("'")?;
+        }
+        Ok(())
+    }
+}
+
+/// A derivation path from the master key. Paths order component by component, so the
+/// keys of one chain sort by index.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct KeyPath {
+    pub components: Vec<ChildIndex>,
+}
+
+impl KeyPath {
+    /// Parse a path such as `m/44'/133'/0'/0/5`. Hardened steps are marked with `'`, or
+    /// `h` / `H` as BIP 380 allows.
+    pub fn parse(path: &str) -> DecodeResult<Self> {
+        let mut steps = path.split('/');
+        if steps.next() != Some("m") {
+            return Err(DecodeError::new(format!(
+                "key path {path:?}: does not start at m"
+            )));
+        }
+        let components = steps
+            .map(|step| {
+                let (digits, hardened) = match step.strip_suffix(['\'', 'h', 'H']) {
+                    Some(digits) => (digits, true),
+                    None => (step, false),
+                };
+                match digits.parse::<u32>() {
+                    Ok(index) if index < HARDENED && digits.bytes().all(|b| b.is_ascii_digit()) => {
+                        Ok(match hardened {
+                            true => ChildIndex::hardened(index),
+                            false => ChildIndex::normal(index),
+                        })
+                    }
+                    _ => Err(DecodeError::new(format!(
+                        "key path {path:?}: invalid step {step:?}"
+                    ))),
+                }
+            })
+            .collect::<DecodeResult<_>>()?;
+        Ok(KeyPath { components })
+    }
+
+    pub fn depth(&self) -> usize {
+        self.components.len()
+    }
+
+    /// The path one step up, or `None` for the master key.
+    pub fn parent(&self) -> Option<KeyPath> {
+        let (_, parent) = self.components.split_last()?;
+        Some(KeyPath {
+            components: parent.to_vec(),
+        })
+    }
+
+    /// The last step, or `None` for the master key.
+    pub fn last(&self) -> Option<ChildIndex> {
+        self.components.last().copied()
+    }
+
+    /// A hardened step at `depth`, without its hardened bit.
+    fn hardened_at(&self, depth: usize) -> Option<u32> {
+        self.components
+            .get(depth)
+            .filter(|step| step.is_hardened())
+            .map(|step| step.index())
+    }
+
+    /// The purpose, e.g. [`PURPOSE_BIP44`] or [`PURPOSE_ZIP32`].
+    pub fn purpose(&self) -> Option<u32> {
+        self.hardened_at(0)
+    }
+
+    /// The SLIP 44 coin type: 133 on mainnet, 1 on testnet and regtest.
+    pub fn coin_type(&self) -> Option<u32> {
+        self.hardened_at(1)
+    }
+
+    /// The account; [`LEGACY_ACCOUNT`] for zcashd's legacy transparent keys.
+    pub fn account(&self) -> Option<u32> {
+        self.hardened_at(2)
+    }
+
+    /// The BIP 44 chain: 0 for external (receiving) keys, 1 for internal (change) keys.
+    /// `None` outside BIP 44, as ZIP 32 paths have no chain.
+    pub fn chain(&self) -> Option<u32> {
+        match self.purpose() {
+            Some(PURPOSE_BIP44) => self
+                .components
+                .get(3)
+                .filter(|step| !step.is_hardened())
+                .map(|step| step.index()),
+            _ => None,
+        }
+    }
+
+    /// The BIP 44 address index within [`Self::chain`].
+    pub fn address_index(&self) -> Option<u32> {
+        self.chain()?;
+        match self.components.get(4) {
+            Some(step) if !step.is_hardened() && self.depth() == 5 => Some(step.index()),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for KeyPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("m")?;
+        for step in &self.components {
+            write!(f, "/{step}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Indices missing from the children of one path.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DerivationGap {
+    /// The path the children derive from.
+    pub parent: KeyPath,
+    pub hardened: bool,
+    /// The missing indices, without the hardened bit.
+    pub missing: Range<u32>,
+}
+
+impl fmt::Display for DerivationGap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mark = if self.hardened { "'" } else { "" };
+        match self.missing.len() {
+            1 => write!(f, "{}/{}{mark} missing", self.parent, self.missing.start),
+            _ => write!(
+                f,
+                "{}/{}{mark} to {}{mark} missing",
+                self.parent,
+                self.missing.start,
+                self.missing.end - 1
+            ),
+        }
+    }
+}
+
+/// The runs of indices missing among the children of each parent in `paths`, counting
+/// from index 0 up to the highest one present. Hardened and normal children are counted
+/// apart.
+pub fn find_gaps<'a>(paths: impl IntoIterator<Item = &'a KeyPath>) -> Vec<DerivationGap> {
+    let mut children: BTreeMap<(KeyPath, bool), Vec<u32>> = BTreeMap::new();
+    for path in paths {
+        if let (Some(parent), Some(last)) = (path.parent(), path.last()) {
+            children
+                .entry((parent, last.is_hardened()))
+                .or_default()
+                .push(last.index());
+        }
+    }
+    let mut gaps = Vec::new();
+    for ((parent, hardened), mut indices) in children {
+        indices.sort_unstable();
+        indices.dedup();
+        let mut next = 0;
+        for index in indices {
+            if index > next {
+                gaps.push(DerivationGap {
+                    parent: parent.clone(),
+                    hardened,
+                    missing: next..index,
+                });
+            }
+            next = index + 1;
+        }
+    }
+    gaps
+}
@@ -0,0 +1,344 @@
+//! Which wallet software, and which network, wrote a wallet.dat.
+//!
+//! zcashd descends from Bitcoin Core, and both (along with their many forks) write the
+//! same Berkeley DB layout and share most record tags. What tells them apart:
+//!
+//! - tags only one family writes: `zkey`, `sapzkey`, `mnemonichdchain`, `networkinfo`
+//!   and the other shielded records for zcashd; descriptor and wallet-flag records for
+//!   Bitcoin Core;
+//! - `networkinfo`, which zcashd writes as the pair `("Zcash", <network id>)`;
+//! - the genesis block hash at the end of the `bestblock` locator, which names the chain.
+//!
+//! The verdict is a heuristic; [`Lineage::evidence`] says what it rests on. When the
+//! evidence disagrees about the network, or the address book holds addresses of another
+//! network, [`Lineage::warnings`] says so: a testnet wallet read as mainnet (or the
+//! reverse) shows funds that do not exist.
+
+use std::{collections::BTreeSet, fmt};
+
+pub use crate::parser::decoders::network::Network;
+use crate::{
+    entry::parser::split_walletdb_key,
+    parser::{
+        decoders::{
+            locator::BlockLocator,
+            network::{NetworkInfoDecoder, ZcashNetwork},
+        },
+        key::RecordKey,
+        record::{DecodeContext, RecordDecoder, RecordKind},
+        serialize::Reader,
+    },
+};
+
+/// Tags only zcashd (and its forks) write.
+const ZCASHD_TAGS: &[&str] = &[
+    "zkey",
+    "czkey",
+    "zkeymeta",
+    "vkey",
+    "sapzkey",
+    "csapzkey",
+    "sapzkeymeta",
+    "sapextfvk",
+    "sapzaddr",
+    "hdseed",
+    "chdseed",
+    "mnemonicphrase",
+    "cmnemonicphrase",
+    "mnemonichdchain",
+    "networkinfo",
+    "witnesscachesize",
+    "orchard_note_commitment_tree",
+    "unifiedaccount",
+    "unifiedfvk",
+    "unifiedaddrmeta",
+    "recipientmapping",
+];
+
+/// Tags only Bitcoin Core (and its forks) write.
+const BITCOIN_TAGS: &[&str] = &[
+    "walletdescriptor",
+    "walletdescriptorcache",
+    "walletdescriptorlhcache",
+    "walletdescriptorkey",
+    "walletdescriptorckey",
+    "activeexternalspk",
+    "activeinternalspk",
+    "flags",
+    "lockedutxo",
+];
+
+/// Genesis block hashes in the byte order they are serialized in, with their chain.
+const GENESIS: &[(&str, &str, &str)] = &[
+    (
+        "08ce3d9731b000c08338455c8a4a6bd05da16e26b11daa1b917184ece80f0400",
+        "Zcash",
+        "main",
+    ),
+    (
+        "382c4a332661c7ed0671f32a34d724619f086c61873bce7c99859dd9920aa605",
+        "Zcash",
+        "test",
+    ),
+    (
+        "27e30134d620e9fe61f719938320bab63e7e72c91b5e23025676f90ed8119f02",
+        "Zcash",
+        "regtest",
+    ),
+    (
+        "6fe28c0ab6f1b372c1a6a246ae63f74f931e8365e15a089c68d6190000000000",
+        "Bitcoin",
+        "main",
+    ),
+    (
+        "43497fd7f826957108f4a30fd9cec3aeba79972084e90ead01ea330900000000",
+        "Bitcoin",
+        "test",
+    ),
+    (
+        "f61eee3b63a380a477a063af32b2bbc97c9ff9f01f2c4225e973988108000000",
+        "Bitcoin",
+        "signet",
+    ),
+    (
+        "06226e46111a0b59caaf126043eb5bbf28c34f3a5e332a1fc7b2b73cf188910f",
+        "Bitcoin",
+        "regtest",
+    ),
+    (
+        "e2bf047e7e5a191aa4ef34d314979dc9986e0f19251edaba5940fd1fe365a712",
+        "Litecoin",
+        "main",
+    ),
+    (
+        "9156352c1818b32e90c9e792efd6a11a82fe7956a630f03bbee236cedae3911a",
+        "Dogecoin",
+        "main",
+    ),
+];
+
+/// The wallet software family a file comes from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WalletSoftware {
+    Zcashd,
+    /// A coin forked from zcashd (it writes shielded records, but for another chain).
+    ZcashdFork,
+    BitcoinCore,
+    /// A coin forked from Bitcoin Core, e.g. Litecoin or Dogecoin.
+    BitcoinCoreFork,
+    /// Not enough evidence either way.
+    Unknown,
+}
+
+impl fmt::Display for WalletSoftware {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            WalletSoftware::Zcashd => "zcashd",
+            WalletSoftware::ZcashdFork => "zcashd fork",
+            WalletSoftware::BitcoinCore => "Bitcoin Core",
+            WalletSoftware::BitcoinCoreFork => "Bitcoin Core fork",
+            WalletSoftware::Unknown => "unknown",
+        })
+    }
+}
+
+/// Evidence that a wallet is being read for the wrong network.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NetworkMismatch {
+    /// `networkinfo` names one chain and the best block locator ends at the genesis
+    /// block of another.
+    Genesis {
+        networkinfo: Network,
+        genesis: Network,
+    },
+    /// An address book entry is encoded for other networks than the wallet's.
+    Address {
+        address: String,
+        wallet: ZcashNetwork,
+        encoded_for: &'static [ZcashNetwork],
+    },
+}
+
+impl fmt::Display for NetworkMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NetworkMismatch::Genesis {
+                networkinfo,
+                genesis,
+            } => write!(
+                f,
+                "NETWORK MISMATCH: networkinfo names {networkinfo}, but the best block \
+                 locator ends at the {genesis} genesis block"
+            ),
+            NetworkMismatch::Address {
+                address,
+                wallet,
+                encoded_for,
+            } => {
+                let networks: Vec<&str> = encoded_for.iter().map(|n| n.id()).collect();
+                write!(
+                    f,
+                    "NETWORK MISMATCH: {wallet} wallet holds address {address}, \
+                     encoded for {}",
+                    networks.join(" or ")
+                )
+            }
+        }
+    }
+}
+
+/// The outcome of [`detect_lineage`].
+#[derive(Debug, Clone)]
+pub struct Lineage {
+    pub software: WalletSoftware,
+    pub network: Option<Network>,
+    /// What the verdict rests on, one observation per line.
+    pub evidence: Vec<String>,
+    /// Contradictions about the network; empty when the evidence agrees.
+    pub warnings: Vec<NetworkMismatch>,
+}
+
+impl fmt::Display for Lineage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Lineage {{")?;
+        writeln!(f, "  software     : {}", self.software)?;
+        match &self.network {
+            Some(network) => writeln!(f, "  network      : {network}")?,
+            None => writeln!(f, "  network      : unknown")?,
+        }
+        for line in &self.evidence {
+            writeln!(f, "  evidence     : {line}")?;
+        }
+        for warning in &self.warnings {
+            writeln!(f, "  warning      : {warning}")?;
+        }
+        write!(f, "}}")
+    }
+}
+
+/// The `(coin, network id)` pair of a `networkinfo` value.
+fn parse_networkinfo(value: &[u8]) -> Option<Network> {
+    NetworkInfoDecoder
+        .decode(&DecodeContext::default(), None, value)
+        .ok()
+}
+
+/// The address an address book record is keyed by.
+fn address_book_key(tag: &str, rest: &[u8]) -> Option<String> {
+    match RecordKey::parse(RecordKind::from_tag(tag), rest).ok()?? {
+        RecordKey::Address(address) | RecordKey::DestData { address, .. } => Some(address),
+        _ => None,
+    }
+}
+
+/// The chain of the first known genesis hash in a `bestblock` locator. The last hash is
+/// the genesis block, but any hash on the list is checked.
+fn locator_chain(value: &[u8]) -> Option<Network> {
+    let locator = BlockLocator::read(&mut Reader::new(value)).ok()?;
+    locator.hashes.iter().find_map(|hash| {
+        let hash = hex::encode(hash);
+        GENESIS
+            .iter()
+            .find(|(genesis, ..)| *genesis == hash)
+            .map(|(_, coin, name)| Network {
+                coin: coin.to_string(),
+                name: name.to_string(),
+            })
+    })
+}
+
+/// Classify the wallet the `(key, value)` records come from.
+pub fn detect_lineage<K, V>(records: impl IntoIterator<Item = (K, V)>) -> Lineage
+where
+    K: AsRef<[u8]>,
+    V: AsRef<[u8]>,
+{
+    let mut zcashd_tags = BTreeSet::new();
+    let mut bitcoin_tags = BTreeSet::new();
+    let mut networkinfo = None;
+    let mut genesis = None;
+    let mut addresses = BTreeSet::new();
+    for (key, value) in records {
+        let Some((tag, rest)) = split_walletdb_key(key.as_ref()) else {
+            continue;
+        };
+        if let Some(&known) = ZCASHD_TAGS.iter().find(|&&t| t == tag) {
+            zcashd_tags.insert(known);
+        }
+        if let Some(&known) = BITCOIN_TAGS.iter().find(|&&t| t == tag) {
+            bitcoin_tags.insert(known);
+        }
+        match tag {
+            "networkinfo" => networkinfo = parse_networkinfo(value.as_ref()),
+            "bestblock" | "bestblock_nomerkle" if genesis.is_none() => {
+                genesis = locator_chain(value.as_ref());
+            }
+            "name" | "purpose" | "destdata" => addresses.extend(address_book_key(tag, rest)),
+            _ => {}
+        }
+    }
+
+    let mut evidence = Vec::new();
+    if !zcashd_tags.is_empty() {
+        let tags: Vec<&str> = zcashd_tags.iter().copied().collect();
+        evidence.push(format!("zcashd records: {}", tags.join(", ")));
+    }
+    if !bitcoin_tags.is_empty() {
+        let tags: Vec<&str> = bitcoin_tags.iter().copied().collect();
+        evidence.push(format!("Bitcoin Core records: {}", tags.join(", ")));
+    }
+    if let Some(network) = &networkinfo {
+        evidence.push(format!("networkinfo names {network}"));
+    }
+    if let Some(network) = &genesis {
+        evidence.push(format!(
+            "best block locator ends at the {network} genesis block"
+        ));
+    }
+
+    let mut warnings = Vec::new();
+    if let (Some(networkinfo), Some(genesis)) = (&networkinfo, &genesis)
+        && networkinfo != genesis
+    {
+        warnings.push(NetworkMismatch::Genesis {
+            networkinfo: networkinfo.clone(),
+            genesis: genesis.clone(),
+        });
+    }
+
+    // The genesis hash is the strongest evidence of the chain; `networkinfo` is kept
+    // as written even by forks that never renamed it.
+    let network = genesis.or(networkinfo);
+    if let Some(wallet) = network.as_ref().and_then(Network::zcash) {
+        for address in addresses {
+            let encoded_for = ZcashNetwork::of_address(&address);
+            if !encoded_for.is_empty() && !encoded_for.contains(&wallet) {
+                warnings.push(NetworkMismatch::Address {
+                    address,
+                    wallet,
+                    encoded_for,
+                });
+            }
+        }
+    }
+    let coin = network.as_ref().map(|n| n.coin.as_str());
+    let software = if !zcashd_tags.is_empty() || coin == Some("Zcash") {
+        match coin {
+            None | Some("Zcash") => WalletSoftware::Zcashd,
+            Some(_) => WalletSoftware::ZcashdFork,
+        }
+    } else if !bitcoin_tags.is_empty() || coin.is_some() {
+        match coin {
+            None | Some("Bitcoin") => WalletSoftware::BitcoinCore,
+            Some(_) => WalletSoftware::BitcoinCoreFork,
+        }
+    } else {
+        WalletSoftware::Unknown
+    };
+    Lineage {
+        software,
+        network,
+        evidence,
+        warnings,
+    }
+}
@@ -0,0 +1,294 @@
+//! Cross-record consistency checks: records that name other records the wallet does not
+//! hold, and secrets kept in a way the rest of the wallet contradicts.
+//!
+//! Each record can decode cleanly and the wallet still be broken as a whole: a `ckey`
+//! without the `mkey` it was encrypted under can never be decrypted, and a `pool` entry
+//! whose key is gone makes zcashd fail the next time it hands out an address. Dangling
+//! metadata is harmless to zcashd but points at records lost to damage or a bad merge.
+//! A record that does not decode at all is reported as well, since every other check is
+//! blind to it.
+
+use std::{collections::BTreeSet, fmt};
+
+use crate::{
+    parser::{
+        decoders::{sapling::SaplingPaymentAddress, sprout::SproutPaymentAddress},
+        record::RecordKind,
+        serialize::uint256_hex,
+        wallet::ZcashdWallet,
+    },
+    storage::consistency::Severity,
+};
+
+/// A contradiction between the wallet's records.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LintIssue {
+    /// A record whose value does not decode, with the [`RecordError`] describing it.
+    ///
+    /// [`RecordError`]: crate::parser::wallet::RecordError
+    Undecodable { kind: RecordKind, error: String },
+    /// Encrypted secrets, but no `mkey` to decrypt them with.
+    EncryptedWithoutMasterKey { kind: RecordKind, count: usize },
+    /// Secrets in the clear in a wallet encrypted with a passphrase, which zcashd
+    /// encrypts all at once.
+    PlaintextInEncryptedWallet { kind: RecordKind, count: usize },
+    /// A `pool` entry whose key pair the wallet does not hold.
+    PoolKeyMissing { index: i64, pubkey: Vec<u8> },
+    /// A `keymeta` record of a key pair the wallet does not hold.
+    KeyMetadataWithoutKey { pubkey: Vec<u8> },
+    /// A `zkeymeta` record of a Sprout address the wallet holds no key for.
+    SproutMetadataWithoutKey { address: SproutPaymentAddress },
+    /// A key pair without a `keymeta` record, so without a creation time to start a
+    /// rescan from.
+    KeyWithoutMetadata { pubkey: Vec<u8> },
+    /// A `sapzaddr` record whose incoming viewing key no spending or watch-only key has.
+    SaplingAddressWithoutKey {
+        address: SaplingPaymentAddress,
+        ivk: [u8; 32],
+    },
+    /// A `unifiedaccount` record whose full viewing key the wallet does not hold.
+    UnifiedAccountWithoutFvk { account_id: u32, ufvk_id: [u8; 32] },
+    /// A `unifiedaddrmeta` record whose full viewing key the wallet does not hold.
+    UnifiedAddressWithoutFvk {
+        ufvk_id: [u8; 32],
+        diversifier_index: [u8; 11],
+    },
+}
+
+impl LintIssue {
+    pub fn severity(&self) -> Severity {
+        match self {
+            LintIssue::Undecodable { .. }
+            | LintIssue::EncryptedWithoutMasterKey { .. }
+            | LintIssue::PoolKeyMissing { .. }
+            | LintIssue::UnifiedAddressWithoutFvk { .. }
+            | LintIssue::UnifiedAccountWithoutFvk { .. } => Severity::Error,
+            LintIssue::PlaintextInEncryptedWallet { .. }
+            | LintIssue::KeyMetadataWithoutKey { .. }
+            | LintIssue::SproutMetadataWithoutKey { .. }
+            | LintIssue::SaplingAddressWithoutKey { .. } => Severity::Warning,
+            LintIssue::KeyWithoutMetadata { .. } => Severity::Info,
+        }
+    }
+}
+
+impl fmt::Display for LintIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LintIssue::Undecodable { error, .. } => write!(f, "{error}"),
+            LintIssue::EncryptedWithoutMasterKey { kind, count } => {
+                write!(f, "{count} {kind} records but no mkey to decrypt them")
+            }
+            LintIssue::PlaintextInEncryptedWallet { kind, count } => write!(
+                f,
+                "{count} {kind} records hold secrets in the clear in an encrypted wallet"
+            ),
+            LintIssue::PoolKeyMissing { index, pubkey } => write!(
+                f,
+                "pool {index}: no key for public key {}",
+                hex::encode(pubkey)
+            ),
+            LintIssue::KeyMetadataWithoutKey { pubkey } => {
+                write!(f, "keymeta {}: no key, wkey or ckey", hex::encode(pubkey))
+            }
+            LintIssue::SproutMetadataWithoutKey { address } => {
+                write!(f, "zkeymeta {}: no zkey, czkey or vkey", address.a_pk_hex())
+            }
+            LintIssue::KeyWithoutMetadata { pubkey } => {
+                write!(f, "key {}: no keymeta", hex::encode(pubkey))
+            }
+            LintIssue::SaplingAddressWithoutKey { address, ivk } => write!(
+                f,
+                "sapzaddr {}/{}: no sapzkey, csapzkey or sapextfvk for ivk {}",
+                hex::encode(address.diversifier),
+                uint256_hex(&address.pk_d),
+                uint256_hex(ivk)
+            ),
+            LintIssue::UnifiedAccountWithoutFvk {
+                account_id,
+                ufvk_id,
+            } => write!(
+                f,
+                "unifiedaccount {account_id}: no unifiedfvk {}",
+                uint256_hex(ufvk_id)
+            ),
+            LintIssue::UnifiedAddressWithoutFvk {
+                ufvk_id,
+                diversifier_index,
+            } => write!(
+                f,
+                "unifiedaddrmeta {}: no unifiedfvk {}",
+                hex::encode(diversifier_index),
+                uint256_hex(ufvk_id)
+            ),
+        }
+    }
+}
+
+/// The outcome of [`LintReport::of`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LintReport {
+    pub issues: Vec<LintIssue>,
+}
+
+impl LintReport {
+    /// Check the records of `wallet` against each other.
+    pub fn of(wallet: &ZcashdWallet) -> Self {
+        let (keys, crypto) = (&wallet.keys, &wallet.crypto);
+        let mut issues: Vec<LintIssue> = wallet
+            .errors
+            .iter()
+            .map(|e| LintIssue::Undecodable {
+                kind: e.kind,
+                error: e.to_string(),
+            })
+            .collect();
+
+        let encrypted = [
+            (RecordKind::CKey, keys.encrypted_transparent.len()),
+            (RecordKind::CZKey, keys.encrypted_sprout.len()),
+            (RecordKind::CSapZKey, keys.encrypted_sapling.len()),
+            (
+                RecordKind::CHdSeed,
+                usize::from(crypto.encrypted_hd_seed.is_some()),
+            ),
+            (
+                RecordKind::CMnemonicPhrase,
+                usize::from(crypto.encrypted_mnemonic.is_some()),
+            ),
+        ];
+        let plaintext = [
+            (RecordKind::Key, keys.transparent.len()),
+            (RecordKind::WKey, keys.wallet_keys.len()),
+            (RecordKind::ZKey, keys.sprout.len()),
+            (RecordKind::SapZKey, keys.sapling.len()),
+            (RecordKind::HdSeed, usize::from(crypto.hd_seed.is_some())),
+            (
+                RecordKind::MnemonicPhrase,
+                usize::from(crypto.mnemonic.is_some()),
+            ),
+        ];
+        // zcashd encrypts every secret when the wallet gets a passphrase.
+        let encrypted_wallet = crypto.is_encrypted();
+        let mismatched = match encrypted_wallet {
+            false => &encrypted[..],
+            true => &plaintext[..],
+        };
+        for &(kind, count) in mismatched.iter().filter(|&&(_, count)| count > 0) {
+            issues.push(match encrypted_wallet {
+                false => LintIssue::EncryptedWithoutMasterKey { kind, count },
+                true => LintIssue::PlaintextInEncryptedWallet { kind, count },
+            });
+        }
+
+        let has_key = |pubkey: &Vec<u8>| {
+            keys.transparent.contains_key(pubkey)
+                || keys.wallet_keys.contains_key(pubkey)
+                || keys.encrypted_transparent.contains_key(pubkey)
+        };
+        for (index, entry) in &keys.key_pool {
+            if !has_key(&entry.pubkey) {
+                issues.push(LintIssue::PoolKeyMissing {
+                    index: *index,
+                    pubkey: entry.pubkey.clone(),
+                });
+            }
+        }
+        for pubkey in keys.transparent_metadata.keys() {
+            if !has_key(pubkey) {
+                issues.push(LintIssue::KeyMetadataWithoutKey {
+                    pubkey: pubkey.clone(),
+                });
+            }
+        }
+        let pubkeys = keys
+            .transparent
+            .keys()
+            .chain(keys.wallet_keys.keys())
+            .chain(keys.encrypted_transparent.keys());
+        for pubkey in pubkeys.collect::<BTreeSet<_>>() {
+            if !keys.transparent_metadata.contains_key(pubkey) {
+                issues.push(LintIssue::KeyWithoutMetadata {
+                    pubkey: pubkey.clone(),
+                });
+            }
+        }
+        for address in keys.sprout_metadata.keys() {
+            if !keys.sprout.contains_key(address)
+                && !keys.encrypted_sprout.contains_key(address)
+                && !keys.sprout_viewing.contains_key(address)
+            {
+                issues.push(LintIssue::SproutMetadataWithoutKey { address: *address });
+            }
+        }
+
+        let watch_only: BTreeSet<[u8; 32]> = keys
+            .sapling_watch_only
+            .iter()
+            .map(|fvk| fvk.ivk())
+            .collect();
+        for (address, ivk) in &keys.sapling_addresses {
+            if !keys.sapling.contains_key(ivk)
+                && !keys.encrypted_sapling.contains_key(ivk)
+                && !watch_only.contains(ivk)
+            {
+                issues.push(LintIssue::SaplingAddressWithoutKey {
+                    address: *address,
+                    ivk: *ivk,
+                });
+            }
+        }
+
+        for account in &keys.unified_accounts {
+            if keys.account_ufvk(account).is_none() {
+                issues.push(LintIssue::UnifiedAccountWithoutFvk {
+                    account_id: account.account_id,
+                    ufvk_id: account.ufvk_id,
+                });
+            }
+        }
+        for address in &keys.unified_addresses {
+            if !keys.unified_fvks.contains_key(&address.ufvk_id) {
+                issues.push(LintIssue::UnifiedAddressWithoutFvk {
+                    ufvk_id: address.ufvk_id,
+                    diversifier_index: address.diversifier_index,
+                });
+            }
+        }
+
+        issues.sort_by_key(|issue| std::cmp::Reverse(issue.severity()));
+        LintReport { issues }
+    }
+
+    /// No warnings or errors; informational findings are allowed.
+    pub fn is_clean(&self) -> bool {
+        self.max_severity().is_none_or(|s| s == Severity::Info)
+    }
+
+    pub fn max_severity(&self) -> Option<Severity> {
+        self.issues.iter().map(LintIssue::severity).max()
+    }
+
+    pub fn count(&self, severity: Severity) -> usize {
+        self.issues
+            .iter()
+            .filter(|issue| issue.severity() == severity)
+            .count()
+    }
+}
+
+impl fmt::Display for LintReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "LintReport {{")?;
+        writeln!(f, "  errors       : {}", self.count(Severity::Error))?;
+        writeln!(f, "  warnings     : {}", self.count(Severity::Warning))?;
+        for issue in &self.issues {
+            writeln!(
+                f,
+                "  {:<13}: {issue}",
+                format!("{:?}", issue.severity()).to_lowercase()
+            )?;
+        }
+        write!(f, "}}")
+    }
+}
@@ -0,0 +1,441 @@
+//! zcashd's incremental Merkle trees of note commitments, as `tx` records cache them in
+//! their Sprout and Sapling note data, and the roots (anchors) they commit to.
+//!
+//! An `IncrementalMerkleTree` keeps only the frontier: the two rightmost leaves and, for
+//! each level above, the left sibling on the path up from them if there is one. An
+//! `IncrementalWitness` is the tree as of the note's own commitment, plus the nodes
+//! filled in to its right since; together they give the note's authentication path,
+//! and the root a spend of the note proves membership in.
+//!
+//! A wallet keeps the witnesses of all its notes at the same height, its best block, so
+//! witnesses of one pool at one height must agree on the root. [`check_witnesses`] checks
+//! that, and that each witness is for the note it is filed under.
+
+use std::{
+    collections::{BTreeMap, VecDeque},
+    fmt,
+    sync::OnceLock,
+};
+
+use crate::{
+    crypto::{pedersen, sha256::sha256_compress},
+    parser::{
+        balance::best_height,
+        decoders::wallet_tx::{NoteOutPoint, WalletTx},
+        record::DecodeResult,
+        serialize::{Reader, uint256_hex},
+        wallet::ZcashdWallet,
+    },
+};
+
+/// How a note commitment tree hashes its nodes.
+pub trait MerkleHasher {
+    /// The depth of the tree.
+    const DEPTH: usize;
+
+    /// The leaf of a position no note commitment fills yet.
+    fn uncommitted() -> [u8; 32];
+
+    /// The parent of `left` and `right` at `level`, counting the leaves as level 0.
+    fn combine(level: usize, left: &[u8; 32], right: &[u8; 32]) -> [u8; 32];
+
+    /// The roots of empty subtrees, by height: the uncommitted leaf first.
+    fn empty_roots() -> &'static [[u8; 32]];
+}
+
+fn compute_empty_roots<H: MerkleHasher>() -> Vec<[u8; 32]> {
+    let mut roots = vec![H::uncommitted()];
+    for level in 0..H::DEPTH {
+        let below = roots[level];
+        roots.push(H::combine(level, &below, &below));
+    }
+    roots
+}
+
+/// The Sprout tree: depth 29, nodes hashed with the bare SHA-256 compression function.
+#[derive(Debug, Clone, Copy)]
+pub struct SproutHasher;
+
+impl MerkleHasher for SproutHasher {
+    const DEPTH: usize = 29;
+
+    fn uncommitted() -> [u8; 32] {
+        [0; 32]
+    }
+
+    fn combine(_level: usize, left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+        let mut block = [0; 64];
+        block[..32].copy_from_slice(left);
+        block[32..].copy_from_slice(right);
+        sha256_compress(&block)
+    }
+
+    fn empty_roots() -> &'static [[u8; 32]] {
+        static ROOTS: OnceLock<Vec<[u8; 32]>> = OnceLock::new();
+        ROOTS.get_or_init(compute_empty_roots::<Self>)
+    }
+}
+
+/// The Sapling tree: depth 32, nodes hashed with the Pedersen hash, empty leaves 1.
+#[derive(Debug, Clone, Copy)]
+pub struct SaplingHasher;
+
+impl MerkleHasher for SaplingHasher {
+    const DEPTH: usize = 32;
+
+    fn uncommitted() -> [u8; 32] {
+        let mut leaf = [0; 32];
+        leaf[0] = 1;
+        leaf
+    }
+
+    fn combine(level: usize, left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+        pedersen::merkle_hash(level as u8, left, right)
+    }
+
+    fn empty_roots() -> &'static [[u8; 32]] {
+        static ROOTS: OnceLock<Vec<[u8; 32]>> = OnceLock::new();
+        ROOTS.get_or_init(compute_empty_roots::<Self>)
+    }
+}
+
+fn read_optional_hash(r: &mut Reader<'_>, what: &str) -> DecodeResult<Option<[u8; 32]>> {
+    r.optional(what, |r| r.uint256(what))
+}
+
+/// An `IncrementalMerkleTree` frontier, as Sprout and Sapling witnesses store it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct MerkleFrontier {
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::util::serde_hex::opt_bytes")
+    )]
+    pub left: Option<[u8; 32]>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::util::serde_hex::opt_bytes")
+    )]
+    pub right: Option<[u8; 32]>,
+    /// Left siblings from level 1 up, where the path from the rightmost leaf has one.
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::util::serde_hex::opt_byte_list")
+    )]
+    pub parents: Vec<Option<[u8; 32]>>,
+}
+
+impl MerkleFrontier {
+    pub fn read(r: &mut Reader<'_>) -> DecodeResult<Self> {
+        Ok(MerkleFrontier {
+            left: read_optional_hash(r, "left")?,
+            right: read_optional_hash(r, "right")?,
+            parents: r.list(1, "parents", |r| read_optional_hash(r, "parent"))?,
+        })
+    }
+
+    /// The number of leaves appended.
+    pub fn size(&self) -> u64 {
+        let leaves = u64::from(self.left.is_some()) + u64::from(self.right.is_some());
+        let parents = self.parents.iter().enumerate();
+        leaves
+            + parents
+                .filter(|(_, parent)| parent.is_some())
+                .map(|(i, _)| 1u64.checked_shl(i as u32 + 1).unwrap_or(0))
+                .sum::<u64>()
+    }
+
+    /// The most recently appended leaf.
+    pub fn last(&self) -> Option<[u8; 32]> {
+        self.right.or(self.left)
+    }
+
+    /// The root of the tree of `H`.
+    pub fn root<H: MerkleHasher>(&self) -> [u8; 32] {
+        self.root_with::<H>(H::DEPTH, VecDeque::new())
+    }
+
+    /// The root of the tree truncated to `depth`, with the missing right-hand nodes
+    /// taken from `filler` first and then from the empty roots.
+    fn root_with<H: MerkleHasher>(&self, depth: usize, mut filler: VecDeque<[u8; 32]>) -> [u8; 32] {
+        let empty = H::empty_roots();
+        let mut next = |level: usize| filler.pop_front().unwrap_or(empty[level]);
+        let left = self.left.unwrap_or_else(|| next(0));
+        let right = self.right.unwrap_or_else(|| next(0));
+        let mut root = H::combine(0, &left, &right);
+        let mut level = 1;
+        for parent in &self.parents {
+            root = match parent {
+                Some(parent) => H::combine(level, parent, &root),
+                None => H::combine(level, &root, &next(level)),
+            };
+            level += 1;
+        }
+        while level < depth {
+            root = H::combine(level, &root, &next(level));
+            level += 1;
+        }
+        root
+    }
+
+    /// The level of the next empty subtree after skipping `skip` of them: where a
+    /// witness's cursor is building the next node of its path.
+    fn next_depth(&self, mut skip: usize) -> usize {
+        let mut empty_at = |level: usize| match skip {
+            0 => Some(level),
+            _ => {
+                skip -= 1;
+                None
+            }
+        };
+        if self.left.is_none()
+            && let Some(level) = empty_at(0)
+        {
+            return level;
+        }
+        if self.right.is_none()
+            && let Some(level) = empty_at(0)
+        {
+            return level;
+        }
+        for (i, parent) in self.parents.iter().enumerate() {
+            if parent.is_none()
+                && let Some(level) = empty_at(i + 1)
+            {
+                return level;
+            }
+        }
+        self.parents.len() + 1 + skip
+    }
+}
+
+/// An `IncrementalWitness`: the authentication path of one note commitment, kept up to
+/// date as blocks arrive.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Witness {
+    /// The tree as of the witnessed note commitment, its last leaf.
+    pub tree: MerkleFrontier,
+    /// Completed right-hand nodes of the path, from the bottom up.
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::util::serde_hex::byte_list")
+    )]
+    pub filled: Vec<[u8; 32]>,
+    /// The next right-hand node, still being built.
+    pub cursor: Option<MerkleFrontier>,
+}
+
+impl Witness {
+    pub fn read(r: &mut Reader<'_>) -> DecodeResult<Self> {
+        Ok(Witness {
+            tree: MerkleFrontier::read(r)?,
+            filled: r.list(32, "filled", |r| r.uint256("filled"))?,
+            cursor: match r.bool("cursor flag")? {
+                true => Some(MerkleFrontier::read(r)?),
+                false => None,
+            },
+        })
+    }
+
+    /// The position of the witnessed note commitment in the tree.
+    pub fn position(&self) -> u64 {
+        self.tree.size().saturating_sub(1)
+    }
+
+    /// The witnessed note commitment.
+    pub fn element(&self) -> Option<[u8; 32]> {
+        self.tree.last()
+    }
+
+    /// The root of the tree as of the witness: the anchor a spend of the note uses.
+    pub fn root<H: MerkleHasher>(&self) -> [u8; 32] {
+        let mut path: VecDeque<[u8; 32]> = self.filled.iter().copied().collect();
+        if let Some(cursor) = &self.cursor {
+            let cursor_depth = self.tree.next_depth(self.filled.len());
+            path.push_back(cursor.root_with::<H>(cursor_depth, VecDeque::new()));
+        }
+        self.tree.root_with::<H>(H::DEPTH, path)
+    }
+}
+
+/// A note whose cached witnesses do not fit the rest of the wallet.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WitnessIssue {
+    /// The newest witness is for another height than the wallet's best block.
+    Stale {
+        note: NoteOutPoint,
+        witness_height: i32,
+        best_height: u32,
+    },
+    /// The witness is for another leaf than the note's commitment, which may be missing
+    /// from the transaction altogether.
+    WrongLeaf {
+        note: NoteOutPoint,
+        leaf: Option<[u8; 32]>,
+        commitment: Option<[u8; 32]>,
+    },
+    /// The root differs from the one most notes of the pool have at the same height.
+    RootMismatch {
+        note: NoteOutPoint,
+        witness_height: i32,
+        root: [u8; 32],
+        expected: [u8; 32],
+    },
+}
+
+impl fmt::Display for WitnessIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let hash = |h: &Option<[u8; 32]>| h.as_ref().map_or("none".into(), uint256_hex);
+        match self {
+            WitnessIssue::Stale {
+                note,
+                witness_height,
+                best_height,
+            } => write!(
+                f,
+                "{}: witnessed at height {witness_height}, best block is {best_height}",
+                note_label(note)
+            ),
+            WitnessIssue::WrongLeaf {
+                note,
+                leaf,
+                commitment,
+            } => write!(
+                f,
+                "{}: witness is for leaf {}, note commitment is {}",
+                note_label(note),
+                hash(leaf),
+                hash(commitment)
+            ),
+            WitnessIssue::RootMismatch {
+                note,
+                witness_height,
+                root,
+                expected,
+            } => write!(
+                f,
+                "{}: root {} at height {witness_height}, other notes have {}",
+                note_label(note),
+                uint256_hex(root),
+                uint256_hex(expected)
+            ),
+        }
+    }
+}
+
+fn note_label(note: &NoteOutPoint) -> String {
+    match note {
+        NoteOutPoint::Sprout(op) => format!("sprout {}:{}:{}", uint256_hex(&op.txid), op.js, op.n),
+        NoteOutPoint::Sapling(op) => format!("sapling {}:{}", uint256_hex(&op.txid), op.n),
+        NoteOutPoint::Orchard { txid, action } => format!("orchard {}:{action}", uint256_hex(txid)),
+    }
+}
+
+/// The newest witness of one note, with what it is checked against.
+struct NewestWitness<'a> {
+    note: NoteOutPoint,
+    witness: &'a Witness,
+    /// The commitment the transaction holds for the note.
+    commitment: Option<[u8; 32]>,
+    witness_height: i32,
+    root: [u8; 32],
+}
+
+fn newest_witnesses(wtx: &WalletTx) -> Vec<NewestWitness<'_>> {
+    let tx = wtx.tx();
+    let sprout = wtx.sprout_note_data.iter().filter_map(|(op, data)| {
+        let witness = data.witnesses.first()?;
+        let commitment = tx.sprout.as_ref().and_then(|bundle| {
+            let js = bundle.joinsplits.get(usize::try_from(op.js).ok()?)?;
+            js.commitments.get(usize::from(op.n)).copied()
+        });
+        Some(NewestWitness {
+            note: NoteOutPoint::Sprout(*op),
+            witness,
+            commitment,
+            witness_height: data.witness_height,
+            root: witness.root::<SproutHasher>(),
+        })
+    });
+    let sapling = wtx.sapling_note_data.iter().filter_map(|(op, data)| {
+        let witness = data.witnesses.first()?;
+        let commitment = tx
+            .sapling
+            .as_ref()
+            .and_then(|bundle| bundle.outputs.get(op.n as usize))
+            .map(|output| output.cmu);
+        Some(NewestWitness {
+            note: NoteOutPoint::Sapling(*op),
+            witness,
+            commitment,
+            witness_height: data.witness_height,
+            root: witness.root::<SaplingHasher>(),
+        })
+    });
+    sprout.chain(sapling).collect()
+}
+
+/// Check the newest cached witness of every Sprout and Sapling note of `wallet`: that it
+/// is for the note's commitment, that it is as of the wallet's best block when that is
+/// known, and that the notes of each pool witnessed at the same height share one root.
+pub fn check_witnesses(wallet: &ZcashdWallet) -> Vec<WitnessIssue> {
+    let best = best_height(wallet);
+    let mut issues = Vec::new();
+    // The root of each note, by pool (whether Sapling) and witness height.
+    type Roots = Vec<(NoteOutPoint, [u8; 32])>;
+    let mut roots: BTreeMap<(bool, i32), Roots> = BTreeMap::new();
+    for wtx in wallet.transactions.values() {
+        for newest in newest_witnesses(wtx) {
+            let NewestWitness {
+                note,
+                commitment,
+                witness_height,
+                root,
+                ..
+            } = newest;
+            let leaf = newest.witness.element();
+            if leaf.is_none() || leaf != commitment {
+                issues.push(WitnessIssue::WrongLeaf {
+                    note,
+                    leaf,
+                    commitment,
+                });
+            }
+            if let Some(best_height) = best
+                && i64::from(witness_height) != i64::from(best_height)
+            {
+                issues.push(WitnessIssue::Stale {
+                    note,
+                    witness_height,
+                    best_height,
+                });
+            }
+            let sapling = matches!(note, NoteOutPoint::Sapling(_));
+            roots
+                .entry((sapling, witness_height))
+                .or_default()
+                .push((note, root));
+        }
+    }
+    for ((_, witness_height), notes) in roots {
+        let mut counts: BTreeMap<[u8; 32], usize> = BTreeMap::new();
+        for (_, root) in &notes {
+            *counts.entry(*root).or_default() += 1;
+        }
+        let Some((&expected, _)) = counts.iter().max_by_key(|(_, count)| **count) else {
+            continue;
+        };
+        for (note, root) in notes {
+            if root != expected {
+                issues.push(WitnessIssue::RootMismatch {
+                    note,
+                    witness_height,
+                    root,
+                    expected,
+                });
+            }
+        }
+    }
+    issues
+}
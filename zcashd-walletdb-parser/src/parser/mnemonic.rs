@@ -0,0 +1,247 @@
+//! ZIP 339 (BIP 39) seed phrases: checking a phrase against its wordlist and checksum,
+//! deriving the seed it stands for, and cross-checking that seed against the
+//! fingerprints the rest of the wallet names.
+//!
+//! A phrase of `n` words encodes `11 n` bits: the entropy, then the first `n / 3` bits of
+//! its SHA-256 as a checksum. The seed is not the entropy but PBKDF2-HMAC-SHA-512 of the
+//! phrase itself, salted `mnemonic` (zcashd sets no passphrase), so a phrase that has been
+//! retyped with a different but valid word derives an unrelated seed. Keys, accounts and
+//! the HD chain all name their seed by fingerprint, which is how a divergence shows.
+//!
+//! Only the English wordlist, the one zcashd generates phrases in, is included. The
+//! seed derivation is exact for any phrase written in ASCII; others would need Unicode
+//! NFKD normalization first, and are not checked.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt,
+    sync::OnceLock,
+};
+
+use crate::{
+    crypto::{Digest, pbkdf2_hmac, sha256::Sha256, sha512::Sha512},
+    parser::{
+        decoders::seed::{Language, MnemonicPhrase, seed_fingerprint},
+        record::RecordKind,
+        secret::SecretBytes,
+        serialize::uint256_hex,
+        wallet::ZcashdWallet,
+    },
+};
+
+/// The length of a BIP 39 seed.
+pub const SEED_LEN: usize = 64;
+
+const PBKDF2_ROUNDS: u32 = 2048;
+
+/// The BIP 39 English wordlist, in index order.
+pub fn english_wordlist() -> &'static [&'static str] {
+    static WORDS: OnceLock<Vec<&'static str>> = OnceLock::new();
+    WORDS.get_or_init(|| include_str!("mnemonic/english.txt").lines().collect())
+}
+
+/// Why a phrase is not a valid ZIP 339 phrase. Words of the phrase are never included,
+/// only their positions.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MnemonicError {
+    /// No wordlist for the language is included.
+    UnsupportedLanguage(Language),
+    /// Not 12, 15, 18, 21 or 24 words.
+    WordCount(usize),
+    /// The word at `position`, counting from 1, is not in the wordlist.
+    UnknownWord { position: usize },
+    /// Every word is in the wordlist, but the checksum bits do not match the entropy.
+    Checksum,
+}
+
+impl fmt::Display for MnemonicError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MnemonicError::UnsupportedLanguage(language) => {
+                write!(f, "no {language} wordlist to check the phrase against")
+            }
+            MnemonicError::WordCount(n) => write!(f, "{n} words, expected 12 to 24 in threes"),
+            MnemonicError::UnknownWord { position } => {
+                write!(f, "word {position} is not in the wordlist")
+            }
+            MnemonicError::Checksum => f.write_str("checksum does not match"),
+        }
+    }
+}
+
+impl std::error::Error for MnemonicError {}
+
+/// The entropy `phrase` encodes, once its words and checksum are checked.
+pub fn phrase_entropy(phrase: &str, language: Language) -> Result<SecretBytes, MnemonicError> {
+    if language != Language::English {
+        return Err(MnemonicError::UnsupportedLanguage(language));
+    }
+    let words: Vec<&str> = phrase.split_whitespace().collect();
+    if !(12..=24).contains(&words.len()) || !words.len().is_multiple_of(3) {
+        return Err(MnemonicError::WordCount(words.len()));
+    }
+    let wordlist = english_wordlist();
+    let mut bits = Vec::with_capacity(words.len() * 11);
+    for (i, word) in words.iter().enumerate() {
+        let index = wordlist
+            .binary_search(word)
+            .map_err(|_| MnemonicError::UnknownWord { position: i + 1 })?;
+        bits.extend((0..11).rev().map(|bit| (index >> bit) & 1 == 1));
+    }
+    let checksum_bits = words.len() / 3;
+    let (entropy_bits, checksum) = bits.split_at(bits.len() - checksum_bits);
+    let entropy = SecretBytes::new(
+        entropy_bits
+            .chunks_exact(8)
+            .map(|byte| byte.iter().fold(0u8, |acc, &bit| acc << 1 | u8::from(bit)))
+            .collect(),
+    );
+    let hash = Sha256::digest(entropy.expose());
+    let expected = (0..checksum_bits).map(|i| (hash[i / 8] >> (7 - i % 8)) & 1 == 1);
+    match expected.eq(checksum.iter().copied()) {
+        true => Ok(entropy),
+        false => Err(MnemonicError::Checksum),
+    }
+}
+
+/// The seed of `phrase`: PBKDF2-HMAC-SHA-512 over 2048 rounds, salted `mnemonic`. `None`
+/// if the phrase is not ASCII, as it would need normalizing first.
+pub fn phrase_seed(phrase: &str) -> Option<SecretBytes> {
+    phrase.is_ascii().then(|| {
+        SecretBytes::new(pbkdf2_hmac::<Sha512>(
+            phrase.as_bytes(),
+            b"mnemonic",
+            PBKDF2_ROUNDS,
+            SEED_LEN,
+        ))
+    })
+}
+
+/// The fingerprint of the seed `phrase` derives, as its `mnemonicphrase` record should
+/// be keyed; `None` where [`phrase_seed`] cannot derive it.
+pub fn phrase_fingerprint(phrase: &str) -> Option<[u8; 32]> {
+    phrase_seed(phrase).map(|seed| seed_fingerprint(seed.expose()))
+}
+
+/// A disagreement between the wallet's seeds and the records that name them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SeedIssue {
+    /// The seed phrase does not check out against the wordlist.
+    InvalidPhrase(MnemonicError),
+    /// A seed, or the phrase, derives another fingerprint than its record is keyed by.
+    FingerprintMismatch {
+        kind: RecordKind,
+        recorded: [u8; 32],
+        derived: [u8; 32],
+    },
+    /// Records that name a seed the wallet does not hold.
+    UnknownSeed {
+        fingerprint: [u8; 32],
+        kind: RecordKind,
+        count: usize,
+    },
+}
+
+impl fmt::Display for SeedIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SeedIssue::InvalidPhrase(error) => write!(f, "seed phrase: {error}"),
+            SeedIssue::FingerprintMismatch {
+                kind,
+                recorded,
+                derived,
+            } => write!(
+                f,
+                "{kind}: keyed by seed {}, derives seed {}",
+                uint256_hex(recorded),
+                uint256_hex(derived)
+            ),
+            SeedIssue::UnknownSeed {
+                fingerprint,
+                kind,
+                count,
+            } => write!(
+                f,
+                "{count} {kind} records name seed {}, which the wallet does not hold",
+                uint256_hex(fingerprint)
+            ),
+        }
+    }
+}
+
+/// Check the wallet's seeds and the records that name them: that the seed phrase is
+/// valid, that each seed derives the fingerprint its record is keyed by, and that every
+/// fingerprint the HD chain, unified accounts and key metadata name is one of the
+/// wallet's seeds.
+///
+/// `mnemonic` is the phrase to check, e.g. one decrypted from `cmnemonicphrase`; by
+/// default the wallet's `mnemonicphrase`. Encrypted seeds that are not passed in count
+/// as held, under the fingerprint their record is keyed by.
+pub fn check_seeds(wallet: &ZcashdWallet, mnemonic: Option<&MnemonicPhrase>) -> Vec<SeedIssue> {
+    let crypto = &wallet.crypto;
+    let mnemonic = mnemonic.or(crypto.mnemonic.as_ref());
+    let mut issues = Vec::new();
+    if let Some(mnemonic) = mnemonic
+        && let Err(error) = phrase_entropy(mnemonic.phrase.expose(), mnemonic.language)
+    {
+        issues.push(SeedIssue::InvalidPhrase(error));
+    }
+    let mut seeds = BTreeSet::new();
+    let mut held = |kind, recorded: [u8; 32], derived: Option<[u8; 32]>| {
+        if let Some(derived) = derived
+            && derived != recorded
+        {
+            issues.push(SeedIssue::FingerprintMismatch {
+                kind,
+                recorded,
+                derived,
+            });
+        }
+        seeds.insert(derived.unwrap_or(recorded));
+    };
+    match mnemonic {
+        Some(mnemonic) => {
+            let derived = phrase_fingerprint(mnemonic.phrase.expose());
+            held(RecordKind::MnemonicPhrase, mnemonic.fingerprint, derived);
+        }
+        None => {
+            if let Some(encrypted) = &crypto.encrypted_mnemonic {
+                held(RecordKind::CMnemonicPhrase, encrypted.fingerprint, None);
+            }
+        }
+    }
+    if let Some(seed) = &crypto.hd_seed {
+        let derived = seed_fingerprint(seed.seed.expose());
+        held(RecordKind::HdSeed, seed.fingerprint, Some(derived));
+    }
+    if let Some(encrypted) = &crypto.encrypted_hd_seed {
+        held(RecordKind::CHdSeed, encrypted.fingerprint, None);
+    }
+
+    let mut named: BTreeMap<([u8; 32], RecordKind), usize> = BTreeMap::new();
+    let chain = crypto.mnemonic_hd_chain.iter();
+    let chain = chain.map(|chain| (chain.seed_fingerprint, RecordKind::MnemonicHdChain));
+    let accounts = wallet.keys.unified_accounts.iter();
+    let accounts = accounts.map(|account| (account.seed_fingerprint, RecordKind::UnifiedAccount));
+    let keys = wallet.keys.transparent_metadata.values();
+    // Keys not derived from a seed name the null fingerprint.
+    let keys = keys
+        .filter_map(|meta| meta.seed_fingerprint)
+        .filter(|fingerprint| *fingerprint != [0; 32])
+        .map(|fingerprint| (fingerprint, RecordKind::KeyMeta));
+    for (fingerprint, kind) in chain.chain(accounts).chain(keys) {
+        if !seeds.contains(&fingerprint) {
+            *named.entry((fingerprint, kind)).or_default() += 1;
+        }
+    }
+    issues.extend(
+        named
+            .into_iter()
+            .map(|((fingerprint, kind), count)| SeedIssue::UnknownSeed {
+                fingerprint,
+                kind,
+                count,
+            }),
+    );
+    issues
+}
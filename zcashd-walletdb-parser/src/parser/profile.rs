@@ -0,0 +1,406 @@
+//! What kind of zcashd wallet a wallet.dat is, as a first step in recovering it.
+//!
+//! zcashd has generated keys three ways over its history, and each asks for a different
+//! recovery path:
+//!
+//! - before v2.0.0, every key is random, and only the keys themselves recover the funds;
+//! - from v2.0.0, Sapling (and later transparent) keys derive from an `hdseed`, a raw
+//!   32-byte seed with no phrase;
+//! - from v4.7.0, keys and accounts derive from a ZIP 339 seed phrase. A wallet upgraded
+//!   from an older version keeps its `hdseed` and random keys alongside it.
+//!
+//! [`WalletProfile`] says which of these a wallet is, whether it is encrypted, which pools
+//! it has ever used, and the oldest zcashd able to have written its records. That floor
+//! comes from the records that first appeared in a given release; `version` names the
+//! release that last wrote the wallet, which must be at or above it.
+
+use std::{collections::BTreeMap, fmt};
+
+use crate::parser::{
+    decoders::{
+        scalars::{ClientVersion, NU5_WALLET_VERSION, SAPLING_WALLET_VERSION},
+        unified::ReceiverType,
+    },
+    record::RecordKind,
+    wallet::ZcashdWallet,
+};
+
+/// Record kinds and the first release that wrote them.
+const FIRST_WRITTEN: &[(RecordKind, ClientVersion)] = &[
+    (RecordKind::HdSeed, SAPLING_WALLET_VERSION),
+    (RecordKind::CHdSeed, SAPLING_WALLET_VERSION),
+    (RecordKind::SapZKey, SAPLING_WALLET_VERSION),
+    (RecordKind::CSapZKey, SAPLING_WALLET_VERSION),
+    (RecordKind::SapZKeyMeta, SAPLING_WALLET_VERSION),
+    (RecordKind::SapExtFvk, SAPLING_WALLET_VERSION),
+    (RecordKind::SapZAddr, SAPLING_WALLET_VERSION),
+    (RecordKind::MnemonicPhrase, NU5_WALLET_VERSION),
+    (RecordKind::CMnemonicPhrase, NU5_WALLET_VERSION),
+    (RecordKind::MnemonicHdChain, NU5_WALLET_VERSION),
+    (RecordKind::UnifiedAccount, NU5_WALLET_VERSION),
+    (RecordKind::UnifiedFvk, NU5_WALLET_VERSION),
+    (RecordKind::UnifiedAddrMeta, NU5_WALLET_VERSION),
+    (RecordKind::OrchardNoteCommitmentTree, NU5_WALLET_VERSION),
+    (RecordKind::RecipientMapping, NU5_WALLET_VERSION),
+];
+
+/// How the wallet derives new keys.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyGeneration {
+    /// No seed: every key is random, as before v2.0.0.
+    Random,
+    /// From the legacy `hdseed`, as from v2.0.0 to v4.6.
+    LegacySeed,
+    /// From the seed phrase, as from v4.7.0.
+    Mnemonic,
+}
+
+impl fmt::Display for KeyGeneration {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            KeyGeneration::Random => "pre-HD (random keys)",
+            KeyGeneration::LegacySeed => "legacy HD seed",
+            KeyGeneration::Mnemonic => "seed phrase",
+        })
+    }
+}
+
+/// A value pool.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Pool {
+    Transparent,
+    Sprout,
+    Sapling,
+    Orchard,
+}
+
+impl fmt::Display for Pool {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Pool::Transparent => "transparent",
+            Pool::Sprout => "sprout",
+            Pool::Sapling => "sapling",
+            Pool::Orchard => "orchard",
+        })
+    }
+}
+
+/// The wallet's footprint in one pool.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PoolUsage {
+    /// Keys of the pool, spending or viewing; for Orchard, whose keys live in unified
+    /// accounts, the unified addresses with an Orchard receiver.
+    pub keys: usize,
+    /// Wallet transactions with a part in the pool.
+    pub transactions: usize,
+}
+
+/// The oldest release able to have written some of the wallet's records.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VersionFloor {
+    pub version: ClientVersion,
+    /// The record kind that first appeared in `version`.
+    pub kind: RecordKind,
+}
+
+/// A contradiction between the wallet's version records and the rest of it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VersionIssue {
+    /// `version` names a release older than one of the records it holds.
+    OlderThanRecords {
+        version: ClientVersion,
+        floor: VersionFloor,
+    },
+    /// `minversion` names a newer release than the one that last wrote the wallet.
+    MinVersionAboveVersion {
+        version: ClientVersion,
+        min_version: ClientVersion,
+    },
+}
+
+impl fmt::Display for VersionIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VersionIssue::OlderThanRecords { version, floor } => write!(
+                f,
+                "version {version} predates its {} records, first written by {}",
+                floor.kind, floor.version
+            ),
+            VersionIssue::MinVersionAboveVersion {
+                version,
+                min_version,
+            } => write!(
+                f,
+                "minversion {min_version} is newer than version {version}, which wrote it"
+            ),
+        }
+    }
+}
+
+/// The outcome of [`WalletProfile::of`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WalletProfile {
+    pub generation: KeyGeneration,
+    /// Whether the wallet holds an `hdseed`, plain or encrypted. A seed phrase wallet
+    /// that does was upgraded from before v4.7.0.
+    pub legacy_seed: bool,
+    /// Transparent keys whose metadata names a derivation path.
+    pub derived_keys: usize,
+    /// Keys derived from no seed: Sprout keys, and transparent keys that are random or
+    /// imported. Sapling keys are not counted either way.
+    pub random_keys: usize,
+    pub encrypted: bool,
+    /// The pools the wallet has keys or transactions in.
+    pub pools: BTreeMap<Pool, PoolUsage>,
+    /// `version`: the release that last wrote the wallet.
+    pub version: Option<ClientVersion>,
+    /// `minversion`: the oldest release able to read it.
+    pub min_version: Option<ClientVersion>,
+    /// The oldest release able to have written every record, if any record tells.
+    pub floor: Option<VersionFloor>,
+    pub issues: Vec<VersionIssue>,
+}
+
+impl WalletProfile {
+    /// Profile `wallet`.
+    pub fn of(wallet: &ZcashdWallet) -> Self {
+        let crypto = &wallet.crypto;
+        let keys = &wallet.keys;
+        let legacy_seed = crypto.hd_seed.is_some() || crypto.encrypted_hd_seed.is_some();
+        let generation = if crypto.mnemonic.is_some() || crypto.encrypted_mnemonic.is_some() {
+            KeyGeneration::Mnemonic
+        } else if legacy_seed {
+            KeyGeneration::LegacySeed
+        } else {
+            KeyGeneration::Random
+        };
+
+        let pubkeys = keys
+            .transparent
+            .keys()
+            .chain(keys.wallet_keys.keys())
+            .chain(keys.encrypted_transparent.keys());
+        let (mut derived_keys, mut random_keys) = (0, keys.sprout_len());
+        for pubkey in pubkeys {
+            match keys.transparent_metadata.get(pubkey) {
+                Some(meta) if meta.is_hd() => derived_keys += 1,
+                _ => random_keys += 1,
+            }
+        }
+
+        let mut pools = BTreeMap::new();
+        let orchard_addresses = keys
+            .unified_addresses
+            .iter()
+            .filter(|address| address.receiver_types.contains(&ReceiverType::Orchard))
+            .count();
+        for (pool, n) in [
+            (
+                Pool::Transparent,
+                keys.transparent_len() + keys.watch_scripts.len(),
+            ),
+            (Pool::Sprout, keys.sprout_len() + keys.sprout_viewing.len()),
+            (
+                Pool::Sapling,
+                keys.sapling_len() + keys.sapling_watch_only.len(),
+            ),
+            (Pool::Orchard, orchard_addresses),
+        ] {
+            if n > 0 {
+                pools.insert(
+                    pool,
+                    PoolUsage {
+                        keys: n,
+                        transactions: 0,
+                    },
+                );
+            }
+        }
+        for wtx in wallet.transactions.values() {
+            let tx = wtx.tx();
+            let sprout = tx.sprout.as_ref().is_some_and(|b| !b.joinsplits.is_empty());
+            let sapling = tx
+                .sapling
+                .as_ref()
+                .is_some_and(|b| !b.spends.is_empty() || !b.outputs.is_empty());
+            let orchard = tx.orchard.as_ref().is_some_and(|b| !b.actions.is_empty());
+            for (pool, used) in [
+                (Pool::Transparent, !tx.vin.is_empty() || !tx.vout.is_empty()),
+                (Pool::Sprout, sprout || !wtx.sprout_note_data.is_empty()),
+                (Pool::Sapling, sapling || !wtx.sapling_note_data.is_empty()),
+                (Pool::Orchard, orchard || wtx.orchard_meta.is_some()),
+            ] {
+                if used {
+                    pools
+                        .entry(pool)
+                        .or_insert_with(PoolUsage::default)
+                        .transactions += 1;
+                }
+            }
+        }
+
+        let version = wallet.metadata.version;
+        let min_version = wallet.metadata.min_version;
+        let kinds = present_kinds(wallet);
+        let floor = FIRST_WRITTEN
+            .iter()
+            .filter(|(kind, _)| kinds.contains(kind))
+            .map(|&(kind, version)| VersionFloor { version, kind })
+            .chain(wallet.orchard.as_ref().map(|tree| VersionFloor {
+                version: tree.client_version,
+                kind: RecordKind::OrchardNoteCommitmentTree,
+            }))
+            .max_by_key(|floor| floor.version);
+        let mut issues = Vec::new();
+        if let (Some(version), Some(floor)) = (version, floor)
+            && version < floor.version
+        {
+            issues.push(VersionIssue::OlderThanRecords { version, floor });
+        }
+        if let (Some(version), Some(min_version)) = (version, min_version)
+            && min_version > version
+        {
+            issues.push(VersionIssue::MinVersionAboveVersion {
+                version,
+                min_version,
+            });
+        }
+
+        WalletProfile {
+            generation,
+            legacy_seed,
+            derived_keys,
+            random_keys,
+            encrypted: crypto.is_encrypted(),
+            pools,
+            version,
+            min_version,
+            floor,
+            issues,
+        }
+    }
+
+    /// What recovering the wallet's funds takes, one step per line.
+    pub fn recovery(&self) -> Vec<String> {
+        let mut steps = Vec::new();
+        if self.encrypted {
+            steps.push("unlock the wallet with its passphrase to reach any secret".to_string());
+        }
+        match self.generation {
+            KeyGeneration::Mnemonic => {
+                steps.push(
+                    "the seed phrase recovers its accounts and the keys derived from it"
+                        .to_string(),
+                );
+                if self.legacy_seed {
+                    steps.push(
+                        "keys from before the upgrade to a seed phrase need the legacy hdseed"
+                            .to_string(),
+                    );
+                }
+            }
+            KeyGeneration::LegacySeed => {
+                steps.push(
+                    "the hdseed recovers the keys derived from it; it has no phrase".to_string(),
+                );
+            }
+            KeyGeneration::Random => {}
+        }
+        if self.random_keys > 0 {
+            steps.push(format!(
+                "{} keys derive from no seed and must be exported one by one",
+                self.random_keys
+            ));
+        }
+        steps
+    }
+}
+
+/// The record kinds `wallet` holds at least one of.
+fn present_kinds(wallet: &ZcashdWallet) -> Vec<RecordKind> {
+    let keys = &wallet.keys;
+    let crypto = &wallet.crypto;
+    let mut kinds: Vec<RecordKind> = wallet.undecoded.keys().copied().collect();
+    for (kind, present) in [
+        (RecordKind::HdSeed, crypto.hd_seed.is_some()),
+        (RecordKind::CHdSeed, crypto.encrypted_hd_seed.is_some()),
+        (RecordKind::MnemonicPhrase, crypto.mnemonic.is_some()),
+        (
+            RecordKind::CMnemonicPhrase,
+            crypto.encrypted_mnemonic.is_some(),
+        ),
+        (
+            RecordKind::MnemonicHdChain,
+            crypto.mnemonic_hd_chain.is_some(),
+        ),
+        (RecordKind::SapZKey, !keys.sapling.is_empty()),
+        (RecordKind::CSapZKey, !keys.encrypted_sapling.is_empty()),
+        (RecordKind::SapExtFvk, !keys.sapling_watch_only.is_empty()),
+        (RecordKind::SapZAddr, !keys.sapling_addresses.is_empty()),
+        (
+            RecordKind::UnifiedAccount,
+            !keys.unified_accounts.is_empty(),
+        ),
+        (RecordKind::UnifiedFvk, !keys.unified_fvks.is_empty()),
+        (
+            RecordKind::UnifiedAddrMeta,
+            !keys.unified_addresses.is_empty(),
+        ),
+        (
+            RecordKind::OrchardNoteCommitmentTree,
+            wallet.orchard.is_some(),
+        ),
+    ] {
+        if present {
+            kinds.push(kind);
+        }
+    }
+    kinds
+}
+
+fn version_or_unknown(version: Option<ClientVersion>) -> String {
+    version.map_or_else(|| "unknown".to_string(), |v| v.to_string())
+}
+
+impl fmt::Display for WalletProfile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "WalletProfile {{")?;
+        writeln!(f, "  generation   : {}", self.generation)?;
+        writeln!(f, "  legacy seed  : {}", self.legacy_seed)?;
+        writeln!(
+            f,
+            "  keys         : {} derived, {} random",
+            self.derived_keys, self.random_keys
+        )?;
+        writeln!(f, "  encrypted    : {}", self.encrypted)?;
+        for (pool, usage) in &self.pools {
+            writeln!(
+                f,
+                "  {:<13}: {} keys, {} transactions",
+                pool.to_string(),
+                usage.keys,
+                usage.transactions
+            )?;
+        }
+        writeln!(f, "  version      : {}", version_or_unknown(self.version))?;
+        writeln!(
+            f,
+            "  minversion   : {}",
+            version_or_unknown(self.min_version)
+        )?;
+        if let Some(floor) = &self.floor {
+            writeln!(
+                f,
+                "  written by   : {} or later ({} records)",
+                floor.version, floor.kind
+            )?;
+        }
+        for step in self.recovery() {
+            writeln!(f, "  recovery     : {step}")?;
+        }
+        for issue in &self.issues {
+            writeln!(f, "  warning      : {issue}")?;
+        }
+        write!(f, "}}")
+    }
+}
@@ -1,31 +1,328 @@
-use std::fmt::Debug;
+use std::fmt::{self, Debug};
 
-/// High-level kind inferred from the raw key bytes.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+use crate::{
+    entry::parser::split_walletdb_key,
+    parser::{
+        decoders::scalars::{ClientVersion, VersionDecoder},
+        key::RecordKey,
+    },
+};
+
+/// Kind of a wallet record, from the tag that starts its key. One variant per tag
+/// zcashd's walletdb writes; see [`RecordKind::from_tag`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum RecordKind {
-    // TODO: Here would go the registered records
+    /// `name`: address book label, keyed by address string.
+    Name,
+    /// `purpose`: address book purpose (`send`/`receive`), keyed by address string.
+    Purpose,
+    /// `key`: unencrypted transparent private key, keyed by public key.
+    Key,
+    /// `wkey`: transparent private key in the legacy `CWalletKey` wrapper.
+    WKey,
+    /// `ckey`: encrypted transparent private key, keyed by public key.
+    CKey,
+    /// `mkey`: master key the wallet passphrase unlocks, keyed by its id.
+    MKey,
+    /// `keymeta`: creation time and HD path of a transparent key.
+    KeyMeta,
+    /// `defaultkey`: the wallet's default public key.
+    DefaultKey,
+    /// `pool`: key pool entry, keyed by its index.
+    Pool,
+    /// `version`: client version that last wrote the wallet.
+    Version,
+    /// `minversion`: oldest client version able to read the wallet.
+    MinVersion,
+    /// `tx`: wallet transaction, keyed by txid.
+    Tx,
+    /// `acc`: legacy account, keyed by account name.
+    Acc,
+    /// `acentry`: legacy accounting entry.
+    AcEntry,
+    /// `bestblock`: block locator of the chain tip the wallet was synced to.
+    BestBlock,
+    /// `bestblock_nomerkle`: block locator, as written since the merkle branch was dropped.
+    BestBlockNoMerkle,
+    /// `orderposnext`: next transaction order position.
+    OrderPosNext,
+    /// `destdata`: per-destination key/value data, e.g. `used` flags.
+    DestData,
+    /// `hdseed`: legacy HD seed, keyed by its fingerprint.
+    HdSeed,
+    /// `chdseed`: encrypted legacy HD seed, keyed by its fingerprint.
+    CHdSeed,
+    /// `hdchain`: legacy HD chain counters.
+    HdChain,
+    /// `mnemonicphrase`: mnemonic seed phrase, keyed by the seed fingerprint.
+    MnemonicPhrase,
+    /// `cmnemonicphrase`: encrypted mnemonic seed phrase.
+    CMnemonicPhrase,
+    /// `mnemonichdchain`: hD chain counters of the mnemonic seed.
+    MnemonicHdChain,
+    /// `zkey`: sprout spending key, keyed by payment address.
+    ZKey,
+    /// `czkey`: encrypted Sprout spending key.
+    CZKey,
+    /// `zkeymeta`: creation time of a Sprout key.
+    ZKeyMeta,
+    /// `vkey`: sprout viewing key, keyed by payment address.
+    VKey,
+    /// `sapzkey`: sapling extended spending key, keyed by its full viewing key.
+    SapZKey,
+    /// `csapzkey`: encrypted Sapling extended spending key.
+    CSapZKey,
+    /// `sapzkeymeta`: creation time and HD path of a Sapling key.
+    SapZKeyMeta,
+    /// `sapzaddr`: sapling payment address and the viewing key it belongs to.
+    SapZAddr,
+    /// `sapextfvk`: watch-only Sapling extended full viewing key.
+    SapExtFvk,
+    /// `watchs`: watch-only transparent script.
+    Watchs,
+    /// `cscript`: redeem script, keyed by its hash.
+    CScript,
+    /// `unifiedaccount`: unified account: seed fingerprint, coin type and account id.
+    UnifiedAccount,
+    /// `unifiedfvk`: unified full viewing key.
+    UnifiedFvk,
+    /// `unifiedaddrmeta`: diversifier index and receiver types of a unified address.
+    UnifiedAddrMeta,
+    /// `orchard_note_commitment_tree`: orchard note commitment tree and wallet witnesses.
+    OrchardNoteCommitmentTree,
+    /// `networkinfo`: coin and network the wallet belongs to.
+    NetworkInfo,
+    /// `recipientmapping`: unified address a transaction output was sent to.
+    RecipientMapping,
+    /// `witnesscachesize`: number of note witnesses kept per note.
+    WitnessCacheSize,
+    /// A tag zcashd does not write, or a key without a readable tag.
+    Unknown,
+}
+
+impl RecordKind {
+    /// Every kind with a tag, in the order above.
+    pub const ALL: &[RecordKind] = &[
+        RecordKind::Name,
+        RecordKind::Purpose,
+        RecordKind::Key,
+        RecordKind::WKey,
+        RecordKind::CKey,
+        RecordKind::MKey,
+        RecordKind::KeyMeta,
+        RecordKind::DefaultKey,
+        RecordKind::Pool,
+        RecordKind::Version,
+        RecordKind::MinVersion,
+        RecordKind::Tx,
+        RecordKind::Acc,
+        RecordKind::AcEntry,
+        RecordKind::BestBlock,
+        RecordKind::BestBlockNoMerkle,
+        RecordKind::OrderPosNext,
+        RecordKind::DestData,
+        RecordKind::HdSeed,
+        RecordKind::CHdSeed,
+        RecordKind::HdChain,
+        RecordKind::MnemonicPhrase,
+        RecordKind::CMnemonicPhrase,
+        RecordKind::MnemonicHdChain,
+        RecordKind::ZKey,
+        RecordKind::CZKey,
+        RecordKind::ZKeyMeta,
+        RecordKind::VKey,
+        RecordKind::SapZKey,
+        RecordKind::CSapZKey,
+        RecordKind::SapZKeyMeta,
+        RecordKind::SapZAddr,
+        RecordKind::SapExtFvk,
+        RecordKind::Watchs,
+        RecordKind::CScript,
+        RecordKind::UnifiedAccount,
+        RecordKind::UnifiedFvk,
+        RecordKind::UnifiedAddrMeta,
+        RecordKind::OrchardNoteCommitmentTree,
+        RecordKind::NetworkInfo,
+        RecordKind::RecipientMapping,
+        RecordKind::WitnessCacheSize,
+    ];
+
+    /// The kind of the record whose key starts with `tag`.
+    pub fn from_tag(tag: &str) -> Self {
+        RecordKind::ALL
+            .iter()
+            .copied()
+            .find(|kind| kind.tag() == Some(tag))
+            .unwrap_or(RecordKind::Unknown)
+    }
+
+    /// The tag zcashd writes for this kind, or `None` for [`RecordKind::Unknown`].
+    pub const fn tag(self) -> Option<&'static str> {
+        Some(match self {
+            RecordKind::Name => "name",
+            RecordKind::Purpose => "purpose",
+            RecordKind::Key => "key",
+            RecordKind::WKey => "wkey",
+            RecordKind::CKey => "ckey",
+            RecordKind::MKey => "mkey",
+            RecordKind::KeyMeta => "keymeta",
+            RecordKind::DefaultKey => "defaultkey",
+            RecordKind::Pool => "pool",
+            RecordKind::Version => "version",
+            RecordKind::MinVersion => "minversion",
+            RecordKind::Tx => "tx",
+            RecordKind::Acc => "acc",
+            RecordKind::AcEntry => "acentry",
+            RecordKind::BestBlock => "bestblock",
+            RecordKind::BestBlockNoMerkle => "bestblock_nomerkle",
+            RecordKind::OrderPosNext => "orderposnext",
+            RecordKind::DestData => "destdata",
+            RecordKind::HdSeed => "hdseed",
+            RecordKind::CHdSeed => "chdseed",
+            RecordKind::HdChain => "hdchain",
+            RecordKind::MnemonicPhrase => "mnemonicphrase",
+            RecordKind::CMnemonicPhrase => "cmnemonicphrase",
+            RecordKind::MnemonicHdChain => "mnemonichdchain",
+            RecordKind::ZKey => "zkey",
+            RecordKind::CZKey => "czkey",
+            RecordKind::ZKeyMeta => "zkeymeta",
+            RecordKind::VKey => "vkey",
+            RecordKind::SapZKey => "sapzkey",
+            RecordKind::CSapZKey => "csapzkey",
+            RecordKind::SapZKeyMeta => "sapzkeymeta",
+            RecordKind::SapZAddr => "sapzaddr",
+            RecordKind::SapExtFvk => "sapextfvk",
+            RecordKind::Watchs => "watchs",
+            RecordKind::CScript => "cscript",
+            RecordKind::UnifiedAccount => "unifiedaccount",
+            RecordKind::UnifiedFvk => "unifiedfvk",
+            RecordKind::UnifiedAddrMeta => "unifiedaddrmeta",
+            RecordKind::OrchardNoteCommitmentTree => "orchard_note_commitment_tree",
+            RecordKind::NetworkInfo => "networkinfo",
+            RecordKind::RecipientMapping => "recipientmapping",
+            RecordKind::WitnessCacheSize => "witnesscachesize",
+            RecordKind::Unknown => return None,
+        })
+    }
+}
+
+impl fmt::Display for RecordKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.tag().unwrap_or("unknown"))
+    }
 }
 
 /// Classifies raw keys into RecordKind with optional parsed key metadata.
 pub trait RecordClassifier {
-    /// Inspect raw key bytes and return kind and optional structured key info.
-    fn classify(&self, key: &[u8]) -> (RecordKind, Option<String>);
+    /// Inspect raw key bytes and return kind and the structured part of the key, if it
+    /// has one past the tag. See [`crate::parser::key::DefaultClassifier`].
+    fn classify(&self, key: &[u8]) -> (RecordKind, Option<RecordKey>);
 }
 
 /// Decoder result type for domain objects. Keep domain types opaque to parser module.
 pub type DecodeResult<T> = Result<T, DecodeError>;
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct DecodeError {
     pub message: String,
+    /// Where in the value (or key) decoding stopped, when a
+    /// [`Reader`](crate::parser::serialize::Reader) failed. Offsets are relative to the
+    /// bytes the reader was created over.
+    pub offset: Option<usize>,
+}
+
+impl DecodeError {
+    pub fn new(message: impl Into<String>) -> Self {
+        DecodeError {
+            message: message.into(),
+            offset: None,
+        }
+    }
+
+    /// An error at byte `offset`.
+    pub fn at(offset: usize, message: impl Into<String>) -> Self {
+        DecodeError {
+            message: message.into(),
+            offset: Some(offset),
+        }
+    }
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)?;
+        match self.offset {
+            Some(offset) => write!(f, " at offset {offset}"),
+            None => Ok(()),
+        }
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+/// What a decoder knows of the wallet a record comes from.
+///
+/// zcashd rewrites `version` whenever a newer client opens the wallet, but leaves the
+/// records older clients wrote in the layout they wrote them in; most records that
+/// changed shape carry a version of their own for that reason. `version` is therefore
+/// an upper bound on the client that wrote any one record: a layout introduced after it
+/// cannot occur. The default context knows neither version, and decoders then expect
+/// the newest layout.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct DecodeContext {
+    /// `version`: the newest client that has written the wallet.
+    pub version: Option<ClientVersion>,
+    /// `minversion`: the oldest client able to read it.
+    pub min_version: Option<ClientVersion>,
+}
+
+impl DecodeContext {
+    /// The context of the wallet the `(key, value)` records come from, read from its
+    /// `version` and `minversion` records.
+    pub fn scan<K, V>(records: impl IntoIterator<Item = (K, V)>) -> Self
+    where
+        K: AsRef<[u8]>,
+        V: AsRef<[u8]>,
+    {
+        let mut ctx = DecodeContext::default();
+        for (key, value) in records {
+            let version = || {
+                VersionDecoder
+                    .decode(&DecodeContext::default(), None, value.as_ref())
+                    .ok()
+            };
+            match split_walletdb_key(key.as_ref()) {
+                Some(("version", [])) => ctx.version = version(),
+                Some(("minversion", [])) => ctx.min_version = version(),
+                _ => {}
+            }
+        }
+        ctx
+    }
+
+    /// Whether every record predates `version`: true only if the wallet's `version` is
+    /// known and older.
+    pub fn predates(&self, version: ClientVersion) -> bool {
+        self.version.is_some_and(|v| v < version)
+    }
 }
 
 /// Decoder trait for converting raw value bytes into a typed domain object.
 pub trait RecordDecoder: Send + Sync {
     type Item: Send + Sync + Debug;
 
-    /// Decode bytes into a typed domain object.
-    fn decode(&self, raw_value: &[u8]) -> DecodeResult<Self::Item>;
+    /// Decode bytes into a typed domain object. `ctx` describes the wallet, for records
+    /// whose layout depends on the client that wrote them; `key` is the structured part
+    /// of the record key, for records whose value only makes sense together with it.
+    fn decode(
+        &self,
+        ctx: &DecodeContext,
+        key: Option<&RecordKey>,
+        raw_value: &[u8],
+    ) -> DecodeResult<Self::Item>;
 
     /// Human-readable name for the decoder.
     fn name(&self) -> &'static str;
@@ -1,18 +1,210 @@
-use crate::parser::record::{RecordDecoder, RecordKind};
+use std::{any::Any, collections::BTreeMap, fmt::Debug};
 
-/// Registry that maps RecordKind -> decoder instance. Tokes ownership of decoders.
+use crate::{
+    entry::parser::split_walletdb_key,
+    parser::{
+        decoders,
+        key::{DefaultClassifier, RecordKey},
+        record::{
+            DecodeContext, DecodeError, DecodeResult, RecordClassifier, RecordDecoder, RecordKind,
+        },
+    },
+};
+
+/// A decoded value with its type erased. Downcast it with [`DecodedItem::as_any`] or
+/// [`DecodedItem::into_any`], or ask the registry for the type directly with
+/// [`DecoderRegistry::decode_as`].
+pub trait DecodedItem: Any + Debug + Send + Sync {
+    fn as_any(&self) -> &dyn Any;
+    fn into_any(self: Box<Self>) -> Box<dyn Any + Send + Sync>;
+}
+
+impl<T: Any + Debug + Send + Sync> DecodedItem for T {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn into_any(self: Box<Self>) -> Box<dyn Any + Send + Sync> {
+        self
+    }
+}
+
+/// A [`RecordDecoder`] with its item type erased, so decoders of different types can
+/// share one registry.
+pub trait ErasedDecoder: Send + Sync {
+    fn decode_erased(
+        &self,
+        ctx: &DecodeContext,
+        key: Option<&RecordKey>,
+        raw_value: &[u8],
+    ) -> DecodeResult<Box<dyn DecodedItem>>;
+
+    fn name(&self) -> &'static str;
+}
+
+impl<D: RecordDecoder> ErasedDecoder for D
+where
+    D::Item: 'static,
+{
+    fn decode_erased(
+        &self,
+        ctx: &DecodeContext,
+        key: Option<&RecordKey>,
+        raw_value: &[u8],
+    ) -> DecodeResult<Box<dyn DecodedItem>> {
+        Ok(Box::new(self.decode(ctx, key, raw_value)?))
+    }
+
+    fn name(&self) -> &'static str {
+        RecordDecoder::name(self)
+    }
+}
+
+/// A raw record run through the registry.
+#[derive(Debug)]
+pub struct DecodedEntry {
+    pub kind: RecordKind,
+    pub key: Option<RecordKey>,
+    /// The decoded value, or `None` if no decoder is registered for `kind`.
+    pub value: Option<DecodeResult<Box<dyn DecodedItem>>>,
+}
+
+/// A record whose tag no [`RecordKind`] stands for, passed through as read. zcashd forks
+/// and newer releases add tags; these records are kept rather than dropped.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct UnknownRecord {
+    /// The tag, or `None` if the key does not start with a well-formed one.
+    pub tag: Option<String>,
+    /// The key after the tag; the whole key if it has none.
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::util::serde_hex::bytes")
+    )]
+    pub key_rest: Vec<u8>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::util::serde_hex::bytes")
+    )]
+    pub value: Vec<u8>,
+}
+
+/// A raw record run through [`DecoderRegistry::decode_record`].
+#[derive(Debug)]
+pub enum DecodedRecord {
+    /// A record of a known kind, decoded if a decoder is registered for it.
+    Known(DecodedEntry),
+    Unknown(UnknownRecord),
+}
+
+/// Registry that maps RecordKind -> decoder instance. Takes ownership of decoders.
+#[derive(Default)]
 pub struct DecoderRegistry {
-    // implementation detail: maps RecordKind to boxed decoders
+    decoders: BTreeMap<RecordKind, Box<dyn ErasedDecoder>>,
 }
 
 impl DecoderRegistry {
-    /// Register a decoder for a kind.
+    /// An empty registry; see [`default_registry`] for one with the built-in decoders.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a decoder for a kind, replacing any decoder registered for it before.
     pub fn register<D: RecordDecoder + 'static>(&mut self, kind: RecordKind, decoder: D) {
-        todo!()
+        self.decoders.insert(kind, Box::new(decoder));
     }
 
     /// Lookup decoder for a kind.
-    pub fn get(&self, kind: RecordKind) -> Option<&dyn RecordDecoder<Item = dyn std::any::Any>> {
-        todo!()
+    pub fn get(&self, kind: RecordKind) -> Option<&dyn ErasedDecoder> {
+        self.decoders.get(&kind).map(|d| &**d)
+    }
+
+    /// The kinds a decoder is registered for, in order.
+    pub fn kinds(&self) -> impl Iterator<Item = RecordKind> + '_ {
+        self.decoders.keys().copied()
+    }
+
+    pub fn len(&self) -> usize {
+        self.decoders.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.decoders.is_empty()
     }
+
+    /// Decode the value of a `kind` record, or `None` if no decoder is registered for it.
+    pub fn decode(
+        &self,
+        kind: RecordKind,
+        ctx: &DecodeContext,
+        key: Option<&RecordKey>,
+        raw_value: &[u8],
+    ) -> Option<DecodeResult<Box<dyn DecodedItem>>> {
+        Some(self.get(kind)?.decode_erased(ctx, key, raw_value))
+    }
+
+    /// Decode the value of a `kind` record as a `T`. A decoder producing another type
+    /// is reported as an error rather than a panic.
+    pub fn decode_as<T: Any>(
+        &self,
+        kind: RecordKind,
+        ctx: &DecodeContext,
+        key: Option<&RecordKey>,
+        raw_value: &[u8],
+    ) -> Option<DecodeResult<T>> {
+        let decoder = self.get(kind)?;
+        Some(decoder.decode_erased(ctx, key, raw_value).and_then(|item| {
+            item.into_any()
+                .downcast::<T>()
+                .map(|item| *item)
+                .map_err(|_| {
+                    DecodeError::new(format!(
+                        "{kind}: decoder {} does not produce a {}",
+                        decoder.name(),
+                        std::any::type_name::<T>()
+                    ))
+                })
+        }))
+    }
+
+    /// Classify a raw key with [`DefaultClassifier`] and decode its value.
+    pub fn decode_entry(
+        &self,
+        ctx: &DecodeContext,
+        raw_key: &[u8],
+        raw_value: &[u8],
+    ) -> DecodedEntry {
+        let (kind, key) = DefaultClassifier.classify(raw_key);
+        let value = self.decode(kind, ctx, key.as_ref(), raw_value);
+        DecodedEntry { kind, key, value }
+    }
+
+    /// Like [`Self::decode_entry`], but pass a record with an unknown tag through whole
+    /// instead of classifying it as [`RecordKind::Unknown`].
+    pub fn decode_record(
+        &self,
+        ctx: &DecodeContext,
+        raw_key: &[u8],
+        raw_value: &[u8],
+    ) -> DecodedRecord {
+        let (tag, key_rest) = match split_walletdb_key(raw_key) {
+            Some((tag, _)) if RecordKind::from_tag(tag) != RecordKind::Unknown => {
+                return DecodedRecord::Known(self.decode_entry(ctx, raw_key, raw_value));
+            }
+            Some((tag, rest)) => (Some(tag.to_string()), rest),
+            None => (None, raw_key),
+        };
+        DecodedRecord::Unknown(UnknownRecord {
+            tag,
+            key_rest: key_rest.to_vec(),
+            value: raw_value.to_vec(),
+        })
+    }
+}
+
+/// A registry with every built-in decoder registered.
+pub fn default_registry() -> DecoderRegistry {
+    let mut registry = DecoderRegistry::new();
+    decoders::register_all(&mut registry);
+    registry
 }
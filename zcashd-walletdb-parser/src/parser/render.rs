@@ -0,0 +1,1040 @@
+//! Terminal summaries of decoded records and of a whole wallet, laid out like the
+//! `Display` of [`BtreeMeta`](crate::headers::BtreeMeta): a title, then one aligned
+//! `label : value` row per field.
+//!
+//! [`Render`] turns a value into a [`Block`]; [`Block::paint`] writes it with ANSI
+//! colors when the [`Style`] asks for them, and its `Display` writes it plain. Hashes
+//! print the way zcashd prints them, Unix times as UTC dates and amounts in ZEC. Secret
+//! material is never written, only its length.
+
+use std::{env, fmt, io::IsTerminal};
+
+use crate::parser::{
+    balance::{ABANDON_HASH, best_height, zec},
+    decoders::{
+        accounting::{Account, AccountingEntry},
+        address_book::{AddressBook, AddressName, AddressPurpose, DestData, Purpose},
+        encryption::MasterKey,
+        hd_chain::MnemonicHdChain,
+        keymeta::{KeyMetadata, TransparentKeyMetadata},
+        keypool::KeyPoolEntry,
+        locator::BlockLocator,
+        network::Network,
+        orchard::OrchardNoteCommitmentTree,
+        sapling::{
+            EncryptedSaplingKey, SaplingAddressIvk, SaplingKeyMetadata, SaplingSpendingKey,
+            SaplingWatchOnlyKey, Zip32Header,
+        },
+        scalars::{ClientVersion, DefaultKey},
+        script::{RedeemScript, WatchScript},
+        seed::{EncryptedHdSeed, EncryptedMnemonicPhrase, HdSeed, MnemonicPhrase},
+        sprout::{EncryptedSproutKey, SproutKeyMetadata, SproutSpendingKey, SproutViewingKey},
+        transparent::{EncryptedKey, TransparentKey, WalletKey},
+        unified::{UnifiedAccount, UnifiedAddressMetadata, UnifiedFullViewingKey},
+        wallet_tx::WalletTx,
+    },
+    registry::{DecodedEntry, DecodedItem, DecodedRecord, UnknownRecord},
+    serialize::uint256_hex,
+    wallet::ZcashdWallet,
+};
+
+/// The width labels are padded to, as in the other `Display` blocks.
+const LABEL_WIDTH: usize = 13;
+
+/// How much of an unknown value is shown, in bytes.
+const VALUE_PREVIEW: usize = 32;
+
+/// What a row's value is, and so how it is colored.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Tone {
+    #[default]
+    Plain,
+    /// Hashes, keys and scripts in hex.
+    Hex,
+    /// Redacted or encrypted secret material.
+    Secret,
+    /// A state that needs nothing done.
+    Good,
+    /// Something worth a second look.
+    Warn,
+    /// Something broken.
+    Bad,
+}
+
+impl Tone {
+    fn sgr(self) -> Option<&'static str> {
+        match self {
+            Tone::Plain => None,
+            Tone::Hex => Some("2"),
+            Tone::Secret => Some("35"),
+            Tone::Good => Some("32"),
+            Tone::Warn => Some("33"),
+            Tone::Bad => Some("31"),
+        }
+    }
+}
+
+/// Whether blocks are painted with ANSI colors.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Style {
+    pub color: bool,
+}
+
+impl Style {
+    pub const PLAIN: Style = Style { color: false };
+    pub const COLOR: Style = Style { color: true };
+
+    /// Color when stdout is a terminal, unless `NO_COLOR` is set or `TERM` is `dumb`.
+    pub fn detect() -> Self {
+        let no_color = env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty());
+        let dumb = env::var_os("TERM").is_some_and(|term| term == "dumb");
+        Style {
+            color: std::io::stdout().is_terminal() && !no_color && !dumb,
+        }
+    }
+
+    fn write(self, f: &mut fmt::Formatter<'_>, sgr: Option<&str>, text: &str) -> fmt::Result {
+        match sgr {
+            Some(sgr) if self.color => write!(f, "\x1b[{sgr}m{text}\x1b[0m"),
+            _ => f.write_str(text),
+        }
+    }
+}
+
+/// One `label : value` line of a [`Block`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Row {
+    pub label: String,
+    pub value: String,
+    pub tone: Tone,
+}
+
+/// A titled list of rows, written as `Title {`, the rows with their labels aligned, and
+/// `}`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Block {
+    pub title: String,
+    pub rows: Vec<Row>,
+}
+
+impl Block {
+    pub fn new(title: impl Into<String>) -> Self {
+        Block {
+            title: title.into(),
+            rows: Vec::new(),
+        }
+    }
+
+    /// Add a [`Tone::Plain`] row.
+    pub fn row(self, label: &str, value: impl fmt::Display) -> Self {
+        self.toned(label, value, Tone::Plain)
+    }
+
+    /// Add a row of hex, such as a key or a hash.
+    pub fn hex(self, label: &str, value: impl fmt::Display) -> Self {
+        self.toned(label, value, Tone::Hex)
+    }
+
+    pub fn toned(mut self, label: &str, value: impl fmt::Display, tone: Tone) -> Self {
+        self.rows.push(Row {
+            label: label.to_string(),
+            value: value.to_string(),
+            tone,
+        });
+        self
+    }
+
+    /// The value of the first row labelled `label`.
+    pub fn get(&self, label: &str) -> Option<&str> {
+        let row = self.rows.iter().find(|row| row.label == label)?;
+        Some(&row.value)
+    }
+
+    pub fn paint(&self, style: Style) -> Painted<'_> {
+        Painted { block: self, style }
+    }
+}
+
+impl fmt::Display for Block {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.paint(Style::PLAIN).fmt(f)
+    }
+}
+
+/// A [`Block`] written in a [`Style`].
+#[derive(Debug, Clone, Copy)]
+pub struct Painted<'a> {
+    block: &'a Block,
+    style: Style,
+}
+
+impl fmt::Display for Painted<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let style = self.style;
+        style.write(f, Some("1"), &self.block.title)?;
+        writeln!(f, " {{")?;
+        for row in &self.block.rows {
+            f.write_str("  ")?;
+            style.write(f, Some("36"), &format!("{:<LABEL_WIDTH$}", row.label))?;
+            f.write_str(": ")?;
+            style.write(f, row.tone.sgr(), &row.value)?;
+            writeln!(f)?;
+        }
+        write!(f, "}}")
+    }
+}
+
+/// A value that renders as a [`Block`].
+pub trait Render {
+    fn render(&self) -> Block;
+}
+
+/// A Unix time as a UTC date and time.
+pub fn utc(secs: i64) -> String {
+    let (year, month, day, secs) = civil(secs);
+    format!(
+        "{year:04}-{month:02}-{day:02} {:02}:{:02}:{:02} UTC",
+        secs / 3600,
+        secs / 60 % 60,
+        secs % 60
+    )
+}
+
+/// A Unix time in ISO 8601, as zcashd's `FormatISO8601DateTime` writes it.
+pub fn iso8601(secs: i64) -> String {
+    let (year, month, day, secs) = civil(secs);
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}Z",
+        secs / 3600,
+        secs / 60 % 60,
+        secs % 60
+    )
+}
+
+/// A Unix time as its year, month, day and second of the day.
+fn civil(secs: i64) -> (i64, i64, i64, i64) {
+    let (days, secs) = (secs.div_euclid(86_400), secs.rem_euclid(86_400));
+    // Days since 1970-01-01 to a proleptic Gregorian date, after Howard Hinnant's
+    // `civil_from_days`: eras of 400 years, each starting on March 1st.
+    let days = days + 719_468;
+    let (era, day_of_era) = (days.div_euclid(146_097), days.rem_euclid(146_097));
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_from_march = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_from_march + 2) / 5 + 1;
+    let month = if month_from_march < 10 {
+        month_from_march + 3
+    } else {
+        month_from_march - 9
+    };
+    let year = era * 400 + year_of_era + i64::from(month <= 2);
+    (year, month, day, secs)
+}
+
+/// A time zcashd writes as 0 when it does not know it.
+fn time_or(secs: i64, unknown: &str) -> String {
+    match secs {
+        0 => unknown.to_string(),
+        secs => utc(secs),
+    }
+}
+
+fn redacted(len: usize) -> String {
+    format!("{len} bytes, redacted")
+}
+
+fn encrypted(len: usize) -> String {
+    format!("{len} bytes, encrypted")
+}
+
+fn yes_no(flag: bool) -> &'static str {
+    if flag { "yes" } else { "no" }
+}
+
+/// A ZIP 32 child index, with a `'` if hardened.
+fn child(header: &Zip32Header) -> String {
+    match header.is_hardened() {
+        true => format!("{}'", header.index()),
+        false => header.index().to_string(),
+    }
+}
+
+fn with_metadata(block: Block, meta: &KeyMetadata) -> Block {
+    let block = block
+        .row("version", meta.version)
+        .row("created", time_or(meta.create_time, "unknown"))
+        .row("path", meta.hd_keypath.as_deref().unwrap_or("none"));
+    match meta.seed_fingerprint_hex() {
+        Some(fingerprint) => block.hex("fingerprint", fingerprint),
+        None => block.row("fingerprint", "none"),
+    }
+}
+
+impl Render for KeyMetadata {
+    fn render(&self) -> Block {
+        with_metadata(Block::new("KeyMetadata"), self)
+    }
+}
+
+impl Render for TransparentKey {
+    fn render(&self) -> Block {
+        let key = &self.private_key;
+        Block::new("TransparentKey")
+            .hex("pubkey", hex::encode(&self.pubkey))
+            .row("compressed", yes_no(key.is_compressed()))
+            .toned("secret", redacted(key.secret.len()), Tone::Secret)
+            .row(
+                "checksum",
+                if self.checksum.is_some() {
+                    "matches"
+                } else {
+                    "none"
+                },
+            )
+    }
+}
+
+impl Render for WalletKey {
+    fn render(&self) -> Block {
+        let block = Block::new("WalletKey")
+            .hex("pubkey", hex::encode(&self.pubkey))
+            .row("version", self.version)
+            .toned(
+                "secret",
+                redacted(self.private_key.secret.len()),
+                Tone::Secret,
+            )
+            .row("created", time_or(self.created, "unknown"))
+            .row("expires", time_or(self.expires, "never"));
+        match self.comment.is_empty() {
+            true => block,
+            false => block.row("comment", format!("{:?}", self.comment)),
+        }
+    }
+}
+
+impl Render for EncryptedKey {
+    fn render(&self) -> Block {
+        Block::new("EncryptedKey")
+            .hex("pubkey", hex::encode(&self.pubkey))
+            .row("compressed", yes_no(self.is_compressed()))
+            .toned(
+                "secret",
+                encrypted(self.encrypted_secret.len()),
+                Tone::Secret,
+            )
+    }
+}
+
+impl Render for TransparentKeyMetadata {
+    fn render(&self) -> Block {
+        let block = Block::new("TransparentKeyMetadata").hex("pubkey", hex::encode(&self.pubkey));
+        with_metadata(block, &self.metadata)
+    }
+}
+
+impl Render for KeyPoolEntry {
+    fn render(&self) -> Block {
+        Block::new("KeyPoolEntry")
+            .row("index", self.index)
+            .row("version", self.version)
+            .row("added", time_or(self.time, "unknown"))
+            .hex("pubkey", hex::encode(&self.pubkey))
+    }
+}
+
+impl Render for WatchScript {
+    fn render(&self) -> Block {
+        Block::new("WatchScript")
+            .hex("script", hex::encode(&self.script))
+            .row("kind", &self.kind)
+    }
+}
+
+impl Render for RedeemScript {
+    fn render(&self) -> Block {
+        Block::new("RedeemScript")
+            .hex("script id", hex::encode(self.script_id))
+            .hex("script", hex::encode(&self.script))
+            .row("kind", &self.kind)
+    }
+}
+
+impl Render for SaplingSpendingKey {
+    fn render(&self) -> Block {
+        let key = &self.key;
+        Block::new("SaplingSpendingKey")
+            .hex("ivk", self.ivk_hex())
+            .row("depth", key.header.depth)
+            .row("child", child(&key.header))
+            .hex("parent tag", hex::encode(key.header.parent_fvk_tag))
+            .toned(
+                "ask, nsk",
+                redacted(key.ask.len() + key.nsk.len()),
+                Tone::Secret,
+            )
+    }
+}
+
+impl Render for EncryptedSaplingKey {
+    fn render(&self) -> Block {
+        let extfvk = &self.extfvk;
+        Block::new("EncryptedSaplingKey")
+            .hex("ivk", self.ivk_hex())
+            .hex("fingerprint", uint256_hex(&extfvk.fingerprint()))
+            .row("depth", extfvk.header.depth)
+            .row("child", child(&extfvk.header))
+            .toned(
+                "secret",
+                encrypted(self.encrypted_secret.len()),
+                Tone::Secret,
+            )
+    }
+}
+
+impl Render for SaplingWatchOnlyKey {
+    fn render(&self) -> Block {
+        let extfvk = &self.extfvk;
+        Block::new("SaplingWatchOnlyKey")
+            .hex("fingerprint", uint256_hex(&extfvk.fingerprint()))
+            .row("depth", extfvk.header.depth)
+            .row("child", child(&extfvk.header))
+    }
+}
+
+impl Render for SaplingAddressIvk {
+    fn render(&self) -> Block {
+        Block::new("SaplingAddressIvk")
+            .hex("diversifier", hex::encode(self.address.diversifier))
+            .hex("pk_d", uint256_hex(&self.address.pk_d))
+            .hex("ivk", self.ivk_hex())
+    }
+}
+
+impl Render for SproutSpendingKey {
+    fn render(&self) -> Block {
+        Block::new("SproutSpendingKey")
+            .hex("a_pk", self.address.a_pk_hex())
+            .hex("pk_enc", uint256_hex(&self.address.pk_enc))
+            .toned("a_sk", redacted(self.a_sk.len()), Tone::Secret)
+    }
+}
+
+impl Render for EncryptedSproutKey {
+    fn render(&self) -> Block {
+        Block::new("EncryptedSproutKey")
+            .hex("a_pk", self.address.a_pk_hex())
+            .hex("pk_enc", uint256_hex(&self.address.pk_enc))
+            .toned(
+                "secret",
+                encrypted(self.encrypted_secret.len()),
+                Tone::Secret,
+            )
+    }
+}
+
+impl Render for SaplingKeyMetadata {
+    fn render(&self) -> Block {
+        let block = Block::new("SaplingKeyMetadata").hex("ivk", uint256_hex(&self.ivk));
+        with_metadata(block, &self.metadata)
+    }
+}
+
+impl Render for SproutKeyMetadata {
+    fn render(&self) -> Block {
+        let block = Block::new("SproutKeyMetadata").hex("a_pk", self.address.a_pk_hex());
+        with_metadata(block, &self.metadata)
+    }
+}
+
+impl Render for SproutViewingKey {
+    fn render(&self) -> Block {
+        Block::new("SproutViewingKey")
+            .hex("a_pk", self.address.a_pk_hex())
+            .hex("pk_enc", uint256_hex(&self.address.pk_enc))
+    }
+}
+
+impl Render for HdSeed {
+    fn render(&self) -> Block {
+        Block::new("HdSeed")
+            .hex("fingerprint", self.fingerprint_hex())
+            .toned("seed", redacted(self.seed.len()), Tone::Secret)
+    }
+}
+
+impl Render for EncryptedHdSeed {
+    fn render(&self) -> Block {
+        Block::new("EncryptedHdSeed")
+            .hex("fingerprint", self.fingerprint_hex())
+            .toned("seed", encrypted(self.encrypted_seed.len()), Tone::Secret)
+    }
+}
+
+impl Render for MnemonicPhrase {
+    fn render(&self) -> Block {
+        Block::new("MnemonicPhrase")
+            .hex("fingerprint", self.fingerprint_hex())
+            .row("language", self.language)
+            .toned(
+                "phrase",
+                format!("{} words, redacted", self.word_count()),
+                Tone::Secret,
+            )
+    }
+}
+
+impl Render for EncryptedMnemonicPhrase {
+    fn render(&self) -> Block {
+        Block::new("EncryptedMnemonicPhrase")
+            .hex("fingerprint", self.fingerprint_hex())
+            .toned(
+                "phrase",
+                encrypted(self.encrypted_phrase.len()),
+                Tone::Secret,
+            )
+    }
+}
+
+impl Render for MnemonicHdChain {
+    fn render(&self) -> Block {
+        let (backed_up, tone) = match self.backup_confirmed {
+            true => ("yes", Tone::Good),
+            false => ("no", Tone::Warn),
+        };
+        Block::new("MnemonicHdChain")
+            .row("version", self.version)
+            .hex("fingerprint", self.seed_fingerprint_hex())
+            .row("created", time_or(self.create_time, "unknown"))
+            .row("accounts", self.account_counter)
+            .row(
+                "transparent",
+                format!(
+                    "{} external, {} internal",
+                    self.legacy_transparent_external_counter,
+                    self.legacy_transparent_internal_counter
+                ),
+            )
+            .row("sapling", self.legacy_sapling_counter)
+            .toned("backed up", backed_up, tone)
+    }
+}
+
+impl Render for MasterKey {
+    fn render(&self) -> Block {
+        Block::new("MasterKey")
+            .row("id", self.id)
+            .row("method", self.derivation_method)
+            .row("iterations", self.derive_iterations)
+            .hex("salt", hex::encode(&self.salt))
+            .toned("key", encrypted(self.encrypted_key.len()), Tone::Secret)
+    }
+}
+
+impl Render for UnifiedAccount {
+    fn render(&self) -> Block {
+        Block::new("UnifiedAccount")
+            .hex("fingerprint", uint256_hex(&self.seed_fingerprint))
+            .row("coin type", self.coin_type)
+            .row("account", self.account_id)
+            .hex("ufvk id", self.ufvk_id_hex())
+    }
+}
+
+impl Render for UnifiedFullViewingKey {
+    fn render(&self) -> Block {
+        Block::new("UnifiedFullViewingKey")
+            .hex("ufvk id", self.ufvk_id_hex())
+            .row("encoding", &self.encoding)
+    }
+}
+
+impl Render for UnifiedAddressMetadata {
+    fn render(&self) -> Block {
+        let receivers: Vec<String> = self.receiver_types.iter().map(|r| r.to_string()).collect();
+        Block::new("UnifiedAddressMetadata")
+            .hex("ufvk id", uint256_hex(&self.ufvk_id))
+            .row("diversifier", self.index())
+            .row("receivers", receivers.join(", "))
+    }
+}
+
+impl Render for Network {
+    fn render(&self) -> Block {
+        let block = Block::new("Network")
+            .row("coin", &self.coin)
+            .row("name", &self.name);
+        match self.zcash() {
+            Some(_) => block,
+            None => block.toned("network", "not a Zcash network", Tone::Warn),
+        }
+    }
+}
+
+impl Render for BlockLocator {
+    fn render(&self) -> Block {
+        let block = Block::new("BlockLocator")
+            .row("version", self.version)
+            .row("hashes", self.hashes.len());
+        match self.tip_hex() {
+            Some(tip) => block.hex("tip", tip),
+            None => block.row("tip", "none"),
+        }
+    }
+}
+
+impl Render for OrchardNoteCommitmentTree {
+    fn render(&self) -> Block {
+        Block::new("OrchardNoteCommitmentTree")
+            .row("written by", self.client_version)
+            .row("version", self.note_state_version)
+            .row(
+                "checkpoint",
+                self.last_checkpoint
+                    .map_or("none".to_string(), |height| height.to_string()),
+            )
+            .row("tree size", self.tree.size())
+            .row(
+                "checkpoints",
+                format!(
+                    "{} of {}",
+                    self.tree.checkpoints.len(),
+                    self.tree.max_checkpoints
+                ),
+            )
+            .row("notes", self.note_count())
+    }
+}
+
+impl Render for AddressName {
+    fn render(&self) -> Block {
+        Block::new("AddressName")
+            .row("address", &self.address)
+            .row("kind", self.kind())
+            .row("label", format!("{:?}", self.label))
+    }
+}
+
+impl Render for AddressPurpose {
+    fn render(&self) -> Block {
+        Block::new("AddressPurpose")
+            .row("address", &self.address)
+            .row("purpose", &self.purpose)
+    }
+}
+
+impl Render for DestData {
+    fn render(&self) -> Block {
+        Block::new("DestData")
+            .row("address", &self.address)
+            .row("key", &self.key)
+            .row("value", format!("{:?}", self.value))
+    }
+}
+
+/// One row per address, labelled by its purpose.
+impl Render for AddressBook {
+    fn render(&self) -> Block {
+        let mut block = Block::new("AddressBook");
+        for (address, entry) in &self.entries {
+            let purpose = entry.purpose.as_ref().map_or("-", Purpose::as_str);
+            let value = match &entry.label {
+                Some(label) => format!("{address} {label:?}"),
+                None => address.clone(),
+            };
+            block = block.row(purpose, value);
+            for (key, value) in &entry.dest_data {
+                block = block.row("", format!("{key} = {value:?}"));
+            }
+        }
+        block
+    }
+}
+
+impl Render for Account {
+    fn render(&self) -> Block {
+        Block::new("Account")
+            .row("name", format!("{:?}", self.name))
+            .row("version", self.version)
+            .hex("pubkey", hex::encode(&self.pubkey))
+    }
+}
+
+impl Render for AccountingEntry {
+    fn render(&self) -> Block {
+        let block = Block::new("AccountingEntry")
+            .row("account", format!("{:?}", self.account))
+            .row("number", self.number)
+            .row("amount", format!("{} ZEC", zec(self.credit_debit)))
+            .row("time", time_or(self.time, "unknown"));
+        let block = match self.other_account.is_empty() {
+            true => block,
+            false => block.row("other", format!("{:?}", self.other_account)),
+        };
+        match self.comment.is_empty() {
+            true => block,
+            false => block.row("comment", format!("{:?}", self.comment)),
+        }
+    }
+}
+
+impl Render for WalletTx {
+    fn render(&self) -> Block {
+        let tx = self.tx();
+        let block = Block::new("WalletTx").hex("txid", self.txid_hex());
+        let mut block = match self.merkle_tx.block_hash {
+            ABANDON_HASH => block.toned("status", "abandoned", Tone::Warn),
+            hash if self.merkle_tx.is_mined() => block
+                .toned("status", "mined", Tone::Good)
+                .hex("block", uint256_hex(&hash)),
+            _ => block.toned("status", "unmined", Tone::Warn),
+        };
+        let received = self.time_smart().unwrap_or(self.time_received);
+        block = block
+            .row("received", utc(received.into()))
+            .row("version", tx.version)
+            .row("from me", yes_no(self.from_me));
+        if let Some(height) = tx.coinbase_height() {
+            block = block.row("coinbase", format!("at height {height}"));
+        }
+        block = block.row(
+            "transparent",
+            format!(
+                "{} in, {} out, {} ZEC out",
+                tx.vin.len(),
+                tx.vout.len(),
+                zec(tx.transparent_value_out())
+            ),
+        );
+        if let Some(sprout) = &tx.sprout {
+            block = block.row("sprout", format!("{} joinsplits", sprout.joinsplits.len()));
+        }
+        if let Some(sapling) = &tx.sapling {
+            block = block.row(
+                "sapling",
+                format!(
+                    "{} spends, {} outputs, {} ZEC balance",
+                    sapling.spends.len(),
+                    sapling.outputs.len(),
+                    zec(sapling.value_balance)
+                ),
+            );
+        }
+        if let Some(orchard) = &tx.orchard {
+            block = block.row(
+                "orchard",
+                format!(
+                    "{} actions, {} ZEC balance",
+                    orchard.actions.len(),
+                    zec(orchard.value_balance)
+                ),
+            );
+        }
+        let caches = self.witness_caches();
+        let unwitnessed = caches.iter().filter(|c| !c.is_witnessed()).count();
+        let orchard_notes = self
+            .orchard_meta
+            .as_ref()
+            .map_or(0, |m| m.action_data.len());
+        if !caches.is_empty() || orchard_notes > 0 {
+            let notes = format!(
+                "{} Sprout, {} Sapling, {orchard_notes} Orchard",
+                self.sprout_note_data.len(),
+                self.sapling_note_data.len()
+            );
+            block = match unwitnessed {
+                0 => block.row("notes", notes),
+                n => block.toned(
+                    "notes",
+                    format!("{notes}; {n} without a witness"),
+                    Tone::Warn,
+                ),
+            };
+        }
+        for (label, key) in [("comment", "comment"), ("to", "to")] {
+            if let Some(value) = self.map_value.get(key) {
+                block = block.row(label, format!("{value:?}"));
+            }
+        }
+        block
+    }
+}
+
+impl Render for DefaultKey {
+    fn render(&self) -> Block {
+        Block::new("DefaultKey").hex("pubkey", hex::encode(&self.pubkey))
+    }
+}
+
+impl Render for ClientVersion {
+    fn render(&self) -> Block {
+        Block::new("ClientVersion").row("version", self)
+    }
+}
+
+impl Render for UnknownRecord {
+    fn render(&self) -> Block {
+        let tag = self
+            .tag
+            .as_ref()
+            .map_or("none".to_string(), |tag| format!("{tag:?}"));
+        let preview = &self.value[..self.value.len().min(VALUE_PREVIEW)];
+        let more = if preview.len() < self.value.len() {
+            ".."
+        } else {
+            ""
+        };
+        Block::new("UnknownRecord")
+            .toned("tag", tag, Tone::Warn)
+            .hex("key", hex::encode(&self.key_rest))
+            .row("value", format!("{} bytes", self.value.len()))
+            .hex("", format!("{}{more}", hex::encode(preview)))
+    }
+}
+
+/// The block of a value one of the built-in decoders produces, or `None` for any other
+/// type.
+pub fn render_item(item: &dyn DecodedItem) -> Option<Block> {
+    let any = item.as_any();
+    macro_rules! render_as {
+        ($($ty:ty),* $(,)?) => {
+            $(
+                if let Some(value) = any.downcast_ref::<$ty>() {
+                    return Some(value.render());
+                }
+            )*
+        };
+    }
+    render_as!(
+        TransparentKey,
+        WalletKey,
+        EncryptedKey,
+        TransparentKeyMetadata,
+        KeyPoolEntry,
+        WatchScript,
+        RedeemScript,
+        SaplingSpendingKey,
+        EncryptedSaplingKey,
+        SaplingKeyMetadata,
+        SaplingWatchOnlyKey,
+        SaplingAddressIvk,
+        SproutSpendingKey,
+        EncryptedSproutKey,
+        SproutKeyMetadata,
+        SproutViewingKey,
+        HdSeed,
+        EncryptedHdSeed,
+        MnemonicPhrase,
+        EncryptedMnemonicPhrase,
+        MnemonicHdChain,
+        MasterKey,
+        UnifiedAccount,
+        UnifiedFullViewingKey,
+        UnifiedAddressMetadata,
+        Network,
+        BlockLocator,
+        OrchardNoteCommitmentTree,
+        AddressName,
+        AddressPurpose,
+        DestData,
+        Account,
+        AccountingEntry,
+        WalletTx,
+        DefaultKey,
+        ClientVersion,
+    );
+    None
+}
+
+/// The decoded value's block, headed by the record's kind. Values of other types than
+/// the built-in decoders produce, such as the integers of `orderposnext`, are shown by
+/// their `Debug`.
+impl Render for DecodedEntry {
+    fn render(&self) -> Block {
+        let mut block = match &self.value {
+            Some(Ok(item)) => render_item(&**item)
+                .unwrap_or_else(|| Block::new("Record").row("value", format!("{item:?}"))),
+            Some(Err(error)) => Block::new("RecordError").toned("error", error, Tone::Bad),
+            None => Block::new("Record").toned("value", "no decoder for this kind", Tone::Warn),
+        };
+        let mut head = vec![Row {
+            label: "record".to_string(),
+            value: self.kind.to_string(),
+            tone: Tone::Plain,
+        }];
+        if let (Some(key), false) = (&self.key, matches!(self.value, Some(Ok(_)))) {
+            head.push(Row {
+                label: "key".to_string(),
+                value: key.to_string(),
+                tone: Tone::Hex,
+            });
+        }
+        block.rows.splice(0..0, head);
+        block
+    }
+}
+
+impl Render for DecodedRecord {
+    fn render(&self) -> Block {
+        match self {
+            DecodedRecord::Known(entry) => entry.render(),
+            DecodedRecord::Unknown(record) => record.render(),
+        }
+    }
+}
+
+/// An overview of the whole wallet: what wrote it, its seed and encryption, its keys
+/// by pool, its transactions, and what could not be decoded.
+impl Render for ZcashdWallet {
+    fn render(&self) -> Block {
+        let (keys, meta, crypto) = (&self.keys, &self.metadata, &self.crypto);
+        let mut block = Block::new("ZcashdWallet");
+        block = match meta.version {
+            Some(version) => block.row("version", version),
+            None => block.toned("version", "unknown", Tone::Warn),
+        };
+        if let Some(min_version) = meta.min_version {
+            block = block.row("minversion", min_version);
+        }
+        block = match (&meta.network, self.network()) {
+            (Some(network), Some(_)) => block.row("network", network),
+            (Some(network), None) => {
+                block.toned("network", format!("{network}, not Zcash"), Tone::Warn)
+            }
+            (None, _) => block.toned("network", "unknown", Tone::Warn),
+        };
+        block = match crypto.master_keys.len() {
+            0 => block.row("encrypted", "no"),
+            n => block.toned("encrypted", format!("yes, {n} master keys"), Tone::Secret),
+        };
+        if let Some(mnemonic) = &crypto.mnemonic {
+            let phrase = format!(
+                "mnemonic, {} words ({}), redacted",
+                mnemonic.word_count(),
+                mnemonic.language
+            );
+            block = block
+                .toned("seed", phrase, Tone::Secret)
+                .hex("fingerprint", mnemonic.fingerprint_hex());
+        } else if let Some(encrypted) = &crypto.encrypted_mnemonic {
+            block = block
+                .toned("seed", "mnemonic, encrypted", Tone::Secret)
+                .hex("fingerprint", encrypted.fingerprint_hex());
+        }
+        if let Some(seed) = &crypto.hd_seed {
+            block = block
+                .toned("legacy seed", redacted(seed.seed.len()), Tone::Secret)
+                .hex("fingerprint", seed.fingerprint_hex());
+        } else if let Some(seed) = &crypto.encrypted_hd_seed {
+            block = block
+                .toned(
+                    "legacy seed",
+                    encrypted(seed.encrypted_seed.len()),
+                    Tone::Secret,
+                )
+                .hex("fingerprint", seed.fingerprint_hex());
+        }
+        if let Some(chain) = &crypto.mnemonic_hd_chain {
+            let created = time_or(chain.create_time, "at an unknown time");
+            block = block.row("hd chain", format!("created {created}"));
+            block = match chain.backup_confirmed {
+                true => block.toned("backed up", "yes", Tone::Good),
+                false => block.toned("backed up", "no", Tone::Warn),
+            };
+        }
+        block = match (
+            best_height(self),
+            meta.best_block.as_ref().and_then(|b| b.tip_hex()),
+        ) {
+            (Some(height), _) => block.row("best block", format!("height {height}")),
+            (None, Some(tip)) => block.hex("best block", tip),
+            (None, None) => block.row("best block", "unknown"),
+        };
+        block = block
+            .row(
+                "transparent",
+                format!(
+                    "{} keys, {} scripts",
+                    keys.transparent_len(),
+                    keys.watch_scripts.len() + keys.redeem_scripts.len()
+                ),
+            )
+            .row("key pool", keys.key_pool.len())
+            .row(
+                "sprout",
+                format!(
+                    "{} keys, {} viewing keys",
+                    keys.sprout_len(),
+                    keys.sprout_viewing.len()
+                ),
+            )
+            .row(
+                "sapling",
+                format!(
+                    "{} keys, {} watch-only, {} addresses",
+                    keys.sapling_len(),
+                    keys.sapling_watch_only.len(),
+                    keys.sapling_addresses.len()
+                ),
+            )
+            .row(
+                "orchard",
+                self.orchard.as_ref().map_or("no tree".to_string(), |tree| {
+                    format!(
+                        "{} notes, tree size {}",
+                        tree.note_count(),
+                        tree.tree.size()
+                    )
+                }),
+            )
+            .row(
+                "unified",
+                format!(
+                    "{} accounts, {} addresses",
+                    keys.unified_accounts.len(),
+                    keys.unified_addresses.len()
+                ),
+            )
+            .row("address book", self.address_book.len());
+        let unmined = self
+            .transactions
+            .values()
+            .filter(|wtx| !wtx.merkle_tx.is_mined())
+            .count();
+        block = match unmined {
+            0 => block.row("transactions", self.transactions.len()),
+            n => block.toned(
+                "transactions",
+                format!("{}, {n} unmined", self.transactions.len()),
+                Tone::Warn,
+            ),
+        };
+        let received = self.transactions.values().map(|wtx| wtx.time_received);
+        if let (Some(first), Some(last)) = (received.clone().min(), received.max()) {
+            block = block
+                .row("first tx", utc(first.into()))
+                .row("last tx", utc(last.into()));
+        }
+        if !self.accounts.is_empty() || !self.accounting_entries.is_empty() {
+            block = block.row(
+                "accounting",
+                format!(
+                    "{} accounts, {} entries",
+                    self.accounts.len(),
+                    self.accounting_entries.len()
+                ),
+            );
+        }
+        for (kind, count) in &self.undecoded {
+            block = block.row("undecoded", format!("{count} {kind}"));
+        }
+        for error in &self.errors {
+            block = block.toned("error", error, Tone::Bad);
+        }
+        for warning in &self.warnings {
+            block = block.toned("warning", warning, Tone::Warn);
+        }
+        block
+    }
+}
@@ -0,0 +1,146 @@
+//! Holders for secret material decoded from a wallet: private keys, seeds, mnemonics.
+//!
+//! They print as `<redacted>` through both `Debug` and `Display`, so a secret only leaves
+//! through an explicit `expose`, and they overwrite their buffer with zeroes when
+//! dropped. With the `serde` feature they serialize as `<redacted>` too, except inside
+//! [`exposing`]. Wiping is best effort: copies the allocator made while the value was built,
+//! or that callers make of the exposed bytes, are not tracked.
+
+use std::{fmt, hint::black_box};
+
+#[cfg(feature = "serde")]
+use std::cell::Cell;
+
+/// Overwrite `bytes` with zeroes in a way the optimizer cannot drop as a dead store.
+fn zeroize(bytes: &mut [u8]) {
+    bytes.fill(0);
+    black_box(bytes);
+}
+
+#[cfg(feature = "serde")]
+thread_local! {
+    static EXPOSED: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Run `f` with the secrets serialized on this thread written out, bytes as hex, instead
+/// of as `<redacted>`. The serialized copies are the caller's to protect.
+#[cfg(feature = "serde")]
+pub fn exposing<R>(f: impl FnOnce() -> R) -> R {
+    struct Restore(bool);
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            EXPOSED.set(self.0);
+        }
+    }
+    let _restore = Restore(EXPOSED.replace(true));
+    f()
+}
+
+/// Secret bytes, such as a private key or a raw seed.
+#[derive(Clone, PartialEq, Eq)]
+pub struct SecretBytes(Vec<u8>);
+
+impl SecretBytes {
+    pub fn new(bytes: Vec<u8>) -> Self {
+        SecretBytes(bytes)
+    }
+
+    /// The secret itself.
+    pub fn expose(&self) -> &[u8] {
+        &self.0
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl From<&[u8]> for SecretBytes {
+    fn from(bytes: &[u8]) -> Self {
+        SecretBytes(bytes.to_vec())
+    }
+}
+
+impl Drop for SecretBytes {
+    fn drop(&mut self) {
+        zeroize(&mut self.0);
+    }
+}
+
+impl fmt::Debug for SecretBytes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("<redacted>")
+    }
+}
+
+impl fmt::Display for SecretBytes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("<redacted>")
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for SecretBytes {
+    fn serialize<S: serde::Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        match EXPOSED.get() {
+            true => s.serialize_str(&hex::encode(&self.0)),
+            false => s.serialize_str("<redacted>"),
+        }
+    }
+}
+
+/// A secret string, such as a mnemonic phrase.
+#[derive(Clone, PartialEq, Eq)]
+pub struct SecretString(String);
+
+impl SecretString {
+    pub fn new(s: String) -> Self {
+        SecretString(s)
+    }
+
+    /// The secret itself.
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl Drop for SecretString {
+    fn drop(&mut self) {
+        let mut bytes = std::mem::take(&mut self.0).into_bytes();
+        zeroize(&mut bytes);
+    }
+}
+
+impl fmt::Debug for SecretString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("<redacted>")
+    }
+}
+
+impl fmt::Display for SecretString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("<redacted>")
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for SecretString {
+    fn serialize<S: serde::Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        match EXPOSED.get() {
+            true => s.serialize_str(&self.0),
+            false => s.serialize_str("<redacted>"),
+        }
+    }
+}
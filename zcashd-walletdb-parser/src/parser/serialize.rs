@@ -0,0 +1,203 @@
+//! Reading the Bitcoin-style serialization zcashd writes keys and values in: little-endian
+//! integers, compact-size lengths, fixed-size blobs such as `uint256`, and the containers
+//! built from them: `std::optional` as a flag byte and the value, vectors as a
+//! compact-size count and the items, maps as a vector of key-value pairs.
+//!
+//! Every record decoder reads through [`Reader`], so errors carry the offset decoding
+//! stopped at.
+
+use std::{collections::BTreeMap, fmt};
+
+use crate::{
+    entry::parser::read_compact_size,
+    parser::record::{DecodeError, DecodeResult},
+};
+
+/// `MAX_SIZE` in zcashd's `serialize.h`: the largest compact size it accepts.
+pub const MAX_SIZE: u64 = 0x0200_0000;
+
+/// A cursor over serialized bytes. Every read names what it was after, so a short or
+/// malformed input reports what was being read and where decoding stopped.
+#[derive(Debug, Clone)]
+pub struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    pub fn new(bytes: &'a [u8]) -> Self {
+        Reader { bytes, pos: 0 }
+    }
+
+    /// Bytes read so far.
+    pub fn position(&self) -> usize {
+        self.pos
+    }
+
+    /// The bytes not read yet.
+    pub fn rest(&self) -> &'a [u8] {
+        &self.bytes[self.pos..]
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pos == self.bytes.len()
+    }
+
+    /// An error about `what`, at the current offset.
+    fn error(&self, what: &str, problem: impl fmt::Display) -> DecodeError {
+        DecodeError::at(self.pos, format!("{what}: {problem}"))
+    }
+
+    /// Fail unless every byte was read.
+    pub fn finish(&self) -> DecodeResult<()> {
+        match self.rest().len() {
+            0 => Ok(()),
+            n => Err(DecodeError::at(self.pos, format!("{n} trailing bytes"))),
+        }
+    }
+
+    /// The next `n` bytes.
+    pub fn bytes(&mut self, n: usize, what: &str) -> DecodeResult<&'a [u8]> {
+        let end = self
+            .pos
+            .checked_add(n)
+            .filter(|&end| end <= self.bytes.len())
+            .ok_or_else(|| {
+                let left = self.bytes.len() - self.pos;
+                self.error(what, format_args!("wanted {n} bytes, {left} left"))
+            })?;
+        let out = &self.bytes[self.pos..end];
+        self.pos = end;
+        Ok(out)
+    }
+
+    pub fn array<const N: usize>(&mut self, what: &str) -> DecodeResult<[u8; N]> {
+        Ok(self.bytes(N, what)?.try_into().unwrap())
+    }
+
+    pub fn u8(&mut self, what: &str) -> DecodeResult<u8> {
+        Ok(self.bytes(1, what)?[0])
+    }
+
+    pub fn bool(&mut self, what: &str) -> DecodeResult<bool> {
+        Ok(self.u8(what)? != 0)
+    }
+
+    pub fn u32(&mut self, what: &str) -> DecodeResult<u32> {
+        self.array(what).map(u32::from_le_bytes)
+    }
+
+    pub fn i32(&mut self, what: &str) -> DecodeResult<i32> {
+        self.array(what).map(i32::from_le_bytes)
+    }
+
+    pub fn u64(&mut self, what: &str) -> DecodeResult<u64> {
+        self.array(what).map(u64::from_le_bytes)
+    }
+
+    pub fn i64(&mut self, what: &str) -> DecodeResult<i64> {
+        self.array(what).map(i64::from_le_bytes)
+    }
+
+    /// A `uint256`, in serialized byte order.
+    pub fn uint256(&mut self, what: &str) -> DecodeResult<[u8; 32]> {
+        self.array(what)
+    }
+
+    /// A compact size, which like zcashd's `ReadCompactSize` must be in its shortest
+    /// encoding and at most [`MAX_SIZE`].
+    pub fn compact_size(&mut self, what: &str) -> DecodeResult<u64> {
+        let (n, len) = read_compact_size(self.rest())
+            .ok_or_else(|| self.error(what, "truncated compact size"))?;
+        let shortest = match n {
+            0..0xfd => 1,
+            0xfd..=0xffff => 3,
+            0x1_0000..=0xffff_ffff => 5,
+            _ => 9,
+        };
+        if len != shortest {
+            return Err(self.error(what, format_args!("non-canonical compact size {n}")));
+        }
+        if n > MAX_SIZE {
+            return Err(self.error(what, format_args!("compact size {n} is too large")));
+        }
+        self.pos += len;
+        Ok(n)
+    }
+
+    /// A compact-size count of items, each at least `min_item_len` bytes, checked against
+    /// the bytes left so a corrupt count cannot ask for a huge allocation.
+    pub fn count(&mut self, min_item_len: usize, what: &str) -> DecodeResult<usize> {
+        let start = self.pos;
+        let n = self.compact_size(what)?;
+        let left = self.rest().len() as u64;
+        if n.saturating_mul(min_item_len.max(1) as u64) > left {
+            return Err(DecodeError::at(
+                start,
+                format!("{what}: {n} items do not fit in the {left} bytes left"),
+            ));
+        }
+        Ok(n as usize)
+    }
+
+    /// Compact-size-prefixed bytes, e.g. a `CPubKey` or a `CScript`.
+    pub fn var_bytes(&mut self, what: &str) -> DecodeResult<&'a [u8]> {
+        let n = self.count(1, what)?;
+        self.bytes(n, what)
+    }
+
+    /// A compact-size-prefixed UTF-8 string.
+    pub fn string(&mut self, what: &str) -> DecodeResult<String> {
+        let start = self.pos;
+        let bytes = self.var_bytes(what)?;
+        String::from_utf8(bytes.to_vec())
+            .map_err(|_| DecodeError::at(start, format!("{what}: not UTF-8")))
+    }
+
+    /// An optional value: a flag byte, then the value if the flag is set.
+    pub fn optional<T>(
+        &mut self,
+        what: &str,
+        value: impl FnOnce(&mut Self) -> DecodeResult<T>,
+    ) -> DecodeResult<Option<T>> {
+        match self.bool(what)? {
+            true => Ok(Some(value(self)?)),
+            false => Ok(None),
+        }
+    }
+
+    /// A compact-size count of items of at least `min_item_len` bytes each, then the
+    /// items.
+    pub fn list<T>(
+        &mut self,
+        min_item_len: usize,
+        what: &str,
+        mut item: impl FnMut(&mut Self) -> DecodeResult<T>,
+    ) -> DecodeResult<Vec<T>> {
+        let n = self.count(min_item_len, what)?;
+        let mut items = Vec::with_capacity(n);
+        for _ in 0..n {
+            items.push(item(self)?);
+        }
+        Ok(items)
+    }
+
+    /// A map, serialized as a list of key-value pairs. A repeated key keeps its last
+    /// value, as it would in zcashd.
+    pub fn map<K: Ord, V>(
+        &mut self,
+        min_item_len: usize,
+        what: &str,
+        entry: impl FnMut(&mut Self) -> DecodeResult<(K, V)>,
+    ) -> DecodeResult<BTreeMap<K, V>> {
+        Ok(self.list(min_item_len, what, entry)?.into_iter().collect())
+    }
+}
+
+/// A `uint256` the way zcashd prints it (`GetHex`): most significant byte first, i.e. the
+/// serialized bytes reversed.
+pub fn uint256_hex(bytes: &[u8; 32]) -> String {
+    let mut reversed = *bytes;
+    reversed.reverse();
+    hex::encode(reversed)
+}
@@ -0,0 +1,164 @@
+//! A one-page summary of a wallet: what it is, what it holds, and what is wrong with it.
+//!
+//! [`WalletSummary`] is the first thing to look at when a wallet comes in for support.
+//! Each of its problems comes from one of the deeper reports, [`LintReport`] (which also
+//! lists the records that failed to decode), [`KeyVerification`], [`WalletProfile`],
+//! [`check_seeds`] and [`check_witnesses`]; those reports tell the rest.
+
+use std::fmt;
+
+use crate::{
+    parser::{
+        balance::{Balances, best_height},
+        decoders::network::ZcashNetwork,
+        lint::LintReport,
+        merkle::check_witnesses,
+        mnemonic::check_seeds,
+        profile::WalletProfile,
+        render::utc,
+        serialize::uint256_hex,
+        verify::KeyVerification,
+        wallet::ZcashdWallet,
+    },
+    storage::consistency::Severity,
+};
+
+/// The outcome of [`WalletSummary::of`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WalletSummary {
+    pub network: Option<ZcashNetwork>,
+    pub encrypted: bool,
+    /// The fingerprint of the seed the wallet derives keys from, if it has one.
+    pub seed_fingerprint: Option<[u8; 32]>,
+    /// Transparent key pairs, plain, wrapped or encrypted.
+    pub transparent_keys: usize,
+    /// Sprout spending keys, plain or encrypted.
+    pub sprout_keys: usize,
+    /// Sapling spending keys, plain or encrypted.
+    pub sapling_keys: usize,
+    pub unified_accounts: usize,
+    /// The wallet's own t-addresses, Sapling addresses and unified addresses.
+    pub addresses: usize,
+    pub transactions: usize,
+    pub best_height: Option<u32>,
+    pub best_block_hash: Option<String>,
+    /// The newest creation time of any key's metadata.
+    pub last_key_birth: Option<i64>,
+    /// Decoder warnings, and the warnings and errors of the other reports (records
+    /// that failed to decode among them).
+    pub problems: Vec<String>,
+}
+
+impl WalletSummary {
+    /// Summarize `wallet`. Encrypted keys are not decrypted, so they are neither checked
+    /// against their records nor counted as problems.
+    pub fn of(wallet: &ZcashdWallet) -> Self {
+        let keys = &wallet.keys;
+        let last_key_birth = keys
+            .transparent_metadata
+            .values()
+            .chain(keys.sprout_metadata.values())
+            .chain(keys.sapling_metadata.values())
+            .map(|meta| meta.create_time)
+            .filter(|&time| time > 0)
+            .max();
+
+        let mut problems: Vec<String> = wallet.warnings.iter().map(|w| w.to_string()).collect();
+        problems.extend(
+            LintReport::of(wallet)
+                .issues
+                .iter()
+                .filter(|issue| issue.severity() > Severity::Info)
+                .map(|issue| issue.to_string()),
+        );
+        problems.extend(
+            KeyVerification::of(wallet, None)
+                .issues
+                .iter()
+                .map(|issue| issue.to_string()),
+        );
+        problems.extend(
+            WalletProfile::of(wallet)
+                .issues
+                .iter()
+                .map(|issue| issue.to_string()),
+        );
+        problems.extend(check_seeds(wallet, None).iter().map(|i| i.to_string()));
+        problems.extend(
+            check_witnesses(wallet)
+                .iter()
+                .map(|i| format!("witness {i}")),
+        );
+        let rescan = Balances::of(wallet).requires_rescan().count();
+        if rescan > 0 {
+            problems.push(format!(
+                "{rescan} notes have no witness; zcashd must rescan to spend them"
+            ));
+        }
+
+        WalletSummary {
+            network: wallet.network(),
+            encrypted: wallet.crypto.is_encrypted(),
+            seed_fingerprint: wallet.crypto.seed_fingerprint(),
+            transparent_keys: keys.transparent_len(),
+            sprout_keys: keys.sprout_len(),
+            sapling_keys: keys.sapling_len(),
+            unified_accounts: keys.unified_accounts.len(),
+            addresses: keys.transparent_addresses().len()
+                + keys.sapling_addresses.len()
+                + keys.unified_addresses.len(),
+            transactions: wallet.transactions.len(),
+            best_height: best_height(wallet),
+            best_block_hash: wallet
+                .metadata
+                .best_block
+                .as_ref()
+                .and_then(|locator| locator.tip_hex()),
+            last_key_birth,
+            problems,
+        }
+    }
+}
+
+impl fmt::Display for WalletSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let or_unknown = |value: Option<String>| value.unwrap_or_else(|| "unknown".to_string());
+        writeln!(f, "WalletSummary {{")?;
+        writeln!(
+            f,
+            "  network      : {}",
+            or_unknown(self.network.map(|n| n.to_string()))
+        )?;
+        writeln!(f, "  encrypted    : {}", self.encrypted)?;
+        writeln!(
+            f,
+            "  seed         : {}",
+            self.seed_fingerprint
+                .as_ref()
+                .map_or_else(|| "none".to_string(), uint256_hex)
+        )?;
+        writeln!(
+            f,
+            "  keys         : {} transparent, {} sprout, {} sapling, {} unified accounts",
+            self.transparent_keys, self.sprout_keys, self.sapling_keys, self.unified_accounts
+        )?;
+        writeln!(f, "  addresses    : {}", self.addresses)?;
+        writeln!(f, "  transactions : {}", self.transactions)?;
+        writeln!(
+            f,
+            "  best block   : {} {}",
+            or_unknown(self.best_height.map(|h| h.to_string())),
+            or_unknown(self.best_block_hash.clone())
+        )?;
+        writeln!(
+            f,
+            "  last key     : {}",
+            or_unknown(self.last_key_birth.map(utc))
+        )?;
+        writeln!(f, "  problems     : {}", self.problems.len())?;
+        for problem in &self.problems {
+            writeln!(f, "  problem      : {problem}")?;
+        }
+        write!(f, "}}")
+    }
+}
@@ -0,0 +1,782 @@
+//! zcashd's `CTransaction`, in every version a wallet can hold: v1 (Bitcoin-style), v2
+//! (Sprout JoinSplits), v3 (Overwinter), v4 (Sapling) and v5 (NU5, ZIP 225).
+//!
+//! The header word carries the `fOverwintered` flag in its top bit and the version in
+//! the rest. From Overwinter on, a version group id follows, which together with the
+//! version picks the layout. v5 moves the lock time and expiry height to the front,
+//! shares one anchor between all Sapling spends, and moves proofs and signatures after
+//! the descriptions they belong to.
+//!
+//! [`Transaction::write`] re-serializes a transaction byte for byte, and
+//! [`Transaction::txid`] hashes it: a double SHA-256 up to v4, the ZIP 244 digest tree
+//! for v5.
+
+mod zip244;
+
+use crate::{
+    crypto::sha256::sha256d,
+    entry::parser::write_compact_size,
+    parser::{
+        record::{DecodeError, DecodeResult},
+        serialize::Reader,
+    },
+};
+
+pub const OVERWINTER_VERSION_GROUP_ID: u32 = 0x03c4_8270;
+pub const SAPLING_VERSION_GROUP_ID: u32 = 0x892f_2085;
+pub const ZIP225_VERSION_GROUP_ID: u32 = 0x26a7_270a;
+
+/// The first version with Sapling shielded data.
+pub const SAPLING_TX_VERSION: u32 = 4;
+/// The first version in the ZIP 225 layout, with Orchard.
+pub const ZIP225_TX_VERSION: u32 = 5;
+
+/// A BCTV14 (PHGR13) proof, used by JoinSplits before Sapling.
+const PHGR_PROOF_LEN: usize = 296;
+/// A Groth16 proof.
+const GROTH_PROOF_LEN: usize = 192;
+const SPROUT_CIPHERTEXT_LEN: usize = 601;
+const ENC_CIPHERTEXT_LEN: usize = 580;
+const OUT_CIPHERTEXT_LEN: usize = 80;
+
+/// A reference to a transparent output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct OutPoint {
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::util::serde_hex::bytes")
+    )]
+    pub txid: [u8; 32],
+    pub n: u32,
+}
+
+impl OutPoint {
+    /// The null outpoint a coinbase input spends.
+    pub fn is_null(&self) -> bool {
+        self.txid == [0; 32] && self.n == u32::MAX
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct TxIn {
+    pub prevout: OutPoint,
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::util::serde_hex::bytes")
+    )]
+    pub script_sig: Vec<u8>,
+    pub sequence: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct TxOut {
+    /// In zatoshis.
+    pub value: i64,
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::util::serde_hex::bytes")
+    )]
+    pub script_pubkey: Vec<u8>,
+}
+
+/// A Sprout JoinSplit description.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct JsDescription {
+    /// Value taken from the transparent pool, in zatoshis.
+    pub vpub_old: i64,
+    /// Value returned to the transparent pool, in zatoshis.
+    pub vpub_new: i64,
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::util::serde_hex::bytes")
+    )]
+    pub anchor: [u8; 32],
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::util::serde_hex::byte_list")
+    )]
+    pub nullifiers: [[u8; 32]; 2],
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::util::serde_hex::byte_list")
+    )]
+    pub commitments: [[u8; 32]; 2],
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::util::serde_hex::bytes")
+    )]
+    pub ephemeral_key: [u8; 32],
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::util::serde_hex::bytes")
+    )]
+    pub random_seed: [u8; 32],
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::util::serde_hex::byte_list")
+    )]
+    pub macs: [[u8; 32]; 2],
+    /// A PHGR13 proof before v4, a Groth16 proof from v4 on.
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::util::serde_hex::bytes")
+    )]
+    pub proof: Vec<u8>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::util::serde_hex::byte_list")
+    )]
+    pub ciphertexts: [Vec<u8>; 2],
+}
+
+/// The JoinSplits of a v2 to v4 transaction and the signature over them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct SproutBundle {
+    pub joinsplits: Vec<JsDescription>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::util::serde_hex::bytes")
+    )]
+    pub pubkey: [u8; 32],
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::util::serde_hex::bytes")
+    )]
+    pub sig: [u8; 64],
+}
+
+/// A Sapling spend. In v5 the anchor is shared by the whole bundle and copied here.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct SpendDescription {
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::util::serde_hex::bytes")
+    )]
+    pub cv: [u8; 32],
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::util::serde_hex::bytes")
+    )]
+    pub anchor: [u8; 32],
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::util::serde_hex::bytes")
+    )]
+    pub nullifier: [u8; 32],
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::util::serde_hex::bytes")
+    )]
+    pub rk: [u8; 32],
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::util::serde_hex::bytes")
+    )]
+    pub zkproof: Vec<u8>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::util::serde_hex::bytes")
+    )]
+    pub spend_auth_sig: [u8; 64],
+}
+
+/// A Sapling output.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct OutputDescription {
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::util::serde_hex::bytes")
+    )]
+    pub cv: [u8; 32],
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::util::serde_hex::bytes")
+    )]
+    pub cmu: [u8; 32],
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::util::serde_hex::bytes")
+    )]
+    pub ephemeral_key: [u8; 32],
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::util::serde_hex::bytes")
+    )]
+    pub enc_ciphertext: Vec<u8>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::util::serde_hex::bytes")
+    )]
+    pub out_ciphertext: Vec<u8>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::util::serde_hex::bytes")
+    )]
+    pub zkproof: Vec<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct SaplingBundle {
+    /// Net value leaving the Sapling pool, in zatoshis.
+    pub value_balance: i64,
+    pub spends: Vec<SpendDescription>,
+    pub outputs: Vec<OutputDescription>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::util::serde_hex::bytes")
+    )]
+    pub binding_sig: [u8; 64],
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct OrchardAction {
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::util::serde_hex::bytes")
+    )]
+    pub cv: [u8; 32],
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::util::serde_hex::bytes")
+    )]
+    pub nullifier: [u8; 32],
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::util::serde_hex::bytes")
+    )]
+    pub rk: [u8; 32],
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::util::serde_hex::bytes")
+    )]
+    pub cmx: [u8; 32],
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::util::serde_hex::bytes")
+    )]
+    pub ephemeral_key: [u8; 32],
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::util::serde_hex::bytes")
+    )]
+    pub enc_ciphertext: Vec<u8>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::util::serde_hex::bytes")
+    )]
+    pub out_ciphertext: Vec<u8>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::util::serde_hex::bytes")
+    )]
+    pub spend_auth_sig: [u8; 64],
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct OrchardBundle {
+    pub actions: Vec<OrchardAction>,
+    /// Bit 0 enables spends, bit 1 outputs.
+    pub flags: u8,
+    /// Net value leaving the Orchard pool, in zatoshis.
+    pub value_balance: i64,
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::util::serde_hex::bytes")
+    )]
+    pub anchor: [u8; 32],
+    /// One aggregated Halo 2 proof for all actions.
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::util::serde_hex::bytes")
+    )]
+    pub proof: Vec<u8>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::util::serde_hex::bytes")
+    )]
+    pub binding_sig: [u8; 64],
+}
+
+/// A decoded `CTransaction`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Transaction {
+    pub overwintered: bool,
+    pub version: u32,
+    /// Present from Overwinter on.
+    pub version_group_id: Option<u32>,
+    /// Present in v5 only.
+    pub consensus_branch_id: Option<u32>,
+    pub vin: Vec<TxIn>,
+    pub vout: Vec<TxOut>,
+    pub lock_time: u32,
+    /// Present from Overwinter on.
+    pub expiry_height: Option<u32>,
+    pub sprout: Option<SproutBundle>,
+    pub sapling: Option<SaplingBundle>,
+    pub orchard: Option<OrchardBundle>,
+}
+
+impl Transaction {
+    /// Read a transaction from `r`.
+    pub fn read(r: &mut Reader<'_>) -> DecodeResult<Self> {
+        let header = r.u32("header")?;
+        let overwintered = header >> 31 == 1;
+        let version = header & 0x7fff_ffff;
+        let version_group_id = match overwintered {
+            true => Some(r.u32("version group id")?),
+            false => None,
+        };
+        let mut tx = Transaction {
+            overwintered,
+            version,
+            version_group_id,
+            consensus_branch_id: None,
+            vin: Vec::new(),
+            vout: Vec::new(),
+            lock_time: 0,
+            expiry_height: None,
+            sprout: None,
+            sapling: None,
+            orchard: None,
+        };
+        match (overwintered, version_group_id, version) {
+            (true, Some(ZIP225_VERSION_GROUP_ID), ZIP225_TX_VERSION) => tx.read_v5(r)?,
+            (true, Some(OVERWINTER_VERSION_GROUP_ID), 3)
+            | (true, Some(SAPLING_VERSION_GROUP_ID), SAPLING_TX_VERSION)
+            | (false, None, 1..=2) => tx.read_v1_to_v4(r)?,
+            _ => {
+                return Err(DecodeError::new(format!(
+                    "transaction: unknown version {version} (overwintered {overwintered}, \
+                     group {version_group_id:08x?})"
+                )));
+            }
+        }
+        Ok(tx)
+    }
+
+    fn read_v1_to_v4(&mut self, r: &mut Reader<'_>) -> DecodeResult<()> {
+        let sapling = self.version >= SAPLING_TX_VERSION;
+        self.vin = read_vin(r)?;
+        self.vout = read_vout(r)?;
+        self.lock_time = r.u32("lock time")?;
+        if self.overwintered {
+            self.expiry_height = Some(r.u32("expiry height")?);
+        }
+        let mut bundle = None;
+        if sapling {
+            let value_balance = r.i64("value balance")?;
+            let spends = r.list(384, "sapling spends", |r| {
+                Ok(SpendDescription {
+                    cv: r.uint256("cv")?,
+                    anchor: r.uint256("anchor")?,
+                    nullifier: r.uint256("nullifier")?,
+                    rk: r.uint256("rk")?,
+                    zkproof: r.bytes(GROTH_PROOF_LEN, "spend proof")?.to_vec(),
+                    spend_auth_sig: r.array("spend auth sig")?,
+                })
+            })?;
+            let outputs = r.list(948, "sapling outputs", |r| {
+                Ok(OutputDescription {
+                    cv: r.uint256("cv")?,
+                    cmu: r.uint256("cmu")?,
+                    ephemeral_key: r.uint256("ephemeral key")?,
+                    enc_ciphertext: r.bytes(ENC_CIPHERTEXT_LEN, "enc ciphertext")?.to_vec(),
+                    out_ciphertext: r.bytes(OUT_CIPHERTEXT_LEN, "out ciphertext")?.to_vec(),
+                    zkproof: r.bytes(GROTH_PROOF_LEN, "output proof")?.to_vec(),
+                })
+            })?;
+            bundle = Some((value_balance, spends, outputs));
+        }
+        if self.version >= 2 {
+            let proof_len = match sapling {
+                true => GROTH_PROOF_LEN,
+                false => PHGR_PROOF_LEN,
+            };
+            let min_len = 8 + 8 + 32 * 9 + proof_len + 2 * SPROUT_CIPHERTEXT_LEN;
+            let joinsplits = r.list(min_len, "joinsplits", |r| read_joinsplit(r, proof_len))?;
+            if !joinsplits.is_empty() {
+                self.sprout = Some(SproutBundle {
+                    joinsplits,
+                    pubkey: r.uint256("joinsplit pubkey")?,
+                    sig: r.array("joinsplit sig")?,
+                });
+            }
+        }
+        if let Some((value_balance, spends, outputs)) = bundle
+            && !(spends.is_empty() && outputs.is_empty())
+        {
+            self.sapling = Some(SaplingBundle {
+                value_balance,
+                spends,
+                outputs,
+                binding_sig: r.array("binding sig")?,
+            });
+        }
+        Ok(())
+    }
+
+    fn read_v5(&mut self, r: &mut Reader<'_>) -> DecodeResult<()> {
+        self.consensus_branch_id = Some(r.u32("consensus branch id")?);
+        self.lock_time = r.u32("lock time")?;
+        self.expiry_height = Some(r.u32("expiry height")?);
+        self.vin = read_vin(r)?;
+        self.vout = read_vout(r)?;
+
+        let spends = r.list(96, "sapling spends", |r| {
+            Ok((r.uint256("cv")?, r.uint256("nullifier")?, r.uint256("rk")?))
+        })?;
+        let outputs = r.list(756, "sapling outputs", |r| {
+            Ok(OutputDescription {
+                cv: r.uint256("cv")?,
+                cmu: r.uint256("cmu")?,
+                ephemeral_key: r.uint256("ephemeral key")?,
+                enc_ciphertext: r.bytes(ENC_CIPHERTEXT_LEN, "enc ciphertext")?.to_vec(),
+                out_ciphertext: r.bytes(OUT_CIPHERTEXT_LEN, "out ciphertext")?.to_vec(),
+                zkproof: Vec::new(),
+            })
+        })?;
+        if !(spends.is_empty() && outputs.is_empty()) {
+            let value_balance = r.i64("sapling value balance")?;
+            let anchor = match spends.is_empty() {
+                true => [0; 32],
+                false => r.uint256("sapling anchor")?,
+            };
+            let mut proofs = Vec::with_capacity(spends.len());
+            for _ in &spends {
+                proofs.push(r.bytes(GROTH_PROOF_LEN, "spend proof")?.to_vec());
+            }
+            let mut spend_descriptions = Vec::with_capacity(spends.len());
+            for ((cv, nullifier, rk), zkproof) in spends.into_iter().zip(proofs) {
+                spend_descriptions.push(SpendDescription {
+                    cv,
+                    anchor,
+                    nullifier,
+                    rk,
+                    zkproof,
+                    spend_auth_sig: r.array("spend auth sig")?,
+                });
+            }
+            let mut outputs = outputs;
+            for output in &mut outputs {
+                output.zkproof = r.bytes(GROTH_PROOF_LEN, "output proof")?.to_vec();
+            }
+            self.sapling = Some(SaplingBundle {
+                value_balance,
+                spends: spend_descriptions,
+                outputs,
+                binding_sig: r.array("sapling binding sig")?,
+            });
+        }
+
+        let mut actions = r.list(820, "orchard actions", |r| {
+            Ok(OrchardAction {
+                cv: r.uint256("cv")?,
+                nullifier: r.uint256("nullifier")?,
+                rk: r.uint256("rk")?,
+                cmx: r.uint256("cmx")?,
+                ephemeral_key: r.uint256("ephemeral key")?,
+                enc_ciphertext: r.bytes(ENC_CIPHERTEXT_LEN, "enc ciphertext")?.to_vec(),
+                out_ciphertext: r.bytes(OUT_CIPHERTEXT_LEN, "out ciphertext")?.to_vec(),
+                spend_auth_sig: [0; 64],
+            })
+        })?;
+        if !actions.is_empty() {
+            let flags = r.u8("orchard flags")?;
+            let value_balance = r.i64("orchard value balance")?;
+            let anchor = r.uint256("orchard anchor")?;
+            let proof = r.var_bytes("orchard proof")?.to_vec();
+            for action in &mut actions {
+                action.spend_auth_sig = r.array("spend auth sig")?;
+            }
+            self.orchard = Some(OrchardBundle {
+                actions,
+                flags,
+                value_balance,
+                anchor,
+                proof,
+                binding_sig: r.array("orchard binding sig")?,
+            });
+        }
+        Ok(())
+    }
+
+    /// Serialize the transaction the way zcashd does, appending to `out`.
+    pub fn write(&self, out: &mut Vec<u8>) {
+        let header = self.version | (u32::from(self.overwintered) << 31);
+        out.extend_from_slice(&header.to_le_bytes());
+        if let Some(group) = self.version_group_id {
+            out.extend_from_slice(&group.to_le_bytes());
+        }
+        match self.version {
+            ZIP225_TX_VERSION if self.overwintered => self.write_v5(out),
+            _ => self.write_v1_to_v4(out),
+        }
+    }
+
+    /// The serialized transaction.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        self.write(&mut out);
+        out
+    }
+
+    /// The txid, in serialized order: [`legacy_txid`] of the serialized transaction up
+    /// to v4, the ZIP 244 digest from v5 on.
+    pub fn txid(&self) -> [u8; 32] {
+        match self.version {
+            ZIP225_TX_VERSION if self.overwintered => zip244::txid(self),
+            _ => legacy_txid(&self.to_bytes()),
+        }
+    }
+
+    fn write_v1_to_v4(&self, out: &mut Vec<u8>) {
+        write_vin(out, &self.vin);
+        write_vout(out, &self.vout);
+        out.extend_from_slice(&self.lock_time.to_le_bytes());
+        if let Some(expiry_height) = self.expiry_height {
+            out.extend_from_slice(&expiry_height.to_le_bytes());
+        }
+        let sapling = self.version >= SAPLING_TX_VERSION;
+        if sapling {
+            let (value_balance, spends, outputs) = match &self.sapling {
+                Some(bundle) => (
+                    bundle.value_balance,
+                    &bundle.spends[..],
+                    &bundle.outputs[..],
+                ),
+                None => (0, &[][..], &[][..]),
+            };
+            out.extend_from_slice(&value_balance.to_le_bytes());
+            write_compact_size(out, spends.len() as u64);
+            for spend in spends {
+                out.extend_from_slice(&spend.cv);
+                out.extend_from_slice(&spend.anchor);
+                out.extend_from_slice(&spend.nullifier);
+                out.extend_from_slice(&spend.rk);
+                out.extend_from_slice(&spend.zkproof);
+                out.extend_from_slice(&spend.spend_auth_sig);
+            }
+            write_compact_size(out, outputs.len() as u64);
+            for output in outputs {
+                write_output_description(out, output);
+                out.extend_from_slice(&output.zkproof);
+            }
+        }
+        if self.version >= 2 {
+            let joinsplits = self.sprout.as_ref().map_or(&[][..], |b| &b.joinsplits[..]);
+            write_compact_size(out, joinsplits.len() as u64);
+            for js in joinsplits {
+                write_joinsplit(out, js);
+            }
+            if let Some(bundle) = &self.sprout {
+                out.extend_from_slice(&bundle.pubkey);
+                out.extend_from_slice(&bundle.sig);
+            }
+        }
+        if sapling && let Some(bundle) = &self.sapling {
+            out.extend_from_slice(&bundle.binding_sig);
+        }
+    }
+
+    fn write_v5(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.consensus_branch_id.unwrap_or(0).to_le_bytes());
+        out.extend_from_slice(&self.lock_time.to_le_bytes());
+        out.extend_from_slice(&self.expiry_height.unwrap_or(0).to_le_bytes());
+        write_vin(out, &self.vin);
+        write_vout(out, &self.vout);
+
+        let (spends, outputs) = match &self.sapling {
+            Some(bundle) => (&bundle.spends[..], &bundle.outputs[..]),
+            None => (&[][..], &[][..]),
+        };
+        write_compact_size(out, spends.len() as u64);
+        for spend in spends {
+            out.extend_from_slice(&spend.cv);
+            out.extend_from_slice(&spend.nullifier);
+            out.extend_from_slice(&spend.rk);
+        }
+        write_compact_size(out, outputs.len() as u64);
+        for output in outputs {
+            write_output_description(out, output);
+        }
+        if let Some(bundle) = &self.sapling {
+            out.extend_from_slice(&bundle.value_balance.to_le_bytes());
+            if let Some(spend) = spends.first() {
+                out.extend_from_slice(&spend.anchor);
+            }
+            for spend in spends {
+                out.extend_from_slice(&spend.zkproof);
+            }
+            for spend in spends {
+                out.extend_from_slice(&spend.spend_auth_sig);
+            }
+            for output in outputs {
+                out.extend_from_slice(&output.zkproof);
+            }
+            out.extend_from_slice(&bundle.binding_sig);
+        }
+
+        let actions = self.orchard.as_ref().map_or(&[][..], |b| &b.actions[..]);
+        write_compact_size(out, actions.len() as u64);
+        for action in actions {
+            out.extend_from_slice(&action.cv);
+            out.extend_from_slice(&action.nullifier);
+            out.extend_from_slice(&action.rk);
+            out.extend_from_slice(&action.cmx);
+            out.extend_from_slice(&action.ephemeral_key);
+            out.extend_from_slice(&action.enc_ciphertext);
+            out.extend_from_slice(&action.out_ciphertext);
+        }
+        if let Some(bundle) = &self.orchard {
+            out.push(bundle.flags);
+            out.extend_from_slice(&bundle.value_balance.to_le_bytes());
+            out.extend_from_slice(&bundle.anchor);
+            write_compact_size(out, bundle.proof.len() as u64);
+            out.extend_from_slice(&bundle.proof);
+            for action in actions {
+                out.extend_from_slice(&action.spend_auth_sig);
+            }
+            out.extend_from_slice(&bundle.binding_sig);
+        }
+    }
+
+    /// Whether this is a coinbase transaction: one input, spending the null outpoint.
+    pub fn is_coinbase(&self) -> bool {
+        matches!(&self.vin[..], [input] if input.prevout.is_null())
+    }
+
+    /// The height of the block a coinbase transaction was mined in, which BIP 34 has it
+    /// push first in its input script: a small-integer opcode or a minimal little-endian
+    /// number. `None` for other transactions.
+    pub fn coinbase_height(&self) -> Option<u32> {
+        if !self.is_coinbase() {
+            return None;
+        }
+        match self.vin[0].script_sig.as_slice() {
+            [0x00, ..] => Some(0),
+            [op @ 0x51..=0x60, ..] => Some(u32::from(op - 0x50)),
+            [len @ 1..=4, rest @ ..] => {
+                let digits = rest.get(..usize::from(*len))?;
+                if digits.last()? & 0x80 != 0 {
+                    return None;
+                }
+                Some(digits.iter().rev().fold(0, |n, &b| n << 8 | u32::from(b)))
+            }
+            _ => None,
+        }
+    }
+
+    /// The sum of the transparent outputs, in zatoshis.
+    pub fn transparent_value_out(&self) -> i64 {
+        self.vout.iter().map(|out| out.value).sum()
+    }
+
+    /// Whether the transaction touches a shielded pool.
+    pub fn is_shielded(&self) -> bool {
+        self.sprout.is_some() || self.sapling.is_some() || self.orchard.is_some()
+    }
+}
+
+/// The txid of a v1 to v4 transaction serialized as `bytes`: its double SHA-256, in
+/// serialized order. v5 txids are ZIP 244 digests; see [`Transaction::txid`].
+pub fn legacy_txid(bytes: &[u8]) -> [u8; 32] {
+    sha256d(bytes)
+}
+
+fn read_vin(r: &mut Reader<'_>) -> DecodeResult<Vec<TxIn>> {
+    r.list(41, "inputs", |r| {
+        Ok(TxIn {
+            prevout: OutPoint {
+                txid: r.uint256("prevout txid")?,
+                n: r.u32("prevout index")?,
+            },
+            script_sig: r.var_bytes("script sig")?.to_vec(),
+            sequence: r.u32("sequence")?,
+        })
+    })
+}
+
+fn read_vout(r: &mut Reader<'_>) -> DecodeResult<Vec<TxOut>> {
+    r.list(9, "outputs", |r| {
+        Ok(TxOut {
+            value: r.i64("value")?,
+            script_pubkey: r.var_bytes("script pubkey")?.to_vec(),
+        })
+    })
+}
+
+fn write_vin(out: &mut Vec<u8>, vin: &[TxIn]) {
+    write_compact_size(out, vin.len() as u64);
+    for input in vin {
+        out.extend_from_slice(&input.prevout.txid);
+        out.extend_from_slice(&input.prevout.n.to_le_bytes());
+        write_compact_size(out, input.script_sig.len() as u64);
+        out.extend_from_slice(&input.script_sig);
+        out.extend_from_slice(&input.sequence.to_le_bytes());
+    }
+}
+
+fn write_vout(out: &mut Vec<u8>, vout: &[TxOut]) {
+    write_compact_size(out, vout.len() as u64);
+    for output in vout {
+        out.extend_from_slice(&output.value.to_le_bytes());
+        write_compact_size(out, output.script_pubkey.len() as u64);
+        out.extend_from_slice(&output.script_pubkey);
+    }
+}
+
+/// An output description without its proof, which v5 moves after the descriptions.
+fn write_output_description(out: &mut Vec<u8>, output: &OutputDescription) {
+    out.extend_from_slice(&output.cv);
+    out.extend_from_slice(&output.cmu);
+    out.extend_from_slice(&output.ephemeral_key);
+    out.extend_from_slice(&output.enc_ciphertext);
+    out.extend_from_slice(&output.out_ciphertext);
+}
+
+fn write_joinsplit(out: &mut Vec<u8>, js: &JsDescription) {
+    out.extend_from_slice(&js.vpub_old.to_le_bytes());
+    out.extend_from_slice(&js.vpub_new.to_le_bytes());
+    out.extend_from_slice(&js.anchor);
+    for hash in [js.nullifiers, js.commitments].iter().flatten() {
+        out.extend_from_slice(hash);
+    }
+    out.extend_from_slice(&js.ephemeral_key);
+    out.extend_from_slice(&js.random_seed);
+    for mac in &js.macs {
+        out.extend_from_slice(mac);
+    }
+    out.extend_from_slice(&js.proof);
+    for ciphertext in &js.ciphertexts {
+        out.extend_from_slice(ciphertext);
+    }
+}
+
+fn read_joinsplit(r: &mut Reader<'_>, proof_len: usize) -> DecodeResult<JsDescription> {
+    Ok(JsDescription {
+        vpub_old: r.i64("vpub_old")?,
+        vpub_new: r.i64("vpub_new")?,
+        anchor: r.uint256("anchor")?,
+        nullifiers: [r.uint256("nullifier")?, r.uint256("nullifier")?],
+        commitments: [r.uint256("commitment")?, r.uint256("commitment")?],
+        ephemeral_key: r.uint256("ephemeral key")?,
+        random_seed: r.uint256("random seed")?,
+        macs: [r.uint256("mac")?, r.uint256("mac")?],
+        proof: r.bytes(proof_len, "joinsplit proof")?.to_vec(),
+        ciphertexts: [
+            r.bytes(SPROUT_CIPHERTEXT_LEN, "ciphertext")?.to_vec(),
+            r.bytes(SPROUT_CIPHERTEXT_LEN, "ciphertext")?.to_vec(),
+        ],
+    })
+}
@@ -0,0 +1,157 @@
+//! ZIP 244 transaction ids: a tree of personalized BLAKE2b-256 digests, one branch per
+//! part of a v5 transaction, so that the txid commits to the effects of the transaction
+//! and not to its proofs and signatures.
+
+use crate::{
+    crypto::blake2b::Blake2b,
+    entry::parser::write_compact_size,
+    parser::tx::{OrchardBundle, SaplingBundle, Transaction},
+};
+
+/// The part of a note ciphertext a compact block carries.
+const COMPACT_NOTE_LEN: usize = 52;
+/// The end of the memo within a note ciphertext.
+const MEMO_END: usize = COMPACT_NOTE_LEN + 512;
+
+/// A note ciphertext's compact part, memo and the rest. Ciphertexts the reader produced
+/// are always full length; shorter ones split as far as they go.
+fn split_note(enc: &[u8]) -> (&[u8], &[u8], &[u8]) {
+    let (compact, rest) = enc.split_at(enc.len().min(COMPACT_NOTE_LEN));
+    let (memo, rest) = rest.split_at(rest.len().min(MEMO_END - COMPACT_NOTE_LEN));
+    (compact, memo, rest)
+}
+
+fn hasher(personal: &[u8; 16]) -> Blake2b {
+    Blake2b::new(32, personal)
+}
+
+fn digest(state: Blake2b) -> [u8; 32] {
+    state
+        .finalize()
+        .try_into()
+        .expect("BLAKE2b-256 yields 32 bytes")
+}
+
+/// The txid of a v5 transaction.
+pub(super) fn txid(tx: &Transaction) -> [u8; 32] {
+    let branch_id = tx.consensus_branch_id.unwrap_or(0);
+    let mut personal = *b"ZcashTxHash_\0\0\0\0";
+    personal[12..].copy_from_slice(&branch_id.to_le_bytes());
+    let mut state = hasher(&personal);
+    state.update(&header_digest(tx));
+    state.update(&transparent_digest(tx));
+    state.update(&sapling_digest(tx.sapling.as_ref()));
+    state.update(&orchard_digest(tx.orchard.as_ref()));
+    digest(state)
+}
+
+fn header_digest(tx: &Transaction) -> [u8; 32] {
+    let mut state = hasher(b"ZTxIdHeadersHash");
+    let header = tx.version | (u32::from(tx.overwintered) << 31);
+    state.update(&header.to_le_bytes());
+    state.update(&tx.version_group_id.unwrap_or(0).to_le_bytes());
+    state.update(&tx.consensus_branch_id.unwrap_or(0).to_le_bytes());
+    state.update(&tx.lock_time.to_le_bytes());
+    state.update(&tx.expiry_height.unwrap_or(0).to_le_bytes());
+    digest(state)
+}
+
+fn transparent_digest(tx: &Transaction) -> [u8; 32] {
+    let mut state = hasher(b"ZTxIdTranspaHash");
+    if tx.vin.is_empty() && tx.vout.is_empty() {
+        return digest(state);
+    }
+    let mut prevouts = hasher(b"ZTxIdPrevoutHash");
+    let mut sequences = hasher(b"ZTxIdSequencHash");
+    for input in &tx.vin {
+        prevouts.update(&input.prevout.txid);
+        prevouts.update(&input.prevout.n.to_le_bytes());
+        sequences.update(&input.sequence.to_le_bytes());
+    }
+    let mut outputs = hasher(b"ZTxIdOutputsHash");
+    for output in &tx.vout {
+        let mut bytes = output.value.to_le_bytes().to_vec();
+        write_compact_size(&mut bytes, output.script_pubkey.len() as u64);
+        bytes.extend_from_slice(&output.script_pubkey);
+        outputs.update(&bytes);
+    }
+    state.update(&digest(prevouts));
+    state.update(&digest(sequences));
+    state.update(&digest(outputs));
+    digest(state)
+}
+
+fn sapling_digest(bundle: Option<&SaplingBundle>) -> [u8; 32] {
+    let mut state = hasher(b"ZTxIdSaplingHash");
+    let Some(bundle) = bundle else {
+        return digest(state);
+    };
+
+    let mut spends = hasher(b"ZTxIdSSpendsHash");
+    if !bundle.spends.is_empty() {
+        let mut compact = hasher(b"ZTxIdSSpendCHash");
+        let mut noncompact = hasher(b"ZTxIdSSpendNHash");
+        for spend in &bundle.spends {
+            compact.update(&spend.nullifier);
+            noncompact.update(&spend.cv);
+            noncompact.update(&spend.anchor);
+            noncompact.update(&spend.rk);
+        }
+        spends.update(&digest(compact));
+        spends.update(&digest(noncompact));
+    }
+
+    let mut outputs = hasher(b"ZTxIdSOutputHash");
+    if !bundle.outputs.is_empty() {
+        let mut compact = hasher(b"ZTxIdSOutC__Hash");
+        let mut memos = hasher(b"ZTxIdSOutM__Hash");
+        let mut noncompact = hasher(b"ZTxIdSOutN__Hash");
+        for output in &bundle.outputs {
+            let (enc_compact, memo, enc_rest) = split_note(&output.enc_ciphertext);
+            compact.update(&output.cmu);
+            compact.update(&output.ephemeral_key);
+            compact.update(enc_compact);
+            memos.update(memo);
+            noncompact.update(&output.cv);
+            noncompact.update(enc_rest);
+            noncompact.update(&output.out_ciphertext);
+        }
+        outputs.update(&digest(compact));
+        outputs.update(&digest(memos));
+        outputs.update(&digest(noncompact));
+    }
+
+    state.update(&digest(spends));
+    state.update(&digest(outputs));
+    state.update(&bundle.value_balance.to_le_bytes());
+    digest(state)
+}
+
+fn orchard_digest(bundle: Option<&OrchardBundle>) -> [u8; 32] {
+    let mut state = hasher(b"ZTxIdOrchardHash");
+    let Some(bundle) = bundle else {
+        return digest(state);
+    };
+    let mut compact = hasher(b"ZTxIdOrcActCHash");
+    let mut memos = hasher(b"ZTxIdOrcActMHash");
+    let mut noncompact = hasher(b"ZTxIdOrcActNHash");
+    for action in &bundle.actions {
+        let (enc_compact, memo, enc_rest) = split_note(&action.enc_ciphertext);
+        compact.update(&action.nullifier);
+        compact.update(&action.cmx);
+        compact.update(&action.ephemeral_key);
+        compact.update(enc_compact);
+        memos.update(memo);
+        noncompact.update(&action.cv);
+        noncompact.update(&action.rk);
+        noncompact.update(enc_rest);
+        noncompact.update(&action.out_ciphertext);
+    }
+    state.update(&digest(compact));
+    state.update(&digest(memos));
+    state.update(&digest(noncompact));
+    state.update(&[bundle.flags]);
+    state.update(&bundle.value_balance.to_le_bytes());
+    state.update(&bundle.anchor);
+    digest(state)
+}
@@ -0,0 +1,261 @@
+//! Cryptographic verification of the wallet's private keys: that each one derives the
+//! public part its record is keyed by, or stored beside it.
+//!
+//! Key bytes damaged in a way that still decodes are the worst kind of corruption: the
+//! wallet opens, lists its addresses and shows its balance, and the funds cannot be
+//! spent. A transparent key must derive its public key on secp256k1, a Sprout `a_sk`
+//! its `a_pk`, and a Sapling extended spending key the full viewing key, and so the
+//! incoming viewing key, its record names.
+
+use std::{collections::BTreeMap, fmt};
+
+use crate::{
+    crypto::secp256k1::public_key,
+    parser::{
+        crypter::DecryptedSecrets, decoders::sprout::SproutPaymentAddress, record::RecordKind,
+        serialize::uint256_hex, wallet::ZcashdWallet,
+    },
+};
+
+/// A private key that does not belong to the public part the wallet keeps of it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KeyIssue {
+    /// The secret is not a secp256k1 private key: zero, or not below the group order.
+    InvalidSecret { kind: RecordKind, pubkey: Vec<u8> },
+    /// The secret derives another public key than the record is keyed by.
+    PubKeyMismatch {
+        kind: RecordKind,
+        recorded: Vec<u8>,
+        derived: Vec<u8>,
+    },
+    /// The `a_sk` derives another `a_pk` than the address the record is keyed by.
+    SproutAddressMismatch {
+        kind: RecordKind,
+        address: SproutPaymentAddress,
+        derived: [u8; 32],
+    },
+    /// The spending key, or the full viewing key stored beside it, derives another
+    /// incoming viewing key than the record is keyed by.
+    SaplingIvkMismatch {
+        kind: RecordKind,
+        recorded: [u8; 32],
+        derived: [u8; 32],
+    },
+    /// A decrypted `csapzkey` spending key derives another full viewing key than the one
+    /// stored in the clear beside it.
+    SaplingFvkMismatch { ivk: [u8; 32] },
+}
+
+impl fmt::Display for KeyIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KeyIssue::InvalidSecret { kind, pubkey } => write!(
+                f,
+                "{kind} {}: secret is not a secp256k1 private key",
+                hex::encode(pubkey)
+            ),
+            KeyIssue::PubKeyMismatch {
+                kind,
+                recorded,
+                derived,
+            } => write!(
+                f,
+                "{kind} {}: secret derives public key {}",
+                hex::encode(recorded),
+                hex::encode(derived)
+            ),
+            KeyIssue::SproutAddressMismatch {
+                kind,
+                address,
+                derived,
+            } => write!(
+                f,
+                "{kind} {}: a_sk derives a_pk {}",
+                address.a_pk_hex(),
+                uint256_hex(derived)
+            ),
+            KeyIssue::SaplingIvkMismatch {
+                kind,
+                recorded,
+                derived,
+            } => write!(
+                f,
+                "{kind} {}: derives ivk {}",
+                uint256_hex(recorded),
+                uint256_hex(derived)
+            ),
+            KeyIssue::SaplingFvkMismatch { ivk } => write!(
+                f,
+                "csapzkey {}: spending key does not derive the stored full viewing key",
+                uint256_hex(ivk)
+            ),
+        }
+    }
+}
+
+/// How the keys of one record kind fared.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Tally {
+    /// Keys that derive what their record says.
+    pub verified: usize,
+    /// Keys that do not.
+    pub mismatched: usize,
+    /// Encrypted keys without a decrypted secret to check.
+    pub locked: usize,
+}
+
+/// The outcome of [`KeyVerification::of`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct KeyVerification {
+    /// Per record kind, for the kinds the wallet has keys of.
+    pub tallies: BTreeMap<RecordKind, Tally>,
+    pub issues: Vec<KeyIssue>,
+}
+
+impl KeyVerification {
+    /// Verify every private key of `wallet`. Encrypted keys are checked when `secrets`
+    /// holds them, e.g. from [`MasterSecret::decrypt_wallet`]; otherwise they count as
+    /// locked, and only the public parts a `csapzkey` keeps in the clear are checked.
+    ///
+    /// [`MasterSecret::decrypt_wallet`]: crate::parser::crypter::MasterSecret::decrypt_wallet
+    pub fn of(wallet: &ZcashdWallet, secrets: Option<&DecryptedSecrets>) -> Self {
+        let keys = &wallet.keys;
+        let mut report = KeyVerification::default();
+
+        let transparent = keys.transparent.iter().map(|(pubkey, key)| {
+            (
+                RecordKind::Key,
+                pubkey,
+                Some(key.private_key.secret.expose()),
+            )
+        });
+        let wallet_keys = keys.wallet_keys.iter().map(|(pubkey, key)| {
+            (
+                RecordKind::WKey,
+                pubkey,
+                Some(key.private_key.secret.expose()),
+            )
+        });
+        let encrypted = keys.encrypted_transparent.keys().map(|pubkey| {
+            let secret = secrets.and_then(|s| s.transparent.get(pubkey));
+            (RecordKind::CKey, pubkey, secret.map(|s| s.expose()))
+        });
+        for (kind, pubkey, secret) in transparent.chain(wallet_keys).chain(encrypted) {
+            let Some(secret) = secret else {
+                report.tally(kind).locked += 1;
+                continue;
+            };
+            let issue = match public_key(secret, pubkey.len() == 33) {
+                None => Some(KeyIssue::InvalidSecret {
+                    kind,
+                    pubkey: pubkey.clone(),
+                }),
+                Some(derived) if derived != *pubkey => Some(KeyIssue::PubKeyMismatch {
+                    kind,
+                    recorded: pubkey.clone(),
+                    derived,
+                }),
+                Some(_) => None,
+            };
+            report.record(kind, issue);
+        }
+
+        let sprout = keys
+            .sprout
+            .iter()
+            .map(|(address, key)| (RecordKind::ZKey, address, Some(key)));
+        let encrypted = keys.encrypted_sprout.keys().map(|address| {
+            let key = secrets.and_then(|s| s.sprout.get(address));
+            (RecordKind::CZKey, address, key)
+        });
+        for (kind, address, key) in sprout.chain(encrypted) {
+            let Some(key) = key else {
+                report.tally(kind).locked += 1;
+                continue;
+            };
+            let derived = key.derive_a_pk();
+            let issue = (derived != address.a_pk).then_some(KeyIssue::SproutAddressMismatch {
+                kind,
+                address: *address,
+                derived,
+            });
+            report.record(kind, issue);
+        }
+
+        for (ivk, key) in &keys.sapling {
+            let derived = key.key.to_extfvk().ivk();
+            let issue = (derived != *ivk).then_some(KeyIssue::SaplingIvkMismatch {
+                kind: RecordKind::SapZKey,
+                recorded: *ivk,
+                derived,
+            });
+            report.record(RecordKind::SapZKey, issue);
+        }
+        for (ivk, csapzkey) in &keys.encrypted_sapling {
+            let derived = csapzkey.extfvk.ivk();
+            if derived != *ivk {
+                report.record(
+                    RecordKind::CSapZKey,
+                    Some(KeyIssue::SaplingIvkMismatch {
+                        kind: RecordKind::CSapZKey,
+                        recorded: *ivk,
+                        derived,
+                    }),
+                );
+                continue;
+            }
+            match secrets.and_then(|s| s.sapling.get(ivk)) {
+                Some(key) => {
+                    let issue = (key.key.to_extfvk() != csapzkey.extfvk)
+                        .then_some(KeyIssue::SaplingFvkMismatch { ivk: *ivk });
+                    report.record(RecordKind::CSapZKey, issue);
+                }
+                None => report.tally(RecordKind::CSapZKey).locked += 1,
+            }
+        }
+        report
+    }
+
+    fn tally(&mut self, kind: RecordKind) -> &mut Tally {
+        self.tallies.entry(kind).or_default()
+    }
+
+    fn record(&mut self, kind: RecordKind, issue: Option<KeyIssue>) {
+        match issue {
+            Some(issue) => {
+                self.tally(kind).mismatched += 1;
+                self.issues.push(issue);
+            }
+            None => self.tally(kind).verified += 1,
+        }
+    }
+
+    /// Every key that could be checked derives what its record says.
+    pub fn is_clean(&self) -> bool {
+        self.issues.is_empty()
+    }
+
+    pub fn verified(&self) -> usize {
+        self.tallies.values().map(|tally| tally.verified).sum()
+    }
+}
+
+impl fmt::Display for KeyVerification {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "KeyVerification {{")?;
+        for (kind, tally) in &self.tallies {
+            write!(f, "  {:<13}: {} verified", kind.to_string(), tally.verified)?;
+            if tally.mismatched > 0 {
+                write!(f, ", {} mismatched", tally.mismatched)?;
+            }
+            if tally.locked > 0 {
+                write!(f, ", {} locked", tally.locked)?;
+            }
+            writeln!(f)?;
+        }
+        for issue in &self.issues {
+            writeln!(f, "  mismatch     : {issue}")?;
+        }
+        write!(f, "}}")
+    }
+}
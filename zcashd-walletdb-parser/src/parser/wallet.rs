@@ -0,0 +1,590 @@
+//! [`ZcashdWallet`]: every decoded record of a wallet.dat, organized by what it is about.
+//!
+//! [`ZcashdWallet::from_records`] runs raw `(key, value)` pairs through a
+//! [`DecoderRegistry`] and files each decoded value: keys by pool, the address book,
+//! transactions, the wallet's metadata and its encryption and seed state. Records that
+//! fail to decode are kept as [`RecordError`]s rather than ending the read, so one
+//! damaged record does not hide the rest of the wallet. Records with a tag the model does
+//! not know are kept as read, each with a [`DecodeWarning`] naming it.
+
+use std::{
+    any::{Any, type_name},
+    collections::BTreeMap,
+    fmt,
+};
+
+use crate::parser::{
+    address::TransparentAddress,
+    decoders::{
+        accounting::{Account, AccountingEntry},
+        address_book::AddressBook,
+        encryption::MasterKey,
+        hd_chain::MnemonicHdChain,
+        keymeta::{KeyMetadata, TransparentKeyMetadata},
+        keypool::KeyPoolEntry,
+        locator::BlockLocator,
+        network::{Network, ZcashNetwork},
+        orchard::OrchardNoteCommitmentTree,
+        sapling::{
+            EncryptedSaplingKey, SaplingAddressIvk, SaplingExtendedFullViewingKey,
+            SaplingKeyMetadata, SaplingPaymentAddress, SaplingSpendingKey, SaplingWatchOnlyKey,
+        },
+        scalars::{ClientVersion, DefaultKey},
+        script::{RedeemScript, WatchScript},
+        seed::{EncryptedHdSeed, EncryptedMnemonicPhrase, HdSeed, MnemonicPhrase},
+        sprout::{
+            EncryptedSproutKey, SproutKeyMetadata, SproutPaymentAddress, SproutSpendingKey,
+            SproutViewingKey,
+        },
+        transparent::{EncryptedKey, TransparentKey, WalletKey},
+        unified::{UnifiedAccount, UnifiedAddressMetadata, UnifiedFullViewingKey},
+        wallet_tx::WalletTx,
+    },
+    key::RecordKey,
+    keypath::KeyPath,
+    record::{DecodeContext, DecodeError, DecodeResult, RecordKind},
+    registry::{DecodedEntry, DecodedItem, DecodedRecord, DecoderRegistry, UnknownRecord},
+};
+
+/// The wallet's keys, by pool, each map keyed by what zcashd keys its records by.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct KeyStore {
+    /// `key` records, by public key.
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::util::serde_hex::byte_keys")
+    )]
+    pub transparent: BTreeMap<Vec<u8>, TransparentKey>,
+    /// `wkey` records, by public key.
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::util::serde_hex::byte_keys")
+    )]
+    pub wallet_keys: BTreeMap<Vec<u8>, WalletKey>,
+    /// `ckey` records, by public key.
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::util::serde_hex::byte_keys")
+    )]
+    pub encrypted_transparent: BTreeMap<Vec<u8>, EncryptedKey>,
+    /// `keymeta` records, by public key.
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::util::serde_hex::byte_keys")
+    )]
+    pub transparent_metadata: BTreeMap<Vec<u8>, KeyMetadata>,
+    /// `pool` records, by position in the key pool.
+    pub key_pool: BTreeMap<i64, KeyPoolEntry>,
+    /// `watchs` records, by script.
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::util::serde_hex::byte_keys")
+    )]
+    pub watch_scripts: BTreeMap<Vec<u8>, WatchScript>,
+    /// `cscript` records, by script hash.
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::util::serde_hex::byte_keys")
+    )]
+    pub redeem_scripts: BTreeMap<[u8; 20], RedeemScript>,
+    /// `zkey` records.
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::util::serde_hex::values")
+    )]
+    pub sprout: BTreeMap<SproutPaymentAddress, SproutSpendingKey>,
+    /// `czkey` records.
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::util::serde_hex::values")
+    )]
+    pub encrypted_sprout: BTreeMap<SproutPaymentAddress, EncryptedSproutKey>,
+    /// `vkey` records.
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::util::serde_hex::values")
+    )]
+    pub sprout_viewing: BTreeMap<SproutPaymentAddress, SproutViewingKey>,
+    /// `zkeymeta` records.
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::util::serde_hex::entries")
+    )]
+    pub sprout_metadata: BTreeMap<SproutPaymentAddress, KeyMetadata>,
+    /// `sapzkey` records, by incoming viewing key.
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::util::serde_hex::byte_keys")
+    )]
+    pub sapling: BTreeMap<[u8; 32], SaplingSpendingKey>,
+    /// `csapzkey` records, by incoming viewing key.
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::util::serde_hex::byte_keys")
+    )]
+    pub encrypted_sapling: BTreeMap<[u8; 32], EncryptedSaplingKey>,
+    /// `sapzkeymeta` records, by incoming viewing key.
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::util::serde_hex::byte_keys")
+    )]
+    pub sapling_metadata: BTreeMap<[u8; 32], KeyMetadata>,
+    /// `sapextfvk` records.
+    pub sapling_watch_only: Vec<SaplingExtendedFullViewingKey>,
+    /// `sapzaddr` records: each address and the incoming viewing key it derives from.
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::util::serde_hex::entries")
+    )]
+    pub sapling_addresses: BTreeMap<SaplingPaymentAddress, [u8; 32]>,
+    /// `unifiedaccount` records.
+    pub unified_accounts: Vec<UnifiedAccount>,
+    /// `unifiedfvk` records, by full viewing key id.
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::util::serde_hex::byte_keys")
+    )]
+    pub unified_fvks: BTreeMap<[u8; 32], UnifiedFullViewingKey>,
+    /// `unifiedaddrmeta` records.
+    pub unified_addresses: Vec<UnifiedAddressMetadata>,
+}
+
+impl KeyStore {
+    /// The number of transparent key pairs, plain, wrapped or encrypted.
+    pub fn transparent_len(&self) -> usize {
+        self.transparent.len() + self.wallet_keys.len() + self.encrypted_transparent.len()
+    }
+
+    /// The number of Sprout spending keys, plain or encrypted.
+    pub fn sprout_len(&self) -> usize {
+        self.sprout.len() + self.encrypted_sprout.len()
+    }
+
+    /// The number of Sapling spending keys, plain or encrypted.
+    pub fn sapling_len(&self) -> usize {
+        self.sapling.len() + self.encrypted_sapling.len()
+    }
+
+    /// The transparent public keys derived from a seed, by derivation path, so the keys
+    /// of each account and chain sort together and in index order.
+    pub fn transparent_key_paths(&self) -> BTreeMap<KeyPath, &[u8]> {
+        self.transparent_metadata
+            .iter()
+            .filter_map(|(pubkey, meta)| Some((meta.key_path()?, pubkey.as_slice())))
+            .collect()
+    }
+
+    /// The unified full viewing key of `account`, if the wallet holds it.
+    pub fn account_ufvk(&self, account: &UnifiedAccount) -> Option<&UnifiedFullViewingKey> {
+        self.unified_fvks.get(&account.ufvk_id)
+    }
+
+    /// The t-addresses of the wallet's key pairs and scripts, each with the tag of the
+    /// first record it comes from. Watched scripts that pay no address are left out.
+    pub fn transparent_addresses(&self) -> BTreeMap<TransparentAddress, &'static str> {
+        let mut addresses = BTreeMap::new();
+        let mut add = |address, tag| {
+            addresses.entry(address).or_insert(tag);
+        };
+        for pubkey in self.transparent.keys() {
+            add(TransparentAddress::from_pubkey(pubkey), "key");
+        }
+        for pubkey in self.wallet_keys.keys() {
+            add(TransparentAddress::from_pubkey(pubkey), "wkey");
+        }
+        for pubkey in self.encrypted_transparent.keys() {
+            add(TransparentAddress::from_pubkey(pubkey), "ckey");
+        }
+        for script_id in self.redeem_scripts.keys() {
+            add(TransparentAddress::ScriptHash(*script_id), "cscript");
+        }
+        for script in self.watch_scripts.keys() {
+            if let Some(address) = TransparentAddress::of_script(script) {
+                add(address, "watchs");
+            }
+        }
+        addresses
+    }
+}
+
+/// The wallet's encryption and seed state.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct CryptoState {
+    /// `mkey` records, by id.
+    pub master_keys: BTreeMap<u32, MasterKey>,
+    pub hd_seed: Option<HdSeed>,
+    pub encrypted_hd_seed: Option<EncryptedHdSeed>,
+    pub mnemonic: Option<MnemonicPhrase>,
+    pub encrypted_mnemonic: Option<EncryptedMnemonicPhrase>,
+    pub mnemonic_hd_chain: Option<MnemonicHdChain>,
+}
+
+impl CryptoState {
+    /// Whether the wallet is encrypted with a passphrase.
+    pub fn is_encrypted(&self) -> bool {
+        !self.master_keys.is_empty()
+    }
+
+    /// The fingerprint of the seed the wallet derives keys from: its seed phrase's, or
+    /// its legacy `hdseed`'s, plain or encrypted.
+    pub fn seed_fingerprint(&self) -> Option<[u8; 32]> {
+        self.mnemonic
+            .as_ref()
+            .map(|m| m.fingerprint)
+            .or(self.encrypted_mnemonic.as_ref().map(|m| m.fingerprint))
+            .or(self.hd_seed.as_ref().map(|s| s.fingerprint))
+            .or(self.encrypted_hd_seed.as_ref().map(|s| s.fingerprint))
+    }
+}
+
+/// The wallet's singleton records.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct WalletMetadata {
+    /// `version`: the client version that last wrote the wallet.
+    pub version: Option<ClientVersion>,
+    /// `minversion`: the oldest client version able to read it.
+    pub min_version: Option<ClientVersion>,
+    /// `networkinfo`.
+    pub network: Option<Network>,
+    /// The non-empty one of `bestblock_nomerkle` and `bestblock`; zcashd writes the
+    /// latter empty.
+    pub best_block: Option<BlockLocator>,
+    pub order_pos_next: Option<i64>,
+    pub witness_cache_size: Option<i64>,
+    /// `defaultkey`.
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::util::serde_hex::opt_bytes")
+    )]
+    pub default_key: Option<Vec<u8>>,
+}
+
+/// A record whose value failed to decode, or decoded to a type the model does not expect.
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct RecordError {
+    pub kind: RecordKind,
+    pub key: Option<RecordKey>,
+    pub error: DecodeError,
+}
+
+impl fmt::Display for RecordError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.key {
+            Some(key) => write!(f, "{} {key}: {}", self.kind, self.error),
+            None => write!(f, "{}: {}", self.kind, self.error),
+        }
+    }
+}
+
+/// Something the decoder passed over rather than failed on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum DecodeWarning {
+    /// A record with a tag the model does not know, kept in [`ZcashdWallet::unknown`].
+    UnknownRecord {
+        tag: Option<String>,
+        #[cfg_attr(
+            feature = "serde",
+            serde(serialize_with = "crate::util::serde_hex::bytes")
+        )]
+        key_rest: Vec<u8>,
+        value_len: usize,
+    },
+}
+
+impl fmt::Display for DecodeWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecodeWarning::UnknownRecord {
+                tag: Some(tag),
+                key_rest,
+                value_len,
+            } => write!(
+                f,
+                "skipped record with unknown tag {tag:?} (key {}, {value_len}-byte value)",
+                hex::encode(key_rest)
+            ),
+            DecodeWarning::UnknownRecord {
+                tag: None,
+                key_rest,
+                value_len,
+            } => write!(
+                f,
+                "skipped record with no walletdb tag (key {}, {value_len}-byte value)",
+                hex::encode(key_rest)
+            ),
+        }
+    }
+}
+
+/// A decoded zcashd wallet.
+#[derive(Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ZcashdWallet {
+    pub keys: KeyStore,
+    pub address_book: AddressBook,
+    /// `tx` records, by txid.
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::util::serde_hex::byte_keys")
+    )]
+    pub transactions: BTreeMap<[u8; 32], WalletTx>,
+    /// `acc` records, by account name.
+    pub accounts: BTreeMap<String, Account>,
+    /// `acentry` records, in key order.
+    pub accounting_entries: Vec<AccountingEntry>,
+    pub orchard: Option<OrchardNoteCommitmentTree>,
+    pub metadata: WalletMetadata,
+    pub crypto: CryptoState,
+    /// The number of records of each kind the model has no place for, e.g. `pool`.
+    pub undecoded: BTreeMap<RecordKind, usize>,
+    /// Records with a tag the model does not know, as read.
+    pub unknown: Vec<UnknownRecord>,
+    pub errors: Vec<RecordError>,
+    /// One per record passed over, in the order they were read.
+    pub warnings: Vec<DecodeWarning>,
+}
+
+impl ZcashdWallet {
+    /// The Zcash network `networkinfo` names, which the wallet's addresses are encoded for.
+    pub fn network(&self) -> Option<ZcashNetwork> {
+        self.metadata.network.as_ref().and_then(Network::zcash)
+    }
+
+    /// Decode the `(key, value)` records with `registry` and organize them, in the
+    /// [`DecodeContext`] their `version` and `minversion` records describe.
+    pub fn from_records<K, V>(
+        registry: &DecoderRegistry,
+        records: impl IntoIterator<Item = (K, V)>,
+    ) -> Self
+    where
+        K: AsRef<[u8]>,
+        V: AsRef<[u8]>,
+    {
+        // `version` sorts after most of the records it describes, so find it first.
+        let records: Vec<(K, V)> = records.into_iter().collect();
+        let ctx = DecodeContext::scan(records.iter().map(|(key, value)| (key, value)));
+        let mut wallet = ZcashdWallet::default();
+        for (key, value) in &records {
+            wallet.insert_record(registry.decode_record(&ctx, key.as_ref(), value.as_ref()));
+        }
+        wallet
+    }
+
+    /// File one record run through [`DecoderRegistry::decode_record`]. A record with an
+    /// unknown tag is kept as read, with a warning.
+    pub fn insert_record(&mut self, record: DecodedRecord) {
+        match record {
+            DecodedRecord::Known(entry) => self.insert(entry),
+            DecodedRecord::Unknown(record) => {
+                self.warnings.push(DecodeWarning::UnknownRecord {
+                    tag: record.tag.clone(),
+                    key_rest: record.key_rest.clone(),
+                    value_len: record.value.len(),
+                });
+                self.unknown.push(record);
+            }
+        }
+    }
+
+    /// File one decoded record. A later singleton record replaces an earlier one.
+    pub fn insert(&mut self, entry: DecodedEntry) {
+        let DecodedEntry { kind, key, value } = entry;
+        let result = match value {
+            Some(Ok(item)) => self.file(kind, item),
+            Some(Err(error)) => Err(error),
+            None => {
+                *self.undecoded.entry(kind).or_default() += 1;
+                Ok(())
+            }
+        };
+        if let Err(error) = result {
+            self.errors.push(RecordError { kind, key, error });
+        }
+    }
+
+    fn file(&mut self, kind: RecordKind, item: Box<dyn DecodedItem>) -> DecodeResult<()> {
+        let keys = &mut self.keys;
+        let crypto = &mut self.crypto;
+        let metadata = &mut self.metadata;
+        match kind {
+            RecordKind::Name => self.address_book.insert_name(take(kind, item)?),
+            RecordKind::Purpose => self.address_book.insert_purpose(take(kind, item)?),
+            RecordKind::DestData => self.address_book.insert_dest_data(take(kind, item)?),
+            RecordKind::Key => {
+                let key: TransparentKey = take(kind, item)?;
+                keys.transparent.insert(key.pubkey.clone(), key);
+            }
+            RecordKind::WKey => {
+                let key: WalletKey = take(kind, item)?;
+                keys.wallet_keys.insert(key.pubkey.clone(), key);
+            }
+            RecordKind::CKey => {
+                let key: EncryptedKey = take(kind, item)?;
+                keys.encrypted_transparent.insert(key.pubkey.clone(), key);
+            }
+            RecordKind::KeyMeta => {
+                let meta: TransparentKeyMetadata = take(kind, item)?;
+                keys.transparent_metadata.insert(meta.pubkey, meta.metadata);
+            }
+            RecordKind::Pool => {
+                let entry: KeyPoolEntry = take(kind, item)?;
+                keys.key_pool.insert(entry.index, entry);
+            }
+            RecordKind::Watchs => {
+                let script: WatchScript = take(kind, item)?;
+                keys.watch_scripts.insert(script.script.clone(), script);
+            }
+            RecordKind::CScript => {
+                let script: RedeemScript = take(kind, item)?;
+                keys.redeem_scripts.insert(script.script_id, script);
+            }
+            RecordKind::ZKey => {
+                let key: SproutSpendingKey = take(kind, item)?;
+                keys.sprout.insert(key.address, key);
+            }
+            RecordKind::CZKey => {
+                let key: EncryptedSproutKey = take(kind, item)?;
+                keys.encrypted_sprout.insert(key.address, key);
+            }
+            RecordKind::VKey => {
+                let key: SproutViewingKey = take(kind, item)?;
+                keys.sprout_viewing.insert(key.address, key);
+            }
+            RecordKind::ZKeyMeta => {
+                let meta: SproutKeyMetadata = take(kind, item)?;
+                keys.sprout_metadata.insert(meta.address, meta.metadata);
+            }
+            RecordKind::SapZKey => {
+                let key: SaplingSpendingKey = take(kind, item)?;
+                keys.sapling.insert(key.ivk, key);
+            }
+            RecordKind::CSapZKey => {
+                let key: EncryptedSaplingKey = take(kind, item)?;
+                keys.encrypted_sapling.insert(key.ivk, key);
+            }
+            RecordKind::SapZKeyMeta => {
+                let meta: SaplingKeyMetadata = take(kind, item)?;
+                keys.sapling_metadata.insert(meta.ivk, meta.metadata);
+            }
+            RecordKind::SapExtFvk => {
+                let key: SaplingWatchOnlyKey = take(kind, item)?;
+                keys.sapling_watch_only.push(key.extfvk);
+            }
+            RecordKind::SapZAddr => {
+                let address: SaplingAddressIvk = take(kind, item)?;
+                keys.sapling_addresses.insert(address.address, address.ivk);
+            }
+            RecordKind::UnifiedAccount => keys.unified_accounts.push(take(kind, item)?),
+            RecordKind::UnifiedFvk => {
+                let ufvk: UnifiedFullViewingKey = take(kind, item)?;
+                keys.unified_fvks.insert(ufvk.ufvk_id, ufvk);
+            }
+            RecordKind::UnifiedAddrMeta => keys.unified_addresses.push(take(kind, item)?),
+            RecordKind::Tx => {
+                let tx: WalletTx = take(kind, item)?;
+                self.transactions.insert(tx.txid, tx);
+            }
+            RecordKind::Acc => {
+                let account: Account = take(kind, item)?;
+                self.accounts.insert(account.name.clone(), account);
+            }
+            RecordKind::AcEntry => self.accounting_entries.push(take(kind, item)?),
+            RecordKind::OrchardNoteCommitmentTree => self.orchard = Some(take(kind, item)?),
+            RecordKind::MKey => {
+                let key: MasterKey = take(kind, item)?;
+                crypto.master_keys.insert(key.id, key);
+            }
+            RecordKind::HdSeed => crypto.hd_seed = Some(take(kind, item)?),
+            RecordKind::CHdSeed => crypto.encrypted_hd_seed = Some(take(kind, item)?),
+            RecordKind::MnemonicPhrase => crypto.mnemonic = Some(take(kind, item)?),
+            RecordKind::CMnemonicPhrase => crypto.encrypted_mnemonic = Some(take(kind, item)?),
+            RecordKind::MnemonicHdChain => crypto.mnemonic_hd_chain = Some(take(kind, item)?),
+            RecordKind::Version => metadata.version = Some(take(kind, item)?),
+            RecordKind::MinVersion => metadata.min_version = Some(take(kind, item)?),
+            RecordKind::NetworkInfo => metadata.network = Some(take(kind, item)?),
+            RecordKind::BestBlock | RecordKind::BestBlockNoMerkle => {
+                let locator: BlockLocator = take(kind, item)?;
+                if !locator.is_empty() || metadata.best_block.is_none() {
+                    metadata.best_block = Some(locator);
+                }
+            }
+            RecordKind::OrderPosNext => metadata.order_pos_next = Some(take(kind, item)?),
+            RecordKind::WitnessCacheSize => metadata.witness_cache_size = Some(take(kind, item)?),
+            RecordKind::DefaultKey => {
+                let key: DefaultKey = take(kind, item)?;
+                metadata.default_key = Some(key.pubkey);
+            }
+            _ => *self.undecoded.entry(kind).or_default() += 1,
+        }
+        Ok(())
+    }
+}
+
+/// Unbox a decoded value as the type the model files `kind` records as. A registry with
+/// another decoder for `kind` is reported rather than trusted.
+fn take<T: Any>(kind: RecordKind, item: Box<dyn DecodedItem>) -> DecodeResult<T> {
+    item.into_any()
+        .downcast::<T>()
+        .map(|item| *item)
+        .map_err(|_| {
+            DecodeError::new(format!(
+                "{kind}: decoded value is not a {}",
+                type_name::<T>()
+            ))
+        })
+}
+
+impl fmt::Display for ZcashdWallet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (keys, meta) = (&self.keys, &self.metadata);
+        writeln!(f, "ZcashdWallet {{")?;
+        match meta.version {
+            Some(version) => writeln!(f, "  version      : {version}")?,
+            None => writeln!(f, "  version      : unknown")?,
+        }
+        match &meta.network {
+            Some(network) => writeln!(f, "  network      : {network}")?,
+            None => writeln!(f, "  network      : unknown")?,
+        }
+        writeln!(f, "  encrypted    : {}", self.crypto.is_encrypted())?;
+        writeln!(
+            f,
+            "  transparent  : {} keys, {} scripts",
+            keys.transparent_len(),
+            keys.watch_scripts.len() + keys.redeem_scripts.len()
+        )?;
+        writeln!(
+            f,
+            "  sprout       : {} keys, {} viewing keys",
+            keys.sprout_len(),
+            keys.sprout_viewing.len()
+        )?;
+        writeln!(
+            f,
+            "  sapling      : {} keys, {} watch-only, {} addresses",
+            keys.sapling_len(),
+            keys.sapling_watch_only.len(),
+            keys.sapling_addresses.len()
+        )?;
+        writeln!(
+            f,
+            "  unified      : {} accounts, {} addresses",
+            keys.unified_accounts.len(),
+            keys.unified_addresses.len()
+        )?;
+        writeln!(f, "  address book : {}", self.address_book.len())?;
+        writeln!(f, "  transactions : {}", self.transactions.len())?;
+        for error in &self.errors {
+            writeln!(f, "  error        : {error}")?;
+        }
+        for warning in &self.warnings {
+            writeln!(f, "  warning      : {warning}")?;
+        }
+        write!(f, "}}")
+    }
+}
@@ -1,7 +1,71 @@
 //! This module contains the storage API for reading the Berkeley DB storage format.
+//!
+//! Without the `std` feature only the page and slot types the byte-level parsers share
+//! are built.
 
+#[cfg(feature = "async")]
+pub mod async_source;
+#[cfg(feature = "std")]
+pub mod blob;
+#[cfg(feature = "std")]
+pub mod borrowed;
+#[cfg(feature = "std")]
 mod btree;
+#[cfg(feature = "std")]
+pub mod byteswap;
+#[cfg(feature = "std")]
+pub mod cache;
+#[cfg(feature = "std")]
+pub mod carve;
+#[cfg(feature = "std")]
+pub mod checksum;
+pub mod compare;
+#[cfg(feature = "std")]
 pub mod consistency;
+#[cfg(feature = "std")]
+pub mod diff;
+#[cfg(feature = "std")]
+pub mod dump;
+#[cfg(feature = "std")]
+pub mod encryption;
 pub mod entry;
+#[cfg(feature = "std")]
+pub mod environment;
+#[cfg(all(feature = "std", any(test, feature = "test-fixtures")))]
+pub mod fixture;
+pub mod flags;
+#[cfg(feature = "std")]
+pub mod freelist;
+#[cfg(feature = "std")]
+pub mod geometry;
+#[cfg(feature = "std")]
+pub mod lsn;
+#[cfg(feature = "std")]
+pub mod meta_recovery;
+#[cfg(feature = "std")]
+pub mod orphans;
 pub mod page;
+#[cfg(feature = "parallel")]
+pub mod parallel;
+#[cfg(feature = "std")]
+pub mod reader;
+#[cfg(feature = "std")]
+pub mod recno;
+#[cfg(feature = "std")]
+pub mod repack;
+#[cfg(feature = "std")]
+pub mod salvage;
+pub mod slots;
+#[cfg(feature = "std")]
+pub mod source;
+#[cfg(feature = "std")]
+pub mod stats;
+#[cfg(feature = "std")]
+pub mod supplier;
 pub mod types;
+#[cfg(feature = "std")]
+pub mod version;
+#[cfg(feature = "std")]
+pub mod walletdb;
+#[cfg(feature = "std")]
+pub mod writer;
@@ -0,0 +1,276 @@
+//! Async counterparts of [`PageSource`] and [`WalletDb::entries`], so server-side tooling
+//! can parse uploads without blocking worker threads.
+//!
+//! Only page reads are asynchronous; decoding a page is CPU-bound and done inline. Both
+//! are written against `std::future` alone, so any executor (tokio included) can drive
+//! them: implement [`AsyncPageSource`] over the runtime's file or network type, or wrap a
+//! synchronous source in [`Blocking`]. The entries are read with an `async fn next`, or
+//! polled through [`EntriesStream`], which has the shape of a `futures` `Stream` without
+//! the crate depending on `futures`.
+//!
+//! [`WalletDb::entries`]: crate::storage::walletdb::WalletDb::entries
+
+use std::{
+    future::Future,
+    io,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll, ready},
+};
+
+use crate::{
+    entry::constants::OverflowRef,
+    headers::{BtreeMeta, parse_btree_meta_page0},
+    storage::{
+        btree::{Descent, Leaf, Stored},
+        entry::{Confidence, Provenance},
+        page::{EntryDescriptor, PageProtection},
+        reader::{BTM_SUBDB, subdb_meta_pgno},
+        recno::RecnoFormat,
+        supplier::OverflowChain,
+        types::{ByteVec, Endianness, PageNumber, PageSource},
+        version::BtreeVersion,
+    },
+    util::u32e,
+};
+
+/// Low-level source of pages that can be read without blocking.
+pub trait AsyncPageSource: Send + Sync {
+    /// Read a single page by page number. Returns the raw bytes.
+    fn read_page(&self, page_no: PageNumber) -> impl Future<Output = io::Result<ByteVec>> + Send;
+
+    /// Total number of pages, if known.
+    fn page_count(&self) -> Option<u64>;
+
+    /// Get path or source identifier (for provenance / logging).
+    fn source_id(&self) -> String;
+}
+
+/// A synchronous [`PageSource`] served to async callers; every read completes at once.
+/// Fine for in-memory images, while file or network I/O should get a native
+/// implementation.
+#[derive(Debug)]
+pub struct Blocking<S>(pub S);
+
+impl<S: PageSource> AsyncPageSource for Blocking<S> {
+    async fn read_page(&self, page_no: PageNumber) -> io::Result<ByteVec> {
+        self.0.read_page(page_no)
+    }
+
+    fn page_count(&self) -> Option<u64> {
+        self.0.page_count()
+    }
+
+    fn source_id(&self) -> String {
+        self.0.source_id()
+    }
+}
+
+/// A wallet.dat read through an [`AsyncPageSource`].
+pub struct AsyncWalletDb<S> {
+    source: Arc<S>,
+    meta: BtreeMeta,
+    endianness: Endianness,
+    protection: PageProtection,
+}
+
+impl<S: AsyncPageSource> AsyncWalletDb<S> {
+    /// Read and check the meta page. Encrypted databases must be decrypted by the
+    /// source.
+    pub async fn open(source: S) -> io::Result<Self> {
+        let page0 = source.read_page(0).await?;
+        let meta = parse_btree_meta_page0(&page0)?;
+        let (version, _) = BtreeVersion::resolve(meta.version)?;
+        if version.supports_encryption() && meta.is_encrypted() {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "database is encrypted; decrypt it in the page source",
+            ));
+        }
+        let checksummed = version.supports_checksums() && meta.has_checksum();
+        Ok(AsyncWalletDb {
+            source: Arc::new(source),
+            endianness: meta.endian.into(),
+            protection: PageProtection::from_meta(0, checksummed),
+            meta,
+        })
+    }
+
+    pub fn meta(&self) -> &BtreeMeta {
+        &self.meta
+    }
+
+    /// The root of the btree described by the meta page `meta`.
+    fn tree_root(&self, meta: &[u8]) -> io::Result<PageNumber> {
+        if RecnoFormat::from_meta_page(meta, self.endianness).is_some() {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "recno databases cannot be read asynchronously",
+            ));
+        }
+        Ok(u32e(self.endianness.into(), &meta[88..92]))
+    }
+
+    async fn data_roots(&self) -> io::Result<Vec<PageNumber>> {
+        if self.meta.flags & BTM_SUBDB == 0 {
+            let page0 = self.source.read_page(0).await?;
+            return Ok(vec![self.tree_root(&page0)?]);
+        }
+        let mut roots = Vec::new();
+        let mut master = self.walk(vec![self.meta.root]);
+        while let Some(entry) = master.next().await {
+            let (_, value, _) = entry?;
+            let sub_meta = self.source.read_page(subdb_meta_pgno(&value)?).await?;
+            roots.push(self.tree_root(&sub_meta)?);
+        }
+        Ok(roots)
+    }
+
+    fn walk(&self, roots: Vec<PageNumber>) -> AsyncEntries<'_, S> {
+        AsyncEntries {
+            db: self,
+            descent: Descent::new(roots),
+            leaf: None,
+        }
+    }
+
+    /// Stream every live `(key, value)` pair, in key order within each database. The
+    /// subdatabase list is read up front.
+    pub async fn entries(&self) -> io::Result<AsyncEntries<'_, S>> {
+        let roots = self.data_roots().await?;
+        Ok(self.walk(roots))
+    }
+
+    async fn read_overflow(&self, r: OverflowRef) -> io::Result<ByteVec> {
+        let mut chain = OverflowChain::new(
+            r,
+            self.endianness,
+            self.protection,
+            self.source.page_count(),
+        );
+        while let Some(pgno) = chain.next_page()? {
+            chain.push(&self.source.read_page(pgno).await?)?;
+        }
+        Ok(chain.into_bytes())
+    }
+
+    /// Blob files are not read: a [`BlobResolver`] blocks on its reads.
+    ///
+    /// [`BlobResolver`]: crate::storage::blob::BlobResolver
+    async fn item(&self, page: &[u8], pgno: PageNumber, stored: Stored) -> io::Result<ByteVec> {
+        match stored {
+            Stored::Inline((start, end)) => Ok(page[start..end].to_vec()),
+            Stored::Overflow(r) => self.read_overflow(r).await,
+            Stored::Blob(r) => Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                format!("page {pgno}: value is stored outside the image ({r})"),
+            )),
+        }
+    }
+
+    async fn entry(&self, leaf: &Leaf<ByteVec>, d: EntryDescriptor) -> AsyncItem {
+        let (pgno, page) = (leaf.pgno, &leaf.page);
+        // The value first, so a pair that cannot be read fails before its key is.
+        let value = self
+            .item(page, pgno, d.value_stored(page, self.endianness))
+            .await?;
+        let key = self
+            .item(page, pgno, d.key_stored(page, self.endianness))
+            .await?;
+        let provenance = Provenance {
+            source_id: self.source.source_id(),
+            page_no: pgno,
+            slot_index: d.slot_index,
+            confidence: Confidence::High,
+        };
+        Ok((key, value, provenance))
+    }
+}
+
+/// A pair read by [`AsyncEntries`], or the reason part of the tree could not be read.
+pub type AsyncItem = io::Result<(ByteVec, ByteVec, Provenance)>;
+
+/// Stream returned by [`AsyncWalletDb::entries`]. Holds at most one leaf page, like its
+/// synchronous counterpart, and walks the pages in the same order.
+pub struct AsyncEntries<'a, S> {
+    db: &'a AsyncWalletDb<S>,
+    descent: Descent,
+    leaf: Option<Leaf<ByteVec>>,
+}
+
+impl<'a, S: AsyncPageSource> AsyncEntries<'a, S> {
+    /// The next pair and where it was read from, or `None` at the end. Structural errors
+    /// are yielded in place of the entries they hide, and the walk continues with the next
+    /// subtree.
+    pub async fn next(&mut self) -> Option<AsyncItem> {
+        loop {
+            if let Some(leaf) = &mut self.leaf {
+                match leaf.next_live() {
+                    Some(d) => return Some(self.db.entry(leaf, d).await),
+                    None => self.leaf = None,
+                }
+            }
+            let pgno = match self.descent.next_page()? {
+                Ok(pgno) => pgno,
+                Err(e) => return Some(Err(e)),
+            };
+            let page = match self.db.source.read_page(pgno).await {
+                Ok(page) => page,
+                Err(e) => return Some(Err(e)),
+            };
+            match self
+                .descent
+                .enter(&page, self.db.endianness, self.db.protection)
+            {
+                Ok(Some(entries)) => self.leaf = Some(Leaf::new(pgno, page, entries)),
+                Ok(None) => {}
+                Err(e) => return Some(Err(e)),
+            }
+        }
+    }
+
+    /// The same pairs, polled rather than awaited.
+    pub fn into_stream(self) -> EntriesStream<'a, S> {
+        EntriesStream {
+            idle: Some(self),
+            pending: None,
+        }
+    }
+}
+
+type Pending<'a, S> =
+    Pin<Box<dyn Future<Output = (AsyncEntries<'a, S>, Option<AsyncItem>)> + Send + 'a>>;
+
+/// [`AsyncEntries`] as a stream: [`Self::poll_next`] has the signature of
+/// `futures::Stream::poll_next`, so a `Stream` impl (or `futures::stream::poll_fn`) only
+/// has to forward to it. The crate itself does not depend on `futures`.
+pub struct EntriesStream<'a, S> {
+    idle: Option<AsyncEntries<'a, S>>,
+    /// The read in progress, which hands the entries back when it completes.
+    pending: Option<Pending<'a, S>>,
+}
+
+impl<S: AsyncPageSource> EntriesStream<'_, S> {
+    /// The next pair once it has been read, or `Ready(None)` at the end.
+    pub fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<AsyncItem>> {
+        let this = self.get_mut();
+        let pending = match &mut this.pending {
+            Some(pending) => pending,
+            None => {
+                let Some(mut entries) = this.idle.take() else {
+                    return Poll::Ready(None);
+                };
+                this.pending.insert(Box::pin(async move {
+                    let item = entries.next().await;
+                    (entries, item)
+                }))
+            }
+        };
+        let (entries, item) = ready!(pending.as_mut().poll(cx));
+        this.pending = None;
+        if item.is_some() {
+            this.idle = Some(entries);
+        }
+        Poll::Ready(item)
+    }
+}
@@ -0,0 +1,201 @@
+//! Values stored outside the database file (`B_BLOB` items, Berkeley DB 6.x).
+//!
+//! A database with a blob threshold keeps every value at least that large in a file of
+//! its own under the environment's blob directory (`__db_bl` by default). The leaf page
+//! only holds a `BBLOB` item naming the file:
+//!
+//! ```text
+//! len:u16 type:u8 encoding:u8 unused:[u8; 12] id:u64 size:u64 file_id:u64 sdb_id:u64
+//! ```
+//!
+//! Blob files hold the raw value bytes. A [`BlobResolver`] turns a [`BlobRef`] into those
+//! bytes; [`BlobDirectory`] reads them from a blob directory on disk.
+
+use std::{
+    fmt, fs, io,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use crate::{
+    storage::{
+        page::ValueSupplier,
+        types::{ByteSlice, ByteVec, Endianness},
+    },
+    util::u64e,
+};
+
+/// Name of the blob directory Berkeley DB creates in the environment home.
+pub const DEFAULT_BLOB_DIR: &str = "__db_bl";
+
+/// Prefix of the per-database and per-subdatabase directories (`BLOB_DIR_PREFIX`).
+const BLOB_DIR_PREFIX: &str = "__db";
+/// Prefix of blob file names (`BLOB_FILE_PREFIX`).
+const BLOB_FILE_PREFIX: &str = "__db.bl";
+/// Blob files are spread over nested directories of at most this many entries.
+const BLOB_DIR_ELEMS: u64 = 1000;
+
+/// A decoded `BBLOB` item.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BlobRef {
+    /// How the file contents are encoded; Berkeley DB 6.x only writes 0 (plain bytes).
+    pub encoding: u8,
+    /// Number of the blob file within its (sub)database.
+    pub blob_id: u64,
+    /// Length of the value in bytes.
+    pub size: u64,
+    /// Blob directory of the database file.
+    pub file_id: u64,
+    /// Blob directory of the subdatabase, or 0 for a database without subdatabases.
+    pub sdb_id: u64,
+}
+
+impl BlobRef {
+    /// Decode the `BBLOB` item `item`, which must be at least 48 bytes long.
+    pub(crate) fn parse(item: &[u8], e: Endianness) -> Self {
+        BlobRef {
+            encoding: item[3],
+            blob_id: u64e(e.into(), &item[16..24]),
+            size: u64e(e.into(), &item[24..32]),
+            file_id: u64e(e.into(), &item[32..40]),
+            sdb_id: u64e(e.into(), &item[40..48]),
+        }
+    }
+
+    /// Path of the blob file relative to the blob directory, as built by
+    /// `__blob_id_to_path`: `__db<file_id>/[__db<sdb_id>/]` followed by one directory per
+    /// three digits of the id above the lowest three, then `__db.bl<id>`.
+    pub fn relative_path(&self) -> PathBuf {
+        let mut path = PathBuf::from(format!("{BLOB_DIR_PREFIX}{}", self.file_id));
+        if self.sdb_id != 0 {
+            path.push(format!("{BLOB_DIR_PREFIX}{}", self.sdb_id));
+        }
+        let mut depth = 0;
+        let mut factor = 1;
+        while self.blob_id / factor >= BLOB_DIR_ELEMS {
+            depth += 1;
+            factor *= BLOB_DIR_ELEMS;
+        }
+        for _ in 0..depth {
+            path.push(format!("{:03}", (self.blob_id / factor) % BLOB_DIR_ELEMS));
+            factor /= BLOB_DIR_ELEMS;
+        }
+        let width = (depth + 1) * 3;
+        path.push(format!("{BLOB_FILE_PREFIX}{:0width$}", self.blob_id));
+        path
+    }
+}
+
+impl fmt::Display for BlobRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "blob {} ({} bytes) at {}",
+            self.blob_id,
+            self.size,
+            self.relative_path().display()
+        )
+    }
+}
+
+/// Reads the value a [`BlobRef`] points to.
+pub trait BlobResolver: Send + Sync {
+    fn read_blob(&self, reference: &BlobRef) -> io::Result<ByteVec>;
+}
+
+/// Blob files in a directory on disk, normally `<wallet dir>/__db_bl`.
+#[derive(Debug, Clone)]
+pub struct BlobDirectory {
+    root: PathBuf,
+}
+
+impl BlobDirectory {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        BlobDirectory { root: root.into() }
+    }
+
+    /// The default blob directory next to `db_path`, if there is one.
+    pub fn beside(db_path: impl AsRef<Path>) -> Option<Self> {
+        let dir = db_path.as_ref().parent()?.join(DEFAULT_BLOB_DIR);
+        dir.is_dir().then(|| BlobDirectory::new(dir))
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+}
+
+impl BlobResolver for BlobDirectory {
+    fn read_blob(&self, reference: &BlobRef) -> io::Result<ByteVec> {
+        if reference.encoding != 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                format!(
+                    "{reference}: encoding {} is not supported",
+                    reference.encoding
+                ),
+            ));
+        }
+        let path = self.root.join(reference.relative_path());
+        let bytes = fs::read(&path)
+            .map_err(|e| io::Error::new(e.kind(), format!("{}: {e}", path.display())))?;
+        if bytes.len() as u64 != reference.size {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "{}: blob file holds {} bytes, the database expects {}",
+                    path.display(),
+                    bytes.len(),
+                    reference.size
+                ),
+            ));
+        }
+        Ok(bytes)
+    }
+}
+
+/// A value stored in a blob file. Nothing is read until `materialize`, which fails when
+/// the reader was given no [`BlobResolver`].
+#[derive(Clone)]
+pub struct BlobSupplier {
+    resolver: Option<Arc<dyn BlobResolver>>,
+    reference: BlobRef,
+}
+
+impl BlobSupplier {
+    pub fn new(resolver: Option<Arc<dyn BlobResolver>>, reference: BlobRef) -> Self {
+        BlobSupplier {
+            resolver,
+            reference,
+        }
+    }
+
+    pub fn reference(&self) -> BlobRef {
+        self.reference
+    }
+}
+
+impl fmt::Debug for BlobSupplier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BlobSupplier")
+            .field("reference", &self.reference)
+            .field("resolver", &self.resolver.is_some())
+            .finish()
+    }
+}
+
+impl ValueSupplier for BlobSupplier {
+    fn materialize(&self) -> io::Result<ByteVec> {
+        match &self.resolver {
+            Some(resolver) => resolver.read_blob(&self.reference),
+            None => Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("{}: no blob directory given", self.reference),
+            )),
+        }
+    }
+
+    fn try_borrow<'a>(&'a self) -> Option<ByteSlice<'a>> {
+        None
+    }
+}
@@ -0,0 +1,214 @@
+//! Zero-copy access to an image held entirely in memory.
+//!
+//! [`BorrowedImage::entries`] hands out keys and values as [`ByteSlice`]s pointing
+//! straight into the image. Only items stored on overflow chains are copied, since their
+//! pages have to be stitched together; everything else is `Cow::Borrowed`.
+
+use std::{borrow::Cow, fmt, io};
+
+use crate::{
+    headers::parse_btree_meta_page0,
+    storage::{
+        btree::{Descent, Leaf, Stored},
+        page::{EntryDescriptor, PageProtection},
+        reader::{BTM_SUBDB, subdb_meta_pgno},
+        recno::RecnoFormat,
+        supplier::read_overflow_chain,
+        types::{ByteSlice, ByteVec, Endianness, PageNumber, PageSource},
+        version::BtreeVersion,
+    },
+    util::{page_slice, u32e},
+};
+
+/// A live entry borrowed from a [`BorrowedImage`].
+#[derive(Debug, Clone)]
+pub struct BorrowedEntry<'a> {
+    pub key: ByteSlice<'a>,
+    pub value: ByteSlice<'a>,
+    pub page_no: PageNumber,
+    pub slot_index: u16,
+}
+
+/// A btree image borrowed from memory, e.g. a whole file read or mapped by the caller.
+#[derive(Clone, Copy)]
+pub struct BorrowedImage<'a> {
+    bytes: &'a [u8],
+    page_size: usize,
+    endianness: Endianness,
+    protection: PageProtection,
+    root: PageNumber,
+    subdb: bool,
+}
+
+impl fmt::Debug for BorrowedImage<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BorrowedImage")
+            .field("len", &self.bytes.len())
+            .field("page_size", &self.page_size)
+            .field("endianness", &self.endianness)
+            .finish()
+    }
+}
+
+impl<'a> BorrowedImage<'a> {
+    /// Read the geometry from the meta page at the start of `bytes`. Encrypted images
+    /// must be decrypted first.
+    pub fn new(bytes: &'a [u8]) -> io::Result<Self> {
+        let meta = parse_btree_meta_page0(bytes)?;
+        let (version, _) = BtreeVersion::resolve(meta.version)?;
+        if version.supports_encryption() && meta.is_encrypted() {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "image is encrypted; decrypt it before borrowing from it",
+            ));
+        }
+        let checksummed = version.supports_checksums() && meta.has_checksum();
+        Ok(BorrowedImage {
+            bytes,
+            page_size: meta.pagesize as usize,
+            endianness: meta.endian.into(),
+            protection: PageProtection::from_meta(0, checksummed),
+            root: meta.root,
+            subdb: meta.flags & BTM_SUBDB != 0,
+        })
+    }
+
+    fn page(&self, pgno: PageNumber) -> io::Result<&'a [u8]> {
+        page_slice(self.bytes, self.page_size, pgno)
+            .map_err(|e| io::Error::new(io::ErrorKind::UnexpectedEof, e.to_string()))
+    }
+
+    /// Roots of the btrees holding the records.
+    fn data_roots(&self) -> io::Result<Vec<PageNumber>> {
+        let tree_root = |meta: &[u8]| {
+            if RecnoFormat::from_meta_page(meta, self.endianness).is_some() {
+                return Err(io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    "recno databases cannot be borrowed from",
+                ));
+            }
+            Ok(u32e(self.endianness.into(), &meta[88..92]))
+        };
+        if !self.subdb {
+            return Ok(vec![tree_root(self.page(0)?)?]);
+        }
+        let mut roots = Vec::new();
+        for entry in self.walk(vec![self.root]) {
+            let pgno = subdb_meta_pgno(&entry?.value)?;
+            roots.push(tree_root(self.page(pgno)?)?);
+        }
+        Ok(roots)
+    }
+
+    fn walk(&self, roots: Vec<PageNumber>) -> BorrowedEntries<'a> {
+        BorrowedEntries {
+            image: *self,
+            descent: Descent::new(roots),
+            leaf: None,
+            error: None,
+        }
+    }
+
+    /// Every live entry of the data trees, in key order within each tree. Structural
+    /// errors are yielded in place of the entries they hide.
+    pub fn entries(&self) -> BorrowedEntries<'a> {
+        match self.data_roots() {
+            Ok(roots) => self.walk(roots),
+            Err(e) => BorrowedEntries {
+                error: Some(e),
+                ..self.walk(Vec::new())
+            },
+        }
+    }
+
+    fn item(&self, page: &'a [u8], pgno: PageNumber, stored: Stored) -> io::Result<ByteSlice<'a>> {
+        match stored {
+            Stored::Inline((start, end)) => Ok(Cow::Borrowed(&page[start..end])),
+            Stored::Overflow(r) => {
+                read_overflow_chain(self, r, self.endianness, self.protection).map(Cow::Owned)
+            }
+            Stored::Blob(r) => Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                format!("page {pgno}: value is stored outside the image ({r})"),
+            )),
+        }
+    }
+}
+
+/// Overflow chains are reassembled through the [`PageSource`] interface, which copies.
+impl PageSource for BorrowedImage<'_> {
+    fn read_page(&self, page_no: PageNumber) -> io::Result<ByteVec> {
+        self.page(page_no).map(<[u8]>::to_vec)
+    }
+
+    fn page_count(&self) -> Option<u64> {
+        Some((self.bytes.len() / self.page_size) as u64)
+    }
+
+    fn source_id(&self) -> String {
+        "borrowed image".to_string()
+    }
+}
+
+/// Depth-first iterator returned by [`BorrowedImage::entries`].
+pub struct BorrowedEntries<'a> {
+    image: BorrowedImage<'a>,
+    descent: Descent,
+    leaf: Option<Leaf<&'a [u8]>>,
+    /// An error finding the trees, yielded first.
+    error: Option<io::Error>,
+}
+
+impl<'a> BorrowedEntries<'a> {
+    fn entry(
+        &self,
+        pgno: PageNumber,
+        page: &'a [u8],
+        d: EntryDescriptor,
+    ) -> io::Result<BorrowedEntry<'a>> {
+        let e = self.image.endianness;
+        // The value first, so a pair that cannot be borrowed fails before its key is read.
+        let value = self.image.item(page, pgno, d.value_stored(page, e))?;
+        Ok(BorrowedEntry {
+            key: self.image.item(page, pgno, d.key_stored(page, e))?,
+            value,
+            page_no: pgno,
+            slot_index: d.slot_index,
+        })
+    }
+}
+
+impl<'a> Iterator for BorrowedEntries<'a> {
+    type Item = io::Result<BorrowedEntry<'a>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(e) = self.error.take() {
+            return Some(Err(e));
+        }
+        loop {
+            if let Some(leaf) = &mut self.leaf {
+                match leaf.next_live() {
+                    Some(d) => {
+                        let (pgno, page) = (leaf.pgno, leaf.page);
+                        return Some(self.entry(pgno, page, d));
+                    }
+                    None => self.leaf = None,
+                }
+            }
+            let pgno = match self.descent.next_page()? {
+                Ok(pgno) => pgno,
+                Err(e) => return Some(Err(e)),
+            };
+            let page = match self.image.page(pgno) {
+                Ok(page) => page,
+                Err(e) => return Some(Err(e)),
+            };
+            let image = &self.image;
+            match self.descent.enter(page, image.endianness, image.protection) {
+                Ok(Some(entries)) => self.leaf = Some(Leaf::new(pgno, page, entries)),
+                Ok(None) => {}
+                Err(e) => return Some(Err(e)),
+            }
+        }
+    }
+}
@@ -1,14 +1,28 @@
-use std::io;
+use std::{cmp::Ordering, collections::HashSet, io, sync::Arc};
 
-use crate::storage::{
-    page::{EntryDescriptor, ValueSupplier},
-    types::{ByteSlice, ByteVec, PageNumber},
+use crate::{
+    entry::constants::OverflowRef,
+    storage::{
+        blob::{BlobRef, BlobResolver, BlobSupplier},
+        compare::bt_compare,
+        entry::{Confidence, Provenance},
+        page::{EntryDescriptor, PageHeader, PageProtection, PageType, ValueSupplier},
+        supplier::{InlineSupplier, OverflowSupplier, read_overflow_chain},
+        types::{ByteSlice, ByteVec, Endianness, PageNumber, PageSource},
+    },
+    util::{u16e, u32e},
 };
 
+// The item types and sizes are shared with the slot validation, which is built without
+// `std` too.
+pub use crate::constants::{B_BLOB, B_DELETE, B_DUPLICATE, B_KEYDATA, B_OVERFLOW};
+pub(crate) use crate::constants::{BBLOB_SIZE, BINTERNAL_SIZE, BOVERFLOW_SIZE};
+
 /// Logical node representation.
+#[allow(dead_code)]
 pub(crate) enum Node<'a> {
     Internal {
-        keys: Vec<ByteSlice<'a>>,
+        keys: Vec<SeparatorKey<'a>>,
         children: Vec<PageNumber>,
     },
     Leaf {
@@ -16,8 +30,596 @@ pub(crate) enum Node<'a> {
     },
 }
 
+/// The key of an internal item: no key in the child it points to sorts before it. The
+/// first key of an internal page is not compared against.
+pub(crate) enum SeparatorKey<'a> {
+    Inline(ByteSlice<'a>),
+    /// The key is stored on an overflow chain.
+    Overflow(OverflowRef),
+}
+
+impl EntryDescriptor {
+    /// The key is stored on an overflow chain; `key_range` covers its `BOVERFLOW` item.
+    pub const KEY_OVERFLOW: u8 = 0x01;
+    /// The value is stored on an overflow chain; `value_range` covers its `BOVERFLOW` item.
+    pub const VALUE_OVERFLOW: u8 = 0x02;
+    /// The value is stored in a blob file; `value_range` covers its `BBLOB` item.
+    pub const VALUE_BLOB: u8 = 0x04;
+    /// The key or the value carries the `B_DELETE` flag.
+    pub const DELETED: u8 = 0x80;
+
+    pub fn is_deleted(&self) -> bool {
+        self.flags & Self::DELETED != 0
+    }
+
+    /// Where the key's bytes are on `page`, the page the descriptor was parsed from.
+    pub(crate) fn key_stored(&self, page: &[u8], e: Endianness) -> Stored {
+        let item = &page[self.key_range.0..self.key_range.1];
+        if self.flags & Self::KEY_OVERFLOW != 0 {
+            Stored::Overflow(overflow_ref(item, e))
+        } else {
+            Stored::Inline(self.key_range)
+        }
+    }
+
+    /// Where the value's bytes are on `page`, the page the descriptor was parsed from.
+    pub(crate) fn value_stored(&self, page: &[u8], e: Endianness) -> Stored {
+        let item = &page[self.value_range.0..self.value_range.1];
+        if self.flags & Self::VALUE_OVERFLOW != 0 {
+            Stored::Overflow(overflow_ref(item, e))
+        } else if self.flags & Self::VALUE_BLOB != 0 {
+            Stored::Blob(BlobRef::parse(item, e))
+        } else {
+            Stored::Inline(self.value_range)
+        }
+    }
+}
+
+/// How a leaf item is stored, and so what reading it takes.
+pub(crate) enum Stored {
+    /// On the leaf page itself, at this range.
+    Inline((usize, usize)),
+    /// On an overflow chain.
+    Overflow(OverflowRef),
+    /// In a blob file outside the database.
+    Blob(BlobRef),
+}
+
+fn invalid(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+/// Absolute offsets from the slot array of a page.
+pub(crate) fn slot_offsets(
+    page: &[u8],
+    hdr: &PageHeader,
+    e: Endianness,
+    protection: PageProtection,
+) -> io::Result<Vec<usize>> {
+    let start = protection.header_size();
+    let end = hdr.lower_bound(start, 2);
+    if end > page.len() {
+        return Err(invalid(format!(
+            "page {}: slot array ({} entries) runs past the page",
+            hdr.pgno, hdr.entries
+        )));
+    }
+    Ok((start..end)
+        .step_by(2)
+        .map(|i| u16e(e.into(), &page[i..i + 2]) as usize)
+        .collect())
+}
+
+/// Like [`slot_offsets`], but keeps whatever part of the slot array fits on the page.
+/// The flag is `true` when the array had to be cut short.
+pub(crate) fn lenient_slot_offsets(
+    page: &[u8],
+    hdr: &PageHeader,
+    e: Endianness,
+    protection: PageProtection,
+) -> (Vec<usize>, bool) {
+    let start = protection.header_size();
+    let end = hdr.lower_bound(start, 2);
+    let fits = end.min(page.len().saturating_sub(1));
+    let offsets = (start..fits)
+        .step_by(2)
+        .map(|i| u16e(e.into(), &page[i..i + 2]) as usize)
+        .collect();
+    (offsets, fits < end)
+}
+
+/// A single leaf item: where its bytes are and how they are stored.
+pub(crate) struct LeafItem {
+    pub(crate) kind: u8,
+    pub(crate) deleted: bool,
+    /// Inline data range, or the range of the `BOVERFLOW` or `BBLOB` item.
+    pub(crate) range: (usize, usize),
+    /// Logical length (overflow `tlen` or blob size for external items).
+    pub(crate) len: usize,
+}
+
+pub(crate) fn parse_leaf_item(page: &[u8], off: usize, e: Endianness) -> io::Result<LeafItem> {
+    if off + 3 > page.len() {
+        return Err(invalid(format!("leaf item header at {off} out of bounds")));
+    }
+    let raw = page[off + 2];
+    let kind = raw & !B_DELETE;
+    let deleted = raw & B_DELETE != 0;
+    match kind {
+        B_KEYDATA => {
+            let len = u16e(e.into(), &page[off..off + 2]) as usize;
+            let range = (off + 3, off + 3 + len);
+            if range.1 > page.len() {
+                return Err(invalid(format!(
+                    "leaf item at {off}: data runs past the page"
+                )));
+            }
+            Ok(LeafItem {
+                kind,
+                deleted,
+                range,
+                len,
+            })
+        }
+        B_OVERFLOW | B_DUPLICATE => {
+            if off + BOVERFLOW_SIZE > page.len() {
+                return Err(invalid(format!("overflow item at {off} out of bounds")));
+            }
+            let len = u32e(e.into(), &page[off + 8..off + 12]) as usize;
+            Ok(LeafItem {
+                kind,
+                deleted,
+                range: (off, off + BOVERFLOW_SIZE),
+                len,
+            })
+        }
+        B_BLOB => {
+            if off + BBLOB_SIZE > page.len() {
+                return Err(invalid(format!("blob item at {off} out of bounds")));
+            }
+            let size = BlobRef::parse(&page[off..off + BBLOB_SIZE], e).size;
+            Ok(LeafItem {
+                kind,
+                deleted,
+                range: (off, off + BBLOB_SIZE),
+                len: usize::try_from(size).unwrap_or(usize::MAX),
+            })
+        }
+        k => Err(invalid(format!("unknown leaf item type {k} at {off}"))),
+    }
+}
+
+/// Decode a `BOVERFLOW` item.
+pub(crate) fn overflow_ref(item: &[u8], e: Endianness) -> OverflowRef {
+    OverflowRef {
+        first_page: u32e(e.into(), &item[4..8]),
+        total_len: u32e(e.into(), &item[8..12]),
+    }
+}
+
+/// Parse a btree page into its logical node.
+pub(crate) fn parse_node<'a>(
+    page: &'a [u8],
+    e: Endianness,
+    protection: PageProtection,
+) -> io::Result<Node<'a>> {
+    let hdr = PageHeader::parse(page, e, protection)?;
+    let offsets = slot_offsets(page, &hdr, e, protection)?;
+    match hdr.kind() {
+        PageType::BtreeInternal => {
+            let mut keys = Vec::with_capacity(offsets.len());
+            let mut children = Vec::with_capacity(offsets.len());
+            for off in offsets {
+                if off + BINTERNAL_SIZE > page.len() {
+                    return Err(invalid(format!(
+                        "page {}: internal item at {off} out of bounds",
+                        hdr.pgno
+                    )));
+                }
+                let len = u16e(e.into(), &page[off..off + 2]) as usize;
+                let data_end = off + BINTERNAL_SIZE + len;
+                if data_end > page.len() {
+                    return Err(invalid(format!(
+                        "page {}: internal key at {off} runs past the page",
+                        hdr.pgno
+                    )));
+                }
+                let data = &page[off + BINTERNAL_SIZE..data_end];
+                children.push(u32e(e.into(), &page[off + 4..off + 8]));
+                keys.push(if page[off + 2] & !B_DELETE == B_OVERFLOW {
+                    if data.len() < BOVERFLOW_SIZE {
+                        return Err(invalid(format!(
+                            "page {}: overflow key at {off} is truncated",
+                            hdr.pgno
+                        )));
+                    }
+                    SeparatorKey::Overflow(overflow_ref(data, e))
+                } else {
+                    SeparatorKey::Inline(ByteSlice::Borrowed(data))
+                });
+            }
+            Ok(Node::Internal { keys, children })
+        }
+        PageType::BtreeLeaf => {
+            let mut entries = Vec::with_capacity(offsets.len() / 2);
+            for (pair, slots) in offsets.chunks(2).enumerate() {
+                let [k, v] = slots else {
+                    return Err(invalid(format!(
+                        "page {}: odd number of leaf slots ({})",
+                        hdr.pgno, hdr.entries
+                    )));
+                };
+                let key = parse_leaf_item(page, *k, e)?;
+                let value = parse_leaf_item(page, *v, e)?;
+                let mut flags = 0;
+                if key.kind == B_OVERFLOW {
+                    flags |= EntryDescriptor::KEY_OVERFLOW;
+                }
+                if value.kind == B_OVERFLOW {
+                    flags |= EntryDescriptor::VALUE_OVERFLOW;
+                }
+                if value.kind == B_BLOB {
+                    flags |= EntryDescriptor::VALUE_BLOB;
+                }
+                if key.deleted || value.deleted {
+                    flags |= EntryDescriptor::DELETED;
+                }
+                if key.kind == B_BLOB {
+                    return Err(invalid(format!(
+                        "page {}: key at {k} is stored as a blob",
+                        hdr.pgno
+                    )));
+                }
+                if key.kind == B_DUPLICATE || value.kind == B_DUPLICATE {
+                    return Err(invalid(format!(
+                        "page {}: off-page duplicates are not supported",
+                        hdr.pgno
+                    )));
+                }
+                entries.push(EntryDescriptor {
+                    slot_index: (pair * 2) as u16,
+                    key_len: key.len,
+                    value_len: value.len,
+                    flags,
+                    key_range: key.range,
+                    value_range: value.range,
+                });
+            }
+            Ok(Node::Leaf { entries })
+        }
+        other => Err(invalid(format!(
+            "page {}: expected a btree page, got {other:?}",
+            hdr.pgno
+        ))),
+    }
+}
+
+/// The depth-first order every walk over btree pages follows: the pages still to read,
+/// and those read already, which give away a page linked twice. Reading the pages is
+/// left to the caller, so that in-memory, shared and async sources walk alike.
+pub(crate) struct Descent {
+    stack: Vec<PageNumber>,
+    visited: HashSet<PageNumber>,
+}
+
+impl Descent {
+    /// Walk the trees under `roots`, one after the other.
+    pub(crate) fn new(roots: Vec<PageNumber>) -> Self {
+        Descent {
+            stack: roots.into_iter().rev().collect(),
+            visited: HashSet::new(),
+        }
+    }
+
+    /// The page to read next, or `None` when the walk is done.
+    pub(crate) fn next_page(&mut self) -> Option<io::Result<PageNumber>> {
+        let pgno = self.stack.pop()?;
+        if !self.visited.insert(pgno) {
+            return Some(Err(invalid(format!(
+                "page {pgno} is reachable twice (tree cycle)"
+            ))));
+        }
+        Some(Ok(pgno))
+    }
+
+    /// Take in `page`, the one [`Self::next_page`] named last. The children of an
+    /// internal page are read next; a leaf page hands back its entries.
+    pub(crate) fn enter(
+        &mut self,
+        page: &[u8],
+        e: Endianness,
+        protection: PageProtection,
+    ) -> io::Result<Option<Vec<EntryDescriptor>>> {
+        match parse_node(page, e, protection)? {
+            Node::Internal { children, .. } => {
+                self.stack.extend(children.into_iter().rev());
+                Ok(None)
+            }
+            Node::Leaf { entries } => Ok(Some(entries)),
+        }
+    }
+}
+
+/// The leaf page a walk is yielding from, held as `P`: owned, shared or borrowed.
+pub(crate) struct Leaf<P> {
+    pub(crate) pgno: PageNumber,
+    pub(crate) page: P,
+    entries: std::vec::IntoIter<EntryDescriptor>,
+}
+
+impl<P> Leaf<P> {
+    pub(crate) fn new(pgno: PageNumber, page: P, entries: Vec<EntryDescriptor>) -> Self {
+        Leaf {
+            pgno,
+            page,
+            entries: entries.into_iter(),
+        }
+    }
+
+    /// The next pair that is not deleted.
+    pub(crate) fn next_live(&mut self) -> Option<EntryDescriptor> {
+        self.entries.find(|d| !d.is_deleted())
+    }
+}
+
+/// Walks one btree (a database or subdatabase) from its root, in key order.
+#[derive(Clone)]
+pub(crate) struct TreeWalker {
+    pub(crate) source: Arc<dyn PageSource>,
+    pub(crate) root: PageNumber,
+    pub(crate) endianness: Endianness,
+    pub(crate) protection: PageProtection,
+    /// Where values stored in blob files are read from.
+    pub(crate) blobs: Option<Arc<dyn BlobResolver>>,
+}
+
+/// A live entry: its key, a supplier for its value, and where it was read from.
+pub(crate) type WalkEntry = (ByteVec, Box<dyn ValueSupplier>, Provenance);
+
+/// An entry produced by the walker, or the reason a part of the tree could not be read.
+pub(crate) type WalkItem = io::Result<WalkEntry>;
+
+impl TreeWalker {
+    /// In-order iterator over all live entries. Structural errors are yielded in place
+    /// of the entries they hide, and the walk continues with the next subtree.
+    pub(crate) fn entries(&self) -> TreeEntries {
+        self.clone().into_entries()
+    }
+
+    /// Like [`Self::entries`], consuming the walker.
+    pub(crate) fn into_entries(self) -> TreeEntries {
+        TreeEntries {
+            descent: Descent::new(vec![self.root]),
+            source_id: self.source.source_id(),
+            walker: self,
+            leaf: None,
+        }
+    }
+
+    /// In-order iterator over the live entries from the leaf that would hold `start`
+    /// onwards. Only the path from the root to that leaf is read up front; entries before
+    /// `start` on that leaf are yielded too, for the caller to skip.
+    pub(crate) fn entries_from(&self, start: &[u8]) -> io::Result<TreeEntries> {
+        let mut stack = Vec::new();
+        let mut visited = HashSet::new();
+        let (pgno, page, entries) = self.descend(start, &mut stack, &mut visited)?;
+        Ok(TreeEntries {
+            walker: self.clone(),
+            descent: Descent { stack, visited },
+            source_id: self.source.source_id(),
+            leaf: Some(Leaf::new(pgno, page, entries)),
+        })
+    }
+
+    /// Look up `key` by descending from the root along the separator keys, reading one
+    /// page per level of the tree. Keys compare with [`bt_compare`].
+    pub(crate) fn get(&self, key: &[u8]) -> io::Result<Option<WalkEntry>> {
+        let (pgno, page, entries) = self.descend(key, &mut Vec::new(), &mut HashSet::new())?;
+        let source_id = self.source.source_id();
+        for d in entries {
+            let stored = match d.key_stored(&page, self.endianness) {
+                Stored::Overflow(r) => ByteSlice::Owned(read_overflow_chain(
+                    self.source.as_ref(),
+                    r,
+                    self.endianness,
+                    self.protection,
+                )?),
+                _ => ByteSlice::Borrowed(&page[d.key_range.0..d.key_range.1]),
+            };
+            match bt_compare(&stored, key) {
+                Ordering::Less => continue,
+                Ordering::Greater => break,
+                Ordering::Equal if d.is_deleted() => continue,
+                Ordering::Equal => return self.leaf_item(pgno, &page, d, &source_id).map(Some),
+            }
+        }
+        Ok(None)
+    }
+
+    /// Descend from the root to the leaf whose key range holds `key`, and return it.
+    /// The children right of the path are pushed onto `right`, the deepest last, so that
+    /// popping them continues a walk in key order past that leaf.
+    fn descend(
+        &self,
+        key: &[u8],
+        right: &mut Vec<PageNumber>,
+        visited: &mut HashSet<PageNumber>,
+    ) -> io::Result<(PageNumber, Arc<[u8]>, Vec<EntryDescriptor>)> {
+        let mut pgno = self.root;
+        loop {
+            if !visited.insert(pgno) {
+                return Err(invalid(format!(
+                    "page {pgno} is reachable twice (tree cycle)"
+                )));
+            }
+            let page: Arc<[u8]> = self.source.read_page(pgno)?.into();
+            match parse_node(&page, self.endianness, self.protection)? {
+                Node::Internal { keys, children } => {
+                    if children.is_empty() {
+                        return Err(invalid(format!("internal page {pgno} has no entries")));
+                    }
+                    let mut i = 0;
+                    while i + 1 < keys.len() && self.separator_cmp(&keys[i + 1], key)?.is_le() {
+                        i += 1;
+                    }
+                    right.extend(children[i + 1..].iter().rev());
+                    pgno = children[i];
+                }
+                Node::Leaf { entries } => return Ok((pgno, page, entries)),
+            }
+        }
+    }
+
+    fn separator_cmp(&self, separator: &SeparatorKey, key: &[u8]) -> io::Result<Ordering> {
+        Ok(match separator {
+            SeparatorKey::Inline(bytes) => bt_compare(bytes, key),
+            SeparatorKey::Overflow(r) => {
+                let bytes = read_overflow_chain(
+                    self.source.as_ref(),
+                    *r,
+                    self.endianness,
+                    self.protection,
+                )?;
+                bt_compare(&bytes, key)
+            }
+        })
+    }
+
+    /// Leaf page numbers in key order. Only internal pages are read.
+    #[cfg(feature = "parallel")]
+    pub(crate) fn leaf_pages(&self) -> io::Result<Vec<PageNumber>> {
+        let mut leaves = Vec::new();
+        let mut stack = vec![self.root];
+        let mut visited = HashSet::new();
+        while let Some(pgno) = stack.pop() {
+            if !visited.insert(pgno) {
+                return Err(invalid(format!(
+                    "page {pgno} is reachable twice (tree cycle)"
+                )));
+            }
+            let page = self.source.read_page(pgno)?;
+            let hdr = PageHeader::parse(&page, self.endianness, self.protection)?;
+            if hdr.kind() == PageType::BtreeLeaf {
+                leaves.push(pgno);
+                continue;
+            }
+            match parse_node(&page, self.endianness, self.protection)? {
+                Node::Internal { children, .. } => stack.extend(children.into_iter().rev()),
+                Node::Leaf { .. } => leaves.push(pgno),
+            }
+        }
+        Ok(leaves)
+    }
+
+    /// Live entries of a single leaf page, in slot order.
+    #[cfg(feature = "parallel")]
+    pub(crate) fn read_leaf(&self, pgno: PageNumber) -> io::Result<Vec<WalkItem>> {
+        let page: Arc<[u8]> = self.source.read_page(pgno)?.into();
+        match parse_node(&page, self.endianness, self.protection)? {
+            Node::Leaf { entries } => Ok(self.leaf_items(pgno, page.clone(), entries)),
+            Node::Internal { .. } => Err(invalid(format!("page {pgno} is not a leaf page"))),
+        }
+    }
+
+    #[cfg(feature = "parallel")]
+    fn leaf_items(
+        &self,
+        pgno: PageNumber,
+        page: Arc<[u8]>,
+        entries: Vec<EntryDescriptor>,
+    ) -> Vec<WalkItem> {
+        let source_id = self.source.source_id();
+        entries
+            .into_iter()
+            .filter(|d| !d.is_deleted())
+            .map(|d| self.leaf_item(pgno, &page, d, &source_id))
+            .collect()
+    }
+
+    /// Decode one live leaf entry. An overflow key is read here; an overflow or blob
+    /// value is only read when its supplier is materialized.
+    fn leaf_item(
+        &self,
+        pgno: PageNumber,
+        page: &Arc<[u8]>,
+        d: EntryDescriptor,
+        source_id: &str,
+    ) -> WalkItem {
+        let key = match d.key_stored(page, self.endianness) {
+            Stored::Overflow(r) => {
+                read_overflow_chain(self.source.as_ref(), r, self.endianness, self.protection)?
+            }
+            _ => page[d.key_range.0..d.key_range.1].to_vec(),
+        };
+        let supplier: Box<dyn ValueSupplier> = match d.value_stored(page, self.endianness) {
+            Stored::Inline((start, end)) => Box::new(InlineSupplier::new(page.clone(), start..end)),
+            Stored::Overflow(r) => Box::new(OverflowSupplier::new(
+                self.source.clone(),
+                r,
+                self.endianness,
+                self.protection,
+            )),
+            Stored::Blob(r) => Box::new(BlobSupplier::new(self.blobs.clone(), r)),
+        };
+        let provenance = Provenance {
+            source_id: source_id.to_string(),
+            page_no: pgno,
+            slot_index: d.slot_index,
+            confidence: Confidence::High,
+        };
+        Ok((key, supplier, provenance))
+    }
+}
+
+/// Depth-first in-order iterator returned by [`TreeWalker::entries`].
+/// Holds at most one leaf page, whose entries are decoded one at a time, plus the
+/// [`Descent`] through the pages above and after it.
+pub(crate) struct TreeEntries {
+    walker: TreeWalker,
+    descent: Descent,
+    source_id: String,
+    leaf: Option<Leaf<Arc<[u8]>>>,
+}
+
+impl Iterator for TreeEntries {
+    type Item = WalkItem;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(leaf) = &mut self.leaf {
+                match leaf.next_live() {
+                    Some(d) => {
+                        return Some(self.walker.leaf_item(
+                            leaf.pgno,
+                            &leaf.page,
+                            d,
+                            &self.source_id,
+                        ));
+                    }
+                    None => self.leaf = None,
+                }
+            }
+            let pgno = match self.descent.next_page()? {
+                Ok(pgno) => pgno,
+                Err(e) => return Some(Err(e)),
+            };
+            let w = &self.walker;
+            let page: Arc<[u8]> = match w.source.read_page(pgno) {
+                Ok(p) => p.into(),
+                Err(e) => return Some(Err(e)),
+            };
+            match self.descent.enter(&page, w.endianness, w.protection) {
+                Ok(Some(entries)) => self.leaf = Some(Leaf::new(pgno, page, entries)),
+                Ok(None) => {}
+                Err(e) => return Some(Err(e)),
+            }
+        }
+    }
+}
+
 /// The BTreeWalker knows how to walk the on-disk tree.
 /// It is the only component that understands separator keys, child pointers, and root lookup.
+#[allow(dead_code)]
 pub(crate) trait BTreeWalker {
     /// In-order traversal yielding descriptors + a supplier that can materialize each value.
     /// The supplier must capture whatever is necessary (page buffer + source) to materialize lazily.
@@ -28,3 +630,23 @@ pub(crate) trait BTreeWalker {
     /// Convenience: collect into a map eagerly (used in tests / simple clients).
     fn collect_map(&self) -> io::Result<std::collections::HashMap<ByteVec, ByteVec>>;
 }
+
+impl BTreeWalker for TreeWalker {
+    fn walk_in_order<'s>(
+        &'s self,
+    ) -> Box<dyn Iterator<Item = (ByteVec, Box<dyn ValueSupplier>)> + 's> {
+        Box::new(
+            self.entries()
+                .filter_map(|r| r.ok().map(|(k, v, _)| (k, v))),
+        )
+    }
+
+    fn collect_map(&self) -> io::Result<std::collections::HashMap<ByteVec, ByteVec>> {
+        let mut map = std::collections::HashMap::new();
+        for item in self.entries() {
+            let (key, value, _) = item?;
+            map.insert(key, value.materialize()?);
+        }
+        Ok(map)
+    }
+}
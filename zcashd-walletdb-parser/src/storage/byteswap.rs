@@ -0,0 +1,201 @@
+//! Convert a btree image between little- and big-endian byte order, the way Berkeley DB's
+//! `__db_byteswap` does when a file created on one architecture is opened on the other.
+//!
+//! Only the on-page metadata is swapped: headers, slot arrays, item lengths and page
+//! pointers. Key and value bytes are opaque to Berkeley DB and stay as they are, as do the
+//! subdatabase page numbers in the master database, which are stored in network order.
+
+use std::io;
+
+use crate::{
+    constants::{
+        DBMETA_CHKSUM, DBMETASIZE, P_BTREEMETA, P_IBTREE, P_INVALID, P_IRECNO, P_LBTREE, P_LRECNO,
+        P_OVERFLOW, SIZEOF_PAGE,
+    },
+    storage::{
+        btree::{
+            B_BLOB, B_DUPLICATE, B_KEYDATA, B_OVERFLOW, BBLOB_SIZE, BINTERNAL_SIZE, BOVERFLOW_SIZE,
+        },
+        checksum::write_page_checksum,
+        types::Endianness,
+    },
+    util::{detect_endian, is_valid_page_size, u16e, u32e},
+};
+
+fn invalid(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+fn swap16(b: &mut [u8], off: usize) {
+    b[off..off + 2].reverse();
+}
+
+fn swap32(b: &mut [u8], off: usize) {
+    b[off..off + 4].reverse();
+}
+
+fn swap64(b: &mut [u8], off: usize) {
+    b[off..off + 8].reverse();
+}
+
+/// Byte-swap a whole image in place and return the byte order it ends up in.
+///
+/// Checksummed images get fresh checksums in the new byte order. Encrypted images and
+/// access methods other than btree are rejected, as are pages whose slots point outside
+/// the page; on error the image is left partly converted.
+pub fn byteswap_image(image: &mut [u8]) -> io::Result<Endianness> {
+    if image.len() < DBMETASIZE {
+        return Err(invalid("image is shorter than a meta page".into()));
+    }
+    let from = detect_endian(image).ok_or_else(|| invalid("meta page magic not found".into()))?;
+    let page_size = u32e(from, &image[20..24]) as usize;
+    if !is_valid_page_size(page_size) {
+        return Err(invalid(format!("unsupported page size {page_size}")));
+    }
+    if image[24] != 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "encrypted images cannot be byte-swapped",
+        ));
+    }
+    let from: Endianness = from.into();
+    let to = match from {
+        Endianness::Little => Endianness::Big,
+        Endianness::Big => Endianness::Little,
+    };
+    let checksummed = image[26] & DBMETA_CHKSUM != 0;
+    let header_size = if checksummed { 32 } else { SIZEOF_PAGE };
+
+    for (pgno, page) in image.chunks_exact_mut(page_size).enumerate() {
+        swap_page(page, from, header_size).map_err(|e| invalid(format!("page {pgno}: {e}")))?;
+        if checksummed {
+            write_page_checksum(page, to);
+        }
+    }
+    Ok(to)
+}
+
+/// Swap one page. `from` is the byte order the page is currently in.
+fn swap_page(page: &mut [u8], from: Endianness, header_size: usize) -> io::Result<()> {
+    let page_type = page[25];
+    if page_type == P_BTREEMETA {
+        swap_btree_meta(page);
+        return Ok(());
+    }
+
+    let entries = u16e(from.into(), &page[20..22]) as usize;
+    // lsn (file, offset), pgno, prev_pgno, next_pgno, entries, hf_offset
+    for off in [0, 4, 8, 12, 16] {
+        swap32(page, off);
+    }
+    swap16(page, 20);
+    swap16(page, 22);
+
+    match page_type {
+        P_INVALID | P_OVERFLOW => Ok(()),
+        P_IBTREE | P_IRECNO | P_LBTREE | P_LRECNO => {
+            if header_size + entries * 2 > page.len() {
+                return Err(invalid(format!("{entries} slots do not fit on the page")));
+            }
+            for slot in 0..entries {
+                let at = header_size + slot * 2;
+                let off = u16e(from.into(), &page[at..at + 2]) as usize;
+                swap16(page, at);
+                match page_type {
+                    P_IBTREE => swap_internal_item(page, off, from)?,
+                    P_IRECNO => swap_recno_internal_item(page, off)?,
+                    _ => swap_leaf_item(page, off, from)?,
+                }
+            }
+            Ok(())
+        }
+        other => Err(invalid(format!("page type {other} is not supported"))),
+    }
+}
+
+/// Swap a `BKEYDATA`, `BOVERFLOW` or `BBLOB` item starting at `off`.
+fn swap_leaf_item(page: &mut [u8], off: usize, from: Endianness) -> io::Result<()> {
+    let Some(kind) = page.get(off + 2).map(|t| t & 0x7f) else {
+        return Err(invalid(format!("item at {off} out of bounds")));
+    };
+    match kind {
+        B_KEYDATA => {
+            let len = u16e(from.into(), &page[off..off + 2]) as usize;
+            if off + 3 + len > page.len() {
+                return Err(invalid(format!("item at {off} runs past the page")));
+            }
+            swap16(page, off);
+        }
+        B_OVERFLOW | B_DUPLICATE => swap_overflow_ref(page, off)?,
+        B_BLOB => {
+            if off + BBLOB_SIZE > page.len() {
+                return Err(invalid(format!("blob item at {off} out of bounds")));
+            }
+            swap16(page, off);
+            for field in [16, 24, 32, 40] {
+                swap64(page, off + field);
+            }
+        }
+        other => return Err(invalid(format!("unknown item type {other} at {off}"))),
+    }
+    Ok(())
+}
+
+/// Swap a `BINTERNAL` item, including the `BOVERFLOW` it embeds for an overflow key.
+fn swap_internal_item(page: &mut [u8], off: usize, from: Endianness) -> io::Result<()> {
+    if off + BINTERNAL_SIZE > page.len() {
+        return Err(invalid(format!("internal item at {off} out of bounds")));
+    }
+    let len = u16e(from.into(), &page[off..off + 2]) as usize;
+    if off + BINTERNAL_SIZE + len > page.len() {
+        return Err(invalid(format!("internal key at {off} runs past the page")));
+    }
+    swap16(page, off);
+    swap32(page, off + 4);
+    swap32(page, off + 8);
+    if page[off + 2] & 0x7f == B_OVERFLOW {
+        swap_overflow_ref(page, off + BINTERNAL_SIZE)?;
+    }
+    Ok(())
+}
+
+/// Swap a `RINTERNAL` item: pgno:u32, nrecs:u32.
+fn swap_recno_internal_item(page: &mut [u8], off: usize) -> io::Result<()> {
+    if off + 8 > page.len() {
+        return Err(invalid(format!(
+            "recno internal item at {off} out of bounds"
+        )));
+    }
+    swap32(page, off);
+    swap32(page, off + 4);
+    Ok(())
+}
+
+/// Swap the page number and total length of a `BOVERFLOW` at `off`.
+fn swap_overflow_ref(page: &mut [u8], off: usize) -> io::Result<()> {
+    if off + BOVERFLOW_SIZE > page.len() {
+        return Err(invalid(format!(
+            "overflow reference at {off} out of bounds"
+        )));
+    }
+    swap32(page, off + 4);
+    swap32(page, off + 8);
+    Ok(())
+}
+
+/// Swap a `BTMETA` page: every field is a 32-bit word except the single-byte
+/// `encrypt_alg`/`type`/`metaflags`, the uid and the iv/checksum bytes.
+fn swap_btree_meta(page: &mut [u8]) {
+    // lsn (file, offset), pgno, magic, version, pagesize
+    for off in [0, 4, 8, 12, 16, 20] {
+        swap32(page, off);
+    }
+    // free, last_pgno, nparts, key_count, record_count, flags
+    for off in (28..52).step_by(4) {
+        swap32(page, off);
+    }
+    // unused1, minkey, re_len, re_pad, root, unused2[92], crypto_magic, trash[3]
+    for off in (72..476).step_by(4) {
+        swap32(page, off);
+    }
+}
@@ -0,0 +1,125 @@
+//! LRU page cache in front of any [`PageSource`].
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    io,
+    sync::Mutex,
+};
+
+use crate::storage::types::{ByteVec, PageNumber, PageSource};
+
+/// Default number of pages kept by [`CachedPageSource`] (512 KiB with 4 KiB pages).
+pub const DEFAULT_CACHE_PAGES: usize = 128;
+
+/// Hit/miss counters of a [`CachedPageSource`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub resident: usize,
+}
+
+#[derive(Debug, Default)]
+struct LruState {
+    /// Cached page and the tick of its last use.
+    pages: HashMap<PageNumber, (ByteVec, u64)>,
+    /// Last-use tick -> page, oldest first.
+    order: BTreeMap<u64, PageNumber>,
+    tick: u64,
+    hits: u64,
+    misses: u64,
+}
+
+impl LruState {
+    fn touch(&mut self, page_no: PageNumber) -> Option<ByteVec> {
+        self.tick += 1;
+        let tick = self.tick;
+        let (page, last) = self.pages.get_mut(&page_no)?;
+        self.order.remove(last);
+        *last = tick;
+        self.order.insert(tick, page_no);
+        Some(page.clone())
+    }
+
+    fn insert(&mut self, page_no: PageNumber, page: ByteVec, capacity: usize) {
+        while self.pages.len() >= capacity {
+            let Some((_, oldest)) = self.order.pop_first() else {
+                break;
+            };
+            self.pages.remove(&oldest);
+        }
+        self.tick += 1;
+        self.order.insert(self.tick, page_no);
+        self.pages.insert(page_no, (page, self.tick));
+    }
+}
+
+/// Keeps the `capacity` most recently used pages of `inner` in memory, so overflow chains,
+/// btree descents and decoders following references don't go back to disk for every read.
+#[derive(Debug)]
+pub struct CachedPageSource<S> {
+    inner: S,
+    capacity: usize,
+    state: Mutex<LruState>,
+}
+
+impl<S: PageSource> CachedPageSource<S> {
+    /// A capacity of 0 disables caching.
+    pub fn new(inner: S, capacity: usize) -> Self {
+        CachedPageSource {
+            inner,
+            capacity,
+            state: Mutex::new(LruState::default()),
+        }
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        self.state
+            .lock()
+            .map(|s| CacheStats {
+                hits: s.hits,
+                misses: s.misses,
+                resident: s.pages.len(),
+            })
+            .unwrap_or_default()
+    }
+
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+}
+
+impl<S: PageSource> PageSource for CachedPageSource<S> {
+    fn read_page(&self, page_no: PageNumber) -> io::Result<ByteVec> {
+        if self.capacity == 0 {
+            return self.inner.read_page(page_no);
+        }
+        {
+            let mut state = self
+                .state
+                .lock()
+                .map_err(|_| io::Error::other("page cache lock poisoned"))?;
+            if let Some(page) = state.touch(page_no) {
+                state.hits += 1;
+                return Ok(page);
+            }
+            state.misses += 1;
+        }
+        // Read outside the lock so concurrent readers of other pages are not serialized.
+        let page = self.inner.read_page(page_no)?;
+        if let Ok(mut state) = self.state.lock()
+            && !state.pages.contains_key(&page_no)
+        {
+            state.insert(page_no, page.clone(), self.capacity);
+        }
+        Ok(page)
+    }
+
+    fn page_count(&self) -> Option<u64> {
+        self.inner.page_count()
+    }
+
+    fn source_id(&self) -> String {
+        self.inner.source_id()
+    }
+}
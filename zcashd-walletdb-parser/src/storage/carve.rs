@@ -0,0 +1,260 @@
+//! Carving Berkeley DB pages out of an arbitrary blob, such as a `dd` image of a failed
+//! drive, for when the file system no longer knows where the wallet was.
+//!
+//! Files are located through their meta pages, the only pages carrying a magic number.
+//! From a meta page at offset `o` naming itself page `p`, the file is assumed to start at
+//! `o - p * pagesize` and to run contiguously for as long as the pages keep naming their
+//! own position. Subdatabase meta pages carry the file's uid too, so every meta page of
+//! one file lands in the same [`CarvedDatabase`].
+//!
+//! Leaf pages no meta page accounts for, e.g. fragments of a file whose start was
+//! overwritten, are grouped by page size and byte order as loose pages.
+
+use std::{
+    collections::{BTreeMap, HashSet},
+    fmt, io,
+    sync::Arc,
+};
+
+use crate::{
+    constants::{DB_MAX_PGSIZE, DB_MIN_PGSIZE, DBMETASIZE},
+    headers::{BtreeMeta, parse_btree_meta_page0},
+    storage::{
+        geometry::plausible_page,
+        page::{PageProtection, PageType},
+        salvage::BestEffortScan,
+        slots::validate_slots,
+        types::{ByteVec, Endianness, PageNumber, PageSize, PageSource},
+    },
+    util::hex,
+};
+
+/// Granularity at which the blob is searched: files start on sector boundaries.
+pub const SECTOR_SIZE: usize = 512;
+
+/// Consecutive pages that may fail to look like pages of a file before its contiguous
+/// run is considered over (freed pages are often zeroed).
+const MAX_GAP_PAGES: usize = 64;
+
+/// Pages carved from a blob that appear to belong to one database file.
+#[derive(Debug, Clone)]
+pub struct CarvedDatabase {
+    /// File ID from the meta page; `None` for loose pages.
+    pub uid: Option<[u8; 20]>,
+    pub page_size: PageSize,
+    pub endianness: Endianness,
+    pub protection: PageProtection,
+    /// Blob offset of each carved page, by page number.
+    pub pages: BTreeMap<PageNumber, u64>,
+    /// Pages found under a page number that was already taken.
+    pub conflicts: usize,
+}
+
+impl CarvedDatabase {
+    fn new(uid: Option<[u8; 20]>, page_size: usize, endianness: Endianness) -> Self {
+        CarvedDatabase {
+            uid,
+            page_size: page_size as PageSize,
+            endianness,
+            protection: PageProtection::None,
+            pages: BTreeMap::new(),
+            conflicts: 0,
+        }
+    }
+
+    fn add(&mut self, pgno: PageNumber, offset: u64) {
+        match self.pages.get(&pgno) {
+            Some(&existing) if existing != offset => self.conflicts += 1,
+            Some(_) => {}
+            None => {
+                self.pages.insert(pgno, offset);
+            }
+        }
+    }
+
+    /// The carved pages as a [`PageSource`] over `blob`, which must be the blob passed
+    /// to [`carve`].
+    pub fn source(&self, blob: Arc<[u8]>, source_id: impl Into<String>) -> CarvedPageSource {
+        CarvedPageSource {
+            blob,
+            page_size: self.page_size as usize,
+            pages: self.pages.clone(),
+            source_id: source_id.into(),
+        }
+    }
+
+    /// Salvage every leaf entry from the carved pages. Meta pages and master database
+    /// leaves are skipped.
+    pub fn entries(&self, blob: Arc<[u8]>, source_id: impl Into<String>) -> BestEffortScan {
+        let pages: HashSet<PageNumber> = self.pages.keys().copied().collect();
+        BestEffortScan::new(
+            Arc::new(self.source(blob, source_id)),
+            self.endianness,
+            self.protection,
+            HashSet::from([0]),
+        )
+        .only_pages(pages)
+        .skip_master_leaves()
+    }
+}
+
+impl fmt::Display for CarvedDatabase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "CarvedDatabase {{")?;
+        match &self.uid {
+            Some(uid) => writeln!(f, "  uid        : {}", hex(uid))?,
+            None => writeln!(f, "  uid        : (loose pages)")?,
+        }
+        writeln!(f, "  pagesize   : {}", self.page_size)?;
+        writeln!(f, "  endianness : {:?}", self.endianness)?;
+        writeln!(f, "  protection : {:?}", self.protection)?;
+        writeln!(f, "  pages      : {}", self.pages.len())?;
+        if let Some((first, last)) = self.pages.values().min().zip(self.pages.values().max()) {
+            writeln!(f, "  offsets    : 0x{first:x}..=0x{last:x}")?;
+        }
+        writeln!(f, "  conflicts  : {}", self.conflicts)?;
+        write!(f, "}}")
+    }
+}
+
+/// Pages picked out of a blob by page number; numbers nothing was carved for are
+/// unreadable.
+#[derive(Debug, Clone)]
+pub struct CarvedPageSource {
+    blob: Arc<[u8]>,
+    page_size: usize,
+    pages: BTreeMap<PageNumber, u64>,
+    source_id: String,
+}
+
+impl PageSource for CarvedPageSource {
+    fn read_page(&self, page_no: PageNumber) -> io::Result<ByteVec> {
+        let offset = *self.pages.get(&page_no).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("page {page_no} was not carved from {}", self.source_id),
+            )
+        })? as usize;
+        Ok(self.blob[offset..offset + self.page_size].to_vec())
+    }
+
+    fn page_count(&self) -> Option<u64> {
+        Some(self.pages.keys().next_back().map_or(0, |&p| p as u64 + 1))
+    }
+
+    fn source_id(&self) -> String {
+        self.source_id.clone()
+    }
+}
+
+/// Every sector-aligned meta page in `blob`.
+fn meta_pages(blob: &[u8]) -> Vec<(usize, BtreeMeta)> {
+    (0..blob.len().saturating_sub(DBMETASIZE - 1))
+        .step_by(SECTOR_SIZE)
+        .filter_map(|offset| {
+            let meta = parse_btree_meta_page0(&blob[offset..offset + DBMETASIZE]).ok()?;
+            (meta.p_type == PageType::Meta).then_some((offset, meta))
+        })
+        .collect()
+}
+
+/// The page size and byte order under which the page at `offset` is a clean btree leaf.
+/// The smallest page size that holds every item is the right one: items are packed
+/// against the end of the page.
+fn loose_leaf(blob: &[u8], offset: usize) -> Option<(usize, Endianness, PageNumber)> {
+    let max_pgno = (blob.len() / SECTOR_SIZE) as PageNumber;
+    for e in [Endianness::Little, Endianness::Big] {
+        let mut page_size = DB_MIN_PGSIZE;
+        while page_size <= DB_MAX_PGSIZE && offset + page_size <= blob.len() {
+            let page = &blob[offset..offset + page_size];
+            if let Some(hdr) = plausible_page(page, e)
+                && hdr.kind() == PageType::BtreeLeaf
+                && hdr.num_slots() > 0
+                // No file holding this page could be larger than the blob itself.
+                && hdr.pgno <= max_pgno
+                && validate_slots(page, &hdr, e, PageProtection::None).is_clean()
+            {
+                return Some((page_size, e, hdr.pgno));
+            }
+            page_size *= 2;
+        }
+    }
+    None
+}
+
+/// Find the Berkeley DB files, or what is left of them, in `blob`.
+///
+/// Databases found through meta pages come first, in the order of their first meta
+/// page, followed by one group of loose leaf pages per page size and byte order.
+pub fn carve(blob: &[u8]) -> Vec<CarvedDatabase> {
+    let mut databases: Vec<CarvedDatabase> = Vec::new();
+    let mut claimed: HashSet<usize> = HashSet::new();
+
+    for (offset, meta) in meta_pages(blob) {
+        let page_size = meta.pagesize as usize;
+        let e: Endianness = meta.endian.into();
+        let Some(base) = offset.checked_sub(meta.pgno as usize * page_size) else {
+            continue;
+        };
+        let index = match databases.iter().position(|d| {
+            d.uid == Some(meta.uid) && d.page_size == meta.pagesize && d.endianness == e
+        }) {
+            Some(index) => index,
+            None => {
+                databases.push(CarvedDatabase::new(Some(meta.uid), page_size, e));
+                databases.len() - 1
+            }
+        };
+        let db = &mut databases[index];
+        if meta.pgno == 0 {
+            db.protection = PageProtection::from_meta(meta.encrypt_alg, meta.has_checksum());
+        }
+        let last_pgno = (meta.pgno == 0).then_some(meta.last_pgno as usize);
+        let mut gap = 0;
+        for k in 0.. {
+            let start = base + k * page_size;
+            if start + page_size > blob.len()
+                || last_pgno.is_some_and(|last| k > last)
+                || gap > MAX_GAP_PAGES
+            {
+                break;
+            }
+            match plausible_page(&blob[start..start + page_size], e) {
+                Some(hdr) if hdr.pgno as usize == k => {
+                    gap = 0;
+                    db.add(hdr.pgno, start as u64);
+                    claimed.extend((start..start + page_size).step_by(SECTOR_SIZE));
+                }
+                _ => gap += 1,
+            }
+        }
+    }
+
+    let mut loose: Vec<CarvedDatabase> = Vec::new();
+    let mut offset = 0;
+    while offset + DB_MIN_PGSIZE <= blob.len() {
+        if claimed.contains(&offset) {
+            offset += SECTOR_SIZE;
+            continue;
+        }
+        let Some((page_size, e, pgno)) = loose_leaf(blob, offset) else {
+            offset += SECTOR_SIZE;
+            continue;
+        };
+        let db = match loose
+            .iter_mut()
+            .position(|d| d.page_size as usize == page_size && d.endianness == e)
+        {
+            Some(index) => &mut loose[index],
+            None => {
+                loose.push(CarvedDatabase::new(None, page_size, e));
+                loose.last_mut().expect("just pushed")
+            }
+        };
+        db.add(pgno, offset as u64);
+        offset += page_size;
+    }
+
+    databases.extend(loose);
+    databases
+}
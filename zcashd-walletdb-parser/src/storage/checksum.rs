@@ -0,0 +1,88 @@
+//! Page checksums for databases created with DB_CHKSUM.
+//!
+//! Unencrypted databases store a 4-byte `__ham_func4` hash of the page, computed with the
+//! checksum bytes themselves zeroed. Meta pages keep theirs inside `BTMETA` and only cover
+//! the first `DBMETASIZE` bytes; every other page keeps it right after the page header.
+
+use crate::{
+    constants::{DBMETASIZE, META_CHKSUM_OFF, PG_CHKSUM_OFF},
+    storage::types::Endianness,
+    util::u32e,
+};
+
+/// Page type codes of the meta pages (hash, btree, queue, heap).
+const META_PAGE_TYPES: [u8; 4] = [8, 9, 10, 14];
+
+/// Outcome of verifying a single page checksum.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChecksumStatus {
+    Valid,
+    Mismatch {
+        stored: u32,
+        computed: u32,
+    },
+    /// The page is too short to hold the checksum it should have.
+    Truncated,
+}
+
+/// Berkeley DB's `__ham_func4` (Chris Torek's hash), used as the non-HMAC page checksum.
+pub fn hash4(data: &[u8]) -> u32 {
+    data.iter().fold(0u32, |h, &b| {
+        (h << 5).wrapping_add(h).wrapping_add(b as u32)
+    })
+}
+
+/// Whether the raw page type byte denotes a meta page.
+pub fn is_meta_page_type(page_type: u8) -> bool {
+    META_PAGE_TYPES.contains(&page_type)
+}
+
+/// Returns the byte range covered by the checksum and the offset of the stored checksum.
+fn checksum_layout(page: &[u8]) -> Option<(usize, usize)> {
+    let page_type = *page.get(25)?;
+    if is_meta_page_type(page_type) {
+        Some((DBMETASIZE, META_CHKSUM_OFF))
+    } else {
+        Some((page.len(), PG_CHKSUM_OFF))
+    }
+}
+
+/// Verify the `__ham_func4` checksum of a page from a DB_CHKSUM database.
+/// The stored checksum is written in the byte order of the file.
+pub fn verify_page_checksum(page: &[u8], endianness: Endianness) -> ChecksumStatus {
+    let Some((covered, off)) = checksum_layout(page) else {
+        return ChecksumStatus::Truncated;
+    };
+    if page.len() < covered || off + 4 > covered {
+        return ChecksumStatus::Truncated;
+    }
+
+    let stored = u32e(endianness.into(), &page[off..off + 4]);
+    let mut scratch = page[..covered].to_vec();
+    scratch[off..off + 4].fill(0);
+    let computed = hash4(&scratch);
+
+    if stored == computed {
+        ChecksumStatus::Valid
+    } else {
+        ChecksumStatus::Mismatch { stored, computed }
+    }
+}
+
+/// Recompute and store the `__ham_func4` checksum of a page, in the given byte order.
+/// Pages too short to hold their checksum are left untouched.
+pub fn write_page_checksum(page: &mut [u8], endianness: Endianness) {
+    let Some((covered, off)) = checksum_layout(page) else {
+        return;
+    };
+    if page.len() < covered || off + 4 > covered {
+        return;
+    }
+    page[off..off + 4].fill(0);
+    let sum = hash4(&page[..covered]);
+    let bytes = match endianness {
+        Endianness::Little => sum.to_le_bytes(),
+        Endianness::Big => sum.to_be_bytes(),
+    };
+    page[off..off + 4].copy_from_slice(&bytes);
+}
@@ -0,0 +1,19 @@
+//! The order Berkeley DB keeps btree keys in.
+//!
+//! A database opened without `DB->set_bt_compare` sorts its keys with `__bam_defcmp`:
+//! bytes compare as unsigned, and when one key is a prefix of the other the shorter one
+//! sorts first. zcashd never sets a comparator, so every wallet tree is in this order,
+//! and keys that break it mean the page was damaged or rewritten by something else.
+
+use core::cmp::Ordering;
+
+/// Berkeley DB's default btree key comparator (`__bam_defcmp`).
+pub fn bt_compare(a: &[u8], b: &[u8]) -> Ordering {
+    let len = a.len().min(b.len());
+    for (x, y) in a[..len].iter().zip(&b[..len]) {
+        if x != y {
+            return x.cmp(y);
+        }
+    }
+    a.len().cmp(&b.len())
+}
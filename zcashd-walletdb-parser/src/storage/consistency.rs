@@ -1,9 +1,24 @@
-use std::io;
+use std::{collections::HashSet, fmt, io};
 
-use crate::storage::{
-    entry::{InMemoryMap, Provenance},
-    page::ValueSupplier,
-    types::{ByteVec, FormatProfile},
+pub use crate::error::ParseMode;
+use crate::{
+    constants::DBMETA_CHKSUM,
+    storage::{
+        btree::{B_OVERFLOW, BINTERNAL_SIZE, BOVERFLOW_SIZE, overflow_ref, parse_leaf_item},
+        checksum::{ChecksumStatus, verify_page_checksum},
+        compare::bt_compare,
+        encryption::DbCipher,
+        entry::{ConflictPolicy, InMemoryMap, Provenance},
+        flags::BtreeMetaFlags,
+        freelist::{FreeListIssue, walk_freelist},
+        orphans::analyze_reachability,
+        page::{LEAFLEVEL, PageHeader, PageProtection, PageType, ValueSupplier},
+        reader::{BTM_SUBDB, DataTree, FileDbImageReader},
+        slots::{SlotReport, SlotViolation, validate_slots},
+        supplier::{OverflowChainError, read_overflow_chain},
+        types::{ByteVec, Endianness, FormatProfile, PageNumber, PageSource},
+    },
+    util::{detect_endian, u32e},
 };
 
 /// Modes controlling how aggressively we read a possibly-dirty DB image.
@@ -13,6 +28,143 @@ pub enum SalvageMode {
     BestEffort,
 }
 
+/// How serious a consistency finding is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
+}
+
+/// The category of a consistency finding.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FindingKind {
+    /// The stored page checksum does not match the page contents.
+    ChecksumMismatch { stored: u32, computed: u32 },
+    /// The HMAC-SHA1 of an encrypted page does not verify.
+    HmacMismatch,
+    /// The page could not be read (short read, I/O error, truncated checksum).
+    PageUnreadable,
+    /// The image holds fewer pages than the meta page's `last_pgno` promises.
+    Truncated {
+        expected_pages: u64,
+        actual_pages: u64,
+    },
+    /// The meta page carries a btree version newer than any this crate knows.
+    UnknownVersion { version: u32 },
+    /// A meta page field is out of range or contradicts the image.
+    MetaInconsistent { field: &'static str },
+    /// The meta page failed its sanity checks and was rebuilt from the rest of the image,
+    /// using the subdatabase meta page `template` when there was one.
+    MetaRebuilt { template: Option<PageNumber> },
+    /// The page header names a different page number than the page's position.
+    PageNumberMismatch { stored: PageNumber },
+    /// The page type is not one the tree can hold at this position.
+    UnexpectedPageType { page_type: u8 },
+    /// The page level does not fit its position in the tree (leaves are level 1).
+    LevelMismatch { expected: u8, found: u8 },
+    /// A leaf's `prev_pgno`/`next_pgno` does not point at its neighbour in key order.
+    SiblingLink {
+        field: &'static str,
+        expected: PageNumber,
+        found: PageNumber,
+    },
+    /// A key on a leaf sorts before the key in the slot before it ([`bt_compare`]).
+    KeyOrder { slot: usize },
+    /// The first key of a leaf does not sort after the last key of the leaf before it.
+    SiblingKeyOrder { prev: PageNumber },
+    /// A page is reached twice while walking the trees.
+    DuplicateReference,
+    /// A btree leaf holds an odd number of slots, so one key has no data item.
+    UnpairedSlot,
+    /// The slot array violates the page layout.
+    BadSlot(SlotViolation),
+    /// An overflow chain referenced from the page cannot be read back.
+    OverflowChain(OverflowChainError),
+    /// A problem with the freelist.
+    FreeList(FreeListIssue),
+    /// An allocated page that nothing references.
+    OrphanPage { page_type: u8 },
+}
+
+/// A single problem detected in a DB image.
+#[derive(Debug, Clone)]
+pub struct Finding {
+    pub page_no: Option<PageNumber>,
+    pub severity: Severity,
+    pub kind: FindingKind,
+    pub message: String,
+}
+
+/// Verify the checksum of every page when the meta page advertises DB_CHKSUM.
+/// Returns no findings for databases created without checksums.
+///
+/// Encrypted databases carry keyed HMACs, so `cipher` is required for them; `source` must
+/// yield the pages as stored on disk (not through a `DecryptingPageSource`).
+pub fn verify_checksums(
+    source: &dyn PageSource,
+    cipher: Option<&DbCipher>,
+) -> io::Result<Vec<Finding>> {
+    let meta = source.read_page(0)?;
+    let endianness = detect_endian(&meta)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "meta page magic not found"))?
+        .into();
+    let encrypted = meta.get(24).is_some_and(|&alg| alg != 0);
+    if encrypted && cipher.is_none() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "encrypted database: page HMACs can only be verified with the passphrase",
+        ));
+    }
+    if !encrypted && meta.get(26).is_none_or(|f| f & DBMETA_CHKSUM == 0) {
+        return Ok(Vec::new());
+    }
+
+    let mut findings = Vec::new();
+    let mut page_no: PageNumber = 0;
+    loop {
+        if source.page_count().is_some_and(|n| page_no as u64 >= n) {
+            break;
+        }
+        let page = match source.read_page(page_no) {
+            Ok(page) => page,
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => break,
+            Err(e) => return Err(e),
+        };
+        if let Some(cipher) = cipher.filter(|_| encrypted) {
+            if !cipher.verify_hmac(&page) {
+                findings.push(Finding {
+                    page_no: Some(page_no),
+                    severity: Severity::Error,
+                    kind: FindingKind::HmacMismatch,
+                    message: "HMAC-SHA1 mismatch".to_string(),
+                });
+            }
+            page_no += 1;
+            continue;
+        }
+        match verify_page_checksum(&page, endianness) {
+            ChecksumStatus::Valid => {}
+            ChecksumStatus::Mismatch { stored, computed } => findings.push(Finding {
+                page_no: Some(page_no),
+                severity: Severity::Error,
+                kind: FindingKind::ChecksumMismatch { stored, computed },
+                message: format!(
+                    "checksum mismatch: stored 0x{stored:08x}, computed 0x{computed:08x}"
+                ),
+            }),
+            ChecksumStatus::Truncated => findings.push(Finding {
+                page_no: Some(page_no),
+                severity: Severity::Error,
+                kind: FindingKind::PageUnreadable,
+                message: "page too short to hold its checksum".to_string(),
+            }),
+        }
+        page_no += 1;
+    }
+    Ok(findings)
+}
+
 /// Exposes high-level operations that drive the parsing pipeline.
 pub trait DbImageReader {
     /// Probe meta page and produce a format profile.
@@ -25,6 +177,530 @@ pub trait DbImageReader {
         salvage: SalvageMode,
     ) -> Box<dyn Iterator<Item = (ByteVec, Box<dyn ValueSupplier>, Provenance)> + 's>;
 
-    /// Build an in-memory map eagerly using the entries iterator.
-    fn build_map(&self, salvage: SalvageMode) -> io::Result<Box<dyn InMemoryMap>>;
+    /// Build an in-memory map eagerly using the entries iterator. A key read more than
+    /// once keeps its newest copy ([`ConflictPolicy::HighestLsn`]).
+    fn build_map(&self, salvage: SalvageMode) -> io::Result<Box<dyn InMemoryMap>> {
+        self.build_map_with(salvage, ConflictPolicy::default())
+    }
+
+    /// Build an in-memory map, settling keys read more than once by `policy`.
+    fn build_map_with(
+        &self,
+        salvage: SalvageMode,
+        policy: ConflictPolicy,
+    ) -> io::Result<Box<dyn InMemoryMap>>;
+}
+
+/// The outcome of [`check`].
+#[derive(Debug, Clone, Default)]
+pub struct ConsistencyReport {
+    /// Tree pages visited by the structural walk.
+    pub pages_checked: usize,
+    pub findings: Vec<Finding>,
+}
+
+impl ConsistencyReport {
+    /// No warnings or errors; informational findings are allowed.
+    pub fn is_clean(&self) -> bool {
+        self.max_severity().is_none_or(|s| s == Severity::Info)
+    }
+
+    pub fn max_severity(&self) -> Option<Severity> {
+        self.findings.iter().map(|f| f.severity).max()
+    }
+
+    pub fn count(&self, severity: Severity) -> usize {
+        self.findings
+            .iter()
+            .filter(|f| f.severity == severity)
+            .count()
+    }
+
+    /// Findings about one page.
+    pub fn for_page(&self, pgno: PageNumber) -> impl Iterator<Item = &Finding> {
+        self.findings
+            .iter()
+            .filter(move |f| f.page_no == Some(pgno))
+    }
+}
+
+impl fmt::Display for ConsistencyReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "ConsistencyReport {{")?;
+        writeln!(f, "  pages checked: {}", self.pages_checked)?;
+        writeln!(f, "  errors       : {}", self.count(Severity::Error))?;
+        writeln!(f, "  warnings     : {}", self.count(Severity::Warning))?;
+        for finding in &self.findings {
+            let page = finding
+                .page_no
+                .map_or_else(|| "-".to_string(), |p| p.to_string());
+            writeln!(
+                f,
+                "  {:<13}: page {page}: {}",
+                format!("{:?}", finding.severity).to_lowercase(),
+                finding.message
+            )?;
+        }
+        write!(f, "}}")
+    }
+}
+
+/// Run every structural check over the image behind `reader`, like `db_verify`:
+///
+/// - meta page sanity (page number, `last_pgno` against the image size, root and
+///   freelist head in range, subdatabase meta pages),
+/// - page numbers, page types and levels along every tree,
+/// - `prev_pgno`/`next_pgno` links between neighbouring leaves,
+/// - key order on each btree leaf and from one leaf to the next ([`bt_compare`]),
+/// - slot arrays ([`validate_slots`]),
+/// - every overflow chain referenced from a tree,
+/// - the freelist, orphaned pages, and page checksums when the database has them.
+///
+/// Only failures to read the meta page are returned as errors; everything else is a
+/// finding in the report.
+pub fn check(reader: &FileDbImageReader) -> io::Result<ConsistencyReport> {
+    let mut checker = Checker {
+        reader,
+        endianness: reader.endianness(),
+        protection: reader.protection(),
+        visited: HashSet::from([0]),
+        report: ConsistencyReport::default(),
+    };
+    checker.report.findings.extend(reader.diagnostics());
+    checker.check_meta();
+    match reader.data_roots() {
+        Ok(trees) => {
+            for tree in trees {
+                checker.check_tree(tree);
+            }
+        }
+        Err(e) => checker.push(
+            None,
+            Severity::Error,
+            FindingKind::PageUnreadable,
+            e.to_string(),
+        ),
+    }
+    checker.check_freelist_and_orphans();
+    if checker.protection == PageProtection::Checksum {
+        let findings = verify_checksums(reader.source().as_ref(), None)?;
+        checker.report.findings.extend(findings);
+    }
+    Ok(checker.report)
+}
+
+struct Checker<'a> {
+    reader: &'a FileDbImageReader,
+    endianness: Endianness,
+    protection: PageProtection,
+    visited: HashSet<PageNumber>,
+    report: ConsistencyReport,
+}
+
+impl Checker<'_> {
+    fn push(
+        &mut self,
+        page_no: Option<PageNumber>,
+        severity: Severity,
+        kind: FindingKind,
+        message: String,
+    ) {
+        self.report.findings.push(Finding {
+            page_no,
+            severity,
+            kind,
+            message,
+        });
+    }
+
+    fn meta_error(&mut self, page_no: PageNumber, field: &'static str, message: String) {
+        self.push(
+            Some(page_no),
+            Severity::Error,
+            FindingKind::MetaInconsistent { field },
+            message,
+        );
+    }
+
+    fn check_meta(&mut self) {
+        let meta = self.reader.meta();
+        let (pgno, root, free, last_pgno) = (meta.pgno, meta.root, meta.free, meta.last_pgno);
+        if pgno != 0 {
+            self.meta_error(0, "pgno", format!("meta page claims pgno {pgno}"));
+        }
+        if root == 0 || root > last_pgno {
+            self.meta_error(0, "root", format!("root {root} outside 1..={last_pgno}"));
+        }
+        if free > last_pgno {
+            self.meta_error(
+                0,
+                "free",
+                format!("freelist head {free} past last_pgno {last_pgno}"),
+            );
+        }
+        let flags = BtreeMetaFlags(meta.flags);
+        if flags.unknown() != 0 || !flags.is_consistent() {
+            self.push(
+                Some(0),
+                Severity::Warning,
+                FindingKind::MetaInconsistent { field: "flags" },
+                format!("btree flags {flags} are not a combination Berkeley DB writes"),
+            );
+        }
+        // Truncation is already among the reader's diagnostics.
+        if let Some(pages) = self.reader.source().page_count()
+            && pages > last_pgno as u64 + 1
+        {
+            self.push(
+                Some(0),
+                Severity::Info,
+                FindingKind::MetaInconsistent { field: "last_pgno" },
+                format!("image holds {pages} pages, last_pgno is {last_pgno}"),
+            );
+        }
+        if meta.flags & BTM_SUBDB == 0 {
+            return;
+        }
+        let subs = match self.reader.subdatabases() {
+            Ok(subs) => subs,
+            Err(e) => {
+                self.push(
+                    None,
+                    Severity::Error,
+                    FindingKind::PageUnreadable,
+                    e.to_string(),
+                );
+                return;
+            }
+        };
+        for sub in subs {
+            self.visited.insert(sub.meta_pgno);
+            let name = String::from_utf8_lossy(&sub.name).into_owned();
+            match self.reader.source().read_page(sub.meta_pgno) {
+                Ok(page) if page.get(25) != Some(&9) => self.push(
+                    Some(sub.meta_pgno),
+                    Severity::Error,
+                    FindingKind::UnexpectedPageType {
+                        page_type: page.get(25).copied().unwrap_or(0),
+                    },
+                    format!("subdatabase {name:?}: meta page is not a btree meta page"),
+                ),
+                Ok(_) => {}
+                Err(e) => self.push(
+                    Some(sub.meta_pgno),
+                    Severity::Error,
+                    FindingKind::PageUnreadable,
+                    format!("subdatabase {name:?}: {e}"),
+                ),
+            }
+            if sub.root == 0 || sub.root > last_pgno {
+                self.meta_error(
+                    sub.meta_pgno,
+                    "root",
+                    format!(
+                        "subdatabase {name:?}: root {} outside 1..={last_pgno}",
+                        sub.root
+                    ),
+                );
+            }
+        }
+    }
+
+    /// Walk one tree depth-first, checking each page and collecting the leaf level in key
+    /// order for the sibling check.
+    fn check_tree(&mut self, tree: DataTree) {
+        let (internal, leaf) = match tree.recno {
+            Some(_) => (PageType::RecnoInternal, PageType::RecnoLeaf),
+            None => (PageType::BtreeInternal, PageType::BtreeLeaf),
+        };
+        let mut leaves: Vec<(PageNumber, PageNumber, PageNumber)> = Vec::new();
+        // First and last key of each non-empty btree leaf, in key order.
+        let mut bounds: Vec<(PageNumber, ByteVec, ByteVec)> = Vec::new();
+        let mut stack: Vec<(PageNumber, Option<u8>)> = vec![(tree.root, None)];
+        while let Some((pgno, expected_level)) = stack.pop() {
+            if !self.visited.insert(pgno) {
+                self.push(
+                    Some(pgno),
+                    Severity::Error,
+                    FindingKind::DuplicateReference,
+                    format!("page {pgno} is referenced more than once"),
+                );
+                continue;
+            }
+            let page = match self.reader.source().read_page(pgno) {
+                Ok(page) => page,
+                Err(e) => {
+                    self.push(
+                        Some(pgno),
+                        Severity::Error,
+                        FindingKind::PageUnreadable,
+                        e.to_string(),
+                    );
+                    continue;
+                }
+            };
+            let hdr = match PageHeader::parse(&page, self.endianness, self.protection) {
+                Ok(hdr) => hdr,
+                Err(e) => {
+                    self.push(
+                        Some(pgno),
+                        Severity::Error,
+                        FindingKind::PageUnreadable,
+                        e.to_string(),
+                    );
+                    continue;
+                }
+            };
+            self.report.pages_checked += 1;
+            if hdr.pgno != pgno {
+                self.push(
+                    Some(pgno),
+                    Severity::Error,
+                    FindingKind::PageNumberMismatch { stored: hdr.pgno },
+                    format!("header names page {}", hdr.pgno),
+                );
+            }
+            let kind = hdr.kind();
+            if kind != internal && kind != leaf {
+                self.push(
+                    Some(pgno),
+                    Severity::Error,
+                    FindingKind::UnexpectedPageType {
+                        page_type: hdr.page_type,
+                    },
+                    format!("expected a {internal:?} or {leaf:?} page, found {kind:?}"),
+                );
+                continue;
+            }
+            let level_ok = hdr.check_level().is_ok();
+            let expected = expected_level.unwrap_or(hdr.level);
+            if !level_ok || hdr.level != expected {
+                let expected = if level_ok {
+                    expected
+                } else if kind == leaf {
+                    LEAFLEVEL
+                } else {
+                    LEAFLEVEL + 1
+                };
+                self.push(
+                    Some(pgno),
+                    Severity::Error,
+                    FindingKind::LevelMismatch {
+                        expected,
+                        found: hdr.level,
+                    },
+                    format!("{kind:?} page at level {}, expected {expected}", hdr.level),
+                );
+            }
+
+            let slots = validate_slots(&page, &hdr, self.endianness, self.protection);
+            for v in &slots.violations {
+                self.push(
+                    Some(pgno),
+                    Severity::Error,
+                    FindingKind::BadSlot(v.clone()),
+                    v.to_string(),
+                );
+            }
+            let e = self.endianness.into();
+            let usable = (0..slots.offsets.len())
+                .filter(|&i| slots.is_usable(i))
+                .map(|i| slots.offsets[i]);
+            if kind == internal {
+                let mut children = Vec::new();
+                for off in usable {
+                    // BINTERNAL keeps the child at +4, RINTERNAL at +0.
+                    let at = if kind == PageType::BtreeInternal {
+                        off + 4
+                    } else {
+                        off
+                    };
+                    children.push(u32e(e, &page[at..at + 4]));
+                    if kind == PageType::BtreeInternal && page[off + 2] & 0x7f == B_OVERFLOW {
+                        let at = off + BINTERNAL_SIZE;
+                        if let Some(item) = page.get(at..at + BOVERFLOW_SIZE) {
+                            self.check_overflow(pgno, item);
+                        }
+                    }
+                }
+                let child_level = hdr.level.saturating_sub(1);
+                stack.extend(children.into_iter().rev().map(|c| (c, Some(child_level))));
+            } else {
+                if kind == PageType::BtreeLeaf && !slots.offsets.len().is_multiple_of(2) {
+                    self.push(
+                        Some(pgno),
+                        Severity::Error,
+                        FindingKind::UnpairedSlot,
+                        format!("odd number of slots ({})", slots.offsets.len()),
+                    );
+                }
+                for off in usable {
+                    if page[off + 2] & 0x7f == B_OVERFLOW {
+                        self.check_overflow(pgno, &page[off..off + BOVERFLOW_SIZE]);
+                    }
+                }
+                if kind == PageType::BtreeLeaf
+                    && let Some(first_last) = self.check_key_order(pgno, &page, &slots)
+                {
+                    bounds.push((pgno, first_last.0, first_last.1));
+                }
+                leaves.push((pgno, hdr.prev_pgno, hdr.next_pgno));
+            }
+        }
+        self.check_siblings(&leaves);
+        for pair in bounds.windows(2) {
+            let ((prev, _, last), (pgno, first, _)) = (&pair[0], &pair[1]);
+            if bt_compare(last, first).is_gt() {
+                self.push(
+                    Some(*pgno),
+                    Severity::Error,
+                    FindingKind::SiblingKeyOrder { prev: *prev },
+                    format!("first key sorts before the last key of page {prev}"),
+                );
+            }
+        }
+    }
+
+    /// Report keys on a btree leaf that sort before the key in the slot before them, and
+    /// return the first and last key for the check across leaves. Keys that cannot be
+    /// read are left out; their slots or overflow chains are reported elsewhere.
+    fn check_key_order(
+        &mut self,
+        pgno: PageNumber,
+        page: &[u8],
+        slots: &SlotReport,
+    ) -> Option<(ByteVec, ByteVec)> {
+        let mut first_last: Option<(ByteVec, ByteVec)> = None;
+        for slot in (0..slots.offsets.len()).step_by(2) {
+            if !slots.is_usable(slot) {
+                continue;
+            }
+            let Some(key) = self.leaf_key(page, slots.offsets[slot]) else {
+                continue;
+            };
+            match &mut first_last {
+                None => first_last = Some((key.clone(), key)),
+                Some((_, last)) => {
+                    if bt_compare(last, &key).is_gt() {
+                        self.push(
+                            Some(pgno),
+                            Severity::Error,
+                            FindingKind::KeyOrder { slot },
+                            format!("key in slot {slot} sorts before the key before it"),
+                        );
+                    }
+                    *last = key;
+                }
+            }
+        }
+        first_last
+    }
+
+    fn leaf_key(&self, page: &[u8], off: usize) -> Option<ByteVec> {
+        let item = parse_leaf_item(page, off, self.endianness).ok()?;
+        let bytes = &page[item.range.0..item.range.1];
+        if item.kind == B_OVERFLOW {
+            let r = overflow_ref(bytes, self.endianness);
+            let source = self.reader.source().as_ref();
+            read_overflow_chain(source, r, self.endianness, self.protection).ok()
+        } else {
+            Some(bytes.to_vec())
+        }
+    }
+
+    fn check_overflow(&mut self, pgno: PageNumber, item: &[u8]) {
+        let r = overflow_ref(item, self.endianness);
+        let source = self.reader.source().as_ref();
+        if let Err(e) = read_overflow_chain(source, r, self.endianness, self.protection) {
+            let kind = e
+                .get_ref()
+                .and_then(|inner| inner.downcast_ref::<OverflowChainError>())
+                .map_or(FindingKind::PageUnreadable, |c| {
+                    FindingKind::OverflowChain(c.clone())
+                });
+            self.push(
+                Some(pgno),
+                Severity::Error,
+                kind,
+                format!("overflow chain from page {}: {e}", r.first_page),
+            );
+        }
+    }
+
+    /// Leaves must form a doubly linked list in key order, open at both ends.
+    fn check_siblings(&mut self, leaves: &[(PageNumber, PageNumber, PageNumber)]) {
+        for (i, &(pgno, prev, next)) in leaves.iter().enumerate() {
+            let expected_prev = if i == 0 { 0 } else { leaves[i - 1].0 };
+            let expected_next = leaves.get(i + 1).map_or(0, |l| l.0);
+            for (field, expected, found) in [
+                ("prev_pgno", expected_prev, prev),
+                ("next_pgno", expected_next, next),
+            ] {
+                if expected != found {
+                    self.push(
+                        Some(pgno),
+                        Severity::Error,
+                        FindingKind::SiblingLink {
+                            field,
+                            expected,
+                            found,
+                        },
+                        format!("{field} is {found}, expected {expected}"),
+                    );
+                }
+            }
+        }
+    }
+
+    fn check_freelist_and_orphans(&mut self) {
+        let source = self.reader.source().as_ref();
+        match walk_freelist(source) {
+            Ok(freelist) => {
+                for issue in freelist.issues {
+                    let page_no = match &issue {
+                        FreeListIssue::Cycle { from, .. }
+                        | FreeListIssue::OutOfRange { from, .. } => *from,
+                        FreeListIssue::NotFree { pgno, .. }
+                        | FreeListIssue::Unreadable { pgno, .. } => *pgno,
+                    };
+                    self.push(
+                        Some(page_no),
+                        Severity::Warning,
+                        FindingKind::FreeList(issue.clone()),
+                        issue.to_string(),
+                    );
+                }
+            }
+            Err(e) => self.push(
+                Some(0),
+                Severity::Warning,
+                FindingKind::PageUnreadable,
+                e.to_string(),
+            ),
+        }
+        match analyze_reachability(self.reader) {
+            Ok(reachability) => {
+                for orphan in reachability.orphans {
+                    self.push(
+                        Some(orphan.pgno),
+                        Severity::Warning,
+                        FindingKind::OrphanPage {
+                            page_type: orphan.page_type,
+                        },
+                        format!(
+                            "{:?} page with {} entries is not referenced",
+                            PageType::from(orphan.page_type),
+                            orphan.entries
+                        ),
+                    );
+                }
+            }
+            Err(e) => self.push(
+                None,
+                Severity::Warning,
+                FindingKind::PageUnreadable,
+                e.to_string(),
+            ),
+        }
+    }
 }
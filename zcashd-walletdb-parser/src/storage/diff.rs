@@ -0,0 +1,192 @@
+//! Page-by-page comparison of two images, e.g. a wallet and its backup.
+//!
+//! Berkeley DB stamps every page it writes with the LSN of the last log record that
+//! touched it, so comparing LSNs tells which copy of a page is newer, and comparing
+//! content hashes tells whether the pages differ at all. Nothing is decoded beyond the
+//! page header.
+
+use std::{cmp::Ordering, fmt, io};
+
+use crate::{
+    crypto::{Digest, sha1::Sha1},
+    storage::{
+        lsn::format_lsn,
+        types::{Endianness, LogSequenceNumber, PageNumber, PageSource},
+    },
+    util::{detect_endian, u32e},
+};
+
+/// LSN and SHA-1 of one copy of a page.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PageState {
+    pub lsn: LogSequenceNumber,
+    pub hash: Vec<u8>,
+}
+
+impl PageState {
+    fn of(page: &[u8], e: Endianness) -> Self {
+        let lsn = match page.get(0..8) {
+            Some(b) => ((u32e(e.into(), &b[0..4]) as u64) << 32) | u32e(e.into(), &b[4..8]) as u64,
+            None => 0,
+        };
+        PageState {
+            lsn,
+            hash: Sha1::digest(page),
+        }
+    }
+}
+
+/// A page that differs between the two images. `a` or `b` is `None` when the page only
+/// exists in the other image.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PageDelta {
+    pub pgno: PageNumber,
+    pub a: Option<PageState>,
+    pub b: Option<PageState>,
+}
+
+impl PageDelta {
+    /// How the LSN of the `b` copy compares to the `a` copy. A page present in only one
+    /// image counts as newer there.
+    pub fn lsn_order(&self) -> Ordering {
+        match (&self.a, &self.b) {
+            (Some(a), Some(b)) => b.lsn.cmp(&a.lsn),
+            (Some(_), None) => Ordering::Less,
+            (None, _) => Ordering::Greater,
+        }
+    }
+
+    /// Both copies carry the same LSN but different bytes. Berkeley DB only produces this
+    /// after resetting LSNs (every page then reads `[0][1]`, as in a wallet detached from
+    /// its environment); otherwise the page was changed outside the log, or is damaged.
+    pub fn is_unlogged(&self) -> bool {
+        matches!((&self.a, &self.b), (Some(a), Some(b)) if a.lsn == b.lsn)
+    }
+}
+
+impl fmt::Display for PageDelta {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (&self.a, &self.b) {
+            (Some(a), Some(b)) => {
+                write!(
+                    f,
+                    "changed, lsn {} -> {}",
+                    format_lsn(a.lsn),
+                    format_lsn(b.lsn)
+                )?;
+                if self.is_unlogged() {
+                    write!(f, " (same lsn)")?;
+                }
+                Ok(())
+            }
+            (Some(a), None) => write!(f, "only in a, lsn {}", format_lsn(a.lsn)),
+            (None, Some(b)) => write!(f, "only in b, lsn {}", format_lsn(b.lsn)),
+            (None, None) => write!(f, "in neither image"),
+        }
+    }
+}
+
+/// Result of [`diff_pages`].
+#[derive(Debug, Clone, Default)]
+pub struct PageDiff {
+    pub pages_a: u64,
+    pub pages_b: u64,
+    /// Pages whose bytes are identical in both images.
+    pub unchanged: u64,
+    /// Every other page, in page order.
+    pub changes: Vec<PageDelta>,
+}
+
+impl PageDiff {
+    pub fn is_identical(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    /// Whether every page that differs is newer in `b` or only exists there, i.e. `a` is
+    /// an older snapshot of `b`. With `a` a backup and `b` the live wallet,
+    /// [`Self::is_identical`] means the backup is current and this means it is stale.
+    pub fn b_is_newer(&self) -> bool {
+        !self.changes.is_empty()
+            && self
+                .changes
+                .iter()
+                .all(|d| d.lsn_order() == Ordering::Greater)
+    }
+}
+
+impl fmt::Display for PageDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "PageDiff {{")?;
+        writeln!(f, "  pages        : {} / {}", self.pages_a, self.pages_b)?;
+        writeln!(f, "  unchanged    : {}", self.unchanged)?;
+        writeln!(f, "  changed      : {}", self.changes.len())?;
+        for delta in &self.changes {
+            writeln!(f, "  {:<12} : {delta}", format!("page {}", delta.pgno))?;
+        }
+        write!(f, "}}")
+    }
+}
+
+/// Byte order of the image behind `source`, from the magic on its meta page.
+fn endianness_of(source: &dyn PageSource) -> io::Result<Endianness> {
+    let page0 = source.read_page(0)?;
+    detect_endian(&page0).map(Endianness::from).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{}: page 0 is not a btree meta page", source.source_id()),
+        )
+    })
+}
+
+/// Compare `a` and `b` page by page. Both images must use the same page size. Pages are
+/// matched by page number, so a page moved by compaction shows up as two changes.
+pub fn diff_pages(a: &dyn PageSource, b: &dyn PageSource) -> io::Result<PageDiff> {
+    let (ea, eb) = (endianness_of(a)?, endianness_of(b)?);
+    let (pages_a, pages_b) = match (a.page_count(), b.page_count()) {
+        (Some(pa), Some(pb)) => (pa, pb),
+        _ => {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "both images need a known page count",
+            ));
+        }
+    };
+    let mut diff = PageDiff {
+        pages_a,
+        pages_b,
+        ..PageDiff::default()
+    };
+    for pgno in 0..pages_a.max(pages_b) {
+        let pgno = pgno as PageNumber;
+        let pa = (u64::from(pgno) < pages_a)
+            .then(|| a.read_page(pgno))
+            .transpose()?;
+        let pb = (u64::from(pgno) < pages_b)
+            .then(|| b.read_page(pgno))
+            .transpose()?;
+        if let (Some(pa), Some(pb)) = (&pa, &pb) {
+            if pa.len() != pb.len() {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!(
+                        "page sizes differ: {} bytes in {}, {} in {}",
+                        pa.len(),
+                        a.source_id(),
+                        pb.len(),
+                        b.source_id()
+                    ),
+                ));
+            }
+            if pa == pb {
+                diff.unchanged += 1;
+                continue;
+            }
+        }
+        diff.changes.push(PageDelta {
+            pgno,
+            a: pa.map(|p| PageState::of(&p, ea)),
+            b: pb.map(|p| PageState::of(&p, eb)),
+        });
+    }
+    Ok(diff)
+}
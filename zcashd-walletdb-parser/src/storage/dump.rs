@@ -0,0 +1,381 @@
+//! Annotated hexdumps of single pages, for working out why a page does not parse.
+//!
+//! [`Page::annotated_dump`] splits a page into the fields Berkeley DB stores there (header,
+//! checksum or crypto block, slot array, the parts of each item) and prints each one as
+//! hex with its meaning alongside. Bytes no field accounts for are printed unannotated;
+//! runs of zeros are collapsed.
+
+use std::{fmt::Write, io};
+
+use crate::{
+    constants::{
+        DB_MAC_KEY, META_CHKSUM_OFF, META_CRYPTO_MAGIC_OFF, META_IV_OFF, PG_CHKSUM_OFF, SIZEOF_PAGE,
+    },
+    storage::{
+        btree::{
+            B_BLOB, B_DELETE, B_DUPLICATE, B_KEYDATA, B_OVERFLOW, BINTERNAL_SIZE, BOVERFLOW_SIZE,
+        },
+        page::{Page, PageHeader, PageProtection, PageType},
+        types::{ByteVec, Endianness},
+    },
+    util::{u16e, u32e, u64e},
+};
+
+/// Bytes shown per line.
+const WIDTH: usize = 16;
+
+/// A byte range of the page and what it holds.
+struct Field {
+    start: usize,
+    end: usize,
+    label: String,
+}
+
+/// Collects the fields of one page.
+struct Layout<'a> {
+    page: &'a [u8],
+    e: Endianness,
+    fields: Vec<Field>,
+}
+
+impl Layout<'_> {
+    fn add(&mut self, start: usize, len: usize, label: impl Into<String>) {
+        self.fields.push(Field {
+            start,
+            end: start + len,
+            label: label.into(),
+        });
+    }
+
+    fn u16_at(&self, at: usize) -> Option<u16> {
+        self.page.get(at..at + 2).map(|b| u16e(self.e.into(), b))
+    }
+
+    fn u32_at(&self, at: usize) -> Option<u32> {
+        self.page.get(at..at + 4).map(|b| u32e(self.e.into(), b))
+    }
+
+    fn u64_at(&self, at: usize) -> Option<u64> {
+        self.page.get(at..at + 8).map(|b| u64e(self.e.into(), b))
+    }
+
+    /// The `u32` at `at` as a labelled field.
+    fn add_u32(&mut self, at: usize, name: &str) {
+        if let Some(v) = self.u32_at(at) {
+            self.add(at, 4, format!("{name} = {v}"));
+        }
+    }
+
+    fn header(&mut self, hdr: &PageHeader) {
+        self.add(
+            0,
+            8,
+            format!("lsn = {}/{}", hdr.lsn >> 32, hdr.lsn & 0xffff_ffff),
+        );
+        self.add(8, 4, format!("pgno = {}", hdr.pgno));
+        if hdr.kind() == PageType::Meta {
+            return;
+        }
+        self.add(12, 4, format!("prev_pgno = {}", hdr.prev_pgno));
+        self.add(16, 4, format!("next_pgno = {}", hdr.next_pgno));
+        self.add(20, 2, format!("entries = {}", hdr.entries));
+        self.add(22, 2, format!("hf_offset = {}", hdr.hf_offset));
+        self.add(24, 1, format!("level = {}", hdr.level));
+        self.add(
+            25,
+            1,
+            format!("type = {} ({:?})", hdr.page_type, hdr.kind()),
+        );
+    }
+
+    fn protection(&mut self, protection: PageProtection) {
+        match protection {
+            PageProtection::None => {}
+            PageProtection::Checksum => {
+                self.add(SIZEOF_PAGE, 2, "unused");
+                self.add(PG_CHKSUM_OFF, 4, "chksum");
+            }
+            PageProtection::Encrypted => {
+                self.add(SIZEOF_PAGE, 2, "unused");
+                self.add(PG_CHKSUM_OFF, DB_MAC_KEY, "hmac");
+                self.add(PG_CHKSUM_OFF + DB_MAC_KEY, 16, "iv");
+            }
+        }
+    }
+
+    /// The btree meta page fields (`BTMETA`).
+    fn meta(&mut self) {
+        self.add_u32(12, "magic");
+        self.add_u32(16, "version");
+        self.add_u32(20, "pagesize");
+        self.add(24, 1, format!("encrypt_alg = {}", self.page[24]));
+        self.add(25, 1, format!("type = {}", self.page[25]));
+        self.add(26, 1, format!("metaflags = 0x{:02x}", self.page[26]));
+        self.add(27, 1, "unused");
+        self.add_u32(28, "free");
+        self.add_u32(32, "last_pgno");
+        self.add_u32(36, "nparts");
+        self.add_u32(40, "key_count");
+        self.add_u32(44, "record_count");
+        if let Some(flags) = self.u32_at(48) {
+            self.add(48, 4, format!("flags = 0x{flags:x}"));
+        }
+        self.add(52, 20, "uid");
+        self.add_u32(72, "unused");
+        self.add_u32(76, "minkey");
+        self.add_u32(80, "re_len");
+        self.add_u32(84, "re_pad");
+        self.add_u32(88, "root");
+        if self.page.len() >= META_CHKSUM_OFF + DB_MAC_KEY {
+            self.add_u32(META_CRYPTO_MAGIC_OFF, "crypto_magic");
+            self.add(META_IV_OFF, 16, "iv");
+            self.add(META_CHKSUM_OFF, DB_MAC_KEY, "chksum");
+        }
+    }
+
+    /// The slot array and the item each slot points to.
+    fn slots(&mut self, hdr: &PageHeader, protection: PageProtection) {
+        let start = protection.header_size();
+        for slot in 0..hdr.num_slots() {
+            let at = start + slot * 2;
+            let Some(off) = self.u16_at(at).map(usize::from) else {
+                break;
+            };
+            self.add(at, 2, format!("slot[{slot}] = {off}"));
+            let role = match hdr.kind() {
+                PageType::BtreeLeaf if slot % 2 == 0 => "key",
+                PageType::BtreeLeaf => "value",
+                PageType::RecnoLeaf => "record",
+                _ => "item",
+            };
+            let name = format!("[{slot}] {role}");
+            match hdr.kind() {
+                PageType::BtreeInternal => self.internal_item(off, &name),
+                PageType::RecnoInternal => {
+                    self.add_u32(off, &format!("{name} pgno"));
+                    self.add_u32(off + 4, &format!("{name} nrecs"));
+                }
+                _ => self.leaf_item(off, &name),
+            }
+        }
+    }
+
+    fn leaf_item(&mut self, off: usize, name: &str) {
+        let Some(&raw) = self.page.get(off + 2) else {
+            self.add(
+                off.min(self.page.len()),
+                0,
+                format!("{name}: past the page"),
+            );
+            return;
+        };
+        let kind = raw & !B_DELETE;
+        let deleted = if raw & B_DELETE != 0 { "|DELETE" } else { "" };
+        match kind {
+            B_KEYDATA => {
+                let len = self.u16_at(off).map_or(0, usize::from);
+                self.add(off, 2, format!("{name} len = {len}"));
+                self.add(off + 2, 1, format!("{name} type = KEYDATA{deleted}"));
+                let end = (off + 3 + len).min(self.page.len());
+                let cut = if end < off + 3 + len {
+                    " (runs past the page)"
+                } else {
+                    ""
+                };
+                self.add(off + 3, end - (off + 3), format!("{name} data{cut}"));
+            }
+            B_OVERFLOW | B_DUPLICATE => {
+                let what = if kind == B_OVERFLOW {
+                    "OVERFLOW"
+                } else {
+                    "DUPLICATE"
+                };
+                self.add(off, 2, format!("{name} unused"));
+                self.add(off + 2, 1, format!("{name} type = {what}{deleted}"));
+                self.add(off + 3, 1, format!("{name} unused"));
+                self.add_u32(off + 4, &format!("{name} pgno"));
+                self.add_u32(off + 8, &format!("{name} tlen"));
+            }
+            B_BLOB => {
+                let len = self.u16_at(off).unwrap_or_default();
+                self.add(off, 2, format!("{name} len = {len}"));
+                self.add(off + 2, 1, format!("{name} type = BLOB{deleted}"));
+                self.add(
+                    off + 3,
+                    1,
+                    format!("{name} encoding = {}", self.page[off + 3]),
+                );
+                self.add(off + 4, 12, format!("{name} unused"));
+                for (at, field) in [(16, "id"), (24, "size"), (32, "file_id"), (40, "sdb_id")] {
+                    if let Some(v) = self.u64_at(off + at) {
+                        self.add(off + at, 8, format!("{name} {field} = {v}"));
+                    }
+                }
+            }
+            other => self.add(off + 2, 1, format!("{name} type = {other} (unknown)")),
+        }
+    }
+
+    fn internal_item(&mut self, off: usize, name: &str) {
+        let Some(len) = self.u16_at(off).map(usize::from) else {
+            self.add(
+                off.min(self.page.len()),
+                0,
+                format!("{name}: past the page"),
+            );
+            return;
+        };
+        let raw = self.page.get(off + 2).copied().unwrap_or_default();
+        let kind = match raw & !B_DELETE {
+            B_KEYDATA => "KEYDATA",
+            B_OVERFLOW => "OVERFLOW",
+            _ => "unknown",
+        };
+        self.add(off, 2, format!("{name} len = {len}"));
+        self.add(off + 2, 1, format!("{name} type = {kind}"));
+        self.add(off + 3, 1, format!("{name} unused"));
+        self.add_u32(off + 4, &format!("{name} pgno"));
+        self.add_u32(off + 8, &format!("{name} nrecs"));
+        let data = off + BINTERNAL_SIZE;
+        if raw & !B_DELETE == B_OVERFLOW {
+            self.add(data, BOVERFLOW_SIZE, format!("{name} overflow ref"));
+        } else if len > 0 {
+            let end = (data + len).min(self.page.len());
+            self.add(data, end.saturating_sub(data), format!("{name} key"));
+        }
+    }
+
+    /// Print every field, and the bytes between them, in page order.
+    fn render(mut self, out: &mut String) {
+        self.fields.sort_by_key(|f| (f.start, f.end));
+        let len = self.page.len();
+        let mut at = 0;
+        for field in &self.fields {
+            let start = field.start.min(len);
+            let end = field.end.min(len);
+            if start > at {
+                gap(out, self.page, at, start);
+            }
+            let overlap = if start < at { " (overlaps)" } else { "" };
+            hex_lines(
+                out,
+                self.page,
+                start,
+                end,
+                &format!("{}{overlap}", field.label),
+            );
+            at = at.max(end);
+        }
+        if at < len {
+            gap(out, self.page, at, len);
+        }
+    }
+}
+
+/// Bytes `start..end` that no field covers, with runs of zeros longer than two lines
+/// collapsed.
+fn gap(out: &mut String, page: &[u8], start: usize, end: usize) {
+    let mut at = start;
+    while at < end {
+        let zeros = page[at..end].iter().take_while(|&&b| b == 0).count();
+        if zeros > 2 * WIDTH {
+            let _ = writeln!(out, "{at:04x}  .. {zeros} zero bytes");
+            at += zeros;
+            continue;
+        }
+        // Up to the next long run of zeros.
+        let mut next = at + zeros.max(1);
+        while next < end {
+            let run = page[next..end].iter().take_while(|&&b| b == 0).count();
+            if run > 2 * WIDTH {
+                break;
+            }
+            next += run.max(1);
+        }
+        hex_lines(out, page, at, next, "");
+        at = next;
+    }
+}
+
+/// `page[start..end]` as hex and ASCII, `label` on the first line.
+fn hex_lines(out: &mut String, page: &[u8], start: usize, end: usize, label: &str) {
+    if start == end {
+        let _ = writeln!(
+            out,
+            "{start:04x}  {:width$}  {label}",
+            "",
+            width = WIDTH * 3 + WIDTH + 2
+        );
+        return;
+    }
+    for (i, chunk) in page[start..end].chunks(WIDTH).enumerate() {
+        let hex: String = chunk.iter().map(|b| format!("{b:02x} ")).collect();
+        let ascii: String = chunk
+            .iter()
+            .map(|&b| {
+                if b.is_ascii_graphic() || b == b' ' {
+                    b as char
+                } else {
+                    '.'
+                }
+            })
+            .collect();
+        let label = if i == 0 { label } else { "" };
+        let _ = writeln!(
+            out,
+            "{:04x}  {hex:width$} |{ascii:WIDTH$}|  {label}",
+            start + i * WIDTH,
+            width = WIDTH * 3
+        );
+    }
+}
+
+impl Page {
+    /// Parse the header of `raw`, a whole page.
+    pub fn parse(
+        raw: ByteVec,
+        endianness: Endianness,
+        protection: PageProtection,
+    ) -> io::Result<Self> {
+        let header = PageHeader::parse(&raw, endianness, protection)?;
+        Ok(Page { header, raw })
+    }
+
+    /// Render the page as a hexdump with every header field, the slot array and the
+    /// parts of each item (length, type and `B_DELETE` flag, payload or reference)
+    /// labelled. Slots pointing past the page are reported rather than followed.
+    pub fn annotated_dump(&self, endianness: Endianness, protection: PageProtection) -> String {
+        let hdr = &self.header;
+        let mut layout = Layout {
+            page: &self.raw,
+            e: endianness,
+            fields: Vec::new(),
+        };
+        layout.header(hdr);
+        let mut out = format!(
+            "page {} ({:?}, {} bytes)\n",
+            hdr.pgno,
+            hdr.kind(),
+            self.raw.len()
+        );
+        match hdr.kind() {
+            PageType::Meta => layout.meta(),
+            PageType::Overflow => {
+                layout.protection(protection);
+                let start = protection.header_size();
+                let end = (start + hdr.upper_bound()).min(self.raw.len());
+                layout.add(start, end.saturating_sub(start), "overflow payload");
+            }
+            PageType::BtreeLeaf
+            | PageType::BtreeInternal
+            | PageType::RecnoLeaf
+            | PageType::RecnoInternal => {
+                layout.protection(protection);
+                layout.slots(hdr, protection);
+            }
+            _ => layout.protection(protection),
+        }
+        layout.render(&mut out);
+        out
+    }
+}
@@ -1,4 +1,29 @@
-use crate::storage::types::{ByteVec, PageNumber};
+use alloc::{
+    boxed::Box,
+    collections::{BTreeMap, btree_map::Entry},
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::{fmt, ops::Bound};
+
+use crate::{
+    entry::parser::walletdb_key_prefix,
+    storage::types::{ByteVec, LogSequenceNumber, PageNumber},
+    util::hex,
+};
+
+/// How much a recovered entry can be trusted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub enum Confidence {
+    /// Value is incomplete (e.g. a broken overflow chain was cut short).
+    Low,
+    /// Entry parsed cleanly, but came from a page with structural anomalies.
+    Medium,
+    /// Entry came from a structurally sound page and is complete.
+    #[default]
+    High,
+}
 
 /// Provenance metadata for debugging or salvaging.
 #[derive(Debug, Clone)]
@@ -6,6 +31,7 @@ pub struct Provenance {
     pub source_id: String,
     pub page_no: PageNumber,
     pub slot_index: u16,
+    pub confidence: Confidence,
 }
 
 /// Map entry stored in-memory. Value may be owned or materialized lazily.
@@ -14,8 +40,53 @@ pub struct MapEntry {
     pub key: ByteVec,
     pub value: ByteVec,
     pub meta: Option<Provenance>,
+    /// Other copies of the key, kept under [`ConflictPolicy::KeepAll`] in the order they
+    /// were read.
+    pub duplicates: Vec<MapEntry>,
+}
+
+/// What to do when a key is read more than once, e.g. a stale copy left on a page that
+/// was split or freed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ConflictPolicy {
+    /// Keep the copy from the page with the highest LSN, i.e. the one written last. Ties
+    /// and copies whose page LSN is unknown go to the copy read last.
+    #[default]
+    HighestLsn,
+    /// Keep the first copy read as the value and every later one in
+    /// [`MapEntry::duplicates`].
+    KeepAll,
+    /// Fail on the first duplicate.
+    Error,
+}
+
+/// A key read twice under [`ConflictPolicy::Error`].
+#[derive(Debug, Clone)]
+pub struct DuplicateKey {
+    pub key: ByteVec,
+    pub first: Option<Provenance>,
+    pub second: Option<Provenance>,
 }
 
+impl fmt::Display for DuplicateKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let page = |p: &Option<Provenance>| {
+            p.as_ref().map_or("an unknown page".to_string(), |p| {
+                format!("page {}", p.page_no)
+            })
+        };
+        write!(
+            f,
+            "key {} is stored on both {} and {}",
+            hex(&self.key),
+            page(&self.first),
+            page(&self.second)
+        )
+    }
+}
+
+impl core::error::Error for DuplicateKey {}
+
 /// Primary in-memory container interface. Implementation may be backed by HashMap or BTreeMap.
 pub trait InMemoryMap {
     /// Insert an owned pair. Returns previous value if present.
@@ -34,4 +105,121 @@ pub trait InMemoryMap {
 
     /// Number of entries.
     fn len(&self) -> usize;
+
+    /// Whether the map holds no entries.
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// [`InMemoryMap`] backed by a `BTreeMap`, so iteration follows the btree key order
+/// (which is also what makes tag and prefix lookups cheap).
+#[derive(Debug, Default)]
+pub struct OrderedWalletMap {
+    entries: BTreeMap<ByteVec, MapEntry>,
+}
+
+impl OrderedWalletMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Get the full entry, including its provenance.
+    pub fn get_entry(&self, key: &[u8]) -> Option<&MapEntry> {
+        self.entries.get(key)
+    }
+
+    /// All entries whose raw key starts with `prefix`, in key order.
+    pub fn with_prefix<'a>(&'a self, prefix: &'a [u8]) -> impl Iterator<Item = &'a MapEntry> + 'a {
+        self.entries
+            .range::<[u8], _>((Bound::Included(prefix), Bound::Unbounded))
+            .take_while(move |(k, _)| k.starts_with(prefix))
+            .map(|(_, v)| v)
+    }
+
+    /// Insert a pair, settling a clash with an existing copy of the key by `policy`.
+    /// `lsn` gives the LSN of the page an entry was read from, where known.
+    pub fn insert_resolving(
+        &mut self,
+        entry: MapEntry,
+        policy: ConflictPolicy,
+        lsn: impl Fn(&MapEntry) -> Option<LogSequenceNumber>,
+    ) -> Result<(), DuplicateKey> {
+        let mut slot = match self.entries.entry(entry.key.clone()) {
+            Entry::Vacant(slot) => {
+                slot.insert(entry);
+                return Ok(());
+            }
+            Entry::Occupied(slot) => slot,
+        };
+        let existing = slot.get_mut();
+        match policy {
+            ConflictPolicy::HighestLsn => {
+                let newer = match (lsn(&entry), lsn(existing)) {
+                    (Some(new), Some(old)) => new >= old,
+                    _ => true,
+                };
+                if newer {
+                    *existing = entry;
+                }
+            }
+            ConflictPolicy::KeepAll => existing.duplicates.push(entry),
+            ConflictPolicy::Error => {
+                return Err(DuplicateKey {
+                    key: entry.key,
+                    first: existing.meta.clone(),
+                    second: entry.meta,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// All walletdb records with the given tag (e.g. `"tx"`, `"key"`), in key order.
+    pub fn with_tag(&self, tag: &str) -> impl Iterator<Item = &MapEntry> + '_ {
+        let prefix = walletdb_key_prefix(tag);
+        self.entries
+            .range::<[u8], _>((Bound::Included(prefix.as_slice()), Bound::Unbounded))
+            .take_while(move |(k, _)| k.starts_with(&prefix))
+            .map(|(_, v)| v)
+    }
+}
+
+impl InMemoryMap for OrderedWalletMap {
+    fn insert(
+        &mut self,
+        key: ByteVec,
+        value: ByteVec,
+        provenance: Option<Provenance>,
+    ) -> Option<MapEntry> {
+        let entry = MapEntry {
+            key: key.clone(),
+            value,
+            meta: provenance,
+            duplicates: Vec::new(),
+        };
+        match self.entries.entry(key) {
+            Entry::Occupied(mut slot) => Some(core::mem::replace(slot.get_mut(), entry)),
+            Entry::Vacant(slot) => {
+                slot.insert(entry);
+                None
+            }
+        }
+    }
+
+    fn get(&self, key: &[u8]) -> Option<&ByteVec> {
+        self.entries.get(key).map(|e| &e.value)
+    }
+
+    fn iter(&self) -> Box<dyn Iterator<Item = (&ByteVec, &MapEntry)> + '_> {
+        Box::new(self.entries.iter())
+    }
+
+    fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
 }
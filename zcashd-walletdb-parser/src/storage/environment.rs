@@ -0,0 +1,192 @@
+//! The Berkeley DB environment files that sit next to a wallet in a zcashd datadir.
+//!
+//! zcashd opens its wallet inside an environment whose home is the datadir: `__db.001`,
+//! `__db.002`, ... are the environment's shared-memory regions, and `database/log.*` are
+//! its transaction logs. Neither is a database file, so pointing a reader at one fails on
+//! the meta page. [`EnvironmentFile::classify`] recognises them by name, and
+//! [`scan_environment`] lists a datadir so the database in it can be picked out.
+//!
+//! Log records are not replayed. Changes zcashd wrote to the log but had not yet
+//! checkpointed into `wallet.dat` (after a crash, or while zcashd is running) are missing
+//! from what the readers see.
+
+use std::{
+    fmt, fs,
+    io::{self, Read},
+    path::{Path, PathBuf},
+};
+
+use crate::util::detect_endian;
+
+/// Directory zcashd keeps its Berkeley DB logs in, relative to the datadir (`lg_dir`).
+pub const ZCASHD_LOG_DIR: &str = "database";
+
+/// A Berkeley DB environment file, named as Berkeley DB names it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EnvironmentFile {
+    /// `__db.NNN`: a shared-memory region (locks, buffer pool, transactions, ...).
+    Region(u32),
+    /// `log.NNNNNNNNNN`: a transaction log file.
+    Log(u32),
+}
+
+impl EnvironmentFile {
+    /// Recognise an environment file by its file name.
+    pub fn classify(path: &Path) -> Option<Self> {
+        let name = path.file_name()?.to_str()?;
+        let numbered = |prefix: &str, digits: usize| {
+            let n = name.strip_prefix(prefix)?;
+            (n.len() == digits && n.bytes().all(|b| b.is_ascii_digit()))
+                .then(|| n.parse().ok())
+                .flatten()
+        };
+        numbered("__db.", 3)
+            .map(EnvironmentFile::Region)
+            .or_else(|| numbered("log.", 10).map(EnvironmentFile::Log))
+    }
+
+    /// Why the file holds no records to read.
+    pub fn explain(&self) -> &'static str {
+        match self {
+            EnvironmentFile::Region(_) => {
+                "an environment region file: it backs the shared memory of the Berkeley DB \
+                 environment and holds no records; read wallet.dat in the same directory"
+            }
+            EnvironmentFile::Log(_) => {
+                "a transaction log file: it holds changes to the databases, not the \
+                 databases themselves, and is not replayed; read wallet.dat in the datadir"
+            }
+        }
+    }
+}
+
+impl fmt::Display for EnvironmentFile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EnvironmentFile::Region(n) => write!(f, "region {n}"),
+            EnvironmentFile::Log(n) => write!(f, "log {n}"),
+        }
+    }
+}
+
+/// The error for a file that is not a database, explaining what it is when it is an
+/// environment file.
+pub(crate) fn not_a_database(path: &Path, e: io::Error) -> io::Error {
+    match EnvironmentFile::classify(path) {
+        Some(kind) => io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{} is {}", path.display(), kind.explain()),
+        ),
+        None => e,
+    }
+}
+
+/// Whether `path` starts with a btree meta page.
+fn is_btree_file(path: &Path) -> bool {
+    let mut head = [0u8; 16];
+    fs::File::open(path)
+        .and_then(|mut f| f.read_exact(&mut head))
+        .is_ok_and(|()| detect_endian(&head).is_some())
+}
+
+/// What [`scan_environment`] found in a datadir.
+#[derive(Debug, Clone, Default)]
+pub struct Environment {
+    pub home: PathBuf,
+    /// Btree database files, e.g. `wallet.dat`, sorted by path.
+    pub databases: Vec<PathBuf>,
+    pub regions: Vec<PathBuf>,
+    /// Log files in the datadir or its `database/` directory, oldest first.
+    pub logs: Vec<PathBuf>,
+}
+
+impl Environment {
+    /// The wallet to read: `wallet.dat` when present, otherwise the only database.
+    pub fn wallet(&self) -> Option<&Path> {
+        let named = self
+            .databases
+            .iter()
+            .find(|p| p.file_name().is_some_and(|n| n == "wallet.dat"));
+        match (named, self.databases.as_slice()) {
+            (Some(path), _) | (None, [path]) => Some(path),
+            _ => None,
+        }
+    }
+
+    /// Caveats about reading the databases without the rest of the environment.
+    pub fn notes(&self) -> Vec<String> {
+        let mut notes = Vec::new();
+        if !self.regions.is_empty() {
+            notes.push(format!(
+                "{} region files: they hold no records, but a wallet read while zcashd is \
+                 running may be mid-write; stop zcashd first or read a copy",
+                self.regions.len()
+            ));
+        }
+        if !self.logs.is_empty() {
+            notes.push(format!(
+                "{} log files are not replayed: changes not yet checkpointed into the \
+                 wallet are missing; run db_recover on a copy of the datadir to fold them in",
+                self.logs.len()
+            ));
+        }
+        notes
+    }
+}
+
+impl fmt::Display for Environment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let relative = |p: &PathBuf| {
+            p.strip_prefix(&self.home)
+                .unwrap_or(p)
+                .display()
+                .to_string()
+        };
+        writeln!(f, "Environment {{")?;
+        writeln!(f, "  home         : {}", self.home.display())?;
+        for p in &self.databases {
+            writeln!(f, "  database     : {}", relative(p))?;
+        }
+        for p in &self.regions {
+            writeln!(f, "  region       : {}", relative(p))?;
+        }
+        for p in &self.logs {
+            writeln!(f, "  log          : {}", relative(p))?;
+        }
+        write!(f, "}}")
+    }
+}
+
+/// List the databases, region files and logs of the environment whose home is `home`,
+/// e.g. a zcashd datadir.
+pub fn scan_environment(home: impl AsRef<Path>) -> io::Result<Environment> {
+    let home = home.as_ref();
+    let mut env = Environment {
+        home: home.to_path_buf(),
+        ..Environment::default()
+    };
+    for dir in [home.to_path_buf(), home.join(ZCASHD_LOG_DIR)] {
+        let entries = match fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == io::ErrorKind::NotFound && dir != home => continue,
+            Err(e) => return Err(e),
+        };
+        for entry in entries {
+            let path = entry?.path();
+            if !path.is_file() {
+                continue;
+            }
+            match EnvironmentFile::classify(&path) {
+                Some(EnvironmentFile::Region(_)) => env.regions.push(path),
+                Some(EnvironmentFile::Log(_)) => env.logs.push(path),
+                None if dir == home && is_btree_file(&path) => env.databases.push(path),
+                None => {}
+            }
+        }
+    }
+    env.databases.sort();
+    env.regions.sort();
+    // Zero-padded numbers, so name order is log order.
+    env.logs.sort_by(|a, b| a.file_name().cmp(&b.file_name()));
+    Ok(env)
+}
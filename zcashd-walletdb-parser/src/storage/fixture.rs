@@ -0,0 +1,187 @@
+//! Synthetic wallet.dat images for tests.
+//!
+//! [`FixtureBuilder`] lays out a wallet with [`write_wallet`], then tombstones the
+//! records asked to be deleted and finally applies raw [`Corruption`]s, so tests can
+//! cover page sizes, byte orders, overflow chains and damage without shipping real
+//! wallet files.
+
+use std::{collections::HashSet, io};
+
+use crate::{
+    entry::parser::walletdb_key_prefix,
+    storage::{
+        borrowed::BorrowedImage,
+        btree::B_DELETE,
+        checksum::write_page_checksum,
+        entry::{InMemoryMap, OrderedWalletMap},
+        page::PageProtection,
+        types::{ByteVec, Endianness, PageNumber, PageSize},
+        version::BtreeVersion,
+        writer::{WriteOptions, write_wallet},
+    },
+    util::u16e,
+};
+
+/// Damage applied to a built image, in the order given.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Corruption {
+    /// Overwrite bytes of page `pgno`, starting `offset` bytes into the page.
+    Patch {
+        pgno: PageNumber,
+        offset: usize,
+        bytes: ByteVec,
+    },
+    /// Fill page `pgno` with zeros.
+    ZeroPage(PageNumber),
+    /// Cut the image to its first `len` bytes.
+    Truncate(usize),
+}
+
+/// Builds small wallet images with chosen geometry, records and damage.
+#[derive(Debug, Clone, Default)]
+pub struct FixtureBuilder {
+    options: WriteOptions,
+    records: Vec<(ByteVec, ByteVec)>,
+    deleted: Vec<ByteVec>,
+    corruptions: Vec<Corruption>,
+}
+
+impl FixtureBuilder {
+    /// An empty wallet with [`WriteOptions::default`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn page_size(mut self, page_size: PageSize) -> Self {
+        self.options.page_size = page_size;
+        self
+    }
+
+    pub fn endianness(mut self, endianness: Endianness) -> Self {
+        self.options.endianness = endianness;
+        self
+    }
+
+    pub fn version(mut self, version: BtreeVersion) -> Self {
+        self.options.version = version;
+        self
+    }
+
+    /// Store a checksum on every page (`DB_CHKSUM`).
+    pub fn checksum(mut self, checksum: bool) -> Self {
+        self.options.checksum = checksum;
+        self
+    }
+
+    /// Add a raw pair. A later pair with the same key replaces it.
+    pub fn record(mut self, key: impl Into<ByteVec>, value: impl Into<ByteVec>) -> Self {
+        self.records.push((key.into(), value.into()));
+        self
+    }
+
+    /// Add a walletdb record: the key is `tag` in walletdb framing followed by `rest`.
+    pub fn wallet_record(self, tag: &str, rest: &[u8], value: impl Into<ByteVec>) -> Self {
+        let mut key = walletdb_key_prefix(tag);
+        key.extend_from_slice(rest);
+        self.record(key, value)
+    }
+
+    /// Add a pair that stays on its page but is marked deleted (`B_DELETE`), the way
+    /// Berkeley DB leaves erased records behind until the page is reorganised.
+    pub fn deleted_record(mut self, key: impl Into<ByteVec>, value: impl Into<ByteVec>) -> Self {
+        let key = key.into();
+        self.deleted.push(key.clone());
+        self.record(key, value)
+    }
+
+    pub fn corrupt(mut self, corruption: Corruption) -> Self {
+        self.corruptions.push(corruption);
+        self
+    }
+
+    /// The live records, i.e. what a reader should return for an undamaged build.
+    pub fn live_records(&self) -> OrderedWalletMap {
+        let deleted: HashSet<&ByteVec> = self.deleted.iter().collect();
+        let mut map = OrderedWalletMap::new();
+        for (key, value) in &self.records {
+            if !deleted.contains(key) {
+                map.insert(key.clone(), value.clone(), None);
+            }
+        }
+        map
+    }
+
+    /// Lay out the image and apply the deletions and corruptions.
+    pub fn build(&self) -> io::Result<ByteVec> {
+        let mut map = OrderedWalletMap::new();
+        for (key, value) in &self.records {
+            map.insert(key.clone(), value.clone(), None);
+        }
+        let mut image = write_wallet(&map, &self.options)?;
+        self.tombstone(&mut image)?;
+        let page_size = self.options.page_size as usize;
+        for corruption in &self.corruptions {
+            match corruption {
+                Corruption::Patch {
+                    pgno,
+                    offset,
+                    bytes,
+                } => {
+                    let start = *pgno as usize * page_size + offset;
+                    image
+                        .get_mut(start..start + bytes.len())
+                        .ok_or_else(|| {
+                            io::Error::new(
+                                io::ErrorKind::InvalidInput,
+                                format!("patch at page {pgno} offset {offset} is past the image"),
+                            )
+                        })?
+                        .copy_from_slice(bytes);
+                }
+                Corruption::ZeroPage(pgno) => {
+                    let start = *pgno as usize * page_size;
+                    if let Some(page) = image.get_mut(start..start + page_size) {
+                        page.fill(0);
+                    }
+                }
+                Corruption::Truncate(len) => image.truncate(*len),
+            }
+        }
+        Ok(image)
+    }
+
+    /// Set `B_DELETE` on both items of every deleted pair, refreshing page checksums.
+    fn tombstone(&self, image: &mut [u8]) -> io::Result<()> {
+        if self.deleted.is_empty() {
+            return Ok(());
+        }
+        let deleted: HashSet<&[u8]> = self.deleted.iter().map(ByteVec::as_slice).collect();
+        let mut slots: Vec<(PageNumber, u16)> = Vec::new();
+        for entry in BorrowedImage::new(image)?.entries() {
+            let entry = entry?;
+            if deleted.contains(entry.key.as_ref()) {
+                slots.push((entry.page_no, entry.slot_index));
+            }
+        }
+        let page_size = self.options.page_size as usize;
+        let protection = if self.options.checksum {
+            PageProtection::Checksum
+        } else {
+            PageProtection::None
+        };
+        let e = self.options.endianness;
+        for (pgno, slot) in slots {
+            let start = pgno as usize * page_size;
+            let page = &mut image[start..start + page_size];
+            for index in [slot as usize, slot as usize + 1] {
+                let at = protection.header_size() + index * 2;
+                let offset = u16e(e.into(), &page[at..at + 2]) as usize;
+                page[offset + 2] |= B_DELETE;
+            }
+            if self.options.checksum {
+                write_page_checksum(page, e);
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,116 @@
+//! Flag bits of btree meta pages.
+//!
+//! The 32-bit `flags` field at 48..52 is specific to each access method; for btree and
+//! recno databases it holds the `BTM_*` bits. The `metaflags` byte at 26 is shared by all
+//! access methods and says how every page of the file is stored.
+
+use core::fmt;
+
+use crate::constants::DBMETA_CHKSUM;
+
+/// Btree meta flag: duplicate keys are allowed (`BTM_DUP`).
+pub const BTM_DUP: u32 = 0x001;
+/// Btree meta flag: the tree is a recno database (`BTM_RECNO`).
+pub const BTM_RECNO: u32 = 0x002;
+/// Btree meta flag: internal pages keep record counts (`BTM_RECNUM`).
+pub const BTM_RECNUM: u32 = 0x004;
+/// Btree meta flag: recno records have a fixed length (`BTM_FIXEDLEN`).
+pub const BTM_FIXEDLEN: u32 = 0x008;
+/// Btree meta flag: record numbers are renumbered on insert and delete (`BTM_RENUMBER`).
+pub const BTM_RENUMBER: u32 = 0x010;
+/// Btree meta flag: the file holds named subdatabases (`BTM_SUBDB`).
+pub const BTM_SUBDB: u32 = 0x020;
+/// Btree meta flag: duplicates are sorted (`BTM_DUPSORT`).
+pub const BTM_DUPSORT: u32 = 0x040;
+/// Btree meta flag: keys and data are compressed (`BTM_COMPRESS`).
+pub const BTM_COMPRESS: u32 = 0x080;
+
+/// `metaflags` bit: the file is partitioned by key range (`DBMETA_PART_RANGE`).
+pub const DBMETA_PART_RANGE: u8 = 0x02;
+/// `metaflags` bit: the file is partitioned by callback (`DBMETA_PART_CALLBACK`).
+pub const DBMETA_PART_CALLBACK: u8 = 0x04;
+
+const BTM_NAMES: [(u32, &str); 8] = [
+    (BTM_DUP, "DUP"),
+    (BTM_RECNO, "RECNO"),
+    (BTM_RECNUM, "RECNUM"),
+    (BTM_FIXEDLEN, "FIXEDLEN"),
+    (BTM_RENUMBER, "RENUMBER"),
+    (BTM_SUBDB, "SUBDB"),
+    (BTM_DUPSORT, "DUPSORT"),
+    (BTM_COMPRESS, "COMPRESS"),
+];
+
+const METAFLAG_NAMES: [(u8, &str); 3] = [
+    (DBMETA_CHKSUM, "CHKSUM"),
+    (DBMETA_PART_RANGE, "PART_RANGE"),
+    (DBMETA_PART_CALLBACK, "PART_CALLBACK"),
+];
+
+/// Write the names of the set bits joined by `|`, then any unknown bits in hex.
+fn write_bits(f: &mut fmt::Formatter<'_>, bits: u32, names: &[(u32, &str)]) -> fmt::Result {
+    let mut rest = bits;
+    let mut sep = "";
+    for &(bit, name) in names {
+        if bits & bit != 0 {
+            write!(f, "{sep}{name}")?;
+            rest &= !bit;
+            sep = "|";
+        }
+    }
+    if rest != 0 {
+        write!(f, "{sep}0x{rest:x}")?;
+    } else if sep.is_empty() {
+        write!(f, "-")?;
+    }
+    Ok(())
+}
+
+/// The `flags` field of a btree or recno meta page.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct BtreeMetaFlags(pub u32);
+
+impl BtreeMetaFlags {
+    pub fn contains(self, bit: u32) -> bool {
+        self.0 & bit == bit
+    }
+
+    /// Bits no Berkeley DB release defines for btree meta pages.
+    pub fn unknown(self) -> u32 {
+        self.0 & !BTM_NAMES.iter().fold(0, |acc, (bit, _)| acc | bit)
+    }
+
+    /// Recno-only bits on a tree without `BTM_RECNO` (or the reverse for `BTM_RECNUM`)
+    /// cannot have been written by Berkeley DB.
+    pub fn is_consistent(self) -> bool {
+        let recno_only = BTM_FIXEDLEN | BTM_RENUMBER;
+        if self.contains(BTM_RECNO) {
+            !self.contains(BTM_DUP) && !self.contains(BTM_RECNUM)
+        } else {
+            self.0 & recno_only == 0
+        }
+    }
+}
+
+impl fmt::Display for BtreeMetaFlags {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_bits(f, self.0, &BTM_NAMES)
+    }
+}
+
+/// The `metaflags` byte shared by every access method.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct MetaFlags(pub u8);
+
+impl MetaFlags {
+    pub fn contains(self, bit: u8) -> bool {
+        self.0 & bit == bit
+    }
+}
+
+impl fmt::Display for MetaFlags {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let names = METAFLAG_NAMES.map(|(bit, name)| (bit as u32, name));
+        write_bits(f, self.0 as u32, &names)
+    }
+}
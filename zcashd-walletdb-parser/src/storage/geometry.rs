@@ -0,0 +1,138 @@
+//! Recovering the page size, byte order and page protection of an image from the page
+//! headers themselves, for when the meta page is overwritten or missing.
+//!
+//! Every page stores its own page number at bytes 8..12. Cut the image at the right page
+//! size and read it in the right byte order, and page `n` says it is page `n`; get either
+//! wrong and almost no page agrees. The candidate with the most self-consistent headers
+//! wins.
+
+use std::fmt;
+
+use crate::{
+    constants::{DB_MAX_PGSIZE, DB_MIN_PGSIZE, SIZEOF_PAGE},
+    storage::{
+        page::{PageHeader, PageProtection, PageType},
+        slots::validate_slots,
+        types::{Endianness, PageSize},
+    },
+};
+
+/// Where an image's geometry came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GeometrySource {
+    /// The meta page parsed and its fields were used as-is.
+    MetaPage,
+    /// The meta page was unusable; the geometry was inferred from page headers.
+    PageHeaders,
+}
+
+/// How an image is cut into pages and decoded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ImageGeometry {
+    pub page_size: PageSize,
+    pub endianness: Endianness,
+    pub protection: PageProtection,
+    pub source: GeometrySource,
+    /// Pages whose header is consistent with this geometry.
+    pub matching_pages: usize,
+    /// Bytes after the last whole page.
+    pub trailing_bytes: usize,
+}
+
+impl fmt::Display for ImageGeometry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "ImageGeometry {{")?;
+        writeln!(f, "  source         : {:?}", self.source)?;
+        writeln!(f, "  pagesize       : {}", self.page_size)?;
+        writeln!(f, "  endianness     : {:?}", self.endianness)?;
+        writeln!(f, "  protection     : {:?}", self.protection)?;
+        writeln!(f, "  matching_pages : {}", self.matching_pages)?;
+        writeln!(f, "  trailing_bytes : {}", self.trailing_bytes)?;
+        write!(f, "}}")
+    }
+}
+
+/// The header of `page` if a real page could carry it, whatever its position.
+pub(crate) fn plausible_page(page: &[u8], e: Endianness) -> Option<PageHeader> {
+    let hdr = PageHeader::parse(page, e, PageProtection::None).ok()?;
+    let slots_end = SIZEOF_PAGE + hdr.num_slots() * 2;
+    let data_region_ok = slots_end <= hdr.upper_bound() && hdr.upper_bound() <= page.len();
+    let plausible = match hdr.kind() {
+        PageType::Meta => true,
+        PageType::BtreeLeaf | PageType::RecnoLeaf => hdr.level == 1 && data_region_ok,
+        PageType::BtreeInternal | PageType::RecnoInternal => hdr.level > 1 && data_region_ok,
+        // hf_offset holds the number of payload bytes on an overflow page.
+        PageType::Overflow => hdr.upper_bound() <= page.len() - SIZEOF_PAGE,
+        _ => false,
+    };
+    plausible.then_some(hdr)
+}
+
+/// Whether `page`, read as page `index`, has a header a real page could carry.
+fn plausible_header(page: &[u8], index: usize, e: Endianness) -> bool {
+    plausible_page(page, e).is_some_and(|hdr| hdr.pgno as usize == index)
+}
+
+/// Count the pages of `image` that are self-consistent when cut at `page_size` and read
+/// as `e`.
+pub(crate) fn matching_pages(image: &[u8], page_size: usize, e: Endianness) -> usize {
+    image
+        .chunks_exact(page_size)
+        .enumerate()
+        .filter(|(index, page)| plausible_header(page, *index, e))
+        .count()
+}
+
+/// Decide between no protection and `DB_CHKSUM` by which one leaves more leaf slot
+/// arrays intact. Encrypted images cannot be salvaged without the key and are not tried.
+fn infer_protection(image: &[u8], page_size: usize, e: Endianness) -> PageProtection {
+    let clean = |protection: PageProtection| {
+        image
+            .chunks_exact(page_size)
+            .enumerate()
+            .filter(|(index, page)| plausible_header(page, *index, e))
+            .filter_map(|(_, page)| {
+                let hdr = PageHeader::parse(page, e, protection).ok()?;
+                (hdr.kind() == PageType::BtreeLeaf && hdr.num_slots() > 0)
+                    .then(|| validate_slots(page, &hdr, e, protection).is_clean())
+            })
+            .filter(|&clean| clean)
+            .count()
+    };
+    if clean(PageProtection::Checksum) > clean(PageProtection::None) {
+        PageProtection::Checksum
+    } else {
+        PageProtection::None
+    }
+}
+
+/// Infer the geometry of `image` from its page headers alone. Returns `None` when no
+/// page size and byte order make even one page self-consistent.
+///
+/// Every supported page size is tried in both byte orders; ties go to the larger page
+/// size, since an image that is all page 0 fits any of them.
+pub fn infer_geometry(image: &[u8]) -> Option<ImageGeometry> {
+    let mut best: Option<(usize, Endianness, usize)> = None;
+    let mut page_size = DB_MIN_PGSIZE;
+    while page_size <= DB_MAX_PGSIZE {
+        for e in [Endianness::Little, Endianness::Big] {
+            let n = matching_pages(image, page_size, e);
+            let better = best.is_none_or(|(best_size, _, best_n)| {
+                n > best_n || (n == best_n && page_size > best_size)
+            });
+            if n > 0 && better {
+                best = Some((page_size, e, n));
+            }
+        }
+        page_size *= 2;
+    }
+    let (page_size, endianness, matching_pages) = best?;
+    Some(ImageGeometry {
+        page_size: page_size as PageSize,
+        endianness,
+        protection: infer_protection(image, page_size, endianness),
+        source: GeometrySource::PageHeaders,
+        matching_pages,
+        trailing_bytes: image.len() % page_size,
+    })
+}
@@ -0,0 +1,172 @@
+//! Page LSNs, and what they say about how a wallet file was copied.
+//!
+//! Every page header carries the LSN of the last log record that changed the page. When
+//! zcashd shuts down cleanly or runs `backupwallet`, it checkpoints and detaches the
+//! wallet from its environment (`DB_ENV->lsn_reset`), which stamps every page with the
+//! reset LSN `[0][1]`: the file then stands on its own. A page that still carries a live
+//! LSN was written while the environment was in use, so the file was copied while zcashd
+//! was running or after it crashed. Changes still only in the log, such as recently
+//! generated keys or received transactions, are missing from such a copy.
+//!
+//! Given the LSN of the last checkpoint (`db_stat -t` prints it), [`analyze_lsns`] also
+//! picks out the pages written after it, which the checkpoint does not vouch for.
+
+use std::{collections::BTreeMap, fmt, io};
+
+use crate::storage::{
+    reader::FileDbImageReader,
+    types::{LogSequenceNumber, PageNumber},
+};
+
+/// The LSN `DB_ENV->lsn_reset` stamps on every page of a detached database.
+pub const RESET_LSN: LogSequenceNumber = 1;
+
+/// The log file number of an LSN.
+pub fn lsn_file(lsn: LogSequenceNumber) -> u32 {
+    (lsn >> 32) as u32
+}
+
+/// The offset of an LSN in its log file.
+pub fn lsn_offset(lsn: LogSequenceNumber) -> u32 {
+    lsn as u32
+}
+
+/// Whether `lsn` points into a log. Log files are numbered from 1, so the reset LSN and
+/// the zero LSN of a page that was never logged are not live.
+pub fn is_live(lsn: LogSequenceNumber) -> bool {
+    lsn_file(lsn) != 0
+}
+
+/// Format an LSN the way Berkeley DB's tools do, e.g. `[1][28]`.
+pub fn format_lsn(lsn: LogSequenceNumber) -> String {
+    format!("[{}][{}]", lsn_file(lsn), lsn_offset(lsn))
+}
+
+/// Parse an LSN written as `file/offset`, as `db_stat -t` prints it, or as `[file][offset]`.
+pub fn parse_lsn(s: &str) -> Option<LogSequenceNumber> {
+    let (file, offset) = match s.strip_prefix('[').and_then(|s| s.strip_suffix(']')) {
+        Some(s) => s.split_once("]["),
+        None => s.split_once('/'),
+    }?;
+    let file: u32 = file.parse().ok()?;
+    let offset: u32 = offset.parse().ok()?;
+    Some((file as u64) << 32 | offset as u64)
+}
+
+/// The outcome of [`analyze_lsns`].
+#[derive(Debug, Clone, Default)]
+pub struct LsnReport {
+    /// Pages whose LSN was read.
+    pub pages: u64,
+    /// Pages whose header could not be read.
+    pub unreadable_pages: u64,
+    /// Pages with the reset LSN, or the zero LSN of a page that was never logged.
+    pub reset_pages: u64,
+    /// Pages with a live LSN, by log file number.
+    pub log_files: BTreeMap<u32, u64>,
+    /// The highest LSN in the image and the first page carrying it.
+    pub max: Option<(PageNumber, LogSequenceNumber)>,
+    /// The checkpoint LSN the pages were compared against, if one was given.
+    pub checkpoint: Option<LogSequenceNumber>,
+    /// Pages whose live LSN is past the checkpoint, in page order.
+    pub past_checkpoint: Vec<(PageNumber, LogSequenceNumber)>,
+}
+
+impl LsnReport {
+    /// Pages carrying a live LSN.
+    pub fn live_pages(&self) -> u64 {
+        self.log_files.values().sum()
+    }
+
+    /// Whether the file was detached from its environment: no page points into a log.
+    pub fn is_detached(&self) -> bool {
+        self.log_files.is_empty()
+    }
+
+    /// What the LSNs imply for the completeness of the file, one warning per line.
+    pub fn warnings(&self) -> Vec<String> {
+        let mut warnings = Vec::new();
+        if !self.is_detached() {
+            warnings.push(format!(
+                "{} pages carry live LSNs: the wallet was not detached from its environment, \
+                 so it was copied while zcashd was running or after a crash; recent keys and \
+                 transactions still only in the log may be missing",
+                self.live_pages()
+            ));
+        }
+        if let (Some(checkpoint), Some(&(pgno, lsn))) = (
+            self.checkpoint,
+            self.past_checkpoint.iter().rev().max_by_key(|(_, lsn)| lsn),
+        ) {
+            warnings.push(format!(
+                "{} pages were written after the checkpoint at {} (up to {} on page {pgno}): \
+                 the copy may have been taken mid-write",
+                self.past_checkpoint.len(),
+                format_lsn(checkpoint),
+                format_lsn(lsn)
+            ));
+        }
+        warnings
+    }
+}
+
+impl fmt::Display for LsnReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "LsnReport {{")?;
+        writeln!(f, "  pages        : {}", self.pages)?;
+        if self.unreadable_pages > 0 {
+            writeln!(f, "  unreadable   : {}", self.unreadable_pages)?;
+        }
+        writeln!(f, "  reset        : {}", self.reset_pages)?;
+        for (file, n) in &self.log_files {
+            writeln!(f, "  {:<13}: {n}", format!("log {file}"))?;
+        }
+        if let Some((pgno, lsn)) = self.max {
+            writeln!(f, "  max lsn      : {} (page {pgno})", format_lsn(lsn))?;
+        }
+        if let Some(checkpoint) = self.checkpoint {
+            writeln!(f, "  checkpoint   : {}", format_lsn(checkpoint))?;
+            writeln!(f, "  past it      : {}", self.past_checkpoint.len())?;
+        }
+        write!(f, "}}")
+    }
+}
+
+/// Read the LSN of every page behind `reader`, comparing them against `checkpoint` when
+/// given. Pages that cannot be read are counted and skipped.
+pub fn analyze_lsns(
+    reader: &FileDbImageReader,
+    checkpoint: Option<LogSequenceNumber>,
+) -> LsnReport {
+    let mut report = LsnReport {
+        checkpoint,
+        ..LsnReport::default()
+    };
+    let page_count = reader.source().page_count();
+    let mut pgno: PageNumber = 0;
+    loop {
+        if page_count.is_some_and(|n| pgno as u64 >= n) {
+            break;
+        }
+        match reader.page_lsn(pgno) {
+            Ok(lsn) => {
+                report.pages += 1;
+                if is_live(lsn) {
+                    *report.log_files.entry(lsn_file(lsn)).or_default() += 1;
+                } else {
+                    report.reset_pages += 1;
+                }
+                if report.max.is_none_or(|(_, max)| lsn > max) {
+                    report.max = Some((pgno, lsn));
+                }
+                if is_live(lsn) && checkpoint.is_some_and(|c| lsn > c) {
+                    report.past_checkpoint.push((pgno, lsn));
+                }
+            }
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => break,
+            Err(_) => report.unreadable_pages += 1,
+        }
+        pgno += 1;
+    }
+    report
+}
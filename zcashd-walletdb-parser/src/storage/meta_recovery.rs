@@ -0,0 +1,255 @@
+//! Rebuilding the meta page of an image whose page 0 is damaged.
+//!
+//! Page 0 is the only page naming the root of the tree, so one bad byte there hides
+//! every record even when all data pages are intact. Most of what it holds is stored
+//! elsewhere too:
+//!
+//! - the page size and byte order are implied by the page headers (see
+//!   [`infer_geometry`]);
+//! - in a file with subdatabases, every subdatabase meta page repeats the magic, version,
+//!   page size, metaflags and file ID, so the first intact one serves as a backup copy;
+//! - the root is the one btree page no internal page points to. With subdatabases it is
+//!   the top of the tree whose leaves map names to subdatabase meta pages.
+//!
+//! The freelist head and cached statistics are not recoverable and are left empty.
+
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+};
+
+use crate::{
+    constants::{DBMETA_CHKSUM, SIZEOF_PAGE},
+    headers::{BtreeMeta, parse_btree_meta_page0},
+    storage::{
+        btree::{Node, parse_node},
+        checksum::{ChecksumStatus, verify_page_checksum},
+        flags::{BTM_SUBDB, BtreeMetaFlags},
+        geometry::{ImageGeometry, infer_geometry, plausible_page},
+        page::{EntryDescriptor, PageHeader, PageProtection, PageType},
+        reader::subdb_meta_pgno,
+        types::{Endianness, PageNumber},
+        version::BtreeVersion,
+    },
+};
+
+/// Magic number of btree meta pages (`DB_BTREEMAGIC`).
+const BTREE_MAGIC: u32 = 0x0005_3162;
+
+/// `minkey` Berkeley DB uses unless told otherwise.
+const DEFAULT_MINKEY: u32 = 2;
+
+/// Why the meta page `meta`, parsed from the start of `image`, cannot be trusted, or
+/// `None` when it passes every check. Encrypted meta pages are only checked for the
+/// fields stored in the clear.
+pub fn meta_problem(meta: &BtreeMeta, image: &[u8]) -> Option<String> {
+    if !matches!(meta.p_type, PageType::Meta) {
+        return Some(format!("page 0 is a {} page, not a meta page", meta.p_type));
+    }
+    if meta.pgno != 0 {
+        return Some(format!("meta page claims pgno {}", meta.pgno));
+    }
+    let flags = BtreeMetaFlags(meta.flags);
+    if flags.unknown() != 0 || !flags.is_consistent() {
+        return Some(format!("meta page flags 0x{:08x} are damaged", meta.flags));
+    }
+    let Ok((version, _)) = BtreeVersion::resolve(meta.version) else {
+        return Some(format!("meta page version {} is damaged", meta.version));
+    };
+    if version.supports_encryption() && meta.is_encrypted() {
+        return None;
+    }
+    let e: Endianness = meta.endian.into();
+    if version.supports_checksums()
+        && meta.has_checksum()
+        && let ChecksumStatus::Mismatch { .. } = verify_page_checksum(image, e)
+    {
+        return Some("meta page checksum does not match".to_string());
+    }
+    if meta.root == 0 || meta.root > meta.last_pgno {
+        return Some(format!("root {} outside 1..={}", meta.root, meta.last_pgno));
+    }
+    // A root past the end of a truncated image cannot be checked.
+    let start = (meta.root as usize).saturating_mul(meta.pagesize as usize);
+    let root = image.get(start..start.saturating_add(SIZEOF_PAGE))?;
+    let hdr = PageHeader::parse(root, e, PageProtection::None).ok()?;
+    let is_root = hdr.pgno == meta.root
+        && matches!(
+            hdr.kind(),
+            PageType::BtreeInternal
+                | PageType::BtreeLeaf
+                | PageType::RecnoInternal
+                | PageType::RecnoLeaf
+        );
+    (!is_root).then(|| format!("root {} is not the root of a tree", meta.root))
+}
+
+/// A meta page rebuilt by [`rebuild_meta`].
+#[derive(Debug)]
+pub struct RebuiltMeta {
+    pub meta: BtreeMeta,
+    /// The subdatabase meta page the file-wide fields were copied from, if any.
+    pub template: Option<PageNumber>,
+    pub geometry: ImageGeometry,
+}
+
+impl RebuiltMeta {
+    /// Where the file-wide fields came from, for messages.
+    pub fn origin(&self) -> String {
+        match self.template {
+            Some(pgno) => format!("subdatabase meta page {pgno}"),
+            None => "the page headers".to_string(),
+        }
+    }
+}
+
+impl fmt::Display for RebuiltMeta {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "RebuiltMeta {{")?;
+        writeln!(f, "  origin     : {}", self.origin())?;
+        writeln!(f, "  pagesize   : {}", self.meta.pagesize)?;
+        writeln!(f, "  endianness : {:?}", self.geometry.endianness)?;
+        writeln!(f, "  version    : {}", self.meta.version)?;
+        writeln!(f, "  flags      : {}", BtreeMetaFlags(self.meta.flags))?;
+        writeln!(f, "  root       : {}", self.meta.root)?;
+        writeln!(f, "  last_pgno  : {}", self.meta.last_pgno)?;
+        write!(f, "}}")
+    }
+}
+
+/// Whether every pair on the leaf `page` maps a name to one of `meta_pages`.
+fn is_master_leaf(
+    page: &[u8],
+    e: Endianness,
+    protection: PageProtection,
+    meta_pages: &HashSet<PageNumber>,
+) -> bool {
+    let Ok(Node::Leaf { entries }) = parse_node(page, e, protection) else {
+        return false;
+    };
+    !entries.is_empty()
+        && entries.iter().all(|d| {
+            let value = &page[d.value_range.0..d.value_range.1];
+            d.flags & EntryDescriptor::VALUE_OVERFLOW == 0
+                && subdb_meta_pgno(value).is_ok_and(|pgno| meta_pages.contains(&pgno))
+        })
+}
+
+/// Rebuild the meta page of `image` from its other pages. Returns `None` when no
+/// geometry fits the image or no root can be told apart.
+///
+/// Only btree files are handled; a single-database recno file has no other copy of its
+/// record settings.
+pub fn rebuild_meta(image: &[u8]) -> Option<RebuiltMeta> {
+    let geometry = infer_geometry(image)?;
+    let page_size = geometry.page_size as usize;
+    let e = geometry.endianness;
+    let pages: Vec<(PageNumber, &[u8], PageHeader)> = image
+        .chunks_exact(page_size)
+        .enumerate()
+        .skip(1)
+        .filter_map(|(index, page)| {
+            let hdr = plausible_page(page, e)?;
+            (hdr.pgno as usize == index).then_some((hdr.pgno, page, hdr))
+        })
+        .collect();
+
+    let mut parents: HashMap<PageNumber, PageNumber> = HashMap::new();
+    let mut sub_metas: Vec<(PageNumber, BtreeMeta)> = Vec::new();
+    for &(pgno, page, ref hdr) in &pages {
+        match hdr.kind() {
+            PageType::BtreeInternal => {
+                if let Ok(Node::Internal { children, .. }) =
+                    parse_node(page, e, geometry.protection)
+                {
+                    parents.extend(children.into_iter().map(|child| (child, pgno)));
+                }
+            }
+            PageType::Meta => {
+                if let Ok(meta) = parse_btree_meta_page0(page)
+                    && meta.pagesize == geometry.page_size
+                    && Endianness::from(meta.endian) == e
+                {
+                    sub_metas.push((pgno, meta));
+                }
+            }
+            _ => {}
+        }
+    }
+    // Follow parent links up to a page nothing points to.
+    let top = |mut pgno: PageNumber| {
+        let mut seen = HashSet::from([pgno]);
+        while let Some(&parent) = parents.get(&pgno) {
+            if !seen.insert(parent) {
+                break;
+            }
+            pgno = parent;
+        }
+        pgno
+    };
+
+    let root = if sub_metas.is_empty() {
+        // The tallest tree nothing points to; ties go to the lowest page number.
+        pages
+            .iter()
+            .filter(|(pgno, _, hdr)| {
+                matches!(hdr.kind(), PageType::BtreeInternal | PageType::BtreeLeaf)
+                    && !parents.contains_key(pgno)
+            })
+            .max_by_key(|(pgno, _, hdr)| (hdr.level, std::cmp::Reverse(*pgno)))
+            .map(|&(pgno, _, _)| pgno)?
+    } else {
+        let meta_pages: HashSet<PageNumber> = sub_metas.iter().map(|(pgno, _)| *pgno).collect();
+        pages
+            .iter()
+            .find(|(_, page, hdr)| {
+                hdr.kind() == PageType::BtreeLeaf
+                    && is_master_leaf(page, e, geometry.protection, &meta_pages)
+            })
+            .map(|&(pgno, _, _)| top(pgno))?
+    };
+
+    let template = sub_metas.into_iter().next();
+    let metaflags = match (&template, geometry.protection) {
+        (Some((_, meta)), _) => meta.metaflags,
+        (None, PageProtection::Checksum) => DBMETA_CHKSUM,
+        (None, _) => 0,
+    };
+    let (version, uid, minkey) = template.as_ref().map_or(
+        (BtreeVersion::V9.code(), [0; 20], DEFAULT_MINKEY),
+        |(_, meta)| (meta.version, meta.uid, meta.minkey),
+    );
+    let meta = BtreeMeta {
+        endian: e.into(),
+        lsn_file: 0,
+        lsn_offset: 0,
+        pgno: 0,
+        magic: BTREE_MAGIC,
+        version,
+        pagesize: geometry.page_size,
+        encrypt_alg: 0,
+        p_type: PageType::Meta,
+        metaflags,
+        _unused1: 0,
+        free: 0,
+        last_pgno: (image.len() / page_size).saturating_sub(1) as PageNumber,
+        _unused3: 0,
+        key_count: 0,
+        record_count: 0,
+        flags: if template.is_some() { BTM_SUBDB } else { 0 },
+        uid,
+        _unused_after_uid: 0,
+        minkey,
+        re_len: 0,
+        re_pad: 0,
+        root,
+        crypto_magic: 0,
+        iv: [0; 16],
+        chksum: [0; 20],
+    };
+    Some(RebuiltMeta {
+        meta,
+        template: template.map(|(pgno, _)| pgno),
+        geometry,
+    })
+}
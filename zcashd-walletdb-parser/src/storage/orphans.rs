@@ -0,0 +1,206 @@
+//! Reachability analysis: which pages the database still references, and which allocated
+//! pages nothing points at any more.
+//!
+//! Pages are reachable from the meta page through the master and subdatabase trees,
+//! through overflow chains and off-page duplicate trees hanging off their items, and
+//! through the freelist. Anything else that is not a blank `P_INVALID` page is an orphan.
+//! Orphaned leaf pages are typically left behind by an interrupted split or a damaged
+//! parent, and still hold records that [`FileDbImageReader::orphan_entries`] recovers.
+
+use std::{
+    collections::{BTreeSet, HashSet},
+    fmt, io,
+};
+
+use crate::{
+    storage::{
+        btree::{B_DUPLICATE, B_OVERFLOW, BINTERNAL_SIZE, BOVERFLOW_SIZE, lenient_slot_offsets},
+        freelist::walk_freelist,
+        page::{PageHeader, PageProtection, PageType},
+        reader::{BTM_SUBDB, FileDbImageReader},
+        salvage::BestEffortScan,
+        types::{Endianness, PageNumber, PageSource},
+    },
+    util::u32e,
+};
+
+/// An allocated page that no tree, overflow chain or freelist link reaches.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OrphanPage {
+    pub pgno: PageNumber,
+    pub page_type: u8,
+    pub entries: u16,
+}
+
+/// The outcome of [`analyze_reachability`].
+#[derive(Debug, Clone, Default)]
+pub struct ReachabilityReport {
+    pub page_count: u64,
+    /// Pages reached from the meta page through trees and overflow chains.
+    pub reachable: BTreeSet<PageNumber>,
+    /// Pages on the freelist.
+    pub free: BTreeSet<PageNumber>,
+    pub orphans: Vec<OrphanPage>,
+}
+
+impl ReachabilityReport {
+    /// Orphans that are btree leaves, i.e. candidates for record recovery.
+    pub fn orphan_leaves(&self) -> impl Iterator<Item = PageNumber> + '_ {
+        self.orphans
+            .iter()
+            .filter(|o| PageType::from(o.page_type) == PageType::BtreeLeaf)
+            .map(|o| o.pgno)
+    }
+}
+
+impl fmt::Display for ReachabilityReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Reachability {{")?;
+        writeln!(f, "  pages        : {}", self.page_count)?;
+        writeln!(f, "  reachable    : {}", self.reachable.len())?;
+        writeln!(f, "  free         : {}", self.free.len())?;
+        writeln!(f, "  orphans      : {}", self.orphans.len())?;
+        for o in &self.orphans {
+            writeln!(
+                f,
+                "  orphan       : page {} type={:?} entries={}",
+                o.pgno,
+                PageType::from(o.page_type),
+                o.entries
+            )?;
+        }
+        write!(f, "}}")
+    }
+}
+
+/// Marks every page reachable from a set of roots. Unreadable pages end their branch.
+struct Marker<'a> {
+    source: &'a dyn PageSource,
+    endianness: Endianness,
+    protection: PageProtection,
+    seen: BTreeSet<PageNumber>,
+}
+
+impl Marker<'_> {
+    fn mark_tree(&mut self, root: PageNumber) {
+        let mut stack = vec![root];
+        while let Some(pgno) = stack.pop() {
+            if pgno == 0 || !self.seen.insert(pgno) {
+                continue;
+            }
+            let Ok(page) = self.source.read_page(pgno) else {
+                continue;
+            };
+            let Ok(hdr) = PageHeader::parse(&page, self.endianness, self.protection) else {
+                continue;
+            };
+            let e = self.endianness.into();
+            let (offsets, _) = lenient_slot_offsets(&page, &hdr, self.endianness, self.protection);
+            for off in offsets {
+                match hdr.kind() {
+                    PageType::BtreeInternal => {
+                        let Some(item) = page.get(off..off + BINTERNAL_SIZE) else {
+                            continue;
+                        };
+                        stack.push(u32e(e, &item[4..8]));
+                        let data = off + BINTERNAL_SIZE;
+                        if item[2] & 0x7f == B_OVERFLOW
+                            && let Some(r) = page.get(data..data + BOVERFLOW_SIZE)
+                        {
+                            self.mark_chain(u32e(e, &r[4..8]));
+                        }
+                    }
+                    PageType::RecnoInternal => {
+                        if let Some(item) = page.get(off..off + 4) {
+                            stack.push(u32e(e, item));
+                        }
+                    }
+                    PageType::BtreeLeaf | PageType::RecnoLeaf => {
+                        let Some(item) = page.get(off..off + BOVERFLOW_SIZE) else {
+                            continue;
+                        };
+                        match item[2] & 0x7f {
+                            B_OVERFLOW => self.mark_chain(u32e(e, &item[4..8])),
+                            B_DUPLICATE => stack.push(u32e(e, &item[4..8])),
+                            _ => {}
+                        }
+                    }
+                    _ => break,
+                }
+            }
+        }
+    }
+
+    fn mark_chain(&mut self, first: PageNumber) {
+        let mut pgno = first;
+        while pgno != 0 && self.seen.insert(pgno) {
+            let next = self
+                .source
+                .read_page(pgno)
+                .and_then(|p| PageHeader::parse(&p, self.endianness, self.protection));
+            match next {
+                Ok(hdr) if hdr.kind() == PageType::Overflow => pgno = hdr.next_pgno,
+                _ => break,
+            }
+        }
+    }
+}
+
+/// Compute reachable, free and orphaned pages of the image behind `reader`.
+pub fn analyze_reachability(reader: &FileDbImageReader) -> io::Result<ReachabilityReport> {
+    let source = reader.source().as_ref();
+    let meta = reader.meta();
+    let mut marker = Marker {
+        source,
+        endianness: reader.endianness(),
+        protection: reader.protection(),
+        seen: BTreeSet::from([0]),
+    };
+    marker.mark_tree(meta.root);
+    if meta.flags & BTM_SUBDB != 0 {
+        for sub in reader.subdatabases()? {
+            marker.seen.insert(sub.meta_pgno);
+            marker.mark_tree(sub.root);
+        }
+    }
+
+    let free: BTreeSet<PageNumber> = walk_freelist(source)?.pages.into_iter().collect();
+    let page_count = source.page_count().unwrap_or(meta.last_pgno as u64 + 1);
+    let mut orphans = Vec::new();
+    for pgno in 1..page_count as PageNumber {
+        if marker.seen.contains(&pgno) || free.contains(&pgno) {
+            continue;
+        }
+        let Ok(page) = source.read_page(pgno) else {
+            continue;
+        };
+        let Ok(hdr) = PageHeader::parse(&page, marker.endianness, marker.protection) else {
+            continue;
+        };
+        // Blank pages past a file extension are unallocated rather than orphaned.
+        if hdr.kind() == PageType::Invalid {
+            continue;
+        }
+        orphans.push(OrphanPage {
+            pgno,
+            page_type: hdr.page_type,
+            entries: hdr.entries,
+        });
+    }
+
+    Ok(ReachabilityReport {
+        page_count,
+        reachable: marker.seen,
+        free,
+        orphans,
+    })
+}
+
+impl FileDbImageReader {
+    /// Records on orphaned leaf pages, recovered with the best-effort page scan.
+    pub fn orphan_entries(&self) -> io::Result<BestEffortScan> {
+        let report = analyze_reachability(self)?;
+        let pages: HashSet<PageNumber> = report.orphan_leaves().collect();
+        Ok(self.best_effort_scan().only_pages(pages))
+    }
+}
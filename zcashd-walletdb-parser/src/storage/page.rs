@@ -1,23 +1,217 @@
+use core::fmt;
+#[cfg(feature = "std")]
 use std::{fmt::Debug, io};
 
-use crate::storage::types::{ByteSlice, ByteVec, DbIndex, LogSequenceNumber, PageNumber};
+#[cfg(feature = "std")]
+use crate::{
+    constants::PG_CHKSUM_OFF,
+    storage::types::{ByteSlice, Endianness},
+};
+use crate::{
+    constants::{
+        P_BTREEMETA, P_DUPLICATE, P_HASH, P_HASH_UNSORTED, P_HASHMETA, P_HEAP, P_HEAPMETA,
+        P_IBTREE, P_IHEAP, P_INVALID, P_IRECNO, P_LBTREE, P_LDUP, P_LRECNO, P_OVERFLOW, P_QAMDATA,
+        P_QAMMETA, PG_CHKSUM_SIZE, PG_CRYPTO_SIZE, SIZEOF_PAGE,
+    },
+    storage::types::{ByteVec, DbIndex, LogSequenceNumber, PageNumber},
+    util::{Endian, u16e, u32e},
+};
 
-/// The type of a BDB page.
+/// The type of a BDB page, decoded from header byte 25. Every code Berkeley DB writes, for
+/// every access method, has a variant; codes it never writes are kept as `Unknown`.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum PageType {
-    Meta,
+    /// `P_INVALID`: a free page, or one that was allocated but never written.
+    Invalid,
+    /// `__P_DUPLICATE`: an off-page duplicate page of Berkeley DB before 3.0.
+    Duplicate,
+    /// `P_HASH_UNSORTED`.
+    HashUnsorted,
+    /// `P_IBTREE`.
     BtreeInternal,
+    /// `P_IRECNO`.
+    RecnoInternal,
+    /// `P_LBTREE`.
     BtreeLeaf,
+    /// `P_LRECNO`.
+    RecnoLeaf,
+    /// `P_OVERFLOW`.
     Overflow,
+    /// `P_HASHMETA`.
+    HashMeta,
+    /// `P_BTREEMETA`: the meta page of a btree or recno database, e.g. page 0 of a wallet.
+    Meta,
+    /// `P_QAMMETA`.
+    QueueMeta,
+    /// `P_QAMDATA`.
+    QueueData,
+    /// `P_LDUP`: a leaf of an off-page duplicate tree.
+    DuplicateLeaf,
+    /// `P_HASH`.
+    Hash,
+    /// `P_HEAPMETA`.
+    HeapMeta,
+    /// `P_HEAP`.
+    Heap,
+    /// `P_IHEAP`.
+    HeapInternal,
     Unknown(u8),
 }
 
+impl From<u8> for PageType {
+    fn from(code: u8) -> Self {
+        match code {
+            P_INVALID => PageType::Invalid,
+            P_DUPLICATE => PageType::Duplicate,
+            P_HASH_UNSORTED => PageType::HashUnsorted,
+            P_IBTREE => PageType::BtreeInternal,
+            P_IRECNO => PageType::RecnoInternal,
+            P_LBTREE => PageType::BtreeLeaf,
+            P_LRECNO => PageType::RecnoLeaf,
+            P_OVERFLOW => PageType::Overflow,
+            P_HASHMETA => PageType::HashMeta,
+            P_BTREEMETA => PageType::Meta,
+            P_QAMMETA => PageType::QueueMeta,
+            P_QAMDATA => PageType::QueueData,
+            P_LDUP => PageType::DuplicateLeaf,
+            P_HASH => PageType::Hash,
+            P_HEAPMETA => PageType::HeapMeta,
+            P_HEAP => PageType::Heap,
+            P_IHEAP => PageType::HeapInternal,
+            x => PageType::Unknown(x),
+        }
+    }
+}
+
+impl From<PageType> for u8 {
+    fn from(page_type: PageType) -> Self {
+        page_type.code()
+    }
+}
+
+impl PageType {
+    /// The on-disk type code.
+    pub const fn code(self) -> u8 {
+        match self {
+            PageType::Invalid => P_INVALID,
+            PageType::Duplicate => P_DUPLICATE,
+            PageType::HashUnsorted => P_HASH_UNSORTED,
+            PageType::BtreeInternal => P_IBTREE,
+            PageType::RecnoInternal => P_IRECNO,
+            PageType::BtreeLeaf => P_LBTREE,
+            PageType::RecnoLeaf => P_LRECNO,
+            PageType::Overflow => P_OVERFLOW,
+            PageType::HashMeta => P_HASHMETA,
+            PageType::Meta => P_BTREEMETA,
+            PageType::QueueMeta => P_QAMMETA,
+            PageType::QueueData => P_QAMDATA,
+            PageType::DuplicateLeaf => P_LDUP,
+            PageType::Hash => P_HASH,
+            PageType::HeapMeta => P_HEAPMETA,
+            PageType::Heap => P_HEAP,
+            PageType::HeapInternal => P_IHEAP,
+            PageType::Unknown(x) => x,
+        }
+    }
+
+    /// Human-readable name.
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            PageType::Invalid => "invalid",
+            PageType::Duplicate => "duplicate",
+            PageType::HashUnsorted => "hash unsorted",
+            PageType::BtreeInternal => "btree internal",
+            PageType::RecnoInternal => "recno internal",
+            PageType::BtreeLeaf => "btree leaf",
+            PageType::RecnoLeaf => "recno leaf",
+            PageType::Overflow => "overflow",
+            PageType::HashMeta => "hash meta",
+            PageType::Meta => "btree meta",
+            PageType::QueueMeta => "queue meta",
+            PageType::QueueData => "queue data",
+            PageType::DuplicateLeaf => "duplicate leaf",
+            PageType::Hash => "hash",
+            PageType::HeapMeta => "heap meta",
+            PageType::Heap => "heap",
+            PageType::HeapInternal => "heap internal",
+            PageType::Unknown(_) => "unknown",
+        }
+    }
+
+    /// Whether this is the meta page of some access method, whose byte 24 is
+    /// `encrypt_alg` rather than a level.
+    pub const fn is_meta(self) -> bool {
+        matches!(
+            self,
+            PageType::Meta | PageType::HashMeta | PageType::QueueMeta | PageType::HeapMeta
+        )
+    }
+}
+
+impl fmt::Display for PageType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// `level` of a btree or recno leaf page (`LEAFLEVEL`); each internal level adds one.
+pub const LEAFLEVEL: u8 = 1;
+
+/// What a page's `level` byte says about its place in a tree.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PageLevel {
+    /// Level 0: pages outside the tree structure (overflow, free and invalid pages).
+    Unleveled,
+    /// [`LEAFLEVEL`].
+    Leaf,
+    /// Above [`LEAFLEVEL`]: an internal page whose children sit one level lower.
+    Internal(u8),
+}
+
+impl From<u8> for PageLevel {
+    fn from(level: u8) -> Self {
+        match level {
+            0 => PageLevel::Unleveled,
+            LEAFLEVEL => PageLevel::Leaf,
+            n => PageLevel::Internal(n),
+        }
+    }
+}
+
+/// A page whose `level` does not fit its type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LevelTypeMismatch {
+    pub page_type: PageType,
+    pub level: u8,
+}
+
+impl fmt::Display for LevelTypeMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let expected = match self.page_type {
+            PageType::BtreeLeaf | PageType::RecnoLeaf | PageType::DuplicateLeaf => "level 1",
+            PageType::BtreeInternal | PageType::RecnoInternal => "a level above 1",
+            _ => "level 0",
+        };
+        write!(
+            f,
+            "{:?} page at level {}, expected {expected}",
+            self.page_type, self.level
+        )
+    }
+}
+
+impl core::error::Error for LevelTypeMismatch {}
+
 /// The header of a BDB page.
 #[derive(Debug, Clone)]
 pub struct PageHeader {
-    /// 00-07: Log sequence number (LSN)
+    /// 00-07: Log sequence number (LSN), packed as `file << 32 | offset`
     pub lsn: LogSequenceNumber,
 
+    /// 08-11: Page number this page claims to be
+    pub pgno: PageNumber,
+
     /// 12-15: Prev in page chain (overflow chain or sequential)
     pub prev_pgno: PageNumber,
 
@@ -38,26 +232,140 @@ pub struct PageHeader {
     pub page_type: u8,
 
     /// Optional flags, checksum, or extended metadata.
-    /// Not all builds enable checksums or the same flags.
+    /// Not all builds enable checksums or the same flags; `checksum` holds the 4-byte
+    /// DB_CHKSUM hash (encrypted databases use a 20-byte HMAC instead).
     pub flags: Option<u32>,
     pub checksum: Option<u32>,
 }
 
 impl PageHeader {
+    /// Decode the generic page header from the first [`SIZEOF_PAGE`] bytes of `raw`,
+    /// which the caller has checked are present.
+    pub(crate) fn decode(raw: &[u8], e: Endian) -> Self {
+        PageHeader {
+            lsn: ((u32e(e, &raw[0..4]) as u64) << 32) | u32e(e, &raw[4..8]) as u64,
+            pgno: u32e(e, &raw[8..12]),
+            prev_pgno: u32e(e, &raw[12..16]),
+            next_pgno: u32e(e, &raw[16..20]),
+            entries: u16e(e, &raw[20..22]),
+            hf_offset: u16e(e, &raw[22..24]),
+            level: raw[24],
+            page_type: raw[25],
+            flags: None,
+            checksum: None,
+        }
+    }
+
+    /// Parse the generic page header from the start of `raw`.
+    /// With `PageProtection::Checksum` the hash stored after the header is read as well.
+    #[cfg(feature = "std")]
+    pub fn parse(
+        raw: &[u8],
+        endianness: Endianness,
+        protection: PageProtection,
+    ) -> io::Result<Self> {
+        let needed = protection.header_size();
+        if raw.len() < needed {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                format!("page header needs {needed} bytes, got {}", raw.len()),
+            ));
+        }
+        let e = endianness.into();
+        let checksum = (protection == PageProtection::Checksum)
+            .then(|| u32e(e, &raw[PG_CHKSUM_OFF..PG_CHKSUM_OFF + 4]));
+        Ok(PageHeader {
+            checksum,
+            ..PageHeader::decode(raw, e)
+        })
+    }
+
+    /// Decoded page type.
+    pub fn kind(&self) -> PageType {
+        PageType::from(self.page_type)
+    }
+
+    /// Decoded `level`. Meaningless on meta pages, whose byte 24 is `encrypt_alg`.
+    pub fn page_level(&self) -> PageLevel {
+        PageLevel::from(self.level)
+    }
+
+    /// Check that `level` agrees with the page type: leaves sit at [`LEAFLEVEL`], internal
+    /// pages above it, and overflow, free, hash, queue and heap pages at 0. Meta pages
+    /// always pass.
+    pub fn check_level(&self) -> Result<PageLevel, LevelTypeMismatch> {
+        let level = self.page_level();
+        let kind = self.kind();
+        let ok = match kind {
+            _ if kind.is_meta() => true,
+            PageType::BtreeLeaf | PageType::RecnoLeaf | PageType::DuplicateLeaf => {
+                level == PageLevel::Leaf
+            }
+            PageType::BtreeInternal | PageType::RecnoInternal => {
+                matches!(level, PageLevel::Internal(_))
+            }
+            _ => level == PageLevel::Unleveled,
+        };
+        if ok {
+            Ok(level)
+        } else {
+            Err(LevelTypeMismatch {
+                page_type: self.kind(),
+                level: self.level,
+            })
+        }
+    }
+
     /// Derived: number of slots as usize
     pub fn num_slots(&self) -> usize {
-        todo!()
+        self.entries as usize
     }
 
     /// Derived: the lower boundary (end of header + slot array) in bytes,
     /// computed from header size and `entries`.
     pub fn lower_bound(&self, header_size: usize, slot_entry_size: usize) -> usize {
-        todo!()
+        header_size + self.num_slots() * slot_entry_size
     }
 
     /// Derived: the upper boundary in bytes (hf_offset as usize).
     pub fn upper_bound(&self) -> usize {
-        todo!()
+        self.hf_offset as usize
+    }
+}
+
+/// What follows the generic page header on every non-meta page.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum PageProtection {
+    /// Nothing: the slot array starts right after the header.
+    #[default]
+    None,
+    /// `PG_CHKSUM`: a 4-byte hash (DB_CHKSUM).
+    Checksum,
+    /// `PG_CRYPTO`: a 20-byte HMAC and a 16-byte IV (DB_ENCRYPT).
+    Encrypted,
+}
+
+impl PageProtection {
+    /// Derive the protection from the meta page `encrypt_alg` and `metaflags` fields.
+    pub fn from_meta(encrypt_alg: u8, checksummed: bool) -> Self {
+        if encrypt_alg != 0 {
+            PageProtection::Encrypted
+        } else if checksummed {
+            PageProtection::Checksum
+        } else {
+            PageProtection::None
+        }
+    }
+
+    /// Size of the page header including the protection block, i.e. where the slot array
+    /// (or overflow payload) starts (`P_OVERHEAD`).
+    pub const fn header_size(self) -> usize {
+        match self {
+            PageProtection::None => SIZEOF_PAGE,
+            PageProtection::Checksum => SIZEOF_PAGE + PG_CHKSUM_SIZE,
+            PageProtection::Encrypted => SIZEOF_PAGE + PG_CRYPTO_SIZE,
+        }
     }
 }
 
@@ -81,6 +389,7 @@ pub struct EntryDescriptor {
 
 /// Encapsulates the ability to materialize a blob for an entry.
 /// Implementations may capture references into a page buffer and a PageSource for overflow follow-ups.
+#[cfg(feature = "std")]
 pub trait ValueSupplier: Send + Sync + Debug {
     /// Materialize the full value bytes. Follows overflow references.
     fn materialize(&self) -> io::Result<ByteVec>;
@@ -88,4 +397,12 @@ pub trait ValueSupplier: Send + Sync + Debug {
     /// Attempt a zero-copy borrow if the value is fully contained in-memory.
     /// Returns None if borrowing is not possible or value requires concatenation.
     fn try_borrow<'a>(&'a self) -> Option<ByteSlice<'a>>;
+
+    /// Write the full value bytes to `sink` and return how many were written. Overflow
+    /// values are streamed page by page rather than materialized first.
+    fn write_to(&self, sink: &mut dyn io::Write) -> io::Result<u64> {
+        let value = self.materialize()?;
+        sink.write_all(&value)?;
+        Ok(value.len() as u64)
+    }
 }
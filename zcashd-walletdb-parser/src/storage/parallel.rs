@@ -0,0 +1,239 @@
+//! Multi-threaded materialization of leaf pages, behind the `parallel` feature.
+//!
+//! Reading runs in two phases. Leaf pages are handed out to worker threads one at a time
+//! and decoded, copying inline values as they go. Values that live elsewhere (overflow
+//! chains and blob files) are then resolved in a second phase, in chunks spread over
+//! every thread: in a large wallet nearly every transaction is an overflow value, and a
+//! leaf full of them would otherwise keep one thread busy while the rest sit idle. The
+//! results are merged back in the order the sequential
+//! [`entries`](crate::storage::consistency::DbImageReader::entries) would have produced them.
+
+use std::{
+    io,
+    sync::{
+        Mutex,
+        atomic::{AtomicUsize, Ordering},
+    },
+    thread,
+};
+
+use crate::storage::{
+    consistency::{Finding, ParseMode, SalvageMode},
+    entry::{Confidence, ConflictPolicy, InMemoryMap, OrderedWalletMap, Provenance},
+    page::ValueSupplier,
+    reader::{DataTree, FileDbImageReader},
+    salvage::ScanItem,
+    types::{ByteVec, PageNumber},
+};
+
+/// A key, its fully materialized value, and where it was found.
+pub type MaterializedEntry = (ByteVec, ByteVec, Provenance);
+
+/// Run `f` over every element of `work` on up to `threads` threads and return the results
+/// in input order.
+fn par_map<W, T, F>(work: &[W], threads: usize, f: F) -> Vec<T>
+where
+    W: Sync,
+    T: Send,
+    F: Fn(&W) -> T + Sync,
+{
+    let threads = threads.clamp(1, work.len().max(1));
+    let next = AtomicUsize::new(0);
+    let results = Mutex::new(Vec::with_capacity(work.len()));
+    thread::scope(|s| {
+        for _ in 0..threads {
+            s.spawn(|| {
+                loop {
+                    let i = next.fetch_add(1, Ordering::Relaxed);
+                    let Some(w) = work.get(i) else {
+                        break;
+                    };
+                    let out = f(w);
+                    if let Ok(mut r) = results.lock() {
+                        r.push((i, out));
+                    }
+                }
+            });
+        }
+    });
+    let mut results = results.into_inner().unwrap_or_default();
+    results.sort_unstable_by_key(|(i, _)| *i);
+    results.into_iter().map(|(_, out)| out).collect()
+}
+
+type PageEntries = Vec<ScanItem>;
+
+/// Deferred values per chunk and thread: enough chunks that a few long chains do not
+/// leave the other threads idle, few enough that handing them out stays cheap.
+const CHUNKS_PER_THREAD: usize = 4;
+
+/// Materialize every value of `entries` on up to `threads` threads, keeping their order.
+///
+/// Inline values are copied in place; overflow and blob values are resolved concurrently,
+/// in chunks. Each entry keeps its own result, so one broken chain does not hide the rest.
+pub fn par_materialize(
+    entries: Vec<(ByteVec, Box<dyn ValueSupplier>, Provenance)>,
+    threads: usize,
+) -> Vec<io::Result<MaterializedEntry>> {
+    let mut values: Vec<Option<io::Result<ByteVec>>> = Vec::with_capacity(entries.len());
+    let mut deferred = Vec::new();
+    for (i, (_, value, _)) in entries.iter().enumerate() {
+        match value.try_borrow() {
+            Some(bytes) => values.push(Some(Ok(bytes.to_vec()))),
+            None => {
+                values.push(None);
+                deferred.push(i);
+            }
+        }
+    }
+    let chunk_len = deferred
+        .len()
+        .div_ceil(threads.max(1) * CHUNKS_PER_THREAD)
+        .max(1);
+    let chunks: Vec<&[usize]> = deferred.chunks(chunk_len).collect();
+    let resolved = par_map(&chunks, threads, |chunk| {
+        chunk
+            .iter()
+            .map(|&i| entries[i].1.materialize())
+            .collect::<Vec<_>>()
+    });
+    for (&i, value) in deferred.iter().zip(resolved.into_iter().flatten()) {
+        values[i] = Some(value);
+    }
+    entries
+        .into_iter()
+        .zip(values)
+        .map(|((key, _, provenance), value)| {
+            let value = value.unwrap_or_else(|| Err(io::Error::other("value not resolved")))?;
+            Ok((key, value, provenance))
+        })
+        .collect()
+}
+
+/// A unit of work for [`FileDbImageReader::par_tree_pages`].
+enum Work {
+    /// One leaf page of the btree rooted at the first page.
+    Leaf(PageNumber, PageNumber),
+    /// A whole recno tree.
+    Recno(DataTree),
+}
+
+impl FileDbImageReader {
+    /// Parallel counterpart of `entries` + `materialize`: same entries, same order.
+    ///
+    /// As with the sequential reader, anomalies are recorded as findings and skipped in
+    /// [`ParseMode::Lenient`]. In [`ParseMode::Strict`] any anomaly, found here or when
+    /// the image was opened, is an error.
+    pub fn par_entries(
+        &self,
+        salvage: SalvageMode,
+        threads: usize,
+    ) -> io::Result<Vec<MaterializedEntry>> {
+        let strict = self.parse_mode() == ParseMode::Strict;
+        if strict && let Some(e) = self.strict_failure(0) {
+            return Err(e);
+        }
+        let pages = match salvage {
+            SalvageMode::Conservative => self.par_tree_pages(threads),
+            SalvageMode::BestEffort => self.par_scan_pages(threads),
+        };
+        let entries: Vec<ScanItem> = pages.into_iter().flatten().collect();
+        let page_nos: Vec<PageNumber> = entries.iter().map(|(_, _, p)| p.page_no).collect();
+        let mut out = Vec::with_capacity(entries.len());
+        for (item, page_no) in par_materialize(entries, threads).into_iter().zip(page_nos) {
+            match item {
+                Ok(entry) => out.push(entry),
+                Err(e) if strict => return Err(e),
+                Err(e) => self.record(Finding {
+                    page_no: Some(page_no),
+                    ..self.walk_error(e)
+                }),
+            }
+        }
+        if strict {
+            if let Some(e) = self.strict_failure(0) {
+                return Err(e);
+            }
+            if let Some((_, _, provenance)) = out
+                .iter()
+                .find(|(_, _, p)| p.confidence != Confidence::High)
+            {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!(
+                        "page {} slot {}: pair read with {:?} confidence",
+                        provenance.page_no, provenance.slot_index, provenance.confidence
+                    ),
+                ));
+            }
+        }
+        Ok(out)
+    }
+
+    /// Parallel counterpart of `build_map_with`.
+    pub fn par_build_map(
+        &self,
+        salvage: SalvageMode,
+        policy: ConflictPolicy,
+        threads: usize,
+    ) -> io::Result<Box<dyn InMemoryMap>> {
+        let mut map = OrderedWalletMap::new();
+        for (key, value, provenance) in self.par_entries(salvage, threads)? {
+            self.insert_resolving(&mut map, key, value, provenance, policy)?;
+        }
+        Ok(Box::new(map))
+    }
+
+    /// Decoded leaf pages of every data tree, in key order.
+    fn par_tree_pages(&self, threads: usize) -> Vec<PageEntries> {
+        let roots = match self.data_roots() {
+            Ok(roots) => roots,
+            Err(e) => {
+                self.record(self.walk_error(e));
+                return Vec::new();
+            }
+        };
+        let mut work = Vec::new();
+        for tree in roots {
+            if tree.recno.is_some() {
+                // Record numbers depend on every preceding leaf, so recno trees are walked
+                // as a single unit.
+                work.push(Work::Recno(tree));
+                continue;
+            }
+            match self.walker(tree.root).leaf_pages() {
+                Ok(pages) => work.extend(pages.into_iter().map(|pgno| Work::Leaf(tree.root, pgno))),
+                Err(e) => self.record(self.walk_error(e)),
+            }
+        }
+        let decoded = par_map(&work, threads, |work| match *work {
+            Work::Leaf(root, pgno) => self.walker(root).read_leaf(pgno),
+            Work::Recno(tree) => Ok(self.tree_entries(tree).collect()),
+        });
+        let mut out = Vec::with_capacity(decoded.len());
+        for page in decoded {
+            let mut entries = Vec::new();
+            for item in page.into_iter().flatten() {
+                match item {
+                    Ok(entry) => entries.push(entry),
+                    Err(e) => self.record(self.walk_error(e)),
+                }
+            }
+            out.push(entries);
+        }
+        out
+    }
+
+    /// Every salvageable leaf page of the image, in page order.
+    fn par_scan_pages(&self, threads: usize) -> Vec<PageEntries> {
+        let scan = self.best_effort_scan();
+        let Some(count) = self.source().page_count() else {
+            // An unsized source has to be read front to back.
+            return vec![scan.collect()];
+        };
+        let pages: Vec<PageNumber> = (1..count as PageNumber).collect();
+        par_map(&pages, threads, |&pgno| {
+            scan.scan_pgno(pgno).unwrap_or_default()
+        })
+    }
+}
@@ -0,0 +1,519 @@
+//! [`DbImageReader`] implementation tying the page source, the btree walker and the
+//! in-memory map together.
+
+use std::{
+    collections::HashSet,
+    fs::File,
+    io,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use crate::{
+    headers::{BtreeMeta, parse_btree_meta_page0},
+    storage::{
+        blob::{BlobDirectory, BlobResolver},
+        btree::{TreeWalker, WalkItem},
+        cache::{CachedPageSource, DEFAULT_CACHE_PAGES},
+        consistency::{DbImageReader, Finding, FindingKind, ParseMode, SalvageMode, Severity},
+        entry::{Confidence, ConflictPolicy, InMemoryMap, MapEntry, OrderedWalletMap, Provenance},
+        meta_recovery::{meta_problem, rebuild_meta},
+        page::{PageHeader, PageProtection, ValueSupplier},
+        recno::{RecnoFormat, RecnoWalker},
+        salvage::{BestEffortScan, SlotFilter},
+        source::{FilePageSource, MemoryPageSource, ReaderPageSource},
+        types::{ByteVec, Endianness, FormatProfile, LogSequenceNumber, PageNumber, PageSource},
+        version::BtreeVersion,
+    },
+    util::u32e,
+};
+
+pub use crate::storage::flags::BTM_SUBDB;
+
+/// A named database stored inside a multi-database file (zcashd keeps its records in "main").
+#[derive(Debug, Clone)]
+pub struct Subdatabase {
+    pub name: ByteVec,
+    pub meta_pgno: PageNumber,
+    pub root: PageNumber,
+    /// Set when the subdatabase is a recno rather than a btree database.
+    pub recno: Option<RecnoFormat>,
+}
+
+/// A tree holding records, and how to walk it.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct DataTree {
+    pub(crate) root: PageNumber,
+    pub(crate) recno: Option<RecnoFormat>,
+}
+
+/// The meta page of a subdatabase, from its entry in the master database. The page
+/// number is stored in network byte order.
+pub(crate) fn subdb_meta_pgno(value: &[u8]) -> io::Result<PageNumber> {
+    match <[u8; 4]>::try_from(value) {
+        Ok(pgno) => Ok(u32::from_be_bytes(pgno)),
+        Err(_) => Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "subdatabase entry does not hold a page number",
+        )),
+    }
+}
+
+/// The page protection `meta` declares, read as `version` (`None` for versions older than
+/// any the crate knows).
+fn protection_of(meta: &BtreeMeta, version: Option<BtreeVersion>) -> PageProtection {
+    // Before version 9 the encrypt_alg and metaflags bytes were unused padding.
+    let encrypt_alg = if version.is_some_and(BtreeVersion::supports_encryption) {
+        meta.encrypt_alg
+    } else {
+        0
+    };
+    let checksummed = version.is_some_and(BtreeVersion::supports_checksums) && meta.has_checksum();
+    PageProtection::from_meta(encrypt_alg, checksummed)
+}
+
+fn format_profile(meta: &BtreeMeta, page_count: Option<u64>) -> FormatProfile {
+    let version = BtreeVersion::resolve(meta.version).ok().map(|(v, _)| v);
+    let protection = protection_of(meta, version);
+    FormatProfile {
+        page_size: meta.pagesize,
+        endianness: meta.endian.into(),
+        btree_root: meta.root,
+        meta_version: meta.version,
+        berkeley_db_version: Some(BtreeVersion::describe(meta.version)),
+        protection,
+        checksummed: protection != PageProtection::None,
+        encrypted: protection == PageProtection::Encrypted,
+        has_subdatabases: meta.flags & BTM_SUBDB != 0,
+        last_pgno: meta.last_pgno,
+        page_count,
+    }
+}
+
+/// Describe the image behind `source` from its meta page alone, without walking any tree.
+/// Unlike opening a [`FileDbImageReader`], this also answers for encrypted images and for
+/// versions the crate cannot read.
+pub fn probe(source: &dyn PageSource) -> io::Result<FormatProfile> {
+    let page0 = source.read_page(0)?;
+    let meta = parse_btree_meta_page0(&page0)?;
+    Ok(format_profile(&meta, source.page_count()))
+}
+
+/// Reads a Berkeley DB btree image through a [`PageSource`].
+///
+/// Problems hit while iterating are recorded as findings (see [`Self::diagnostics`]) since
+/// the entries iterator cannot return errors itself.
+pub struct FileDbImageReader {
+    source: Arc<dyn PageSource>,
+    meta: BtreeMeta,
+    version: BtreeVersion,
+    diagnostics: Mutex<Vec<Finding>>,
+    blobs: Option<Arc<dyn BlobResolver>>,
+    mode: ParseMode,
+}
+
+impl FileDbImageReader {
+    /// Open a file on disk, behind an LRU cache of [`DEFAULT_CACHE_PAGES`] pages. Values
+    /// stored in blob files are read from the default blob directory next to it, if any.
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref();
+        let source = FilePageSource::open(path)?;
+        let reader =
+            Self::from_source(Arc::new(CachedPageSource::new(source, DEFAULT_CACHE_PAGES)))?;
+        Ok(match BlobDirectory::beside(path) {
+            Some(dir) => reader.with_blob_resolver(Arc::new(dir)),
+            None => reader,
+        })
+    }
+
+    /// Open a wallet embedded `offset` bytes into a file, e.g. in a container or a disk
+    /// image, behind the same cache as [`Self::open`].
+    pub fn open_at(path: impl AsRef<Path>, offset: u64) -> io::Result<Self> {
+        let path = path.as_ref();
+        let id = format!("{}@{offset}", path.display());
+        let source = ReaderPageSource::new(File::open(path)?, offset, id)?;
+        Self::from_source(Arc::new(CachedPageSource::new(source, DEFAULT_CACHE_PAGES)))
+    }
+
+    /// Read from any page source. Encrypted databases must be wrapped in a
+    /// `DecryptingPageSource` first.
+    pub fn from_source(source: Arc<dyn PageSource>) -> io::Result<Self> {
+        let page0 = source.read_page(0)?;
+        let meta = parse_btree_meta_page0(&page0)?;
+        Self::with_meta(source, meta)
+    }
+
+    /// Read an image held in memory. If its meta page fails [`meta_problem`], the meta
+    /// page is rebuilt from the rest of the image (see [`rebuild_meta`]) and the rebuild
+    /// is recorded among the diagnostics.
+    pub fn from_image(image: ByteVec, source_id: impl Into<String>) -> io::Result<Self> {
+        let problem = match parse_btree_meta_page0(&image) {
+            Ok(meta) => match meta_problem(&meta, &image) {
+                None => {
+                    let source = MemoryPageSource::new(image, meta.pagesize, source_id)?;
+                    return Self::with_meta(Arc::new(source), meta);
+                }
+                Some(problem) => problem,
+            },
+            Err(e) => e.to_string(),
+        };
+        let Some(rebuilt) = rebuild_meta(&image) else {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{problem}, and no meta page could be rebuilt"),
+            ));
+        };
+        let origin = rebuilt.origin();
+        let source = MemoryPageSource::new(image, rebuilt.meta.pagesize, source_id)?;
+        let reader = Self::with_meta(Arc::new(source), rebuilt.meta)?;
+        reader.record(Finding {
+            page_no: Some(0),
+            severity: Severity::Warning,
+            kind: FindingKind::MetaRebuilt {
+                template: rebuilt.template,
+            },
+            message: format!("{problem}; rebuilt the meta page from {origin}"),
+        });
+        Ok(reader)
+    }
+
+    /// Read from `source` using `meta` in place of its meta page, e.g. one rebuilt by
+    /// [`rebuild_meta`] or parsed from a decrypted copy of page 0.
+    pub fn with_meta(source: Arc<dyn PageSource>, meta: BtreeMeta) -> io::Result<Self> {
+        let (version, warning) = BtreeVersion::resolve(meta.version)?;
+        let reader = FileDbImageReader {
+            source,
+            meta,
+            version,
+            diagnostics: Mutex::new(Vec::new()),
+            blobs: None,
+            mode: ParseMode::default(),
+        };
+        if let Some(message) = warning {
+            reader.record(Finding {
+                page_no: Some(0),
+                severity: Severity::Warning,
+                kind: FindingKind::UnknownVersion {
+                    version: reader.meta.version,
+                },
+                message,
+            });
+        }
+        let expected_pages = reader.meta.last_pgno as u64 + 1;
+        if let Some(actual_pages) = reader.source.page_count()
+            && actual_pages < expected_pages
+        {
+            reader.record(Finding {
+                page_no: None,
+                severity: Severity::Warning,
+                kind: FindingKind::Truncated {
+                    expected_pages,
+                    actual_pages,
+                },
+                message: format!(
+                    "image is truncated: meta page expects {expected_pages} pages, found {actual_pages}"
+                ),
+            });
+        }
+        Ok(reader)
+    }
+
+    /// Read values stored in blob files (`B_BLOB` items) through `resolver`. Without one,
+    /// materializing such a value fails.
+    pub fn with_blob_resolver(mut self, resolver: Arc<dyn BlobResolver>) -> Self {
+        self.blobs = Some(resolver);
+        self
+    }
+
+    /// Read values stored in blob files from the blob directory `dir`.
+    pub fn with_blob_dir(self, dir: impl Into<PathBuf>) -> Self {
+        self.with_blob_resolver(Arc::new(BlobDirectory::new(dir)))
+    }
+
+    /// Choose what `build_map` does on a structural anomaly; see [`ParseMode`]. The
+    /// `entries` iterator cannot fail, so it always records anomalies and goes on.
+    pub fn with_parse_mode(mut self, mode: ParseMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    pub fn parse_mode(&self) -> ParseMode {
+        self.mode
+    }
+
+    pub fn meta(&self) -> &BtreeMeta {
+        &self.meta
+    }
+
+    pub fn source(&self) -> &Arc<dyn PageSource> {
+        &self.source
+    }
+
+    /// The on-disk format version the image is read as.
+    pub fn version(&self) -> BtreeVersion {
+        self.version
+    }
+
+    pub(crate) fn endianness(&self) -> Endianness {
+        self.meta.endian.into()
+    }
+
+    pub(crate) fn protection(&self) -> PageProtection {
+        protection_of(&self.meta, Some(self.version))
+    }
+
+    /// Findings recorded so far by `entries`/`build_map`.
+    pub fn diagnostics(&self) -> Vec<Finding> {
+        self.diagnostics
+            .lock()
+            .map(|d| d.clone())
+            .unwrap_or_default()
+    }
+
+    pub(crate) fn record(&self, finding: Finding) {
+        if let Ok(mut d) = self.diagnostics.lock() {
+            d.push(finding);
+        }
+    }
+
+    pub(crate) fn walker(&self, root: PageNumber) -> TreeWalker {
+        TreeWalker {
+            source: self.source.clone(),
+            root,
+            endianness: self.endianness(),
+            protection: self.protection(),
+            blobs: self.blobs.clone(),
+        }
+    }
+
+    /// Subdatabases listed in the master database, or an empty list for single-database files.
+    pub fn subdatabases(&self) -> io::Result<Vec<Subdatabase>> {
+        if self.meta.flags & BTM_SUBDB == 0 {
+            return Ok(Vec::new());
+        }
+        let mut out = Vec::new();
+        for item in self.walker(self.meta.root).entries() {
+            let (name, value, _) = item?;
+            let meta_pgno = subdb_meta_pgno(&value.materialize()?)?;
+            let sub_meta = self.source.read_page(meta_pgno)?;
+            let root = u32e(self.meta.endian, &sub_meta[88..92]);
+            out.push(Subdatabase {
+                name,
+                meta_pgno,
+                root,
+                recno: RecnoFormat::from_meta_page(&sub_meta, self.endianness()),
+            });
+        }
+        Ok(out)
+    }
+
+    /// Roots of the trees holding the actual records.
+    pub(crate) fn data_roots(&self) -> io::Result<Vec<DataTree>> {
+        if self.meta.flags & BTM_SUBDB == 0 {
+            return Ok(vec![DataTree {
+                root: self.meta.root,
+                recno: RecnoFormat::from_meta(&self.meta),
+            }]);
+        }
+        Ok(self
+            .subdatabases()?
+            .into_iter()
+            .map(|s| DataTree {
+                root: s.root,
+                recno: s.recno,
+            })
+            .collect())
+    }
+
+    /// Walk one data tree. Recno records are keyed by
+    /// [`recno_key`](crate::storage::recno::recno_key).
+    pub(crate) fn tree_entries(&self, tree: DataTree) -> Box<dyn Iterator<Item = WalkItem>> {
+        match tree.recno {
+            Some(format) => Box::new(
+                RecnoWalker {
+                    source: self.source.clone(),
+                    root: tree.root,
+                    endianness: self.endianness(),
+                    protection: self.protection(),
+                    format,
+                    blobs: self.blobs.clone(),
+                }
+                .into_entries(),
+            ),
+            None => Box::new(self.walker(tree.root).into_entries()),
+        }
+    }
+
+    /// Whole-file scan used by [`SalvageMode::BestEffort`]. The meta page and, when the
+    /// master database can still be read, its subdatabase pages are excluded.
+    pub fn best_effort_scan(&self) -> BestEffortScan {
+        let mut skip = HashSet::from([0]);
+        if self.meta.flags & BTM_SUBDB != 0 {
+            skip.insert(self.meta.root);
+            if let Ok(subs) = self.subdatabases() {
+                skip.extend(subs.iter().map(|s| s.meta_pgno));
+            }
+        }
+        let scan = BestEffortScan::new(
+            self.source.clone(),
+            self.endianness(),
+            self.protection(),
+            skip,
+        );
+        match &self.blobs {
+            Some(resolver) => scan.with_blobs(resolver.clone()),
+            None => scan,
+        }
+    }
+
+    /// Whole-file scan for tombstoned pairs that are still physically present.
+    pub fn deleted_entries(&self) -> BestEffortScan {
+        self.best_effort_scan().with_filter(SlotFilter::Deleted)
+    }
+
+    /// The LSN of page `pgno`: the log record that last changed it, or the reset LSN
+    /// `[0][1]` when the database was detached from its environment.
+    pub fn page_lsn(&self, pgno: PageNumber) -> io::Result<LogSequenceNumber> {
+        let page = self.source.read_page(pgno)?;
+        PageHeader::parse(&page, self.endianness(), self.protection()).map(|hdr| hdr.lsn)
+    }
+
+    /// Add a pair to a map under construction, settling duplicates by `policy`.
+    pub(crate) fn insert_resolving(
+        &self,
+        map: &mut OrderedWalletMap,
+        key: ByteVec,
+        value: ByteVec,
+        provenance: Provenance,
+        policy: ConflictPolicy,
+    ) -> io::Result<()> {
+        let entry = MapEntry {
+            key,
+            value,
+            meta: Some(provenance),
+            duplicates: Vec::new(),
+        };
+        map.insert_resolving(entry, policy, |e| {
+            self.page_lsn(e.meta.as_ref()?.page_no).ok()
+        })
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    /// The first anomaly recorded so far that [`ParseMode::Strict`] refuses, as an error.
+    pub(crate) fn strict_failure(&self, since: usize) -> Option<io::Error> {
+        let diagnostics = self.diagnostics();
+        let finding = diagnostics
+            .get(since..)?
+            .iter()
+            .find(|f| f.severity >= Severity::Warning)?;
+        Some(io::Error::new(
+            io::ErrorKind::InvalidData,
+            finding.message.clone(),
+        ))
+    }
+
+    /// [`ParseMode::Strict`] counterpart of `build_map_with`: anything short of a clean
+    /// read of every record is an error, including anomalies found when the image was
+    /// opened and pairs the best-effort scan could only partly recover.
+    fn build_map_strict(
+        &self,
+        salvage: SalvageMode,
+        policy: ConflictPolicy,
+    ) -> io::Result<Box<dyn InMemoryMap>> {
+        if let Some(e) = self.strict_failure(0) {
+            return Err(e);
+        }
+        let items: Box<dyn Iterator<Item = WalkItem>> = match salvage {
+            SalvageMode::Conservative => Box::new(
+                self.data_roots()?
+                    .into_iter()
+                    .flat_map(|tree| self.tree_entries(tree)),
+            ),
+            SalvageMode::BestEffort => Box::new(self.best_effort_scan().map(Ok)),
+        };
+        let mut map = OrderedWalletMap::new();
+        for item in items {
+            let (key, value, provenance) = item?;
+            if provenance.confidence != Confidence::High {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!(
+                        "page {} slot {}: pair read with {:?} confidence",
+                        provenance.page_no, provenance.slot_index, provenance.confidence
+                    ),
+                ));
+            }
+            let value = value.materialize()?;
+            self.insert_resolving(&mut map, key, value, provenance, policy)?;
+        }
+        Ok(Box::new(map))
+    }
+
+    pub(crate) fn walk_error(&self, e: io::Error) -> Finding {
+        Finding {
+            page_no: None,
+            severity: Severity::Error,
+            kind: FindingKind::PageUnreadable,
+            message: e.to_string(),
+        }
+    }
+}
+
+impl DbImageReader for FileDbImageReader {
+    fn probe(&self) -> io::Result<FormatProfile> {
+        Ok(format_profile(&self.meta, self.source.page_count()))
+    }
+
+    fn entries<'s>(
+        &'s self,
+        salvage: SalvageMode,
+    ) -> Box<dyn Iterator<Item = (ByteVec, Box<dyn ValueSupplier>, Provenance)> + 's> {
+        if matches!(salvage, SalvageMode::BestEffort) {
+            return Box::new(self.best_effort_scan());
+        }
+        let roots = match self.data_roots() {
+            Ok(roots) => roots,
+            Err(e) => {
+                self.record(self.walk_error(e));
+                Vec::new()
+            }
+        };
+        Box::new(
+            roots
+                .into_iter()
+                .flat_map(move |tree| self.tree_entries(tree))
+                .filter_map(move |item| match item {
+                    Ok(entry) => Some(entry),
+                    Err(e) => {
+                        self.record(self.walk_error(e));
+                        None
+                    }
+                }),
+        )
+    }
+
+    fn build_map_with(
+        &self,
+        salvage: SalvageMode,
+        policy: ConflictPolicy,
+    ) -> io::Result<Box<dyn InMemoryMap>> {
+        if self.mode == ParseMode::Strict {
+            return self.build_map_strict(salvage, policy);
+        }
+        let mut map = OrderedWalletMap::new();
+        for (key, value, provenance) in self.entries(salvage) {
+            let value = match value.materialize() {
+                Ok(v) => v,
+                Err(e) => {
+                    self.record(Finding {
+                        page_no: Some(provenance.page_no),
+                        ..self.walk_error(e)
+                    });
+                    continue;
+                }
+            };
+            self.insert_resolving(&mut map, key, value, provenance, policy)?;
+        }
+        Ok(Box::new(map))
+    }
+}
@@ -0,0 +1,230 @@
+//! Recno (record number) databases.
+//!
+//! A recno tree uses the btree page layout with two differences: internal pages
+//! (`P_IRECNO`) hold `RINTERNAL` items (child page and record count, no keys), and leaf
+//! pages (`P_LRECNO`) hold one data item per record instead of key/data pairs. Records are
+//! numbered from 1 in tree order.
+
+use std::{collections::HashSet, io, sync::Arc};
+
+use crate::{
+    headers::BtreeMeta,
+    storage::{
+        blob::{BlobRef, BlobResolver, BlobSupplier},
+        btree::{B_BLOB, B_OVERFLOW, WalkItem, overflow_ref, parse_leaf_item, slot_offsets},
+        entry::{Confidence, Provenance},
+        page::{PageHeader, PageProtection, PageType, ValueSupplier},
+        supplier::{InlineSupplier, OverflowSupplier},
+        types::{ByteVec, Endianness, PageNumber, PageSource},
+    },
+    util::u32e,
+};
+
+pub use crate::storage::flags::{BTM_FIXEDLEN, BTM_RECNO, BTM_RENUMBER};
+
+/// Size of a `RINTERNAL` item: pgno:u32, nrecs:u32.
+const RINTERNAL_SIZE: usize = 8;
+
+/// How the records of a recno database are stored, from its meta page.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RecnoFormat {
+    /// `re_len` for fixed-length databases.
+    pub fixed_len: Option<u32>,
+    /// `re_pad`: the byte short fixed-length records are padded with.
+    pub pad: u8,
+    pub renumber: bool,
+}
+
+impl RecnoFormat {
+    /// Read the recno settings from a btree meta page, or `None` if it is not a recno tree.
+    pub fn from_meta_page(meta: &[u8], e: Endianness) -> Option<Self> {
+        let flags = u32e(e.into(), meta.get(48..52)?);
+        if flags & BTM_RECNO == 0 {
+            return None;
+        }
+        let re_len = u32e(e.into(), meta.get(80..84)?);
+        let re_pad = u32e(e.into(), meta.get(84..88)?);
+        Some(RecnoFormat {
+            fixed_len: (flags & BTM_FIXEDLEN != 0).then_some(re_len),
+            pad: re_pad as u8,
+            renumber: flags & BTM_RENUMBER != 0,
+        })
+    }
+
+    /// The recno settings of an already parsed meta page.
+    pub fn from_meta(meta: &BtreeMeta) -> Option<Self> {
+        if meta.flags & BTM_RECNO == 0 {
+            return None;
+        }
+        Some(RecnoFormat {
+            fixed_len: (meta.flags & BTM_FIXEDLEN != 0).then_some(meta.re_len),
+            pad: meta.re_pad as u8,
+            renumber: meta.flags & BTM_RENUMBER != 0,
+        })
+    }
+}
+
+/// The key under which a record is yielded: its record number, big-endian so that keys
+/// sort in record order.
+pub fn recno_key(recno: u32) -> ByteVec {
+    recno.to_be_bytes().to_vec()
+}
+
+fn invalid(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+/// Walks a recno tree from its root, in record order.
+#[derive(Clone)]
+pub(crate) struct RecnoWalker {
+    pub(crate) source: Arc<dyn PageSource>,
+    pub(crate) root: PageNumber,
+    pub(crate) endianness: Endianness,
+    pub(crate) protection: PageProtection,
+    pub(crate) format: RecnoFormat,
+    pub(crate) blobs: Option<Arc<dyn BlobResolver>>,
+}
+
+impl RecnoWalker {
+    /// Iterator over all live records, keyed by [`recno_key`]. Structural errors are
+    /// yielded in place of the records they hide.
+    pub(crate) fn into_entries(self) -> RecnoEntries {
+        RecnoEntries {
+            stack: vec![self.root],
+            walker: self,
+            visited: HashSet::new(),
+            pending: Vec::new(),
+            next_recno: 1,
+        }
+    }
+
+    fn children(&self, page: &[u8], hdr: &PageHeader) -> io::Result<Vec<PageNumber>> {
+        slot_offsets(page, hdr, self.endianness, self.protection)?
+            .into_iter()
+            .map(|off| {
+                page.get(off..off + RINTERNAL_SIZE)
+                    .map(|item| u32e(self.endianness.into(), &item[0..4]))
+                    .ok_or_else(|| {
+                        invalid(format!(
+                            "page {}: recno internal item at {off} out of bounds",
+                            hdr.pgno
+                        ))
+                    })
+            })
+            .collect()
+    }
+
+    /// Records of one leaf page, numbered from `first`. Returns the items and the number
+    /// of slots consumed (deleted records keep their number).
+    fn records(
+        &self,
+        pgno: PageNumber,
+        page: Arc<[u8]>,
+        hdr: &PageHeader,
+        first: u32,
+    ) -> io::Result<(Vec<WalkItem>, u32)> {
+        let offsets = slot_offsets(&page, hdr, self.endianness, self.protection)?;
+        let source_id = self.source.source_id();
+        let mut out = Vec::with_capacity(offsets.len());
+        for (slot, off) in offsets.iter().enumerate() {
+            let item = match parse_leaf_item(&page, *off, self.endianness) {
+                Ok(item) => item,
+                Err(e) => {
+                    out.push(Err(invalid(format!("page {pgno}: {e}"))));
+                    continue;
+                }
+            };
+            if item.deleted {
+                continue;
+            }
+            let value: Box<dyn ValueSupplier> = if item.kind == B_OVERFLOW {
+                let r = overflow_ref(&page[item.range.0..item.range.1], self.endianness);
+                Box::new(OverflowSupplier::new(
+                    self.source.clone(),
+                    r,
+                    self.endianness,
+                    self.protection,
+                ))
+            } else if item.kind == B_BLOB {
+                let r = BlobRef::parse(&page[item.range.0..item.range.1], self.endianness);
+                Box::new(BlobSupplier::new(self.blobs.clone(), r))
+            } else {
+                match self.format.fixed_len {
+                    Some(len) if (item.len as u32) < len => {
+                        let mut record = page[item.range.0..item.range.1].to_vec();
+                        record.resize(len as usize, self.format.pad);
+                        Box::new(InlineSupplier::new(record.into(), 0..len as usize))
+                    }
+                    _ => Box::new(InlineSupplier::new(
+                        page.clone(),
+                        item.range.0..item.range.1,
+                    )),
+                }
+            };
+            let provenance = Provenance {
+                source_id: source_id.clone(),
+                page_no: pgno,
+                slot_index: slot as u16,
+                confidence: Confidence::High,
+            };
+            out.push(Ok((recno_key(first + slot as u32), value, provenance)));
+        }
+        Ok((out, offsets.len() as u32))
+    }
+}
+
+/// Depth-first iterator returned by [`RecnoWalker::into_entries`].
+pub(crate) struct RecnoEntries {
+    walker: RecnoWalker,
+    stack: Vec<PageNumber>,
+    visited: HashSet<PageNumber>,
+    /// Records of the current leaf, reversed so `pop` yields them in order.
+    pending: Vec<WalkItem>,
+    next_recno: u32,
+}
+
+impl Iterator for RecnoEntries {
+    type Item = WalkItem;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(item) = self.pending.pop() {
+                return Some(item);
+            }
+            let pgno = self.stack.pop()?;
+            if !self.visited.insert(pgno) {
+                return Some(Err(invalid(format!(
+                    "page {pgno} is reachable twice (tree cycle)"
+                ))));
+            }
+            let w = &self.walker;
+            let page: Arc<[u8]> = match w.source.read_page(pgno) {
+                Ok(p) => p.into(),
+                Err(e) => return Some(Err(e)),
+            };
+            let hdr = match PageHeader::parse(&page, w.endianness, w.protection) {
+                Ok(hdr) => hdr,
+                Err(e) => return Some(Err(e)),
+            };
+            match hdr.kind() {
+                PageType::RecnoInternal => match w.children(&page, &hdr) {
+                    Ok(children) => self.stack.extend(children.into_iter().rev()),
+                    Err(e) => return Some(Err(e)),
+                },
+                PageType::RecnoLeaf => match w.records(pgno, page.clone(), &hdr, self.next_recno) {
+                    Ok((mut items, consumed)) => {
+                        self.next_recno += consumed;
+                        items.reverse();
+                        self.pending = items;
+                    }
+                    Err(e) => return Some(Err(e)),
+                },
+                other => {
+                    return Some(Err(invalid(format!(
+                        "page {pgno}: expected a recno page, got {other:?}"
+                    ))));
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,130 @@
+//! Rewriting a wallet with only its live records, packed into as few pages as they fit.
+//!
+//! Deleted items, free pages and half-empty leaves left by erasing records (e.g. after
+//! `-zapwallettxes`) are all dropped. The result is read back and compared record by
+//! record with the original before it is handed out.
+
+use std::{fmt, io};
+
+use crate::storage::{
+    borrowed::BorrowedImage,
+    consistency::{DbImageReader, SalvageMode},
+    entry::{ConflictPolicy, InMemoryMap},
+    page::PageProtection,
+    reader::FileDbImageReader,
+    types::ByteVec,
+    writer::{WriteOptions, write_wallet},
+};
+
+/// What [`repack`] did.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RepackReport {
+    pub records: usize,
+    pub pages_before: u64,
+    pub pages_after: u64,
+    pub page_size: u32,
+}
+
+impl fmt::Display for RepackReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let bytes = |pages: u64| pages * self.page_size as u64;
+        writeln!(f, "RepackReport {{")?;
+        writeln!(f, "  records      : {}", self.records)?;
+        writeln!(
+            f,
+            "  pages_before : {} ({} bytes)",
+            self.pages_before,
+            bytes(self.pages_before)
+        )?;
+        writeln!(
+            f,
+            "  pages_after  : {} ({} bytes)",
+            self.pages_after,
+            bytes(self.pages_after)
+        )?;
+        write!(f, "}}")
+    }
+}
+
+fn invalid(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+fn pairs(map: &dyn InMemoryMap) -> impl Iterator<Item = (&[u8], &[u8])> {
+    map.iter()
+        .map(|(key, entry)| (key.as_slice(), entry.value.as_slice()))
+}
+
+/// Write the live records of `reader` into a fresh image with the same page size, byte
+/// order, format version, checksum setting, file ID and subdatabase name, and report
+/// how far the file shrank.
+///
+/// Only wallets holding exactly one btree subdatabase can be repacked. Fails rather than
+/// lose data when any part of the tree cannot be read, when a key is stored twice, or
+/// when the new image does not read back to exactly the same records. Encrypted
+/// wallets, read through a `DecryptingPageSource`, are written out unencrypted.
+pub fn repack(reader: &FileDbImageReader) -> io::Result<(ByteVec, RepackReport)> {
+    let subdatabase = match reader.subdatabases()?.as_slice() {
+        [sub] if sub.recno.is_none() => sub.name.clone(),
+        [_] => {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "recno subdatabases cannot be repacked",
+            ));
+        }
+        subs => {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                format!(
+                    "only wallets with one subdatabase can be repacked, found {}",
+                    subs.len()
+                ),
+            ));
+        }
+    };
+    let before = reader.diagnostics().len();
+    let map = reader.build_map_with(SalvageMode::Conservative, ConflictPolicy::Error)?;
+    if let Some(finding) = reader.diagnostics().get(before) {
+        return Err(invalid(format!(
+            "refusing to repack a damaged tree: {}",
+            finding.message
+        )));
+    }
+
+    let meta = reader.meta();
+    let options = WriteOptions {
+        page_size: meta.pagesize,
+        endianness: meta.endian.into(),
+        version: reader.version(),
+        checksum: reader.protection() == PageProtection::Checksum,
+        uid: meta.uid,
+        subdatabase,
+    };
+    let image = write_wallet(map.as_ref(), &options)?;
+    // Read the new image back in place and compare it with the records it was built from.
+    let mut expected = pairs(map.as_ref());
+    for entry in BorrowedImage::new(&image)?.entries() {
+        let entry = entry?;
+        if expected.next() != Some((entry.key.as_ref(), entry.value.as_ref())) {
+            return Err(invalid(
+                "repacked image does not read back to the original records".to_string(),
+            ));
+        }
+    }
+    if expected.next().is_some() {
+        return Err(invalid(
+            "repacked image is missing records of the original".to_string(),
+        ));
+    }
+
+    let report = RepackReport {
+        records: map.len(),
+        pages_before: reader
+            .source()
+            .page_count()
+            .unwrap_or(meta.last_pgno as u64 + 1),
+        pages_after: (image.len() / meta.pagesize as usize) as u64,
+        page_size: meta.pagesize,
+    };
+    Ok((image, report))
+}
@@ -0,0 +1,291 @@
+//! Best-effort recovery: scan every page of the image, whether or not the btree still
+//! reaches it, and reconstruct whatever key/value pairs survive.
+
+use std::{collections::HashSet, io, sync::Arc};
+
+use crate::{
+    headers::parse_btree_meta_page0,
+    storage::{
+        blob::{BlobRef, BlobResolver},
+        btree::{B_BLOB, B_OVERFLOW, LeafItem, overflow_ref, parse_leaf_item},
+        entry::{Confidence, Provenance},
+        geometry::{GeometrySource, ImageGeometry, infer_geometry, matching_pages},
+        page::{PageHeader, PageProtection, PageType, ValueSupplier},
+        slots::validate_slots,
+        source::MemoryPageSource,
+        supplier::{InlineSupplier, read_overflow_lenient},
+        types::{ByteVec, Endianness, PageNumber, PageSource},
+    },
+};
+
+/// An entry recovered by the scan.
+pub(crate) type ScanItem = (ByteVec, Box<dyn ValueSupplier>, Provenance);
+
+/// Which leaf pairs a scan yields.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SlotFilter {
+    /// Pairs whose key and value are both live.
+    Live,
+    /// Tombstoned pairs: the key or the value carries `B_DELETE`.
+    Deleted,
+}
+
+/// Iterator over every leaf entry in the image matching a [`SlotFilter`], in page/slot order.
+///
+/// Unlike the tree walk, nothing here is fatal: unreadable pages are skipped, slots that
+/// point outside the page are ignored, and broken overflow chains yield partial values.
+/// Each entry's [`Provenance::confidence`] says how much of that happened to it.
+pub struct BestEffortScan {
+    source: Arc<dyn PageSource>,
+    endianness: Endianness,
+    protection: PageProtection,
+    /// Pages that hold no wallet records (meta pages, master database leaves).
+    skip: HashSet<PageNumber>,
+    filter: SlotFilter,
+    /// When set, only these pages are scanned.
+    only: Option<HashSet<PageNumber>>,
+    /// Where values stored in blob files are read from.
+    blobs: Option<Arc<dyn BlobResolver>>,
+    next_page: PageNumber,
+    pending: Vec<ScanItem>,
+}
+
+impl BestEffortScan {
+    pub fn new(
+        source: Arc<dyn PageSource>,
+        endianness: Endianness,
+        protection: PageProtection,
+        skip: HashSet<PageNumber>,
+    ) -> Self {
+        BestEffortScan {
+            source,
+            endianness,
+            protection,
+            skip,
+            filter: SlotFilter::Live,
+            only: None,
+            blobs: None,
+            next_page: 1,
+            pending: Vec::new(),
+        }
+    }
+
+    /// Yield tombstoned pairs instead of live ones, e.g. records removed by
+    /// `zapwallettxes` or key rotation whose bytes are still on the page.
+    pub fn with_filter(mut self, filter: SlotFilter) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    /// Restrict the scan to `pages`, e.g. the orphaned leaves found by
+    /// [`analyze_reachability`](crate::storage::orphans::analyze_reachability).
+    pub fn only_pages(mut self, pages: HashSet<PageNumber>) -> Self {
+        self.only = Some(pages);
+        self
+    }
+
+    /// Read values stored in blob files through `resolver`. Without one, such values are
+    /// recovered empty with [`Confidence::Low`].
+    pub fn with_blobs(mut self, resolver: Arc<dyn BlobResolver>) -> Self {
+        self.blobs = Some(resolver);
+        self
+    }
+
+    fn value_of(&self, page: &Arc<[u8]>, item: &LeafItem) -> (ByteVec, bool) {
+        if item.kind == B_OVERFLOW {
+            let r = overflow_ref(&page[item.range.0..item.range.1], self.endianness);
+            read_overflow_lenient(self.source.as_ref(), r, self.endianness, self.protection)
+        } else if item.kind == B_BLOB {
+            let r = BlobRef::parse(&page[item.range.0..item.range.1], self.endianness);
+            match self.blobs.as_ref().map(|b| b.read_blob(&r)) {
+                Some(Ok(value)) => (value, true),
+                _ => (Vec::new(), false),
+            }
+        } else {
+            (page[item.range.0..item.range.1].to_vec(), true)
+        }
+    }
+
+    /// Salvage every live pair on one leaf page.
+    fn scan_page(&self, pgno: PageNumber, page: Arc<[u8]>, hdr: &PageHeader) -> Vec<ScanItem> {
+        let report = validate_slots(&page, hdr, self.endianness, self.protection);
+        let items: Vec<Option<LeafItem>> = report
+            .offsets
+            .iter()
+            .enumerate()
+            .map(|(slot, &off)| {
+                report
+                    .is_usable(slot)
+                    .then(|| parse_leaf_item(&page, off, self.endianness).ok())
+                    .flatten()
+            })
+            .collect();
+        let degraded = !report.is_clean() || !items.len().is_multiple_of(2);
+
+        let mut out = Vec::new();
+        for (pair, slots) in items.chunks_exact(2).enumerate() {
+            let [Some(k), Some(v)] = slots else {
+                continue;
+            };
+            let deleted = k.deleted || v.deleted;
+            if deleted != (self.filter == SlotFilter::Deleted) {
+                continue;
+            }
+            let (key, key_complete) = self.value_of(&page, k);
+            let (value, value_complete) = self.value_of(&page, v);
+            let confidence = if !key_complete || !value_complete {
+                Confidence::Low
+            } else if degraded {
+                Confidence::Medium
+            } else {
+                Confidence::High
+            };
+            let supplier: Box<dyn ValueSupplier> = if v.kind == B_OVERFLOW || v.kind == B_BLOB {
+                let len = value.len();
+                Box::new(InlineSupplier::new(value.into(), 0..len))
+            } else {
+                Box::new(InlineSupplier::new(page.clone(), v.range.0..v.range.1))
+            };
+            out.push((
+                key,
+                supplier,
+                Provenance {
+                    source_id: self.source.source_id(),
+                    page_no: pgno,
+                    slot_index: (pair * 2) as u16,
+                    confidence,
+                },
+            ));
+        }
+        out
+    }
+
+    /// Also skip leaves of the master database, recognised by their contents since the
+    /// master root may be lost. See [`master_leaves`].
+    pub(crate) fn skip_master_leaves(mut self) -> Self {
+        let pages = master_leaves(&self, self.source.page_count().unwrap_or(0));
+        self.skip.extend(pages);
+        self
+    }
+
+    /// Salvage one page by number; pages that are unreadable, skipped or not leaves
+    /// yield nothing. `None` marks a short read past the end of an unsized source.
+    pub(crate) fn scan_pgno(&self, pgno: PageNumber) -> Option<Vec<ScanItem>> {
+        if self.skip.contains(&pgno) || self.only.as_ref().is_some_and(|o| !o.contains(&pgno)) {
+            return Some(Vec::new());
+        }
+        let page: Arc<[u8]> = match self.source.read_page(pgno) {
+            Ok(p) => p.into(),
+            // Without a page count the first short read marks the end of the image.
+            Err(_) if self.source.page_count().is_none() => return None,
+            Err(_) => return Some(Vec::new()),
+        };
+        let Ok(hdr) = PageHeader::parse(&page, self.endianness, self.protection) else {
+            return Some(Vec::new());
+        };
+        if hdr.kind() != PageType::BtreeLeaf {
+            return Some(Vec::new());
+        }
+        Some(self.scan_page(pgno, page, &hdr))
+    }
+}
+
+impl Iterator for BestEffortScan {
+    type Item = ScanItem;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(item) = self.pending.pop() {
+                return Some(item);
+            }
+            let pgno = self.next_page;
+            if self.source.page_count().is_some_and(|n| pgno as u64 >= n) {
+                return None;
+            }
+            self.next_page += 1;
+            let mut items = self.scan_pgno(pgno)?;
+            items.reverse();
+            self.pending = items;
+        }
+    }
+}
+
+/// The geometry given by the meta page of `image`, if it parses. Encrypted images are
+/// refused: their pages must be decrypted before anything can be salvaged.
+fn meta_geometry(image: &[u8]) -> io::Result<Option<ImageGeometry>> {
+    let Ok(meta) = parse_btree_meta_page0(image) else {
+        return Ok(None);
+    };
+    if meta.is_encrypted() {
+        return Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "image is encrypted; decrypt it before salvaging",
+        ));
+    }
+    let page_size = meta.pagesize as usize;
+    let endianness = meta.endian.into();
+    Ok(Some(ImageGeometry {
+        page_size: meta.pagesize,
+        endianness,
+        protection: PageProtection::from_meta(0, meta.has_checksum()),
+        source: GeometrySource::MetaPage,
+        matching_pages: matching_pages(image, page_size, endianness),
+        trailing_bytes: image.len() % page_size,
+    }))
+}
+
+/// Leaves of the master database: every pair maps a subdatabase name to the big-endian
+/// number of a meta page. They hold no wallet records.
+fn master_leaves(scan: &BestEffortScan, page_count: u64) -> HashSet<PageNumber> {
+    let names_meta_page = |value: &dyn ValueSupplier| {
+        let Ok(Ok(pgno)) = value.materialize().map(<[u8; 4]>::try_from) else {
+            return false;
+        };
+        scan.source
+            .read_page(u32::from_be_bytes(pgno))
+            .ok()
+            .and_then(|p| PageHeader::parse(&p, scan.endianness, scan.protection).ok())
+            .is_some_and(|hdr| hdr.kind() == PageType::Meta)
+    };
+    (1..page_count as PageNumber)
+        .filter(|&pgno| {
+            scan.scan_pgno(pgno).is_some_and(|items| {
+                !items.is_empty() && items.iter().all(|(_, v, _)| names_meta_page(v.as_ref()))
+            })
+        })
+        .collect()
+}
+
+/// Salvage every parseable leaf entry from a raw image that may be truncated or have a
+/// damaged meta page.
+///
+/// The geometry comes from the meta page when it still parses, unless the page headers
+/// fit an inferred geometry (see [`infer_geometry`]) better, as when a stray write left a
+/// valid magic next to a wrong page size. A partial last page is dropped, pages
+/// whose header no longer parses are skipped, and master database leaves are recognised
+/// by their contents rather than through the (possibly lost) root.
+pub fn salvage_image(
+    image: ByteVec,
+    source_id: impl Into<String>,
+) -> io::Result<(ImageGeometry, BestEffortScan)> {
+    let geometry = match (meta_geometry(&image)?, infer_geometry(&image)) {
+        (Some(meta), Some(inferred)) if inferred.matching_pages > meta.matching_pages => inferred,
+        (Some(meta), _) => meta,
+        (None, Some(inferred)) => inferred,
+        (None, None) => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "no page size and byte order yield a plausible page header",
+            ));
+        }
+    };
+    let source = MemoryPageSource::new(image, geometry.page_size, source_id)?;
+    let scan = BestEffortScan::new(
+        Arc::new(source),
+        geometry.endianness,
+        geometry.protection,
+        HashSet::from([0]),
+    )
+    .skip_master_leaves();
+    Ok((geometry, scan))
+}
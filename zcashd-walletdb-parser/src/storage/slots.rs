@@ -0,0 +1,303 @@
+//! Slot array validation.
+//!
+//! Every slot of a btree or recno page holds the offset of an item in the data region,
+//! which runs from `hf_offset` to the end of the page. A slot that points below
+//! `hf_offset` (into free space or the slot array itself), an item that runs off the page,
+//! or two items that partially overlap all mean the page is damaged. On-page duplicates
+//! legitimately share a key item, so slots with identical offsets are not an overlap.
+
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::{
+    constants::{
+        B_BLOB, B_DUPLICATE, B_KEYDATA, B_OVERFLOW, BBLOB_SIZE, BINTERNAL_SIZE, BOVERFLOW_SIZE,
+    },
+    storage::{
+        page::{PageHeader, PageProtection, PageType},
+        types::{Endianness, PageNumber},
+    },
+    util::u16e,
+};
+
+/// Size of a `RINTERNAL` item on a recno internal page.
+const RINTERNAL_SIZE: usize = 8;
+
+/// One problem with a page's slot array.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SlotViolation {
+    /// The slot array ends past `hf_offset` or past the page; slots from `first_lost` on
+    /// could not be read at all.
+    ArrayOverrun {
+        slots_end: usize,
+        hf_offset: usize,
+        first_lost: usize,
+    },
+    /// The offset points below `hf_offset`, i.e. into free space.
+    BelowDataRegion {
+        slot: usize,
+        offset: usize,
+        hf_offset: usize,
+    },
+    /// The offset points into the page header or the slot array.
+    IntoSlotArray {
+        slot: usize,
+        offset: usize,
+        slots_end: usize,
+    },
+    /// The item starts, or ends, past the end of the page.
+    PastPageEnd {
+        slot: usize,
+        offset: usize,
+        end: usize,
+        page_len: usize,
+    },
+    /// The item type byte is not one this page type can hold.
+    UnknownItemType {
+        slot: usize,
+        offset: usize,
+        item_type: u8,
+    },
+    /// The item partially overlaps the item of another slot.
+    Overlap {
+        slot: usize,
+        other: usize,
+        offset: usize,
+        other_end: usize,
+    },
+}
+
+impl SlotViolation {
+    /// The slot the violation is about, if it concerns a single slot.
+    pub fn slot(&self) -> Option<usize> {
+        match self {
+            SlotViolation::ArrayOverrun { .. } => None,
+            SlotViolation::BelowDataRegion { slot, .. }
+            | SlotViolation::IntoSlotArray { slot, .. }
+            | SlotViolation::PastPageEnd { slot, .. }
+            | SlotViolation::UnknownItemType { slot, .. }
+            | SlotViolation::Overlap { slot, .. } => Some(*slot),
+        }
+    }
+}
+
+impl fmt::Display for SlotViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SlotViolation::ArrayOverrun {
+                slots_end,
+                hf_offset,
+                first_lost,
+            } => write!(
+                f,
+                "slot array ends at {slots_end}, past hf_offset {hf_offset}; slots from {first_lost} unreadable"
+            ),
+            SlotViolation::BelowDataRegion {
+                slot,
+                offset,
+                hf_offset,
+            } => write!(
+                f,
+                "slot {slot}: offset {offset} is below hf_offset {hf_offset}"
+            ),
+            SlotViolation::IntoSlotArray {
+                slot,
+                offset,
+                slots_end,
+            } => write!(
+                f,
+                "slot {slot}: offset {offset} points into the slot array (ends at {slots_end})"
+            ),
+            SlotViolation::PastPageEnd {
+                slot,
+                offset,
+                end,
+                page_len,
+            } => write!(
+                f,
+                "slot {slot}: item {offset}..{end} runs past the page ({page_len} bytes)"
+            ),
+            SlotViolation::UnknownItemType {
+                slot,
+                offset,
+                item_type,
+            } => write!(f, "slot {slot}: unknown item type {item_type} at {offset}"),
+            SlotViolation::Overlap {
+                slot,
+                other,
+                offset,
+                other_end,
+            } => write!(
+                f,
+                "slot {slot}: item at {offset} overlaps slot {other} (ends at {other_end})"
+            ),
+        }
+    }
+}
+
+/// The result of validating one page's slot array.
+#[derive(Debug, Clone, Default)]
+pub struct SlotReport {
+    pub pgno: PageNumber,
+    /// Slot offsets that could be read, in slot order.
+    pub offsets: Vec<usize>,
+    pub violations: Vec<SlotViolation>,
+}
+
+impl SlotReport {
+    pub fn is_clean(&self) -> bool {
+        self.violations.is_empty()
+    }
+
+    /// Whether the item of `slot` can be parsed safely. Overlapping items are still
+    /// in bounds, so only the violations that make the item unreadable count.
+    pub fn is_usable(&self, slot: usize) -> bool {
+        slot < self.offsets.len()
+            && !self
+                .violations
+                .iter()
+                .any(|v| v.slot() == Some(slot) && !matches!(v, SlotViolation::Overlap { .. }))
+    }
+}
+
+/// Size of the item at `off`, according to the page type, or the violation that
+/// prevents reading it.
+fn item_extent(
+    page: &[u8],
+    page_type: PageType,
+    slot: usize,
+    off: usize,
+    e: Endianness,
+) -> Result<usize, SlotViolation> {
+    let past_end = |end| SlotViolation::PastPageEnd {
+        slot,
+        offset: off,
+        end,
+        page_len: page.len(),
+    };
+    let len_at = |at: usize| {
+        page.get(at..at + 2)
+            .map(|b| u16e(e.into(), b) as usize)
+            .ok_or(past_end(at + 2))
+    };
+    let type_at = |at: usize| page.get(at).map(|t| t & 0x7f).ok_or(past_end(at + 1));
+    let size = match page_type {
+        PageType::RecnoInternal => RINTERNAL_SIZE,
+        PageType::BtreeInternal => {
+            let item_type = type_at(off + 2)?;
+            BINTERNAL_SIZE
+                + len_at(off)?.max(if item_type == B_OVERFLOW {
+                    BOVERFLOW_SIZE
+                } else {
+                    0
+                })
+        }
+        _ => match type_at(off + 2)? {
+            B_KEYDATA => 3 + len_at(off)?,
+            B_OVERFLOW | B_DUPLICATE => BOVERFLOW_SIZE,
+            B_BLOB => BBLOB_SIZE,
+            item_type => {
+                return Err(SlotViolation::UnknownItemType {
+                    slot,
+                    offset: off,
+                    item_type,
+                });
+            }
+        },
+    };
+    if off + size > page.len() {
+        return Err(past_end(off + size));
+    }
+    Ok(size)
+}
+
+/// Check every slot of a btree or recno page against the layout rules.
+pub fn validate_slots(
+    page: &[u8],
+    hdr: &PageHeader,
+    e: Endianness,
+    protection: PageProtection,
+) -> SlotReport {
+    validate_slot_array(
+        page,
+        hdr.pgno,
+        hdr.kind(),
+        protection.header_size(),
+        hdr.num_slots(),
+        hdr.upper_bound(),
+        e,
+    )
+}
+
+/// [`validate_slots`] on raw header fields: the slot array starts at `slots_start` and
+/// holds `entries` offsets; the data region starts at `hf_offset`.
+pub fn validate_slot_array(
+    page: &[u8],
+    pgno: PageNumber,
+    page_type: PageType,
+    slots_start: usize,
+    entries: usize,
+    hf_offset: usize,
+    e: Endianness,
+) -> SlotReport {
+    let mut report = SlotReport {
+        pgno,
+        ..Default::default()
+    };
+    // Both counts come from the header, so the end is computed without overflowing, and
+    // only whole offsets that lie on the page are read.
+    let slots_end = entries.saturating_mul(2).saturating_add(slots_start);
+    let readable = slots_end.min(page.len()).saturating_sub(slots_start) / 2;
+    if slots_end > hf_offset || slots_end > page.len() {
+        report.violations.push(SlotViolation::ArrayOverrun {
+            slots_end,
+            hf_offset,
+            first_lost: readable,
+        });
+    }
+    report.offsets = (0..readable)
+        .map(|i| slots_start + i * 2)
+        .map(|at| u16e(e.into(), &page[at..at + 2]) as usize)
+        .collect();
+
+    let mut extents = Vec::with_capacity(report.offsets.len());
+    for (slot, &off) in report.offsets.iter().enumerate() {
+        if off < slots_end {
+            report.violations.push(SlotViolation::IntoSlotArray {
+                slot,
+                offset: off,
+                slots_end,
+            });
+            continue;
+        }
+        if off < hf_offset {
+            report.violations.push(SlotViolation::BelowDataRegion {
+                slot,
+                offset: off,
+                hf_offset,
+            });
+            continue;
+        }
+        match item_extent(page, page_type, slot, off, e) {
+            Ok(size) => extents.push((off, off + size, slot)),
+            Err(v) => report.violations.push(v),
+        }
+    }
+
+    extents.sort_unstable();
+    extents.dedup_by_key(|(start, _, _)| *start);
+    for pair in extents.windows(2) {
+        let [(_, prev_end, prev_slot), (start, _, slot)] = pair else {
+            continue;
+        };
+        if start < prev_end {
+            report.violations.push(SlotViolation::Overlap {
+                slot: *slot,
+                other: *prev_slot,
+                offset: *start,
+                other_end: *prev_end,
+            });
+        }
+    }
+    report
+}
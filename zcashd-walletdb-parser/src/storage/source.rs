@@ -0,0 +1,277 @@
+//! Concrete [`PageSource`] implementations.
+
+use std::{
+    fmt,
+    fs::File,
+    io::{self, Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use crate::{
+    constants::DBMETASIZE,
+    storage::{
+        borrowed::BorrowedImage,
+        environment::not_a_database,
+        types::{ByteVec, PageNumber, PageSize, PageSource},
+    },
+    util::{detect_endian, is_valid_page_size, u32e},
+};
+
+/// Read the page size from the meta page at the start of `reader`.
+pub(crate) fn probe_page_size<R: Read>(reader: &mut R) -> io::Result<PageSize> {
+    let mut meta = [0u8; DBMETASIZE];
+    reader.read_exact(&mut meta)?;
+    let endian = detect_endian(&meta)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "meta page magic not found"))?;
+    let page_size = u32e(endian, &meta[20..24]);
+    if !is_valid_page_size(page_size as usize) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("implausible pagesize {page_size}"),
+        ));
+    }
+    Ok(page_size)
+}
+
+/// The error for a page that does not fit in an image of `len` bytes: either it starts past
+/// the end, or the image stops part-way through it.
+fn missing_page(page_no: PageNumber, page_size: usize, len: u64, source_id: &str) -> io::Error {
+    let start = page_no as u64 * page_size as u64;
+    let msg = if start >= len {
+        format!(
+            "page {page_no} is past the end of {source_id} ({} pages)",
+            len / page_size as u64
+        )
+    } else {
+        format!(
+            "page {page_no} of {source_id} is truncated ({} of {page_size} bytes present)",
+            len - start
+        )
+    };
+    io::Error::new(io::ErrorKind::UnexpectedEof, msg)
+}
+
+/// Pages read on demand from a file. Only the requested page is ever resident.
+#[derive(Debug)]
+pub struct FilePageSource {
+    file: Mutex<File>,
+    len: u64,
+    page_size: usize,
+    path: PathBuf,
+}
+
+impl FilePageSource {
+    /// Open `path`, taking the page size from its meta page.
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let mut file = File::open(&path)?;
+        let len = file.metadata()?.len();
+        let page_size = probe_page_size(&mut file).map_err(|e| not_a_database(&path, e))?;
+        Ok(FilePageSource {
+            file: Mutex::new(file),
+            len,
+            page_size: page_size as usize,
+            path,
+        })
+    }
+
+    pub fn page_size(&self) -> PageSize {
+        self.page_size as PageSize
+    }
+
+    /// Bytes after the last whole page; non-zero when the file was cut off mid-page.
+    pub fn trailing_bytes(&self) -> usize {
+        (self.len % self.page_size as u64) as usize
+    }
+}
+
+impl PageSource for FilePageSource {
+    fn read_page(&self, page_no: PageNumber) -> io::Result<ByteVec> {
+        let start = page_no as u64 * self.page_size as u64;
+        if start + self.page_size as u64 > self.len {
+            return Err(missing_page(
+                page_no,
+                self.page_size,
+                self.len,
+                &self.path.display().to_string(),
+            ));
+        }
+        let mut page = vec![0u8; self.page_size];
+        let mut file = self
+            .file
+            .lock()
+            .map_err(|_| io::Error::other("page source lock poisoned"))?;
+        file.seek(SeekFrom::Start(start))?;
+        file.read_exact(&mut page)?;
+        Ok(page)
+    }
+
+    fn page_count(&self) -> Option<u64> {
+        Some(self.len / self.page_size as u64)
+    }
+
+    fn source_id(&self) -> String {
+        self.path.display().to_string()
+    }
+}
+
+/// Pages read on demand from any seekable reader, e.g. a member of an archive, a block
+/// device or a custom container, with the image starting `offset` bytes in.
+pub struct ReaderPageSource<R> {
+    reader: Mutex<R>,
+    offset: u64,
+    len: u64,
+    page_size: usize,
+    source_id: String,
+}
+
+impl<R: Read + Seek> ReaderPageSource<R> {
+    /// Take the page size from the meta page at `offset`. The image is assumed to run to
+    /// the end of the reader.
+    pub fn new(mut reader: R, offset: u64, source_id: impl Into<String>) -> io::Result<Self> {
+        let end = reader.seek(SeekFrom::End(0))?;
+        let len = end.checked_sub(offset).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("offset {offset} is past the end of the input ({end} bytes)"),
+            )
+        })?;
+        reader.seek(SeekFrom::Start(offset))?;
+        let page_size = probe_page_size(&mut reader)?;
+        Ok(ReaderPageSource {
+            reader: Mutex::new(reader),
+            offset,
+            len,
+            page_size: page_size as usize,
+            source_id: source_id.into(),
+        })
+    }
+
+    pub fn page_size(&self) -> PageSize {
+        self.page_size as PageSize
+    }
+
+    /// Bytes after the last whole page; non-zero when the image was cut off mid-page.
+    pub fn trailing_bytes(&self) -> usize {
+        (self.len % self.page_size as u64) as usize
+    }
+
+    /// Give back the reader.
+    pub fn into_inner(self) -> R {
+        self.reader
+            .into_inner()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl<R> fmt::Debug for ReaderPageSource<R> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ReaderPageSource")
+            .field("source_id", &self.source_id)
+            .field("offset", &self.offset)
+            .field("len", &self.len)
+            .field("page_size", &self.page_size)
+            .finish()
+    }
+}
+
+impl<R: Read + Seek + Send> PageSource for ReaderPageSource<R> {
+    fn read_page(&self, page_no: PageNumber) -> io::Result<ByteVec> {
+        let start = page_no as u64 * self.page_size as u64;
+        if start + self.page_size as u64 > self.len {
+            return Err(missing_page(
+                page_no,
+                self.page_size,
+                self.len,
+                &self.source_id,
+            ));
+        }
+        let mut page = vec![0u8; self.page_size];
+        let mut reader = self
+            .reader
+            .lock()
+            .map_err(|_| io::Error::other("page source lock poisoned"))?;
+        reader.seek(SeekFrom::Start(self.offset + start))?;
+        reader.read_exact(&mut page)?;
+        Ok(page)
+    }
+
+    fn page_count(&self) -> Option<u64> {
+        Some(self.len / self.page_size as u64)
+    }
+
+    fn source_id(&self) -> String {
+        self.source_id.clone()
+    }
+}
+
+/// A DB image held entirely in memory.
+#[derive(Debug, Clone)]
+pub struct MemoryPageSource {
+    bytes: ByteVec,
+    page_size: usize,
+    source_id: String,
+}
+
+impl MemoryPageSource {
+    /// Fails if `page_size` is not a power of two between 512 bytes and 64 KiB.
+    pub fn new(
+        bytes: ByteVec,
+        page_size: PageSize,
+        source_id: impl Into<String>,
+    ) -> io::Result<Self> {
+        if !is_valid_page_size(page_size as usize) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("unsupported page size {page_size}"),
+            ));
+        }
+        Ok(MemoryPageSource {
+            bytes,
+            page_size: page_size as usize,
+            source_id: source_id.into(),
+        })
+    }
+
+    /// The whole underlying image.
+    pub fn bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    /// Bytes after the last whole page; non-zero when the image was cut off mid-page.
+    pub fn trailing_bytes(&self) -> usize {
+        self.bytes.len() % self.page_size
+    }
+
+    /// Walk the image without copying inline keys and values.
+    pub fn borrowed(&self) -> io::Result<BorrowedImage<'_>> {
+        BorrowedImage::new(&self.bytes)
+    }
+}
+
+impl PageSource for MemoryPageSource {
+    fn read_page(&self, page_no: PageNumber) -> io::Result<ByteVec> {
+        // Saturating, so a page number past the end fails instead of wrapping on 32-bit.
+        let start = (page_no as usize).saturating_mul(self.page_size);
+        self.bytes
+            .get(start..start.saturating_add(self.page_size))
+            .map(<[u8]>::to_vec)
+            .ok_or_else(|| {
+                missing_page(
+                    page_no,
+                    self.page_size,
+                    self.bytes.len() as u64,
+                    &self.source_id,
+                )
+            })
+    }
+
+    fn page_count(&self) -> Option<u64> {
+        Some((self.bytes.len() / self.page_size) as u64)
+    }
+
+    fn source_id(&self) -> String {
+        self.source_id.clone()
+    }
+}
@@ -0,0 +1,293 @@
+//! Database statistics, in the spirit of `db_stat -d`: what the pages of an image are
+//! used for, how full the leaves are, and where the large records live.
+//!
+//! Everything is gathered in one pass over the page headers plus one walk of every tree.
+//! Statistics are best effort: pages that cannot be read or parsed are counted as
+//! unreadable and skipped, so a damaged image still gets a report.
+
+use std::{
+    cmp::Reverse,
+    collections::{BTreeMap, HashSet},
+    fmt, io,
+};
+
+use crate::{
+    entry::parser::split_walletdb_key,
+    storage::{
+        btree::{Node, SeparatorKey, overflow_ref, parse_node},
+        freelist::walk_freelist,
+        page::{EntryDescriptor, PageHeader, PageProtection, PageType},
+        reader::{BTM_SUBDB, FileDbImageReader},
+        supplier::read_overflow_chain,
+        types::{ByteVec, Endianness, PageNumber, PageSource},
+    },
+};
+
+/// Size of one record, as counted by [`collect_stats`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecordSize {
+    pub key: ByteVec,
+    /// Logical value length, including any overflow or blob part.
+    pub value_len: usize,
+    pub page_no: PageNumber,
+    pub slot_index: u16,
+}
+
+impl RecordSize {
+    pub fn total_len(&self) -> usize {
+        self.key.len() + self.value_len
+    }
+}
+
+/// The outcome of [`collect_stats`].
+#[derive(Debug, Clone, Default)]
+pub struct DbStats {
+    pub page_size: usize,
+    pub page_count: u64,
+    /// Pages of each on-disk page type, over the whole image.
+    pub page_types: BTreeMap<u8, u64>,
+    /// Pages whose header could not be read.
+    pub unreadable_pages: u64,
+    /// Tree pages at each level (leaves are level 1), over every tree.
+    pub levels: BTreeMap<u8, u64>,
+    pub leaf_pages: u64,
+    /// Free bytes between the slot array and the items, summed over the leaves.
+    pub leaf_free_bytes: u64,
+    /// Live key/value pairs on btree leaves.
+    pub records: u64,
+    /// Overflow chains referenced from the trees, by length in pages.
+    pub overflow_chains: BTreeMap<usize, u64>,
+    /// The largest live records, largest first.
+    pub largest: Vec<RecordSize>,
+    /// Pages on the freelist.
+    pub free_pages: usize,
+}
+
+impl DbStats {
+    /// Average share of a leaf page in use, from 0.0 to 1.0: `db_stat`'s fill factor.
+    pub fn leaf_fill_factor(&self) -> f64 {
+        let total = self.leaf_pages * self.page_size as u64;
+        if total == 0 {
+            return 0.0;
+        }
+        1.0 - self.leaf_free_bytes as f64 / total as f64
+    }
+
+    /// Pages taken by all overflow chains.
+    pub fn overflow_pages(&self) -> u64 {
+        self.overflow_chains
+            .iter()
+            .map(|(len, n)| *len as u64 * n)
+            .sum()
+    }
+}
+
+impl fmt::Display for DbStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "DbStats {{")?;
+        writeln!(f, "  page size    : {}", self.page_size)?;
+        writeln!(f, "  pages        : {}", self.page_count)?;
+        for (page_type, n) in &self.page_types {
+            let name = format!("{:?}", PageType::from(*page_type));
+            writeln!(f, "  {name:<13}: {n}")?;
+        }
+        if self.unreadable_pages > 0 {
+            writeln!(f, "  unreadable   : {}", self.unreadable_pages)?;
+        }
+        for (level, n) in self.levels.iter().rev() {
+            writeln!(f, "  {:<13}: {n}", format!("level {level}"))?;
+        }
+        writeln!(
+            f,
+            "  leaf fill    : {:.1}% ({} bytes free)",
+            self.leaf_fill_factor() * 100.0,
+            self.leaf_free_bytes
+        )?;
+        writeln!(f, "  records      : {}", self.records)?;
+        writeln!(
+            f,
+            "  overflow     : {} chains, {} pages",
+            self.overflow_chains.values().sum::<u64>(),
+            self.overflow_pages()
+        )?;
+        for (len, n) in &self.overflow_chains {
+            writeln!(f, "  {:<13}: {n}", format!("chain of {len}"))?;
+        }
+        writeln!(f, "  free pages   : {}", self.free_pages)?;
+        for r in &self.largest {
+            let tag = split_walletdb_key(&r.key).map_or("?", |(tag, _)| tag);
+            writeln!(
+                f,
+                "  largest      : page {} slot {}: tag={tag} key_len={} val_len={}",
+                r.page_no,
+                r.slot_index,
+                r.key.len(),
+                r.value_len
+            )?;
+        }
+        write!(f, "}}")
+    }
+}
+
+struct Collector<'a> {
+    source: &'a dyn PageSource,
+    endianness: Endianness,
+    protection: PageProtection,
+    largest: usize,
+    seen: HashSet<PageNumber>,
+    stats: DbStats,
+}
+
+impl Collector<'_> {
+    /// Count the pages of every header in the image by type.
+    fn count_pages(&mut self) {
+        let mut pgno: PageNumber = 0;
+        loop {
+            if self.source.page_count().is_some_and(|n| pgno as u64 >= n) {
+                break;
+            }
+            match self.source.read_page(pgno) {
+                Ok(page) => {
+                    if self.stats.page_size == 0 {
+                        self.stats.page_size = page.len();
+                    }
+                    match PageHeader::parse(&page, self.endianness, self.protection) {
+                        Ok(hdr) => *self.stats.page_types.entry(hdr.page_type).or_default() += 1,
+                        Err(_) => self.stats.unreadable_pages += 1,
+                    }
+                }
+                Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => break,
+                Err(_) => self.stats.unreadable_pages += 1,
+            }
+            pgno += 1;
+        }
+        self.stats.page_count = pgno as u64;
+    }
+
+    fn walk_tree(&mut self, root: PageNumber) {
+        let mut stack = vec![root];
+        while let Some(pgno) = stack.pop() {
+            if pgno == 0 || !self.seen.insert(pgno) {
+                continue;
+            }
+            let Ok(page) = self.source.read_page(pgno) else {
+                continue;
+            };
+            let Ok(hdr) = PageHeader::parse(&page, self.endianness, self.protection) else {
+                continue;
+            };
+            *self.stats.levels.entry(hdr.level).or_default() += 1;
+            if matches!(hdr.kind(), PageType::BtreeLeaf | PageType::RecnoLeaf) {
+                let lower = hdr.lower_bound(self.protection.header_size(), 2);
+                self.stats.leaf_pages += 1;
+                self.stats.leaf_free_bytes += hdr.upper_bound().saturating_sub(lower) as u64;
+            }
+            match parse_node(&page, self.endianness, self.protection) {
+                Ok(Node::Internal { keys, children }) => {
+                    for key in keys {
+                        if let SeparatorKey::Overflow(r) = key {
+                            self.count_chain(r.first_page);
+                        }
+                    }
+                    stack.extend(children.into_iter().rev());
+                }
+                Ok(Node::Leaf { entries }) => {
+                    for d in entries {
+                        self.count_entry(pgno, &page, d);
+                    }
+                }
+                Err(_) => {}
+            }
+        }
+    }
+
+    fn count_entry(&mut self, pgno: PageNumber, page: &[u8], d: EntryDescriptor) {
+        let key_item = &page[d.key_range.0..d.key_range.1];
+        let value_item = &page[d.value_range.0..d.value_range.1];
+        if d.flags & EntryDescriptor::KEY_OVERFLOW != 0 {
+            self.count_chain(overflow_ref(key_item, self.endianness).first_page);
+        }
+        if d.flags & EntryDescriptor::VALUE_OVERFLOW != 0 {
+            self.count_chain(overflow_ref(value_item, self.endianness).first_page);
+        }
+        if d.is_deleted() {
+            return;
+        }
+        self.stats.records += 1;
+        if self.largest == 0 {
+            return;
+        }
+        let smallest_kept = match self.stats.largest.len() {
+            n if n < self.largest => 0,
+            n => self.stats.largest[n - 1].total_len(),
+        };
+        if d.key_len + d.value_len <= smallest_kept {
+            return;
+        }
+        let key = if d.flags & EntryDescriptor::KEY_OVERFLOW != 0 {
+            let r = overflow_ref(key_item, self.endianness);
+            match read_overflow_chain(self.source, r, self.endianness, self.protection) {
+                Ok(key) => key,
+                Err(_) => return,
+            }
+        } else {
+            key_item.to_vec()
+        };
+        self.stats.largest.push(RecordSize {
+            key,
+            value_len: d.value_len,
+            page_no: pgno,
+            slot_index: d.slot_index,
+        });
+        self.stats
+            .largest
+            .sort_by_key(|r| (Reverse(r.total_len()), r.page_no, r.slot_index));
+        self.stats.largest.truncate(self.largest);
+    }
+
+    /// Record the length of the overflow chain starting at `first`, up to the first page
+    /// that is not an overflow page or was seen before.
+    fn count_chain(&mut self, first: PageNumber) {
+        let mut pgno = first;
+        let mut len = 0;
+        while pgno != 0 && self.seen.insert(pgno) {
+            let next = self
+                .source
+                .read_page(pgno)
+                .and_then(|p| PageHeader::parse(&p, self.endianness, self.protection));
+            match next {
+                Ok(hdr) if hdr.kind() == PageType::Overflow => {
+                    len += 1;
+                    pgno = hdr.next_pgno;
+                }
+                _ => break,
+            }
+        }
+        if len > 0 {
+            *self.stats.overflow_chains.entry(len).or_default() += 1;
+        }
+    }
+}
+
+/// Gather statistics over the image behind `reader`, keeping the `largest` biggest
+/// records. Only a failure to walk the freelist or list the subdatabases is an error.
+pub fn collect_stats(reader: &FileDbImageReader, largest: usize) -> io::Result<DbStats> {
+    let meta = reader.meta();
+    let mut collector = Collector {
+        source: reader.source().as_ref(),
+        endianness: reader.endianness(),
+        protection: reader.protection(),
+        largest,
+        seen: HashSet::from([0]),
+        stats: DbStats::default(),
+    };
+    collector.count_pages();
+    collector.walk_tree(meta.root);
+    if meta.flags & BTM_SUBDB != 0 {
+        for sub in reader.subdatabases()? {
+            collector.walk_tree(sub.root);
+        }
+    }
+    collector.stats.free_pages = walk_freelist(reader.source().as_ref())?.pages.len();
+    Ok(collector.stats)
+}
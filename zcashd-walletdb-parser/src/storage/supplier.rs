@@ -0,0 +1,486 @@
+//! Concrete [`ValueSupplier`]s for values stored inline on a leaf page and values stored
+//! on an overflow chain.
+
+use std::{
+    borrow::Cow,
+    collections::HashSet,
+    fmt,
+    io::{self, Write},
+    ops::Range,
+    sync::Arc,
+};
+
+pub use crate::error::OverflowChainError;
+use crate::{
+    entry::constants::OverflowRef,
+    storage::{
+        page::{PageHeader, PageProtection, PageType, ValueSupplier},
+        types::{ByteSlice, ByteVec, Endianness, PageNumber, PageSource},
+    },
+};
+
+/// A value that lives inline on a leaf page. Holds a shared reference to the page buffer,
+/// so borrowing never copies.
+#[derive(Clone)]
+pub struct InlineSupplier {
+    page: Arc<[u8]>,
+    range: Range<usize>,
+}
+
+impl InlineSupplier {
+    /// `range` must lie within `page`.
+    pub fn new(page: Arc<[u8]>, range: Range<usize>) -> Self {
+        debug_assert!(range.end <= page.len());
+        InlineSupplier { page, range }
+    }
+}
+
+impl fmt::Debug for InlineSupplier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("InlineSupplier")
+            .field("range", &self.range)
+            .finish()
+    }
+}
+
+impl ValueSupplier for InlineSupplier {
+    fn materialize(&self) -> io::Result<ByteVec> {
+        Ok(self.page[self.range.clone()].to_vec())
+    }
+
+    fn try_borrow<'a>(&'a self) -> Option<ByteSlice<'a>> {
+        Some(Cow::Borrowed(&self.page[self.range.clone()]))
+    }
+}
+
+/// A value stored on a chain of overflow pages. Nothing is read until `materialize`.
+#[derive(Clone)]
+pub struct OverflowSupplier {
+    source: Arc<dyn PageSource>,
+    reference: OverflowRef,
+    endianness: Endianness,
+    protection: PageProtection,
+}
+
+impl OverflowSupplier {
+    pub fn new(
+        source: Arc<dyn PageSource>,
+        reference: OverflowRef,
+        endianness: Endianness,
+        protection: PageProtection,
+    ) -> Self {
+        OverflowSupplier {
+            source,
+            reference,
+            endianness,
+            protection,
+        }
+    }
+
+    pub fn reference(&self) -> OverflowRef {
+        self.reference
+    }
+}
+
+impl fmt::Debug for OverflowSupplier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OverflowSupplier")
+            .field("source", &self.source.source_id())
+            .field("reference", &self.reference)
+            .finish()
+    }
+}
+
+impl ValueSupplier for OverflowSupplier {
+    fn materialize(&self) -> io::Result<ByteVec> {
+        read_overflow_chain(
+            self.source.as_ref(),
+            self.reference,
+            self.endianness,
+            self.protection,
+        )
+    }
+
+    fn try_borrow<'a>(&'a self) -> Option<ByteSlice<'a>> {
+        None
+    }
+
+    fn write_to(&self, sink: &mut dyn Write) -> io::Result<u64> {
+        read_overflow_chain_into(
+            self.source.as_ref(),
+            self.reference,
+            self.endianness,
+            self.protection,
+            sink,
+        )
+    }
+}
+
+impl From<OverflowChainError> for io::Error {
+    fn from(e: OverflowChainError) -> Self {
+        io::Error::new(io::ErrorKind::InvalidData, e)
+    }
+}
+
+/// Incremental reassembly of an overflow chain: ask [`Self::next_page`] which page to read,
+/// hand it to [`Self::push`], and repeat until `next_page` returns `None`. Keeps the
+/// chain checks independent of how pages are fetched.
+pub(crate) struct OverflowChain {
+    reference: OverflowRef,
+    endianness: Endianness,
+    protection: PageProtection,
+    page_count: Option<u64>,
+    out: ByteVec,
+    seen: HashSet<PageNumber>,
+    pgno: PageNumber,
+    rem: usize,
+}
+
+impl OverflowChain {
+    pub(crate) fn new(
+        reference: OverflowRef,
+        endianness: Endianness,
+        protection: PageProtection,
+        page_count: Option<u64>,
+    ) -> Self {
+        OverflowChain {
+            reference,
+            endianness,
+            protection,
+            page_count,
+            out: Vec::new(),
+            seen: HashSet::new(),
+            pgno: reference.first_page,
+            rem: reference.total_len as usize,
+        }
+    }
+
+    /// The page to read next, or `None` once all `total_len` bytes are in.
+    pub(crate) fn next_page(&mut self) -> Result<Option<PageNumber>, OverflowChainError> {
+        if self.rem == 0 {
+            return Ok(None);
+        }
+        if self.pgno == 0 {
+            return Err(OverflowChainError::TooShort {
+                total_len: self.reference.total_len,
+                missing: self.rem,
+            });
+        }
+        if !self.seen.insert(self.pgno) {
+            return Err(OverflowChainError::Cycle { pgno: self.pgno });
+        }
+        Ok(Some(self.pgno))
+    }
+
+    /// Check `page`, the page returned by the last [`Self::next_page`], and return the
+    /// part of its payload that belongs to the value.
+    pub(crate) fn payload<'p>(&mut self, page: &'p [u8]) -> io::Result<&'p [u8]> {
+        let overhead = self.protection.header_size();
+        if self.seen.len() == 1 {
+            let per_page = page.len().saturating_sub(overhead).max(1);
+            if let Some(max_pages) = self.page_count
+                && self.rem.div_ceil(per_page) as u64 > max_pages
+            {
+                return Err(OverflowChainError::TooLong {
+                    total_len: self.reference.total_len,
+                    max_pages,
+                }
+                .into());
+            }
+        }
+        let hdr = PageHeader::parse(page, self.endianness, self.protection)?;
+        if hdr.kind() != PageType::Overflow {
+            return Err(OverflowChainError::WrongPageType {
+                pgno: self.pgno,
+                page_type: hdr.page_type,
+            }
+            .into());
+        }
+        let payload = &page[overhead..];
+        let take = self.rem.min(hdr.upper_bound()).min(payload.len());
+        self.rem -= take;
+        self.pgno = hdr.next_pgno;
+        Ok(&payload[..take])
+    }
+
+    /// Append the payload of the page returned by the last [`Self::next_page`].
+    pub(crate) fn push(&mut self, page: &[u8]) -> io::Result<()> {
+        let payload = self.payload(page)?;
+        if self.out.capacity() == 0 {
+            // Without a page count a bogus `total_len` cannot be caught up front, so only
+            // reserve what the first page held and grow as the chain proves longer.
+            self.out.reserve_exact(match self.page_count {
+                Some(_) => payload.len() + self.rem,
+                None => payload.len(),
+            });
+        }
+        self.out.extend_from_slice(payload);
+        Ok(())
+    }
+
+    pub(crate) fn into_bytes(self) -> ByteVec {
+        self.out
+    }
+}
+
+/// Reassemble `total_len` bytes from an overflow chain read through `source`.
+/// Each overflow page holds `hf_offset` payload bytes right after the page header.
+///
+/// The chain may visit each page once and may not be longer than the image, so corrupted
+/// `next_pgno` links or a bogus `total_len` fail with an [`OverflowChainError`] instead of
+/// looping or allocating without bound.
+pub fn read_overflow_chain(
+    source: &dyn PageSource,
+    reference: OverflowRef,
+    endianness: Endianness,
+    protection: PageProtection,
+) -> io::Result<ByteVec> {
+    let mut chain = OverflowChain::new(reference, endianness, protection, source.page_count());
+    while let Some(pgno) = chain.next_page()? {
+        chain.push(&source.read_page(pgno)?)?;
+    }
+    Ok(chain.into_bytes())
+}
+
+/// Like [`read_overflow_chain`], but write the value to `sink` one page at a time instead
+/// of collecting it, so memory use does not grow with the value. Returns the number of
+/// bytes written. On error, `sink` holds the part of the value read so far.
+pub fn read_overflow_chain_into(
+    source: &dyn PageSource,
+    reference: OverflowRef,
+    endianness: Endianness,
+    protection: PageProtection,
+    sink: &mut dyn Write,
+) -> io::Result<u64> {
+    let mut chain = OverflowChain::new(reference, endianness, protection, source.page_count());
+    let mut written = 0;
+    while let Some(pgno) = chain.next_page()? {
+        let page = source.read_page(pgno)?;
+        let payload = chain.payload(&page)?;
+        sink.write_all(payload)?;
+        written += payload.len() as u64;
+    }
+    Ok(written)
+}
+
+/// Best-effort variant of [`read_overflow_chain`]: stops at the first page that cannot be
+/// followed (unreadable, wrong type, cycle, early end) and returns what was collected.
+/// The flag is `true` only when all `total_len` bytes were recovered.
+pub(crate) fn read_overflow_lenient(
+    source: &dyn PageSource,
+    reference: OverflowRef,
+    endianness: Endianness,
+    protection: PageProtection,
+) -> (ByteVec, bool) {
+    let overhead = protection.header_size();
+    let mut out = Vec::new();
+    let mut seen = HashSet::new();
+    let mut pgno = reference.first_page;
+    let mut rem = reference.total_len as usize;
+
+    while rem > 0 && pgno != 0 && seen.insert(pgno) {
+        let Ok(page) = source.read_page(pgno) else {
+            break;
+        };
+        let Ok(hdr) = PageHeader::parse(&page, endianness, protection) else {
+            break;
+        };
+        if hdr.kind() != PageType::Overflow {
+            break;
+        }
+        let payload = &page[overhead..];
+        let take = rem.min(hdr.upper_bound()).min(payload.len());
+        out.extend_from_slice(&payload[..take]);
+        rem -= take;
+        pgno = hdr.next_pgno;
+    }
+    (out, rem == 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+    use crate::{
+        constants::{P_LBTREE, P_OVERFLOW},
+        storage::source::MemoryPageSource,
+    };
+
+    const PAGE: usize = 512;
+
+    /// A little-endian page `pgno` of type `page_type`, linked to `next`, whose payload is
+    /// `payload`.
+    fn page(pgno: u32, next: u32, page_type: u8, payload: &[u8]) -> Vec<u8> {
+        let mut page = vec![0; PAGE];
+        page[8..12].copy_from_slice(&pgno.to_le_bytes());
+        page[16..20].copy_from_slice(&next.to_le_bytes());
+        page[22..24].copy_from_slice(&(payload.len() as u16).to_le_bytes());
+        page[25] = page_type;
+        page[26..26 + payload.len()].copy_from_slice(payload);
+        page
+    }
+
+    /// An image of page 0 followed by `pages`, numbered from 1.
+    fn source(pages: &[Vec<u8>]) -> MemoryPageSource {
+        let mut image = vec![0; PAGE];
+        image.extend(pages.iter().flatten());
+        MemoryPageSource::new(image, PAGE as u32, "chain").unwrap()
+    }
+
+    fn read(source: &MemoryPageSource, total_len: u32) -> io::Result<ByteVec> {
+        let reference = OverflowRef {
+            first_page: 1,
+            total_len,
+        };
+        read_overflow_chain(source, reference, Endianness::Little, PageProtection::None)
+    }
+
+    fn chain_error(result: io::Result<ByteVec>) -> OverflowChainError {
+        let e = result.unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+        e.get_ref()
+            .and_then(|e| e.downcast_ref::<OverflowChainError>())
+            .cloned()
+            .unwrap()
+    }
+
+    #[test]
+    fn a_whole_chain_is_read_in_order() {
+        let source = source(&[
+            page(1, 2, P_OVERFLOW, &[1; 400]),
+            page(2, 3, P_OVERFLOW, &[2; 400]),
+            page(3, 0, P_OVERFLOW, &[3; 100]),
+        ]);
+        let value = read(&source, 900).unwrap();
+        assert_eq!(value, [[1; 400].as_slice(), &[2; 400], &[3; 100]].concat());
+    }
+
+    #[test]
+    fn a_chain_linking_back_is_a_cycle() {
+        let source = source(&[
+            page(1, 2, P_OVERFLOW, &[1; 400]),
+            page(2, 1, P_OVERFLOW, &[2; 400]),
+        ]);
+        assert_eq!(
+            chain_error(read(&source, 1_000)),
+            OverflowChainError::Cycle { pgno: 1 }
+        );
+    }
+
+    #[test]
+    fn a_chain_ending_early_is_too_short() {
+        let source = source(&[
+            page(1, 2, P_OVERFLOW, &[1; 400]),
+            page(2, 0, P_OVERFLOW, &[2; 400]),
+        ]);
+        assert_eq!(
+            chain_error(read(&source, 1_000)),
+            OverflowChainError::TooShort {
+                total_len: 1_000,
+                missing: 200,
+            }
+        );
+    }
+
+    #[test]
+    fn a_length_the_image_cannot_hold_is_too_long() {
+        let source = source(&[page(1, 0, P_OVERFLOW, &[1; 400])]);
+        // 486 payload bytes a page, and the image holds two pages.
+        assert_eq!(
+            chain_error(read(&source, 10_000)),
+            OverflowChainError::TooLong {
+                total_len: 10_000,
+                max_pages: 2,
+            }
+        );
+    }
+
+    #[test]
+    fn a_chain_through_a_leaf_page_has_the_wrong_page_type() {
+        let source = source(&[
+            page(1, 2, P_OVERFLOW, &[1; 400]),
+            page(2, 0, P_LBTREE, &[2; 400]),
+        ]);
+        assert_eq!(
+            chain_error(read(&source, 800)),
+            OverflowChainError::WrongPageType {
+                pgno: 2,
+                page_type: P_LBTREE,
+            }
+        );
+    }
+
+    /// Counts the pages read through it.
+    #[derive(Debug)]
+    struct Counting(MemoryPageSource, AtomicUsize);
+
+    impl Counting {
+        fn reads(&self) -> usize {
+            self.1.load(Ordering::Relaxed)
+        }
+    }
+
+    impl PageSource for Counting {
+        fn read_page(&self, page_no: PageNumber) -> io::Result<ByteVec> {
+            self.1.fetch_add(1, Ordering::Relaxed);
+            self.0.read_page(page_no)
+        }
+
+        fn page_count(&self) -> Option<u64> {
+            self.0.page_count()
+        }
+
+        fn source_id(&self) -> String {
+            self.0.source_id()
+        }
+    }
+
+    #[test]
+    fn inline_values_are_borrowed_from_their_page() {
+        let page: Arc<[u8]> = (0..=255).collect::<Vec<u8>>().into();
+        let supplier = InlineSupplier::new(page.clone(), 10..14);
+        let Some(Cow::Borrowed(borrowed)) = supplier.try_borrow() else {
+            panic!("an inline value is borrowed");
+        };
+        assert!(std::ptr::eq(borrowed, &page[10..14]));
+        assert_eq!(supplier.materialize().unwrap(), [10, 11, 12, 13]);
+        let mut sink = Vec::new();
+        assert_eq!(supplier.write_to(&mut sink).unwrap(), 4);
+        assert_eq!(sink, [10, 11, 12, 13]);
+        assert_eq!(InlineSupplier::new(page, 7..7).materialize().unwrap(), []);
+    }
+
+    #[test]
+    fn overflow_values_are_read_only_when_asked_for() {
+        let source = Arc::new(Counting(
+            source(&[
+                page(1, 2, P_OVERFLOW, &[1; 400]),
+                page(2, 0, P_OVERFLOW, &[2; 100]),
+            ]),
+            Default::default(),
+        ));
+        let reference = OverflowRef {
+            first_page: 1,
+            total_len: 500,
+        };
+        let supplier = OverflowSupplier::new(
+            source.clone(),
+            reference,
+            Endianness::Little,
+            PageProtection::None,
+        );
+        // The length is known from the reference alone.
+        assert_eq!(supplier.reference().total_len, 500);
+        assert!(supplier.try_borrow().is_none());
+        assert_eq!(source.reads(), 0);
+
+        let value = supplier.materialize().unwrap();
+        assert_eq!(value, [[1; 400].as_slice(), &[2; 100]].concat());
+        assert_eq!(source.reads(), 2);
+        let mut sink = Vec::new();
+        assert_eq!(supplier.write_to(&mut sink).unwrap(), 500);
+        assert_eq!(sink, value);
+        assert_eq!(source.reads(), 4);
+    }
+}
@@ -1,11 +1,35 @@
-use std::{borrow::Cow, fmt::Debug, io};
+use alloc::{borrow::Cow, string::String, vec::Vec};
+use core::fmt;
+#[cfg(feature = "std")]
+use std::{fmt::Debug, io};
 
-#[derive(Debug, Clone, Copy)]
+use crate::{storage::page::PageProtection, util::Endian};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum Endianness {
     Little,
     Big,
 }
 
+impl From<Endian> for Endianness {
+    fn from(e: Endian) -> Self {
+        match e {
+            Endian::Le => Endianness::Little,
+            Endian::Be => Endianness::Big,
+        }
+    }
+}
+
+impl From<Endianness> for Endian {
+    fn from(e: Endianness) -> Self {
+        match e {
+            Endianness::Little => Endian::Le,
+            Endianness::Big => Endian::Be,
+        }
+    }
+}
+
 /// A page number in the BDB storage format.
 /// This represents the `pgno` field of a page header.
 pub type PageNumber = u32;
@@ -22,6 +46,7 @@ pub type ByteVec = Vec<u8>;
 pub type ByteSlice<'a> = Cow<'a, [u8]>;
 
 /// Low-level source of pages.
+#[cfg(feature = "std")]
 pub trait PageSource: Debug + Send + Sync {
     /// Read a single page by page number. Returns the raw bytes.
     fn read_page(&self, page_no: PageNumber) -> io::Result<ByteVec>;
@@ -35,9 +60,47 @@ pub trait PageSource: Debug + Send + Sync {
 
 /// Represents the format of the BDB storage.
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct FormatProfile {
     pub page_size: PageSize,
     pub endianness: Endianness,
     pub btree_root: PageNumber,
+    /// The raw `version` field of the meta page.
+    pub meta_version: u32,
+    /// The Berkeley DB releases that write `meta_version`, e.g. "Berkeley DB 4.1-5.3".
     pub berkeley_db_version: Option<String>,
+    /// What follows the header of every non-meta page.
+    pub protection: PageProtection,
+    /// Pages carry a checksum (`DB_CHKSUM`, or the HMAC of an encrypted database).
+    pub checksummed: bool,
+    /// Pages are encrypted (`DB_ENCRYPT`).
+    pub encrypted: bool,
+    /// The file holds named subdatabases rather than a single database.
+    pub has_subdatabases: bool,
+    /// The highest page number the meta page claims is allocated.
+    pub last_pgno: PageNumber,
+    /// Whole pages actually present, if the source knows.
+    pub page_count: Option<u64>,
+}
+
+impl fmt::Display for FormatProfile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "FormatProfile {{")?;
+        writeln!(f, "  endianness   : {:?}", self.endianness)?;
+        writeln!(f, "  pagesize     : {}", self.page_size)?;
+        writeln!(f, "  version      : {}", self.meta_version)?;
+        if let Some(release) = &self.berkeley_db_version {
+            writeln!(f, "  release      : {release}")?;
+        }
+        writeln!(f, "  protection   : {:?}", self.protection)?;
+        writeln!(f, "  checksummed  : {}", self.checksummed)?;
+        writeln!(f, "  encrypted    : {}", self.encrypted)?;
+        writeln!(f, "  subdatabases : {}", self.has_subdatabases)?;
+        writeln!(f, "  root         : {}", self.btree_root)?;
+        writeln!(f, "  last_pgno    : {}", self.last_pgno)?;
+        if let Some(n) = self.page_count {
+            writeln!(f, "  page_count   : {n}")?;
+        }
+        write!(f, "}}")
+    }
 }
@@ -0,0 +1,119 @@
+//! Btree on-disk format versions and the features each one can carry.
+//!
+//! The page header and item layouts are the same across the versions understood here; what
+//! changes is which meta fields are meaningful. `encrypt_alg` and the `DB_CHKSUM` metaflag
+//! only exist from version 9, and blob (external file) items only from version 10.
+
+use std::{fmt, io};
+
+/// A btree meta page `version` the crate knows how to read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum BtreeVersion {
+    /// Berkeley DB 4.0.
+    V8,
+    /// Berkeley DB 4.1 through 5.3. Bitcoin Core and early zcashd builds use 4.8.
+    V9,
+    /// Berkeley DB 6.x. Current zcashd builds use 6.2.
+    V10,
+}
+
+impl BtreeVersion {
+    pub const OLDEST: BtreeVersion = BtreeVersion::V8;
+    pub const NEWEST: BtreeVersion = BtreeVersion::V10;
+
+    pub fn from_meta(version: u32) -> Option<Self> {
+        match version {
+            8 => Some(BtreeVersion::V8),
+            9 => Some(BtreeVersion::V9),
+            10 => Some(BtreeVersion::V10),
+            _ => None,
+        }
+    }
+
+    /// The value stored in the meta page.
+    pub fn code(self) -> u32 {
+        match self {
+            BtreeVersion::V8 => 8,
+            BtreeVersion::V9 => 9,
+            BtreeVersion::V10 => 10,
+        }
+    }
+
+    /// The Berkeley DB releases that write this version.
+    pub fn releases(self) -> &'static str {
+        match self {
+            BtreeVersion::V8 => "4.0",
+            BtreeVersion::V9 => "4.1-5.3",
+            BtreeVersion::V10 => "6.x",
+        }
+    }
+
+    /// Page checksums (`DB_CHKSUM`) appeared in 4.1.
+    pub fn supports_checksums(self) -> bool {
+        self >= BtreeVersion::V9
+    }
+
+    /// Page encryption (`DB_ENCRYPT`) appeared in 4.1.
+    pub fn supports_encryption(self) -> bool {
+        self >= BtreeVersion::V9
+    }
+
+    /// Blob items stored in external files appeared in 6.0.
+    pub fn supports_blobs(self) -> bool {
+        self >= BtreeVersion::V10
+    }
+
+    /// A human-readable name for the releases that write meta page `version`, including
+    /// versions this crate cannot read.
+    pub fn describe(version: u32) -> String {
+        match Self::from_meta(version) {
+            Some(v) => format!("Berkeley DB {}", v.releases()),
+            None if version > Self::NEWEST.code() => format!(
+                "Berkeley DB newer than {} (btree version {version})",
+                Self::NEWEST.releases()
+            ),
+            None => format!(
+                "Berkeley DB older than {} (btree version {version})",
+                Self::OLDEST.releases()
+            ),
+        }
+    }
+
+    /// Resolve a meta page version. Versions older than [`Self::OLDEST`] use page layouts
+    /// this crate does not parse and are rejected; versions newer than [`Self::NEWEST`]
+    /// are read as the newest known one, with a warning returned alongside.
+    pub fn resolve(version: u32) -> io::Result<(Self, Option<String>)> {
+        if let Some(v) = Self::from_meta(version) {
+            return Ok((v, None));
+        }
+        if version > Self::NEWEST.code() {
+            return Ok((
+                Self::NEWEST,
+                Some(format!(
+                    "btree version {version} is newer than any known release; \
+                     reading it as version {} (Berkeley DB {})",
+                    Self::NEWEST.code(),
+                    Self::NEWEST.releases()
+                )),
+            ));
+        }
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!(
+                "btree version {version} predates Berkeley DB {}; upgrade the file with db_upgrade",
+                Self::OLDEST.releases()
+            ),
+        ))
+    }
+}
+
+impl fmt::Display for BtreeVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "btree v{} (Berkeley DB {})",
+            self.code(),
+            self.releases()
+        )
+    }
+}
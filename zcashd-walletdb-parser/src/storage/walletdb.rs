@@ -0,0 +1,205 @@
+//! [`WalletDb`]: a pull-based view of a wallet.dat that never builds the full map.
+//!
+//! # Memory bounds
+//!
+//! [`WalletDb::open`] reads pages straight from the file without a page cache, and
+//! [`WalletDb::entries`] walks the trees depth-first, decoding leaf entries one at a time.
+//! While iterating, at most the following is resident:
+//!
+//! - the current leaf page (plus the internal page being expanded, briefly);
+//! - the pair being yielded, including one reassembled overflow chain when the key or
+//!   value is stored off-page;
+//! - the walk's bookkeeping: the stack of unvisited child page numbers and the set of
+//!   visited page numbers (four bytes per page of the trees).
+//!
+//! Nothing is retained once the caller drops a yielded pair.
+//!
+//! [`WalletDb::get`] reads only the pages on the path from the root of each tree to the
+//! leaf that would hold the key, plus the overflow pages of that path's keys and of the
+//! value found: a handful of pages however large the file is. [`WalletDb::scan_prefix`]
+//! and [`WalletDb::scan_tag`] descend the same way to the first key of the range, then
+//! walk on in key order and stop after its last key.
+
+use std::{io, path::Path, sync::Arc};
+
+use crate::{
+    entry::parser::walletdb_key_prefix,
+    parser::{
+        record::DecodeContext, registry::default_registry, summary::WalletSummary,
+        wallet::ZcashdWallet,
+    },
+    storage::{
+        btree::WalkItem,
+        compare::bt_compare,
+        entry::Provenance,
+        reader::{DataTree, FileDbImageReader},
+        source::FilePageSource,
+        types::{ByteVec, PageSource},
+    },
+};
+
+/// A wallet.dat opened for streaming.
+pub struct WalletDb {
+    reader: FileDbImageReader,
+}
+
+impl WalletDb {
+    /// Open a file on disk, reading each page on demand.
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::from_source(Arc::new(FilePageSource::open(path)?))
+    }
+
+    /// Stream from any page source. Wrapping the source in a cache trades the memory
+    /// bound for fewer reads.
+    pub fn from_source(source: Arc<dyn PageSource>) -> io::Result<Self> {
+        Ok(WalletDb {
+            reader: FileDbImageReader::from_source(source)?,
+        })
+    }
+
+    /// The underlying reader, for metadata and diagnostics.
+    pub fn reader(&self) -> &FileDbImageReader {
+        &self.reader
+    }
+
+    /// Look up the value stored under `key` and the page and slot it was read from.
+    ///
+    /// Each btree is descended from its root along the separator keys of its internal
+    /// pages rather than scanned; record-number trees, which are not keyed by bytes, are
+    /// skipped. With subdatabases, the first tree holding `key` wins.
+    pub fn get(&self, key: &[u8]) -> io::Result<Option<(ByteVec, Provenance)>> {
+        for tree in self.reader.data_roots()? {
+            if tree.recno.is_some() {
+                continue;
+            }
+            if let Some((_, value, prov)) = self.reader.walker(tree.root).get(key)? {
+                return Ok(Some((value.materialize()?, prov)));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Iterate over every live `(key, value)` pair and the page and slot it was read
+    /// from, in key order within each database.
+    ///
+    /// Unlike [`DbImageReader::entries`](crate::storage::consistency::DbImageReader::entries),
+    /// structural errors are yielded in place of the entries they hide rather than recorded,
+    /// and the walk continues with the next subtree.
+    pub fn entries(&self) -> WalletEntries<'_> {
+        let (trees, error) = match self.reader.data_roots() {
+            Ok(trees) => (trees, None),
+            Err(e) => (Vec::new(), Some(e)),
+        };
+        WalletEntries {
+            db: self,
+            trees: trees.into_iter(),
+            current: None,
+            error,
+            prefix: None,
+        }
+    }
+
+    /// Iterate over the live pairs whose key starts with `prefix`, in key order within
+    /// each database. Only the part of each tree that holds the range is read, so the cost
+    /// follows the number of matching pairs rather than the size of the file.
+    /// Record-number trees are skipped, as in [`Self::get`].
+    pub fn scan_prefix(&self, prefix: &[u8]) -> WalletEntries<'_> {
+        WalletEntries {
+            prefix: Some(prefix.to_vec()),
+            ..self.entries()
+        }
+    }
+
+    /// Iterate over the walletdb records with the given tag (e.g. `"tx"`, `"key"`), in
+    /// key order. See [`Self::scan_prefix`].
+    pub fn scan_tag(&self, tag: &str) -> WalletEntries<'_> {
+        self.scan_prefix(&walletdb_key_prefix(tag))
+    }
+
+    /// Decode every record with the built-in decoders into one [`ZcashdWallet`].
+    ///
+    /// Unlike the other readers this one builds the whole model in memory. A structural
+    /// error ends the read; a record that fails to decode is kept in
+    /// [`ZcashdWallet::errors`], one with an unknown tag in [`ZcashdWallet::unknown`], and
+    /// the read goes on.
+    pub fn decode(&self) -> io::Result<ZcashdWallet> {
+        let registry = default_registry();
+        let versions = self.scan_tag("version").chain(self.scan_tag("minversion"));
+        let versions = versions.collect::<io::Result<Vec<_>>>()?;
+        let ctx = DecodeContext::scan(versions.into_iter().map(|(key, value, _)| (key, value)));
+        let mut wallet = ZcashdWallet::default();
+        for entry in self.entries() {
+            let (key, value, _) = entry?;
+            wallet.insert_record(registry.decode_record(&ctx, &key, &value));
+        }
+        Ok(wallet)
+    }
+
+    /// Decode the wallet and summarize it on one page: see [`WalletSummary`].
+    pub fn summary(&self) -> io::Result<WalletSummary> {
+        self.decode().map(|wallet| WalletSummary::of(&wallet))
+    }
+
+    /// Entries of `tree` whose key starts with `prefix`. Errors are passed through, as
+    /// they may hide entries of the range.
+    fn prefix_entries(&self, tree: DataTree, prefix: &[u8]) -> Box<dyn Iterator<Item = WalkItem>> {
+        let entries = match self.reader.walker(tree.root).entries_from(prefix) {
+            Ok(entries) => entries,
+            Err(e) => return Box::new(std::iter::once(Err(e))),
+        };
+        let (end, keep) = (prefix.to_vec(), prefix.to_vec());
+        Box::new(
+            entries
+                .take_while(move |item| match item {
+                    Ok((key, ..)) => key.starts_with(&end) || bt_compare(key, &end).is_lt(),
+                    Err(_) => true,
+                })
+                .filter(move |item| match item {
+                    Ok((key, ..)) => key.starts_with(&keep),
+                    Err(_) => true,
+                }),
+        )
+    }
+}
+
+/// Iterator returned by [`WalletDb::entries`], [`WalletDb::scan_prefix`] and
+/// [`WalletDb::scan_tag`].
+pub struct WalletEntries<'a> {
+    db: &'a WalletDb,
+    trees: std::vec::IntoIter<DataTree>,
+    current: Option<Box<dyn Iterator<Item = WalkItem>>>,
+    /// An error finding the trees, yielded first.
+    error: Option<io::Error>,
+    /// Only yield keys starting with this, visiting only the trees' matching ranges.
+    prefix: Option<ByteVec>,
+}
+
+impl Iterator for WalletEntries<'_> {
+    type Item = io::Result<(ByteVec, ByteVec, Provenance)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(e) = self.error.take() {
+            return Some(Err(e));
+        }
+        loop {
+            if let Some(entries) = &mut self.current {
+                match entries.next() {
+                    Some(item) => {
+                        return Some(
+                            item.and_then(|(key, value, prov)| {
+                                Ok((key, value.materialize()?, prov))
+                            }),
+                        );
+                    }
+                    None => self.current = None,
+                }
+            }
+            let tree = self.trees.next()?;
+            self.current = match &self.prefix {
+                None => Some(self.db.reader.tree_entries(tree)),
+                Some(_) if tree.recno.is_some() => None,
+                Some(prefix) => Some(self.db.prefix_entries(tree, prefix)),
+            };
+        }
+    }
+}
@@ -0,0 +1,553 @@
+//! Serializing an [`InMemoryMap`] into a fresh wallet.dat.
+//!
+//! The output has the layout zcashd's Berkeley DB produces for a wallet: a master
+//! database whose only entry names one subdatabase (`"main"` by default), and that
+//! subdatabase's btree holding every pair in key order.
+//!
+//! | page | contents                                              |
+//! |------|-------------------------------------------------------|
+//! | 0    | meta page of the master database (`BTM_SUBDB`)        |
+//! | 1    | master database leaf: subdatabase name → meta page 2  |
+//! | 2    | meta page of the subdatabase                          |
+//! | 3    | root of the subdatabase tree                          |
+//! | 4..  | internal pages top-down, leaves in key order, overflow pages |
+//!
+//! Leaves are packed as full as they go, which is what a bulk load of sorted keys
+//! leaves behind. Items larger than Berkeley DB's overflow threshold for the page size
+//! are moved to overflow chains, and separator keys on internal pages are cut to the
+//! shortest prefix that still separates their children. Every page is stamped with the
+//! "not logged" LSN `[0][1]`, as after `db_load`.
+
+use std::io;
+
+use crate::{
+    constants::{DBMETA_CHKSUM, P_BTREEMETA, P_IBTREE, P_LBTREE, P_OVERFLOW},
+    storage::{
+        btree::{B_KEYDATA, B_OVERFLOW, BINTERNAL_SIZE, BOVERFLOW_SIZE},
+        checksum::write_page_checksum,
+        entry::InMemoryMap,
+        flags::BTM_SUBDB,
+        page::PageProtection,
+        types::{ByteVec, Endianness, PageNumber, PageSize},
+        version::BtreeVersion,
+    },
+    util::is_valid_page_size,
+};
+
+const BTREE_MAGIC: u32 = 0x0005_3162;
+
+const MASTER_ROOT: PageNumber = 1;
+const SUBDB_META: PageNumber = 2;
+const DATA_ROOT: PageNumber = 3;
+
+/// `minkey` Berkeley DB uses unless told otherwise; it sets the overflow threshold.
+const MINKEY: usize = 2;
+/// Pad byte recorded in btree meta pages (`re_pad` defaults to a space).
+const DEFAULT_RE_PAD: u32 = 0x20;
+
+/// How [`write_wallet`] lays out the file.
+#[derive(Debug, Clone)]
+pub struct WriteOptions {
+    pub page_size: PageSize,
+    pub endianness: Endianness,
+    pub version: BtreeVersion,
+    /// Store a checksum on every page (`DB_CHKSUM`).
+    pub checksum: bool,
+    /// File ID recorded in both meta pages.
+    pub uid: [u8; 20],
+    /// Name of the subdatabase holding the pairs.
+    pub subdatabase: ByteVec,
+}
+
+impl Default for WriteOptions {
+    /// 4 KiB little-endian pages in the Berkeley DB 4.8 format, as Bitcoin Core and
+    /// early zcashd builds write them.
+    fn default() -> Self {
+        WriteOptions {
+            page_size: 4096,
+            endianness: Endianness::Little,
+            version: BtreeVersion::V9,
+            checksum: false,
+            uid: [0; 20],
+            subdatabase: b"main".to_vec(),
+        }
+    }
+}
+
+fn invalid_input(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, msg)
+}
+
+/// Round up to the 4-byte alignment of on-page items.
+fn align4(n: usize) -> usize {
+    n.div_ceil(4) * 4
+}
+
+/// On-page size of a `BKEYDATA` item holding `len` bytes.
+fn bkeydata_size(len: usize) -> usize {
+    align4(3 + len)
+}
+
+/// A key or value as stored on a leaf or internal page.
+#[derive(Debug, Clone)]
+enum Item {
+    Inline(ByteVec),
+    /// Stored on overflow chain `chain`.
+    Overflow {
+        chain: usize,
+        len: u32,
+    },
+}
+
+impl Item {
+    fn leaf_size(&self) -> usize {
+        match self {
+            Item::Inline(bytes) => bkeydata_size(bytes.len()),
+            Item::Overflow { .. } => BOVERFLOW_SIZE,
+        }
+    }
+
+    fn internal_size(&self) -> usize {
+        match self {
+            Item::Inline(bytes) => align4(BINTERNAL_SIZE + bytes.len()),
+            Item::Overflow { .. } => align4(BINTERNAL_SIZE + BOVERFLOW_SIZE),
+        }
+    }
+}
+
+/// A page of the data tree before page numbers are assigned.
+#[derive(Debug)]
+struct Node {
+    /// Leaf items in slot order (key, value, key, value, ...), or one separator per
+    /// child on internal pages.
+    items: Vec<Item>,
+    /// Children, as indexes into the level below.
+    children: Vec<usize>,
+    /// Indexes of the first and last pair under this page.
+    first: usize,
+    last: usize,
+}
+
+/// Writes integers into a page in the byte order of the file.
+struct PageWriter<'a> {
+    page: &'a mut [u8],
+    e: Endianness,
+}
+
+impl PageWriter<'_> {
+    fn u16(&mut self, off: usize, v: u16) {
+        let b = match self.e {
+            Endianness::Little => v.to_le_bytes(),
+            Endianness::Big => v.to_be_bytes(),
+        };
+        self.page[off..off + 2].copy_from_slice(&b);
+    }
+
+    fn u32(&mut self, off: usize, v: u32) {
+        let b = match self.e {
+            Endianness::Little => v.to_le_bytes(),
+            Endianness::Big => v.to_be_bytes(),
+        };
+        self.page[off..off + 4].copy_from_slice(&b);
+    }
+
+    /// Fill in the 26-byte page header.
+    #[allow(clippy::too_many_arguments)]
+    fn header(
+        &mut self,
+        pgno: PageNumber,
+        prev: PageNumber,
+        next: PageNumber,
+        entries: u16,
+        hf_offset: u16,
+        level: u8,
+        page_type: u8,
+    ) {
+        // LSN [0][1]: not logged.
+        self.u32(0, 0);
+        self.u32(4, 1);
+        self.u32(8, pgno);
+        self.u32(12, prev);
+        self.u32(16, next);
+        self.u16(20, entries);
+        self.u16(22, hf_offset);
+        self.page[24] = level;
+        self.page[25] = page_type;
+    }
+
+    /// Write a `BOVERFLOW` body at `off`.
+    fn overflow_ref(&mut self, off: usize, pgno: PageNumber, len: u32) {
+        self.u16(off, 0);
+        self.page[off + 2] = B_OVERFLOW;
+        self.page[off + 3] = 0;
+        self.u32(off + 4, pgno);
+        self.u32(off + 8, len);
+    }
+}
+
+/// Everything needed to serialize the tree once its shape is known.
+struct Layout<'o> {
+    options: &'o WriteOptions,
+    header_size: usize,
+    /// Largest item kept on a page (`B_MINKEY_TO_OVFLSIZE`).
+    overflow_size: usize,
+    overflows: Vec<ByteVec>,
+}
+
+impl Layout<'_> {
+    fn page_size(&self) -> usize {
+        self.options.page_size as usize
+    }
+
+    /// Room for slots and items on one page.
+    fn capacity(&self) -> usize {
+        self.page_size() - self.header_size
+    }
+
+    fn item(&mut self, bytes: &[u8]) -> Item {
+        if bytes.len() <= self.overflow_size {
+            return Item::Inline(bytes.to_vec());
+        }
+        self.overflows.push(bytes.to_vec());
+        Item::Overflow {
+            chain: self.overflows.len() - 1,
+            len: bytes.len() as u32,
+        }
+    }
+
+    fn leaves(&mut self, pairs: &[(&[u8], &[u8])]) -> Vec<Node> {
+        let mut leaves = Vec::new();
+        let mut current = Node {
+            items: Vec::new(),
+            children: Vec::new(),
+            first: 0,
+            last: 0,
+        };
+        let mut used = 0;
+        for (i, (key, value)) in pairs.iter().enumerate() {
+            let key = self.item(key);
+            let value = self.item(value);
+            let need = 4 + key.leaf_size() + value.leaf_size();
+            if used + need > self.capacity() && !current.items.is_empty() {
+                let next = Node {
+                    items: Vec::new(),
+                    children: Vec::new(),
+                    first: i,
+                    last: i,
+                };
+                leaves.push(std::mem::replace(&mut current, next));
+                used = 0;
+            }
+            current.items.extend([key, value]);
+            current.last = i;
+            used += need;
+        }
+        leaves.push(current);
+        leaves
+    }
+
+    /// Internal pages over `below`. The first separator of a level is empty: Berkeley
+    /// DB never compares against it.
+    fn parents(&mut self, below: &[Node], pairs: &[(&[u8], &[u8])]) -> Vec<Node> {
+        let mut parents: Vec<Node> = Vec::new();
+        let mut used = 0;
+        for (child, node) in below.iter().enumerate() {
+            let separator = if child == 0 {
+                Item::Inline(Vec::new())
+            } else {
+                let prev = pairs[below[child - 1].last].0;
+                let first = pairs[node.first].0;
+                let common = prev.iter().zip(first).take_while(|(a, b)| a == b).count();
+                self.item(&first[..(common + 1).min(first.len())])
+            };
+            let need = 2 + separator.internal_size();
+            match parents.last_mut() {
+                Some(parent) if used + need <= self.capacity() => {
+                    parent.items.push(separator);
+                    parent.children.push(child);
+                    parent.last = node.last;
+                    used += need;
+                }
+                _ => {
+                    parents.push(Node {
+                        items: vec![separator],
+                        children: vec![child],
+                        first: node.first,
+                        last: node.last,
+                    });
+                    used = need;
+                }
+            }
+        }
+        parents
+    }
+
+    /// Pages an overflow chain of `len` bytes occupies.
+    fn chain_pages(&self, len: usize) -> usize {
+        len.div_ceil(self.capacity()).max(1)
+    }
+}
+
+/// Serialize `map` into a wallet.dat image.
+///
+/// Pairs are written in key order whatever order the map iterates in. The image reads
+/// back through [`FileDbImageReader`](crate::storage::reader::FileDbImageReader) to the
+/// same pairs and passes [`check`](crate::storage::consistency::check).
+pub fn write_wallet(map: &dyn InMemoryMap, options: &WriteOptions) -> io::Result<ByteVec> {
+    let page_size = options.page_size as usize;
+    if !is_valid_page_size(page_size) {
+        return Err(invalid_input(format!(
+            "unsupported page size {}",
+            options.page_size
+        )));
+    }
+    if options.checksum && !options.version.supports_checksums() {
+        return Err(invalid_input(format!(
+            "btree version {} cannot carry page checksums",
+            options.version.code()
+        )));
+    }
+    let protection = if options.checksum {
+        PageProtection::Checksum
+    } else {
+        PageProtection::None
+    };
+    let header_size = protection.header_size();
+    let mut layout = Layout {
+        options,
+        header_size,
+        overflow_size: (page_size - header_size) / (MINKEY * 2) - (bkeydata_size(0) + align4(1)),
+        overflows: Vec::new(),
+    };
+    if options.subdatabase.len() > layout.overflow_size {
+        return Err(invalid_input(format!(
+            "subdatabase name of {} bytes does not fit on a page",
+            options.subdatabase.len()
+        )));
+    }
+
+    let mut pairs: Vec<(&[u8], &[u8])> = map
+        .iter()
+        .map(|(key, entry)| (key.as_slice(), entry.value.as_slice()))
+        .collect();
+    pairs.sort_by(|a, b| a.0.cmp(b.0));
+
+    // levels[0] holds the leaves, the last level the root alone.
+    let mut levels = vec![layout.leaves(&pairs)];
+    while levels.last().is_some_and(|level| level.len() > 1) {
+        let parents = layout.parents(levels.last().expect("checked above"), &pairs);
+        levels.push(parents);
+    }
+
+    // The root sits at a fixed page; the other pages follow top-down, then the overflow
+    // chains.
+    let mut pgnos: Vec<Vec<PageNumber>> = levels.iter().map(|l| vec![0; l.len()]).collect();
+    let mut next = DATA_ROOT;
+    for numbers in pgnos.iter_mut().rev() {
+        for pgno in numbers.iter_mut() {
+            *pgno = next;
+            next += 1;
+        }
+    }
+    let mut chains = Vec::with_capacity(layout.overflows.len());
+    for bytes in &layout.overflows {
+        chains.push(next);
+        next += layout.chain_pages(bytes.len()) as PageNumber;
+    }
+    let page_count = next as usize;
+    let mut image = vec![0u8; page_count * page_size];
+    let metaflags = if options.checksum { DBMETA_CHKSUM } else { 0 };
+    let mut pages = image.chunks_exact_mut(page_size).collect::<Vec<_>>();
+    let e = options.endianness;
+
+    write_meta(
+        pages[0],
+        0,
+        options,
+        metaflags,
+        (page_count - 1) as PageNumber,
+        MASTER_ROOT,
+    );
+    write_meta(
+        pages[SUBDB_META as usize],
+        SUBDB_META,
+        options,
+        metaflags,
+        SUBDB_META,
+        DATA_ROOT,
+    );
+    write_leaf(
+        &mut PageWriter {
+            page: pages[MASTER_ROOT as usize],
+            e,
+        },
+        &layout,
+        MASTER_ROOT,
+        (0, 0),
+        &[
+            Item::Inline(options.subdatabase.clone()),
+            Item::Inline(SUBDB_META.to_be_bytes().to_vec()),
+        ],
+        &[],
+    );
+
+    for (level, nodes) in levels.iter().enumerate() {
+        for (index, node) in nodes.iter().enumerate() {
+            let pgno = pgnos[level][index];
+            let mut w = PageWriter {
+                page: &mut *pages[pgno as usize],
+                e,
+            };
+            if level == 0 {
+                let sibling =
+                    |i: Option<usize>| i.and_then(|i| pgnos[0].get(i)).copied().unwrap_or(0);
+                let links = (sibling(index.checked_sub(1)), sibling(Some(index + 1)));
+                write_leaf(&mut w, &layout, pgno, links, &node.items, &chains);
+            } else {
+                let children: Vec<PageNumber> =
+                    node.children.iter().map(|&c| pgnos[level - 1][c]).collect();
+                write_internal(
+                    &mut w,
+                    &layout,
+                    pgno,
+                    level as u8 + 1,
+                    node,
+                    &children,
+                    &chains,
+                );
+            }
+        }
+    }
+
+    for (bytes, &first) in layout.overflows.iter().zip(&chains) {
+        let chunks: Vec<&[u8]> = bytes.chunks(layout.capacity()).collect();
+        for (i, chunk) in chunks.iter().enumerate() {
+            let pgno = first + i as PageNumber;
+            let prev = if i == 0 { 0 } else { pgno - 1 };
+            let next = if i + 1 == chunks.len() { 0 } else { pgno + 1 };
+            let mut w = PageWriter {
+                page: &mut *pages[pgno as usize],
+                e,
+            };
+            // On overflow pages `entries` is the reference count and `hf_offset` the
+            // number of payload bytes.
+            w.header(pgno, prev, next, 1, chunk.len() as u16, 0, P_OVERFLOW);
+            w.page[header_size..header_size + chunk.len()].copy_from_slice(chunk);
+        }
+    }
+
+    if options.checksum {
+        for page in pages.iter_mut() {
+            write_page_checksum(page, e);
+        }
+    }
+    Ok(image)
+}
+
+fn write_meta(
+    page: &mut [u8],
+    pgno: PageNumber,
+    options: &WriteOptions,
+    metaflags: u8,
+    last_pgno: PageNumber,
+    root: PageNumber,
+) {
+    let mut w = PageWriter {
+        page,
+        e: options.endianness,
+    };
+    w.u32(0, 0);
+    w.u32(4, 1);
+    w.u32(8, pgno);
+    w.u32(12, BTREE_MAGIC);
+    w.u32(16, options.version.code());
+    w.u32(20, options.page_size);
+    w.page[24] = 0;
+    w.page[25] = P_BTREEMETA;
+    w.page[26] = metaflags;
+    w.u32(28, 0);
+    w.u32(32, last_pgno);
+    // Subdatabase meta pages carry BTM_SUBDB too, as Berkeley DB writes them.
+    w.u32(48, BTM_SUBDB);
+    w.page[52..72].copy_from_slice(&options.uid);
+    w.u32(76, MINKEY as u32);
+    w.u32(84, DEFAULT_RE_PAD);
+    w.u32(88, root);
+}
+
+/// Write a leaf page holding `items` in slot order. Items are packed against the end of
+/// the page, each key above its value.
+fn write_leaf(
+    w: &mut PageWriter<'_>,
+    layout: &Layout<'_>,
+    pgno: PageNumber,
+    (prev, next): (PageNumber, PageNumber),
+    items: &[Item],
+    chains: &[PageNumber],
+) {
+    let mut offset = layout.page_size();
+    for (slot, item) in items.iter().enumerate() {
+        offset -= item.leaf_size();
+        match item {
+            Item::Inline(bytes) => {
+                w.u16(offset, bytes.len() as u16);
+                w.page[offset + 2] = B_KEYDATA;
+                w.page[offset + 3..offset + 3 + bytes.len()].copy_from_slice(bytes);
+            }
+            Item::Overflow { chain, len } => w.overflow_ref(offset, chains[*chain], *len),
+        }
+        w.u16(layout.header_size + slot * 2, offset as u16);
+    }
+    w.header(
+        pgno,
+        prev,
+        next,
+        items.len() as u16,
+        offset as u16,
+        1,
+        P_LBTREE,
+    );
+}
+
+/// Write an internal page: one `BINTERNAL` item per child.
+fn write_internal(
+    w: &mut PageWriter<'_>,
+    layout: &Layout<'_>,
+    pgno: PageNumber,
+    level: u8,
+    node: &Node,
+    children: &[PageNumber],
+    chains: &[PageNumber],
+) {
+    let mut offset = layout.page_size();
+    for (slot, (item, &child)) in node.items.iter().zip(children).enumerate() {
+        offset -= item.internal_size();
+        match item {
+            Item::Inline(bytes) => {
+                w.u16(offset, bytes.len() as u16);
+                w.page[offset + 2] = B_KEYDATA;
+                w.page[offset + BINTERNAL_SIZE..offset + BINTERNAL_SIZE + bytes.len()]
+                    .copy_from_slice(bytes);
+            }
+            Item::Overflow { chain, len } => {
+                w.u16(offset, BOVERFLOW_SIZE as u16);
+                w.page[offset + 2] = B_OVERFLOW;
+                w.overflow_ref(offset + BINTERNAL_SIZE, chains[*chain], *len);
+            }
+        }
+        w.page[offset + 3] = 0;
+        w.u32(offset + 4, child);
+        // nrecs is only kept by BTM_RECNUM trees.
+        w.u32(offset + 8, 0);
+        w.u16(layout.header_size + slot * 2, offset as u16);
+    }
+    w.header(
+        pgno,
+        0,
+        0,
+        node.items.len() as u16,
+        offset as u16,
+        level,
+        P_IBTREE,
+    );
+}
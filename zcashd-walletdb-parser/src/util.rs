@@ -1,6 +1,17 @@
-use crate::page::PageType;
+use alloc::string::String;
+use core::fmt;
+
+#[cfg(feature = "serde")]
+pub mod serde_hex;
+
+use crate::{
+    constants::{DB_MAX_PGSIZE, DB_MIN_PGSIZE, SIZEOF_PAGE},
+    error::WalletDbError,
+    storage::page::PageHeader,
+};
 
 #[derive(Copy, Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum Endian {
     Le,
     Be,
@@ -44,44 +55,77 @@ pub fn detect_endian(buf: &[u8]) -> Option<Endian> {
 pub fn hex(bytes: &[u8]) -> String {
     let mut s = String::with_capacity(bytes.len() * 2);
     for b in bytes {
-        use std::fmt::Write;
+        use core::fmt::Write;
         let _ = write!(s, "{:02x}", b);
     }
     s
 }
 
-#[derive(Debug)]
-pub struct PageHeader {
-    pub lsn_file: u32,   // 0..=3
-    pub lsn_off: u32,    // 4..=7
-    pub pgno: u32,       // 8..=11
-    pub prev: u32,       // 12..=15
-    pub next: u32,       // 16..=19
-    pub entries: u16,    // 20..=21  (#slots)
-    pub hf_offset: u16,  // 22..=23  (start of data region)
-    pub level: u8,       // 24
-    pub ptype: PageType, // 25
+/// Parse the generic page header at the start of `page`, without any checksum block.
+pub fn parse_page_header(page: &[u8], e: Endian) -> Result<PageHeader, WalletDbError> {
+    if page.len() < SIZEOF_PAGE {
+        return Err(WalletDbError::BadPageHeader { len: page.len() });
+    }
+    Ok(PageHeader::decode(page, e))
+}
+
+/// Page sizes Berkeley DB can create: a power of two from 512 bytes to 64 KiB.
+pub fn is_valid_page_size(ps: usize) -> bool {
+    (DB_MIN_PGSIZE..=DB_MAX_PGSIZE).contains(&ps) && ps.is_power_of_two()
+}
+
+/// Why [`page_slice`] could not return a page.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PageSliceError {
+    /// The page size is not one Berkeley DB can produce.
+    BadPageSize { page_size: usize },
+    /// The page starts at or past the end of the image.
+    OutOfRange { pgno: u32, page_count: usize },
+    /// The image ends part-way through the page.
+    Truncated {
+        pgno: u32,
+        available: usize,
+        page_size: usize,
+    },
 }
 
-pub fn parse_page_header(page: &[u8], e: Endian) -> anyhow::Result<PageHeader> {
-    use anyhow::bail;
-    if page.len() < 26 {
-        bail!("short page");
+impl fmt::Display for PageSliceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PageSliceError::BadPageSize { page_size } => {
+                write!(f, "unsupported page size {page_size}")
+            }
+            PageSliceError::OutOfRange { pgno, page_count } => {
+                write!(f, "page {pgno} out of range (image has {page_count} pages)")
+            }
+            PageSliceError::Truncated {
+                pgno,
+                available,
+                page_size,
+            } => write!(
+                f,
+                "page {pgno} truncated ({available} of {page_size} bytes present)"
+            ),
+        }
     }
-    Ok(PageHeader {
-        lsn_file: u32e(e, &page[0..4]),
-        lsn_off: u32e(e, &page[4..8]),
-        pgno: u32e(e, &page[8..12]),
-        prev: u32e(e, &page[12..16]),
-        next: u32e(e, &page[16..20]),
-        entries: u16e(e, &page[20..22]),
-        hf_offset: u16e(e, &page[22..24]),
-        level: page[24],
-        ptype: PageType::from(page[25]),
-    })
 }
 
-pub fn page_slice<'a>(all: &'a [u8], ps: usize, pgno: u32) -> &'a [u8] {
-    let i = pgno as usize;
-    &all[i * ps..(i + 1) * ps]
+impl core::error::Error for PageSliceError {}
+
+pub fn page_slice(all: &[u8], ps: usize, pgno: u32) -> Result<&[u8], PageSliceError> {
+    if !is_valid_page_size(ps) {
+        return Err(PageSliceError::BadPageSize { page_size: ps });
+    }
+    let start = (pgno as usize).saturating_mul(ps);
+    if start >= all.len() {
+        return Err(PageSliceError::OutOfRange {
+            pgno,
+            page_count: all.len().div_ceil(ps),
+        });
+    }
+    all.get(start..start + ps).ok_or(PageSliceError::Truncated {
+        pgno,
+        available: all.len() - start,
+        page_size: ps,
+    })
 }
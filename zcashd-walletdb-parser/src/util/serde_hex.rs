@@ -0,0 +1,59 @@
+//! `serialize_with` helpers for the `serde` feature: bytes as lowercase hex strings, and
+//! maps with non-string keys in shapes that formats such as JSON accept.
+
+use alloc::collections::BTreeMap;
+
+use serde::{Serialize, Serializer};
+
+/// Bytes as a hex string.
+pub fn bytes<T: AsRef<[u8]>, S: Serializer>(bytes: &T, s: S) -> Result<S::Ok, S::Error> {
+    s.serialize_str(&hex::encode(bytes))
+}
+
+/// Optional bytes as a hex string, or none.
+pub fn opt_bytes<T: AsRef<[u8]>, S: Serializer>(
+    bytes: &Option<T>,
+    s: S,
+) -> Result<S::Ok, S::Error> {
+    match bytes {
+        Some(bytes) => s.serialize_some(&hex::encode(bytes)),
+        None => s.serialize_none(),
+    }
+}
+
+/// A list of byte strings as a list of hex strings.
+pub fn byte_list<T: AsRef<[u8]>, S: Serializer>(list: &[T], s: S) -> Result<S::Ok, S::Error> {
+    s.collect_seq(list.iter().map(hex::encode))
+}
+
+/// A list of optional byte strings as a list of hex strings and nones.
+pub fn opt_byte_list<T: AsRef<[u8]>, S: Serializer>(
+    list: &[Option<T>],
+    s: S,
+) -> Result<S::Ok, S::Error> {
+    s.collect_seq(list.iter().map(|bytes| bytes.as_ref().map(hex::encode)))
+}
+
+/// A map keyed by bytes, with hex string keys.
+pub fn byte_keys<K: AsRef<[u8]>, V: Serialize, S: Serializer>(
+    map: &BTreeMap<K, V>,
+    s: S,
+) -> Result<S::Ok, S::Error> {
+    s.collect_map(map.iter().map(|(key, value)| (hex::encode(key), value)))
+}
+
+/// A map whose values repeat their key, as the list of its values.
+pub fn values<K, V: Serialize, S: Serializer>(
+    map: &BTreeMap<K, V>,
+    s: S,
+) -> Result<S::Ok, S::Error> {
+    s.collect_seq(map.values())
+}
+
+/// A map with keys that are not strings, as a list of `[key, value]` pairs.
+pub fn entries<K: Serialize, V: Serialize, S: Serializer>(
+    map: &BTreeMap<K, V>,
+    s: S,
+) -> Result<S::Ok, S::Error> {
+    s.collect_seq(map.iter())
+}
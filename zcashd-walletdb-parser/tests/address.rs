@@ -0,0 +1,221 @@
+//! Transparent address derivation and the Base58Check encoding.
+
+use pretty_assertions::assert_eq;
+use zcashd_walletdb_parser::{
+    crypto::{Digest, blake2b::blake2b, ripemd160::Ripemd160},
+    parser::{
+        address::{
+            TransparentAddress, base58,
+            bech32::{self, Variant},
+            unified::{UnifiedEncoding, UnifiedItem, UnifiedKind, f4jumble, f4jumble_inv},
+        },
+        decoders::{
+            network::ZcashNetwork,
+            unified::{ReceiverType, UnifiedFullViewingKey},
+        },
+    },
+    storage::walletdb::WalletDb,
+};
+
+const WALLETS: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../dat_files");
+
+#[test]
+fn ripemd160_matches_the_reference_vectors() {
+    for (input, digest) in [
+        ("", "9c1185a5c5e9fc54612808977ee8f548b2258d31"),
+        ("abc", "8eb208f7e05d987a9b044a8e98c6b087f15a0bfc"),
+        ("message digest", "5d0689ef49d2fae572b881b123a85ffa21595f36"),
+        (
+            "12345678901234567890123456789012345678901234567890123456789012345678901234567890",
+            "9b752e45573d4b39f4dbd3323cab82bf63326bfb",
+        ),
+    ] {
+        assert_eq!(hex::encode(Ripemd160::digest(input.as_bytes())), digest);
+    }
+}
+
+#[test]
+fn base58_keeps_leading_zeros_and_checks_checksums() {
+    assert_eq!(base58::encode(b""), "");
+    assert_eq!(base58::encode(&[0, 0, 0x28, 0x7f, 0xb4, 0xcd]), "11233QC4");
+    assert_eq!(
+        base58::decode("11233QC4").unwrap(),
+        [0, 0, 0x28, 0x7f, 0xb4, 0xcd]
+    );
+    assert_eq!(base58::decode("0OIl"), None);
+
+    let encoded = base58::encode_check(b"zcash");
+    assert_eq!(base58::decode_check(&encoded).unwrap(), b"zcash");
+    let mut damaged = encoded.into_bytes();
+    damaged[2] = if damaged[2] == b'2' { b'3' } else { b'2' };
+    assert_eq!(
+        base58::decode_check(std::str::from_utf8(&damaged).unwrap()),
+        None
+    );
+}
+
+#[test]
+fn addresses_round_trip_with_their_network_prefixes() {
+    let pubkey =
+        hex::decode("0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798").unwrap();
+    let address = TransparentAddress::from_pubkey(&pubkey);
+    assert_eq!(
+        hex::encode(address.hash()),
+        "751e76e8199196d454941c45d1b3a323f1433bd6"
+    );
+    let script_address = TransparentAddress::from_redeem_script(&[0x51]);
+    for (network, p2pkh, p2sh) in [
+        (ZcashNetwork::Main, "t1", "t3"),
+        (ZcashNetwork::Test, "tm", "t2"),
+        (ZcashNetwork::Regtest, "tm", "t2"),
+    ] {
+        for (address, prefix) in [(address, p2pkh), (script_address, p2sh)] {
+            let encoded = address.encode(network);
+            assert!(encoded.starts_with(prefix), "{encoded}");
+            let (decoded, networks) = TransparentAddress::decode(&encoded).unwrap();
+            assert_eq!(decoded, address);
+            assert!(networks.contains(&network));
+            assert_eq!(networks, ZcashNetwork::of_address(&encoded));
+        }
+    }
+
+    // `<pubkey> OP_CHECKSIG` pays the key's P2PKH address.
+    let p2pk = [&[33][..], &pubkey, &[0xac]].concat();
+    assert_eq!(TransparentAddress::of_script(&p2pk), Some(address));
+    assert_eq!(TransparentAddress::of_script(&[0x6a]), None);
+}
+
+#[test]
+fn fixture_address_book_addresses_derive_from_wallet_keys() {
+    for n in 0..8 {
+        let wallet = WalletDb::open(format!("{WALLETS}/wallet{n}.dat"))
+            .unwrap()
+            .decode()
+            .unwrap();
+        let network = wallet.network().unwrap();
+        let derived: Vec<String> = wallet
+            .keys
+            .transparent_addresses()
+            .keys()
+            .map(|address| address.encode(network))
+            .collect();
+        assert_eq!(derived.len(), wallet.keys.transparent_len());
+        for address in wallet.address_book.entries.keys() {
+            assert!(derived.contains(address), "wallet{n}: {address}");
+        }
+    }
+}
+
+#[test]
+fn blake2b_matches_the_reference_vectors() {
+    assert_eq!(
+        hex::encode(blake2b(64, b"", b"")),
+        "786a02f742015903c6c6fd852552d272912f4740e15847618a86e217f71f5419\
+         d25e1031afee585313896444934eb04b903a685b1448b755d56f701afe9be2ce"
+    );
+    assert_eq!(
+        hex::encode(blake2b(64, b"", b"abc")),
+        "ba80a53f981c4d0d6a2797b69f12f6e94c212f14685ac4b74b12bb6fdbffa2d1\
+         7d87c5392aab792dc252d5de4533cc9518d38aa8dbf1925ab92386edd4009923"
+    );
+    // Personalization and output length both change the digest.
+    assert_ne!(blake2b(32, b"Zcash", b"abc"), blake2b(32, b"", b"abc"));
+    assert_ne!(blake2b(32, b"", b"abc"), blake2b(64, b"", b"abc")[..32]);
+}
+
+#[test]
+fn bech32_and_bech32m_checksums_are_told_apart() {
+    for (encoded, variant) in [
+        ("A12UEL5L", Variant::Bech32),
+        (
+            "abcdef1qpzry9x8gf2tvdw0s3jn54khce6mua7lmqqqxw",
+            Variant::Bech32,
+        ),
+        ("A1LQFN3A", Variant::Bech32m),
+        (
+            "abcdef1l7aum6echk45nj3s0wdvt2fg8x9yrzpqzd3ryx",
+            Variant::Bech32m,
+        ),
+    ] {
+        let (hrp, data, found) = bech32::decode(encoded).unwrap();
+        assert_eq!(found, variant, "{encoded}");
+        assert_eq!(bech32::encode(&hrp, &data, variant), encoded.to_lowercase());
+    }
+    assert_eq!(bech32::decode("a12uel5m"), None);
+    assert_eq!(bech32::decode("A12uEL5L"), None);
+    assert_eq!(bech32::convert_bits(&[0xff], 8, 5, true).unwrap(), [31, 28]);
+    assert_eq!(
+        bech32::convert_bits(&[31, 28], 5, 8, false).unwrap(),
+        [0xff]
+    );
+    assert_eq!(bech32::convert_bits(&[31, 29], 5, 8, false), None);
+}
+
+#[test]
+fn f4jumble_is_a_permutation_of_every_byte() {
+    for len in [48, 63, 128, 129, 300] {
+        let message: Vec<u8> = (0..len).map(|i| i as u8).collect();
+        let jumbled = f4jumble(&message).unwrap();
+        assert_eq!(f4jumble_inv(&jumbled).unwrap(), message);
+        let mut flipped = message.clone();
+        flipped[len - 1] ^= 1;
+        let other = f4jumble(&flipped).unwrap();
+        assert!(jumbled.iter().zip(&other).filter(|(a, b)| a != b).count() > len / 2);
+    }
+    assert_eq!(f4jumble(&[0; 47]), None);
+}
+
+#[test]
+fn unified_encodings_round_trip() {
+    let receivers = vec![
+        UnifiedItem {
+            typecode: 3,
+            data: vec![0x33; 43],
+        },
+        UnifiedItem {
+            typecode: 0,
+            data: vec![0x11; 20],
+        },
+    ];
+    let address = UnifiedEncoding::address(ZcashNetwork::Regtest, receivers);
+    let encoded = address.encode();
+    assert!(encoded.starts_with("uregtest1"), "{encoded}");
+    let decoded = UnifiedEncoding::decode(&encoded).unwrap();
+    assert_eq!(decoded, address);
+    assert_eq!(decoded.items[0].receiver_type(), ReceiverType::P2pkh);
+    assert!(decoded.item(ReceiverType::Sapling).is_none());
+
+    // A wrong item length, or a string that is not Bech32m, is refused.
+    let short = UnifiedEncoding::address(
+        ZcashNetwork::Main,
+        vec![UnifiedItem {
+            typecode: 2,
+            data: vec![0; 42],
+        }],
+    );
+    let err = UnifiedEncoding::decode(&short.encode()).unwrap_err();
+    assert!(err.message.contains("expected 43"), "{err}");
+    assert!(UnifiedEncoding::decode("A1LQFN3A").is_err());
+
+    let ufvk = UnifiedEncoding {
+        kind: UnifiedKind::FullViewingKey,
+        network: ZcashNetwork::Main,
+        items: vec![UnifiedItem {
+            typecode: 3,
+            data: (0..96).collect(),
+        }],
+    };
+    let stored = UnifiedFullViewingKey {
+        ufvk_id: [0; 32],
+        encoding: ufvk.encode(),
+    };
+    assert!(stored.encoding.starts_with("uview1"));
+    assert_eq!(stored.parse().unwrap(), ufvk);
+    let not_a_key = UnifiedFullViewingKey {
+        encoding: address.encode(),
+        ..stored
+    };
+    // The caller names the key, so the error does not.
+    let err = not_a_key.parse().unwrap_err().to_string();
+    assert_eq!(err, "encoding is a Address, not a full viewing key");
+}
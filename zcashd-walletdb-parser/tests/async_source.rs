@@ -0,0 +1,185 @@
+//! The `async` feature: the entries stream reads what the synchronous walk does, whether
+//! the source answers at once or makes the caller wait.
+#![cfg(feature = "async")]
+
+use std::{
+    future::Future,
+    io,
+    pin::{Pin, pin},
+    sync::Arc,
+    task::{Context, Poll, Waker},
+};
+
+use pretty_assertions::assert_eq;
+use zcashd_walletdb_parser::{
+    constants::B_BLOB,
+    storage::{
+        async_source::{AsyncPageSource, AsyncWalletDb, Blocking},
+        fixture::FixtureBuilder,
+        source::MemoryPageSource,
+        types::{ByteVec, PageNumber, PageSource},
+        walletdb::WalletDb,
+    },
+};
+
+type Read = Vec<(Vec<u8>, Vec<u8>, u32, u16)>;
+
+/// Poll `future` to completion on this thread; the sources here never need waking.
+fn block_on<F: Future>(future: F) -> F::Output {
+    let mut future = pin!(future);
+    let mut cx = Context::from_waker(Waker::noop());
+    loop {
+        if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+            return output;
+        }
+    }
+}
+
+/// Returns `Pending` once before finishing, as a read waiting on I/O would.
+struct YieldOnce(bool);
+
+impl Future for YieldOnce {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<()> {
+        if std::mem::replace(&mut self.0, true) {
+            Poll::Ready(())
+        } else {
+            Poll::Pending
+        }
+    }
+}
+
+/// A source whose every read makes the caller wait once.
+struct Slow(MemoryPageSource);
+
+impl AsyncPageSource for Slow {
+    async fn read_page(&self, page_no: PageNumber) -> io::Result<ByteVec> {
+        YieldOnce(false).await;
+        self.0.read_page(page_no)
+    }
+
+    fn page_count(&self) -> Option<u64> {
+        self.0.page_count()
+    }
+
+    fn source_id(&self) -> String {
+        self.0.source_id()
+    }
+}
+
+fn image() -> Vec<u8> {
+    (0u32..120)
+        .fold(FixtureBuilder::new().page_size(512), |b, i| {
+            let len = if i % 7 == 0 { 1_500 } else { 25 };
+            b.wallet_record("tx", &i.to_be_bytes(), vec![i as u8; len])
+        })
+        .build()
+        .unwrap()
+}
+
+fn memory(image: &[u8]) -> MemoryPageSource {
+    MemoryPageSource::new(image.to_vec(), 512, "async").unwrap()
+}
+
+fn streamed<S: AsyncPageSource>(source: S) -> Read {
+    block_on(async {
+        let db = AsyncWalletDb::open(source).await.unwrap();
+        let mut entries = db.entries().await.unwrap();
+        let mut out = Vec::new();
+        while let Some(entry) = entries.next().await {
+            let (key, value, p) = entry.unwrap();
+            out.push((key, value, p.page_no, p.slot_index));
+        }
+        out
+    })
+}
+
+/// Read the entries through `EntriesStream::poll_next`, as a `Stream` impl would.
+fn polled<S: AsyncPageSource>(source: S) -> Read {
+    let db = block_on(AsyncWalletDb::open(source)).unwrap();
+    let mut stream = pin!(block_on(db.entries()).unwrap().into_stream());
+    let mut cx = Context::from_waker(Waker::noop());
+    let mut out = Vec::new();
+    loop {
+        match stream.as_mut().poll_next(&mut cx) {
+            Poll::Ready(Some(entry)) => {
+                let (key, value, p) = entry.unwrap();
+                out.push((key, value, p.page_no, p.slot_index));
+            }
+            Poll::Ready(None) => return out,
+            Poll::Pending => {}
+        }
+    }
+}
+
+#[test]
+fn the_entries_stream_matches_the_synchronous_walk() {
+    let image = image();
+    let expected: Read = WalletDb::from_source(Arc::new(memory(&image)))
+        .unwrap()
+        .entries()
+        .map(|entry| {
+            let (key, value, p) = entry.unwrap();
+            (key, value, p.page_no, p.slot_index)
+        })
+        .collect();
+    assert_eq!(expected.len(), 120);
+    assert_eq!(streamed(Blocking(memory(&image))), expected);
+    assert_eq!(streamed(Slow(memory(&image))), expected);
+    assert_eq!(polled(Slow(memory(&image))), expected);
+}
+
+#[test]
+fn a_missing_page_is_yielded_as_an_error() {
+    let image = image();
+    let truncated = &image[..image.len() - 512];
+    let errors = block_on(async {
+        let db = AsyncWalletDb::open(Blocking(memory(truncated)))
+            .await
+            .unwrap();
+        let mut entries = db.entries().await.unwrap();
+        let mut errors = 0;
+        while let Some(entry) = entries.next().await {
+            errors += entry.is_err() as usize;
+        }
+        errors
+    });
+    assert!(errors > 0);
+}
+
+#[test]
+fn a_value_in_a_blob_file_is_an_error_not_its_descriptor() {
+    // A 45-byte value makes a 48-byte item, the size of a `BBLOB` item.
+    let marker = [0xbb; 45];
+    let mut image = FixtureBuilder::new()
+        .page_size(512)
+        .wallet_record("name", b"t1", marker.to_vec())
+        .wallet_record("tx", &[1], vec![1; 20])
+        .build()
+        .unwrap();
+    let at = image.windows(45).position(|w| w == marker).unwrap() - 3;
+    let item = &mut image[at..at + 48];
+    item.fill(0);
+    item[2] = B_BLOB;
+    for (field, n) in [(16, 7u64), (24, 100), (32, 1), (40, 2)] {
+        item[field..field + 8].copy_from_slice(&n.to_le_bytes());
+    }
+
+    let read = block_on(async {
+        let db = AsyncWalletDb::open(Blocking(memory(&image))).await.unwrap();
+        let mut entries = db.entries().await.unwrap();
+        let mut read = Vec::new();
+        while let Some(entry) = entries.next().await {
+            read.push(entry.map(|(key, value, _)| (key, value)));
+        }
+        read
+    });
+    let [tx, name] = &read[..] else {
+        panic!("two entries, got {read:?}");
+    };
+    assert_eq!(tx.as_ref().unwrap().1, vec![1; 20]);
+    let e = name.as_ref().unwrap_err();
+    assert_eq!(e.kind(), io::ErrorKind::Unsupported);
+    assert!(e.to_string().contains("value is stored outside the image"));
+}
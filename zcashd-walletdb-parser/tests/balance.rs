@@ -0,0 +1,100 @@
+//! [`Balances`] against the shipped fixtures, whose regtest chain was mined to height 200
+//! while the wallets last recorded block 176 as their best.
+
+use pretty_assertions::assert_eq;
+use zcashd_walletdb_parser::{
+    parser::{
+        balance::{Balances, TransparentBalance},
+        decoders::locator::BlockLocator,
+        record::{DecodeError, RecordKind},
+        wallet::RecordError,
+    },
+    storage::walletdb::WalletDb,
+};
+
+const WALLETS: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../dat_files");
+const ZEC: i64 = 100_000_000;
+
+fn balances(n: u32) -> Balances {
+    let wallet = WalletDb::open(format!("{WALLETS}/wallet{n}.dat"))
+        .unwrap()
+        .decode()
+        .unwrap();
+    Balances::of(&wallet)
+}
+
+#[test]
+fn coinbase_outputs_split_by_maturity_and_best_block() {
+    // wallet3 mined blocks 76 to 100 and 176 to 200. Block 76 is 101 deep, the rest up
+    // to 176 are immature, and those after the best block are not confirmed yet.
+    // Regtest halves the 10 ZEC miner reward at block 150.
+    let wallet3 = balances(3);
+    assert_eq!(wallet3.best_height, Some(176));
+    let expected = TransparentBalance {
+        confirmed: 10 * ZEC,
+        unconfirmed: 24 * 625 * ZEC / 100,
+        immature: 24 * 10 * ZEC + 625 * ZEC / 100,
+        watch_only: 0,
+        utxos: 50,
+    };
+    assert_eq!(wallet3.transparent, expected);
+
+    // wallet0 mined blocks 1 to 25 and 101 to 125.
+    let wallet0 = balances(0);
+    assert_eq!(wallet0.transparent.confirmed, 250 * ZEC);
+    assert_eq!(wallet0.transparent.immature, 250 * ZEC);
+    assert_eq!(wallet0.requires_rescan().count(), 0);
+    assert!(wallet0.sapling.is_empty() && wallet0.orchard.is_empty());
+
+    let shown = wallet3.to_string();
+    assert!(
+        shown.contains("10.00000000 ZEC confirmed, 150.00000000 unconfirmed"),
+        "{shown}"
+    );
+}
+
+#[test]
+fn locator_depths_step_back_then_double() {
+    let hashes: Vec<[u8; 32]> = (0..16).map(|i| [i; 32]).collect();
+    let locator = BlockLocator {
+        version: 6_000_050,
+        hashes: hashes.clone(),
+    };
+    let depths: Vec<_> = hashes.iter().map(|h| locator.depth_of(h)).collect();
+    let mut expected: Vec<_> = (0..12).map(Some).collect();
+    expected.extend([Some(13), Some(17), Some(25), None]);
+    assert_eq!(depths, expected);
+    assert_eq!(locator.depth_of(&[0xff; 32]), None);
+}
+
+#[test]
+fn undecodable_tx_records_are_counted_apart_from_the_totals() {
+    let mut wallet = WalletDb::open(format!("{WALLETS}/wallet0.dat"))
+        .unwrap()
+        .decode()
+        .unwrap();
+    let whole = Balances::of(&wallet);
+    assert_eq!(whole.undecoded_transactions, 0);
+    assert!(!whole.to_string().contains("undecoded"));
+
+    // Lose a mature coinbase as if its record had failed to decode.
+    let (&txid, _) = wallet
+        .transactions
+        .iter()
+        .find(|(_, wtx)| wtx.tx().vout.iter().any(|out| out.value == 10 * ZEC))
+        .unwrap();
+    wallet.transactions.remove(&txid);
+    wallet.errors.push(RecordError {
+        kind: RecordKind::Tx,
+        key: None,
+        error: DecodeError::new("truncated"),
+    });
+    let partial = Balances::of(&wallet);
+    assert_eq!(partial.undecoded_transactions, 1);
+    assert!(partial.transparent.confirmed + partial.transparent.immature < 500 * ZEC);
+    assert!(
+        partial
+            .to_string()
+            .contains("  undecoded    : 1 tx records, left out of the totals above\n")
+    );
+}
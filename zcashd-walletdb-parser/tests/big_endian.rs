@@ -0,0 +1,122 @@
+//! Byte-swapped copies of the little-endian fixtures must decode to the same records.
+
+use std::{fs, sync::Arc};
+
+use pretty_assertions::assert_eq;
+use zcashd_walletdb_parser::{
+    storage::{
+        byteswap::byteswap_image,
+        consistency::{DbImageReader, SalvageMode},
+        freelist::walk_freelist,
+        reader::FileDbImageReader,
+        source::MemoryPageSource,
+        types::Endianness,
+    },
+    util::{detect_endian, u32e},
+};
+
+const FIXTURES: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../dat_files");
+
+fn fixtures() -> Vec<(String, Vec<u8>)> {
+    let mut out: Vec<_> = fs::read_dir(FIXTURES)
+        .unwrap()
+        .map(|e| e.unwrap().path())
+        .filter(|p| p.extension().is_some_and(|x| x == "dat"))
+        .map(|p| (p.display().to_string(), fs::read(&p).unwrap()))
+        .collect();
+    out.sort();
+    assert!(!out.is_empty(), "no fixtures in {FIXTURES}");
+    out
+}
+
+fn reader(bytes: Vec<u8>, id: &str) -> FileDbImageReader {
+    let page_size = u32e(detect_endian(&bytes).unwrap(), &bytes[20..24]);
+    let source = MemoryPageSource::new(bytes, page_size, id).unwrap();
+    FileDbImageReader::from_source(Arc::new(source)).unwrap()
+}
+
+type Record = (Vec<u8>, Vec<u8>, u32, u16);
+
+fn records(reader: &FileDbImageReader, salvage: SalvageMode) -> Vec<Record> {
+    reader
+        .entries(salvage)
+        .map(|(k, v, p)| (k, v.materialize().unwrap(), p.page_no, p.slot_index))
+        .collect()
+}
+
+fn swapped(bytes: &[u8]) -> Vec<u8> {
+    let mut be = bytes.to_vec();
+    assert_eq!(byteswap_image(&mut be).unwrap(), Endianness::Big);
+    be
+}
+
+#[test]
+fn swapping_twice_restores_the_image() {
+    for (name, le) in fixtures() {
+        let mut image = swapped(&le);
+        assert_ne!(image, le, "{name}");
+        assert_eq!(byteswap_image(&mut image).unwrap(), Endianness::Little);
+        assert!(image == le, "{name}: round trip changed the image");
+    }
+}
+
+#[test]
+fn big_endian_meta_matches() {
+    for (name, le) in fixtures() {
+        let le_reader = reader(le.clone(), &name);
+        let be_reader = reader(swapped(&le), &name);
+        let (a, b) = (le_reader.probe().unwrap(), be_reader.probe().unwrap());
+        assert_eq!(a.endianness, Endianness::Little);
+        assert_eq!(b.endianness, Endianness::Big);
+        assert_eq!(a.page_size, b.page_size, "{name}");
+        assert_eq!(a.btree_root, b.btree_root, "{name}");
+        assert_eq!(le_reader.meta().last_pgno, be_reader.meta().last_pgno);
+        assert_eq!(le_reader.meta().free, be_reader.meta().free);
+        assert_eq!(le_reader.meta().uid, be_reader.meta().uid);
+    }
+}
+
+#[test]
+fn big_endian_tree_walk_matches() {
+    for (name, le) in fixtures() {
+        let le_reader = reader(le.clone(), &name);
+        let be_reader = reader(swapped(&le), &name);
+        let subs = |r: &FileDbImageReader| {
+            r.subdatabases()
+                .unwrap()
+                .into_iter()
+                .map(|s| (s.name, s.meta_pgno, s.root))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(subs(&le_reader), subs(&be_reader), "{name}");
+        let expected = records(&le_reader, SalvageMode::Conservative);
+        assert!(!expected.is_empty(), "{name}");
+        assert_eq!(expected, records(&be_reader, SalvageMode::Conservative));
+        assert!(be_reader.diagnostics().is_empty(), "{name}");
+    }
+}
+
+#[test]
+fn big_endian_page_scan_matches() {
+    for (name, le) in fixtures() {
+        let le_reader = reader(le.clone(), &name);
+        let be_reader = reader(swapped(&le), &name);
+        assert_eq!(
+            records(&le_reader, SalvageMode::BestEffort),
+            records(&be_reader, SalvageMode::BestEffort),
+            "{name}"
+        );
+    }
+}
+
+#[test]
+fn big_endian_freelist_matches() {
+    for (name, le) in fixtures() {
+        let le_reader = reader(le.clone(), &name);
+        let be_reader = reader(swapped(&le), &name);
+        let a = walk_freelist(le_reader.source().as_ref()).unwrap();
+        let b = walk_freelist(be_reader.source().as_ref()).unwrap();
+        assert_eq!(a.pages, b.pages, "{name}");
+        assert!(b.issues.is_empty(), "{name}");
+    }
+}
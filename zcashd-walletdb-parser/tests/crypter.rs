@@ -0,0 +1,222 @@
+//! Decrypting an encrypted wallet: the passphrase KDF against known answers, and a wallet
+//! encrypted here the way zcashd encrypts one.
+
+use pretty_assertions::assert_eq;
+use zcashd_walletdb_parser::{
+    crypto::{Digest, aes::Aes, sha256::sha256d, sha512::Sha512},
+    entry::parser::write_compact_size,
+    parser::{
+        crypter::{MasterSecret, UnlockError, bytes_to_key_sha512, decrypt_wallet},
+        decoders::{
+            encryption::{DerivationMethod, MasterKey},
+            sapling::{EncryptedSaplingKey, SaplingExtendedFullViewingKey, Zip32Header},
+            seed::{EncryptedHdSeed, EncryptedMnemonicPhrase, seed_fingerprint},
+            sprout::{EncryptedSproutKey, SproutPaymentAddress, SproutSpendingKey},
+            transparent::EncryptedKey,
+        },
+        mnemonic::phrase_fingerprint,
+        record::RecordKind,
+        secret::SecretBytes,
+        wallet::ZcashdWallet,
+    },
+    storage::walletdb::WalletDb,
+};
+
+const WALLETS: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../dat_files");
+const PASSPHRASE: &[u8] = b"correct horse battery staple";
+const MASTER_KEY: [u8; 32] = [0x5a; 32];
+
+#[test]
+fn sha512_matches_fips_180() {
+    assert_eq!(
+        hex::encode(Sha512::digest(b"abc")),
+        "ddaf35a193617abacc417349ae20413112e6fa4e89a97ea20a9eeee64b55d39a\
+         2192992a274fc1a836ba3c23a3feebbd454d4423643ce80e2a9ac94fa54ca49f"
+    );
+    // 112 bytes, so the padding spills into a second block.
+    assert_eq!(
+        hex::encode(Sha512::digest(
+            b"abcdefghbcdefghicdefghijdefghijkefghijklfghijklmghijklmnhijklmno\
+              ijklmnopjklmnopqklmnopqrlmnopqrsmnopqrstnopqrstu"
+        )),
+        "8e959b75dae313da8cf4f72814fc143f8f7779c6eb9f7fa17299aeadb6889018\
+         501d289e4900f7e4331b99dec4b5433ac7d329eeb6dd26545e96e55b874be909"
+    );
+}
+
+#[test]
+fn passphrase_key_matches_bitcoin_vectors() {
+    let (key, iv) = bytes_to_key_sha512(b"test", &hex::decode("0000deadbeef0000").unwrap(), 25000);
+    assert_eq!(
+        hex::encode(key.expose()),
+        "fc7aba077ad5f4c3a0988d8daa4810d0d4a0e3bcb53af662998898f33df0556a"
+    );
+    assert_eq!(hex::encode(iv), "cf2f2691526dd1aa220896fb8bf7c369");
+}
+
+/// An `mkey` wrapping [`MASTER_KEY`] under [`PASSPHRASE`].
+fn master_key() -> MasterKey {
+    let salt = b"saltsalt".to_vec();
+    let (key, iv) = bytes_to_key_sha512(PASSPHRASE, &salt, 1000);
+    let mut encrypted_key = [MASTER_KEY.as_slice(), &[16; 16]].concat();
+    Aes::new(key.expose())
+        .unwrap()
+        .cbc_encrypt(&iv, &mut encrypted_key);
+    MasterKey {
+        id: 1,
+        encrypted_key,
+        salt,
+        derivation_method: DerivationMethod::Sha512,
+        derive_iterations: 1000,
+        other_params: vec![],
+    }
+}
+
+/// A wallet with one encrypted secret of each kind.
+fn encrypted_wallet() -> ZcashdWallet {
+    let mut wallet = ZcashdWallet::default();
+    let mkey = master_key();
+    let master = MasterSecret::decrypt(&mkey, PASSPHRASE).unwrap();
+    wallet.crypto.master_keys.insert(1, mkey);
+
+    let pubkey = [[2].as_slice(), &[7; 32]].concat();
+    let encrypted_secret = master.encrypt_secret(&[9; 32], &sha256d(&pubkey));
+    wallet.keys.encrypted_transparent.insert(
+        pubkey.clone(),
+        EncryptedKey {
+            pubkey,
+            encrypted_secret,
+        },
+    );
+
+    let a_sk = [0x0b; 32];
+    let sprout = SproutSpendingKey {
+        address: SproutPaymentAddress {
+            a_pk: [0; 32],
+            pk_enc: [3; 32],
+        },
+        a_sk: SecretBytes::from(a_sk.as_slice()),
+    };
+    let address = SproutPaymentAddress {
+        a_pk: sprout.derive_a_pk(),
+        ..sprout.address
+    };
+    let iv_hash = sha256d(&[address.a_pk, address.pk_enc].concat());
+    wallet.keys.encrypted_sprout.insert(
+        address,
+        EncryptedSproutKey {
+            address,
+            receiving_key: [4; 32],
+            encrypted_secret: master.encrypt_secret(&a_sk, &iv_hash),
+        },
+    );
+
+    let header = Zip32Header {
+        depth: 3,
+        parent_fvk_tag: [1, 2, 3, 4],
+        child_index: 0x8000_0000,
+        chain_code: [5; 32],
+    };
+    let extfvk = SaplingExtendedFullViewingKey {
+        header,
+        ak: [6; 32],
+        nk: [7; 32],
+        ovk: [8; 32],
+        dk: [9; 32],
+    };
+    let mut extsk = vec![header.depth];
+    extsk.extend(header.parent_fvk_tag);
+    extsk.extend(header.child_index.to_le_bytes());
+    extsk.extend(header.chain_code);
+    extsk.extend([[0xa; 32], [0xb; 32], extfvk.ovk, extfvk.dk].concat());
+    let encrypted_secret = master.encrypt_secret(&extsk, &extfvk.fingerprint());
+    wallet.keys.encrypted_sapling.insert(
+        [0xc; 32],
+        EncryptedSaplingKey {
+            ivk: [0xc; 32],
+            extfvk,
+            encrypted_secret,
+        },
+    );
+
+    let seed = [0xd; 32];
+    let fingerprint = seed_fingerprint(&seed);
+    wallet.crypto.encrypted_hd_seed = Some(EncryptedHdSeed {
+        fingerprint,
+        encrypted_seed: master.encrypt_secret(&seed, &fingerprint),
+    });
+
+    let mut mnemonic = 0u32.to_le_bytes().to_vec();
+    let phrase = "abandon ".repeat(23) + "art";
+    write_compact_size(&mut mnemonic, phrase.len() as u64);
+    mnemonic.extend(phrase.as_bytes());
+    let fingerprint = phrase_fingerprint(&phrase).unwrap();
+    wallet.crypto.encrypted_mnemonic = Some(EncryptedMnemonicPhrase {
+        fingerprint,
+        encrypted_phrase: master.encrypt_secret(&mnemonic, &fingerprint),
+    });
+    wallet
+}
+
+#[test]
+fn encrypted_wallet_decrypts_with_its_passphrase() {
+    let wallet = encrypted_wallet();
+    let secrets = decrypt_wallet(&wallet, PASSPHRASE).unwrap();
+    assert!(secrets.failures.is_empty(), "{:?}", secrets.failures);
+    assert_eq!(secrets.master_key_id, 1);
+
+    let (_, secret) = secrets.transparent.first_key_value().unwrap();
+    assert_eq!(secret.expose(), [9; 32]);
+    let (_, sprout) = secrets.sprout.first_key_value().unwrap();
+    assert_eq!(sprout.a_sk.expose(), [0x0b; 32]);
+    let sapling = &secrets.sapling[&[0xc; 32]];
+    assert_eq!(sapling.key.ask.expose(), [0xa; 32]);
+    assert_eq!(sapling.key.nsk.expose(), [0xb; 32]);
+    assert_eq!(secrets.hd_seed.as_ref().unwrap().seed.expose(), [0xd; 32]);
+    let mnemonic = secrets.mnemonic.as_ref().unwrap();
+    assert_eq!(mnemonic.word_count(), 24);
+    assert!(mnemonic.phrase.expose().ends_with(" art"));
+
+    let shown = secrets.to_string();
+    assert!(shown.contains("sapling      : 1 keys"));
+    assert!(!shown.contains("abandon"));
+}
+
+#[test]
+fn wrong_passphrase_and_damaged_secrets_are_reported() {
+    let mut wallet = encrypted_wallet();
+    assert_eq!(
+        decrypt_wallet(&wallet, b"wrong").unwrap_err(),
+        UnlockError::WrongPassphrase
+    );
+    assert_eq!(
+        decrypt_wallet(&ZcashdWallet::default(), PASSPHRASE).unwrap_err(),
+        UnlockError::NotEncrypted
+    );
+
+    // The Sprout key is filed under another address, whose IV garbles the first block.
+    let (address, mut czkey) = wallet.keys.encrypted_sprout.pop_first().unwrap();
+    czkey.address.pk_enc = [0xf; 32];
+    let moved = SproutPaymentAddress {
+        pk_enc: [0xf; 32],
+        ..address
+    };
+    wallet.keys.encrypted_sprout.insert(moved, czkey);
+    let secrets = decrypt_wallet(&wallet, PASSPHRASE).unwrap();
+    let failed: Vec<_> = secrets.failures.iter().map(|f| f.kind).collect();
+    assert_eq!(failed, [RecordKind::CZKey]);
+    assert!(secrets.sprout.is_empty());
+    assert_eq!(secrets.transparent.len(), 1);
+}
+
+#[test]
+fn fixtures_are_not_encrypted() {
+    let wallet = WalletDb::open(format!("{WALLETS}/wallet0.dat"))
+        .unwrap()
+        .decode()
+        .unwrap();
+    assert_eq!(
+        decrypt_wallet(&wallet, PASSPHRASE).unwrap_err(),
+        UnlockError::NotEncrypted
+    );
+}
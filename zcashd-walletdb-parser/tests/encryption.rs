@@ -0,0 +1,118 @@
+//! DB_ENCRYPT pages: the primitives against their published test vectors, and a page
+//! encrypted the way Berkeley DB does it read back through [`DecryptingPageSource`].
+
+use pretty_assertions::assert_eq;
+use zcashd_walletdb_parser::{
+    constants::{DB_MAC_KEY, PG_CHKSUM_OFF, PG_IV_OFF},
+    crypto::{Digest, aes::Aes, hmac, sha1::Sha1},
+    storage::{
+        encryption::{DbCipher, DecryptingPageSource},
+        page::PageProtection,
+        source::MemoryPageSource,
+        types::PageSource,
+    },
+};
+
+const PAGE_SIZE: usize = 4096;
+
+#[test]
+fn primitives_match_their_test_vectors() {
+    // FIPS 197, appendix C.1.
+    let key: Vec<u8> = (0..16).collect();
+    let aes = Aes::new(&key).unwrap();
+    let mut block: [u8; 16] = hex::decode("00112233445566778899aabbccddeeff")
+        .unwrap()
+        .try_into()
+        .unwrap();
+    aes.encrypt_block(&mut block);
+    assert_eq!(hex::encode(block), "69c4e0d86a7b0430d8cdb78070b4c55a");
+    aes.decrypt_block(&mut block);
+    assert_eq!(hex::encode(block), "00112233445566778899aabbccddeeff");
+    assert!(Aes::new(&[0; 15]).is_none());
+
+    // FIPS 180-4 and RFC 2202, test case 1.
+    assert_eq!(
+        hex::encode(Sha1::digest(b"abc")),
+        "a9993e364706816aba3e25717850c26c9cd0d89d"
+    );
+    assert_eq!(
+        hex::encode(hmac::<Sha1>(&[0x0b; 20], b"Hi There")),
+        "b617318655057264e28bc0b6fb378c8ef146be00"
+    );
+}
+
+/// SHA1(pw || magic || pw), with the passphrase's NUL terminator, as `__crypto_derive_key`
+/// computes it.
+fn derive(passphrase: &[u8], magic: &[u8]) -> Vec<u8> {
+    let mut pw = passphrase.to_vec();
+    pw.push(0);
+    let mut h = Sha1::new();
+    h.update(&pw);
+    h.update(magic);
+    h.update(&pw);
+    h.finalize()
+}
+
+/// A leaf page encrypted from `P_OVERHEAD` onwards, with its IV and HMAC in the
+/// `PG_CRYPTO` block.
+fn encrypted_leaf(passphrase: &[u8], plain: &[u8]) -> Vec<u8> {
+    let mut page = plain.to_vec();
+    let iv = [0x5a; 16];
+    page[PG_IV_OFF..PG_IV_OFF + 16].copy_from_slice(&iv);
+    let key = derive(passphrase, b"encryption and decryption key value magic");
+    Aes::new(&key[..16])
+        .unwrap()
+        .cbc_encrypt(&iv, &mut page[PageProtection::Encrypted.header_size()..]);
+    let mac_key = derive(passphrase, b"mac derivation key magic value");
+    let mac = hmac::<Sha1>(&mac_key, &page);
+    page[PG_CHKSUM_OFF..PG_CHKSUM_OFF + DB_MAC_KEY].copy_from_slice(&mac);
+    page
+}
+
+fn plain_leaf() -> Vec<u8> {
+    let mut page = vec![0u8; PAGE_SIZE];
+    page[8..12].copy_from_slice(&1u32.to_le_bytes());
+    page[24] = 1;
+    page[25] = 5;
+    for (i, b) in page[PageProtection::Encrypted.header_size()..]
+        .iter_mut()
+        .enumerate()
+    {
+        *b = (i * 7) as u8;
+    }
+    page
+}
+
+fn source(page: Vec<u8>, passphrase: &[u8]) -> DecryptingPageSource<MemoryPageSource> {
+    let mut image = vec![0u8; PAGE_SIZE];
+    image.extend(page);
+    let inner = MemoryPageSource::new(image, PAGE_SIZE as u32, "encrypted").unwrap();
+    DecryptingPageSource::new(inner, DbCipher::from_passphrase(passphrase))
+}
+
+#[test]
+fn pages_decrypt_with_the_passphrase_they_were_encrypted_with() {
+    let plain = plain_leaf();
+    let encrypted = encrypted_leaf(b"hunter2", &plain);
+    let overhead = PageProtection::Encrypted.header_size();
+    assert_ne!(encrypted[overhead..], plain[overhead..]);
+    assert!(DbCipher::from_passphrase(b"hunter2").verify_hmac(&encrypted));
+
+    let page = source(encrypted, b"hunter2").read_page(1).unwrap();
+    assert_eq!(page[..PG_CHKSUM_OFF], plain[..PG_CHKSUM_OFF]);
+    assert_eq!(page[overhead..], plain[overhead..]);
+}
+
+#[test]
+fn pages_with_a_wrong_passphrase_or_a_flipped_byte_are_rejected() {
+    let encrypted = encrypted_leaf(b"hunter2", &plain_leaf());
+    assert!(!DbCipher::from_passphrase(b"hunter3").verify_hmac(&encrypted));
+    let err = source(encrypted.clone(), b"hunter3")
+        .read_page(1)
+        .unwrap_err();
+    assert!(err.to_string().contains("HMAC mismatch"), "{err}");
+
+    let mut damaged = encrypted;
+    damaged[PAGE_SIZE - 1] ^= 1;
+    assert!(source(damaged, b"hunter2").read_page(1).is_err());
+}
@@ -0,0 +1,423 @@
+//! Exported secrets: encodings against reference vectors, and the fixtures' keys
+//! exported and read back.
+
+use pretty_assertions::assert_eq;
+use zcashd_walletdb_parser::{
+    crypto::secp256k1::public_key,
+    entry::parser::walletdb_key_prefix,
+    parser::{
+        address::TransparentAddress,
+        decoders::{
+            address_book::AddressName,
+            keymeta::KeyMetadata,
+            network::ZcashNetwork,
+            sapling::{
+                SaplingExtendedSpendingKey, SaplingPaymentAddress, SaplingSpendingKey, Zip32Header,
+            },
+            sprout::{SproutPaymentAddress, SproutSpendingKey},
+            unified::{
+                ReceiverType, UnifiedAccount, UnifiedAddressMetadata, UnifiedFullViewingKey,
+            },
+        },
+        export::{
+            dump::write_wallet_dump,
+            extsk, transparent_keys, wif,
+            zewif::{ProtocolAddress, SeedMaterial, Sourced, Zewif},
+        },
+        record::RecordKind,
+        registry::default_registry,
+        secret::SecretBytes,
+        wallet::ZcashdWallet,
+    },
+    storage::walletdb::WalletDb,
+};
+
+const WALLETS: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../dat_files");
+
+#[test]
+fn wif_matches_the_reference_vectors() {
+    let mut one = [0; 32];
+    one[31] = 1;
+    let encoded = |compressed, network| wif::encode(&one, compressed, network);
+    assert_eq!(
+        encoded(true, ZcashNetwork::Main).expose(),
+        "KwDiBf89QgGbjEhKnhXJuH7LrciVrZi3qYjgd9M7rFU73sVHnoWn"
+    );
+    assert_eq!(
+        encoded(false, ZcashNetwork::Main).expose(),
+        "5HpHagT65TZzG1PH3CSu63k8DbpvD8s5ip4nEB3kEsreAnchuDf"
+    );
+    assert_eq!(
+        encoded(true, ZcashNetwork::Regtest).expose(),
+        encoded(true, ZcashNetwork::Test).expose()
+    );
+
+    let decoded = wif::decode(encoded(false, ZcashNetwork::Test).expose()).unwrap();
+    assert_eq!(decoded.secret.expose(), one);
+    assert!(!decoded.compressed);
+    assert_eq!(
+        decoded.networks,
+        [ZcashNetwork::Test, ZcashNetwork::Regtest]
+    );
+    // A t-address is Base58Check too, with another prefix.
+    assert_eq!(wif::decode("tmRB99mRSC98YDJUXezZKiU6Q4xpzSwcJ8d"), None);
+}
+
+#[test]
+fn fixture_keys_export_to_wif_that_derives_their_addresses() {
+    let wallet = WalletDb::open(format!("{WALLETS}/wallet0.dat"))
+        .unwrap()
+        .decode()
+        .unwrap();
+    let exported = transparent_keys(&wallet, None, ZcashNetwork::Regtest);
+    assert_eq!(exported.len(), 52);
+    for key in &exported {
+        assert_eq!(key.kind, RecordKind::Key);
+        assert!(key.wif.expose().starts_with('c'));
+        let decoded = wif::decode(key.wif.expose()).unwrap();
+        assert!(decoded.networks.contains(&ZcashNetwork::Regtest));
+        assert_eq!(
+            public_key(decoded.secret.expose(), decoded.compressed).as_ref(),
+            Some(&key.pubkey)
+        );
+    }
+}
+
+fn sapling_key() -> SaplingExtendedSpendingKey {
+    SaplingExtendedSpendingKey {
+        header: Zip32Header {
+            depth: 3,
+            parent_fvk_tag: [1, 2, 3, 4],
+            child_index: 0x8000_0002,
+            chain_code: [5; 32],
+        },
+        ask: SecretBytes::new(vec![6; 32]),
+        nsk: SecretBytes::new(vec![7; 32]),
+        ovk: [8; 32],
+        dk: [9; 32],
+    }
+}
+
+#[test]
+fn sapling_extended_spending_keys_round_trip_through_bech32() {
+    let key = sapling_key();
+    let bytes = key.to_bytes();
+    assert_eq!(bytes.len(), 169);
+    assert_eq!(&bytes.expose()[..9], [3, 1, 2, 3, 4, 2, 0, 0, 0x80]);
+
+    for (network, prefix) in [
+        (ZcashNetwork::Main, "secret-extended-key-main1"),
+        (ZcashNetwork::Test, "secret-extended-key-test1"),
+        (ZcashNetwork::Regtest, "secret-extended-key-regtest1"),
+    ] {
+        let encoded = extsk::encode(&key, network);
+        assert!(encoded.expose().starts_with(prefix));
+        assert_eq!(
+            extsk::decode(encoded.expose()),
+            Some((key.clone(), network))
+        );
+    }
+    let encoded = extsk::encode(&key, ZcashNetwork::Main);
+    let mut damaged = encoded.expose().to_string();
+    damaged.pop();
+    damaged.push('q');
+    assert_eq!(extsk::decode(&damaged), None);
+}
+
+#[test]
+fn dumps_read_like_z_exportwallet() {
+    let mut wallet = WalletDb::open(format!("{WALLETS}/wallet0.dat"))
+        .unwrap()
+        .decode()
+        .unwrap();
+    let keys = &mut wallet.keys;
+    let address = SproutPaymentAddress {
+        a_pk: [1; 32],
+        pk_enc: [2; 32],
+    };
+    let sprout = SproutSpendingKey {
+        address,
+        a_sk: SecretBytes::new(vec![3; 32]),
+    };
+    keys.sprout.insert(address, sprout);
+    let sapling = SaplingPaymentAddress {
+        diversifier: [4; 11],
+        pk_d: [5; 32],
+    };
+    keys.sapling_addresses.insert(sapling, [6; 32]);
+    keys.sapling.insert(
+        [6; 32],
+        SaplingSpendingKey {
+            ivk: [6; 32],
+            key: sapling_key(),
+        },
+    );
+    keys.sapling_metadata.insert(
+        [6; 32],
+        KeyMetadata {
+            version: 1,
+            create_time: 951_782_400,
+            hd_keypath: Some("m/32'/1'/2'".to_string()),
+            seed_fingerprint: Some([7; 32]),
+        },
+    );
+    let first = transparent_keys(&wallet, None, ZcashNetwork::Regtest)[0].address;
+    wallet.address_book.insert_name(AddressName {
+        address: first.encode(ZcashNetwork::Regtest),
+        label: "savings 100%".to_string(),
+    });
+
+    let mut out = Vec::new();
+    write_wallet_dump(&mut out, &wallet, None, ZcashNetwork::Regtest, 0).unwrap();
+    let dump = String::from_utf8(out).unwrap();
+    let lines: Vec<&str> = dump.lines().collect();
+    assert!(lines[0].starts_with("# Wallet dump created by "));
+    assert_eq!(lines[1], "# * Created on 1970-01-01T00:00:00Z");
+    assert!(lines[2].starts_with("# * Best block at time of backup was 176 ("));
+    assert!(lines[2].ends_with(')'));
+    assert!(lines.contains(&"# - language=English"));
+    assert_eq!(lines.last(), Some(&"# End of dump"));
+
+    let key_lines: Vec<&str> = lines
+        .iter()
+        .copied()
+        .filter(|l| l.starts_with('c'))
+        .collect();
+    assert_eq!(key_lines.len(), 52);
+    assert!(
+        key_lines
+            .iter()
+            .all(|l| l.contains(" # addr=tm") && l.contains(" hdkeypath=m/44'/1'/"))
+    );
+    assert_eq!(
+        key_lines
+            .iter()
+            .filter(|l| l.contains(" reserve=1 "))
+            .count(),
+        1
+    );
+    assert!(dump.contains(" label=savings%20100%25 # addr="));
+
+    let zkeys = lines.iter().position(|l| *l == "# Zkeys").unwrap();
+    assert!(lines[zkeys + 2].starts_with("ST"));
+    assert!(lines[zkeys + 2].ends_with(&format!(
+        " 1970-01-01T00:00:00Z # zaddr={}",
+        address.encode(ZcashNetwork::Regtest)
+    )));
+    assert!(address.encode(ZcashNetwork::Main).starts_with("zc"));
+    let sapling_keys = lines.iter().position(|l| *l == "# Sapling keys").unwrap();
+    assert_eq!(
+        lines[sapling_keys + 2],
+        format!(
+            "{} 2000-02-29T00:00:00Z # zaddr={} hdkeypath=m/32'/1'/2'",
+            extsk::encode(&sapling_key(), ZcashNetwork::Regtest).expose(),
+            sapling.encode(ZcashNetwork::Regtest)
+        )
+    );
+    assert!(
+        sapling
+            .encode(ZcashNetwork::Regtest)
+            .starts_with("zregtestsapling1")
+    );
+}
+
+/// The Sapling key lines of a dump, without the section around them.
+fn sapling_key_lines(wallet: &ZcashdWallet) -> Vec<String> {
+    let mut out = Vec::new();
+    write_wallet_dump(&mut out, wallet, None, ZcashNetwork::Regtest, 0).unwrap();
+    let dump = String::from_utf8(out).unwrap();
+    let (_, sapling) = dump.split_once("# Sapling keys\n").unwrap();
+    sapling
+        .lines()
+        .filter(|l| !l.is_empty() && !l.starts_with('#'))
+        .map(str::to_string)
+        .collect()
+}
+
+#[test]
+fn sapling_key_lines_are_dated_and_pathed_by_their_sapzkeymeta() {
+    let ivk = [6; 32];
+    let address = SaplingPaymentAddress {
+        diversifier: [4; 11],
+        pk_d: [5; 32],
+    };
+    let record = |tag: &str, key: &[u8]| [&walletdb_key_prefix(tag)[..], key].concat();
+    // A `CKeyMetadata` at `VERSION_WITH_HDDATA`, as `GenerateNewSaplingZKey` writes it.
+    let path = "m/32'/1'/2'";
+    let mut meta = 10i32.to_le_bytes().to_vec();
+    meta.extend_from_slice(&951_782_400i64.to_le_bytes());
+    meta.push(path.len() as u8);
+    meta.extend_from_slice(path.as_bytes());
+    meta.extend_from_slice(&[7; 32]);
+    let records = [
+        (
+            record(
+                "sapzaddr",
+                &[&address.diversifier[..], &address.pk_d].concat(),
+            ),
+            ivk.to_vec(),
+        ),
+        (
+            record("sapzkey", &ivk),
+            sapling_key().to_bytes().expose().to_vec(),
+        ),
+        (record("sapzkeymeta", &ivk), meta),
+    ];
+    let wallet = ZcashdWallet::from_records(&default_registry(), records);
+    assert!(wallet.errors.is_empty(), "{:?}", wallet.errors);
+    assert_eq!(
+        sapling_key_lines(&wallet),
+        [format!(
+            "{} 2000-02-29T00:00:00Z # zaddr={} hdkeypath={path}",
+            extsk::encode(&sapling_key(), ZcashNetwork::Regtest).expose(),
+            address.encode(ZcashNetwork::Regtest)
+        )]
+    );
+}
+
+#[test]
+fn sapling_keys_of_unified_accounts_have_no_birth_time_or_path() {
+    // zcashd derives these keys for `z_getnewaccount` and stores them with a
+    // default-constructed `CKeyMetadata`, so `z_exportwallet` dates them 1970 too.
+    let wallet = WalletDb::open(format!(
+        "{WALLETS}/golden-v5.6.0/extracted_wallets/node0_wallet"
+    ))
+    .unwrap()
+    .decode()
+    .unwrap();
+    let keys = &wallet.keys;
+    assert_eq!(keys.sapling_metadata.len(), 4);
+    for meta in keys.sapling_metadata.values() {
+        assert_eq!(
+            meta,
+            &KeyMetadata {
+                version: 10,
+                create_time: 0,
+                hd_keypath: Some(String::new()),
+                seed_fingerprint: Some([0; 32]),
+            }
+        );
+    }
+    let lines = sapling_key_lines(&wallet);
+    assert_eq!(lines.len(), 2);
+    for line in &lines {
+        assert!(line.contains(" 1970-01-01T00:00:00Z # zaddr=zregtestsapling1"));
+        assert!(!line.contains("hdkeypath="));
+    }
+}
+
+#[test]
+fn zewif_accounts_hold_keys_by_the_account_their_path_names() {
+    let mut wallet = WalletDb::open(format!("{WALLETS}/wallet0.dat"))
+        .unwrap()
+        .decode()
+        .unwrap();
+    let keys = &mut wallet.keys;
+    let (pubkey, meta) = keys.transparent_metadata.iter_mut().next().unwrap();
+    meta.hd_keypath = Some("m/44'/1'/0'/0/0".to_string());
+    let seed_fingerprint = meta.seed_fingerprint.unwrap();
+    let moved = TransparentAddress::from_pubkey(pubkey).encode(ZcashNetwork::Regtest);
+    let account = UnifiedAccount {
+        seed_fingerprint,
+        coin_type: 1,
+        account_id: 0,
+        ufvk_id: [1; 32],
+    };
+    keys.unified_accounts.push(account);
+    keys.unified_fvks.insert(
+        [1; 32],
+        UnifiedFullViewingKey {
+            ufvk_id: [1; 32],
+            encoding: "uviewregtest1".to_string(),
+        },
+    );
+    keys.unified_addresses.push(UnifiedAddressMetadata {
+        ufvk_id: [1; 32],
+        diversifier_index: [2; 11],
+        receiver_types: vec![ReceiverType::Orchard],
+    });
+    wallet.address_book.insert_name(AddressName {
+        address: TransparentAddress::ScriptHash([9; 20]).encode(ZcashNetwork::Regtest),
+        label: "exchange".to_string(),
+    });
+
+    let zewif = Zewif::of(&wallet, None, ZcashNetwork::Regtest);
+    assert_eq!(zewif.export_height, Some(176));
+    assert_eq!(zewif.transactions.len(), wallet.transactions.len());
+    let [export] = &zewif.wallets[..] else {
+        panic!("one wallet");
+    };
+    assert!(matches!(
+        export.seed_material,
+        Some(Sourced {
+            value: SeedMaterial::Bip39Mnemonic { .. },
+            source: RecordKind::MnemonicPhrase,
+        })
+    ));
+    let [legacy, unified] = &export.accounts[..] else {
+        panic!("a legacy and a unified account");
+    };
+    assert_eq!(legacy.zip32_account_id, None);
+    assert_eq!(unified.zip32_account_id, Some(0));
+    assert_eq!(
+        unified.ufvk,
+        Some(Sourced::new(
+            "uviewregtest1".to_string(),
+            RecordKind::UnifiedFvk
+        ))
+    );
+
+    let encoded = |address: &ProtocolAddress| match address {
+        ProtocolAddress::Encoded { address, .. } => Some(address.clone()),
+        ProtocolAddress::Unified { .. } => None,
+    };
+    assert_eq!(unified.addresses.len(), 2);
+    assert_eq!(encoded(&unified.addresses[0].address), Some(moved));
+    assert_eq!(
+        unified.addresses[1].address,
+        ProtocolAddress::Unified {
+            diversifier_index: [2; 11],
+            receiver_types: vec![ReceiverType::Orchard],
+        }
+    );
+    // 51 keys and the address book entry of someone else's address.
+    assert_eq!(legacy.addresses.len(), 52);
+    let other = legacy.addresses.last().unwrap();
+    assert_eq!(other.source, RecordKind::Name);
+    assert_eq!(other.spending_key, None);
+    assert_eq!(
+        other.name,
+        Some(Sourced::new("exchange".to_string(), RecordKind::Name))
+    );
+
+    let exported: Vec<_> = transparent_keys(&wallet, None, ZcashNetwork::Regtest);
+    for address in &legacy.addresses[..51] {
+        let key = address.spending_key.as_ref().unwrap();
+        assert_eq!(key.source, RecordKind::Key);
+        let export = exported
+            .iter()
+            .find(|export| {
+                Some(export.address.encode(ZcashNetwork::Regtest)) == encoded(&address.address)
+            })
+            .unwrap();
+        assert_eq!(key.value, export.wif);
+        let path = address.hd_derivation_path.as_ref().unwrap();
+        assert!(path.value.starts_with("m/44'/1'/2147483647'/"));
+        assert_eq!(path.source, RecordKind::KeyMeta);
+        assert!(address.birth_time.is_some());
+    }
+    for txid in legacy
+        .relevant_transactions
+        .iter()
+        .chain(&unified.relevant_transactions)
+    {
+        assert!(zewif.transactions.contains_key(txid));
+    }
+    assert!(!legacy.relevant_transactions.is_empty());
+    assert!(
+        zewif
+            .transactions
+            .values()
+            .all(|tx| tx.mined_height.is_some() && tx.block_hash.is_some())
+    );
+}
@@ -0,0 +1,443 @@
+//! Readers against synthetic images from `FixtureBuilder`, covering geometries and
+//! damage the shipped fixtures do not.
+
+use std::{collections::BTreeSet, io, sync::Arc};
+
+use pretty_assertions::assert_eq;
+use zcashd_walletdb_parser::{
+    constants::P_OVERFLOW,
+    entry::parser::{leaf_pairs_on_page_checked, split_walletdb_key},
+    error::{ParseMode, WalletDbError},
+    storage::{
+        compare::bt_compare,
+        consistency::{DbImageReader, FindingKind, SalvageMode, check, verify_checksums},
+        entry::{Confidence, InMemoryMap},
+        fixture::{Corruption, FixtureBuilder},
+        page::{LevelTypeMismatch, PageHeader, PageProtection, PageType},
+        reader::FileDbImageReader,
+        slots::SlotViolation,
+        source::MemoryPageSource,
+        types::Endianness,
+        version::BtreeVersion,
+    },
+    util::Endian,
+};
+
+type Pairs = Vec<(Vec<u8>, Vec<u8>)>;
+
+fn wallet() -> FixtureBuilder {
+    let mut builder = FixtureBuilder::new();
+    for i in 0u32..200 {
+        builder = builder.wallet_record("key", &i.to_be_bytes(), vec![i as u8; 40 + i as usize]);
+    }
+    builder
+        .wallet_record("tx", &[1; 32], vec![0xab; 6000])
+        .record(vec![0xff; 3000], b"long key".to_vec())
+        .wallet_record("version", &[], 5_070_150u32.to_le_bytes().to_vec())
+}
+
+fn pairs(map: &dyn InMemoryMap) -> Pairs {
+    map.iter()
+        .map(|(k, e)| (k.clone(), e.value.clone()))
+        .collect()
+}
+
+fn read(reader: &FileDbImageReader) -> Pairs {
+    pairs(
+        reader
+            .build_map(SalvageMode::Conservative)
+            .unwrap()
+            .as_ref(),
+    )
+}
+
+#[test]
+fn builds_every_geometry() {
+    for page_size in [512, 4096, 65536] {
+        for endianness in [Endianness::Little, Endianness::Big] {
+            for checksum in [false, true] {
+                let builder = wallet()
+                    .page_size(page_size)
+                    .endianness(endianness)
+                    .checksum(checksum)
+                    .version(BtreeVersion::V10);
+                let name = format!("{page_size} {endianness:?} checksum={checksum}");
+                let reader =
+                    FileDbImageReader::from_image(builder.build().unwrap(), &name).unwrap();
+                assert_eq!(read(&reader), pairs(&builder.live_records()), "{name}");
+                let report = check(&reader).unwrap();
+                assert!(report.is_clean(), "{name}: {report}");
+                assert!(reader.diagnostics().is_empty(), "{name}");
+            }
+        }
+    }
+}
+
+#[test]
+fn wallet_records_carry_their_tag() {
+    let reader = FileDbImageReader::from_image(wallet().build().unwrap(), "tags").unwrap();
+    let tags: BTreeSet<String> = read(&reader)
+        .iter()
+        .filter_map(|(k, _)| split_walletdb_key(k).map(|(tag, _)| tag.to_string()))
+        .collect();
+    assert_eq!(
+        tags,
+        BTreeSet::from(["key", "tx", "version"].map(String::from))
+    );
+}
+
+#[test]
+fn deleted_records_are_skipped_but_salvageable() {
+    let builder = wallet()
+        .deleted_record(b"\x04gone".to_vec(), b"old value".to_vec())
+        .deleted_record(b"\x05gone2".to_vec(), vec![7; 5000]);
+    let reader = FileDbImageReader::from_image(builder.build().unwrap(), "deleted").unwrap();
+    assert_eq!(read(&reader), pairs(&builder.live_records()));
+    let deleted: Vec<Vec<u8>> = reader.deleted_entries().map(|(k, _, _)| k).collect();
+    assert_eq!(deleted, vec![b"\x04gone".to_vec(), b"\x05gone2".to_vec()]);
+}
+
+#[test]
+fn zeroed_meta_page_is_rebuilt() {
+    let builder = wallet().corrupt(Corruption::ZeroPage(0));
+    let reader = FileDbImageReader::from_image(builder.build().unwrap(), "zeroed").unwrap();
+    assert_eq!(read(&reader), pairs(&builder.live_records()));
+    let kinds: Vec<FindingKind> = reader.diagnostics().into_iter().map(|f| f.kind).collect();
+    assert!(
+        matches!(
+            kinds.as_slice(),
+            [FindingKind::MetaRebuilt { template: Some(2) }]
+        ),
+        "{kinds:?}"
+    );
+}
+
+#[test]
+fn truncation_is_reported() {
+    let image = wallet().build().unwrap();
+    let cut = image.len() - 3 * 4096;
+    let truncated = wallet().corrupt(Corruption::Truncate(cut)).build().unwrap();
+    assert_eq!(truncated.len(), cut);
+    let reader = FileDbImageReader::from_image(truncated, "truncated").unwrap();
+    let salvaged = reader.entries(SalvageMode::Conservative).count();
+    assert!(salvaged < wallet().live_records().len());
+    assert!(
+        reader
+            .diagnostics()
+            .iter()
+            .any(|f| matches!(f.kind, FindingKind::Truncated { .. }))
+    );
+}
+
+#[test]
+fn patched_checksummed_page_fails_verification() {
+    let builder = wallet().checksum(true).corrupt(Corruption::Patch {
+        pgno: 4,
+        offset: 2000,
+        bytes: vec![0xff; 4],
+    });
+    let source = MemoryPageSource::new(builder.build().unwrap(), 4096, "patched").unwrap();
+    let findings = verify_checksums(&source, None).unwrap();
+    let pages: Vec<_> = findings.iter().map(|f| f.page_no).collect();
+    assert_eq!(pages, vec![Some(4)]);
+    assert!(matches!(
+        findings[0].kind,
+        FindingKind::ChecksumMismatch { .. }
+    ));
+}
+
+#[test]
+fn meta_versions_older_than_any_known_are_rejected() {
+    let open = |version: u32| {
+        let builder = wallet().corrupt(Corruption::Patch {
+            pgno: 0,
+            offset: 16,
+            bytes: version.to_le_bytes().to_vec(),
+        });
+        let source = MemoryPageSource::new(builder.build().unwrap(), 4096, "version").unwrap();
+        FileDbImageReader::from_source(Arc::new(source))
+    };
+
+    let err = open(7).err().unwrap();
+    assert_eq!(err.kind(), io::ErrorKind::Unsupported);
+    assert_eq!(
+        err.to_string(),
+        "btree version 7 predates Berkeley DB 4.0; upgrade the file with db_upgrade"
+    );
+    assert!(open(0).is_err());
+
+    // A newer version is read as the newest known one, with a warning.
+    let reader = open(11).unwrap();
+    assert_eq!(reader.version(), BtreeVersion::NEWEST);
+    let kinds: Vec<_> = reader.diagnostics().into_iter().map(|f| f.kind).collect();
+    assert!(
+        matches!(kinds[..], [FindingKind::UnknownVersion { version: 11 }]),
+        "{kinds:?}"
+    );
+    assert_eq!(read(&reader), pairs(&wallet().live_records()));
+}
+
+#[test]
+fn strict_mode_fails_where_lenient_mode_skips_a_bad_slot() {
+    let clean = FileDbImageReader::from_image(wallet().build().unwrap(), "slots").unwrap();
+    let leaf = clean
+        .entries(SalvageMode::Conservative)
+        .find(|(_, _, p)| p.slot_index == 2)
+        .map(|(_, _, p)| p.page_no)
+        .unwrap();
+    let on_leaf: Vec<_> = clean
+        .entries(SalvageMode::Conservative)
+        .filter(|(_, _, p)| p.page_no == leaf)
+        .map(|(k, _, _)| k)
+        .collect();
+
+    // Point the key slot of the leaf's second pair past the end of the page.
+    let image = wallet()
+        .corrupt(Corruption::Patch {
+            pgno: leaf,
+            offset: 26 + 2 * 2,
+            bytes: 0xfff0u16.to_le_bytes().to_vec(),
+        })
+        .build()
+        .unwrap();
+    let open = |mode| {
+        FileDbImageReader::from_image(image.clone(), "bad slot")
+            .unwrap()
+            .with_parse_mode(mode)
+    };
+
+    let err = open(ParseMode::Strict)
+        .build_map(SalvageMode::Conservative)
+        .err()
+        .unwrap();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+    // Lenient reads every other leaf and records the one it could not.
+    let reader = open(ParseMode::Lenient);
+    let mut expected = pairs(&wallet().live_records());
+    expected.retain(|(k, _)| !on_leaf.contains(k));
+    assert!(!expected.is_empty());
+    assert_eq!(read(&reader), expected);
+    let kinds: Vec<_> = reader.diagnostics().into_iter().map(|f| f.kind).collect();
+    assert!(
+        matches!(kinds[..], [FindingKind::PageUnreadable]),
+        "{kinds:?}"
+    );
+
+    // On the page itself, lenient parsing skips only the damaged pair.
+    let page = &image[leaf as usize * 4096..][..4096];
+    let hdr = PageHeader::parse(page, Endianness::Little, PageProtection::None).unwrap();
+    let parse = |mode| {
+        leaf_pairs_on_page_checked(
+            "bad slot",
+            &image,
+            4096,
+            Endian::Le,
+            PageProtection::None,
+            page,
+            &hdr,
+            mode,
+        )
+    };
+    assert!(matches!(
+        parse(ParseMode::Strict),
+        Err(WalletDbError::BadSlot { pgno, violation: SlotViolation::PastPageEnd { slot: 2, .. } })
+            if pgno == leaf
+    ));
+    let lenient = parse(ParseMode::Lenient).unwrap();
+    let keys: Vec<_> = lenient.pairs.iter().map(|(k, _, _)| k.clone()).collect();
+    let mut survivors = on_leaf.clone();
+    survivors.remove(1);
+    assert_eq!(keys, survivors);
+    let skipped: Vec<_> = lenient.skipped.iter().map(|(slot, _)| *slot).collect();
+    assert_eq!(skipped, [2]);
+}
+
+#[test]
+fn salvaged_entries_carry_a_lowered_confidence() {
+    let image = wallet().build().unwrap();
+    let reader = FileDbImageReader::from_image(image.clone(), "clean").unwrap();
+    let leaf = reader
+        .entries(SalvageMode::Conservative)
+        .find(|(k, _, _)| k.starts_with(b"\x03key"))
+        .map(|(_, _, p)| p.page_no)
+        .unwrap();
+    // The last page of the `tx` value's two-page overflow chain.
+    let chain_end = image
+        .chunks(4096)
+        .position(|page| page[25] == P_OVERFLOW && page[12..16] != [0; 4])
+        .unwrap() as u32;
+
+    let damaged = wallet()
+        .corrupt(Corruption::Patch {
+            pgno: leaf,
+            offset: 26 + 2 * 2,
+            bytes: 0xfff0u16.to_le_bytes().to_vec(),
+        })
+        .corrupt(Corruption::ZeroPage(chain_end));
+    let reader = FileDbImageReader::from_image(damaged.build().unwrap(), "damaged").unwrap();
+
+    // The tree walk only yields what it read from sound pages.
+    assert!(
+        reader
+            .entries(SalvageMode::Conservative)
+            .all(|(_, _, p)| p.confidence == Confidence::High)
+    );
+
+    let mut salvaged = Vec::new();
+    for (key, _, p) in reader.entries(SalvageMode::BestEffort) {
+        let expected = if key.starts_with(b"\x02tx") {
+            Confidence::Low
+        } else if p.page_no == leaf {
+            Confidence::Medium
+        } else {
+            Confidence::High
+        };
+        assert_eq!(p.confidence, expected, "{key:?} on page {}", p.page_no);
+        salvaged.push(p.confidence);
+    }
+    for confidence in [Confidence::Low, Confidence::Medium, Confidence::High] {
+        assert!(salvaged.contains(&confidence), "no {confidence:?} entry");
+    }
+}
+
+#[test]
+fn unsorted_keys_are_reported() {
+    let image = wallet().build().unwrap();
+    let reader = FileDbImageReader::from_image(image.clone(), "sorted").unwrap();
+    // The first entries of the first two leaves holding `key` records.
+    let mut leaves: Vec<u32> = reader
+        .entries(SalvageMode::Conservative)
+        .filter(|(k, _, p)| k.starts_with(b"\x03key") && p.slot_index == 0)
+        .map(|(_, _, p)| p.page_no)
+        .collect();
+    leaves.truncate(2);
+    let [first, second] = leaves[..] else {
+        panic!("the `key` records fit one leaf: {leaves:?}")
+    };
+    let slot = |pgno: u32, i: usize| {
+        let at = pgno as usize * 4096 + 26 + 2 * i;
+        image[at..at + 2].to_vec()
+    };
+
+    // Swap the first two keys of a leaf.
+    let swapped = wallet()
+        .corrupt(Corruption::Patch {
+            pgno: first,
+            offset: 26,
+            bytes: slot(first, 2),
+        })
+        .corrupt(Corruption::Patch {
+            pgno: first,
+            offset: 30,
+            bytes: slot(first, 0),
+        });
+    let reader = FileDbImageReader::from_image(swapped.build().unwrap(), "swapped").unwrap();
+    let kinds: Vec<_> = check(&reader)
+        .unwrap()
+        .findings
+        .into_iter()
+        .map(|f| (f.page_no, f.kind))
+        .collect();
+    assert!(
+        matches!(kinds[..], [(Some(p), FindingKind::KeyOrder { slot: 2 })] if p == first),
+        "{kinds:?}"
+    );
+
+    // Lower the first key of the second leaf below every key of the first: `key` + [0; 4].
+    let key = u16::from_le_bytes(slot(second, 0).try_into().unwrap()) as usize;
+    let lowered = wallet().corrupt(Corruption::Patch {
+        pgno: second,
+        offset: key + 3 + 4,
+        bytes: vec![0; 4],
+    });
+    let reader = FileDbImageReader::from_image(lowered.build().unwrap(), "lowered").unwrap();
+    let kinds: Vec<_> = check(&reader)
+        .unwrap()
+        .findings
+        .into_iter()
+        .map(|f| (f.page_no, f.kind))
+        .collect();
+    assert!(
+        matches!(kinds[..], [(Some(p), FindingKind::SiblingKeyOrder { prev })] if p == second && prev == first),
+        "{kinds:?}"
+    );
+}
+
+#[test]
+fn a_child_at_the_wrong_level_is_reported() {
+    let image = wallet().build().unwrap();
+    let reader = FileDbImageReader::from_image(image, "levels").unwrap();
+    let leaf = reader
+        .entries(SalvageMode::Conservative)
+        .find(|(k, _, _)| k.starts_with(b"\x03key"))
+        .map(|(_, _, p)| p.page_no)
+        .unwrap();
+    assert!(check(&reader).unwrap().findings.is_empty());
+
+    // A leaf under the root claiming the root's own level.
+    let raised = wallet().corrupt(Corruption::Patch {
+        pgno: leaf,
+        offset: 24,
+        bytes: vec![2],
+    });
+    let image = raised.build().unwrap();
+    let page = &image[leaf as usize * 4096..][..4096];
+    let hdr = PageHeader::parse(page, Endianness::Little, PageProtection::None).unwrap();
+    let mismatch = hdr.check_level().unwrap_err();
+    assert_eq!(
+        mismatch,
+        LevelTypeMismatch {
+            page_type: PageType::BtreeLeaf,
+            level: 2,
+        }
+    );
+    assert_eq!(
+        mismatch.to_string(),
+        "BtreeLeaf page at level 2, expected level 1"
+    );
+
+    let reader = FileDbImageReader::from_image(image, "raised").unwrap();
+    let kinds: Vec<_> = check(&reader)
+        .unwrap()
+        .findings
+        .into_iter()
+        .map(|f| (f.page_no, f.kind))
+        .collect();
+    assert!(
+        matches!(
+            kinds[..],
+            [(Some(p), FindingKind::LevelMismatch { expected: 1, found: 2 })] if p == leaf
+        ),
+        "{kinds:?}"
+    );
+}
+
+#[test]
+fn keys_sort_as_unsigned_bytes_with_prefixes_first() {
+    use std::cmp::Ordering::{Equal, Greater, Less};
+    assert_eq!(bt_compare(b"", b""), Equal);
+    assert_eq!(bt_compare(b"", b"\x00"), Less);
+    assert_eq!(bt_compare(b"\x02tx", b"\x02txs"), Less);
+    assert_eq!(bt_compare(b"\x03key", b"\x02tx"), Greater);
+    // Unsigned: 0x80 sorts above 0x7f.
+    assert_eq!(bt_compare(&[0x80], &[0x7f, 0xff]), Greater);
+    assert_eq!(bt_compare(&[1, 2, 3], &[1, 2, 3]), Equal);
+
+    // The trees Berkeley DB wrote are in that order, so checking them finds nothing.
+    for node in 0..4 {
+        let path = format!(
+            "{}/../dat_files/golden-v5.6.0/extracted_wallets/node{node}_wallet",
+            env!("CARGO_MANIFEST_DIR")
+        );
+        let reader = FileDbImageReader::open(&path).unwrap();
+        let findings = check(&reader).unwrap().findings;
+        assert!(
+            !findings.iter().any(|f| matches!(
+                f.kind,
+                FindingKind::KeyOrder { .. } | FindingKind::SiblingKeyOrder { .. }
+            )),
+            "node{node}: {findings:?}"
+        );
+    }
+}
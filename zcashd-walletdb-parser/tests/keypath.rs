@@ -0,0 +1,87 @@
+//! HD key paths from key metadata, and the gaps between them.
+
+use pretty_assertions::assert_eq;
+use zcashd_walletdb_parser::{
+    parser::keypath::{
+        ChildIndex, KeyPath, LEGACY_ACCOUNT, PURPOSE_BIP44, PURPOSE_ZIP32, find_gaps,
+    },
+    storage::walletdb::WalletDb,
+};
+
+const WALLETS: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../dat_files");
+
+fn path(s: &str) -> KeyPath {
+    KeyPath::parse(s).unwrap()
+}
+
+#[test]
+fn bip44_and_zip32_paths_parse_into_their_components() {
+    let transparent = path("m/44'/133'/0'/1/5");
+    assert_eq!(transparent.purpose(), Some(PURPOSE_BIP44));
+    assert_eq!(transparent.coin_type(), Some(133));
+    assert_eq!(transparent.account(), Some(0));
+    assert_eq!(transparent.chain(), Some(1));
+    assert_eq!(transparent.address_index(), Some(5));
+    assert_eq!(transparent.to_string(), "m/44'/133'/0'/1/5");
+    assert_eq!(path("m/44h/133H/0'/1/5"), transparent);
+
+    let sapling = path("m/32'/1'/7'");
+    assert_eq!(sapling.purpose(), Some(PURPOSE_ZIP32));
+    assert_eq!(sapling.account(), Some(7));
+    assert_eq!((sapling.chain(), sapling.address_index()), (None, None));
+    assert_eq!(sapling.last(), Some(ChildIndex::hardened(7)));
+    assert_eq!(path("m"), KeyPath::default());
+
+    for bad in [
+        "",
+        "44'/0",
+        "m/",
+        "m/x",
+        "m/-1",
+        "m/+1",
+        "m/2147483648",
+        "m/1''",
+    ] {
+        assert!(KeyPath::parse(bad).is_err(), "{bad:?}");
+    }
+}
+
+#[test]
+fn paths_order_by_index_and_gaps_are_found() {
+    let mut paths: Vec<KeyPath> = ["m/44'/1'/0'/0/10", "m/44'/1'/0'/0/2", "m/44'/1'/0'/0/0"]
+        .map(path)
+        .into();
+    paths.sort();
+    assert_eq!(paths[1].address_index(), Some(2));
+    assert_eq!(paths[2].address_index(), Some(10));
+
+    let gaps = find_gaps(&paths);
+    let shown: Vec<String> = gaps.iter().map(ToString::to_string).collect();
+    assert_eq!(
+        shown,
+        ["m/44'/1'/0'/0/1 missing", "m/44'/1'/0'/0/3 to 9 missing"]
+    );
+    assert!(find_gaps(&[path("m/32'/1'/0'"), path("m/32'/1'/1'")]).is_empty());
+}
+
+#[test]
+fn fixture_keys_derive_without_gaps() {
+    for n in 0..8 {
+        let wallet = WalletDb::open(format!("{WALLETS}/wallet{n}.dat"))
+            .unwrap()
+            .decode()
+            .unwrap();
+        let paths = wallet.keys.transparent_key_paths();
+        assert_eq!(paths.len(), wallet.keys.transparent_len());
+        assert!(
+            paths
+                .keys()
+                .all(|p| p.coin_type() == Some(1) && p.account() == Some(LEGACY_ACCOUNT))
+        );
+        assert_eq!(find_gaps(paths.keys()), [], "wallet{n}");
+
+        let chain = wallet.crypto.mnemonic_hd_chain.as_ref().unwrap();
+        let internal = paths.keys().filter(|p| p.chain() == Some(1)).count();
+        assert_eq!(internal as u32, chain.legacy_transparent_internal_counter);
+    }
+}
@@ -0,0 +1,129 @@
+//! Wallet lineage detection against the shipped zcashd fixtures and synthetic records of
+//! other wallet software.
+
+use zcashd_walletdb_parser::{
+    entry::parser::walletdb_key_prefix,
+    parser::{
+        decoders::network::ZcashNetwork,
+        lineage::{NetworkMismatch, WalletSoftware, detect_lineage},
+    },
+    storage::walletdb::WalletDb,
+};
+
+const WALLET: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../dat_files/wallet0.dat");
+
+/// A `bestblock` value: a locator holding only the genesis hash, given in display order.
+fn locator(genesis: &str) -> Vec<u8> {
+    let mut hash = hex::decode(genesis).unwrap();
+    hash.reverse();
+    let mut value = 170_100i32.to_le_bytes().to_vec();
+    value.push(1);
+    value.extend(hash);
+    value
+}
+
+#[test]
+fn zcashd_fixture_is_zcashd_regtest() {
+    let db = WalletDb::open(WALLET).unwrap();
+    let lineage = detect_lineage(db.entries().map(|r| {
+        let (key, value, _) = r.unwrap();
+        (key, value)
+    }));
+    assert_eq!(lineage.software, WalletSoftware::Zcashd);
+    let network = lineage.network.unwrap();
+    assert_eq!(
+        (network.coin.as_str(), network.name.as_str()),
+        ("Zcash", "regtest")
+    );
+    assert!(lineage.warnings.is_empty());
+}
+
+#[test]
+fn bitcoin_wallets_are_told_apart_by_genesis() {
+    let bitcoin = "000000000019d6689c085ae165831e934ff763ae46a2a6c172b3f1b60a8ce26f";
+    let litecoin = "12a765e31ffd4059bada1e25190f6e98c99d9714d334efa41a195a7e7e04bfe2";
+    for (genesis, software) in [
+        (bitcoin, WalletSoftware::BitcoinCore),
+        (litecoin, WalletSoftware::BitcoinCoreFork),
+    ] {
+        let records = [
+            (
+                walletdb_key_prefix("version"),
+                170_100u32.to_le_bytes().to_vec(),
+            ),
+            (walletdb_key_prefix("hdchain"), vec![0; 44]),
+            (walletdb_key_prefix("bestblock"), locator(genesis)),
+        ];
+        assert_eq!(detect_lineage(records).software, software, "{genesis}");
+    }
+    let untagged = [(walletdb_key_prefix("version"), vec![0; 4])];
+    assert_eq!(detect_lineage(untagged).software, WalletSoftware::Unknown);
+}
+
+fn networkinfo(id: &str) -> Vec<u8> {
+    let mut value = vec![5];
+    value.extend_from_slice(b"Zcash");
+    value.push(id.len() as u8);
+    value.extend_from_slice(id.as_bytes());
+    value
+}
+
+#[test]
+fn network_mismatches_are_reported() {
+    let regtest = "029f11d80ef9765602235e1bc9727e3eb6ba20839319f761fee920d63401e327";
+    let records = [
+        (walletdb_key_prefix("networkinfo"), networkinfo("main")),
+        (walletdb_key_prefix("bestblock"), locator(regtest)),
+    ];
+    let lineage = detect_lineage(records);
+    assert_eq!(lineage.network.unwrap().name, "regtest");
+    assert!(matches!(
+        &lineage.warnings[..],
+        [NetworkMismatch::Genesis { networkinfo, .. }] if networkinfo.name == "main"
+    ));
+
+    let mainnet = "t1Kn5Ne1NZ8hXTCDq7ZoXMoPZiDhHXUgqNP";
+    let mut name = walletdb_key_prefix("name");
+    name.push(mainnet.len() as u8);
+    name.extend_from_slice(mainnet.as_bytes());
+    let records = [
+        (walletdb_key_prefix("networkinfo"), networkinfo("test")),
+        (name, vec![0]),
+    ];
+    let lineage = detect_lineage(records);
+    assert_eq!(
+        lineage.warnings,
+        [NetworkMismatch::Address {
+            address: mainnet.to_string(),
+            wallet: ZcashNetwork::Test,
+            encoded_for: &[ZcashNetwork::Main],
+        }]
+    );
+    assert!(lineage.to_string().contains("NETWORK MISMATCH"));
+}
+
+#[test]
+fn addresses_name_their_networks() {
+    for (address, networks) in [
+        (
+            "tmEZhbWHTpdKMw5it8YDspUXSMGQyFwovpU",
+            &[ZcashNetwork::Test, ZcashNetwork::Regtest][..],
+        ),
+        (
+            "zs1z7rejlpsa98s2rrrfkwmaxu53e4ue0ulcrw0h4x5g8jl04tak0d3mm47vdtahatqrlkngh9sly",
+            &[ZcashNetwork::Main],
+        ),
+        (
+            "ztestsapling1qqqqqqqqqqqqqqqqqqqqqqqqq",
+            &[ZcashNetwork::Test],
+        ),
+        ("uregtest1qqqqqqqq", &[ZcashNetwork::Regtest]),
+        (
+            "zcU1Cd6zYyZCd2VJF8yKgmzjxdiiU1rgTTjEwoN1CGUWCziPkUTXUjXmX7TMqdMNsTfuiGN1jQoVN4kGxUR4sAPN4XZ7pxb",
+            &[ZcashNetwork::Main],
+        ),
+        ("bc1qar0srrr7xfkvy5l643lydnw9re59gtzzwf5mdq", &[]),
+    ] {
+        assert_eq!(ZcashNetwork::of_address(address), networks, "{address}");
+    }
+}
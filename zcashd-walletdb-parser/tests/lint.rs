@@ -0,0 +1,166 @@
+//! [`LintReport`] against the shipped fixtures and wallets with dangling records.
+
+use pretty_assertions::assert_eq;
+use zcashd_walletdb_parser::{
+    entry::parser::walletdb_key_prefix,
+    parser::{
+        decoders::{
+            encryption::{DerivationMethod, MasterKey},
+            keymeta::KeyMetadata,
+            keypool::KeyPoolEntry,
+            sapling::SaplingPaymentAddress,
+            transparent::EncryptedKey,
+            unified::{ReceiverType, UnifiedAddressMetadata},
+        },
+        lint::{LintIssue, LintReport},
+        record::RecordKind,
+        registry::default_registry,
+        summary::WalletSummary,
+        wallet::ZcashdWallet,
+    },
+    storage::{consistency::Severity, walletdb::WalletDb},
+};
+
+const WALLETS: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../dat_files");
+
+#[test]
+fn fixtures_are_consistent() {
+    for n in 0..8 {
+        let wallet = WalletDb::open(format!("{WALLETS}/wallet{n}.dat"))
+            .unwrap()
+            .decode()
+            .unwrap();
+        let report = LintReport::of(&wallet);
+        assert_eq!(report.issues, [], "wallet{n}");
+        assert!(report.is_clean());
+    }
+}
+
+#[test]
+fn dangling_records_are_reported_by_severity() {
+    let pubkey = |b: u8| [vec![0x02], vec![b; 32]].concat();
+    let mut wallet = ZcashdWallet::default();
+    let keys = &mut wallet.keys;
+    keys.encrypted_transparent.insert(
+        pubkey(1),
+        EncryptedKey {
+            pubkey: pubkey(1),
+            encrypted_secret: vec![0; 48],
+        },
+    );
+    keys.key_pool.insert(
+        7,
+        KeyPoolEntry {
+            index: 7,
+            version: 6_000_050,
+            time: 0,
+            pubkey: pubkey(2),
+        },
+    );
+    let metadata = KeyMetadata {
+        version: 10,
+        create_time: 0,
+        hd_keypath: Some(String::new()),
+        seed_fingerprint: Some([0; 32]),
+    };
+    keys.transparent_metadata
+        .insert(pubkey(1), metadata.clone());
+    keys.transparent_metadata.insert(pubkey(3), metadata);
+    let address = SaplingPaymentAddress {
+        diversifier: [4; 11],
+        pk_d: [5; 32],
+    };
+    keys.sapling_addresses.insert(address, [6; 32]);
+    keys.unified_addresses.push(UnifiedAddressMetadata {
+        ufvk_id: [8; 32],
+        diversifier_index: [0; 11],
+        receiver_types: vec![ReceiverType::Sapling],
+    });
+
+    let report = LintReport::of(&wallet);
+    assert_eq!(
+        report.issues,
+        [
+            LintIssue::EncryptedWithoutMasterKey {
+                kind: RecordKind::CKey,
+                count: 1,
+            },
+            LintIssue::PoolKeyMissing {
+                index: 7,
+                pubkey: pubkey(2),
+            },
+            LintIssue::UnifiedAddressWithoutFvk {
+                ufvk_id: [8; 32],
+                diversifier_index: [0; 11],
+            },
+            LintIssue::KeyMetadataWithoutKey { pubkey: pubkey(3) },
+            LintIssue::SaplingAddressWithoutKey {
+                address,
+                ivk: [6; 32],
+            },
+        ]
+    );
+    assert_eq!(report.max_severity(), Some(Severity::Error));
+    assert_eq!(
+        (
+            report.count(Severity::Error),
+            report.count(Severity::Warning)
+        ),
+        (3, 2)
+    );
+    assert!(
+        report
+            .to_string()
+            .contains("  error        : pool 7: no key")
+    );
+
+    // With a master key the ciphertext is expected, and a plaintext seed is not.
+    wallet.crypto.master_keys.insert(
+        1,
+        MasterKey {
+            id: 1,
+            encrypted_key: vec![0; 48],
+            salt: vec![0; 8],
+            derivation_method: DerivationMethod::Sha512,
+            derive_iterations: 25_000,
+            other_params: vec![],
+        },
+    );
+    let issues = LintReport::of(&wallet).issues;
+    assert!(
+        !issues
+            .iter()
+            .any(|issue| matches!(issue, LintIssue::EncryptedWithoutMasterKey { .. }))
+    );
+}
+
+#[test]
+fn records_that_do_not_decode_are_errors() {
+    let mut key = walletdb_key_prefix("unifiedaddrmeta");
+    key.extend_from_slice(&[0xaa; 32]);
+    key.extend_from_slice(&[0; 11]);
+    key.extend_from_slice(&[1, 2]);
+    let records = [(key, 7u32.to_le_bytes().to_vec())];
+    let wallet = ZcashdWallet::from_records(&default_registry(), records);
+    assert_eq!(wallet.errors.len(), 1);
+
+    let report = LintReport::of(&wallet);
+    assert_eq!(
+        report.issues,
+        [LintIssue::Undecodable {
+            kind: RecordKind::UnifiedAddrMeta,
+            error: wallet.errors[0].to_string(),
+        }]
+    );
+    assert!(!report.is_clean());
+    assert_eq!(report.max_severity(), Some(Severity::Error));
+    assert!(
+        report
+            .to_string()
+            .contains("  error        : unifiedaddrmeta aaaa")
+    );
+
+    // The summary takes the failure from the lint report, once.
+    let problems = WalletSummary::of(&wallet).problems;
+    assert_eq!(problems, [wallet.errors[0].to_string()]);
+}
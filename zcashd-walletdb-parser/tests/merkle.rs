@@ -0,0 +1,139 @@
+//! Note commitment tree roots against the protocol's empty roots and a tree built leaf by
+//! leaf, and the witness checks against the shipped fixtures.
+
+use pretty_assertions::assert_eq;
+use zcashd_walletdb_parser::{
+    crypto::blake2s::blake2s,
+    parser::merkle::{
+        MerkleFrontier, MerkleHasher, SaplingHasher, SproutHasher, Witness, check_witnesses,
+    },
+    storage::walletdb::WalletDb,
+};
+
+const WALLETS: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../dat_files");
+
+fn leaf(n: u8) -> [u8; 32] {
+    [n; 32]
+}
+
+/// The root of the tree holding `leaves`, hashing every level in full.
+fn naive_root<H: MerkleHasher>(leaves: &[[u8; 32]]) -> [u8; 32] {
+    let mut nodes = leaves.to_vec();
+    for level in 0..H::DEPTH {
+        if nodes.len() % 2 == 1 {
+            nodes.push(H::empty_roots()[level]);
+        }
+        if nodes.is_empty() {
+            return H::empty_roots()[H::DEPTH];
+        }
+        nodes = nodes
+            .chunks_exact(2)
+            .map(|pair| H::combine(level, &pair[0], &pair[1]))
+            .collect();
+    }
+    nodes[0]
+}
+
+#[test]
+fn blake2s_matches_rfc_7693() {
+    assert_eq!(
+        hex::encode(blake2s(32, b"", b"abc")),
+        "508c5e8c327c14e2e1a72ba34eeb452f37458b209ed63a294d999b4c86675982"
+    );
+}
+
+#[test]
+fn empty_roots_match_the_protocol() {
+    assert_eq!(
+        hex::encode(SproutHasher::empty_roots()[1]),
+        "da5698be17b9b46962335799779fbeca8ce5d491c0d26243bafef9ea1837a9d8"
+    );
+    let sapling = SaplingHasher::empty_roots();
+    assert_eq!(
+        hex::encode(sapling[1]),
+        "817de36ab2d57feb077634bca77819c8e0bd298c04f6fed0e6a83cc1356ca155"
+    );
+    assert_eq!(
+        hex::encode(sapling[2]),
+        "ffe9fc03f18b176c998806439ff0bb8ad193afdb27b2ccbc88856916dd804e34"
+    );
+
+    let empty = MerkleFrontier {
+        left: None,
+        right: None,
+        parents: vec![],
+    };
+    assert_eq!(empty.size(), 0);
+    assert_eq!(
+        empty.root::<SproutHasher>(),
+        SproutHasher::empty_roots()[SproutHasher::DEPTH]
+    );
+}
+
+#[test]
+fn frontier_and_witness_roots_match_the_full_tree() {
+    let leaves: Vec<_> = (1..=5).map(leaf).collect();
+    // After five leaves: the fifth on the left, and the first four hashed up to level 2.
+    let four = naive_root_of_level::<SproutHasher>(&leaves[..4], 2);
+    let frontier = MerkleFrontier {
+        left: Some(leaves[4]),
+        right: None,
+        parents: vec![None, Some(four)],
+    };
+    assert_eq!(frontier.size(), 5);
+    assert_eq!(frontier.last(), Some(leaves[4]));
+    assert_eq!(
+        frontier.root::<SproutHasher>(),
+        naive_root::<SproutHasher>(&leaves)
+    );
+
+    // The first leaf, witnessed while the second is filled in and the third starts the
+    // cursor's subtree at level 1.
+    let witness = Witness {
+        tree: MerkleFrontier {
+            left: Some(leaves[0]),
+            right: None,
+            parents: vec![],
+        },
+        filled: vec![leaves[1]],
+        cursor: Some(MerkleFrontier {
+            left: Some(leaves[2]),
+            right: None,
+            parents: vec![],
+        }),
+    };
+    assert_eq!(witness.position(), 0);
+    assert_eq!(witness.element(), Some(leaves[0]));
+    assert_eq!(
+        witness.root::<SproutHasher>(),
+        naive_root::<SproutHasher>(&leaves[..3])
+    );
+    assert_eq!(
+        witness.root::<SaplingHasher>(),
+        naive_root::<SaplingHasher>(&leaves[..3])
+    );
+}
+
+/// The node at `level` above the first `2^level` leaves.
+fn naive_root_of_level<H: MerkleHasher>(leaves: &[[u8; 32]], level: usize) -> [u8; 32] {
+    let mut nodes = leaves.to_vec();
+    for l in 0..level {
+        nodes = nodes
+            .chunks_exact(2)
+            .map(|pair| H::combine(l, &pair[0], &pair[1]))
+            .collect();
+    }
+    nodes[0]
+}
+
+#[test]
+fn fixture_witnesses_are_consistent() {
+    // The fixtures hold no shielded notes, so there is nothing to be inconsistent.
+    for n in 0..8 {
+        let wallet = WalletDb::open(format!("{WALLETS}/wallet{n}.dat"))
+            .unwrap()
+            .decode()
+            .unwrap();
+        assert_eq!(check_witnesses(&wallet), vec![], "wallet{n}");
+    }
+}
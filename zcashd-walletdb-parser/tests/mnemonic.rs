@@ -0,0 +1,137 @@
+//! Seed phrases against the BIP 39 reference vectors, and the seed cross-checks against
+//! the shipped fixtures, whose phrases derive the seeds their keys name.
+
+use pretty_assertions::assert_eq;
+use zcashd_walletdb_parser::{
+    crypto::{pbkdf2_hmac, sha1::Sha1},
+    parser::{
+        decoders::seed::{Language, MnemonicPhrase},
+        mnemonic::{
+            MnemonicError, SeedIssue, check_seeds, english_wordlist, phrase_entropy, phrase_seed,
+        },
+        record::RecordKind,
+        secret::SecretString,
+        wallet::ZcashdWallet,
+    },
+    storage::walletdb::WalletDb,
+};
+
+const WALLETS: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../dat_files");
+
+fn wallet(n: u32) -> ZcashdWallet {
+    WalletDb::open(format!("{WALLETS}/wallet{n}.dat"))
+        .unwrap()
+        .decode()
+        .unwrap()
+}
+
+#[test]
+fn pbkdf2_matches_rfc_6070() {
+    assert_eq!(
+        hex::encode(pbkdf2_hmac::<Sha1>(b"password", b"salt", 2, 20)),
+        "ea6c014dc72d6f8ccd1ed92ace1d41f0d8de8957"
+    );
+    assert_eq!(
+        hex::encode(pbkdf2_hmac::<Sha1>(b"password", b"salt", 4096, 20)),
+        "4b007901b765489abead49d926f721d065a429c1"
+    );
+}
+
+#[test]
+fn phrases_match_the_reference_vectors() {
+    let words = english_wordlist();
+    assert_eq!(
+        (words.len(), words[0], words[2047]),
+        (2048, "abandon", "zoo")
+    );
+
+    let cases = [
+        (
+            "abandon ".repeat(11) + "about",
+            "00000000000000000000000000000000",
+        ),
+        (
+            "legal winner thank year wave sausage worth useful legal winner thank yellow".into(),
+            "7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f",
+        ),
+        ("zoo ".repeat(23) + "vote", &"ff".repeat(32)),
+    ];
+    for (phrase, entropy) in &cases {
+        let decoded = phrase_entropy(phrase, Language::English).unwrap();
+        assert_eq!(hex::encode(decoded.expose()), *entropy, "{phrase}");
+    }
+
+    let seed = phrase_seed(&cases[0].0).unwrap();
+    assert_eq!(
+        hex::encode(seed.expose()),
+        "5eb00bbddcf069084889a8ab9155568165f5c453ccb85e70811aaed6f6da5fc1\
+         9a5ac40b389cd370d086206dec8aa6c43daea6690f20ad3d8d48b2d2ce9e38e4"
+    );
+}
+
+#[test]
+fn invalid_phrases_are_rejected_without_echoing_words() {
+    let check = |phrase: &str| phrase_entropy(phrase, Language::English).unwrap_err();
+    assert_eq!(check(&"abandon ".repeat(12)), MnemonicError::Checksum);
+    assert_eq!(check(&"abandon ".repeat(11)), MnemonicError::WordCount(11));
+    let typo = "abandon ".repeat(5) + "abandonn " + &"abandon ".repeat(5) + "about";
+    let error = check(&typo);
+    assert_eq!(error, MnemonicError::UnknownWord { position: 6 });
+    assert!(!error.to_string().contains("abandonn"));
+    assert_eq!(
+        phrase_entropy("", Language::Japanese).unwrap_err(),
+        MnemonicError::UnsupportedLanguage(Language::Japanese)
+    );
+}
+
+#[test]
+fn fixture_phrases_derive_the_seeds_their_keys_name() {
+    for n in 0..8 {
+        let wallet = wallet(n);
+        let mnemonic = wallet.crypto.mnemonic.as_ref().unwrap();
+        assert!(phrase_entropy(mnemonic.phrase.expose(), mnemonic.language).is_ok());
+        assert_eq!(check_seeds(&wallet, None), vec![], "wallet{n}");
+    }
+}
+
+#[test]
+fn a_diverged_phrase_is_reported() {
+    let wallet = wallet(0);
+    let recorded = wallet.crypto.mnemonic.as_ref().unwrap();
+    let keys = wallet.keys.transparent_metadata.len();
+    // Another valid phrase, filed under the original phrase's fingerprint.
+    let other = MnemonicPhrase {
+        phrase: SecretString::new("abandon ".repeat(11) + "about"),
+        ..recorded.clone()
+    };
+    let issues = check_seeds(&wallet, Some(&other));
+    assert_eq!(issues.len(), 3, "{issues:?}");
+    assert!(matches!(
+        issues[0],
+        SeedIssue::FingerprintMismatch {
+            kind: RecordKind::MnemonicPhrase,
+            recorded: fingerprint,
+            ..
+        } if fingerprint == recorded.fingerprint
+    ));
+    // The HD chain and every key name the seed the phrase no longer derives.
+    let unknown: Vec<_> = issues[1..]
+        .iter()
+        .map(|issue| match issue {
+            SeedIssue::UnknownSeed { kind, count, .. } => (*kind, *count),
+            other => panic!("{other}"),
+        })
+        .collect();
+    assert_eq!(
+        unknown,
+        [
+            (RecordKind::KeyMeta, keys),
+            (RecordKind::MnemonicHdChain, 1)
+        ]
+    );
+
+    let mut invalid = other;
+    invalid.phrase = SecretString::new("abandon ".repeat(12));
+    let issues = check_seeds(&wallet, Some(&invalid));
+    assert_eq!(issues[0], SeedIssue::InvalidPhrase(MnemonicError::Checksum));
+}
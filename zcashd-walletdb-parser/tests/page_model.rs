@@ -0,0 +1,336 @@
+//! The one page model shared by the byte-level parsers in `util`/`leaf` and the storage
+//! layer: type codes map one to one, both header parsers read the same fields, and both
+//! find the slot array and overflow payloads past the same protection block.
+
+use std::{collections::HashSet, fs};
+
+use zcashd_walletdb_parser::{
+    constants::B_KEYDATA,
+    entry::parser::extract_leaf_pairs,
+    leaf::{LeafItemKind, leaf_slots},
+    storage::{
+        consistency::{FindingKind, check},
+        entry::InMemoryMap,
+        fixture::{Corruption, FixtureBuilder},
+        page::{PageHeader, PageProtection, PageType},
+        reader::FileDbImageReader,
+        slots::{SlotViolation, validate_slots},
+        types::Endianness,
+    },
+    util::{Endian, parse_page_header},
+};
+
+const WALLETS: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../dat_files");
+
+#[test]
+fn every_type_code_round_trips() {
+    let mut names = HashSet::new();
+    for code in 0..=u8::MAX {
+        let page_type = PageType::from(code);
+        assert_eq!(page_type.code(), code);
+        assert_eq!(u8::from(page_type), code);
+        let known = !matches!(page_type, PageType::Unknown(_));
+        assert_eq!(known, code <= 16, "code {code}");
+        if known {
+            assert!(names.insert(page_type.as_str()), "{page_type} named twice");
+        }
+    }
+}
+
+#[test]
+fn access_method_codes_match_berkeley_db() {
+    let expected = [
+        (0, PageType::Invalid),
+        (3, PageType::BtreeInternal),
+        (4, PageType::RecnoInternal),
+        (5, PageType::BtreeLeaf),
+        (6, PageType::RecnoLeaf),
+        (7, PageType::Overflow),
+        (8, PageType::HashMeta),
+        (9, PageType::Meta),
+        (10, PageType::QueueMeta),
+        (11, PageType::QueueData),
+        (12, PageType::DuplicateLeaf),
+        (13, PageType::Hash),
+        (14, PageType::HeapMeta),
+        (15, PageType::Heap),
+    ];
+    for (code, page_type) in expected {
+        assert_eq!(PageType::from(code), page_type);
+    }
+    let metas: Vec<u8> = (0..=u8::MAX)
+        .filter(|&c| PageType::from(c).is_meta())
+        .collect();
+    assert_eq!(metas, [8, 9, 10, 14]);
+}
+
+#[test]
+fn both_header_parsers_agree_on_the_fixtures() {
+    for entry in fs::read_dir(WALLETS).unwrap() {
+        let path = entry.unwrap().path();
+        if path.extension().is_none_or(|ext| ext != "dat") {
+            continue;
+        }
+        let image = fs::read(path).unwrap();
+        for page in image.chunks_exact(4096) {
+            let a = parse_page_header(page, Endian::Le).unwrap();
+            let b = PageHeader::parse(page, Endianness::Little, PageProtection::None).unwrap();
+            assert_eq!(
+                (a.lsn, a.pgno, a.prev_pgno, a.next_pgno),
+                (b.lsn, b.pgno, b.prev_pgno, b.next_pgno)
+            );
+            assert_eq!((a.entries, a.hf_offset), (b.entries, b.hf_offset));
+            assert_eq!((a.level, a.kind()), (b.level, b.kind()));
+        }
+    }
+}
+
+#[test]
+fn leaf_pairs_start_past_the_protection_block() {
+    for (checksum, protection) in [
+        (false, PageProtection::None),
+        (true, PageProtection::Checksum),
+    ] {
+        let mut builder = FixtureBuilder::new().page_size(512).checksum(checksum);
+        for i in 0u8..40 {
+            builder = builder.wallet_record("key", &[i], vec![i; 20 + i as usize]);
+        }
+        // Longer than a page, so it sits on an overflow chain.
+        builder = builder.wallet_record("tx", &[1; 32], vec![0xab; 1500]);
+        let image = builder.build().unwrap();
+
+        let mut read = Vec::new();
+        for pgno in 0..(image.len() / 512) as u32 {
+            let hdr = parse_page_header(&image[pgno as usize * 512..], Endian::Le).unwrap();
+            if hdr.kind() != PageType::BtreeLeaf {
+                continue;
+            }
+            let pairs = extract_leaf_pairs("model", &image, 512, Endian::Le, protection, pgno);
+            // The master database names the `main` subdatabase on a leaf of its own.
+            let pairs = pairs.unwrap().into_iter().filter(|(k, _, _)| k != b"main");
+            read.extend(pairs.map(|(k, v, _)| (k, v)));
+        }
+        read.sort();
+        let expected: Vec<_> = builder
+            .live_records()
+            .iter()
+            .map(|(k, e)| (k.clone(), e.value.clone()))
+            .collect();
+        assert_eq!(read, expected, "{protection:?}");
+    }
+}
+
+#[test]
+fn leaf_slots_match_the_storage_slot_array_on_checksummed_pages() {
+    let mut builder = FixtureBuilder::new().page_size(512).checksum(true);
+    for i in 0u8..40 {
+        builder = builder.wallet_record("key", &[i], vec![i; 20 + i as usize]);
+    }
+    let image = builder.build().unwrap();
+    let protection = PageProtection::Checksum;
+    let mut leaves = 0;
+    for page in image.chunks_exact(512) {
+        let hdr = PageHeader::parse(page, Endianness::Little, protection).unwrap();
+        if hdr.kind() != PageType::BtreeLeaf {
+            continue;
+        }
+        leaves += 1;
+        let offsets: Vec<usize> = leaf_slots(page, Endian::Le, protection)
+            .unwrap()
+            .map(|slot| slot.offset)
+            .collect();
+        let report = validate_slots(page, &hdr, Endianness::Little, protection);
+        assert!(report.is_clean(), "{report:?}");
+        assert_eq!(offsets, report.offsets);
+    }
+    assert!(leaves > 1);
+}
+
+#[test]
+fn leaf_slots_flag_a_deleted_pair_the_live_walk_skips() {
+    let image = FixtureBuilder::new()
+        .page_size(512)
+        .record(b"a".to_vec(), b"1".to_vec())
+        .deleted_record(b"b".to_vec(), b"2".to_vec())
+        .record(b"c".to_vec(), b"3".to_vec())
+        .build()
+        .unwrap();
+    // Page 3 is the root of the `main` subdatabase, a single leaf.
+    let page = &image[3 * 512..4 * 512];
+    let slots: Vec<_> = leaf_slots(page, Endian::Le, PageProtection::None)
+        .unwrap()
+        .map(|slot| {
+            // The data of a `BKEYDATA` item follows its length and type.
+            let data = slot.bytes(page).unwrap()[3..].to_vec();
+            (slot.index, slot.kind, slot.deleted, data)
+        })
+        .collect();
+    let kd = Some(LeafItemKind::KeyData);
+    assert_eq!(
+        slots,
+        [
+            (0, kd, false, b"a".to_vec()),
+            (1, kd, false, b"1".to_vec()),
+            (2, kd, true, b"b".to_vec()),
+            (3, kd, true, b"2".to_vec()),
+            (4, kd, false, b"c".to_vec()),
+            (5, kd, false, b"3".to_vec()),
+        ]
+    );
+
+    let pairs: Vec<_> =
+        extract_leaf_pairs("deleted", &image, 512, Endian::Le, PageProtection::None, 3)
+            .unwrap()
+            .into_iter()
+            .map(|(k, v, p)| (k, v, p.slot_index))
+            .collect();
+    assert_eq!(
+        pairs,
+        [
+            (b"a".to_vec(), b"1".to_vec(), 0),
+            (b"c".to_vec(), b"3".to_vec(), 4)
+        ]
+    );
+}
+
+/// The slot violations of a 512-byte leaf whose slots hold `slots`, with the data region
+/// from `hf_offset`, and `(offset, len, type)` item headers written at `items`.
+fn violations(slots: &[u16], hf_offset: u16, items: &[(usize, u16, u8)]) -> Vec<SlotViolation> {
+    let mut page = vec![0; 512];
+    page[20..22].copy_from_slice(&(slots.len() as u16).to_le_bytes());
+    page[22..24].copy_from_slice(&hf_offset.to_le_bytes());
+    page[24] = 1;
+    page[25] = PageType::BtreeLeaf.code();
+    for (i, slot) in slots.iter().enumerate() {
+        page[26 + i * 2..28 + i * 2].copy_from_slice(&slot.to_le_bytes());
+    }
+    for &(offset, len, item_type) in items {
+        page[offset..offset + 2].copy_from_slice(&len.to_le_bytes());
+        page[offset + 2] = item_type;
+    }
+    let hdr = PageHeader::parse(&page, Endianness::Little, PageProtection::None).unwrap();
+    validate_slots(&page, &hdr, Endianness::Little, PageProtection::None).violations
+}
+
+#[test]
+fn overlapping_items_are_a_slot_violation() {
+    assert_eq!(
+        violations(
+            &[400, 410],
+            400,
+            &[(400, 20, B_KEYDATA), (410, 4, B_KEYDATA)]
+        ),
+        [SlotViolation::Overlap {
+            slot: 1,
+            other: 0,
+            offset: 410,
+            other_end: 423,
+        }]
+    );
+    // On-page duplicates share their key item, which is not an overlap.
+    assert_eq!(
+        violations(
+            &[400, 410, 400, 420],
+            400,
+            &[
+                (400, 4, B_KEYDATA),
+                (410, 4, B_KEYDATA),
+                (420, 4, B_KEYDATA),
+            ]
+        ),
+        []
+    );
+}
+
+#[test]
+fn an_item_running_off_the_page_is_a_slot_violation() {
+    assert_eq!(
+        violations(
+            &[480, 505],
+            480,
+            &[(480, 4, B_KEYDATA), (505, 10, B_KEYDATA)]
+        ),
+        [SlotViolation::PastPageEnd {
+            slot: 1,
+            offset: 505,
+            end: 518,
+            page_len: 512,
+        }]
+    );
+}
+
+#[test]
+fn an_offset_below_the_data_region_is_a_slot_violation() {
+    assert_eq!(
+        violations(&[480, 300], 480, &[(480, 4, B_KEYDATA)]),
+        [SlotViolation::BelowDataRegion {
+            slot: 1,
+            offset: 300,
+            hf_offset: 480,
+        }]
+    );
+}
+
+#[test]
+fn an_offset_into_the_slot_array_is_a_slot_violation() {
+    assert_eq!(
+        violations(&[480, 28], 480, &[(480, 4, B_KEYDATA)]),
+        [SlotViolation::IntoSlotArray {
+            slot: 1,
+            offset: 28,
+            slots_end: 30,
+        }]
+    );
+}
+
+#[test]
+fn an_unknown_item_type_is_a_slot_violation() {
+    assert_eq!(
+        violations(&[480, 490], 480, &[(480, 4, B_KEYDATA), (490, 4, 9)]),
+        [SlotViolation::UnknownItemType {
+            slot: 1,
+            offset: 490,
+            item_type: 9,
+        }]
+    );
+}
+
+#[test]
+fn a_slot_array_past_the_data_region_is_a_slot_violation() {
+    assert_eq!(
+        violations(&[480, 490], 28, &[(480, 4, B_KEYDATA), (490, 4, B_KEYDATA)]),
+        [SlotViolation::ArrayOverrun {
+            slots_end: 30,
+            hf_offset: 28,
+            first_lost: 2,
+        }]
+    );
+}
+
+#[test]
+fn an_odd_slot_count_on_a_leaf_is_reported() {
+    let builder = FixtureBuilder::new()
+        .page_size(512)
+        .record(b"a".to_vec(), b"1".to_vec())
+        .record(b"b".to_vec(), b"2".to_vec());
+    // Drop the last value of the single leaf of `main` from its slot count.
+    let image = builder
+        .corrupt(Corruption::Patch {
+            pgno: 3,
+            offset: 20,
+            bytes: 3u16.to_le_bytes().to_vec(),
+        })
+        .build()
+        .unwrap();
+    let reader = FileDbImageReader::from_image(image, "odd").unwrap();
+    let kinds: Vec<_> = check(&reader)
+        .unwrap()
+        .findings
+        .into_iter()
+        .map(|f| (f.page_no, f.kind))
+        .collect();
+    assert!(
+        matches!(kinds[..], [(Some(3), FindingKind::UnpairedSlot)]),
+        "{kinds:?}"
+    );
+}
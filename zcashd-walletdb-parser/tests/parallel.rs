@@ -0,0 +1,97 @@
+//! The `parallel` feature: the threaded readers return what the sequential ones do, in
+//! the same order.
+#![cfg(feature = "parallel")]
+
+use std::collections::BTreeSet;
+
+use pretty_assertions::assert_eq;
+use zcashd_walletdb_parser::storage::{
+    consistency::{DbImageReader, SalvageMode},
+    fixture::{Corruption, FixtureBuilder},
+    parallel::par_materialize,
+    reader::FileDbImageReader,
+};
+
+type Read = Vec<(Vec<u8>, Vec<u8>, u32, u16)>;
+
+/// Small pages, so the records spread over many leaves and most values overflow.
+fn wallet() -> FixtureBuilder {
+    let mut builder = FixtureBuilder::new().page_size(512);
+    for i in 0u32..300 {
+        let len = if i % 3 == 0 { 1_000 + i as usize } else { 20 };
+        builder = builder.wallet_record("tx", &i.to_be_bytes(), vec![i as u8; len]);
+    }
+    builder
+}
+
+fn sequential(reader: &FileDbImageReader, salvage: SalvageMode) -> Read {
+    reader
+        .entries(salvage)
+        .map(|(k, v, p)| (k, v.materialize().unwrap(), p.page_no, p.slot_index))
+        .collect()
+}
+
+fn parallel(reader: &FileDbImageReader, salvage: SalvageMode, threads: usize) -> Read {
+    reader
+        .par_entries(salvage, threads)
+        .unwrap()
+        .into_iter()
+        .map(|(k, v, p)| (k, v, p.page_no, p.slot_index))
+        .collect()
+}
+
+#[test]
+fn parallel_entries_match_the_sequential_walk() {
+    let reader = FileDbImageReader::from_image(wallet().build().unwrap(), "parallel").unwrap();
+    for salvage in [SalvageMode::Conservative, SalvageMode::BestEffort] {
+        let expected = sequential(&reader, salvage);
+        assert!(expected.len() >= 300, "{salvage:?}");
+        let pages: BTreeSet<u32> = expected.iter().map(|e| e.2).collect();
+        assert!(pages.len() > 8, "{salvage:?}: {pages:?}");
+        for threads in [1, 3, 8] {
+            assert_eq!(
+                parallel(&reader, salvage, threads),
+                expected,
+                "{salvage:?} on {threads} threads"
+            );
+        }
+    }
+    let map = reader
+        .par_build_map(SalvageMode::Conservative, Default::default(), 4)
+        .unwrap();
+    assert_eq!(map.len(), 300);
+}
+
+#[test]
+fn a_broken_overflow_chain_fails_only_its_own_value() {
+    let image = wallet().build().unwrap();
+    let overflow = image
+        .chunks_exact(512)
+        .position(|page| page[25] == 7)
+        .unwrap();
+    let broken = wallet()
+        .corrupt(Corruption::ZeroPage(overflow as u32))
+        .build()
+        .unwrap();
+    let reader = FileDbImageReader::from_image(broken, "broken").unwrap();
+
+    let entries: Vec<_> = reader.entries(SalvageMode::Conservative).collect();
+    let expected: Vec<bool> = entries
+        .iter()
+        .map(|(_, value, _)| value.materialize().is_ok())
+        .collect();
+    assert_eq!(expected.iter().filter(|ok| !**ok).count(), 1);
+    for threads in [1, 4] {
+        let entries: Vec<_> = reader.entries(SalvageMode::Conservative).collect();
+        let resolved: Vec<bool> = par_materialize(entries, threads)
+            .iter()
+            .map(Result::is_ok)
+            .collect();
+        assert_eq!(resolved, expected, "{threads} threads");
+    }
+
+    // Read leniently, the value is left out and reported on the page that named it.
+    let read = reader.par_entries(SalvageMode::Conservative, 4).unwrap();
+    assert_eq!(read.len(), 299);
+    assert!(reader.diagnostics().iter().any(|f| f.page_no.is_some()));
+}
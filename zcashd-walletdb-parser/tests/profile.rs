@@ -0,0 +1,67 @@
+//! [`WalletProfile`] against the shipped fixtures and wallets shaped after older zcashd
+//! releases.
+
+use pretty_assertions::assert_eq;
+use zcashd_walletdb_parser::{
+    parser::{
+        decoders::scalars::ClientVersion,
+        profile::{KeyGeneration, Pool, PoolUsage, VersionFloor, VersionIssue, WalletProfile},
+        record::RecordKind,
+        wallet::ZcashdWallet,
+    },
+    storage::walletdb::WalletDb,
+};
+
+const WALLETS: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../dat_files");
+
+#[test]
+fn fixtures_are_unencrypted_seed_phrase_wallets() {
+    let wallet = WalletDb::open(format!("{WALLETS}/wallet0.dat"))
+        .unwrap()
+        .decode()
+        .unwrap();
+    let profile = WalletProfile::of(&wallet);
+    assert_eq!(profile.generation, KeyGeneration::Mnemonic);
+    assert!(!profile.legacy_seed && !profile.encrypted);
+    assert_eq!((profile.derived_keys, profile.random_keys), (52, 0));
+    let transparent = PoolUsage {
+        keys: 52,
+        transactions: 50,
+    };
+    assert_eq!(
+        profile.pools.into_iter().collect::<Vec<_>>(),
+        [(Pool::Transparent, transparent)]
+    );
+    assert_eq!(profile.version, Some(ClientVersion(6_000_050)));
+    assert!(profile.floor.unwrap().version <= profile.version.unwrap());
+    assert!(profile.issues.is_empty());
+}
+
+#[test]
+fn versions_older_than_the_records_are_reported() {
+    let mut wallet = ZcashdWallet::default();
+    let profile = WalletProfile::of(&wallet);
+    assert_eq!(profile.generation, KeyGeneration::Random);
+    assert!(profile.pools.is_empty() && profile.floor.is_none());
+    assert!(profile.recovery().is_empty());
+
+    // A v1.0.0 wallet cannot hold Sapling key metadata, nor require v2.0.0 to read it.
+    let v1 = ClientVersion(1_000_050);
+    wallet.metadata.version = Some(v1);
+    wallet.metadata.min_version = Some(ClientVersion(2_000_050));
+    wallet.undecoded.insert(RecordKind::SapZKeyMeta, 1);
+    let floor = VersionFloor {
+        version: ClientVersion(2_000_050),
+        kind: RecordKind::SapZKeyMeta,
+    };
+    assert_eq!(
+        WalletProfile::of(&wallet).issues,
+        [
+            VersionIssue::OlderThanRecords { version: v1, floor },
+            VersionIssue::MinVersionAboveVersion {
+                version: v1,
+                min_version: floor.version,
+            },
+        ]
+    );
+}
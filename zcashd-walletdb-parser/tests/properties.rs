@@ -0,0 +1,515 @@
+//! Properties of the parsers over generated input: random page images, slot arrays,
+//! items and overflow references, plus mutated images from `FixtureBuilder`. Every
+//! parser must return instead of panicking, and whatever it returns must lie within the
+//! input and agree with the fields it was read from.
+//!
+//! Cases come from a seeded generator, so a failure names the case that reproduces it.
+
+use std::sync::Arc;
+
+use zcashd_walletdb_parser::{
+    entry::{
+        constants::{OverflowRef, iter_slots},
+        parser::{
+            leaf_pairs_on_page_checked, read_compact_size, read_leaf_item, read_overflow,
+            read_overflow_into, split_walletdb_key, write_compact_size,
+        },
+    },
+    error::ParseMode,
+    headers::parse_btree_meta_page0,
+    leaf::{LeafItem, LeafItemKind, leaf_slots, parse_leaf_entry},
+    storage::{
+        consistency::{DbImageReader, SalvageMode, check},
+        fixture::FixtureBuilder,
+        freelist::walk_freelist,
+        lsn::analyze_lsns,
+        orphans::analyze_reachability,
+        page::{PageHeader, PageProtection, PageType},
+        reader::FileDbImageReader,
+        slots::validate_slot_array,
+        source::MemoryPageSource,
+        stats::collect_stats,
+        types::{Endianness, PageSource},
+    },
+    util::{Endian, is_valid_page_size, page_slice, parse_page_header, u16e, u32e},
+};
+
+/// xorshift64 with helpers that favour the values parsers get wrong: zero, boundaries
+/// and the largest values a field can hold.
+struct Gen(u64);
+
+impl Gen {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n.max(1) as u64) as usize
+    }
+
+    fn chance(&mut self, one_in: usize) -> bool {
+        self.below(one_in) == 0
+    }
+
+    /// A value up to `max`, often 0, 1, `max` or near it.
+    fn edgy(&mut self, max: u64) -> u64 {
+        match self.below(8) {
+            0 => 0,
+            1 => 1,
+            2 => max,
+            3 => max.saturating_sub(self.below(16) as u64),
+            _ => self.next() % max.saturating_add(1).max(1),
+        }
+    }
+
+    /// Like [`Gen::edgy`], but now and then up to `usize::MAX`.
+    fn edgy_or_huge(&mut self, max: u64) -> u64 {
+        let max = if self.chance(8) {
+            usize::MAX as u64
+        } else {
+            max
+        };
+        self.edgy(max)
+    }
+
+    fn bytes(&mut self, len: usize) -> Vec<u8> {
+        (0..len).map(|_| self.next() as u8).collect()
+    }
+
+    fn page_size(&mut self) -> usize {
+        [512, 1024, 4096][self.below(3)]
+    }
+
+    fn endian(&mut self) -> Endian {
+        if self.chance(2) {
+            Endian::Le
+        } else {
+            Endian::Be
+        }
+    }
+}
+
+fn put16(page: &mut [u8], at: usize, v: u16, e: Endian) {
+    let b = match e {
+        Endian::Le => v.to_le_bytes(),
+        Endian::Be => v.to_be_bytes(),
+    };
+    page[at..at + 2].copy_from_slice(&b);
+}
+
+fn put32(page: &mut [u8], at: usize, v: u32, e: Endian) {
+    let b = match e {
+        Endian::Le => v.to_le_bytes(),
+        Endian::Be => v.to_be_bytes(),
+    };
+    page[at..at + 4].copy_from_slice(&b);
+}
+
+/// A page that looks like a btree page: plausible header fields, a slot array whose
+/// offsets mostly point into the page, and items of every type with random lengths.
+fn random_page(g: &mut Gen, ps: usize, pgno: u32, pages: u32, e: Endian) -> Vec<u8> {
+    let mut page = g.bytes(ps);
+    let page_type = [3u8, 5, 5, 5, 6, 7, 0, 9, g.next() as u8][g.below(9)];
+    let max_entries = if g.chance(4) { u16::MAX as u64 } else { 40 };
+    let entries = g.edgy(max_entries) as u16;
+    put32(&mut page, 0, g.next() as u32, e);
+    put32(&mut page, 4, g.next() as u32, e);
+    put32(
+        &mut page,
+        8,
+        if g.chance(8) { g.next() as u32 } else { pgno },
+        e,
+    );
+    for at in [12, 16] {
+        put32(&mut page, at, g.edgy(pages as u64 + 2) as u32, e);
+    }
+    put16(&mut page, 20, entries, e);
+    put16(&mut page, 22, g.edgy(ps as u64 + 8) as u16, e);
+    page[24] = [0, 1, 2, g.next() as u8][g.below(4)];
+    page[25] = page_type;
+    let slots = (entries as usize).min((ps - 26) / 2);
+    for i in 0..slots {
+        let off = if g.chance(16) {
+            g.next() as u16
+        } else {
+            (26 + g.below(ps - 26)) as u16
+        };
+        put16(&mut page, 26 + i * 2, off, e);
+        let off = off as usize;
+        if off + 12 <= ps {
+            let kind = [1u8, 1, 1, 3, 2, 5, 0x81, 0x83, g.next() as u8][g.below(9)];
+            put16(&mut page, off, g.edgy(ps as u64 * 2) as u16, e);
+            page[off + 2] = kind;
+            if kind & 0x7f == 3 {
+                put32(&mut page, off + 4, g.edgy(pages as u64 + 1) as u32, e);
+                put32(&mut page, off + 8, g.edgy(ps as u64 * 4) as u32, e);
+            }
+        }
+    }
+    page
+}
+
+/// An image of random pages behind a meta page whose fields may or may not hold up.
+fn random_image(g: &mut Gen) -> (Vec<u8>, usize, Endian) {
+    let ps = g.page_size();
+    let e = g.endian();
+    let pages = 1 + g.below(8) as u32;
+    let mut image = Vec::with_capacity(ps * pages as usize);
+    let mut meta = vec![0u8; ps];
+    put32(&mut meta, 12, 0x0005_3162, e);
+    put32(&mut meta, 16, [9, 10, g.next() as u32][g.below(3)], e);
+    put32(
+        &mut meta,
+        20,
+        if g.chance(8) {
+            g.next() as u32
+        } else {
+            ps as u32
+        },
+        e,
+    );
+    meta[25] = 9;
+    put32(&mut meta, 28, g.edgy(pages as u64) as u32, e);
+    put32(&mut meta, 32, g.edgy(pages as u64 + 2) as u32, e);
+    put32(&mut meta, 48, [0, 0x20, g.next() as u32][g.below(3)], e);
+    put32(&mut meta, 88, g.edgy(pages as u64) as u32, e);
+    image.extend(meta);
+    for pgno in 1..pages {
+        image.extend(random_page(g, ps, pgno, pages, e));
+    }
+    if g.chance(8) {
+        image.truncate(image.len() - g.below(ps));
+    }
+    (image, ps, e)
+}
+
+/// Whether `inner` lies within `outer` in memory.
+fn within(outer: &[u8], inner: &[u8]) -> bool {
+    let outer = outer.as_ptr_range();
+    let inner = inner.as_ptr_range();
+    outer.start <= inner.start && inner.end <= outer.end
+}
+
+#[test]
+fn page_headers_read_the_fields_they_claim() {
+    let mut g = Gen(0x9e37_79b9_7f4a_7c15);
+    for case in 0..5_000 {
+        let e = g.endian();
+        let len = if g.chance(4) { g.below(40) } else { 512 };
+        let page = g.bytes(len);
+        match parse_page_header(&page, e) {
+            Ok(hdr) => {
+                assert!(len >= 26, "case {case}");
+                assert_eq!(hdr.entries, u16e(e, &page[20..22]), "case {case}");
+                assert_eq!(hdr.kind().code(), page[25], "case {case}");
+                assert_eq!(
+                    hdr.lsn >> 32,
+                    u64::from(u32e(e, &page[0..4])),
+                    "case {case}"
+                );
+            }
+            Err(_) => assert!(len < 26, "case {case}"),
+        }
+        for protection in [PageProtection::Checksum, PageProtection::Encrypted] {
+            let ok = PageHeader::parse(&page, e.into(), protection).is_ok();
+            assert_eq!(ok, len >= protection.header_size(), "case {case}");
+        }
+    }
+}
+
+#[test]
+fn leaf_items_stay_within_the_page() {
+    let mut g = Gen(0x0123_4567_89ab_cdef);
+    for case in 0..20_000 {
+        let ps = g.page_size();
+        let e = g.endian();
+        let page = random_page(&mut g, ps, 1, 4, e);
+        let off = match g.below(4) {
+            0 => usize::MAX - g.below(16),
+            1 => g.below(ps + 16),
+            _ => 26 + g.below(ps - 26),
+        };
+        if let Ok(entry) = parse_leaf_entry(&page, off, e) {
+            match entry.item() {
+                LeafItem::KeyData(bytes) => {
+                    assert!(within(&page, bytes), "case {case}");
+                    assert_eq!(bytes.len(), u16e(e, &page[off..off + 2]) as usize);
+                    assert_eq!(bytes.as_ptr(), page[off + 3..].as_ptr(), "case {case}");
+                }
+                LeafItem::Overflow { .. } => assert!(off + 12 <= page.len(), "case {case}"),
+            }
+        }
+        // The page is the whole image, so no field can be longer than it.
+        if let Ok(item) = read_leaf_item(&page, ps, e, PageProtection::None, &page, off) {
+            assert!(item.len() <= ps, "case {case}");
+        }
+    }
+}
+
+#[test]
+fn leaf_slots_report_what_the_page_holds() {
+    let mut g = Gen(0xdead_beef_cafe_f00d);
+    for case in 0..10_000 {
+        let ps = g.page_size();
+        let e = g.endian();
+        let mut page = random_page(&mut g, ps, 1, 4, e);
+        page[25] = 5;
+        let entries = u16e(e, &page[20..22]) as usize;
+        let protection = [
+            PageProtection::None,
+            PageProtection::Checksum,
+            PageProtection::Encrypted,
+        ][g.below(3)];
+        let room = (ps - protection.header_size()) / 2;
+        let slots: Vec<_> = leaf_slots(&page, e, protection).unwrap().collect();
+        assert!(slots.len() <= entries.min(room), "case {case}");
+        for (i, slot) in slots.iter().enumerate() {
+            assert_eq!(slot.index, i, "case {case}");
+            assert_eq!(
+                slot.kind.is_some(),
+                slot.offset + 2 < page.len(),
+                "case {case}"
+            );
+            if let Some(bytes) = slot.bytes(&page) {
+                assert!(within(&page, bytes), "case {case}");
+                assert_eq!(Some(bytes.len()), slot.len, "case {case}");
+            }
+            if slot.kind == Some(LeafItemKind::Overflow) {
+                assert_eq!(slot.len, Some(12), "case {case}");
+            }
+        }
+        let lower = g.next() as u16;
+        assert!(
+            iter_slots(&page, e, protection, lower).count() <= room,
+            "case {case}"
+        );
+    }
+}
+
+#[test]
+fn slot_validation_takes_any_header_fields() {
+    let mut g = Gen(0x5151_7a7a_0101_fefe);
+    for case in 0..10_000 {
+        let ps = g.page_size();
+        let e = g.endian();
+        let page = random_page(&mut g, ps, 1, 4, e);
+        let len = if g.chance(8) { g.below(ps) } else { ps };
+        let page = &page[..len];
+        let slots_start = [26, 27, 32, 64, g.below(ps + 8), usize::MAX - g.below(4)][g.below(6)];
+        let entries = g.edgy_or_huge(64) as usize;
+        let hf_offset = g.edgy_or_huge(ps as u64) as usize;
+        let page_type = PageType::from([3u8, 4, 5, 6, g.next() as u8][g.below(5)]);
+        let report = validate_slot_array(
+            page,
+            1,
+            page_type,
+            slots_start,
+            entries,
+            hf_offset,
+            Endianness::from(e),
+        );
+        assert!(report.offsets.len() <= entries, "case {case}");
+        let room = len.saturating_sub(slots_start) / 2;
+        assert_eq!(report.offsets.len(), entries.min(room), "case {case}");
+        for (slot, &off) in report.offsets.iter().enumerate() {
+            let at = slots_start + slot * 2;
+            assert_eq!(off, u16e(e, &page[at..at + 2]) as usize, "case {case}");
+        }
+    }
+}
+
+#[test]
+fn leaf_pairs_name_the_page_they_were_read_from() {
+    let mut g = Gen(0x1357_9bdf_2468_ace0);
+    for case in 0..1_000 {
+        let (image, ps, e) = random_image(&mut g);
+        let pages = image.len() / ps;
+        for pgno in 0..pages as u32 {
+            let page = page_slice(&image, ps, pgno).unwrap();
+            assert_eq!(page.len(), ps);
+            let Ok(hdr) = parse_page_header(page, e) else {
+                continue;
+            };
+            for mode in [ParseMode::Lenient, ParseMode::Strict] {
+                let Ok(pairs) = leaf_pairs_on_page_checked(
+                    "gen",
+                    &image,
+                    ps,
+                    e,
+                    PageProtection::None,
+                    page,
+                    &hdr,
+                    mode,
+                ) else {
+                    continue;
+                };
+                assert_eq!(hdr.kind(), PageType::BtreeLeaf, "case {case}");
+                assert!(pairs.pairs.len() <= hdr.entries as usize / 2, "case {case}");
+                for (_, _, prov) in &pairs.pairs {
+                    assert_eq!(prov.page_no, hdr.pgno, "case {case}");
+                }
+                if mode == ParseMode::Strict {
+                    assert!(pairs.skipped.is_empty(), "case {case}");
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn overflow_values_have_the_referenced_length() {
+    let mut g = Gen(0x0f0f_f0f0_3c3c_c3c3);
+    for case in 0..5_000 {
+        let (image, ps, e) = random_image(&mut g);
+        let pages = image.len().div_ceil(ps) as u64;
+        let reference = OverflowRef {
+            first_page: g.edgy(pages + 1) as u32,
+            total_len: if g.chance(8) {
+                u32::MAX - g.below(4) as u32
+            } else {
+                g.edgy(ps as u64 * 3) as u32
+            },
+        };
+        let protection = [
+            PageProtection::None,
+            PageProtection::Checksum,
+            PageProtection::Encrypted,
+        ][g.below(3)];
+        let read = read_overflow(&image, ps, e, protection, reference);
+        let mut sink = Vec::new();
+        let streamed = read_overflow_into(&image, ps, e, protection, reference, &mut sink);
+        match read {
+            Ok(value) => {
+                assert_eq!(value.len(), reference.total_len as usize, "case {case}");
+                assert!(streamed.is_ok(), "case {case}");
+                assert_eq!(sink, value, "case {case}");
+            }
+            Err(_) => assert!(streamed.is_err(), "case {case}"),
+        }
+    }
+}
+
+#[test]
+fn compact_sizes_and_wallet_keys_split_within_the_input() {
+    let mut g = Gen(0x7777_1111_3333_9999);
+    for case in 0..20_000 {
+        let n = match g.below(4) {
+            0 => g.edgy(0xfc),
+            1 => g.edgy(0xffff),
+            2 => g.edgy(0xffff_ffff),
+            _ => g.next(),
+        };
+        let mut out = Vec::new();
+        write_compact_size(&mut out, n);
+        assert_eq!(read_compact_size(&out), Some((n, out.len())), "case {case}");
+
+        let len = g.below(48);
+        let key = g.bytes(len);
+        if let Some((len, used)) = read_compact_size(&key) {
+            assert!(
+                [1, 3, 5, 9].contains(&used) && used <= key.len(),
+                "case {case}"
+            );
+            assert!(used == 1 || len > 0xfc || key[0] < 0xfd, "case {case}");
+        }
+        if let Some((tag, rest)) = split_walletdb_key(&key) {
+            assert!(
+                within(&key, tag.as_bytes()) && within(&key, rest),
+                "case {case}"
+            );
+            assert!(tag.len() + rest.len() < key.len(), "case {case}");
+            assert!(key.ends_with(rest), "case {case}");
+        }
+    }
+}
+
+#[test]
+fn meta_pages_that_parse_are_consistent() {
+    let mut g = Gen(0xa5a5_5a5a_1234_4321);
+    for case in 0..5_000 {
+        let (image, ..) = random_image(&mut g);
+        let head = &image[..image
+            .len()
+            .min(if g.chance(4) { g.below(600) } else { 512 })];
+        if let Ok(meta) = parse_btree_meta_page0(head) {
+            assert!(head.len() >= 512, "case {case}");
+            assert!(is_valid_page_size(meta.pagesize as usize), "case {case}");
+            assert_eq!(meta.magic, 0x0005_3162, "case {case}");
+        }
+    }
+}
+
+/// Every whole-image analysis on one reader. Iterators are capped so a walk that never
+/// ends fails the test instead of hanging it.
+fn exercise(reader: &FileDbImageReader, page_count: u64, case: usize) {
+    for salvage in [SalvageMode::Conservative, SalvageMode::BestEffort] {
+        for (key, value, prov) in reader.entries(salvage).take(10_000) {
+            assert!((prov.page_no as u64) < page_count, "case {case}");
+            let _ = (key, value.materialize());
+        }
+    }
+    let _ = check(reader);
+    let _ = collect_stats(reader, 4);
+    let _ = analyze_reachability(reader);
+    let _ = walk_freelist(reader.source().as_ref());
+    let lsns = analyze_lsns(reader, None);
+    assert_eq!(
+        lsns.pages + lsns.unreadable_pages,
+        page_count,
+        "case {case}"
+    );
+}
+
+#[test]
+fn readers_survive_generated_images() {
+    let mut g = Gen(0xfeed_face_0bad_c0de);
+    for case in 0..1_000 {
+        let (mut image, ps, _) = random_image(&mut g);
+        image.truncate(image.len() / ps * ps);
+        if image.is_empty() {
+            continue;
+        }
+        let page_count = (image.len() / ps) as u64;
+        let Ok(source) = MemoryPageSource::new(image, ps as u32, "gen") else {
+            continue;
+        };
+        assert!(source.read_page(u32::MAX - g.below(4) as u32).is_err());
+        if let Ok(reader) = FileDbImageReader::from_source(Arc::new(source)) {
+            exercise(&reader, page_count, case);
+        }
+    }
+}
+
+#[test]
+fn readers_survive_mutated_builder_images() {
+    let mut builder = FixtureBuilder::new().page_size(512);
+    for i in 0u32..60 {
+        builder =
+            builder.wallet_record("key", &i.to_be_bytes(), vec![i as u8; 20 + 17 * i as usize]);
+    }
+    let clean = builder
+        .deleted_record(b"gone".to_vec(), vec![1; 900])
+        .build()
+        .unwrap();
+    let mut g = Gen(0x0ddb_a11c_afe0_0001);
+    for case in 0..400 {
+        let mut image = clean.clone();
+        for _ in 0..1 + g.below(12) {
+            let at = g.below(image.len());
+            image[at] = match g.below(4) {
+                0 => 0,
+                1 => 0xff,
+                _ => g.next() as u8,
+            };
+        }
+        let page_count = (image.len() / 512) as u64;
+        let source = MemoryPageSource::new(image, 512, "mutated").unwrap();
+        let Ok(reader) = FileDbImageReader::from_source(Arc::new(source)) else {
+            continue;
+        };
+        exercise(&reader, page_count, case);
+    }
+}
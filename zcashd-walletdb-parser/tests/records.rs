@@ -0,0 +1,1451 @@
+//! Record classification and decoding against the shipped zcashd fixtures.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use zcashd_walletdb_parser::{
+    crypto::sha256::sha256d,
+    entry::parser::{split_walletdb_key, walletdb_key_prefix},
+    parser::{
+        decoders::{
+            accounting::{Account, AccountingEntry},
+            address_book::{
+                AddressBook, AddressKind, AddressName, AddressPurpose, DestData, Purpose,
+            },
+            encryption::{DerivationMethod, MasterKey},
+            hd_chain::MnemonicHdChain,
+            keymeta::{KeyMetadata, TransparentKeyMetadata},
+            locator::BlockLocator,
+            orchard::{OrchardNoteCommitmentTree, TreeAddress},
+            sapling::{
+                EXTENDED_KEY_LEN, EncryptedSaplingKey, SaplingAddressIvk, SaplingSpendingKey,
+                SaplingWatchOnlyKey,
+            },
+            scalars::{ClientVersion, DefaultKey, NU5_WALLET_VERSION},
+            script::{RedeemScript, ScriptKind, WatchScript},
+            seed::{EncryptedHdSeed, EncryptedMnemonicPhrase, HdSeed, Language, MnemonicPhrase},
+            sprout::{EncryptedSproutKey, SproutKeyMetadata, SproutSpendingKey, SproutViewingKey},
+            transparent::{EncryptedKey, TransparentKey, WalletKey},
+            unified::{
+                ReceiverType, UnifiedAccount, UnifiedAddressMetadata, UnifiedFullViewingKey,
+            },
+            wallet_tx::{JsOutPoint, NoteOutPoint, WalletTx},
+        },
+        key::{DefaultClassifier, RecordKey},
+        record::{
+            DecodeContext, DecodeError, DecodeResult, RecordClassifier, RecordDecoder, RecordKind,
+        },
+        registry::{DecoderRegistry, default_registry},
+    },
+    storage::walletdb::WalletDb,
+};
+
+const WALLETS: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../dat_files");
+
+fn fixture(n: usize) -> WalletDb {
+    WalletDb::open(format!("{WALLETS}/wallet{n}.dat")).unwrap()
+}
+
+#[test]
+fn record_kinds_round_trip_through_their_tags() {
+    for &kind in RecordKind::ALL {
+        assert_eq!(RecordKind::from_tag(kind.tag().unwrap()), kind);
+    }
+    assert_eq!(RecordKind::Unknown.tag(), None);
+    assert_eq!(
+        RecordKind::from_tag("walletdescriptor"),
+        RecordKind::Unknown
+    );
+}
+
+#[test]
+fn every_fixture_record_has_a_known_kind() {
+    for n in 0..8 {
+        for record in fixture(n).entries() {
+            let (key, _, _) = record.unwrap();
+            let (tag, _) = split_walletdb_key(&key).unwrap();
+            assert_ne!(RecordKind::from_tag(tag), RecordKind::Unknown, "tag {tag}");
+        }
+    }
+}
+
+#[test]
+fn every_fixture_key_classifies_into_a_typed_key() {
+    for n in 0..8 {
+        for record in fixture(n).entries() {
+            let (key, _, _) = record.unwrap();
+            let (kind, parsed) = DefaultClassifier.classify(&key);
+            match (kind, parsed) {
+                (_, Some(RecordKey::Raw(rest))) => panic!("{kind} key left raw: {rest:02x?}"),
+                (RecordKind::Name | RecordKind::Purpose, Some(RecordKey::Address(a))) => {
+                    assert!(a.starts_with("tm"), "{a}")
+                }
+                (RecordKind::Key | RecordKind::KeyMeta, Some(RecordKey::PubKey(k))) => {
+                    assert_eq!(k.len(), 33)
+                }
+                (RecordKind::Tx, Some(RecordKey::TxId(_)))
+                | (RecordKind::Pool, Some(RecordKey::PoolIndex(_)))
+                | (RecordKind::MnemonicPhrase, Some(RecordKey::SeedFingerprint(_))) => {}
+                (kind, Some(key)) => panic!("{kind} key decoded as {key:?}"),
+                (_, None) => assert_eq!(key, walletdb_key_prefix(&kind.to_string())),
+            }
+        }
+    }
+}
+
+#[test]
+fn pair_keys_decode_and_malformed_keys_stay_raw() {
+    let mut key = walletdb_key_prefix("destdata");
+    key.extend_from_slice(b"\x03t1a\x04used");
+    assert_eq!(
+        DefaultClassifier.classify(&key),
+        (
+            RecordKind::DestData,
+            Some(RecordKey::DestData {
+                address: "t1a".into(),
+                key: "used".into()
+            })
+        )
+    );
+    assert_eq!(
+        DefaultClassifier.classify(&key).1.unwrap().to_string(),
+        "t1a/used"
+    );
+
+    let mut key = walletdb_key_prefix("tx");
+    key.extend_from_slice(&[0xab; 31]);
+    assert_eq!(
+        DefaultClassifier.classify(&key),
+        (RecordKind::Tx, Some(RecordKey::Raw(vec![0xab; 31])))
+    );
+
+    let key = walletdb_key_prefix("version");
+    assert_eq!(
+        DefaultClassifier.classify(&key),
+        (RecordKind::Version, None)
+    );
+    assert_eq!(
+        DefaultClassifier.classify(&[0x05, b'x']),
+        (RecordKind::Unknown, Some(RecordKey::Raw(vec![0x05, b'x'])))
+    );
+}
+
+struct VersionDecoder;
+
+impl RecordDecoder for VersionDecoder {
+    type Item = i32;
+
+    fn decode(
+        &self,
+        _ctx: &DecodeContext,
+        _key: Option<&RecordKey>,
+        raw_value: &[u8],
+    ) -> DecodeResult<i32> {
+        let bytes = raw_value
+            .try_into()
+            .map_err(|_| DecodeError::new("version: not 4 bytes"))?;
+        Ok(i32::from_le_bytes(bytes))
+    }
+
+    fn name(&self) -> &'static str {
+        "version"
+    }
+}
+
+#[test]
+fn registry_decodes_through_erased_decoders() {
+    let mut registry = DecoderRegistry::new();
+    assert!(
+        registry
+            .decode(
+                RecordKind::Version,
+                &DecodeContext::default(),
+                None,
+                &[0; 4]
+            )
+            .is_none()
+    );
+    registry.register(RecordKind::Version, VersionDecoder);
+    assert_eq!(registry.kinds().collect::<Vec<_>>(), [RecordKind::Version]);
+    assert_eq!(registry.get(RecordKind::Version).unwrap().name(), "version");
+
+    let value = 6_020_050i32.to_le_bytes();
+    let item = registry
+        .decode(RecordKind::Version, &DecodeContext::default(), None, &value)
+        .unwrap()
+        .unwrap();
+    assert_eq!(item.as_any().downcast_ref::<i32>(), Some(&6_020_050));
+    assert_eq!(
+        registry
+            .decode_as::<i32>(RecordKind::Version, &DecodeContext::default(), None, &value)
+            .unwrap()
+            .unwrap(),
+        6_020_050
+    );
+    let wrong =
+        registry.decode_as::<u64>(RecordKind::Version, &DecodeContext::default(), None, &value);
+    assert!(wrong.unwrap().unwrap_err().message.contains("u64"));
+    assert!(
+        registry
+            .decode(
+                RecordKind::Version,
+                &DecodeContext::default(),
+                None,
+                &[0; 3]
+            )
+            .unwrap()
+            .is_err()
+    );
+
+    let entry = registry.decode_entry(
+        &DecodeContext::default(),
+        &walletdb_key_prefix("version"),
+        &value,
+    );
+    assert_eq!((entry.kind, entry.key), (RecordKind::Version, None));
+    assert!(entry.value.unwrap().is_ok());
+    let entry = registry.decode_entry(
+        &DecodeContext::default(),
+        &walletdb_key_prefix("minversion"),
+        &value,
+    );
+    assert!(entry.value.is_none());
+}
+
+#[test]
+fn name_records_decode_into_labelled_addresses() {
+    let registry = default_registry();
+    let mut names = 0;
+    for n in 0..8 {
+        for record in fixture(n).entries() {
+            let (key, value, _) = record.unwrap();
+            let entry = registry.decode_entry(&DecodeContext::default(), &key, &value);
+            if entry.kind != RecordKind::Name {
+                continue;
+            }
+            let item = entry.value.unwrap().unwrap();
+            let name = item.as_any().downcast_ref::<AddressName>().unwrap();
+            assert_eq!(name.kind(), AddressKind::Transparent);
+            assert_eq!(name.label, "");
+            names += 1;
+        }
+    }
+    assert_eq!(names, 8);
+
+    let address = "zs1z7rejlpsa98s2rrrfkwmaxu53e4ue0ulcrw0h4x5g8jl04tak0d3mm47vdtahatqrlkngh9slya";
+    let key = RecordKey::Address(address.into());
+    let name = registry
+        .decode_as::<AddressName>(
+            RecordKind::Name,
+            &DecodeContext::default(),
+            Some(&key),
+            b"\x07savings",
+        )
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        (name.kind(), name.label.as_str()),
+        (AddressKind::Sapling, "savings")
+    );
+    assert!(name.kind().is_shielded());
+    assert!(
+        registry
+            .decode(RecordKind::Name, &DecodeContext::default(), None, b"\x00")
+            .unwrap()
+            .is_err()
+    );
+    assert_eq!(AddressKind::of("u1qqqq"), AddressKind::Unified);
+    assert_eq!(
+        AddressKind::of(
+            "zcU1Cd6zYyZCd2VJF8yKgmzjxdiiU1rgTTjEwoN1CGUWCziPkUTXUjXmX7TMqdMNsTfuiGN1jQoVN4kGxUR4sAPN4XZ7pxb"
+        ),
+        AddressKind::Sprout
+    );
+}
+
+#[test]
+fn address_book_joins_names_and_purposes() {
+    let registry = default_registry();
+    for n in 0..8 {
+        let mut book = AddressBook::new();
+        for record in fixture(n).entries() {
+            let (key, value, _) = record.unwrap();
+            let entry = registry.decode_entry(&DecodeContext::default(), &key, &value);
+            let Some(item) = entry.value else { continue };
+            let item = item.unwrap().into_any();
+            match entry.kind {
+                RecordKind::Name => book.insert_name(*item.downcast::<AddressName>().unwrap()),
+                RecordKind::Purpose => {
+                    book.insert_purpose(*item.downcast::<AddressPurpose>().unwrap())
+                }
+                _ => {}
+            }
+        }
+        assert_eq!(book.len(), 1);
+        assert_eq!(book.incomplete().count(), 0);
+        let entry = book.entries.values().next().unwrap();
+        assert_eq!(entry.purpose, Some(Purpose::Receive));
+    }
+
+    let mut book = AddressBook::new();
+    book.insert_purpose(AddressPurpose {
+        address: "t1a".into(),
+        purpose: Purpose::parse("refund"),
+    });
+    assert_eq!(book.incomplete().collect::<Vec<_>>(), ["t1a"]);
+    assert_eq!(
+        book.get("t1a").unwrap().purpose.as_ref().unwrap().as_str(),
+        "refund"
+    );
+
+    let mut key = walletdb_key_prefix("destdata");
+    key.extend_from_slice(b"\x03t1b\x04used");
+    let item = registry
+        .decode_entry(&DecodeContext::default(), &key, b"\x01p")
+        .value
+        .unwrap()
+        .unwrap();
+    book.insert_dest_data(*item.into_any().downcast::<DestData>().unwrap());
+    assert!(book.get("t1b").unwrap().is_used());
+    assert_eq!(book.incomplete().count(), 1);
+}
+
+#[test]
+fn key_records_decode_into_checked_key_pairs() {
+    let registry = default_registry();
+    let mut keys = 0;
+    let mut sample = None;
+    for n in 0..8 {
+        for record in fixture(n).entries() {
+            let (key, value, _) = record.unwrap();
+            let entry = registry.decode_entry(&DecodeContext::default(), &key, &value);
+            if entry.kind != RecordKind::Key {
+                continue;
+            }
+            let item = entry.value.unwrap().unwrap().into_any();
+            let pair = item.downcast::<TransparentKey>().unwrap();
+            assert!(pair.private_key.is_compressed());
+            assert!(pair.checksum.is_some());
+            assert!(!format!("{pair:?}").contains(&hex::encode(pair.private_key.secret.expose())));
+            keys += 1;
+            sample.get_or_insert((entry.key.unwrap(), value));
+        }
+    }
+    assert_eq!(keys, 216);
+
+    // A flipped checksum bit, and the same key filed under another public key.
+    let (key, mut value) = sample.unwrap();
+    *value.last_mut().unwrap() ^= 1;
+    let err = registry
+        .decode(
+            RecordKind::Key,
+            &DecodeContext::default(),
+            Some(&key),
+            &value,
+        )
+        .unwrap();
+    assert!(err.unwrap_err().message.contains("checksum"));
+    *value.last_mut().unwrap() ^= 1;
+    let RecordKey::PubKey(mut other) = key.clone() else {
+        unreachable!()
+    };
+    other[1] ^= 1;
+    let err = registry.decode(
+        RecordKind::Key,
+        &DecodeContext::default(),
+        Some(&RecordKey::PubKey(other)),
+        &value,
+    );
+    assert!(err.unwrap().unwrap_err().message.contains("embeds"));
+
+    // The same private key wrapped as a `wkey`.
+    let RecordKey::PubKey(pubkey) = &key else {
+        unreachable!()
+    };
+    let der_len = value.len() - 32;
+    let mut wkey = 1i32.to_le_bytes().to_vec();
+    wkey.extend_from_slice(&value[..der_len]);
+    wkey.extend_from_slice(&1_500_000_000i64.to_le_bytes());
+    wkey.extend_from_slice(&0i64.to_le_bytes());
+    wkey.extend_from_slice(b"\x03old");
+    let wallet_key = registry
+        .decode_as::<WalletKey>(
+            RecordKind::WKey,
+            &DecodeContext::default(),
+            Some(&key),
+            &wkey,
+        )
+        .unwrap()
+        .unwrap();
+    assert_eq!(&wallet_key.pubkey, pubkey);
+    assert_eq!(
+        (wallet_key.created, wallet_key.comment.as_str()),
+        (1_500_000_000, "old")
+    );
+}
+
+#[test]
+fn ckey_records_keep_pubkey_and_ciphertext() {
+    let registry = default_registry();
+    let pubkey = [[0x02].as_slice(), &[0x11; 32]].concat();
+    let key = RecordKey::PubKey(pubkey.clone());
+    let mut value = vec![48];
+    value.extend_from_slice(&[0xcc; 48]);
+    let ckey = registry
+        .decode_as::<EncryptedKey>(
+            RecordKind::CKey,
+            &DecodeContext::default(),
+            Some(&key),
+            &value,
+        )
+        .unwrap()
+        .unwrap();
+    assert_eq!(ckey.pubkey, pubkey);
+    assert_eq!(ckey.encrypted_secret, [0xcc; 48]);
+    assert!(ckey.is_compressed());
+
+    let mut short = vec![20];
+    short.extend_from_slice(&[0xcc; 20]);
+    let err = registry
+        .decode(
+            RecordKind::CKey,
+            &DecodeContext::default(),
+            Some(&key),
+            &short,
+        )
+        .unwrap();
+    assert!(err.unwrap_err().message.contains("AES blocks"));
+}
+
+#[test]
+fn mkey_records_decode_into_master_keys() {
+    let registry = default_registry();
+    let mut value = vec![48];
+    value.extend_from_slice(&[0xee; 48]);
+    value.push(8);
+    value.extend_from_slice(&[0x5a; 8]);
+    value.extend_from_slice(&0u32.to_le_bytes());
+    value.extend_from_slice(&185_000u32.to_le_bytes());
+    value.push(0);
+    let key = RecordKey::MasterKeyId(1);
+    let mkey = registry
+        .decode_as::<MasterKey>(
+            RecordKind::MKey,
+            &DecodeContext::default(),
+            Some(&key),
+            &value,
+        )
+        .unwrap()
+        .unwrap();
+    assert_eq!((mkey.id, mkey.derive_iterations), (1, 185_000));
+    assert_eq!(mkey.derivation_method, DerivationMethod::Sha512);
+    assert_eq!((mkey.salt.len(), mkey.encrypted_key.len()), (8, 48));
+    assert!(mkey.other_params.is_empty());
+    assert!(mkey.to_string().contains("iterations   : 185000"));
+
+    value.pop();
+    assert!(
+        registry
+            .decode(
+                RecordKind::MKey,
+                &DecodeContext::default(),
+                Some(&key),
+                &value
+            )
+            .unwrap()
+            .is_err()
+    );
+}
+
+#[test]
+fn keymeta_records_carry_hd_paths_and_birth_times() {
+    let registry = default_registry();
+    let mut seeds = std::collections::BTreeSet::new();
+    let mut metas = 0;
+    for n in 0..8 {
+        for record in fixture(n).entries() {
+            let (key, value, _) = record.unwrap();
+            let entry = registry.decode_entry(&DecodeContext::default(), &key, &value);
+            if entry.kind != RecordKind::KeyMeta {
+                continue;
+            }
+            let item = entry.value.unwrap().unwrap().into_any();
+            let meta = item.downcast::<TransparentKeyMetadata>().unwrap();
+            assert_eq!(meta.metadata.version, 10);
+            assert!(meta.metadata.create_time > 1_700_000_000);
+            assert!(meta.metadata.is_hd());
+            assert!(meta.metadata.hd_keypath.unwrap().starts_with("m/44'/1'/"));
+            seeds.insert(meta.metadata.seed_fingerprint.unwrap());
+            metas += 1;
+        }
+    }
+    assert_eq!((metas, seeds.len()), (216, 8));
+
+    // A basic, pre-HD entry stops after the creation time.
+    let mut value = 1i32.to_le_bytes().to_vec();
+    value.extend_from_slice(&1_400_000_000i64.to_le_bytes());
+    let key = RecordKey::PubKey(vec![0x02; 33]);
+    let meta = registry
+        .decode_as::<TransparentKeyMetadata>(
+            RecordKind::KeyMeta,
+            &DecodeContext::default(),
+            Some(&key),
+            &value,
+        )
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        meta.metadata,
+        KeyMetadata {
+            version: 1,
+            create_time: 1_400_000_000,
+            hd_keypath: None,
+            seed_fingerprint: None,
+        }
+    );
+    assert!(!meta.metadata.is_hd());
+}
+
+#[test]
+fn scalar_records_report_versions_and_ordering() {
+    let registry = default_registry();
+    for n in 0..8 {
+        let db = fixture(n);
+        let mut versions = Vec::new();
+        for record in db.entries() {
+            let (key, value, _) = record.unwrap();
+            let entry = registry.decode_entry(&DecodeContext::default(), &key, &value);
+            let Some(item) = entry.value else { continue };
+            let item = item.unwrap();
+            let any = item.as_any();
+            match entry.kind {
+                RecordKind::Version | RecordKind::MinVersion => {
+                    versions.push((entry.kind, *any.downcast_ref::<ClientVersion>().unwrap()))
+                }
+                RecordKind::DefaultKey => {
+                    assert_eq!(any.downcast_ref::<DefaultKey>().unwrap().pubkey.len(), 33)
+                }
+                RecordKind::OrderPosNext => assert!(*any.downcast_ref::<i64>().unwrap() > 0),
+                RecordKind::WitnessCacheSize => {
+                    assert_eq!(*any.downcast_ref::<i64>().unwrap(), 100)
+                }
+                _ => {}
+            }
+        }
+        versions.sort();
+        let [
+            (RecordKind::Version, version),
+            (RecordKind::MinVersion, min),
+        ] = versions[..]
+        else {
+            panic!("{versions:?}")
+        };
+        assert!(min < version);
+        assert_eq!(min, ClientVersion(60_000));
+        let records = db.entries().map(|record| {
+            let (key, value, _) = record.unwrap();
+            (key, value)
+        });
+        let ctx = DecodeContext::scan(records);
+        assert_eq!((ctx.version, ctx.min_version), (Some(version), Some(min)));
+    }
+
+    assert_eq!(ClientVersion(5_060_050).to_string(), "v5.6.0");
+    assert_eq!(ClientVersion(6_000_025).to_string(), "v6.0.0-rc1");
+    assert_eq!(ClientVersion(4_070_003).to_string(), "v4.7.0-beta4");
+    assert_eq!(ClientVersion(2_001_152).to_string(), "v2.0.11-2");
+}
+
+#[test]
+fn tx_records_decode_into_wallet_transactions() {
+    let registry = default_registry();
+    let mut txs = 0;
+    for n in 0..8 {
+        for record in fixture(n).entries() {
+            let (key, value, _) = record.unwrap();
+            let entry = registry.decode_entry(&DecodeContext::default(), &key, &value);
+            if entry.kind != RecordKind::Tx {
+                continue;
+            }
+            let item = entry.value.unwrap().unwrap().into_any();
+            let wtx = item.downcast::<WalletTx>().unwrap();
+            assert_eq!((wtx.tx().version, wtx.tx().overwintered), (4, true));
+            assert!(wtx.tx().is_coinbase() && wtx.merkle_tx.is_mined());
+            assert!(wtx.order_pos().is_some() && wtx.time_smart().is_some());
+            assert!(wtx.sapling_note_data.is_empty() && wtx.orchard_meta.is_none());
+            txs += 1;
+        }
+    }
+    assert_eq!(txs, 200);
+}
+
+/// The wallet fields of a `CWalletTx` after its transaction, with one `mapValue` entry,
+/// `sprout` and `sapling` note data entries as given.
+fn wallet_tx_tail(sprout: &[u8], sapling: Option<&[u8]>) -> Vec<u8> {
+    let mut out = vec![0x22; 32]; // block hash
+    out.extend_from_slice(&[0, 3, 0, 0, 0, 0]); // no merkle branch, index 3, no vtxPrev
+    out.extend_from_slice(b"\x01\x01n\x0242");
+    out.extend_from_slice(sprout);
+    out.push(0); // vOrderForm
+    out.extend_from_slice(&0u32.to_le_bytes());
+    out.extend_from_slice(&1_700_000_000u32.to_le_bytes());
+    out.extend_from_slice(&[1, 0]); // fFromMe, fSpent
+    if let Some(sapling) = sapling {
+        out.extend_from_slice(sapling);
+    }
+    out
+}
+
+/// A witness with a left leaf, one filled parent and no cursor.
+fn witness() -> Vec<u8> {
+    let mut out = vec![1];
+    out.extend_from_slice(&[0x33; 32]);
+    out.extend_from_slice(&[0, 2, 1]);
+    out.extend_from_slice(&[0x44; 32]);
+    out.extend_from_slice(&[0, 1]);
+    out.extend_from_slice(&[0x55; 32]);
+    out.push(0);
+    out
+}
+
+#[test]
+fn v5_transactions_decode_sapling_and_orchard_bundles() {
+    let mut tx = (5u32 | 1 << 31).to_le_bytes().to_vec();
+    for word in [0x26a7_270a_u32, 0xc2d6_d0b4, 0, 110] {
+        tx.extend_from_slice(&word.to_le_bytes());
+    }
+    tx.extend_from_slice(&[0, 1]); // no inputs, one output
+    tx.extend_from_slice(&5_000i64.to_le_bytes());
+    tx.extend_from_slice(&[1, 0x51]);
+    tx.push(1); // sapling spend: cv, nullifier, rk
+    tx.extend_from_slice(&[0xa1; 96]);
+    tx.push(1); // sapling output without its proof
+    tx.extend_from_slice(&[0xa2; 756]);
+    tx.extend_from_slice(&(-5_000i64).to_le_bytes());
+    tx.extend_from_slice(&[0xa3; 32]); // anchor
+    tx.extend_from_slice(&[0xa4; 192 + 64 + 192 + 64]); // proofs and signatures
+    tx.push(1); // orchard action
+    tx.extend_from_slice(&[0xb1; 820]);
+    tx.push(3);
+    tx.extend_from_slice(&0i64.to_le_bytes());
+    tx.extend_from_slice(&[0xb2; 32]);
+    tx.push(10);
+    tx.extend_from_slice(&[0xb3; 10 + 64 + 64]);
+
+    let mut sapling = vec![1];
+    sapling.extend_from_slice(&[0x77; 32]);
+    sapling.extend_from_slice(&0u32.to_le_bytes());
+    sapling.extend_from_slice(&1i32.to_le_bytes());
+    sapling.extend_from_slice(&[0x66; 32]); // ivk
+    sapling.push(0); // no nullifier
+    sapling.push(1);
+    sapling.extend_from_slice(&witness());
+    sapling.extend_from_slice(&90i32.to_le_bytes());
+    sapling.extend_from_slice(&1i32.to_le_bytes()); // orchard meta
+    sapling.push(1);
+    sapling.extend_from_slice(&0u32.to_le_bytes());
+    sapling.extend_from_slice(&[0x87; 32]); // dk
+    sapling.extend_from_slice(&[0x88; 32]); // ivk
+    sapling.extend_from_slice(&[1, 0, 0, 0, 0]);
+    let value = [tx, wallet_tx_tail(&[0], Some(&sapling))].concat();
+
+    let key = RecordKey::TxId([0x77; 32]);
+    let wtx = default_registry()
+        .decode_as::<WalletTx>(
+            RecordKind::Tx,
+            &DecodeContext::default(),
+            Some(&key),
+            &value,
+        )
+        .unwrap()
+        .unwrap();
+    let tx = wtx.tx();
+    assert_eq!((tx.version, tx.expiry_height), (5, Some(110)));
+    assert_eq!(tx.transparent_value_out(), 5_000);
+    let sapling = tx.sapling.as_ref().unwrap();
+    assert_eq!(sapling.value_balance, -5_000);
+    assert_eq!(sapling.spends[0].anchor, [0xa3; 32]);
+    assert_eq!(sapling.outputs[0].zkproof, [0xa4; 192]);
+    let orchard = tx.orchard.as_ref().unwrap();
+    assert_eq!((orchard.flags, orchard.proof.len()), (3, 10));
+    assert_eq!(orchard.actions[0].spend_auth_sig, [0xb3; 64]);
+    assert_eq!((wtx.order_pos(), wtx.from_me), (Some(42), true));
+    let (outpoint, note) = &wtx.sapling_note_data[0];
+    assert_eq!((outpoint.n, note.witness_height), (0, 90));
+    assert_eq!(note.witnesses[0].tree.parents, [Some([0x44; 32]), None]);
+    let [cache] = wtx.witness_caches()[..] else {
+        panic!()
+    };
+    assert_eq!((cache.depth, cache.witness_height), (1, 90));
+    assert!(cache.is_witnessed());
+    let meta = wtx.orchard_meta.as_ref().unwrap();
+    assert_eq!(meta.action_data[&0].dk, [0x87; 32]);
+    assert_eq!(meta.action_data[&0].ivk, [0x88; 32]);
+    assert_eq!(meta.actions_spending_my_notes, [0]);
+
+    let truncated = &value[..value.len() - 1];
+    assert!(
+        default_registry()
+            .decode(
+                RecordKind::Tx,
+                &DecodeContext::default(),
+                Some(&key),
+                truncated
+            )
+            .unwrap()
+            .is_err()
+    );
+
+    // Written by a client from before the Orchard wallet, the record ends with the
+    // Sapling note data.
+    let legacy = &value[..value.len() - (4 + 1 + 4 + 64 + 5)];
+    let ctx = DecodeContext {
+        version: Some(ClientVersion(4_060_050)),
+        min_version: None,
+    };
+    assert!(ctx.predates(NU5_WALLET_VERSION));
+    let wtx = default_registry()
+        .decode_as::<WalletTx>(RecordKind::Tx, &ctx, Some(&key), legacy)
+        .unwrap()
+        .unwrap();
+    assert!(wtx.orchard_meta.is_none());
+    assert_eq!(wtx.sapling_note_data.len(), 1);
+    let newest = default_registry().decode(
+        RecordKind::Tx,
+        &DecodeContext::default(),
+        Some(&key),
+        legacy,
+    );
+    assert!(newest.unwrap().is_err());
+}
+
+#[test]
+fn zcashd_v5_6_tx_records_keep_an_orchard_ivk_per_received_action() {
+    for (node, txs) in [("node0", 139), ("node2", 138)] {
+        let path = format!("{WALLETS}/golden-v5.6.0/extracted_wallets/{node}_wallet");
+        let wallet = WalletDb::open(path).unwrap().decode().unwrap();
+        let errors: Vec<_> = wallet
+            .errors
+            .iter()
+            .filter(|e| e.kind == RecordKind::Tx)
+            .collect();
+        assert_eq!(errors.len(), 0, "{node}: {errors:?}");
+        assert_eq!(wallet.transactions.len(), txs);
+        let ivks: BTreeSet<_> = wallet
+            .transactions
+            .values()
+            .filter_map(|wtx| wtx.orchard_meta.as_ref())
+            .flat_map(|meta| meta.action_data.values())
+            .map(|ivk| (ivk.dk, ivk.ivk))
+            .collect();
+        // Every received action names the external or internal ivk of one of the node's
+        // unified accounts.
+        assert!(!ivks.is_empty(), "{node}");
+        assert!(
+            ivks.len() <= 2 * wallet.keys.unified_accounts.len(),
+            "{node}"
+        );
+    }
+}
+
+#[test]
+fn v2_transactions_decode_joinsplits_and_check_their_txid() {
+    let mut tx = 2u32.to_le_bytes().to_vec();
+    tx.push(1); // one input
+    tx.extend_from_slice(&[0x11; 36]);
+    tx.extend_from_slice(&[0, 0xff, 0xff, 0xff, 0xff]);
+    tx.push(0); // no outputs
+    tx.extend_from_slice(&0u32.to_le_bytes());
+    tx.push(1);
+    tx.extend_from_slice(&100_000i64.to_le_bytes());
+    tx.extend_from_slice(&0i64.to_le_bytes());
+    tx.extend_from_slice(&[0xc1; 32 * 9 + 296 + 2 * 601]);
+    tx.extend_from_slice(&[0xc2; 32 + 64]);
+    let txid = sha256d(&tx);
+
+    let mut sprout = vec![1];
+    sprout.extend_from_slice(&txid);
+    sprout.extend_from_slice(&0u64.to_le_bytes());
+    sprout.push(1);
+    sprout.extend_from_slice(&[0xd1; 64]);
+    sprout.push(1);
+    sprout.extend_from_slice(&[0xd2; 32]);
+    sprout.push(0); // no witnesses
+    sprout.extend_from_slice(&(-1i32).to_le_bytes());
+    let value = [tx, wallet_tx_tail(&sprout, None)].concat();
+
+    let registry = default_registry();
+    let wtx = registry
+        .decode_as::<WalletTx>(
+            RecordKind::Tx,
+            &DecodeContext::default(),
+            Some(&RecordKey::TxId(txid)),
+            &value,
+        )
+        .unwrap()
+        .unwrap();
+    let sprout = wtx.tx().sprout.as_ref().unwrap();
+    assert_eq!(sprout.joinsplits[0].vpub_old, 100_000);
+    assert_eq!(sprout.joinsplits[0].proof.len(), 296);
+    assert_eq!(wtx.sprout_note_data[0].1.nullifier, Some([0xd2; 32]));
+    let cache = wtx.witness_caches()[0];
+    assert!(matches!(
+        cache.outpoint,
+        NoteOutPoint::Sprout(JsOutPoint { js: 0, n: 1, .. })
+    ));
+    assert!(!cache.is_witnessed());
+    assert!(!wtx.tx().is_coinbase() && wtx.tx().is_shielded());
+
+    let err = registry.decode(
+        RecordKind::Tx,
+        &DecodeContext::default(),
+        Some(&RecordKey::TxId([0; 32])),
+        &value,
+    );
+    assert!(err.unwrap().unwrap_err().message.contains("hash"));
+}
+
+#[test]
+fn legacy_accounting_records_decode() {
+    let registry = default_registry();
+    let mut key = walletdb_key_prefix("acc");
+    key.extend_from_slice(b"\x07savings");
+    let mut value = 1i32.to_le_bytes().to_vec();
+    value.push(33);
+    value.extend_from_slice(&[0x02; 33]);
+    let entry = registry.decode_entry(&DecodeContext::default(), &key, &value);
+    let item = entry.value.unwrap().unwrap();
+    let account = item.as_any().downcast_ref::<Account>().unwrap();
+    assert_eq!(
+        (account.name.as_str(), account.pubkey.len()),
+        ("savings", 33)
+    );
+
+    let mut key = walletdb_key_prefix("acentry");
+    key.extend_from_slice(b"\x07savings");
+    key.extend_from_slice(&3u64.to_le_bytes());
+    let mut value = 1i32.to_le_bytes().to_vec();
+    value.extend_from_slice(&(-150_000i64).to_le_bytes());
+    value.extend_from_slice(&1_500_000_000i64.to_le_bytes());
+    value.extend_from_slice(b"\x08checking");
+    let comment = b"rent\x00\x02\x01n\x0217\x02to\x03bob";
+    value.push(comment.len() as u8);
+    value.extend_from_slice(comment);
+    let entry = registry.decode_entry(&DecodeContext::default(), &key, &value);
+    let item = entry.value.unwrap().unwrap();
+    let acentry = item.as_any().downcast_ref::<AccountingEntry>().unwrap();
+    assert_eq!((acentry.account.as_str(), acentry.number), ("savings", 3));
+    assert_eq!(acentry.credit_debit, -150_000);
+    assert_eq!(acentry.other_account, "checking");
+    assert_eq!(
+        (acentry.comment.as_str(), acentry.order_pos),
+        ("rent", Some(17))
+    );
+    assert_eq!(acentry.map_value["to"], "bob");
+}
+
+#[test]
+fn best_block_locators_end_at_the_regtest_genesis() {
+    let registry = default_registry();
+    let regtest = "27e30134d620e9fe61f719938320bab63e7e72c91b5e23025676f90ed8119f02";
+    for n in 0..8 {
+        let mut tips = Vec::new();
+        for record in fixture(n).entries() {
+            let (key, value, _) = record.unwrap();
+            let entry = registry.decode_entry(&DecodeContext::default(), &key, &value);
+            if !matches!(
+                entry.kind,
+                RecordKind::BestBlock | RecordKind::BestBlockNoMerkle
+            ) {
+                continue;
+            }
+            let item = entry.value.unwrap().unwrap().into_any();
+            let locator = item.downcast::<BlockLocator>().unwrap();
+            if entry.kind == RecordKind::BestBlock {
+                assert!(locator.is_empty());
+                continue;
+            }
+            assert_eq!(hex::encode(locator.genesis().unwrap()), regtest);
+            tips.push(locator.tip_hex().unwrap());
+        }
+        assert_eq!(tips.len(), 1);
+    }
+}
+
+#[test]
+fn hd_seed_records_surface_the_fingerprint_and_hide_the_seed() {
+    let registry = default_registry();
+    let mut fingerprint = [0u8; 32];
+    fingerprint[0] = 0xfe;
+    let mut key = walletdb_key_prefix("hdseed");
+    key.extend_from_slice(&fingerprint);
+    let mut value = vec![32];
+    value.extend_from_slice(&[0x5e; 32]);
+    let item = registry
+        .decode_entry(&DecodeContext::default(), &key, &value)
+        .value
+        .unwrap()
+        .unwrap();
+    let seed = item.as_any().downcast_ref::<HdSeed>().unwrap();
+    assert_eq!(seed.seed.expose(), [0x5e; 32]);
+    assert!(seed.fingerprint_hex().ends_with("fe"));
+    for shown in [format!("{seed:?}"), seed.to_string()] {
+        assert!(shown.contains(&seed.fingerprint_hex()));
+        assert!(!shown.contains("5e5e"));
+    }
+
+    let mut key = walletdb_key_prefix("chdseed");
+    key.extend_from_slice(&fingerprint);
+    let mut value = vec![48];
+    value.extend_from_slice(&[0xee; 48]);
+    let item = registry
+        .decode_entry(&DecodeContext::default(), &key, &value)
+        .value
+        .unwrap()
+        .unwrap();
+    let seed = item.as_any().downcast_ref::<EncryptedHdSeed>().unwrap();
+    assert_eq!(
+        (seed.fingerprint, seed.encrypted_seed.len()),
+        (fingerprint, 48)
+    );
+}
+
+#[test]
+fn mnemonic_phrases_decode_and_stay_redacted() {
+    let registry = default_registry();
+    let mut phrases = 0;
+    for n in 0..8 {
+        for record in fixture(n).entries() {
+            let (key, value, _) = record.unwrap();
+            let entry = registry.decode_entry(&DecodeContext::default(), &key, &value);
+            if entry.kind != RecordKind::MnemonicPhrase {
+                continue;
+            }
+            let item = entry.value.unwrap().unwrap();
+            let mnemonic = item.as_any().downcast_ref::<MnemonicPhrase>().unwrap();
+            assert_eq!(
+                (mnemonic.language, mnemonic.word_count()),
+                (Language::English, 24)
+            );
+            let first = mnemonic.phrase.expose().split(' ').next().unwrap();
+            for shown in [format!("{item:?}"), mnemonic.to_string()] {
+                assert!(!shown.contains(first), "{shown}");
+            }
+            assert!(mnemonic.to_string().contains(&mnemonic.fingerprint_hex()));
+            phrases += 1;
+        }
+    }
+    assert_eq!(phrases, 8);
+
+    let mut key = walletdb_key_prefix("cmnemonicphrase");
+    key.extend_from_slice(&[0x0f; 32]);
+    let mut value = vec![176];
+    value.extend_from_slice(&[0xee; 176]);
+    let item = registry
+        .decode_entry(&DecodeContext::default(), &key, &value)
+        .value
+        .unwrap()
+        .unwrap();
+    let encrypted = item
+        .as_any()
+        .downcast_ref::<EncryptedMnemonicPhrase>()
+        .unwrap();
+    assert_eq!(encrypted.encrypted_phrase.len(), 176);
+}
+
+#[test]
+fn mnemonic_hd_chains_count_the_keys_derived_from_the_seed() {
+    let registry = default_registry();
+    for n in 0..8 {
+        let mut chain = None;
+        let mut phrase = None;
+        let mut hd_keys = 0;
+        for record in fixture(n).entries() {
+            let (key, value, _) = record.unwrap();
+            let entry = registry.decode_entry(&DecodeContext::default(), &key, &value);
+            let Some(Ok(item)) = entry.value else {
+                continue;
+            };
+            if let Some(found) = item.as_any().downcast_ref::<MnemonicHdChain>() {
+                chain = Some(found.clone());
+            } else if let Some(found) = item.as_any().downcast_ref::<MnemonicPhrase>() {
+                phrase = Some(found.fingerprint);
+            } else if let Some(meta) = item.as_any().downcast_ref::<TransparentKeyMetadata>()
+                && meta.metadata.is_hd()
+            {
+                hd_keys += 1;
+            }
+        }
+        let chain = chain.unwrap();
+        assert_eq!(chain.version, 1);
+        assert_eq!(Some(chain.seed_fingerprint), phrase);
+        assert_eq!(chain.legacy_transparent_keys(), hd_keys);
+        assert_eq!(
+            (chain.account_counter, chain.legacy_sapling_counter),
+            (0, 0)
+        );
+        assert!(!chain.backup_confirmed);
+        assert!(chain.to_string().contains(&chain.seed_fingerprint_hex()));
+    }
+}
+
+#[test]
+fn sprout_key_records_decode_against_their_address() {
+    let registry = default_registry();
+    let (a_pk, pk_enc) = ([0xa1; 32], [0xe2; 32]);
+    let record = |tag: &str, value: &[u8]| {
+        let mut key = walletdb_key_prefix(tag);
+        key.extend_from_slice(&a_pk);
+        key.extend_from_slice(&pk_enc);
+        registry
+            .decode_entry(&DecodeContext::default(), &key, value)
+            .value
+            .unwrap()
+    };
+
+    let mut a_sk = [0x0c; 32];
+    let item = record("zkey", &a_sk).unwrap();
+    let spending = item.as_any().downcast_ref::<SproutSpendingKey>().unwrap();
+    assert_eq!(spending.address.pk_enc, pk_enc);
+    assert_eq!(spending.a_sk.expose(), a_sk);
+    assert!(!format!("{spending:?}").contains("12, 12"));
+    a_sk[0] = 0x1c;
+    assert!(record("zkey", &a_sk).is_err());
+
+    let mut value = [0x5c; 32].to_vec();
+    value.push(48);
+    value.extend_from_slice(&[0xee; 48]);
+    let item = record("czkey", &value).unwrap();
+    let encrypted = item.as_any().downcast_ref::<EncryptedSproutKey>().unwrap();
+    assert_eq!(
+        (encrypted.receiving_key, encrypted.encrypted_secret.len()),
+        ([0x5c; 32], 48)
+    );
+    assert!(record("czkey", &value[..value.len() - 1]).is_err());
+
+    let mut value = 1i32.to_le_bytes().to_vec();
+    value.extend_from_slice(&1_600_000_000i64.to_le_bytes());
+    let item = record("zkeymeta", &value).unwrap();
+    let meta = item.as_any().downcast_ref::<SproutKeyMetadata>().unwrap();
+    assert_eq!(meta.metadata.create_time, 1_600_000_000);
+    assert!(!meta.metadata.is_hd());
+
+    let value = [a_pk, [0x5c; 32]].concat();
+    let item = record("vkey", &value).unwrap();
+    let viewing = item.as_any().downcast_ref::<SproutViewingKey>().unwrap();
+    assert_eq!(viewing.receiving_key, [0x5c; 32]);
+    let value = [[0xb1; 32], [0x5c; 32]].concat();
+    assert!(record("vkey", &value).is_err());
+}
+
+fn zip32_key(depth: u8, child_index: u32, fill: u8) -> Vec<u8> {
+    let mut key = vec![depth, 0xaa, 0xbb, 0xcc, 0xdd];
+    key.extend_from_slice(&child_index.to_le_bytes());
+    key.extend_from_slice(&[0xcc; 32]);
+    for part in 0..4 {
+        key.extend_from_slice(&[fill + part; 32]);
+    }
+    key
+}
+
+#[test]
+fn sapling_key_records_expose_their_zip32_position() {
+    let registry = default_registry();
+    let ivk = [0x1f; 32];
+    let record = |tag: &str, value: &[u8]| {
+        let mut key = walletdb_key_prefix(tag);
+        key.extend_from_slice(&ivk);
+        registry
+            .decode_entry(&DecodeContext::default(), &key, value)
+            .value
+            .unwrap()
+    };
+
+    let extsk = zip32_key(3, 0x8000_0005, 0x40);
+    assert_eq!(extsk.len(), EXTENDED_KEY_LEN);
+    let item = record("sapzkey", &extsk).unwrap();
+    let spending = item.as_any().downcast_ref::<SaplingSpendingKey>().unwrap();
+    assert_eq!(spending.ivk, ivk);
+    let header = spending.key.header;
+    assert_eq!(
+        (header.depth, header.is_hardened(), header.index()),
+        (3, true, 5)
+    );
+    assert_eq!(header.parent_fvk_tag, [0xaa, 0xbb, 0xcc, 0xdd]);
+    assert_eq!(spending.key.ask.expose(), [0x40; 32]);
+    assert_eq!(
+        (spending.key.ovk, spending.key.dk),
+        ([0x42; 32], [0x43; 32])
+    );
+    assert!(!format!("{spending:?}").contains("64, 64"));
+    assert!(record("sapzkey", &extsk[..EXTENDED_KEY_LEN - 1]).is_err());
+
+    let mut value = zip32_key(0, 0, 0x60);
+    value.push(176);
+    value.extend_from_slice(&[0xee; 176]);
+    let item = record("csapzkey", &value).unwrap();
+    let encrypted = item.as_any().downcast_ref::<EncryptedSaplingKey>().unwrap();
+    let header = encrypted.extfvk.header;
+    assert_eq!((header.depth, header.is_hardened()), (0, false));
+    assert_eq!(
+        (encrypted.extfvk.ak, encrypted.extfvk.nk),
+        ([0x60; 32], [0x61; 32])
+    );
+    assert_eq!(encrypted.encrypted_secret.len(), 176);
+    assert!(record("csapzkey", &value[..value.len() - 1]).is_err());
+}
+
+#[test]
+fn sapzaddr_records_map_diversified_addresses_to_their_ivk() {
+    let registry = default_registry();
+    let ivk = [0x1f; 32];
+    let mut by_ivk = BTreeMap::<[u8; 32], Vec<u8>>::new();
+    for d in 0..3u8 {
+        let mut key = walletdb_key_prefix("sapzaddr");
+        key.extend_from_slice(&[d; 11]);
+        key.extend_from_slice(&[0xd0 + d; 32]);
+        let item = registry
+            .decode_entry(&DecodeContext::default(), &key, &ivk)
+            .value
+            .unwrap()
+            .unwrap();
+        let mapping = item.as_any().downcast_ref::<SaplingAddressIvk>().unwrap();
+        assert_eq!(mapping.address.pk_d, [0xd0 + d; 32]);
+        by_ivk
+            .entry(mapping.ivk)
+            .or_default()
+            .push(mapping.address.diversifier[0]);
+    }
+    assert_eq!(by_ivk[&ivk], [0, 1, 2]);
+
+    let mut key = walletdb_key_prefix("sapzaddr");
+    key.extend_from_slice(&[0; 43]);
+    let entry = registry.decode_entry(&DecodeContext::default(), &key, &ivk[..31]);
+    assert_eq!(entry.kind, RecordKind::SapZAddr);
+    assert!(entry.value.unwrap().is_err());
+}
+
+#[test]
+fn sapextfvk_records_decode_their_key_as_a_viewing_key() {
+    let registry = default_registry();
+    let mut key = walletdb_key_prefix("sapextfvk");
+    key.extend_from_slice(&zip32_key(2, 0x8000_0001, 0x70));
+    let item = registry
+        .decode_entry(&DecodeContext::default(), &key, b"1")
+        .value
+        .unwrap()
+        .unwrap();
+    let watch_only = item.as_any().downcast_ref::<SaplingWatchOnlyKey>().unwrap();
+    assert_eq!(watch_only.extfvk.header.depth, 2);
+    assert_eq!(watch_only.extfvk.ak, [0x70; 32]);
+    assert_eq!(watch_only.extfvk.dk, [0x73; 32]);
+    assert!(
+        registry
+            .decode_entry(&DecodeContext::default(), &key, b"0")
+            .value
+            .unwrap()
+            .is_err()
+    );
+}
+
+#[test]
+fn script_records_classify_watch_only_and_redeem_scripts() {
+    let registry = default_registry();
+    let (a, b) = ([0x02; 33], [0x03; 33]);
+    let p2pkh = [&[0x76, 0xa9, 20][..], &[0x11; 20], &[0x88, 0xac]].concat();
+    let p2sh = [&[0xa9, 20][..], &[0x22; 20], &[0x87]].concat();
+    let p2pk = [&[33][..], &a, &[0xac]].concat();
+    let multisig = [&[0x51, 33][..], &a, &[33], &b, &[0x52, 0xae]].concat();
+    let cases = [
+        (&p2pkh, ScriptKind::PubKeyHash([0x11; 20])),
+        (&p2sh, ScriptKind::ScriptHash([0x22; 20])),
+        (&p2pk, ScriptKind::PubKey(a.to_vec())),
+        (
+            &multisig,
+            ScriptKind::Multisig {
+                required: 1,
+                pubkeys: vec![a.to_vec(), b.to_vec()],
+            },
+        ),
+    ];
+    for (script, kind) in cases {
+        let mut key = walletdb_key_prefix("watchs");
+        key.push(script.len() as u8);
+        key.extend_from_slice(script);
+        let item = registry
+            .decode_entry(&DecodeContext::default(), &key, b"1")
+            .value
+            .unwrap()
+            .unwrap();
+        let watched = item.as_any().downcast_ref::<WatchScript>().unwrap();
+        assert_eq!((&watched.script, &watched.kind), (script, &kind));
+    }
+    assert_eq!(ScriptKind::of(&multisig).to_string(), "multisig 1-of-2");
+    assert_eq!(ScriptKind::of(&multisig[1..]), ScriptKind::NonStandard);
+    assert_eq!(ScriptKind::of(&p2pkh[..24]), ScriptKind::NonStandard);
+
+    let mut key = walletdb_key_prefix("cscript");
+    key.extend_from_slice(&[0x22; 20]);
+    let mut value = vec![multisig.len() as u8];
+    value.extend_from_slice(&multisig);
+    let item = registry
+        .decode_entry(&DecodeContext::default(), &key, &value)
+        .value
+        .unwrap()
+        .unwrap();
+    let redeem = item.as_any().downcast_ref::<RedeemScript>().unwrap();
+    assert_eq!(redeem.script_id, [0x22; 20]);
+    assert!(matches!(
+        redeem.kind,
+        ScriptKind::Multisig { required: 1, .. }
+    ));
+}
+
+#[test]
+fn unified_account_records_link_seed_accounts_and_addresses() {
+    let registry = default_registry();
+    let ufvk_id = [0x3c; 32];
+
+    let mut key = walletdb_key_prefix("unifiedaccount");
+    key.extend_from_slice(&[0x0f; 32]);
+    key.extend_from_slice(&133u32.to_le_bytes());
+    key.extend_from_slice(&2u32.to_le_bytes());
+    key.extend_from_slice(&ufvk_id);
+    let item = registry
+        .decode_entry(&DecodeContext::default(), &key, &[0; 4])
+        .value
+        .unwrap()
+        .unwrap();
+    let account = item.as_any().downcast_ref::<UnifiedAccount>().unwrap();
+    assert_eq!((account.coin_type, account.account_id), (133, 2));
+    assert_eq!(account.ufvk_id, ufvk_id);
+    assert!(account.to_string().contains(&account.ufvk_id_hex()));
+
+    let mut key = walletdb_key_prefix("unifiedfvk");
+    key.extend_from_slice(&ufvk_id);
+    let mut value = vec![14];
+    value.extend_from_slice(b"uviewregtest1q");
+    let item = registry
+        .decode_entry(&DecodeContext::default(), &key, &value)
+        .value
+        .unwrap()
+        .unwrap();
+    let ufvk = item
+        .as_any()
+        .downcast_ref::<UnifiedFullViewingKey>()
+        .unwrap();
+    assert_eq!(ufvk.encoding, "uviewregtest1q");
+
+    let mut key = walletdb_key_prefix("unifiedaddrmeta");
+    key.extend_from_slice(&ufvk_id);
+    key.extend_from_slice(&[0x01, 0x01, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+    key.extend_from_slice(&[3, 0, 2, 3]); // receiver types
+    let value = 0u32.to_le_bytes();
+    let item = registry
+        .decode_entry(&DecodeContext::default(), &key, &value)
+        .value
+        .unwrap()
+        .unwrap();
+    let address = item
+        .as_any()
+        .downcast_ref::<UnifiedAddressMetadata>()
+        .unwrap();
+    assert_eq!(address.index(), 257);
+    assert_eq!(
+        address.receiver_types,
+        [
+            ReceiverType::P2pkh,
+            ReceiverType::Sapling,
+            ReceiverType::Orchard
+        ]
+    );
+    assert!(!address.has_receiver(ReceiverType::P2sh));
+    // zcashd reads no other metadata version, and keys without receiver types stay raw.
+    for (key, value) in [
+        (&key[..], &1u32.to_le_bytes()[..]),
+        (&key[..], &value[..3]),
+        (&key[..key.len() - 4], &value[..]),
+    ] {
+        let decoded = registry.decode_entry(&DecodeContext::default(), key, value);
+        assert!(decoded.value.unwrap().is_err());
+    }
+
+    let node0 = format!("{WALLETS}/golden-v5.6.0/extracted_wallets/node0_wallet");
+    let wallet = WalletDb::open(node0).unwrap().decode().unwrap();
+    assert!(
+        wallet
+            .errors
+            .iter()
+            .all(|e| e.kind != RecordKind::UnifiedAddrMeta)
+    );
+    let addresses = &wallet.keys.unified_addresses;
+    assert_eq!(addresses.len(), 3);
+    assert!(
+        addresses
+            .iter()
+            .all(|a| a.receiver_types == [ReceiverType::Orchard])
+    );
+}
+
+#[test]
+fn orchard_note_commitment_trees_report_size_and_checkpoints() {
+    let registry = default_registry();
+    for n in 0..8 {
+        let tree = registry
+            .decode_as::<OrchardNoteCommitmentTree>(
+                RecordKind::OrchardNoteCommitmentTree,
+                &DecodeContext::default(),
+                None,
+                &fixture(n)
+                    .get(&walletdb_key_prefix("orchard_note_commitment_tree"))
+                    .unwrap()
+                    .unwrap()
+                    .0,
+            )
+            .unwrap()
+            .unwrap();
+        assert_eq!(tree.client_version.to_string(), "v6.0.0");
+        assert_eq!((tree.last_checkpoint, tree.tree.size()), (None, 0));
+        assert_eq!(tree.tree.max_checkpoints, 100);
+        assert!(tree.tree.checkpoints.is_empty() && tree.notes.is_empty());
+    }
+
+    let mut value = 6_000_050i32.to_le_bytes().to_vec();
+    value.extend_from_slice(&[1, 1]);
+    value.extend_from_slice(&1_234u32.to_le_bytes());
+    value.extend_from_slice(&[3, 0, 1, 2, 0, 0, 0]);
+    value.extend_from_slice(&5u64.to_le_bytes());
+    value.extend_from_slice(&[0x51; 32]);
+    value.push(1);
+    value.extend_from_slice(&[0x52; 32]);
+    value.push(1);
+    value.extend_from_slice(&[0x53; 32]);
+    value.push(0);
+    value.push(1);
+    value.extend_from_slice(&1_234u32.to_le_bytes());
+    value.extend_from_slice(&1u64.to_le_bytes());
+    value.extend_from_slice(&[0, 0]);
+    value.extend_from_slice(&100u64.to_le_bytes());
+    value.push(1);
+    value.extend_from_slice(&[0x7e; 32]);
+    value.extend_from_slice(&1_230u32.to_le_bytes());
+    value.push(1);
+    value.extend_from_slice(&0u32.to_le_bytes());
+    value.extend_from_slice(&4u64.to_le_bytes());
+    let item = registry
+        .decode(
+            RecordKind::OrchardNoteCommitmentTree,
+            &DecodeContext::default(),
+            None,
+            &value,
+        )
+        .unwrap()
+        .unwrap();
+    let tree = item
+        .as_any()
+        .downcast_ref::<OrchardNoteCommitmentTree>()
+        .unwrap();
+    assert_eq!((tree.last_checkpoint, tree.tree.size()), (Some(1_234), 6));
+    let frontier = &tree.tree.current_bridge.as_ref().unwrap().frontier;
+    assert_eq!(
+        (frontier.leaf, frontier.ommers.as_slice()),
+        ([0x52; 32], &[[0x51; 32], [0x53; 32]][..])
+    );
+    assert_eq!(tree.tree.checkpoints[0].id, Some(1_234));
+    assert_eq!(tree.notes[0].note_positions, [(0, 4)]);
+    assert_eq!(tree.note_count(), 1);
+    assert!(tree.to_string().contains("checkpoints  : 1 of 100"));
+
+    // A right-hand frontier leaf without its left-hand sibling.
+    let mut odd = value.clone();
+    odd[4 + 1 + 5 + 7] = 6;
+    // A tree serialization version no zcashd release writes.
+    let mut tree_v4 = value.clone();
+    tree_v4[4 + 1 + 5] = 4;
+    // A note state version no zcashd release writes.
+    value[4] = 2;
+    for value in [odd, tree_v4, value] {
+        assert!(
+            registry
+                .decode(
+                    RecordKind::OrchardNoteCommitmentTree,
+                    &DecodeContext::default(),
+                    None,
+                    &value
+                )
+                .unwrap()
+                .is_err()
+        );
+    }
+}
+
+#[test]
+fn zcashd_orchard_note_commitment_trees_decode_in_v2_and_v3() {
+    let tree = |path: &str| {
+        let wallet = WalletDb::open(format!("{WALLETS}/{path}"))
+            .unwrap()
+            .decode()
+            .unwrap();
+        assert!(
+            wallet
+                .errors
+                .iter()
+                .all(|e| e.kind != RecordKind::OrchardNoteCommitmentTree),
+            "{path}"
+        );
+        wallet.orchard.unwrap()
+    };
+
+    // zcashd 5.5 wrote the golden trees in v2, which records no checkpoint ids.
+    let node0 = tree("golden-v5.6.0/extracted_wallets/node0_wallet");
+    assert_eq!(node0.client_version.to_string(), "v5.5.1");
+    assert_eq!((node0.last_checkpoint, node0.tree.size()), (Some(353), 34));
+    assert_eq!(node0.tree.prior_bridges.len(), 23);
+    assert_eq!(node0.tree.marked_indices.len(), 22);
+    assert_eq!(node0.tree.checkpoints.len(), 100);
+    assert!(node0.tree.checkpoints.iter().all(|c| c.id.is_none()));
+    assert_eq!((node0.notes.len(), node0.note_count()), (12, 22));
+    let frontier = &node0.tree.current_bridge.as_ref().unwrap().frontier;
+    // Leaf 33 is the right-hand one of a pair, whose left-hand leaf comes first.
+    assert_eq!(
+        (
+            hex::encode(&frontier.leaf[..4]),
+            hex::encode(&frontier.ommers[0][..4])
+        ),
+        ("1fdab651".to_string(), "eea7858d".to_string())
+    );
+
+    // The ommer a v1 auth fragment saw for leaf 2 is leaf 3 itself.
+    let node2 = tree("golden-v5.6.0/extracted_wallets/node2_wallet");
+    assert_eq!(
+        (node2.tree.prior_bridges.len(), node2.note_count()),
+        (16, 11)
+    );
+    let leaf3 = node0
+        .tree
+        .prior_bridges
+        .iter()
+        .find(|bridge| bridge.frontier.position == 3)
+        .unwrap()
+        .frontier
+        .leaf;
+    assert!(node2.tree.prior_bridges.iter().any(|bridge| {
+        bridge
+            .ommers
+            .contains(&(TreeAddress { level: 0, index: 3 }, leaf3))
+    }));
+
+    // zcashd 5.6 rewrote them in v3 with the checkpoint of the block it saw next.
+    let node0 = tree("tarnished-v5.6.0/extracted_wallet/node0_wallet");
+    assert_eq!((node0.last_checkpoint, node0.tree.size()), (Some(354), 36));
+    assert_eq!(node0.tree.prior_bridges.len(), 1);
+    assert_eq!(node0.tree.checkpoints.len(), 1);
+    assert_eq!(
+        (
+            node0.tree.checkpoints[0].id,
+            node0.tree.checkpoints[0].bridges_len
+        ),
+        (Some(354), 1)
+    );
+    let node3 = tree("tarnished-v5.6.0/extracted_wallet/node3_wallet");
+    assert_eq!(node3.notes[0].note_positions[0].1, 35);
+}
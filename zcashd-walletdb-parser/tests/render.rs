@@ -0,0 +1,91 @@
+//! [`Render`] blocks of the shipped fixtures, plain and painted.
+
+use pretty_assertions::assert_eq;
+use zcashd_walletdb_parser::{
+    parser::{
+        decoders::seed::HdSeed,
+        record::{DecodeContext, DecodeError, RecordKind},
+        registry::{DecodedEntry, DecodedRecord, UnknownRecord, default_registry},
+        render::{Block, Render, Style, Tone, utc},
+        secret::SecretBytes,
+    },
+    storage::walletdb::WalletDb,
+};
+
+const WALLETS: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../dat_files");
+
+#[test]
+fn wallet_summary_of_a_fixture() {
+    let wallet = WalletDb::open(format!("{WALLETS}/wallet0.dat"))
+        .unwrap()
+        .decode()
+        .unwrap();
+    let block = wallet.render();
+    assert_eq!(block.title, "ZcashdWallet");
+    assert_eq!(block.get("version"), Some("v6.0.0"));
+    assert_eq!(block.get("network"), Some("Zcash regtest"));
+    assert_eq!(block.get("encrypted"), Some("no"));
+    assert_eq!(
+        block.get("seed"),
+        Some("mnemonic, 24 words (English), redacted")
+    );
+    assert_eq!(block.get("transparent"), Some("52 keys, 0 scripts"));
+    assert_eq!(block.get("transactions"), Some("50"));
+    assert!(block.rows.iter().all(|row| row.tone != Tone::Bad));
+}
+
+#[test]
+fn blocks_align_and_paint_only_when_asked() {
+    let seed = HdSeed {
+        fingerprint: [0xab; 32],
+        seed: SecretBytes::new(vec![7; 32]),
+    };
+    let block = seed.render();
+    assert_eq!(
+        block.to_string(),
+        format!(
+            "HdSeed {{\n  fingerprint  : {}\n  seed         : 32 bytes, redacted\n}}",
+            "ab".repeat(32)
+        )
+    );
+    assert_eq!(block.paint(Style::PLAIN).to_string(), block.to_string());
+    let painted = block.paint(Style::COLOR).to_string();
+    assert!(painted.starts_with("\x1b[1mHdSeed\x1b[0m {\n"));
+    assert!(painted.contains("\x1b[35m32 bytes, redacted\x1b[0m"));
+}
+
+#[test]
+fn records_are_headed_by_their_kind() {
+    let ctx = DecodeContext::default();
+    let failed = DecodedEntry {
+        kind: RecordKind::Key,
+        key: None,
+        value: Some(Err(DecodeError::new("truncated"))),
+    };
+    let block = failed.render();
+    assert_eq!(block.title, "RecordError");
+    assert_eq!(block.rows[0].value, "key");
+    assert_eq!(block.get("error"), Some("truncated"));
+
+    let registry = default_registry();
+    let record = registry.decode_record(&ctx, b"\x07unknown\x01", &[0xff; 40]);
+    assert!(matches!(
+        record,
+        DecodedRecord::Unknown(UnknownRecord { .. })
+    ));
+    let block: Block = record.render();
+    assert_eq!(block.get("tag"), Some("\"unknown\""));
+    assert_eq!(block.get("value"), Some("40 bytes"));
+    assert_eq!(
+        block.rows.last().unwrap().value,
+        format!("{}..", "ff".repeat(32))
+    );
+}
+
+#[test]
+fn unix_times_as_utc_dates() {
+    assert_eq!(utc(0), "1970-01-01 00:00:00 UTC");
+    assert_eq!(utc(951_782_400), "2000-02-29 00:00:00 UTC");
+    assert_eq!(utc(1_732_699_326), "2024-11-27 09:22:06 UTC");
+    assert_eq!(utc(-1), "1969-12-31 23:59:59 UTC");
+}
@@ -0,0 +1,92 @@
+//! The `serde` feature: the meta page, format profile and wallet model as JSON, with
+//! secrets redacted unless exposed.
+#![cfg(feature = "serde")]
+
+use pretty_assertions::assert_eq;
+use serde_json::{Value, json};
+use zcashd_walletdb_parser::{
+    headers::parse_btree_meta_page0,
+    parser::{decoders::network::ZcashNetwork, export::zewif::Zewif, secret::exposing},
+    storage::{consistency::DbImageReader, reader::FileDbImageReader, walletdb::WalletDb},
+};
+
+const WALLET: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../dat_files/wallet0.dat");
+
+#[test]
+fn meta_page_and_format_profile_serialize() {
+    let page = std::fs::read(WALLET).unwrap();
+    let meta = serde_json::to_value(parse_btree_meta_page0(&page).unwrap()).unwrap();
+    assert_eq!(
+        (&meta["pagesize"], &meta["endian"]),
+        (&json!(4096), &json!("Le"))
+    );
+    assert_eq!(meta["uid"].as_str().unwrap().len(), 40);
+
+    let profile = FileDbImageReader::open(WALLET).unwrap().probe().unwrap();
+    let profile = serde_json::to_value(profile).unwrap();
+    assert_eq!(profile["page_size"], json!(4096));
+    assert_eq!(profile["encrypted"], json!(false));
+}
+
+#[test]
+fn wallet_serializes_with_secrets_redacted_unless_exposed() {
+    let wallet = WalletDb::open(WALLET).unwrap().decode().unwrap();
+    let value = serde_json::to_value(&wallet).unwrap();
+    let mnemonic = &value["crypto"]["mnemonic"];
+    assert_eq!(mnemonic["phrase"], json!("<redacted>"));
+    assert_eq!(mnemonic["language"], json!("English"));
+    let fingerprint = wallet.crypto.mnemonic.as_ref().unwrap().fingerprint;
+    assert_eq!(mnemonic["fingerprint"], json!(hex::encode(fingerprint)));
+
+    // Maps keyed by bytes have hex keys, and records keep their decoded shape.
+    let transactions = value["transactions"].as_object().unwrap();
+    assert_eq!(transactions.len(), wallet.transactions.len());
+    let (txid, wtx) = wallet.transactions.iter().next().unwrap();
+    assert_eq!(
+        transactions[&hex::encode(txid)]["from_me"],
+        json!(wtx.from_me)
+    );
+    let keys = value["keys"]["transparent"].as_object().unwrap();
+    let key = keys.values().next().unwrap();
+    assert_eq!(key["private_key"]["secret"], json!("<redacted>"));
+
+    let exposed = exposing(|| serde_json::to_value(&wallet).unwrap());
+    let phrase = wallet.crypto.mnemonic.as_ref().unwrap().phrase.expose();
+    assert_eq!(exposed["crypto"]["mnemonic"]["phrase"], json!(phrase));
+    let (pubkey, key) = wallet.keys.transparent.iter().next().unwrap();
+    let secret = &exposed["keys"]["transparent"][hex::encode(pubkey)]["private_key"]["secret"];
+    assert_eq!(secret, &json!(hex::encode(key.private_key.secret.expose())));
+    // Exposure ends with the closure.
+    let again: Value = serde_json::to_value(&wallet).unwrap();
+    assert_eq!(again, value);
+}
+
+#[test]
+fn zewif_export_names_the_record_of_each_field() {
+    let wallet = WalletDb::open(WALLET).unwrap().decode().unwrap();
+    let zewif = Zewif::of(&wallet, None, ZcashNetwork::Regtest);
+    let value = serde_json::to_value(&zewif).unwrap();
+    let export = &value["wallets"][0];
+    assert_eq!(export["network"], json!("Regtest"));
+    assert_eq!(export["seed_material"]["source"], json!("MnemonicPhrase"));
+    let phrase = &export["seed_material"]["value"]["Bip39Mnemonic"]["phrase"];
+    assert_eq!(phrase, &json!("<redacted>"));
+
+    let address = &export["accounts"][0]["addresses"][0];
+    assert_eq!(address["source"], json!("Key"));
+    assert_eq!(address["spending_key"]["value"], json!("<redacted>"));
+    assert_eq!(address["birth_time"]["source"], json!("KeyMeta"));
+    let (txid, tx) = value["transactions"]
+        .as_object()
+        .unwrap()
+        .iter()
+        .next()
+        .unwrap();
+    assert_eq!(tx["txid"], json!(txid));
+    assert_eq!(tx["mined_height"]["source"], json!("Tx"));
+
+    let exposed = exposing(|| serde_json::to_value(&zewif).unwrap());
+    let address = &exposed["wallets"][0]["accounts"][0]["addresses"][0];
+    let wif = address["spending_key"]["value"].as_str().unwrap();
+    assert!(wif.starts_with('c'));
+}
@@ -0,0 +1,124 @@
+//! The serialization primitives record decoders are built on.
+
+use std::collections::BTreeMap;
+
+use pretty_assertions::assert_eq;
+use zcashd_walletdb_parser::parser::serialize::{MAX_SIZE, Reader, uint256_hex};
+
+#[test]
+fn fixed_width_reads_are_little_endian() {
+    let mut bytes = vec![0x01, 0x00];
+    bytes.extend_from_slice(&0xdead_beefu32.to_le_bytes());
+    bytes.extend_from_slice(&(-2i32).to_le_bytes());
+    bytes.extend_from_slice(&u64::MAX.to_le_bytes());
+    bytes.extend_from_slice(&i64::MIN.to_le_bytes());
+    let mut r = Reader::new(&bytes);
+    assert_eq!((r.bool("a").unwrap(), r.u8("b").unwrap()), (true, 0));
+    assert_eq!(r.u32("c").unwrap(), 0xdead_beef);
+    assert_eq!(r.i32("d").unwrap(), -2);
+    assert_eq!(r.u64("e").unwrap(), u64::MAX);
+    assert_eq!(r.i64("f").unwrap(), i64::MIN);
+    assert!(r.is_empty());
+    r.finish().unwrap();
+}
+
+#[test]
+fn errors_name_what_was_read_and_where() {
+    let mut r = Reader::new(&[1, 2, 3]);
+    r.u8("first").unwrap();
+    let err = r.u32("block height").unwrap_err();
+    assert_eq!(err.offset, Some(1));
+    assert_eq!(
+        err.to_string(),
+        "block height: wanted 4 bytes, 2 left at offset 1"
+    );
+    assert_eq!(r.position(), 1);
+
+    let err = r.finish().unwrap_err();
+    assert_eq!(
+        (err.message.as_str(), err.offset),
+        ("2 trailing bytes", Some(1))
+    );
+
+    let err = Reader::new(&[2, b'h', 0xff]).string("label").unwrap_err();
+    assert_eq!(
+        (err.message.as_str(), err.offset),
+        ("label: not UTF-8", Some(0))
+    );
+}
+
+#[test]
+fn compact_sizes_must_be_canonical_and_bounded() {
+    for (bytes, n) in [
+        (&[0xfc][..], 0xfc),
+        (&[0xfd, 0xfd, 0x00], 0xfd),
+        (&[0xfe, 0x00, 0x00, 0x01, 0x00], 0x1_0000),
+        (&[0xfe, 0x00, 0x00, 0x00, 0x02], MAX_SIZE),
+    ] {
+        assert_eq!(Reader::new(bytes).compact_size("n").unwrap(), n);
+    }
+    for bytes in [
+        &[0xfd, 0x10, 0x00][..],
+        &[0xfe, 0xff, 0xff, 0x00, 0x00],
+        &[0xff, 1, 0, 0, 0, 0, 0, 0, 0],
+    ] {
+        let err = Reader::new(bytes).compact_size("n").unwrap_err();
+        assert!(err.message.contains("non-canonical"), "{err}");
+    }
+    let err = Reader::new(&[0xfe, 0x01, 0x00, 0x00, 0x02])
+        .compact_size("n")
+        .unwrap_err();
+    assert!(err.message.contains("too large"), "{err}");
+    assert!(Reader::new(&[0xfd, 0x10]).compact_size("n").is_err());
+}
+
+#[test]
+fn counts_cannot_outrun_the_input() {
+    let mut r = Reader::new(&[3, 0xaa, 0xbb]);
+    let err = r.count(1, "items").unwrap_err();
+    assert_eq!(err.offset, Some(0));
+    assert_eq!(Reader::new(&[2, 0xaa, 0xbb]).count(1, "items").unwrap(), 2);
+    assert_eq!(
+        Reader::new(&[2, 0xaa, 0xbb]).var_bytes("bytes").unwrap(),
+        [0xaa, 0xbb]
+    );
+}
+
+#[test]
+fn containers_read_optionals_lists_and_maps() {
+    let mut bytes = vec![0, 1, 7, 3];
+    bytes.extend_from_slice(&[1, b'a', 1, b'x', 1, b'b', 1, b'y', 1, b'a', 1, b'z']);
+    let mut r = Reader::new(&bytes);
+    assert_eq!(r.optional("none", |r| r.u8("value")).unwrap(), None);
+    assert_eq!(r.optional("some", |r| r.u8("value")).unwrap(), Some(7));
+    let map = r
+        .map(2, "map", |r| Ok((r.string("key")?, r.string("value")?)))
+        .unwrap();
+    let expected = BTreeMap::from([("a".into(), "z".into()), ("b".into(), "y".into())]);
+    assert_eq!(map, expected);
+    r.finish().unwrap();
+
+    let list = Reader::new(&[2, 5, 0, 6, 0])
+        .list(2, "list", |r| Ok(u16::from_le_bytes(r.array("item")?)))
+        .unwrap();
+    assert_eq!(list, [5, 6]);
+    let err = Reader::new(&[2, 5, 0, 6])
+        .list(1, "list", |r| Ok(u16::from_le_bytes(r.array("item")?)))
+        .unwrap_err();
+    assert_eq!(
+        (err.message.as_str(), err.offset),
+        ("item: wanted 2 bytes, 1 left", Some(3))
+    );
+}
+
+#[test]
+fn uint256_hex_reverses_the_serialized_bytes() {
+    let mut hash = [0u8; 32];
+    hash[0] = 0x01;
+    hash[31] = 0xff;
+    let mut r = Reader::new(&hash);
+    let read = r.uint256("hash").unwrap();
+    assert_eq!(read, hash);
+    let shown = uint256_hex(&read);
+    assert!(shown.starts_with("ff") && shown.ends_with("01"), "{shown}");
+}
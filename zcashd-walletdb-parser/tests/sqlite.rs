@@ -0,0 +1,116 @@
+//! The `sqlite` feature: a fixture wallet written as a `zcash_client_sqlite` database.
+#![cfg(feature = "sqlite")]
+
+use pretty_assertions::assert_eq;
+use rusqlite::Connection;
+use zcashd_walletdb_parser::{
+    parser::{
+        decoders::{
+            network::ZcashNetwork,
+            unified::{UnifiedAccount, UnifiedFullViewingKey},
+        },
+        export::sqlite::{
+            ACCOUNT_DERIVED, ACCOUNT_IMPORTED, RECEIVER_P2PKH, SCOPE_EXTERNAL, SCOPE_INTERNAL,
+            write_wallet_db,
+        },
+    },
+    storage::walletdb::WalletDb,
+};
+
+const WALLET: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../dat_files/wallet0.dat");
+
+#[test]
+fn wallet_db_holds_accounts_addresses_birthdays_and_transactions() {
+    let mut wallet = WalletDb::open(WALLET).unwrap().decode().unwrap();
+    let keys = &mut wallet.keys;
+    for account_id in [0, 1] {
+        keys.unified_accounts.push(UnifiedAccount {
+            seed_fingerprint: [1; 32],
+            coin_type: 1,
+            account_id,
+            ufvk_id: [account_id as u8; 32],
+        });
+    }
+    keys.unified_fvks.insert(
+        [0; 32],
+        UnifiedFullViewingKey {
+            ufvk_id: [0; 32],
+            encoding: "uviewregtest1".to_string(),
+        },
+    );
+    let mut conn = Connection::open_in_memory().unwrap();
+    write_wallet_db(&mut conn, &wallet, ZcashNetwork::Regtest).unwrap();
+
+    let accounts: Vec<(String, i64, Option<u32>, u32)> = conn
+        .prepare("SELECT name, account_kind, hd_account_index, birthday_height FROM accounts")
+        .unwrap()
+        .query_map([], |row| {
+            Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?))
+        })
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .unwrap();
+    // Unified accounts without transactions are born at the best block, and one without
+    // a `unifiedfvk` record is written as imported.
+    assert_eq!(
+        accounts,
+        [
+            ("Legacy".to_string(), ACCOUNT_IMPORTED, None, 1),
+            ("Account 0".to_string(), ACCOUNT_DERIVED, Some(0), 176),
+            ("Account 1".to_string(), ACCOUNT_IMPORTED, Some(1), 176),
+        ]
+    );
+    assert!(
+        conn.execute(
+            "INSERT INTO accounts (uuid, account_kind, birthday_height) VALUES (x'00', 0, 0)",
+            [],
+        )
+        .is_err(),
+        "a derived account needs a seed, an index and a ufvk"
+    );
+
+    let count = |sql: &str| -> i64 { conn.query_row(sql, [], |row| row.get(0)).unwrap() };
+    assert_eq!(
+        count("SELECT COUNT(*) FROM addresses WHERE account_id = 1"),
+        52
+    );
+    assert_eq!(
+        count(&format!(
+            "SELECT COUNT(*) FROM addresses WHERE receiver_flags = {RECEIVER_P2PKH}"
+        )),
+        52
+    );
+    assert_eq!(
+        count(&format!(
+            "SELECT COUNT(*) FROM addresses WHERE key_scope = {SCOPE_EXTERNAL}"
+        )) + count(&format!(
+            "SELECT COUNT(*) FROM addresses WHERE key_scope = {SCOPE_INTERNAL}"
+        )),
+        52
+    );
+    assert_eq!(
+        count(
+            "SELECT COUNT(*) FROM key_birthdays JOIN addresses ON addresses.id = address_id
+             WHERE hd_keypath LIKE 'm/44''/1''/2147483647''/%' AND birth_time > 0"
+        ),
+        52
+    );
+
+    assert_eq!(
+        count("SELECT COUNT(*) FROM transactions"),
+        wallet.transactions.len() as i64
+    );
+    assert_eq!(
+        count("SELECT COUNT(*) FROM transactions WHERE mined_height IS NULL"),
+        0
+    );
+    let (txid, wtx) = wallet.transactions.iter().next().unwrap();
+    let raw: Vec<u8> = conn
+        .query_row(
+            "SELECT raw FROM transactions WHERE txid = ?1",
+            [&txid[..]],
+            |row| row.get(0),
+        )
+        .unwrap();
+    assert_eq!(raw, wtx.tx().to_bytes());
+}
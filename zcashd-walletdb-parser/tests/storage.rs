@@ -0,0 +1,1149 @@
+//! Building blocks of the storage layer, on their own and against synthetic images from
+//! `FixtureBuilder`.
+
+use std::{
+    borrow::Cow,
+    collections::BTreeMap,
+    env, fs,
+    io::{self, Cursor},
+    path::{Path, PathBuf},
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    },
+};
+
+use pretty_assertions::assert_eq;
+use zcashd_walletdb_parser::{
+    constants::{B_BLOB, B_OVERFLOW, P_BTREEMETA, P_IBTREE, P_LBTREE, P_OVERFLOW},
+    entry::{
+        constants::OverflowRef,
+        parser::{read_overflow_into, walletdb_key_prefix},
+    },
+    error::{OverflowChainError, WalletDbError},
+    storage::{
+        blob::{BlobDirectory, BlobRef, BlobResolver},
+        borrowed::BorrowedImage,
+        cache::{CacheStats, CachedPageSource},
+        carve::{SECTOR_SIZE, carve},
+        consistency::{DbImageReader, FindingKind, SalvageMode},
+        diff::diff_pages,
+        entry::{Confidence, ConflictPolicy, InMemoryMap, OrderedWalletMap, Provenance},
+        environment::{EnvironmentFile, scan_environment},
+        fixture::{Corruption, FixtureBuilder},
+        freelist::{FreeListIssue, walk_freelist},
+        lsn::{analyze_lsns, parse_lsn},
+        orphans::{OrphanPage, analyze_reachability},
+        page::{Page, PageProtection},
+        reader::FileDbImageReader,
+        recno::{BTM_FIXEDLEN, BTM_RECNO, RecnoFormat, recno_key},
+        repack::repack,
+        source::{FilePageSource, MemoryPageSource, ReaderPageSource},
+        stats::collect_stats,
+        types::{ByteVec, Endianness, PageNumber, PageSource},
+        walletdb::WalletDb,
+    },
+    util::Endian,
+};
+
+fn wallet_key(tag: &str, rest: &[u8]) -> Vec<u8> {
+    let mut key = walletdb_key_prefix(tag);
+    key.extend_from_slice(rest);
+    key
+}
+
+fn provenance(page_no: u32, slot_index: u16) -> Provenance {
+    Provenance {
+        source_id: "map".to_string(),
+        page_no,
+        slot_index,
+        confidence: Confidence::High,
+    }
+}
+
+#[test]
+fn ordered_wallet_map_iterates_in_key_order_and_finds_tags() {
+    let mut map = OrderedWalletMap::new();
+    assert!(map.is_empty());
+    for (key, page) in [
+        (wallet_key("tx", &[2]), 4),
+        (wallet_key("key", &[1]), 2),
+        (wallet_key("tx", &[1]), 3),
+        (wallet_key("txs", &[]), 5),
+        (wallet_key("version", &[]), 6),
+    ] {
+        assert!(
+            map.insert(key, vec![page as u8], Some(provenance(page, 0)))
+                .is_none()
+        );
+    }
+    assert_eq!(map.len(), 5);
+
+    // Keys sort as bytes: the compact-size length of the tag first.
+    let order: Vec<u8> = map.iter().map(|(_, entry)| entry.value[0]).collect();
+    assert_eq!(order, [3, 4, 2, 5, 6]);
+
+    // `txs` shares the letters of `tx` but not its framing.
+    let txs: Vec<&[u8]> = map.with_tag("tx").map(|e| e.key.as_slice()).collect();
+    assert_eq!(txs, [wallet_key("tx", &[1]), wallet_key("tx", &[2])]);
+    assert_eq!(map.with_prefix(&wallet_key("tx", &[2])).count(), 1);
+    assert_eq!(map.with_prefix(b"\x02t").count(), 2);
+    assert_eq!(map.with_tag("name").count(), 0);
+
+    // A second copy replaces the first and hands it back, provenance included.
+    let old = map
+        .insert(wallet_key("key", &[1]), vec![9], Some(provenance(7, 3)))
+        .unwrap();
+    assert_eq!((old.value, old.meta.unwrap().page_no), (vec![2], 2));
+    let entry = map.get_entry(&wallet_key("key", &[1])).unwrap();
+    let meta = entry.meta.as_ref().unwrap();
+    assert_eq!((meta.page_no, meta.slot_index), (7, 3));
+    assert_eq!(map.get(&wallet_key("key", &[1])), Some(&vec![9]));
+    assert_eq!(map.len(), 5);
+}
+
+/// A [`MemoryPageSource`] that counts the reads reaching it.
+#[derive(Debug)]
+struct Counting(MemoryPageSource, AtomicUsize);
+
+impl PageSource for Counting {
+    fn read_page(&self, page_no: PageNumber) -> io::Result<ByteVec> {
+        self.1.fetch_add(1, Ordering::Relaxed);
+        self.0.read_page(page_no)
+    }
+
+    fn page_count(&self) -> Option<u64> {
+        self.0.page_count()
+    }
+
+    fn source_id(&self) -> String {
+        self.0.source_id()
+    }
+}
+
+fn counting(pages: u8) -> Counting {
+    let image = (0..pages).flat_map(|n| vec![n; 512]).collect();
+    Counting(
+        MemoryPageSource::new(image, 512, "pages").unwrap(),
+        AtomicUsize::new(0),
+    )
+}
+
+#[test]
+fn cached_page_source_evicts_the_least_recently_used_page() {
+    let cache = CachedPageSource::new(counting(4), 2);
+    for pgno in [1, 2, 1, 3, 1, 2] {
+        assert_eq!(cache.read_page(pgno).unwrap(), vec![pgno as u8; 512]);
+    }
+    // 1 and 2 miss, 1 hits, 3 evicts 2, 1 hits again and 2 is read back in place of 3.
+    let expected = CacheStats {
+        hits: 2,
+        misses: 4,
+        resident: 2,
+    };
+    assert_eq!(cache.stats(), expected);
+    assert!(cache.read_page(9).is_err());
+    let inner = cache.into_inner();
+    assert_eq!(inner.1.load(Ordering::Relaxed), 5);
+
+    // Without capacity every read reaches the source.
+    let uncached = CachedPageSource::new(counting(4), 0);
+    for _ in 0..3 {
+        uncached.read_page(1).unwrap();
+    }
+    assert_eq!(uncached.stats(), CacheStats::default());
+    assert_eq!(uncached.into_inner().1.load(Ordering::Relaxed), 3);
+}
+
+/// A wallet image whose "main" subdatabase is turned into a fixed-length recno database:
+/// its meta page gets the recno flags and its root leaf becomes a `P_LRECNO` page holding
+/// `records`, `(deleted, data)` each.
+/// A little-endian 512-byte overflow page linked to `next`, holding `payload`.
+fn overflow_page(pgno: u32, next: u32, payload: &[u8]) -> Vec<u8> {
+    let mut page = vec![0; 512];
+    page[8..12].copy_from_slice(&pgno.to_le_bytes());
+    page[16..20].copy_from_slice(&next.to_le_bytes());
+    page[22..24].copy_from_slice(&(payload.len() as u16).to_le_bytes());
+    page[25] = P_OVERFLOW;
+    page[26..26 + payload.len()].copy_from_slice(payload);
+    page
+}
+
+#[test]
+fn overflow_chains_stream_into_a_reused_sink() {
+    // Two chains of several pages, and one that runs into an empty page.
+    let image: Vec<u8> = [
+        vec![0; 512],
+        overflow_page(1, 2, &[1; 486]),
+        overflow_page(2, 3, &[2; 486]),
+        overflow_page(3, 0, &[3; 28]),
+        overflow_page(4, 5, &[4; 300]),
+        overflow_page(5, 0, &[5; 50]),
+        overflow_page(6, 7, &[6; 486]),
+        vec![0; 512],
+    ]
+    .concat();
+    let read = |first_page, total_len, sink: &mut Vec<u8>| {
+        let reference = OverflowRef {
+            first_page,
+            total_len,
+        };
+        read_overflow_into(
+            &image,
+            512,
+            Endian::Le,
+            PageProtection::None,
+            reference,
+            sink,
+        )
+    };
+    let first = [[1; 486].as_slice(), &[2; 486], &[3; 28]].concat();
+    let second = [[4; 300].as_slice(), &[5; 50]].concat();
+
+    let mut sink = Vec::new();
+    read(1, 1_000, &mut sink).unwrap();
+    assert_eq!(sink, first);
+    // A second value is appended after the first, and a cleared sink starts over.
+    read(4, 350, &mut sink).unwrap();
+    assert_eq!(sink, [first.as_slice(), &second].concat());
+    sink.clear();
+    read(4, 350, &mut sink).unwrap();
+    assert_eq!(sink, second);
+
+    // On error the sink keeps what was read before the chain broke.
+    sink.clear();
+    let e = read(6, 900, &mut sink).unwrap_err();
+    assert!(matches!(
+        e,
+        WalletDbError::OverflowChainBroken(OverflowChainError::WrongPageType {
+            pgno: 7,
+            page_type: 0,
+        })
+    ));
+    assert_eq!(sink, [6; 486]);
+}
+
+fn recno_image(records: &[(bool, &[u8])]) -> (Vec<u8>, PageNumber) {
+    const PAGE: usize = 512;
+    let mut image = FixtureBuilder::new()
+        .page_size(PAGE as u32)
+        .wallet_record("name", b"a", b"b".to_vec())
+        .build()
+        .unwrap();
+    let reader = FileDbImageReader::from_image(image.clone(), "recno").unwrap();
+    let main = reader.subdatabases().unwrap().remove(0);
+
+    let meta = &mut image[main.meta_pgno as usize * PAGE..][..PAGE];
+    let flags = u32::from_le_bytes(meta[48..52].try_into().unwrap());
+    meta[48..52].copy_from_slice(&(flags | BTM_RECNO | BTM_FIXEDLEN).to_le_bytes());
+    meta[80..84].copy_from_slice(&6u32.to_le_bytes());
+    meta[84..88].copy_from_slice(&(b'.' as u32).to_le_bytes());
+
+    let leaf = &mut image[main.root as usize * PAGE..][..PAGE];
+    leaf[20..].fill(0);
+    let mut top = PAGE;
+    for (i, (deleted, data)) in records.iter().enumerate() {
+        top -= (3 + data.len() + 3) & !3;
+        leaf[top..top + 2].copy_from_slice(&(data.len() as u16).to_le_bytes());
+        leaf[top + 2] = if *deleted { 0x81 } else { 1 };
+        leaf[top + 3..top + 3 + data.len()].copy_from_slice(data);
+        leaf[26 + 2 * i..28 + 2 * i].copy_from_slice(&(top as u16).to_le_bytes());
+    }
+    leaf[20..22].copy_from_slice(&(records.len() as u16).to_le_bytes());
+    leaf[22..24].copy_from_slice(&((top - 26 - 2 * records.len()) as u16).to_le_bytes());
+    leaf[24] = 1;
+    leaf[25] = 6;
+    (image, main.root)
+}
+
+#[test]
+fn recno_records_are_numbered_from_one_and_padded_to_their_length() {
+    let (image, root) = recno_image(&[
+        (false, b"abc"),
+        (true, b"gone"),
+        (false, b"123456"),
+        (false, b""),
+    ]);
+    let reader = FileDbImageReader::from_image(image, "recno").unwrap();
+    let main = reader.subdatabases().unwrap().remove(0);
+    assert_eq!(
+        main.recno,
+        Some(RecnoFormat {
+            fixed_len: Some(6),
+            pad: b'.',
+            renumber: false,
+        })
+    );
+
+    let read: Vec<_> = reader
+        .entries(SalvageMode::Conservative)
+        .map(|(k, v, p)| (k, v.materialize().unwrap(), p.page_no, p.slot_index))
+        .collect();
+    // The deleted record keeps its number.
+    let expected = vec![
+        (recno_key(1), b"abc...".to_vec(), root, 0),
+        (recno_key(3), b"123456".to_vec(), root, 2),
+        (recno_key(4), b"......".to_vec(), root, 3),
+    ];
+    assert_eq!(read, expected);
+    assert_eq!(recno_key(258), [0, 0, 1, 2]);
+}
+
+fn carved_wallet(page_size: u32, records: u32) -> FixtureBuilder {
+    (0..records).fold(FixtureBuilder::new().page_size(page_size), |b, i| {
+        b.wallet_record("tx", &i.to_be_bytes(), vec![i as u8; 40])
+    })
+}
+
+#[test]
+fn carving_finds_a_wallet_and_a_loose_leaf_between_junk_sectors() {
+    let wallet = carved_wallet(1024, 60);
+    let image = wallet.build().unwrap();
+    // Small enough that the leaf's page number could fall inside the blob.
+    let fragment = carved_wallet(512, 30).build().unwrap();
+    let leaf = fragment
+        .chunks_exact(512)
+        .rposition(|page| page[25] == 5)
+        .unwrap();
+
+    let junk = |sectors: usize| vec![0xa5; sectors * SECTOR_SIZE];
+    let start = 3 * SECTOR_SIZE;
+    let mut blob = junk(3);
+    blob.extend(&image);
+    blob.extend(junk(2));
+    let loose_at = blob.len();
+    blob.extend(&fragment[leaf * 512..][..512]);
+    blob.extend(junk(1));
+
+    let found = carve(&blob);
+    assert_eq!(found.len(), 2);
+    let (db, loose) = (&found[0], &found[1]);
+    assert!(db.uid.is_some());
+    assert_eq!((db.page_size, db.conflicts), (1024, 0));
+    assert_eq!(db.pages.len(), image.len() / 1024);
+    assert_eq!(db.pages[&0], start as u64);
+
+    let blob: Arc<[u8]> = blob.into();
+    let records: BTreeMap<Vec<u8>, Vec<u8>> = db
+        .entries(blob.clone(), "carved")
+        .map(|(k, v, _)| (k, v.materialize().unwrap()))
+        .collect();
+    let expected: BTreeMap<Vec<u8>, Vec<u8>> = wallet
+        .live_records()
+        .iter()
+        .map(|(key, entry)| (key.clone(), entry.value.clone()))
+        .collect();
+    assert_eq!(records, expected);
+
+    assert_eq!(loose.uid, None);
+    assert_eq!(loose.page_size, 512);
+    assert_eq!(
+        loose.pages.iter().collect::<Vec<_>>(),
+        [(&(leaf as u32), &(loose_at as u64))]
+    );
+    let salvaged: Vec<_> = loose.entries(blob, "loose").collect();
+    assert!(!salvaged.is_empty());
+    for (key, _, provenance) in salvaged {
+        assert_eq!(provenance.page_no, leaf as u32);
+        assert_eq!(key[..3], *b"\x02tx");
+    }
+}
+
+#[test]
+fn borrowed_entries_point_into_the_image_and_copy_only_overflow_values() {
+    let image = (0u32..80)
+        .fold(FixtureBuilder::new().page_size(512), |b, i| {
+            let len = if i % 10 == 0 { 900 } else { 30 };
+            b.wallet_record("tx", &i.to_be_bytes(), vec![i as u8; len])
+        })
+        .build()
+        .unwrap();
+    let borrowed: Vec<_> = BorrowedImage::new(&image)
+        .unwrap()
+        .entries()
+        .map(Result::unwrap)
+        .collect();
+    let reader = FileDbImageReader::from_image(image.clone(), "owned").unwrap();
+    let owned: Vec<_> = reader
+        .entries(SalvageMode::Conservative)
+        .map(|(k, v, p)| (k, v.materialize().unwrap(), p.page_no, p.slot_index))
+        .collect();
+    assert_eq!(
+        borrowed
+            .iter()
+            .map(|e| (e.key.to_vec(), e.value.to_vec(), e.page_no, e.slot_index))
+            .collect::<Vec<_>>(),
+        owned
+    );
+
+    let in_image = |bytes: &[u8]| image.as_ptr_range().contains(&bytes.as_ptr());
+    for entry in &borrowed {
+        assert!(matches!(&entry.key, Cow::Borrowed(key) if in_image(key)));
+        match &entry.value {
+            Cow::Borrowed(value) => assert!(in_image(value) && value.len() == 30),
+            Cow::Owned(value) => assert_eq!(value.len(), 900),
+        }
+    }
+    let copied = borrowed
+        .iter()
+        .filter(|e| matches!(e.value, Cow::Owned(_)))
+        .count();
+    assert_eq!(copied, 8);
+}
+
+#[test]
+fn reader_page_source_reads_a_wallet_embedded_at_an_offset() {
+    let wallet = carved_wallet(1024, 40);
+    let image = wallet.build().unwrap();
+    let mut container = vec![0xee; 1000];
+    container.extend(&image);
+    container.extend([0xee; 100]);
+
+    let source = ReaderPageSource::new(Cursor::new(container.clone()), 1000, "embedded").unwrap();
+    assert_eq!(source.page_size(), 1024);
+    assert_eq!(source.page_count(), Some((image.len() / 1024) as u64));
+    assert_eq!(source.trailing_bytes(), 100);
+    assert_eq!(source.read_page(1).unwrap(), image[1024..2048]);
+    assert!(
+        source
+            .read_page(source.page_count().unwrap() as u32)
+            .is_err()
+    );
+
+    let reader = FileDbImageReader::from_source(Arc::new(source)).unwrap();
+    let records: BTreeMap<Vec<u8>, Vec<u8>> = reader
+        .entries(SalvageMode::Conservative)
+        .map(|(k, v, _)| (k, v.materialize().unwrap()))
+        .collect();
+    assert_eq!(records.len(), 40);
+    for (key, entry) in wallet.live_records().iter() {
+        assert_eq!(records.get(key), Some(&entry.value));
+    }
+
+    // The wrong offset finds no meta page; one past the end is refused outright.
+    assert!(ReaderPageSource::new(Cursor::new(container.clone()), 512, "off").is_err());
+    let err = ReaderPageSource::new(Cursor::new(container), 1_000_000, "past").unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+}
+
+#[test]
+fn a_damaged_meta_page_is_rebuilt_from_the_rest_of_the_image() {
+    let wallet = carved_wallet(1024, 50);
+    let main = FileDbImageReader::from_image(wallet.build().unwrap(), "intact")
+        .unwrap()
+        .subdatabases()
+        .unwrap()
+        .remove(0);
+    let expected = wallet.live_records();
+
+    for (what, damage) in [
+        (
+            "magic",
+            Corruption::Patch {
+                pgno: 0,
+                offset: 12,
+                bytes: vec![0xff],
+            },
+        ),
+        ("page 0", Corruption::ZeroPage(0)),
+    ] {
+        let image = wallet.clone().corrupt(damage).build().unwrap();
+        let reader = FileDbImageReader::from_image(image, what).unwrap();
+        let rebuilt: Vec<_> = reader
+            .diagnostics()
+            .into_iter()
+            .filter(|f| matches!(f.kind, FindingKind::MetaRebuilt { .. }))
+            .collect();
+        assert_eq!(rebuilt.len(), 1, "{what}");
+        assert_eq!(
+            rebuilt[0].kind,
+            FindingKind::MetaRebuilt {
+                template: Some(main.meta_pgno)
+            },
+            "{what}"
+        );
+
+        let map = reader.build_map(SalvageMode::Conservative).unwrap();
+        assert_eq!(map.len(), expected.len(), "{what}");
+        for (key, entry) in expected.iter() {
+            assert_eq!(map.get(key), Some(&entry.value), "{what}");
+        }
+    }
+
+    // Without subdatabase meta pages or page headers there is nothing to rebuild from.
+    let blank = vec![0; 4 * 1024];
+    assert!(FileDbImageReader::from_image(blank, "blank").is_err());
+}
+
+/// An image where the first wallet leaf was also written over a later one, as a page
+/// split can leave a stale copy behind. Returns the image, the leaf and the copy.
+fn stale_copy() -> (Vec<u8>, usize, usize) {
+    let mut image = carved_wallet(512, 60).build().unwrap();
+    let leaves: Vec<usize> = image
+        .chunks_exact(512)
+        .enumerate()
+        .filter(|(_, page)| page[25] == 5 && page.windows(3).any(|w| w == b"\x02tx"))
+        .map(|(pgno, _)| pgno)
+        .collect();
+    let (leaf, copy) = (leaves[0], *leaves.last().unwrap());
+    image.copy_within(leaf * 512..(leaf + 1) * 512, copy * 512);
+    image[copy * 512 + 8..][..4].copy_from_slice(&(copy as u32).to_le_bytes());
+    // The copy was written later.
+    image[copy * 512..][..4].copy_from_slice(&1u32.to_le_bytes());
+    (image, leaf, copy)
+}
+
+#[test]
+fn keys_read_twice_are_settled_by_the_conflict_policy() {
+    let (image, leaf, copy) = stale_copy();
+    let reader = FileDbImageReader::from_image(image, "stale").unwrap();
+    let copies = |map: &dyn InMemoryMap, pgno: usize| {
+        map.iter()
+            .filter(|(_, e)| e.meta.as_ref().unwrap().page_no == pgno as u32)
+            .count()
+    };
+
+    let newest = reader
+        .build_map_with(SalvageMode::BestEffort, ConflictPolicy::HighestLsn)
+        .unwrap();
+    let duplicated = copies(&*newest, copy);
+    assert!(duplicated > 0);
+    assert_eq!(copies(&*newest, leaf), 0);
+
+    let all = reader
+        .build_map_with(SalvageMode::BestEffort, ConflictPolicy::KeepAll)
+        .unwrap();
+    assert_eq!(all.len(), newest.len());
+    let kept: Vec<_> = all
+        .iter()
+        .filter(|(_, e)| !e.duplicates.is_empty())
+        .collect();
+    assert_eq!(kept.len(), duplicated);
+    for (_, entry) in kept {
+        let pages = [&entry.meta, &entry.duplicates[0].meta].map(|m| m.as_ref().unwrap().page_no);
+        assert_eq!(pages, [leaf as u32, copy as u32]);
+        assert_eq!(entry.value, entry.duplicates[0].value);
+    }
+
+    let err = reader
+        .build_map_with(SalvageMode::BestEffort, ConflictPolicy::Error)
+        .err()
+        .unwrap();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    assert!(err.to_string().contains(&format!("page {copy}")), "{err}");
+}
+
+#[test]
+fn repack_keeps_every_live_record_in_fewer_pages() {
+    // Most of the wallet was erased, as by `-zapwallettxes`, and left on its pages.
+    let wallet = (0u32..300).fold(FixtureBuilder::new().page_size(512), |b, i| {
+        let key = wallet_key("tx", &i.to_be_bytes());
+        let value = vec![i as u8; if i % 10 == 5 { 2_000 } else { 60 }];
+        match i % 10 {
+            0 => b.record(key, value),
+            _ => b.deleted_record(key, value),
+        }
+    });
+    let reader = FileDbImageReader::from_image(wallet.build().unwrap(), "zapped").unwrap();
+    let (image, report) = repack(&reader).unwrap();
+    assert_eq!(report.records, 30);
+    assert_eq!(report.page_size, 512);
+    assert_eq!(report.pages_after, (image.len() / 512) as u64);
+    assert!(
+        report.pages_after * 3 < report.pages_before,
+        "{} -> {}",
+        report.pages_before,
+        report.pages_after
+    );
+
+    let repacked = FileDbImageReader::from_image(image, "repacked").unwrap();
+    assert_eq!(repacked.subdatabases().unwrap()[0].name, b"main");
+    let map = repacked.build_map(SalvageMode::Conservative).unwrap();
+    let expected = wallet.live_records();
+    assert_eq!(map.len(), expected.len());
+    for (key, entry) in expected.iter() {
+        assert_eq!(map.get(key), Some(&entry.value));
+    }
+    assert!(repacked.deleted_entries().next().is_none());
+    assert!(repacked.diagnostics().is_empty());
+}
+
+#[test]
+fn repack_refuses_what_it_cannot_rewrite_faithfully() {
+    let (recno, _) = recno_image(&[(false, b"abc")]);
+    let reader = FileDbImageReader::from_image(recno, "recno").unwrap();
+    assert_eq!(
+        repack(&reader).unwrap_err().kind(),
+        io::ErrorKind::Unsupported
+    );
+
+    let (stale, _, _) = stale_copy();
+    let reader = FileDbImageReader::from_image(stale, "stale").unwrap();
+    assert!(repack(&reader).is_err());
+}
+
+/// A wallet whose `name` record keeps its value in blob `blob_id` of subdatabase 2, as
+/// Berkeley DB 6.x does for values above the blob threshold.
+fn blob_image(blob_id: u64, size: u64) -> Vec<u8> {
+    // A 45-byte value makes a 48-byte item, the size of a `BBLOB` item.
+    let marker = [0xbb; 45];
+    let mut image = FixtureBuilder::new()
+        .page_size(512)
+        .wallet_record("name", b"t1", marker.to_vec())
+        .wallet_record("tx", &[1], vec![1; 20])
+        .build()
+        .unwrap();
+    let at = image.windows(45).position(|w| w == marker).unwrap() - 3;
+    let item = &mut image[at..at + 48];
+    item.fill(0);
+    item[2] = B_BLOB;
+    for (field, n) in [(16, blob_id), (24, size), (32, 1), (40, 2)] {
+        item[field..field + 8].copy_from_slice(&n.to_le_bytes());
+    }
+    image
+}
+
+/// Blobs held in memory, by id.
+struct Blobs(BTreeMap<u64, Vec<u8>>);
+
+impl BlobResolver for Blobs {
+    fn read_blob(&self, reference: &BlobRef) -> io::Result<ByteVec> {
+        self.0
+            .get(&reference.blob_id)
+            .cloned()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, reference.to_string()))
+    }
+}
+
+fn name_value(reader: &FileDbImageReader) -> io::Result<ByteVec> {
+    let (_, value, _) = reader
+        .entries(SalvageMode::Conservative)
+        .find(|(key, _, _)| key.starts_with(&wallet_key("name", &[])))
+        .unwrap();
+    value.materialize()
+}
+
+#[test]
+fn blob_values_are_read_through_the_resolver() {
+    let value: Vec<u8> = (0..5_000u32).map(|i| i as u8).collect();
+    let image = blob_image(1005, value.len() as u64);
+
+    let reader = FileDbImageReader::from_image(image.clone(), "blob").unwrap();
+    assert!(name_value(&reader).is_err());
+
+    let blobs = Blobs(BTreeMap::from([(1005, value.clone())]));
+    let reader = reader.with_blob_resolver(Arc::new(blobs));
+    assert_eq!(name_value(&reader).unwrap(), value);
+    let map = reader.build_map(SalvageMode::Conservative).unwrap();
+    assert_eq!(map.get(&wallet_key("name", b"t1")), Some(&value));
+    assert_eq!(map.get(&wallet_key("tx", &[1])), Some(&vec![1; 20]));
+    assert!(reader.diagnostics().is_empty());
+}
+
+#[test]
+fn blob_directory_follows_the_berkeley_db_layout() {
+    let path = |blob_id| {
+        BlobRef {
+            encoding: 0,
+            blob_id,
+            size: 0,
+            file_id: 1,
+            sdb_id: 2,
+        }
+        .relative_path()
+    };
+    assert_eq!(path(7), Path::new("__db1/__db2/__db.bl007"));
+    assert_eq!(path(1005), Path::new("__db1/__db2/001/__db.bl001005"));
+    assert_eq!(
+        path(2_003_004),
+        Path::new("__db1/__db2/002/003/__db.bl002003004")
+    );
+
+    let root = env::temp_dir().join(format!("walletdb-blobs-{}", std::process::id()));
+    let file = root.join(path(1005));
+    fs::create_dir_all(file.parent().unwrap()).unwrap();
+    fs::write(&file, b"abc").unwrap();
+    let reader = FileDbImageReader::from_image(blob_image(1005, 3), "blob")
+        .unwrap()
+        .with_blob_resolver(Arc::new(BlobDirectory::new(&root)));
+    let read = name_value(&reader);
+    let short = FileDbImageReader::from_image(blob_image(1005, 4), "blob")
+        .unwrap()
+        .with_blob_resolver(Arc::new(BlobDirectory::new(&root)));
+    let mismatch = name_value(&short);
+    fs::remove_dir_all(&root).unwrap();
+    assert_eq!(read.unwrap(), b"abc");
+    assert_eq!(mismatch.unwrap_err().kind(), io::ErrorKind::InvalidData);
+}
+
+#[test]
+fn annotated_dump_labels_the_parts_of_a_leaf() {
+    let image = FixtureBuilder::new()
+        .page_size(512)
+        .wallet_record("name", b"t1", b"label".to_vec())
+        .deleted_record(wallet_key("tx", &[1]), vec![1; 10])
+        .wallet_record("tx", &[2], vec![2; 900])
+        .build()
+        .unwrap();
+    let pages: Vec<&[u8]> = image.chunks_exact(512).collect();
+    let dump = |pgno: usize| {
+        Page::parse(
+            pages[pgno].to_vec(),
+            Endianness::Little,
+            PageProtection::None,
+        )
+        .unwrap()
+        .annotated_dump(Endianness::Little, PageProtection::None)
+    };
+
+    let meta = dump(0);
+    assert!(meta.starts_with("page 0 (Meta, 512 bytes)\n"), "{meta}");
+    for field in ["magic = ", "pagesize = 512", "root = ", "uid"] {
+        assert!(meta.contains(field), "{field}: {meta}");
+    }
+
+    let leaf = pages
+        .iter()
+        .rposition(|page| page[25] == 5 && page.windows(3).any(|w| w == b"\x02tx"))
+        .unwrap();
+    let dump = dump(leaf);
+    for field in [
+        "type = 5 (BtreeLeaf)",
+        "entries = 6",
+        "slot[0] = 504",
+        "[0] key len = 4",
+        "[0] key type = KEYDATA|DELETE",
+        "[1] value len = 10",
+        "[3] value type = OVERFLOW",
+        "[3] value tlen = 900",
+        "[4] key type = KEYDATA",
+        "[5] value data",
+    ] {
+        assert!(dump.contains(field), "{field}: {dump}");
+    }
+    // Each line is an offset, 16 bytes of hex and their ASCII.
+    let line = dump.lines().find(|l| l.ends_with("[4] key data")).unwrap();
+    assert!(
+        line.starts_with("01cb  04 6e 61 6d 65 74 31 ") && line.contains("|.namet1 "),
+        "{line}"
+    );
+
+    // A slot pointing past the page is reported, not followed.
+    let mut bad = pages[leaf].to_vec();
+    bad[26..28].copy_from_slice(&600u16.to_le_bytes());
+    let dump = Page::parse(bad, Endianness::Little, PageProtection::None)
+        .unwrap()
+        .annotated_dump(Endianness::Little, PageProtection::None);
+    assert!(dump.contains("[0] key: past the page"), "{dump}");
+}
+
+fn memory(image: &[u8], page_size: u32) -> MemoryPageSource {
+    MemoryPageSource::new(image.to_vec(), page_size, "diff").unwrap()
+}
+
+#[test]
+fn diff_pages_tells_a_stale_backup_from_the_live_wallet() {
+    let backup = carved_wallet(512, 40).build().unwrap();
+    let leaf = backup.chunks_exact(512).rposition(|p| p[25] == 5).unwrap();
+    // The live wallet rewrote one leaf under a later LSN and grew by a page.
+    let mut live = backup.clone();
+    live[leaf * 512..][..8].copy_from_slice(&[2, 0, 0, 0, 0x10, 0, 0, 0]);
+    live[(leaf + 1) * 512 - 1] ^= 0xff;
+    let mut grown = live[leaf * 512..][..512].to_vec();
+    grown[8..12].copy_from_slice(&((backup.len() / 512) as u32).to_le_bytes());
+    live.extend(grown);
+
+    let same = diff_pages(&memory(&backup, 512), &memory(&backup, 512)).unwrap();
+    assert!(same.is_identical() && !same.b_is_newer());
+    assert_eq!(same.unchanged, (backup.len() / 512) as u64);
+
+    let diff = diff_pages(&memory(&backup, 512), &memory(&live, 512)).unwrap();
+    assert_eq!(
+        (diff.pages_a + 1, diff.pages_b),
+        (diff.pages_b, diff.pages_b)
+    );
+    assert_eq!(diff.unchanged, diff.pages_a - 1);
+    let changed: Vec<(u32, bool, bool)> = diff
+        .changes
+        .iter()
+        .map(|d| (d.pgno, d.a.is_some(), d.b.is_some()))
+        .collect();
+    assert_eq!(
+        changed,
+        [
+            (leaf as u32, true, true),
+            (diff.pages_a as u32, false, true)
+        ]
+    );
+    assert_eq!(diff.changes[0].b.as_ref().unwrap().lsn, (2 << 32) | 0x10);
+    assert!(diff.b_is_newer());
+    let shown = diff.to_string();
+    assert!(shown.contains(": changed, lsn ") && shown.contains(": only in b, lsn "));
+    assert!(
+        !diff_pages(&memory(&live, 512), &memory(&backup, 512))
+            .unwrap()
+            .b_is_newer()
+    );
+
+    // Same LSN, different bytes: changed outside the log.
+    let mut patched = backup.clone();
+    patched[(leaf + 1) * 512 - 1] ^= 0xff;
+    let diff = diff_pages(&memory(&backup, 512), &memory(&patched, 512)).unwrap();
+    assert_eq!(diff.changes.len(), 1);
+    assert!(diff.changes[0].is_unlogged() && !diff.b_is_newer());
+
+    let wide = carved_wallet(1024, 40).build().unwrap();
+    let err = diff_pages(&memory(&backup, 512), &memory(&wide, 1024)).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+}
+
+#[test]
+fn scan_tag_reads_only_the_leaves_of_its_range() {
+    let wallet = (0u32..200).fold(FixtureBuilder::new().page_size(512), |b, i| {
+        b.wallet_record("key", &i.to_be_bytes(), vec![1; 40])
+            .wallet_record("tx", &i.to_be_bytes(), vec![2; 40])
+            .wallet_record("txs", &i.to_be_bytes(), vec![3; 40])
+    });
+    let image = wallet.build().unwrap();
+    let pages = image.len() / 512;
+    let source = Arc::new(Counting(
+        MemoryPageSource::new(image, 512, "scan").unwrap(),
+        AtomicUsize::new(0),
+    ));
+    let db = WalletDb::from_source(source.clone()).unwrap();
+    let reads = || source.1.swap(0, Ordering::Relaxed);
+    reads();
+
+    let expected: Vec<Vec<u8>> = wallet
+        .live_records()
+        .with_tag("tx")
+        .map(|e| e.key.clone())
+        .collect();
+    let scanned: Vec<Vec<u8>> = db.scan_tag("tx").map(|e| e.unwrap().0).collect();
+    assert_eq!(scanned.len(), 200);
+    assert_eq!(scanned, expected);
+    let scan_reads = reads();
+    assert!(scan_reads * 2 < pages, "{scan_reads} of {pages} pages");
+
+    // A prefix inside the range, one past its end, and one matching nothing.
+    assert_eq!(db.scan_prefix(&wallet_key("tx", &[0, 0, 0])).count(), 200);
+    let last = db
+        .scan_prefix(&wallet_key("tx", &199u32.to_be_bytes()))
+        .map(|e| e.unwrap().1)
+        .collect::<Vec<_>>();
+    assert_eq!(last, [vec![2; 40]]);
+    assert_eq!(db.scan_tag("name").count(), 0);
+    assert_eq!(db.scan_prefix(b"").count(), 600);
+}
+
+#[test]
+fn environment_files_in_a_datadir_are_recognised_and_explained() {
+    let classify = |name: &str| EnvironmentFile::classify(Path::new(name));
+    assert_eq!(classify("/data/__db.001"), Some(EnvironmentFile::Region(1)));
+    assert_eq!(
+        classify("database/log.0000000012"),
+        Some(EnvironmentFile::Log(12))
+    );
+    for name in ["wallet.dat", "__db.1", "__db.abc", "log.12", "debug.log"] {
+        assert_eq!(classify(name), None, "{name}");
+    }
+
+    let home = env::temp_dir().join(format!("walletdb-datadir-{}", std::process::id()));
+    fs::create_dir_all(home.join("database")).unwrap();
+    let wallet = carved_wallet(512, 5).build().unwrap();
+    fs::write(home.join("wallet.dat"), &wallet).unwrap();
+    for name in ["__db.002", "__db.001", "peers.dat", "debug.log"] {
+        fs::write(home.join(name), [0x42; 1024]).unwrap();
+    }
+    for name in ["log.0000000002", "log.0000000001"] {
+        fs::write(home.join("database").join(name), [0; 64]).unwrap();
+    }
+    let scanned = scan_environment(&home);
+    let region = FilePageSource::open(home.join("__db.001"));
+    fs::remove_dir_all(&home).unwrap();
+
+    let env = scanned.unwrap();
+    let names = |paths: &[PathBuf]| -> Vec<String> {
+        paths
+            .iter()
+            .map(|p| p.file_name().unwrap().to_string_lossy().into_owned())
+            .collect()
+    };
+    assert_eq!(names(&env.databases), ["wallet.dat"]);
+    assert_eq!(names(&env.regions), ["__db.001", "__db.002"]);
+    assert_eq!(names(&env.logs), ["log.0000000001", "log.0000000002"]);
+    assert_eq!(env.wallet(), Some(home.join("wallet.dat").as_path()));
+    let notes = env.notes();
+    assert_eq!(notes.len(), 2);
+    assert!(notes[0].starts_with("2 region files") && notes[1].starts_with("2 log files"));
+
+    let err = region.unwrap_err().to_string();
+    assert!(
+        err.contains("__db.001 is an environment region file"),
+        "{err}"
+    );
+}
+
+#[test]
+fn a_leaf_no_tree_points_at_is_an_orphan() {
+    let builder = (0u32..60).fold(FixtureBuilder::new().page_size(512), |b, i| {
+        b.wallet_record("tx", &i.to_be_bytes(), vec![i as u8; 40])
+    });
+    let mut image = builder.build().unwrap();
+    let leaf = BorrowedImage::new(&image)
+        .unwrap()
+        .entries()
+        .nth(30)
+        .unwrap()
+        .unwrap()
+        .page_no;
+    // A copy of that leaf left behind at the end of the file, as an interrupted split
+    // leaves one.
+    let detached = (image.len() / 512) as PageNumber;
+    let mut copy = image[leaf as usize * 512..][..512].to_vec();
+    copy[8..12].copy_from_slice(&detached.to_le_bytes());
+    image.extend_from_slice(&copy);
+    let entries = u16::from_le_bytes([copy[20], copy[21]]);
+
+    let reader = FileDbImageReader::from_image(image, "orphans").unwrap();
+    let report = analyze_reachability(&reader).unwrap();
+    assert_eq!(
+        report.orphans,
+        [OrphanPage {
+            pgno: detached,
+            page_type: 5,
+            entries,
+        }]
+    );
+    assert!(report.reachable.contains(&leaf));
+    assert!(!report.reachable.contains(&detached));
+    // Every other page, the meta page included, is reached.
+    let accounted = report.reachable.len() + report.free.len() + report.orphans.len();
+    assert_eq!(accounted as u64, report.page_count);
+    assert_eq!(report.orphan_leaves().collect::<Vec<_>>(), [detached]);
+
+    // Its records are recovered from the copy, not from the leaf still in the tree.
+    let recovered: Vec<(Vec<u8>, u32)> = reader
+        .orphan_entries()
+        .unwrap()
+        .map(|(key, _, p)| (key, p.page_no))
+        .collect();
+    assert_eq!(recovered.len(), entries as usize / 2);
+    assert!(recovered.iter().all(|(_, pgno)| *pgno == detached));
+}
+
+/// A small wallet with free pages appended, each linked to the page paired with it, and
+/// the meta page pointing at the first. `last_pgno` is the last page appended.
+fn with_free_pages(links: &[(PageNumber, PageNumber)]) -> MemoryPageSource {
+    let mut image = FixtureBuilder::new()
+        .page_size(512)
+        .wallet_record("tx", &[1], vec![1; 20])
+        .build()
+        .unwrap();
+    for (pgno, next) in links {
+        assert_eq!(image.len() / 512, *pgno as usize);
+        let mut page = vec![0; 512];
+        page[8..12].copy_from_slice(&pgno.to_le_bytes());
+        page[16..20].copy_from_slice(&next.to_le_bytes());
+        image.extend_from_slice(&page);
+    }
+    let last = (image.len() / 512 - 1) as PageNumber;
+    image[28..32].copy_from_slice(&links[0].0.to_le_bytes());
+    image[32..36].copy_from_slice(&last.to_le_bytes());
+    memory(&image, 512)
+}
+
+#[test]
+fn freelist_walks_stop_at_cycles_and_links_out_of_range() {
+    let first = (FixtureBuilder::new()
+        .page_size(512)
+        .wallet_record("tx", &[1], vec![1; 20])
+        .build()
+        .unwrap()
+        .len()
+        / 512) as PageNumber;
+
+    let chain = walk_freelist(&with_free_pages(&[(first, first + 1), (first + 1, 0)])).unwrap();
+    assert_eq!(chain.pages, [first, first + 1]);
+    assert_eq!(chain.issues, []);
+
+    let looping = walk_freelist(&with_free_pages(&[(first, first)])).unwrap();
+    assert_eq!(looping.pages, [first]);
+    assert_eq!(
+        looping.issues,
+        [FreeListIssue::Cycle {
+            from: first,
+            to: first,
+        }]
+    );
+
+    let past_the_end = walk_freelist(&with_free_pages(&[(first, first + 5)])).unwrap();
+    assert_eq!(past_the_end.last_pgno, first);
+    assert_eq!(past_the_end.pages, [first]);
+    assert_eq!(
+        past_the_end.issues,
+        [FreeListIssue::OutOfRange {
+            from: first,
+            to: first + 5,
+        }]
+    );
+    assert!(past_the_end.to_string().contains(&format!(
+        "page {first} links to out-of-range page {}",
+        first + 5
+    )));
+}
+
+/// A key sharing a long prefix with its neighbours, so that the separators between
+/// leaves are past the overflow threshold of a 512-byte page.
+fn long_key(i: u16) -> Vec<u8> {
+    let mut rest = vec![7; 120];
+    rest.extend_from_slice(&i.to_be_bytes());
+    wallet_key("k", &rest)
+}
+
+#[test]
+fn get_descends_a_multi_level_tree_to_the_key() {
+    let wallet = (0u16..300)
+        .filter(|&i| i != 151)
+        .fold(FixtureBuilder::new().page_size(512), |b, i| {
+            b.record(long_key(i), i.to_le_bytes().to_vec())
+        })
+        .deleted_record(long_key(151), vec![0xde; 2]);
+    let image = wallet.build().unwrap();
+
+    // The lookups below go through internal pages whose separators are on overflow pages.
+    let overflow_separators = image
+        .chunks(512)
+        .filter(|page| page[25] == P_IBTREE)
+        .flat_map(|page| {
+            (0..u16::from_le_bytes([page[20], page[21]]) as usize).map(move |i| {
+                let at = u16::from_le_bytes([page[26 + i * 2], page[27 + i * 2]]) as usize;
+                page[at + 2]
+            })
+        })
+        .filter(|&item_type| item_type == B_OVERFLOW)
+        .count();
+    assert!(overflow_separators > 1, "{overflow_separators}");
+
+    let db = WalletDb::from_source(Arc::new(memory(&image, 512))).unwrap();
+    for i in [0, 150, 152, 299] {
+        let (value, prov) = db.get(&long_key(i)).unwrap().unwrap();
+        assert_eq!(value, i.to_le_bytes());
+        let page = &image[prov.page_no as usize * 512..][..512];
+        assert_eq!(page[25], P_LBTREE, "page {} is not a leaf", prov.page_no);
+        assert_eq!(prov.confidence, Confidence::High);
+    }
+
+    // The deleted pair is still on its page, but is not found.
+    assert!(db.get(&long_key(151)).unwrap().is_none());
+    let mut between = long_key(150);
+    between.push(0);
+    for missing in [
+        between,
+        long_key(300),
+        wallet_key("k", &[7; 120]),
+        wallet_key("j", &[]),
+    ] {
+        assert!(db.get(&missing).unwrap().is_none(), "{missing:?}");
+    }
+}
+
+#[test]
+fn stats_count_pages_by_type_and_measure_large_values() {
+    let image = FixtureBuilder::new()
+        .page_size(512)
+        .wallet_record("tx", &[1], vec![1; 20])
+        .wallet_record("tx", &[2], vec![2; 300])
+        .wallet_record("tx", &[3], vec![3; 1000])
+        .deleted_record(wallet_key("tx", &[4]), vec![4; 20])
+        .build()
+        .unwrap();
+    let reader = FileDbImageReader::from_image(image, "stats").unwrap();
+    let stats = collect_stats(&reader, 2).unwrap();
+    assert_eq!((stats.page_size, stats.page_count), (512, 8));
+    assert_eq!(
+        stats.page_types,
+        BTreeMap::from([(P_LBTREE, 2), (P_OVERFLOW, 4), (P_BTREEMETA, 2)])
+    );
+    assert_eq!(stats.unreadable_pages, 0);
+    assert_eq!(stats.levels, BTreeMap::from([(1, 2)]));
+    assert_eq!(stats.leaf_pages, 2);
+    // Three live wallet records and the master database's entry; the deleted pair is not
+    // counted.
+    assert_eq!(stats.records, 4);
+    // 300 bytes fit on one overflow page, 1000 need three.
+    assert_eq!(stats.overflow_chains, BTreeMap::from([(1, 1), (3, 1)]));
+    assert_eq!(stats.overflow_pages(), 4);
+    let largest: Vec<_> = stats
+        .largest
+        .iter()
+        .map(|r| (r.key.clone(), r.value_len))
+        .collect();
+    assert_eq!(
+        largest,
+        [
+            (wallet_key("tx", &[3]), 1000),
+            (wallet_key("tx", &[2]), 300)
+        ]
+    );
+    assert_eq!(stats.free_pages, 0);
+
+    // A blob value is measured by the size its reference records, without the blob.
+    let reader = FileDbImageReader::from_image(blob_image(1005, 5_000), "blob").unwrap();
+    let stats = collect_stats(&reader, 1).unwrap();
+    assert_eq!(stats.overflow_chains, BTreeMap::new());
+    assert_eq!(stats.largest[0].key, wallet_key("name", b"t1"));
+    assert_eq!(stats.largest[0].value_len, 5_000);
+}
+
+#[test]
+fn pages_written_after_the_checkpoint_are_warned_about() {
+    let wallet = FixtureBuilder::new()
+        .page_size(512)
+        .wallet_record("tx", &[1], vec![1; 20]);
+    let detached = FileDbImageReader::from_image(wallet.build().unwrap(), "lsn").unwrap();
+    let report = analyze_lsns(&detached, parse_lsn("2/200"));
+    assert!(report.is_detached());
+    assert_eq!(report.warnings(), Vec::<String>::new());
+
+    // Page 1 was last written in log 1, pages 2 and 3 in log 2, before and after the
+    // checkpoint.
+    let stamp = |pgno, file: u32, offset: u32| Corruption::Patch {
+        pgno,
+        offset: 0,
+        bytes: [file.to_le_bytes(), offset.to_le_bytes()].concat(),
+    };
+    let image = wallet
+        .corrupt(stamp(1, 1, 50))
+        .corrupt(stamp(2, 2, 100))
+        .corrupt(stamp(3, 2, 500))
+        .build()
+        .unwrap();
+    let reader = FileDbImageReader::from_image(image, "lsn").unwrap();
+
+    let report = analyze_lsns(&reader, None);
+    assert_eq!(report.live_pages(), 3);
+    assert_eq!(report.log_files, BTreeMap::from([(1, 1), (2, 2)]));
+    assert_eq!(report.max, Some((3, parse_lsn("[2][500]").unwrap())));
+    assert_eq!(report.past_checkpoint, []);
+    assert_eq!(report.warnings().len(), 1);
+
+    let report = analyze_lsns(&reader, parse_lsn("2/200"));
+    assert_eq!(
+        report.past_checkpoint,
+        [(3, parse_lsn("[2][500]").unwrap())]
+    );
+    let warnings = report.warnings();
+    assert_eq!(warnings.len(), 2);
+    assert!(warnings[0].starts_with("3 pages carry live LSNs"));
+    assert_eq!(
+        warnings[1],
+        "1 pages were written after the checkpoint at [2][200] (up to [2][500] on page 3): \
+         the copy may have been taken mid-write"
+    );
+
+    // A checkpoint past every page leaves only the warning about the environment.
+    let report = analyze_lsns(&reader, parse_lsn("3/0"));
+    assert_eq!(report.past_checkpoint, []);
+    assert_eq!(report.warnings().len(), 1);
+}
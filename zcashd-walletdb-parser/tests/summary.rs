@@ -0,0 +1,72 @@
+//! [`WalletSummary`] of a shipped fixture, clean and with a dangling record.
+
+use pretty_assertions::assert_eq;
+use zcashd_walletdb_parser::{
+    parser::{
+        decoders::{keypool::KeyPoolEntry, network::ZcashNetwork},
+        summary::WalletSummary,
+    },
+    storage::walletdb::WalletDb,
+};
+
+const WALLET: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../dat_files/wallet0.dat");
+
+#[test]
+fn summary_counts_what_the_wallet_holds() {
+    let db = WalletDb::open(WALLET).unwrap();
+    let summary = db.summary().unwrap();
+    let wallet = db.decode().unwrap();
+    assert_eq!(summary.network, Some(ZcashNetwork::Regtest));
+    assert!(!summary.encrypted);
+    assert_eq!(
+        summary.seed_fingerprint,
+        wallet.crypto.mnemonic.as_ref().map(|m| m.fingerprint)
+    );
+    assert_eq!(
+        (
+            summary.transparent_keys,
+            summary.sprout_keys,
+            summary.sapling_keys,
+            summary.unified_accounts
+        ),
+        (52, 0, 0, 0)
+    );
+    assert_eq!(summary.addresses, 52);
+    assert_eq!(summary.transactions, 50);
+    assert_eq!(summary.best_height, Some(176));
+    assert_eq!(
+        summary.best_block_hash.as_deref(),
+        Some("07d05fb47aab07ef185ca2c6d718da87bf02614f89f9d4b0420235f161022d8c")
+    );
+    let newest = wallet
+        .keys
+        .transparent_metadata
+        .values()
+        .map(|meta| meta.create_time)
+        .max();
+    assert_eq!(summary.last_key_birth, newest);
+    assert_eq!(summary.problems, Vec::<String>::new());
+    assert!(summary.to_string().contains("  problems     : 0\n"));
+}
+
+#[test]
+fn summary_lists_the_problems_of_the_deeper_reports() {
+    let mut wallet = WalletDb::open(WALLET).unwrap().decode().unwrap();
+    wallet.keys.key_pool.insert(
+        1000,
+        KeyPoolEntry {
+            index: 1000,
+            version: 6_000_050,
+            time: 0,
+            pubkey: vec![2; 33],
+        },
+    );
+    let summary = WalletSummary::of(&wallet);
+    assert_eq!(summary.problems.len(), 1);
+    assert!(summary.problems[0].starts_with("pool 1000: no key"));
+    assert!(
+        summary
+            .to_string()
+            .contains("  problem      : pool 1000: no key")
+    );
+}
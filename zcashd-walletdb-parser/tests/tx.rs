@@ -0,0 +1,106 @@
+//! Transactions re-serialize byte for byte and hash to their txids.
+
+use pretty_assertions::assert_eq;
+use zcashd_walletdb_parser::{
+    parser::{serialize::Reader, tx::Transaction},
+    storage::walletdb::WalletDb,
+};
+
+const WALLETS: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../dat_files");
+
+fn read(bytes: &[u8]) -> Transaction {
+    let mut r = Reader::new(bytes);
+    let tx = Transaction::read(&mut r).unwrap();
+    r.finish().unwrap();
+    tx
+}
+
+#[test]
+fn fixture_transactions_hash_to_their_keys() {
+    for n in 0..8 {
+        let wallet = WalletDb::open(format!("{WALLETS}/wallet{n}.dat"))
+            .unwrap()
+            .decode()
+            .unwrap();
+        for (txid, wtx) in &wallet.transactions {
+            let tx = wtx.tx();
+            assert_eq!(read(&tx.to_bytes()), *tx);
+            assert_eq!(tx.txid(), *txid, "wallet{n}: {}", wtx.txid_hex());
+        }
+    }
+}
+
+/// A v5 transaction with one transparent output, one Sapling spend and output, and one
+/// Orchard action.
+fn v5_transaction() -> Vec<u8> {
+    let mut tx = (5u32 | 1 << 31).to_le_bytes().to_vec();
+    for word in [0x26a7_270a_u32, 0xc2d6_d0b4, 0, 110] {
+        tx.extend_from_slice(&word.to_le_bytes());
+    }
+    tx.extend_from_slice(&[0, 1]); // no inputs, one output
+    tx.extend_from_slice(&5_000i64.to_le_bytes());
+    tx.extend_from_slice(&[1, 0x51]);
+    tx.push(1); // sapling spend: cv, nullifier, rk
+    tx.extend_from_slice(&[0xa1; 96]);
+    tx.push(1); // sapling output without its proof
+    tx.extend_from_slice(&[0xa2; 756]);
+    tx.extend_from_slice(&(-5_000i64).to_le_bytes());
+    tx.extend_from_slice(&[0xa3; 32]); // anchor
+    tx.extend_from_slice(&[0xa4; 192 + 64 + 192 + 64]); // proofs and signatures
+    tx.push(1); // orchard action
+    tx.extend_from_slice(&[0xb1; 820]);
+    tx.push(3);
+    tx.extend_from_slice(&0i64.to_le_bytes());
+    tx.extend_from_slice(&[0xb2; 32]);
+    tx.push(10);
+    tx.extend_from_slice(&[0xb3; 10 + 64 + 64]);
+    tx
+}
+
+#[test]
+fn every_version_round_trips() {
+    let bytes = v5_transaction();
+    let tx = read(&bytes);
+    assert_eq!(tx.to_bytes(), bytes);
+
+    // A v2 transaction with a JoinSplit and a v4 one with nothing shielded.
+    let mut v2 = 2u32.to_le_bytes().to_vec();
+    v2.push(1);
+    v2.extend_from_slice(&[0x11; 36]);
+    v2.extend_from_slice(&[0, 0xff, 0xff, 0xff, 0xff]);
+    v2.extend_from_slice(&[0, 0, 0, 0, 0]); // no outputs, lock time
+    v2.push(1);
+    v2.extend_from_slice(&100_000i64.to_le_bytes());
+    v2.extend_from_slice(&0i64.to_le_bytes());
+    v2.extend_from_slice(&[0xc1; 32 * 9 + 296 + 2 * 601]);
+    v2.extend_from_slice(&[0xc2; 32 + 64]);
+    let mut v4 = (4u32 | 1 << 31).to_le_bytes().to_vec();
+    v4.extend_from_slice(&0x892f_2085_u32.to_le_bytes());
+    v4.extend_from_slice(&[0, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+    v4.extend_from_slice(&[0; 8 + 3]); // value balance, no spends, outputs or joinsplits
+    for bytes in [v2, v4] {
+        assert_eq!(read(&bytes).to_bytes(), bytes);
+    }
+}
+
+#[test]
+fn v5_txids_commit_to_effects_not_authorization() {
+    let tx = read(&v5_transaction());
+    let txid = tx.txid();
+
+    let mut signed = tx.clone();
+    signed.orchard.as_mut().unwrap().actions[0].spend_auth_sig = [0; 64];
+    signed.sapling.as_mut().unwrap().outputs[0].zkproof = vec![0; 192];
+    signed.orchard.as_mut().unwrap().binding_sig = [0; 64];
+    assert_eq!(signed.txid(), txid);
+
+    let mut paid = tx.clone();
+    paid.vout[0].value += 1;
+    let mut expiring = tx.clone();
+    expiring.expiry_height = Some(111);
+    let mut memo = tx;
+    memo.sapling.as_mut().unwrap().outputs[0].enc_ciphertext[100] ^= 1;
+    for changed in [paid, expiring, memo] {
+        assert_ne!(changed.txid(), txid);
+    }
+}
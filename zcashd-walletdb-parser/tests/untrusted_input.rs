@@ -0,0 +1,144 @@
+//! The byte parsers take damaged and hostile input: they must return errors, never panic
+//! or allocate what the input merely claims to need. The cases here are the ones the fuzz
+//! targets in `fuzz/` found, plus a deterministic sweep over mutated fixture pages.
+
+use std::{fs, io};
+
+use zcashd_walletdb_parser::{
+    entry::{
+        constants::{OverflowRef, iter_slots},
+        parser::{read_leaf_item, split_walletdb_key},
+    },
+    error::WalletDbError,
+    headers::parse_btree_meta_page0,
+    leaf::parse_leaf_entry,
+    storage::{
+        page::{PageHeader, PageProtection},
+        source::MemoryPageSource,
+        supplier::{OverflowChainError, read_overflow_chain},
+        types::{ByteVec, Endianness, PageNumber, PageSource},
+    },
+    util::{Endian, parse_page_header},
+};
+
+const WALLET: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../dat_files/wallet0.dat");
+
+/// A [`MemoryPageSource`] that does not tell how many pages it has, like a stream.
+#[derive(Debug)]
+struct Streaming(MemoryPageSource);
+
+impl PageSource for Streaming {
+    fn read_page(&self, page_no: PageNumber) -> io::Result<ByteVec> {
+        self.0.read_page(page_no)
+    }
+
+    fn page_count(&self) -> Option<u64> {
+        None
+    }
+
+    fn source_id(&self) -> String {
+        self.0.source_id()
+    }
+}
+
+#[test]
+fn slot_array_past_the_page_end_stops_at_the_end() {
+    let page = [0u8; 40];
+    // `lower` claims a slot array running far past the 40-byte page.
+    assert_eq!(
+        iter_slots(&page, Endian::Le, PageProtection::None, 4000).count(),
+        7
+    );
+    assert_eq!(
+        iter_slots(&page, Endian::Le, PageProtection::Checksum, 4000).count(),
+        4
+    );
+}
+
+#[test]
+fn item_offsets_near_usize_max_are_out_of_bounds() {
+    let page = [1u8; 64];
+    for off in [usize::MAX, usize::MAX - 2, usize::MAX - 8] {
+        assert!(matches!(
+            parse_leaf_entry(&page, off, Endian::Le),
+            Err(WalletDbError::SlotOutOfBounds { .. })
+        ));
+        assert!(matches!(
+            read_leaf_item(&page, 512, Endian::Le, PageProtection::None, &page, off),
+            Err(WalletDbError::SlotOutOfBounds { .. })
+        ));
+    }
+}
+
+#[test]
+fn keydata_lengths_past_the_page_are_out_of_bounds() {
+    let mut page = [0u8; 64];
+    page[0..2].copy_from_slice(&u16::MAX.to_le_bytes()); // inline length
+    page[2] = 1; // B_KEYDATA
+    assert!(matches!(
+        read_leaf_item(&page, 512, Endian::Le, PageProtection::None, &page, 0),
+        Err(WalletDbError::SlotOutOfBounds { .. })
+    ));
+}
+
+#[test]
+fn bogus_overflow_length_fails_without_a_page_count() {
+    // Page 1 is an overflow page whose chain ends at once, referenced with a 4 GiB length.
+    let mut image = vec![0u8; 1024];
+    image[512 + 25] = 7;
+    image[512 + 22..512 + 24].copy_from_slice(&100u16.to_le_bytes());
+    let source = Streaming(MemoryPageSource::new(image, 512, "stream").unwrap());
+    let reference = OverflowRef {
+        first_page: 1,
+        total_len: u32::MAX,
+    };
+    let err = read_overflow_chain(&source, reference, Endianness::Little, PageProtection::None)
+        .unwrap_err();
+    assert!(matches!(
+        err.get_ref().and_then(|e| e.downcast_ref()),
+        Some(OverflowChainError::TooShort { .. })
+    ));
+}
+
+/// xorshift64, so the sweep is the same on every run.
+struct Noise(u64);
+
+impl Noise {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n.max(1) as u64) as usize
+    }
+}
+
+#[test]
+fn mutated_pages_never_panic() {
+    let wallet = fs::read(WALLET).unwrap();
+    let mut noise = Noise(0x5eed_f0f0_beef_0001);
+    for _ in 0..20_000 {
+        let start = noise.below(wallet.len() / 4096) * 4096;
+        let len = noise.below(700);
+        let mut data = wallet[start..start + len].to_vec();
+        for _ in 0..noise.below(6) {
+            if !data.is_empty() {
+                let at = noise.below(data.len());
+                data[at] = noise.next() as u8;
+            }
+        }
+        let _ = parse_btree_meta_page0(&data);
+        let _ = split_walletdb_key(&data);
+        for e in [Endian::Le, Endian::Be] {
+            let _ = parse_page_header(&data, e);
+            let _ = PageHeader::parse(&data, e.into(), PageProtection::Checksum);
+            let off = noise.below(data.len() + 16);
+            let _ = parse_leaf_entry(&data, off, e);
+            let _ = read_leaf_item(&wallet, 4096, e, PageProtection::None, &data, off);
+            let _ = iter_slots(&data, e, PageProtection::None, noise.next() as u16).count();
+        }
+    }
+}
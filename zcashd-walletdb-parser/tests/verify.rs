@@ -0,0 +1,177 @@
+//! [`KeyVerification`] of the shipped fixtures and of damaged keys, and the curve
+//! arithmetic under it.
+
+use pretty_assertions::assert_eq;
+use zcashd_walletdb_parser::{
+    crypto::{
+        jubjub::{proof_generation_key_generator, spending_key_generator},
+        secp256k1::{N, public_key},
+    },
+    parser::{
+        decoders::sapling::{SaplingExtendedSpendingKey, SaplingSpendingKey, Zip32Header},
+        record::RecordKind,
+        secret::SecretBytes,
+        verify::{KeyIssue, KeyVerification, Tally},
+    },
+    storage::walletdb::WalletDb,
+};
+
+const WALLETS: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../dat_files");
+
+fn scalar(n: u8) -> [u8; 32] {
+    let mut scalar = [0; 32];
+    scalar[31] = n;
+    scalar
+}
+
+#[test]
+fn secp256k1_public_keys_match_the_known_multiples_of_g() {
+    let g = "79be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798";
+    assert_eq!(
+        hex::encode(public_key(&scalar(1), true).unwrap()),
+        format!("02{g}")
+    );
+    assert_eq!(
+        hex::encode(public_key(&scalar(1), false).unwrap()),
+        format!("04{g}483ada7726a3c4655da4fbfc0e1108a8fd17b448a68554199c47d08ffb10d4b8")
+    );
+    assert_eq!(
+        hex::encode(public_key(&scalar(3), true).unwrap()),
+        "02f9308a019258c31049344f85f89d5229b531c845836f99b08601f113bce036f9"
+    );
+    // n - 1 is -1: G mirrored, with an odd y.
+    let mut minus_one = N;
+    minus_one[31] -= 1;
+    assert_eq!(
+        hex::encode(public_key(&minus_one, true).unwrap()),
+        format!("03{g}")
+    );
+    assert_eq!(public_key(&[0; 32], true), None);
+    assert_eq!(public_key(&N, true), None);
+    assert_eq!(public_key(&scalar(1)[1..], true), None);
+}
+
+#[test]
+fn sapling_generators_and_key_derivation() {
+    let (u, _) = spending_key_generator().to_affine();
+    assert_eq!(
+        format!("{u:?}"),
+        "0x0926d4f32059c712d418a7ff26753b6ad5b9a7d3ef8e282747bf46920a95a753"
+    );
+    let mut one = [0; 32];
+    one[0] = 1;
+    let key = SaplingExtendedSpendingKey {
+        header: Zip32Header {
+            depth: 0,
+            parent_fvk_tag: [0; 4],
+            child_index: 0,
+            chain_code: [1; 32],
+        },
+        ask: SecretBytes::new(one.to_vec()),
+        nsk: SecretBytes::new(one.to_vec()),
+        ovk: [2; 32],
+        dk: [3; 32],
+    };
+    let extfvk = key.to_extfvk();
+    assert_eq!(extfvk.ak, spending_key_generator().to_bytes());
+    assert_eq!(extfvk.nk, proof_generation_key_generator().to_bytes());
+    assert_eq!(
+        (extfvk.header, extfvk.ovk, extfvk.dk),
+        (key.header, key.ovk, key.dk)
+    );
+}
+
+#[test]
+fn fixture_keys_derive_their_public_keys() {
+    for n in 0..8 {
+        let wallet = WalletDb::open(format!("{WALLETS}/wallet{n}.dat"))
+            .unwrap()
+            .decode()
+            .unwrap();
+        let report = KeyVerification::of(&wallet, None);
+        assert_eq!(report.issues, [], "wallet{n}");
+        assert_eq!(
+            report.verified(),
+            wallet.keys.transparent.len(),
+            "wallet{n}"
+        );
+    }
+}
+
+#[test]
+fn damaged_keys_are_reported() {
+    let mut wallet = WalletDb::open(format!("{WALLETS}/wallet0.dat"))
+        .unwrap()
+        .decode()
+        .unwrap();
+    let mut pubkeys = wallet.keys.transparent.keys().cloned();
+    let (first, second) = (pubkeys.next().unwrap(), pubkeys.next().unwrap());
+    wallet
+        .keys
+        .transparent
+        .get_mut(&first)
+        .unwrap()
+        .private_key
+        .secret = SecretBytes::new(scalar(1).to_vec());
+    wallet
+        .keys
+        .transparent
+        .get_mut(&second)
+        .unwrap()
+        .private_key
+        .secret = SecretBytes::new(N.to_vec());
+
+    let mut ask = [0; 32];
+    ask[0] = 5;
+    let sapling = SaplingExtendedSpendingKey {
+        header: Zip32Header {
+            depth: 0,
+            parent_fvk_tag: [0; 4],
+            child_index: 0,
+            chain_code: [0; 32],
+        },
+        ask: SecretBytes::new(ask.to_vec()),
+        nsk: SecretBytes::new(ask.to_vec()),
+        ovk: [0; 32],
+        dk: [0; 32],
+    };
+    let derived = sapling.to_extfvk().ivk();
+    wallet.keys.sapling.insert(
+        [9; 32],
+        SaplingSpendingKey {
+            ivk: [9; 32],
+            key: sapling,
+        },
+    );
+
+    let report = KeyVerification::of(&wallet, None);
+    assert!(!report.is_clean());
+    assert_eq!(
+        report.tallies[&RecordKind::Key],
+        Tally {
+            verified: 50,
+            mismatched: 2,
+            locked: 0
+        }
+    );
+    let g = public_key(&scalar(1), first.len() == 33).unwrap();
+    assert!(report.issues.contains(&KeyIssue::PubKeyMismatch {
+        kind: RecordKind::Key,
+        recorded: first,
+        derived: g,
+    }));
+    assert!(report.issues.contains(&KeyIssue::InvalidSecret {
+        kind: RecordKind::Key,
+        pubkey: second,
+    }));
+    assert!(report.issues.contains(&KeyIssue::SaplingIvkMismatch {
+        kind: RecordKind::SapZKey,
+        recorded: [9; 32],
+        derived,
+    }));
+    assert!(
+        report
+            .to_string()
+            .contains("  key          : 50 verified, 2 mismatched\n")
+    );
+}
@@ -0,0 +1,130 @@
+//! The [`ZcashdWallet`] model, against the shipped zcashd fixtures and synthetic records.
+
+use pretty_assertions::assert_eq;
+use zcashd_walletdb_parser::{
+    entry::parser::walletdb_key_prefix,
+    parser::{
+        decoders::{network::ZcashNetwork, scalars::ClientVersion},
+        record::RecordKind,
+        registry::{UnknownRecord, default_registry},
+        wallet::{DecodeWarning, ZcashdWallet},
+    },
+    storage::walletdb::WalletDb,
+};
+
+const WALLETS: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../dat_files");
+
+#[test]
+fn fixtures_decode_into_one_wallet() {
+    for n in 0..8 {
+        let wallet = WalletDb::open(format!("{WALLETS}/wallet{n}.dat"))
+            .unwrap()
+            .decode()
+            .unwrap();
+        assert!(wallet.errors.is_empty(), "wallet{n}: {wallet}");
+        let meta = &wallet.metadata;
+        assert_eq!(meta.version, Some(ClientVersion(6_000_050)));
+        let network = meta.network.as_ref().and_then(|n| n.zcash());
+        assert_eq!(network, Some(ZcashNetwork::Regtest));
+        assert_eq!(meta.witness_cache_size, Some(100));
+        assert!(meta.best_block.as_ref().is_some_and(|b| !b.is_empty()));
+
+        // Every key pair has its metadata, and the HD ones are what the chain counted.
+        let keys = &wallet.keys;
+        assert_eq!(keys.transparent.len(), keys.transparent_metadata.len());
+        let chain = wallet.crypto.mnemonic_hd_chain.as_ref().unwrap();
+        assert_eq!(
+            keys.transparent_len() as u64,
+            chain.legacy_transparent_keys()
+        );
+        assert!(!wallet.crypto.is_encrypted());
+        assert!(wallet.crypto.mnemonic.is_some());
+        assert!(wallet.orchard.is_some());
+        assert!(wallet.undecoded.is_empty());
+        // The pool holds keys the wallet has.
+        assert!(!keys.key_pool.is_empty());
+        assert!(
+            keys.key_pool
+                .values()
+                .all(|entry| keys.transparent.contains_key(&entry.pubkey))
+        );
+        assert!(wallet.unknown.is_empty() && wallet.warnings.is_empty());
+    }
+}
+
+#[test]
+fn damaged_records_are_kept_as_errors() {
+    let version = 6_000_050i32.to_le_bytes();
+    let records = [
+        (walletdb_key_prefix("version"), version.to_vec()),
+        (walletdb_key_prefix("minversion"), vec![0x01]),
+        (walletdb_key_prefix("walletdescriptor"), vec![]),
+    ];
+    let wallet = ZcashdWallet::from_records(&default_registry(), records);
+    assert_eq!(wallet.metadata.version, Some(ClientVersion(6_000_050)));
+    assert_eq!(wallet.metadata.min_version, None);
+    assert_eq!(wallet.errors.len(), 1);
+    assert_eq!(wallet.errors[0].kind, RecordKind::MinVersion);
+    assert!(wallet.undecoded.is_empty());
+}
+
+#[test]
+fn record_errors_name_the_record_once() {
+    let mut key = walletdb_key_prefix("unifiedaddrmeta");
+    key.extend_from_slice(&[0xaa; 32]);
+    key.extend_from_slice(&[0; 11]);
+    key.extend_from_slice(&[1, 2]);
+    let wallet =
+        ZcashdWallet::from_records(&default_registry(), [(key, 7u32.to_le_bytes().to_vec())]);
+    assert_eq!(wallet.errors.len(), 1);
+    let message = wallet.errors[0].to_string();
+    assert!(message.starts_with("unifiedaddrmeta aaaa"), "{message}");
+    assert!(
+        message.ends_with("/sapling: unsupported metadata version 7"),
+        "{message}"
+    );
+    assert_eq!(message.matches("unifiedaddrmeta").count(), 1, "{message}");
+}
+
+#[test]
+fn unknown_records_pass_through_with_warnings() {
+    let mut descriptor = walletdb_key_prefix("walletdescriptor");
+    descriptor.extend_from_slice(&[0xd1; 32]);
+    let records = [
+        (descriptor, vec![0xd2; 5]),
+        (vec![0xff], vec![0xd3]),
+        (walletdb_key_prefix("hdchain"), vec![0; 4]),
+    ];
+    let wallet = ZcashdWallet::from_records(&default_registry(), records);
+    assert_eq!(
+        wallet.unknown,
+        [
+            UnknownRecord {
+                tag: Some("walletdescriptor".to_string()),
+                key_rest: vec![0xd1; 32],
+                value: vec![0xd2; 5],
+            },
+            UnknownRecord {
+                tag: None,
+                key_rest: vec![0xff],
+                value: vec![0xd3],
+            },
+        ]
+    );
+    assert_eq!(
+        wallet.warnings[0],
+        DecodeWarning::UnknownRecord {
+            tag: Some("walletdescriptor".to_string()),
+            key_rest: vec![0xd1; 32],
+            value_len: 5,
+        }
+    );
+    assert!(
+        wallet.warnings[1]
+            .to_string()
+            .contains("no walletdb tag (key ff")
+    );
+    // A known tag without a decoder is counted, not passed through.
+    assert_eq!(wallet.undecoded.get(&RecordKind::HdChain).copied(), Some(1));
+    assert!(wallet.errors.is_empty());
+}